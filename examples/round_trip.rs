@@ -0,0 +1,30 @@
+//! Parses a types.xml file through the JSON wrappers the `wasm` feature exports, bumps
+//! every lifetime, and writes the result back out — the same calls a browser makes.
+//!
+//!     cargo run --example round_trip --no-default-features --features wasm -- example/types.xml
+
+use dayz_loot_editor_rust::wasm::{diff_types_json, parse_types_json, serialize_types_json, validate_types_json};
+
+fn main() -> Result<(), String> {
+    let path = std::env::args().nth(1).ok_or("usage: round_trip <types.xml>")?;
+    let xml = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+
+    let parsed: serde_json::Value = serde_json::from_str(&parse_types_json(&xml)?).map_err(|e| e.to_string())?;
+    let before = parsed["types"].clone();
+    let mut after = before.clone();
+    for ty in after.as_array_mut().into_iter().flatten() {
+        for field in ty["fields"].as_array_mut().into_iter().flatten() {
+            if field["key"]["kind"] == "element" && field["key"]["name"] == "lifetime" {
+                let seconds: i64 = field["value"].as_str().and_then(|v| v.parse().ok()).unwrap_or(0);
+                field["value"] = (seconds * 2).to_string().into();
+            }
+        }
+    }
+
+    let (before, after) = (before.to_string(), after.to_string());
+    eprintln!("warnings: {}", parsed["warnings"]);
+    eprintln!("issues:   {}", validate_types_json(&after, "")?);
+    eprintln!("diff:     {}", diff_types_json(&before, &after)?);
+    print!("{}", serialize_types_json(&after, false)?);
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Feeds `parse_types` → `serialize_types` → `parse_types` with arbitrary bytes and with
+//! tag-level mutations of a vanilla snippet, checking that nothing panics and that the
+//! second pass changes nothing. The parser's debug assertions bound what it reads by
+//! the size of the input.
+//!
+//!     cargo +nightly fuzz run parse_round_trip fuzz/corpus/parse_round_trip
+
+#![no_main]
+
+use dayz_loot_editor_rust::model::{parse_types, serialize_types};
+use libfuzzer_sys::fuzz_target;
+
+const SEED: &str = include_str!("../corpus/parse_round_trip/vanilla.xml");
+
+fuzz_target!(|data: &[u8]| {
+    match data.split_first() {
+        // Odd first byte: the rest steers edits to the seed's tags.
+        Some((mode, steps)) if mode % 2 == 1 => round_trip(&mutate_seed(steps)),
+        _ => round_trip(&String::from_utf8_lossy(data)),
+    }
+});
+
+fn round_trip(xml: &str) {
+    let Ok((types, _)) = parse_types(xml) else {
+        return;
+    };
+    // Names that can't be written are refused with an error, not written broken.
+    let Ok(first) = serialize_types(&types, false) else {
+        return;
+    };
+    let (again, _) = parse_types(&first).expect("what the serializer wrote parses");
+    let second = serialize_types(&again, false).expect("what was read back serializes");
+    assert_eq!(first, second, "a second save changes nothing");
+    let (third, _) = parse_types(&second).expect("the second save parses");
+    assert_eq!(again, third);
+}
+
+/// The seed cut before every `<`, then edited one step per three bytes: drop a piece,
+/// repeat one elsewhere, swap two, or put a byte of text in front of one.
+fn mutate_seed(steps: &[u8]) -> String {
+    let mut pieces: Vec<String> = Vec::new();
+    for (i, c) in SEED.char_indices() {
+        if c == '<' || pieces.is_empty() {
+            pieces.push(String::new());
+        }
+        pieces.last_mut().expect("pushed above").push_str(&SEED[i..i + c.len_utf8()]);
+    }
+    for step in steps.chunks_exact(3) {
+        if pieces.is_empty() {
+            break;
+        }
+        let a = step[1] as usize % pieces.len();
+        let b = step[2] as usize % pieces.len();
+        match step[0] % 4 {
+            0 => {
+                pieces.remove(a);
+            }
+            1 => {
+                let copy = pieces[a].clone();
+                pieces.insert(b, copy);
+            }
+            2 => pieces.swap(a, b),
+            _ => pieces[a].insert(0, char::from(step[2])),
+        }
+    }
+    pieces.concat()
+}
@@ -1,5 +1,10 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Action {
     Quit,
+    /// Ctrl+Q: leaves the app from anywhere, unsaved changes or not.
+    ForceQuit,
+    /// Ctrl+Z: hands the terminal back to the shell until `fg`.
+    Suspend,
     Up,
     Down,
     Left,
@@ -11,6 +16,8 @@ pub enum Action {
     Add,
     AddAttribute,
     Copy,
+    /// `C`: copy the highlighted type under a name typed right away.
+    Duplicate,
     Delete,
     Help,
     ToggleRemote,
@@ -18,5 +25,47 @@ pub enum Action {
     PgDown,
     PgUp,
     Tab,
+    /// Shift+Tab.
+    BackTab,
+    ToggleFilter,
+    Stats,
+    NextIssue,
+    PrevIssue,
+    Validate,
+    Undo,
+    Redo,
+    NominalTool,
+    ToggleSelect,
+    PropagateValues,
+    Command,
+    RecordMacro,
+    ReplayMacro,
+    /// `.`: the last field edit again, on the highlighted type.
+    RepeatEdit,
+    ToggleTable,
+    CycleInlineValues,
+    /// `F`: show or hide the categories pane left of the Types pane.
+    ToggleCategories,
+    /// Ctrl+Left/Ctrl+Right: move the pane boundary right of the focused pane.
+    ResizeLeft,
+    ResizeRight,
+    /// Ctrl+O or Alt+Left: back to the previous place in the jump list.
+    JumpBack,
+    /// Ctrl+I (where the terminal tells it from Tab) or Alt+Right: forward again.
+    JumpForward,
+    /// `R`: reload the open file from disk, or dismiss a change noticed there.
+    Reload,
+    /// F2 in the file picker.
+    Rename,
+    /// The Delete key: deletes the highlighted file in the file picker, the character
+    /// under the cursor while typing.
+    Remove,
+    /// Home/End while typing: to the start or end of the text.
+    Home,
+    End,
+    /// Ctrl+U while typing: empty the text.
+    ClearLine,
+    /// Ctrl+W while typing: delete the word before the cursor.
+    DeleteWord,
     None
 }
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diff::FieldChange,
+    model::{FieldKey, TypeEntry},
+    utils,
+};
+
+/// Who last changed which field, kept next to the types file as `<stem>.notes.toml`.
+/// Never read back into the XML.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Annotations {
+    #[serde(rename = "field", skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldNote>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FieldNote {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// `nominal`, `usage @name`; repeated elements add their position: `usage @name #2`.
+    pub field: String,
+    pub author: String,
+    /// Unix seconds.
+    pub at: u64,
+    /// `None` when the edit added the field.
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl FieldNote {
+    /// `last changed by Alex on 2024-05-02: 8 → 15`.
+    pub fn describe(&self) -> String {
+        let (y, m, d, ..) = utils::utc_datetime(self.at);
+        let change = match &self.old {
+            Some(old) => format!("{old} → {}", self.new),
+            None => format!("added as {}", self.new),
+        };
+        format!("last changed by {} on {y:04}-{m:02}-{d:02}: {change}", self.author)
+    }
+}
+
+pub fn notes_path(types_path: &Path) -> PathBuf {
+    types_path.with_extension("notes.toml")
+}
+
+/// Identifies a field within its type.
+pub fn field_id(key: &FieldKey) -> String {
+    match key {
+        FieldKey::Element { index: 0, .. } | FieldKey::Attribute { index: 0, .. } => key.label(),
+        FieldKey::Element { index, .. } | FieldKey::Attribute { index, .. } => format!("{} #{}", key.label(), index + 1),
+    }
+}
+
+impl Annotations {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e: toml::de::Error| e.message().to_string())
+    }
+
+    pub fn to_toml(&self) -> String {
+        let body = toml::to_string(self).unwrap_or_default();
+        format!("# Who last changed each field; written by the editor, not part of the types file.\n\n{body}")
+    }
+
+    pub fn get(&self, type_name: &str, key: &FieldKey) -> Option<&FieldNote> {
+        let id = field_id(key);
+        self.fields.iter().find(|n| n.type_name == type_name && n.field == id)
+    }
+
+    /// Records `changes` to `type_name` by `author`, replacing older notes on the same fields.
+    /// Removed fields and changes the notes already show are skipped; returns how many
+    /// notes were written.
+    pub fn record(&mut self, type_name: &str, changes: &[FieldChange], author: &str, at: u64) -> usize {
+        let mut recorded = 0;
+        for change in changes {
+            let Some(new) = &change.new else {
+                continue;
+            };
+            let id = field_id(&change.key);
+            let existing = self.fields.iter().position(|n| n.type_name == type_name && n.field == id);
+            if let Some(i) = existing {
+                if self.fields[i].new == *new && self.fields[i].author == author {
+                    continue;
+                }
+                self.fields.remove(i);
+            }
+            self.fields.push(FieldNote {
+                type_name: type_name.to_string(),
+                field: id,
+                author: author.to_string(),
+                at,
+                old: change.old.clone(),
+                new: new.clone(),
+            });
+            recorded += 1;
+        }
+        recorded
+    }
+
+    /// Drops notes on types and fields `types` no longer has; returns how many.
+    pub fn prune(&mut self, types: &[TypeEntry]) -> usize {
+        let before = self.fields.len();
+        self.fields.retain(|note| {
+            types
+                .iter()
+                .find(|t| t.name == note.type_name)
+                .is_some_and(|t| t.fields.iter().any(|f| field_id(&f.key) == note.field))
+        });
+        before - self.fields.len()
+    }
+
+    /// Notes by `author` (case-insensitive), newest first.
+    pub fn by_author(&self, author: &str) -> Vec<&FieldNote> {
+        let mut notes: Vec<&FieldNote> =
+            self.fields.iter().filter(|n| n.author.eq_ignore_ascii_case(author)).collect();
+        notes.sort_by_key(|n| std::cmp::Reverse(n.at));
+        notes
+    }
+
+    pub fn authors(&self) -> Vec<&str> {
+        let mut authors: Vec<&str> = self.fields.iter().map(|n| n.author.as_str()).collect();
+        authors.sort_unstable();
+        authors.dedup();
+        authors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_types;
+
+    fn element(name: &str, index: usize) -> FieldKey {
+        FieldKey::Element { name: name.to_string(), index }
+    }
+
+    fn change(key: FieldKey, old: Option<&str>, new: Option<&str>) -> FieldChange {
+        FieldChange { key, old: old.map(String::from), new: new.map(String::from) }
+    }
+
+    #[test]
+    fn record_keeps_one_note_per_field_and_skips_removals() {
+        let mut notes = Annotations::default();
+        let changes = [change(element("nominal", 0), Some("8"), Some("15")), change(element("cost", 0), Some("100"), None)];
+        assert_eq!(notes.record("AKM", &changes, "Alex", 1_714_608_000), 1);
+        assert_eq!(notes.record("AKM", &changes, "Alex", 1_714_608_060), 0, "same value by the same author");
+
+        let again = [change(element("nominal", 0), Some("15"), Some("20"))];
+        assert_eq!(notes.record("AKM", &again, "Sam", 1_714_608_120), 1);
+        assert_eq!(notes.fields.len(), 1);
+        let note = notes.get("AKM", &element("nominal", 0)).unwrap();
+        assert_eq!(note.describe(), "last changed by Sam on 2024-05-02: 15 → 20");
+
+        let usage = FieldKey::Attribute { element: String::from("usage"), index: 1, attr: String::from("name") };
+        notes.record("AKM", &[change(usage.clone(), None, Some("Town"))], "Alex", 1_714_608_180);
+        assert_eq!(field_id(&usage), "usage @name #2");
+        assert_eq!(notes.get("AKM", &usage).unwrap().describe(), "last changed by Alex on 2024-05-02: added as Town");
+        assert_eq!(notes.authors(), ["Alex", "Sam"]);
+        let by_alex: Vec<&str> = notes.by_author("alex").iter().map(|n| n.field.as_str()).collect();
+        assert_eq!(by_alex, ["usage @name #2"]);
+    }
+
+    #[test]
+    fn sidecar_round_trips_and_prunes_notes_on_missing_fields() {
+        let mut notes = Annotations::default();
+        notes.record("AKM", &[change(element("nominal", 0), Some("8"), Some("15"))], "Alex", 1);
+        notes.record("AKM", &[change(element("cost", 0), None, Some("100"))], "Alex", 2);
+        notes.record("Gone", &[change(element("nominal", 0), None, Some("1"))], "Alex", 3);
+
+        let toml = notes.to_toml();
+        assert!(toml.starts_with("# Who last changed each field"), "{toml}");
+        assert_eq!(Annotations::parse(&toml).unwrap(), notes);
+        assert!(Annotations::parse("[[field]]\ntype = \"AKM\"\nbogus = 1\n").is_err());
+
+        let (types, _) = parse_types(r#"<types><type name="AKM"><nominal>15</nominal></type></types>"#).unwrap();
+        assert_eq!(notes.prune(&types), 2);
+        assert_eq!(notes.fields.len(), 1);
+        assert_eq!(notes_path(Path::new("/db/types.xml")), PathBuf::from("/db/types.notes.toml"));
+    }
+}
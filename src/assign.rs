@@ -0,0 +1,224 @@
+use crate::{
+    bulk::name_matches,
+    model::{Field, FieldKey, TypeEntry, NAME_ELEMENTS},
+    utils,
+};
+
+/// One type `:addusage`, `:addtag` or `:setcategory` would touch.
+#[derive(Clone, Debug)]
+pub struct AssignChange {
+    pub type_idx: usize,
+    pub name: String,
+    /// Category values the new one replaces; always empty for usage and tag.
+    pub replaces: Vec<String>,
+}
+
+/// A name attribute given to every type whose name matches a pattern, worked out before
+/// anything changes so the preview shows exactly what Enter will do.
+#[derive(Clone, Debug)]
+pub struct Assignment {
+    /// `usage`, `tag` or `category`.
+    pub element: String,
+    pub value: String,
+    pub pattern: String,
+    /// Planned for the marked types (`:again`) instead of the types `pattern` matches.
+    pub marked_only: bool,
+    pub changes: Vec<AssignChange>,
+    /// Matching types that already carry the value.
+    pub skipped: Vec<usize>,
+}
+
+impl Assignment {
+    /// Category is a singleton: it replaces what a type has instead of adding to it.
+    pub fn replaces(&self) -> bool {
+        self.element == "category"
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.element, self.value)
+    }
+
+    /// The preview: every type that changes, then the ones skipped.
+    pub fn lines(&self, types: &[TypeEntry]) -> Vec<String> {
+        let verb = if self.replaces() { "Set" } else { "Add" };
+        let scope = if self.marked_only {
+            format!("the {}", utils::plural(self.changes.len() + self.skipped.len(), "marked type"))
+        } else {
+            format!("types matching '{}'", self.pattern)
+        };
+        let mut lines = vec![format!("{verb} {} on {scope}:", self.describe()), String::new()];
+        for change in &self.changes {
+            match change.replaces.as_slice() {
+                [] => lines.push(format!("  + {}", change.name)),
+                old => lines.push(format!("  ~ {} (was {})", change.name, old.join(", "))),
+            }
+        }
+        if !self.skipped.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("Already {}:", self.describe()));
+            lines.extend(self.skipped.iter().filter_map(|&i| types.get(i)).map(|t| format!("    {}", t.name)));
+        }
+        lines
+    }
+}
+
+fn is_name_of(field: &Field, element: &str) -> bool {
+    match &field.key {
+        FieldKey::Attribute { element: e, attr, .. } => e == element && attr == "name",
+        // Text form, when normalize_names is off.
+        FieldKey::Element { name, .. } => name == element,
+    }
+}
+
+/// Matches `pattern` (`*` and `?` wildcards, any case) against every type name. `element`
+/// must be one of `category`, `usage` or `tag`.
+pub fn plan(types: &[TypeEntry], element: &str, value: &str, pattern: &str) -> Result<Assignment, String> {
+    let matching: Vec<usize> = (0..types.len()).filter(|&i| name_matches(pattern, &types[i].name)).collect();
+    plan_for(types, element, value, pattern, &matching, false)
+}
+
+/// Like `plan`, for the types at `marked` whatever their names; `pattern` is kept so the
+/// assignment can be repeated as it was first given.
+pub fn plan_marked(types: &[TypeEntry], element: &str, value: &str, pattern: &str, marked: &[usize]) -> Result<Assignment, String> {
+    plan_for(types, element, value, pattern, marked, true)
+}
+
+fn plan_for(
+    types: &[TypeEntry],
+    element: &str,
+    value: &str,
+    pattern: &str,
+    indices: &[usize],
+    marked_only: bool,
+) -> Result<Assignment, String> {
+    if !NAME_ELEMENTS.contains(&element) {
+        return Err(format!("'{element}' is not one of {}", NAME_ELEMENTS.join(", ")));
+    }
+    let mut assignment = Assignment {
+        element: element.to_string(),
+        value: value.to_string(),
+        pattern: pattern.to_string(),
+        marked_only,
+        changes: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (idx, ty) in indices.iter().filter_map(|&i| Some((i, types.get(i)?))) {
+        let current: Vec<String> = ty
+            .fields
+            .iter()
+            .filter(|f| is_name_of(f, element))
+            .map(|f| f.value.clone())
+            .collect();
+        let done = if assignment.replaces() {
+            current == [value]
+        } else {
+            current.iter().any(|v| v == value)
+        };
+        if done {
+            assignment.skipped.push(idx);
+        } else {
+            assignment.changes.push(AssignChange {
+                type_idx: idx,
+                name: ty.name.clone(),
+                replaces: if assignment.replaces() { current } else { Vec::new() },
+            });
+        }
+    }
+    Ok(assignment)
+}
+
+/// Applies a plan. A new element gets the next free index and goes after the existing
+/// elements of its name; for category those are removed first.
+pub fn apply(types: &mut [TypeEntry], assignment: &Assignment) {
+    let element = assignment.element.as_str();
+    for change in &assignment.changes {
+        let Some(ty) = types.get_mut(change.type_idx) else {
+            continue;
+        };
+        let mut position = ty.fields.iter().rposition(|f| f.key.get_element_name() == element).map(|p| p + 1);
+        if assignment.replaces() {
+            let first = ty.fields.iter().position(|f| f.key.get_element_name() == element);
+            ty.fields.retain(|f| f.key.get_element_name() != element);
+            position = first;
+        }
+        let index = ty
+            .fields
+            .iter()
+            .filter(|f| f.key.get_element_name() == element)
+            .map(|f| f.key.element_key().1 + 1)
+            .max()
+            .unwrap_or(0);
+        let position = position.unwrap_or(ty.fields.len());
+        ty.fields.insert(
+            position,
+            Field {
+                key: FieldKey::Attribute {
+                    element: element.to_string(),
+                    index,
+                    attr: String::from("name"),
+                },
+                value: assignment.value.clone(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    fn names_of(ty: &TypeEntry, element: &str) -> Vec<(usize, String)> {
+        ty.fields
+            .iter()
+            .filter(|f| is_name_of(f, element))
+            .map(|f| (f.key.element_key().1, f.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn usage_and_tag_append_with_the_next_index_and_skip_types_that_have_them() {
+        let content = SAMPLE
+            .replacen("<category name=\"weapons\" />", "<category name=\"weapons\" />\n    <usage name=\"Police\" />", 1)
+            .replacen("<category name=\"food\" />", "<category name=\"food\" />\n    <usage name=\"Military\" />", 1);
+        let (mut types, _) = parse_types(&content).unwrap();
+
+        let usage = plan(&types, "usage", "Military", "a*").unwrap();
+        assert_eq!(usage.changes.iter().map(|c| c.type_idx).collect::<Vec<_>>(), [0]);
+        assert_eq!(usage.skipped, [1]);
+        assert_eq!(usage.lines(&types), ["Add usage Military on types matching 'a*':", "", "  + AKM", "", "Already usage Military:", "    Apple"]);
+        apply(&mut types, &usage);
+        assert_eq!(names_of(&types[0], "usage"), [(0, String::from("Police")), (1, String::from("Military"))]);
+        let last = types[0].fields.last().unwrap();
+        assert_eq!(last.key.label(), "usage @name", "it goes after the type's other usages");
+
+        let tag = plan(&types, "tag", "floor", "mosin*").unwrap();
+        apply(&mut types, &tag);
+        assert_eq!(names_of(&types[2], "tag"), [(0, String::from("floor"))]);
+        assert!(plan(&types, "tag", "floor", "*").unwrap().skipped.contains(&2));
+    }
+
+    #[test]
+    fn category_replaces_what_a_type_has() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        let position = types[0].fields.iter().position(|f| is_name_of(f, "category")).unwrap();
+
+        let category = plan(&types, "category", "food", "*").unwrap();
+        assert!(category.replaces());
+        assert_eq!(category.skipped, [1]);
+        assert_eq!(category.lines(&types)[2..4], ["  ~ AKM (was weapons)", "  ~ Mosin9130 (was weapons)"]);
+        apply(&mut types, &category);
+        assert_eq!(names_of(&types[0], "category"), [(0, String::from("food"))]);
+        assert!(is_name_of(&types[0].fields[position], "category"), "it stays where the old one was");
+    }
+
+    #[test]
+    fn marked_plans_ignore_the_pattern_and_other_elements_are_refused() {
+        let (types, _) = parse_types(SAMPLE).unwrap();
+        let marked = plan_marked(&types, "usage", "Town", "nothing*", &[2]).unwrap();
+        assert_eq!(marked.changes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["Mosin9130"]);
+        assert_eq!(marked.lines(&types)[0], "Add usage Town on the 1 marked type:");
+
+        assert_eq!(plan(&types, "flags", "1", "*").unwrap_err(), "'flags' is not one of category, usage, tag");
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::remote::{DirEntry, FileSource};
+
+/// A backup of the open file found in its directory.
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub generation: usize,
+    pub size: u64,
+    pub modified: Option<u64>,
+}
+
+/// `types.xml.bak` for generation 0, then `types.xml.bak.1`, `types.xml.bak.2`, ...
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    let mut backup = path.to_path_buf();
+    backup.add_extension("bak");
+    if generation > 0 {
+        backup.add_extension(generation.to_string());
+    }
+    backup
+}
+
+/// Generation of `name` if it is a backup of `path` (`types.xml.bak` is 0, `.bak.N` is N).
+pub fn generation_of(path: &Path, name: &str) -> Option<usize> {
+    let base = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix(base)?.strip_prefix(".bak")?;
+    match rest {
+        "" => Some(0),
+        _ => {
+            // Only the names `backup_path` writes: no sign, no leading zeros.
+            let digits = rest.strip_prefix('.')?;
+            if !digits.bytes().all(|b| b.is_ascii_digit()) || digits.starts_with('0') {
+                return None;
+            }
+            digits.parse().ok()
+        }
+    }
+}
+
+/// Backups of `path` among the entries of its directory, newest generation first.
+pub fn backups_of(path: &Path, entries: Vec<DirEntry>) -> Vec<BackupFile> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut found: Vec<BackupFile> = entries
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .filter_map(|e| {
+            Some(BackupFile {
+                generation: generation_of(path, &e.name)?,
+                path: dir.join(&e.name),
+                size: e.size,
+                modified: e.modified,
+            })
+        })
+        .collect();
+    found.sort_by_key(|b| b.generation);
+    found
+}
+
+/// Shifts existing backups up one generation so `backup_path(path, 0)` is free, letting the
+/// oldest fall off beyond `keep`. `rename` does the move locally or over SFTP; missing
+/// generations are skipped. Stops at the first backup that can't be moved, as going on
+/// would rename the next one over it; the error names that file, like `describe_failure`.
+pub fn rotate(path: &Path, keep: usize, mut rename: impl FnMut(&Path, &Path) -> io::Result<()>) -> io::Result<()> {
+    for generation in (1..keep).rev() {
+        let from = backup_path(path, generation - 1);
+        match rename(&from, &backup_path(path, generation)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(io::Error::new(err.kind(), describe_failure(&from, &err)));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `path` with `content`, first rotating its backups and saving `previous` (what
+/// is there now) as `.bak`; a backup that can't be written fails the whole write. Used
+/// where there is no editor session for the file. Over SFTP the old file is renamed to
+/// `.bak` instead of uploading `previous` again.
+pub fn write_with_backup(source: &FileSource, path: &Path, previous: &str, content: &str, keep: usize) -> io::Result<()> {
+    let backup = backup_path(path, 0);
+    match source {
+        FileSource::Local => {
+            rotate(path, keep, |from, to| fs::rename(from, to))?;
+            fs::write(&backup, previous)?;
+            replace(path, content)
+        }
+        FileSource::Remote(client) => {
+            let client = client.lock().map_err(|_| io::Error::other("SSH backend in use"))?;
+            rotate(path, keep, |from, to| client.replace(from, to))?;
+            client.write_file_with_backup(path, content, &backup, true, &mut |_, _| {}).map(|_| ())
+        }
+    }
+}
+
+/// Writes `content` to `<path>.part` and renames it over `path`, so a failed or interrupted
+/// write leaves the file as it was.
+fn replace(path: &Path, content: &str) -> io::Result<()> {
+    let mut partial = path.to_path_buf();
+    partial.add_extension("part");
+    if let Err(err) = fs::write(&partial, content) {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    fs::rename(&partial, path)
+}
+
+/// `permission denied (os error 13) on types.xml.bak`, for "Saved, but backup failed: ...".
+pub fn describe_failure(backup: &Path, err: &io::Error) -> String {
+    let message = err.to_string();
+    let mut chars = message.chars();
+    let message: String = chars.next().map(|c| c.to_lowercase().chain(chars).collect()).unwrap_or_default();
+    let name = backup.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| backup.display().to_string());
+    format!("{message} on {name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::BTreeMap};
+
+    use super::*;
+    use crate::remote::{self, RemoteFiles};
+
+    fn entry(name: &str, is_dir: bool) -> DirEntry {
+        DirEntry { name: name.to_string(), is_dir, link: false, size: 0, modified: None }
+    }
+
+    #[test]
+    fn look_alikes_are_not_backups() {
+        let path = Path::new("/srv/types.xml");
+        assert_eq!(generation_of(path, "types.xml.bak"), Some(0));
+        assert_eq!(generation_of(path, "types.xml.bak.3"), Some(3));
+        for name in [
+            "types.xml",
+            "types.xml.bak.0",
+            "types.xml.bak.",
+            "types.xml.bak.x",
+            "types.xml.bak.-1",
+            "types.xml.bak.+1",
+            "types.xml.bak.01",
+            "types.xml.bak1",
+            "types.xml.backup",
+            "types.xml.bak.2.tmp",
+            "types.xml.part",
+            "old_types.xml.bak",
+            "Types.xml.bak",
+        ] {
+            assert_eq!(generation_of(path, name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn backups_sort_by_generation_as_numbers() {
+        let path = Path::new("/srv/types.xml");
+        let entries = ["types.xml.bak.10", "types.xml.bak.2", "notes.txt", "types.xml.bak", "types.xml.bak.9", "types.xml.bak.x"]
+            .map(|name| entry(name, false))
+            .into_iter()
+            .chain([entry("types.xml.bak.1", true)])
+            .collect();
+        let found = backups_of(path, entries);
+        let generations: Vec<_> = found.iter().map(|b| b.generation).collect();
+        assert_eq!(generations, [0, 2, 9, 10]);
+        assert_eq!(found[3].path, Path::new("/srv/types.xml.bak.10"));
+    }
+
+    #[test]
+    fn rotation_skips_missing_generations_and_stops_at_a_failure() {
+        let path = Path::new("/srv/types.xml");
+        let mut moved = Vec::new();
+        let result = rotate(path, 4, |from, to| {
+            moved.push((from.to_path_buf(), to.to_path_buf()));
+            match from.extension().and_then(|e| e.to_str()) {
+                Some("2") => Err(io::Error::from(io::ErrorKind::NotFound)),
+                Some("1") => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+                _ => Ok(()),
+            }
+        });
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "permission denied on types.xml.bak.1");
+        assert_eq!(moved, [(backup_path(path, 2), backup_path(path, 3)), (backup_path(path, 1), backup_path(path, 2))]);
+
+        assert!(rotate(path, 1, |_, _| unreachable!()).is_ok());
+    }
+
+    /// An SFTP v3 server like OpenSSH's: renames never overwrite, and renames of `broken`
+    /// fail.
+    #[derive(Default)]
+    struct Server {
+        files: RefCell<BTreeMap<PathBuf, String>>,
+        broken: Option<PathBuf>,
+    }
+
+    impl Server {
+        fn put(&self, path: &Path, contents: &str) {
+            self.files.borrow_mut().insert(path.to_path_buf(), contents.to_string());
+        }
+
+        fn get(&self, path: &Path) -> Option<String> {
+            self.files.borrow().get(path).cloned()
+        }
+
+        /// Rotates backups and moves an uploaded `contents` into place, as a remote save does.
+        fn save(&self, path: &Path, contents: &str, keep: usize) -> io::Result<Option<io::Error>> {
+            rotate(path, keep, |from, to| remote::replace(self, from, to))?;
+            let partial = remote::partial_path(path);
+            self.put(&partial, contents);
+            remote::move_into_place(self, &partial, path, Some((&backup_path(path, 0), true)))
+        }
+    }
+
+    impl RemoteFiles for Server {
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut files = self.files.borrow_mut();
+            if files.contains_key(to) || self.broken.as_deref() == Some(from) {
+                return Err(io::Error::other("SFTP rename: failure"));
+            }
+            let contents = files.remove(from).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            files.insert(to.to_path_buf(), contents);
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> io::Result<bool> {
+            Ok(self.files.borrow().contains_key(path))
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.files.borrow_mut().remove(path).map(|_| ()).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn remote_saves_replace_files_on_a_server_that_refuses_to_overwrite() {
+        let path = Path::new("/srv/types.xml");
+        let mut server = Server::default();
+        server.put(path, "v1");
+        for contents in ["v2", "v3", "v4"] {
+            assert!(server.save(path, contents, 2).unwrap().is_none());
+        }
+        let names: Vec<_> = server.files.borrow().keys().cloned().collect();
+        assert_eq!(names, [path.to_path_buf(), backup_path(path, 0), backup_path(path, 1)]);
+        assert_eq!(server.get(path).as_deref(), Some("v4"));
+        assert_eq!(server.get(&backup_path(path, 0)).as_deref(), Some("v3"));
+        assert_eq!(server.get(&backup_path(path, 1)).as_deref(), Some("v2"));
+
+        // The upload can't take the file's place: the file comes back from `.bak` and the
+        // upload stays on the server.
+        server.broken = Some(remote::partial_path(path));
+        let err = server.save(path, "v5", 2).unwrap_err();
+        assert!(err.to_string().ends_with("the upload is kept as /srv/types.xml.part"), "{err}");
+        assert_eq!(server.get(path).as_deref(), Some("v4"));
+        assert_eq!(server.get(&remote::partial_path(path)).as_deref(), Some("v5"));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::model::{FieldKey, TypeEntry};
+
+/// Summary of the `nominal` values across a set of types.
+#[derive(Clone, Debug)]
+pub struct NominalStats {
+    pub count: usize,
+    pub min: i64,
+    pub max: i64,
+    pub p50: i64,
+    pub p90: i64,
+    pub p95: i64,
+    pub p99: i64,
+    /// `(low, high, count)` buckets with doubling widths, so a few huge values don't flatten the rest.
+    pub histogram: Vec<(i64, i64, usize)>,
+}
+
+/// One type touched by a nominal cap.
+#[derive(Clone, Debug)]
+pub struct CapChange {
+    pub type_idx: usize,
+    pub name: String,
+    pub old_nominal: i64,
+    pub new_nominal: i64,
+    /// `(old, new)` when `min` was scaled along with nominal.
+    pub min: Option<(i64, i64)>,
+    /// `((quantmin, quantmax) old, new)` when the stack quantities were scaled along.
+    pub quantity: Option<((i64, i64), (i64, i64))>,
+}
+
+impl CapChange {
+    /// `AKM: 300 → 100 (min 40 → 13) (quantity 20–50% → 7–17%)`.
+    pub fn describe(&self) -> String {
+        let mut line = format!("{}: {} → {}", self.name, self.old_nominal, self.new_nominal);
+        if let Some((old, new)) = self.min {
+            line.push_str(&format!(" (min {old} → {new})"));
+        }
+        if let Some(((old_min, old_max), (new_min, new_max))) = self.quantity {
+            line.push_str(&format!(" (quantity {old_min}–{old_max}% → {new_min}–{new_max}%)"));
+        }
+        line
+    }
+}
+
+pub fn element_value(ty: &TypeEntry, element: &str) -> Option<i64> {
+    ty.fields.iter().find_map(|f| match &f.key {
+        FieldKey::Element { name, .. } if name == element => f.value.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn set_element_value(ty: &mut TypeEntry, element: &str, value: i64) {
+    if let Some(field) = ty
+        .fields
+        .iter_mut()
+        .find(|f| matches!(&f.key, FieldKey::Element { name, .. } if name == element))
+    {
+        field.value = value.to_string();
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice.
+pub fn percentile(sorted: &[i64], pct: u32) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct as usize * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+pub fn nominal_stats(types: &[TypeEntry], scope: &[usize]) -> Option<NominalStats> {
+    let mut values: Vec<i64> = scope
+        .iter()
+        .filter_map(|&i| types.get(i))
+        .filter_map(|t| element_value(t, "nominal"))
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+
+    let mut histogram: Vec<(i64, i64, usize)> = Vec::new();
+    let max = *values.last().unwrap_or(&0);
+    let (mut low, mut high) = (0, 0);
+    // The last bucket ends at `max`, so a nominal near i64::MAX can't overflow the bounds.
+    while low <= max {
+        let top = high.min(max);
+        let count = values.iter().filter(|&&v| v >= low && v <= top).count();
+        histogram.push((low, top, count));
+        if top == max {
+            break;
+        }
+        low = top + 1;
+        high = high.checked_mul(2).and_then(|h| h.checked_add(1)).unwrap_or(max);
+    }
+    let below_zero = values.iter().filter(|&&v| v < 0).count();
+    if below_zero > 0 {
+        histogram.insert(0, (values[0], -1, below_zero));
+    }
+
+    Some(NominalStats {
+        count: values.len(),
+        min: values[0],
+        max,
+        p50: percentile(&values, 50),
+        p90: percentile(&values, 90),
+        p95: percentile(&values, 95),
+        p99: percentile(&values, 99),
+        histogram,
+    })
+}
+
+/// quantmin/quantmax of a type that uses them: both present and not the -1/-1 "unused".
+pub fn stack_quantity(ty: &TypeEntry) -> Option<(i64, i64)> {
+    let quantity = (element_value(ty, "quantmin")?, element_value(ty, "quantmax")?);
+    (quantity.0 != -1 && quantity.1 != -1).then_some(quantity)
+}
+
+/// Lists the types in `scope` whose nominal exceeds `cap`, without changing anything.
+/// With `scale_min`, `min` shrinks by the same ratio so it never ends up above the new nominal.
+/// With `scale_quantity`, so do quantmin/quantmax (ammo, stackables), kept within 1..=100
+/// and min ≤ max; types without stack quantities keep theirs.
+pub fn plan_cap(types: &[TypeEntry], scope: &[usize], cap: i64, scale_min: bool, scale_quantity: bool) -> Vec<CapChange> {
+    scope
+        .iter()
+        .filter_map(|&type_idx| {
+            let ty = types.get(type_idx)?;
+            let old_nominal = element_value(ty, "nominal")?;
+            if old_nominal <= cap {
+                return None;
+            }
+            let min = if scale_min {
+                element_value(ty, "min").map(|old_min| {
+                    let scaled = (old_min as f64 * cap as f64 / old_nominal as f64).round() as i64;
+                    (old_min, scaled.clamp(0, cap))
+                })
+            } else {
+                None
+            };
+            let ratio = cap as f64 / old_nominal as f64;
+            let quantity = stack_quantity(ty).filter(|_| scale_quantity).map(|(old_min, old_max)| {
+                let scale = |v: i64| ((v as f64 * ratio).round() as i64).clamp(1, 100);
+                let new_max = scale(old_max);
+                ((old_min, old_max), (scale(old_min).min(new_max), new_max))
+            });
+            Some(CapChange {
+                type_idx,
+                name: ty.name.clone(),
+                old_nominal,
+                new_nominal: cap,
+                min,
+                quantity,
+            })
+        })
+        .collect()
+}
+
+pub fn apply_cap(types: &mut [TypeEntry], changes: &[CapChange]) {
+    for change in changes {
+        let Some(ty) = types.get_mut(change.type_idx) else {
+            continue;
+        };
+        set_element_value(ty, "nominal", change.new_nominal);
+        if let Some((_, new_min)) = change.min {
+            set_element_value(ty, "min", new_min);
+        }
+        if let Some((_, (quantmin, quantmax))) = change.quantity {
+            set_element_value(ty, "quantmin", quantmin);
+            set_element_value(ty, "quantmax", quantmax);
+        }
+    }
+}
+
+/// What the type's numbers make the CE do, for the Tips pane: `keeps 4–8 on map; respawns
+/// instantly when below 4 (restock 0); stacks spawn at 30–70%`. `None` without a nominal.
+pub fn spawn_summary(ty: &TypeEntry) -> Option<String> {
+    let nominal = element_value(ty, "nominal")?;
+    if nominal <= 0 {
+        return Some(String::from("never spawned by the CE (nominal 0)"));
+    }
+    let min = element_value(ty, "min").filter(|&m| m > 0).map(|m| m.min(nominal));
+    let mut parts = vec![match min {
+        Some(min) if min < nominal => format!("keeps {min}–{nominal} on map"),
+        Some(_) => format!("keeps {nominal} on map"),
+        None => format!("keeps up to {nominal} on map (no min)"),
+    }];
+    let when = match min {
+        Some(min) => format!("below {min}"),
+        None => String::from("none are left"),
+    };
+    match element_value(ty, "restock") {
+        Some(0) => parts.push(format!("respawns instantly when {when} (restock 0)")),
+        Some(restock) if restock > 0 => parts.push(format!("respawns when {when}, a wave at most every {}", duration(restock))),
+        _ => {}
+    }
+    if let Some((low, high)) = stack_quantity(ty) {
+        parts.push(if low == high { format!("stacks spawn at {low}%") } else { format!("stacks spawn at {low}–{high}%") });
+    }
+    Some(parts.join("; "))
+}
+
+/// `45 s`, `30 min`, `2 h`, `3 days`: whole units only when they fit exactly.
+pub fn duration(seconds: i64) -> String {
+    match seconds {
+        s if s >= 86400 && s % 86400 == 0 => match s / 86400 {
+            1 => String::from("1 day"),
+            days => format!("{days} days"),
+        },
+        s if s >= 3600 && s % 3600 == 0 => format!("{} h", s / 3600),
+        s if s >= 60 && s % 60 == 0 => format!("{} min", s / 60),
+        s => format!("{s} s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{parse_types, TypeEntry};
+
+    fn type_with(elements: &str) -> TypeEntry {
+        let xml = format!("<types><type name=\"T\">{elements}</type></types>");
+        parse_types(&xml).unwrap().0.remove(0)
+    }
+
+    #[test]
+    fn spawn_summary_reads_nominal_min_restock_and_quantities_together() {
+        let summary = |elements: &str| spawn_summary(&type_with(elements));
+        assert_eq!(
+            summary("<nominal>8</nominal><min>4</min><restock>0</restock><quantmin>30</quantmin><quantmax>70</quantmax>"),
+            Some(String::from("keeps 4–8 on map; respawns instantly when below 4 (restock 0); stacks spawn at 30–70%"))
+        );
+        assert_eq!(
+            summary("<nominal>8</nominal><min>4</min><restock>1800</restock><quantmin>-1</quantmin><quantmax>-1</quantmax>"),
+            Some(String::from("keeps 4–8 on map; respawns when below 4, a wave at most every 30 min"))
+        );
+        assert_eq!(
+            summary("<nominal>5</nominal><restock>0</restock>"),
+            Some(String::from("keeps up to 5 on map (no min); respawns instantly when none are left (restock 0)"))
+        );
+        assert_eq!(summary("<nominal>3</nominal><min>9</min>"), Some(String::from("keeps 3 on map")));
+        assert_eq!(summary("<nominal>0</nominal><min>0</min>"), Some(String::from("never spawned by the CE (nominal 0)")));
+        assert_eq!(summary("<lifetime>3600</lifetime>"), None);
+        assert_eq!(duration(7200), "2 h");
+        assert_eq!(duration(90), "90 s");
+    }
+
+    #[test]
+    fn percentiles_take_the_nearest_rank() {
+        assert_eq!(percentile(&[], 50), 0);
+        assert_eq!(percentile(&[7], 99), 7);
+        let odd = [1, 2, 3, 4, 5];
+        assert_eq!((percentile(&odd, 50), percentile(&odd, 90), percentile(&odd, 99)), (3, 5, 5));
+        let even = [10, 20, 30, 40];
+        assert_eq!((percentile(&even, 50), percentile(&even, 90), percentile(&even, 99)), (20, 40, 40));
+        let hundred: Vec<i64> = (1..=100).collect();
+        assert_eq!((percentile(&hundred, 50), percentile(&hundred, 90), percentile(&hundred, 99)), (50, 90, 99));
+    }
+
+    fn nominals(values: &[i64]) -> Vec<TypeEntry> {
+        values.iter().map(|v| type_with(&format!("<nominal>{v}</nominal><min>{}</min>", v / 2))).collect()
+    }
+
+    #[test]
+    fn buckets_double_and_the_last_ends_at_the_largest_nominal() {
+        let types = nominals(&[0, 1, 2, 5, 20, -3]);
+        let all: Vec<usize> = (0..types.len()).collect();
+        let stats = nominal_stats(&types, &all).unwrap();
+        assert_eq!((stats.count, stats.min, stats.max), (6, -3, 20));
+        assert_eq!(stats.histogram, [(-3, -1, 1), (0, 0, 1), (1, 1, 1), (2, 3, 1), (4, 7, 1), (8, 15, 0), (16, 20, 1)]);
+        assert!(nominal_stats(&types, &[]).is_none());
+
+        let huge = nominals(&[3, i64::MAX]);
+        let stats = nominal_stats(&huge, &[0, 1]).unwrap();
+        assert_eq!(stats.histogram.len(), 64);
+        assert_eq!(stats.histogram.last(), Some(&(1 << 62, i64::MAX, 1)));
+    }
+
+    #[test]
+    fn capping_takes_types_above_the_cap_and_scales_min_along() {
+        let mut types = nominals(&[300, 100, 50]);
+        let changes = plan_cap(&types, &[0, 1, 2], 100, true, false);
+        assert_eq!(changes.len(), 1, "a nominal at the cap stays");
+        assert_eq!(changes[0].describe(), "T: 300 → 100 (min 150 → 50)");
+        assert!(plan_cap(&types, &[0, 1, 2], 100, false, false)[0].min.is_none());
+
+        apply_cap(&mut types, &changes);
+        assert_eq!((element_value(&types[0], "nominal"), element_value(&types[0], "min")), (Some(100), Some(50)));
+        assert_eq!(element_value(&types[1], "nominal"), Some(100));
+
+        let mut odd = vec![type_with("<nominal>300</nominal><min>40</min>")];
+        let changes = plan_cap(&odd, &[0], 100, true, false);
+        apply_cap(&mut odd, &changes);
+        assert_eq!(element_value(&odd[0], "min"), Some(13), "40 × 100/300 rounds to 13");
+    }
+
+    #[test]
+    fn scaled_quantities_stay_within_1_to_100_with_min_at_most_max() {
+        let quantity = |quantmin: i64, quantmax: i64, nominal: i64, cap: i64| {
+            let ty = type_with(&format!("<nominal>{nominal}</nominal><quantmin>{quantmin}</quantmin><quantmax>{quantmax}</quantmax>"));
+            plan_cap(&[ty], &[0], cap, false, true).remove(0).quantity.map(|(_, new)| new)
+        };
+        assert_eq!(quantity(20, 50, 100, 10), Some((2, 5)));
+        assert_eq!(quantity(1, 5, 1000, 10), Some((1, 1)), "never below 1");
+        assert_eq!(quantity(150, 300, 100, 50), Some((75, 100)), "never above 100");
+        assert_eq!(quantity(80, 40, 100, 50), Some((20, 20)), "min pulled down to max");
+        assert_eq!(quantity(-1, -1, 100, 50), None, "unused quantities stay");
+
+        let mut types = vec![type_with("<nominal>40</nominal><min>20</min><quantmin>30</quantmin><quantmax>90</quantmax>")];
+        assert!(plan_cap(&types, &[0], 20, true, false)[0].quantity.is_none());
+        let changes = plan_cap(&types, &[0], 20, true, true);
+        assert_eq!(changes[0].describe(), "T: 40 → 20 (min 20 → 10) (quantity 30–90% → 15–45%)");
+        apply_cap(&mut types, &changes);
+        let values = ["nominal", "min", "quantmin", "quantmax"].map(|e| element_value(&types[0], e));
+        assert_eq!(values, [Some(20), Some(10), Some(15), Some(45)]);
+    }
+}
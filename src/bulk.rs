@@ -0,0 +1,559 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    backup,
+    config::LineEndings,
+    flags,
+    model::{self, parse_types, Field, FieldKey, LineEnding, TypeEntry},
+    progress::Reporter,
+    remote::FileSource,
+    utils,
+};
+
+/// What `:apply` does to each matching field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BulkOp {
+    Set(String),
+    Scale(f64),
+}
+
+/// One edit applied across many files: `<pattern> <field> <=value|*factor>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkEdit {
+    /// Type name pattern; `*` and `?` wildcards, case-insensitive.
+    pub pattern: String,
+    /// Element name (`lifetime`) or `element@attr` (`flags@deloot`).
+    pub field: String,
+    pub op: BulkOp,
+    /// Setting one of the six flags to 0 or 1 gives types without `<flags>` the defaults
+    /// first, instead of skipping them.
+    pub create_flags: bool,
+}
+
+/// Per-file counts of a bulk edit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BulkCount {
+    pub matched_types: usize,
+    pub changed_fields: usize,
+    /// Types that got a default `<flags>` so the edit could apply.
+    pub created_flags: usize,
+}
+
+impl BulkEdit {
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        let [pattern, field, op] = args else {
+            return Err(String::from("expected <type pattern> <field> <=value|*factor>"));
+        };
+        let op = if let Some(value) = op.strip_prefix('=') {
+            BulkOp::Set(value.to_string())
+        } else if let Some(factor) = op.strip_prefix('*') {
+            let factor: f64 = factor.parse().map_err(|_| format!("'{factor}' is not a number"))?;
+            if !factor.is_finite() || factor < 0.0 {
+                return Err(format!("can't scale by {factor}"));
+            }
+            BulkOp::Scale(factor)
+        } else {
+            return Err(format!("'{op}' should be =value or *factor"));
+        };
+        Ok(Self {
+            pattern: pattern.to_string(),
+            field: field.to_string(),
+            op,
+            create_flags: false,
+        })
+    }
+
+    /// `lifetime =14400 on AK*` for titles and summaries.
+    pub fn describe(&self) -> String {
+        let op = match &self.op {
+            BulkOp::Set(value) => format!("={value}"),
+            BulkOp::Scale(factor) => format!("*{factor}"),
+        };
+        format!("{} {op} on {}", self.field, self.pattern)
+    }
+
+    fn targets(&self, key: &FieldKey) -> bool {
+        match (key, self.field.split_once('@')) {
+            (FieldKey::Element { name, .. }, None) => name == &self.field,
+            (FieldKey::Attribute { element, attr, .. }, Some((e, a))) => element == e && attr == a,
+            _ => false,
+        }
+    }
+
+    /// Applies the edit in place. Types without the field count as matched but unchanged;
+    /// non-numeric values are left alone when scaling.
+    pub fn apply(&self, types: &mut [TypeEntry]) -> BulkCount {
+        let mut count = BulkCount::default();
+        let new_flag = match (&self.op, self.field.split_once('@')) {
+            (BulkOp::Set(value), Some(("flags", flag))) if self.create_flags && flags::FLAGS.contains(&flag) => {
+                matches!(value.as_str(), "0" | "1").then_some((flag, value == "1"))
+            }
+            _ => None,
+        };
+        for ty in types.iter_mut().filter(|t| name_matches(&self.pattern, &t.name)) {
+            count.matched_types += 1;
+            if let Some((flag, on)) = new_flag
+                && !ty.fields.iter().any(|f| f.key.get_element_name() == "flags")
+            {
+                flags::set_flag(ty, flag, on, true);
+                count.changed_fields += 1;
+                count.created_flags += 1;
+                continue;
+            }
+            for field in ty.fields.iter_mut().filter(|f| self.targets(&f.key)) {
+                let new = match &self.op {
+                    BulkOp::Set(value) => Some(value.clone()),
+                    BulkOp::Scale(factor) => scale(&field.value, *factor),
+                };
+                if let Some(new) = new
+                    && new != field.value
+                {
+                    field.value = new;
+                    count.changed_fields += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Applies the edit to one type whatever its name; a `Set` adds the field when the
+    /// type has none.
+    pub fn apply_to(&self, ty: &mut TypeEntry) {
+        let any = Self { pattern: String::from("*"), ..self.clone() };
+        any.apply(std::slice::from_mut(ty));
+        if let BulkOp::Set(value) = &self.op
+            && !ty.fields.iter().any(|f| self.targets(&f.key))
+        {
+            let key = match self.field.split_once('@') {
+                Some((element, attr)) => FieldKey::Attribute { element: element.to_string(), index: 0, attr: attr.to_string() },
+                None => FieldKey::Element { name: self.field.clone(), index: 0 },
+            };
+            ty.fields.push(Field { key, value: value.clone() });
+        }
+    }
+}
+
+/// Integers stay integers (rounded); decimals keep up to three places.
+fn scale(value: &str, factor: f64) -> Option<String> {
+    let trimmed = value.trim();
+    if let Ok(n) = trimmed.parse::<i64>() {
+        return Some(((n as f64) * factor).round().to_string());
+    }
+    let n: f64 = trimmed.parse().ok()?;
+    let scaled = format!("{:.3}", n * factor);
+    Some(scaled.trim_end_matches('0').trim_end_matches('.').to_string())
+}
+
+/// Glob match with `*` (any run) and `?` (one character), ignoring ASCII case.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let name: Vec<char> = name.to_ascii_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The mission file that registers types files besides `db/types.xml`.
+pub const ECONOMY_CORE: &str = "cfgeconomycore.xml";
+
+/// Whether the document's root element is `<types>`.
+pub fn is_types_document(content: &str) -> bool {
+    model::root_element(content) == Some("types")
+}
+
+/// A file the dry run found: the content it read and the edited types, or why it can't be edited.
+pub struct BulkFile {
+    pub path: PathBuf,
+    pub plan: Result<BulkPlan, String>,
+}
+
+pub struct BulkPlan {
+    pub original: String,
+    pub types: Vec<TypeEntry>,
+    pub count: BulkCount,
+    /// The file's inline changelog, written back unchanged.
+    pub changelog: Vec<String>,
+}
+
+/// What an edit would do to every types file under `root`; nothing is written yet.
+pub struct DryRun {
+    pub edit: BulkEdit,
+    pub root: PathBuf,
+    pub files: Vec<BulkFile>,
+    /// `.xml` files under the root whose root element isn't `<types>`.
+    pub other_xml: usize,
+    /// The files are the ones the root's `cfgeconomycore.xml` registers, not every `.xml`.
+    pub registered: bool,
+}
+
+impl DryRun {
+    /// Files the write step would touch.
+    pub fn writable(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| f.plan.as_ref().is_ok_and(|p| p.count.changed_fields > 0))
+            .count()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} under {}", self.edit.describe(), self.root.display()),
+            String::new(),
+        ];
+        if self.registered {
+            lines.insert(1, format!("(db/types.xml and the types files {ECONOMY_CORE} registers)"));
+        }
+        for file in &self.files {
+            lines.push(match &file.plan {
+                Ok(plan) if plan.count.changed_fields == 0 => format!(
+                    "  {}: {} types match, nothing to change",
+                    file.path.display(),
+                    plan.count.matched_types
+                ),
+                Ok(plan) => format!(
+                    "  {}: {} types match, {} fields change{}",
+                    file.path.display(),
+                    plan.count.matched_types,
+                    plan.count.changed_fields,
+                    match plan.count.created_flags {
+                        0 => String::new(),
+                        n => format!(" (creates <flags> on {})", utils::plural(n, "type")),
+                    }
+                ),
+                Err(err) => format!("  {}: error: {err}", file.path.display()),
+            });
+        }
+        if self.other_xml > 0 {
+            lines.push(format!("  ({} other .xml files are not types files)", self.other_xml));
+        }
+        lines
+    }
+}
+
+pub struct WrittenFile {
+    pub path: PathBuf,
+    pub changed_fields: usize,
+    pub created_flags: usize,
+    pub types: Vec<TypeEntry>,
+    pub xml: String,
+}
+
+/// Outcome of the write step, split by file.
+pub struct BulkWritten {
+    pub succeeded: Vec<WrittenFile>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// Files left alone because the job was cancelled.
+    pub skipped: usize,
+}
+
+/// The types files `cfgeconomycore.xml` registers, relative to the mission folder:
+/// `<ce folder="custom"><file name="types.xml" type="types" /></ce>`.
+fn registered_types_files(content: &str) -> Result<Vec<PathBuf>, xml::reader::Error> {
+    let mut files = Vec::new();
+    let mut folder: Option<String> = None;
+    for event in EventReader::new(content.as_bytes()) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attr = |key: &str| attributes.iter().find(|a| a.name.local_name == key).map(|a| a.value.clone());
+                match name.local_name.as_str() {
+                    "ce" => folder = attr("folder"),
+                    "file" if attr("type").as_deref() == Some("types") => {
+                        if let (Some(folder), Some(file)) = (&folder, attr("name")) {
+                            files.push(Path::new(folder).join(file));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } if name.local_name == "ce" => folder = None,
+            _ => {}
+        }
+    }
+    Ok(files)
+}
+
+/// The types files the mission at `root` loads: `db/types.xml` and the ones its
+/// `cfgeconomycore.xml` registers. `None` when there is no such file, it doesn't parse or
+/// registers none, and every `.xml` file is looked at instead.
+fn registered_files(source: &FileSource, root: &Path) -> Option<Vec<(PathBuf, Result<(), String>)>> {
+    let content = source.read_file(&root.join(ECONOMY_CORE)).ok()?;
+    let registered = registered_types_files(&content).ok().filter(|files| !files.is_empty())?;
+    let mut files = vec![root.join("db").join("types.xml")];
+    for path in registered.into_iter().map(|p| root.join(p)) {
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    Some(files.into_iter().map(|path| (path, Ok(()))).collect())
+}
+
+/// `.xml` files below `root`, depth-first; unreadable directories are reported as errors.
+fn find_xml_files(source: &FileSource, root: &Path) -> Vec<(PathBuf, Result<(), String>)> {
+    const MAX_DEPTH: usize = 8;
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match source.list_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                found.push((dir, Err(format!("can't list directory: {err}"))));
+                continue;
+            }
+        };
+        for entry in entries {
+            if entry.name.starts_with('.') {
+                continue;
+            }
+            let path = dir.join(&entry.name);
+            if entry.is_dir {
+                if depth < MAX_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if Path::new(&entry.name).extension().is_some_and(|e| e.eq_ignore_ascii_case("xml")) {
+                found.push((path, Ok(())));
+            }
+        }
+    }
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+/// Reads and edits every types file under `root` in memory (the ones its
+/// `cfgeconomycore.xml` registers, when it has one), first converting text-form
+/// category/usage/tag elements when `normalize_names` is set. `None` if cancelled.
+pub fn dry_run(source: &FileSource, edit: BulkEdit, root: PathBuf, normalize_names: bool, progress: &Reporter) -> Option<DryRun> {
+    progress.step(0, 0, format!("Looking for .xml files under {}", root.display()));
+    let registered = registered_files(source, &root);
+    let mut run = DryRun {
+        edit,
+        files: Vec::new(),
+        other_xml: 0,
+        registered: registered.is_some(),
+        root,
+    };
+    let found = registered.unwrap_or_else(|| find_xml_files(source, &run.root));
+    for (i, (path, listed)) in found.iter().enumerate() {
+        if !progress.step(i, found.len(), path.display().to_string()) {
+            return None;
+        }
+        let plan = listed
+            .clone()
+            .and_then(|()| source.read_file(path).map_err(|e| e.to_string()))
+            .and_then(|original| {
+                if !is_types_document(&original) {
+                    return Ok(None);
+                }
+                let (mut types, report) = parse_types(&original).map_err(|e| format!("XML parse error: {e}"))?;
+                if normalize_names {
+                    model::normalize_name_elements(&mut types);
+                }
+                let count = run.edit.apply(&mut types);
+                Ok(Some(BulkPlan { original, types, count, changelog: report.changelog }))
+            });
+        match plan {
+            Ok(None) => run.other_xml += 1,
+            Ok(Some(plan)) => run.files.push(BulkFile { path: path.clone(), plan: Ok(plan) }),
+            Err(err) => run.files.push(BulkFile { path: path.clone(), plan: Err(err) }),
+        }
+    }
+    Some(run)
+}
+
+/// Writes each planned file that has changes, with a backup. A file that changed since
+/// the dry run, or fails to write, is reported and the rest carry on.
+pub fn write_all(
+    source: &FileSource,
+    files: Vec<BulkFile>,
+    canonical_order: bool,
+    line_endings: LineEndings,
+    keep: usize,
+    progress: &Reporter,
+) -> BulkWritten {
+    let mut written = BulkWritten {
+        succeeded: Vec::new(),
+        failed: Vec::new(),
+        skipped: 0,
+    };
+    let total = files.len();
+    for (i, file) in files.into_iter().enumerate() {
+        let plan = match file.plan {
+            Ok(plan) if plan.count.changed_fields == 0 => continue,
+            Ok(plan) => plan,
+            Err(err) => {
+                written.failed.push((file.path, err));
+                continue;
+            }
+        };
+        if !progress.step(i, total, file.path.display().to_string()) {
+            written.skipped += 1;
+            continue;
+        }
+        let result = source
+            .read_file(&file.path)
+            .and_then(|current| {
+                if current != plan.original {
+                    return Err(io::Error::other("changed on disk since the dry run"));
+                }
+                let (detected, _) = LineEnding::detect(&plan.original);
+                let xml = model::serialize_document(&plan.types, canonical_order, &plan.changelog)?;
+                Ok(line_endings.resolve(detected).apply(xml))
+            })
+            .and_then(|xml| {
+                backup::write_with_backup(source, &file.path, &plan.original, &xml, keep)?;
+                Ok(xml)
+            });
+        match result {
+            Ok(xml) => written.succeeded.push(WrittenFile {
+                path: file.path,
+                changed_fields: plan.count.changed_fields,
+                created_flags: plan.count.created_flags,
+                types: plan.types,
+                xml,
+            }),
+            Err(err) => written.failed.push((file.path, err.to_string())),
+        }
+    }
+    progress.step(total, total, "");
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        progress,
+        test_harness::{Fixture, SAMPLE},
+    };
+
+    /// Runs `work` as a progress job and waits for it, for the functions that report steps.
+    fn run<T: Send + 'static>(work: impl FnOnce(&Reporter) -> T + Send + 'static) -> T {
+        let mut job = progress::spawn("test", work);
+        loop {
+            if let Some(result) = job.poll() {
+                return result.expect("job finished");
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn patterns_glob_over_the_whole_name_ignoring_case() {
+        assert!(name_matches("AKM", "akm"));
+        assert!(name_matches("*", "Apple"));
+        assert!(name_matches("mosin*", "Mosin9130"));
+        assert!(name_matches("*9130", "Mosin9130"));
+        assert!(name_matches("A?M", "AKM"));
+        assert!(name_matches("*o*9*", "Mosin9130"));
+        assert!(!name_matches("AK", "AKM"));
+        assert!(!name_matches("A?", "AKM"));
+        assert!(!name_matches("?AKM", "AKM"));
+        assert!(!name_matches("*Apple", "Apples"));
+    }
+
+    #[test]
+    fn a_broken_file_is_reported_while_the_rest_are_planned_and_written() {
+        let fixture = Fixture::new();
+        let first = fixture.write("types.xml", SAMPLE);
+        let second = fixture.write("expansion/types.xml", &SAMPLE.replace("AKM", "AKS74U"));
+        let broken = fixture.write("broken.xml", "<types><type name=\"Torn\">");
+        fixture.write("cfgeconomycore.xml", "<economycore />");
+
+        let root = fixture.dir.clone();
+        let edit = BulkEdit::parse(&["AK*", "lifetime", "=900"]).unwrap();
+        let planned = run(move |progress| dry_run(&FileSource::Local, edit, root, false, progress)).expect("not cancelled");
+
+        assert_eq!(planned.other_xml, 1);
+        assert_eq!(planned.writable(), 2);
+        let paths: Vec<_> = planned.files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [broken.clone(), second.clone(), first.clone()]);
+        assert!(planned.files[0].plan.as_ref().is_err_and(|e| e.starts_with("XML parse error")));
+        for file in &planned.files[1..] {
+            let count = &file.plan.as_ref().unwrap().count;
+            assert_eq!((count.matched_types, count.changed_fields), (1, 1));
+        }
+
+        let files = planned.files;
+        let written = run(move |progress| write_all(&FileSource::Local, files, false, LineEndings::Auto, 3, progress));
+        assert_eq!(written.failed.len(), 1);
+        assert_eq!(written.failed[0].0, broken);
+        assert_eq!(written.succeeded.len(), 2);
+        for (name, original) in [("types.xml", SAMPLE.to_string()), ("expansion/types.xml", SAMPLE.replace("AKM", "AKS74U"))] {
+            assert!(fixture.read(name).contains("<lifetime>900</lifetime>"));
+            assert_eq!(fixture.read(&format!("{name}.bak")), original);
+            assert!(!fixture.dir.join(format!("{name}.part")).exists());
+        }
+    }
+
+    #[test]
+    fn a_mission_with_cfgeconomycore_edits_the_files_it_registers() {
+        let fixture = Fixture::new();
+        let vanilla = fixture.write("db/types.xml", SAMPLE);
+        let custom = fixture.write("custom/types.xml", &SAMPLE.replace("AKM", "AKS74U"));
+        fixture.write("old/types.xml", SAMPLE);
+        fixture.write(
+            ECONOMY_CORE,
+            r#"<economycore>
+  <classes />
+  <ce folder="custom">
+    <file name="types.xml" type="types" />
+    <file name="events.xml" type="events" />
+    <file name="missing.xml" type="types" />
+  </ce>
+</economycore>"#,
+        );
+
+        let root = fixture.dir.clone();
+        let edit = BulkEdit::parse(&["AK*", "lifetime", "=900"]).unwrap();
+        let planned = run(move |progress| dry_run(&FileSource::Local, edit, root, false, progress)).expect("not cancelled");
+        assert!(planned.registered);
+        let paths: Vec<_> = planned.files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [vanilla, custom, fixture.dir.join("custom/missing.xml")], "old/types.xml isn't registered");
+        assert!(planned.files[2].plan.is_err());
+        assert_eq!(planned.lines()[1], "(db/types.xml and the types files cfgeconomycore.xml registers)");
+
+        // One that registers no types files says nothing about where they are.
+        fixture.write(ECONOMY_CORE, "<economycore><classes /></economycore>");
+        let root = fixture.dir.clone();
+        let edit = BulkEdit::parse(&["AK*", "lifetime", "=900"]).unwrap();
+        let planned = run(move |progress| dry_run(&FileSource::Local, edit, root, false, progress)).expect("not cancelled");
+        assert!(!planned.registered);
+        assert_eq!((planned.files.len(), planned.other_xml), (3, 1));
+    }
+
+    #[test]
+    fn setting_a_flag_creates_flags_only_when_asked() {
+        let xml = r#"<types><type name="Bare"><nominal>1</nominal></type><type name="Flagged"><flags deloot="0" /></type></types>"#;
+        let (types, _) = parse_types(xml).unwrap();
+        let edit = BulkEdit::parse(&["*", "flags@deloot", "=1"]).unwrap();
+
+        let mut skipped = types.clone();
+        assert_eq!(edit.apply(&mut skipped), BulkCount { matched_types: 2, changed_fields: 1, created_flags: 0 });
+        assert_eq!(skipped[0], types[0]);
+
+        let mut created = types;
+        let count = BulkEdit { create_flags: true, ..edit }.apply(&mut created);
+        assert_eq!(count, BulkCount { matched_types: 2, changed_fields: 2, created_flags: 1 });
+        assert_eq!(flags::flag_of(&created[0], "deloot"), Some("1"));
+        assert_eq!(flags::flag_of(&created[0], "count_in_map"), Some("1"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::model::TypeEntry;
+
+/// How many completions the name prompts offer at most.
+pub const COMPLETION_LIMIT: usize = 8;
+
+/// The server's classnames as they are spelled (`classnames` in the config, or
+/// `:classnames <file>`): plain text, one per line, e.g. dumped from the server's config.
+/// The CE matches type names in any case, but trader configs and admin tools don't, so an
+/// `akm` that loads fine can still miss downstream.
+#[derive(Clone, Debug)]
+pub struct Classnames {
+    /// Where the list came from, for messages: the file's name.
+    pub source: String,
+    /// Sorted case-insensitively, for completion.
+    names: Vec<String>,
+    /// Lowercased name to its index in `names`.
+    by_lower: HashMap<String, usize>,
+}
+
+impl Classnames {
+    /// Reads one classname per line; blank lines and `//` or `#` comments are skipped, and
+    /// of two spellings of one name the first counts.
+    pub fn parse(text: &str, source: impl Into<String>) -> Self {
+        let mut seen = HashSet::new();
+        let mut names: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with('#'))
+            .filter(|name| seen.insert(name.to_lowercase()))
+            .map(String::from)
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        let by_lower = names.iter().enumerate().map(|(i, name)| (name.to_lowercase(), i)).collect();
+        Self { source: source.into(), names, by_lower }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The list's spelling of `name`, matched in any case.
+    pub fn canonical(&self, name: &str) -> Option<&str> {
+        self.by_lower.get(&name.to_lowercase()).map(|&i| self.names[i].as_str())
+    }
+
+    /// Listed names starting with `prefix` in any case, other than `prefix` itself.
+    pub fn completions(&self, prefix: &str) -> Vec<&str> {
+        let lower = prefix.to_lowercase();
+        if lower.is_empty() {
+            return Vec::new();
+        }
+        let start = self.names.partition_point(|name| name.to_lowercase() < lower);
+        self.names[start..]
+            .iter()
+            .take_while(|name| name.to_lowercase().starts_with(&lower))
+            .filter(|name| *name != prefix)
+            .take(COMPLETION_LIMIT)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// What's wrong with `name` against the list, for the status after an add or rename:
+    /// a different case, or no such classname at all.
+    pub fn note(&self, name: &str) -> Option<String> {
+        match self.canonical(name) {
+            Some(spelled) if spelled == name => None,
+            Some(spelled) => Some(format!("{} spells it '{spelled}' (:fixcase)", self.source)),
+            None => Some(format!("'{name}' is not in {} (a typo?)", self.source)),
+        }
+    }
+}
+
+/// A type whose name differs from the list's only in case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseFix {
+    pub type_idx: usize,
+    pub from: String,
+    pub to: String,
+}
+
+/// `:fixcase`: every type name checked against the list before anything changes, so the
+/// preview shows exactly what Enter will do.
+#[derive(Clone, Debug)]
+pub struct CaseReview {
+    pub source: String,
+    pub fixes: Vec<CaseFix>,
+    /// Renames left out because the file already has a type of the list's spelling.
+    pub clashes: Vec<CaseFix>,
+    /// Names the list doesn't have in any case: likely typos, or classes of a mod the list
+    /// wasn't dumped with.
+    pub unknown: Vec<String>,
+}
+
+pub fn review(types: &[TypeEntry], list: &Classnames) -> CaseReview {
+    let present: HashSet<&str> = types.iter().map(|t| t.name.as_str()).collect();
+    let mut review = CaseReview { source: list.source.clone(), fixes: Vec::new(), clashes: Vec::new(), unknown: Vec::new() };
+    for (idx, ty) in types.iter().enumerate() {
+        match list.canonical(&ty.name) {
+            Some(spelled) if spelled == ty.name => {}
+            Some(spelled) => {
+                let fix = CaseFix { type_idx: idx, from: ty.name.clone(), to: spelled.to_string() };
+                if present.contains(spelled) { review.clashes.push(fix) } else { review.fixes.push(fix) }
+            }
+            None => review.unknown.push(ty.name.clone()),
+        }
+    }
+    review
+}
+
+impl CaseReview {
+    /// The preview: the renames, the ones left out, then the names the list lacks.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("Type names spelled as in {}:", self.source), String::new()];
+        if self.fixes.is_empty() {
+            lines.push(String::from("  (none to rename)"));
+        }
+        lines.extend(self.fixes.iter().map(|fix| format!("  {} → {}", fix.from, fix.to)));
+        if !self.clashes.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Left alone, the file already has a type of that spelling:"));
+            lines.extend(self.clashes.iter().map(|fix| format!("    {} (≠ {})", fix.from, fix.to)));
+        }
+        if !self.unknown.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("Not in {} in any case, likely typos:", self.source));
+            lines.extend(self.unknown.iter().map(|name| format!("    {name}")));
+        }
+        lines
+    }
+}
+
+/// Renames the types of `fixes`.
+pub fn apply(types: &mut [TypeEntry], fixes: &[CaseFix]) {
+    for fix in fixes {
+        if let Some(ty) = types.get_mut(fix.type_idx) {
+            ty.name = fix.to.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    fn list() -> Classnames {
+        Classnames::parse("// dumped from the server\nAKM\n\nApple\nM4A1\n  Mosin9130  \nakm\n# weapons end\nM4A1_Green\n", "classnames.txt")
+    }
+
+    #[test]
+    fn the_list_matches_in_any_case_and_completes_prefixes() {
+        let list = list();
+        assert_eq!(list.len(), 5, "comments, blank lines and the second akm don't count");
+        assert_eq!(list.canonical("m4a1"), Some("M4A1"));
+        assert_eq!(list.canonical("Mosin9130"), Some("Mosin9130"));
+        assert_eq!(list.canonical("AK"), None);
+        assert_eq!(list.completions("m4"), ["M4A1", "M4A1_Green"]);
+        assert_eq!(list.completions("M4A1"), ["M4A1_Green"]);
+        assert!(list.completions("").is_empty());
+        assert_eq!(list.note("AKM"), None);
+        assert_eq!(list.note("akm").as_deref(), Some("classnames.txt spells it 'AKM' (:fixcase)"));
+        assert_eq!(list.note("AKMM").as_deref(), Some("'AKMM' is not in classnames.txt (a typo?)"));
+    }
+
+    #[test]
+    fn the_review_renames_case_only_and_reports_the_rest() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        types[0].name = String::from("akm");
+        types[2].name = String::from("MOSIN9130");
+        let mut extra = types[1].clone();
+        extra.name = String::from("m4a1");
+        types.push(extra.clone());
+        extra.name = String::from("M4A1");
+        types.push(extra.clone());
+        extra.name = String::from("Pear");
+        types.push(extra);
+
+        let review = review(&types, &list());
+        assert_eq!(
+            review.fixes.iter().map(|f| (f.type_idx, f.to.as_str())).collect::<Vec<_>>(),
+            [(0, "AKM"), (2, "Mosin9130")]
+        );
+        assert_eq!(review.clashes.len(), 1);
+        assert_eq!(review.unknown, ["Pear"]);
+        assert!(review.lines().contains(&String::from("  akm → AKM")));
+        assert!(review.lines().contains(&String::from("    m4a1 (≠ M4A1)")));
+
+        apply(&mut types, &review.fixes);
+        let names: Vec<&str> = types.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["AKM", "Apple", "Mosin9130", "m4a1", "M4A1", "Pear"]);
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+
+use crate::{
+    config::{ConfigLayer, Theme},
+    remote::SshUri,
+};
+
+pub const USAGE: &str = "Usage: dayz-loot-editor-rust [--readonly] [--no-color] [--dir DIR] [FILE [--goto TYPE [--field ELEMENT]]]
+       dayz-loot-editor-rust --plain [--readonly]
+       dayz-loot-editor-rust doctor [--connect]
+       dayz-loot-editor-rust check [--format text|json] FILE...
+       dayz-loot-editor-rust diff [--format text|json] OLD NEW
+       dayz-loot-editor-rust apply [--write] DIR PATTERN FIELD =VALUE|*FACTOR
+
+Options:
+  FILE           open this types file instead of starting in the file picker; an
+                 ssh://user@host[:port]/path downloads it over SSH (the key or password
+                 comes from SSH_* or [ssh] when they name the same host, else the agent)
+  --goto TYPE    highlight this type once the file (FILE, or the first one picked, also
+                 over SSH) is open; the exact name, else the first name containing it
+  --field ELEMENT  with --goto: select this field on the Fields pane, e.g. nominal
+  --dir DIR      start the file picker in DIR, as default_dir in the config does
+  --readonly     open files read-only; edits and saves are refused
+  --no-color     draw without colours, as theme = \"mono\" does; also picked
+                 automatically for NO_COLOR or TERM=dumb unless the config sets a theme
+  --plain        line-oriented interface on stdin/stdout instead of the full screen
+                 (for screen readers, serial consoles and scripts; `help` lists commands)
+  -V, --version  print the version and build details
+  -h, --help     show this help
+
+Commands:
+  doctor         check the terminal, config file, SSH_* profile, key file and write
+                 access, one OK/WARN/FAIL line each; exits 0, 1 (warnings) or 2 (failures)
+    --connect    also log in to the SSH host (gives up after a few seconds)
+  check          validate types files as the editor does, with each file's project config;
+                 exits 0 (clean), 1 (warnings only) or 2 (errors, or a file that won't read)
+  diff           compare two types files by type name; exits 0 (same), 1 (different) or 2
+    --format     text (default) or json, for scripts and CI
+  apply          dry-run one edit over the types files under DIR, as :apply does (the ones
+                 its cfgeconomycore.xml registers, when it has one), e.g. apply . AK* lifetime
+                 =14400; exits 0, or 2 when a file can't be read or written
+    --write      then write the changed files, each with a .bak";
+
+/// The commands that print a result and exit without a screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Headless {
+    Check,
+    Diff,
+    Apply,
+}
+
+/// How `check` and `diff` print their results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Command-line options; anything given here beats every config file.
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub readonly: bool,
+    /// Forces the monochrome theme over config and environment.
+    pub no_color: bool,
+    pub plain: bool,
+    pub help: bool,
+    pub version: bool,
+    /// Run the environment checks and exit instead of starting the editor.
+    pub doctor: bool,
+    /// `doctor` also tries the SSH connection.
+    pub connect: bool,
+    /// Opened at start instead of showing the file picker.
+    pub file: Option<PathBuf>,
+    /// `FILE` given as `ssh://user@host[:port]/path`; `file` is then empty.
+    pub remote: Option<SshUri>,
+    /// Type to highlight in the first file that opens.
+    pub goto: Option<String>,
+    /// Field to select in that type.
+    pub field: Option<String>,
+    /// Where the file picker starts.
+    pub dir: Option<PathBuf>,
+    /// `check` or `diff` instead of the editor.
+    pub headless: Option<Headless>,
+    /// The files `check` or `diff` read, or the directory `apply` works in.
+    pub paths: Vec<PathBuf>,
+    pub format: Format,
+    /// `apply`'s edit: pattern, field and `=value` or `*factor`.
+    pub edit: Vec<String>,
+    /// `apply` writes the files after listing them.
+    pub write: bool,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--readonly" => cli.readonly = true,
+                "--no-color" => cli.no_color = true,
+                "--plain" => cli.plain = true,
+                "-h" | "--help" => cli.help = true,
+                "-V" | "--version" => cli.version = true,
+                "doctor" => cli.doctor = true,
+                "--connect" => cli.connect = true,
+                "check" if cli.headless.is_none() => cli.headless = Some(Headless::Check),
+                "diff" if cli.headless.is_none() => cli.headless = Some(Headless::Diff),
+                "apply" if cli.headless.is_none() => cli.headless = Some(Headless::Apply),
+                "--write" => cli.write = true,
+                "--format" => {
+                    cli.format = match args.next().as_deref() {
+                        Some("text") => Format::Text,
+                        Some("json") => Format::Json,
+                        _ => return Err(String::from("--format needs text or json")),
+                    }
+                }
+                "--goto" => cli.goto = Some(args.next().ok_or("--goto needs a type name")?),
+                "--field" => cli.field = Some(args.next().ok_or("--field needs an element name")?),
+                "--dir" => cli.dir = Some(PathBuf::from(args.next().ok_or("--dir needs a directory")?)),
+                other if other.starts_with('-') => return Err(format!("unknown argument '{other}'")),
+                _ if cli.headless == Some(Headless::Apply) && !cli.paths.is_empty() => cli.edit.push(arg),
+                _ if cli.headless.is_some() => cli.paths.push(PathBuf::from(arg)),
+                _ if cli.file.is_some() => return Err(format!("only one file can be opened, '{arg}' is a second")),
+                _ => cli.file = Some(PathBuf::from(arg)),
+            }
+        }
+        if let Some(command) = cli.headless {
+            // A file named before the command is one of its inputs too.
+            if let Some(file) = cli.file.take() {
+                cli.paths.insert(0, file);
+            }
+            match command {
+                Headless::Check if cli.paths.is_empty() => return Err(String::from("check needs at least one file")),
+                Headless::Diff if cli.paths.len() != 2 => return Err(String::from("diff needs two files, the old one first")),
+                Headless::Apply if cli.paths.len() != 1 || cli.edit.len() != 3 => {
+                    return Err(String::from("apply needs a directory and an edit: PATTERN FIELD =VALUE|*FACTOR"));
+                }
+                Headless::Apply if cli.format != Format::Text => return Err(String::from("--format only applies to check and diff")),
+                _ => {}
+            }
+            if cli.doctor || cli.plain || cli.goto.is_some() {
+                return Err(String::from("check, diff and apply only print their result; they take no other command or editor option"));
+            }
+        } else if cli.format != Format::Text {
+            return Err(String::from("--format only applies to check and diff"));
+        }
+        if cli.write && cli.headless != Some(Headless::Apply) {
+            return Err(String::from("--write only applies to apply"));
+        }
+        if cli.connect && !cli.doctor {
+            return Err(String::from("--connect only applies to doctor"));
+        }
+        if cli.field.is_some() && cli.goto.is_none() {
+            return Err(String::from("--field needs --goto to say which type"));
+        }
+        if cli.plain && (cli.file.is_some() || cli.goto.is_some()) {
+            return Err(String::from("--plain opens files with its `open PATH` command; FILE and --goto are for the full-screen editor"));
+        }
+        if let Some(uri) = cli.file.as_deref().and_then(|file| SshUri::parse(&file.to_string_lossy())) {
+            cli.remote = Some(uri?);
+            cli.file = None;
+        }
+        Ok(cli)
+    }
+
+    /// The options that override configuration, as the top config layer.
+    pub fn config_layer(&self) -> ConfigLayer {
+        ConfigLayer {
+            readonly: self.readonly.then_some(true),
+            default_dir: self.dir.clone(),
+            theme: self.no_color.then_some(Theme::Mono),
+            ..ConfigLayer::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote::{AuthMethod, RemoteConfig};
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn a_file_can_be_opened_at_a_type_and_field() {
+        let cli = parse(&["db/types.xml", "--goto", "AKM", "--field", "nominal", "--readonly"]).unwrap();
+        assert_eq!(cli.file, Some(PathBuf::from("db/types.xml")));
+        assert_eq!((cli.goto.as_deref(), cli.field.as_deref(), cli.readonly), (Some("AKM"), Some("nominal"), true));
+        assert!(parse(&["--goto", "AKM"]).unwrap().file.is_none(), "--goto alone waits for the picked file");
+        assert_eq!(parse(&["--no-color"]).unwrap().config_layer().theme, Some(Theme::Mono));
+        assert_eq!(parse(&["--dir", "/srv/dayz"]).unwrap().config_layer().default_dir, Some(PathBuf::from("/srv/dayz")));
+
+        assert_eq!(parse(&["--goto"]).unwrap_err(), "--goto needs a type name");
+        assert!(parse(&["types.xml", "--field", "nominal"]).is_err());
+        assert!(parse(&["a.xml", "b.xml"]).is_err());
+        assert!(parse(&["--plain", "types.xml"]).is_err());
+        assert!(parse(&["--gotoo"]).is_err());
+    }
+
+    #[test]
+    fn an_ssh_uri_opens_a_remote_file() {
+        let cli = parse(&["ssh://dayz@203.0.113.5:2222/srv/dayz/db/types.xml", "--goto", "AKM"]).unwrap();
+        let uri = cli.remote.unwrap();
+        assert!(cli.file.is_none());
+        assert_eq!((uri.username.as_str(), uri.host.as_str(), uri.port), ("dayz", "203.0.113.5", 2222));
+        assert_eq!(uri.path, PathBuf::from("/srv/dayz/db/types.xml"));
+        assert_eq!(parse(&["ssh://dayz@host/types.xml"]).unwrap().remote.unwrap().port, 22);
+
+        assert!(parse(&["ssh://host/types.xml"]).unwrap_err().contains("needs a user"));
+        assert!(parse(&["ssh://dayz@host:ssh/types.xml"]).unwrap_err().contains("'ssh' is not a port"));
+        assert!(parse(&["ssh://dayz@host/"]).unwrap_err().contains("names no file"));
+        assert!(parse(&["--plain", "ssh://dayz@host/types.xml"]).is_err());
+    }
+
+    #[test]
+    fn an_ssh_uri_borrows_the_login_of_a_profile_for_the_same_host() {
+        let uri = SshUri::parse("ssh://dayz@host/types.xml").unwrap().unwrap();
+        let profile = RemoteConfig {
+            host: String::from("host"),
+            port: 22,
+            username: String::from("admin"),
+            password: None,
+            key_path: Some(PathBuf::from("/keys/dayz")),
+            passphrase: None,
+            auth: AuthMethod::Key,
+            readonly: true,
+        };
+        let cfg = uri.remote_config(Some(profile.clone()));
+        assert_eq!((cfg.username.as_str(), cfg.auth, cfg.readonly), ("dayz", AuthMethod::Key, true));
+        assert_eq!(cfg.key_path, Some(PathBuf::from("/keys/dayz")));
+
+        let other = RemoteConfig { host: String::from("elsewhere"), ..profile };
+        let cfg = uri.remote_config(Some(other));
+        assert_eq!((cfg.auth, cfg.key_path, cfg.readonly), (AuthMethod::Agent, None, false));
+    }
+
+    #[test]
+    fn check_and_diff_take_their_files_and_a_format() {
+        let cli = parse(&["check", "--format", "json", "a.xml", "b.xml"]).unwrap();
+        assert_eq!((cli.headless, cli.format), (Some(Headless::Check), Format::Json));
+        assert_eq!(cli.paths, [PathBuf::from("a.xml"), PathBuf::from("b.xml")]);
+        assert!(cli.file.is_none());
+        assert_eq!(parse(&["old.xml", "diff", "new.xml"]).unwrap().paths.len(), 2);
+
+        assert_eq!(parse(&["diff", "a.xml"]).unwrap_err(), "diff needs two files, the old one first");
+        assert!(parse(&["check"]).is_err());
+        assert!(parse(&["check", "a.xml", "--format", "yaml"]).is_err());
+        assert!(parse(&["a.xml", "--format", "json"]).is_err(), "the editor has no format");
+        assert!(parse(&["check", "a.xml", "--plain"]).is_err());
+    }
+
+    #[test]
+    fn apply_takes_a_directory_and_an_edit() {
+        let cli = parse(&["apply", "--write", "mpmissions/dayzOffline.chernarusplus", "AK*", "lifetime", "=14400"]).unwrap();
+        assert_eq!((cli.headless, cli.write), (Some(Headless::Apply), true));
+        assert_eq!(cli.paths, [PathBuf::from("mpmissions/dayzOffline.chernarusplus")]);
+        assert_eq!(cli.edit, ["AK*", "lifetime", "=14400"]);
+        assert!(!parse(&["apply", ".", "*", "nominal", "*2"]).unwrap().write, "a dry run unless asked");
+
+        assert_eq!(parse(&["apply", ".", "AK*"]).unwrap_err(), "apply needs a directory and an edit: PATTERN FIELD =VALUE|*FACTOR");
+        assert!(parse(&["apply", ".", "AK*", "lifetime", "=1", "extra"]).is_err());
+        assert!(parse(&["apply", "--format", "json", ".", "AK*", "lifetime", "=1"]).is_err());
+        assert_eq!(parse(&["check", "a.xml", "--write"]).unwrap_err(), "--write only applies to apply");
+    }
+}
@@ -0,0 +1,344 @@
+use std::{fs, io, path::PathBuf};
+
+use tui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::{
+    action::Action,
+    diff::{self, FieldChange, TypesDiff},
+    line_edit,
+    model::{parse_types, TypeEntry},
+    remote::FileSelection,
+    utils,
+};
+
+/// Reads and parses one side of a comparison, locally or over SFTP.
+pub fn load_side(selection: &FileSelection) -> io::Result<Vec<TypeEntry>> {
+    let content = selection.source.read_file(&selection.path)?;
+    let (types, _) = parse_types(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("XML parse error: {e}")))?;
+    Ok(types)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RowKind {
+    Changed,
+    Field,
+    Added,
+    Removed,
+}
+
+/// One line of the two-column view; an empty side is blank.
+struct Row {
+    kind: RowKind,
+    left: String,
+    right: String,
+}
+
+/// Read-only side-by-side diff of two files picked in the file picker.
+pub struct CompareView {
+    left: String,
+    right: String,
+    diff: TypesDiff,
+    /// Only changes to this element (or exact field label, e.g. `flags @deloot`) are listed.
+    filter: String,
+    editing_filter: bool,
+    filter_cursor: Option<usize>,
+    rows: Vec<Row>,
+    state: ListState,
+    export_dir: PathBuf,
+    status: String,
+}
+
+impl CompareView {
+    pub fn new(left: String, left_types: &[TypeEntry], right: String, right_types: &[TypeEntry], export_dir: PathBuf) -> Self {
+        let diff = diff::diff_types(left_types, right_types);
+        let status = if diff.is_empty() {
+            String::from("No differences")
+        } else {
+            format!(
+                "{} changed, {} only in {right}, {} only in {left}",
+                diff.changed.len(),
+                diff.added.len(),
+                diff.removed.len()
+            )
+        };
+        let mut view = Self {
+            left,
+            right,
+            diff,
+            filter: String::new(),
+            editing_filter: false,
+            filter_cursor: None,
+            rows: Vec::new(),
+            state: ListState::default(),
+            export_dir,
+            status,
+        };
+        view.rebuild_rows();
+        view
+    }
+
+    /// Whether keys should arrive as text (the field filter is being typed).
+    pub fn is_editing(&self) -> bool {
+        self.editing_filter
+    }
+
+    /// Returns `true` when the view should close.
+    pub fn handle_action(&mut self, action: Action) -> bool {
+        if self.editing_filter {
+            match action {
+                _ if line_edit::edit(&mut self.filter, &mut self.filter_cursor, &action) => {}
+                Action::Activate => self.editing_filter = false,
+                Action::Cancel => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => return false,
+            }
+            self.rebuild_rows();
+            return false;
+        }
+        match action {
+            Action::Up => self.scroll(-1),
+            Action::Down => self.scroll(1),
+            Action::PgUp => self.scroll(-10),
+            Action::PgDown => self.scroll(10),
+            Action::ToggleFilter => {
+                self.editing_filter = true;
+                self.filter_cursor = None;
+            }
+            Action::Input('e') => self.export(),
+            Action::Cancel if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.rebuild_rows();
+            }
+            Action::Cancel | Action::Quit => return true,
+            _ => {}
+        }
+        false
+    }
+
+    fn matches(&self, change: &FieldChange) -> bool {
+        let filter = self.filter.trim();
+        filter.is_empty()
+            || change.key.element_key().0.eq_ignore_ascii_case(filter)
+            || change.key.label().eq_ignore_ascii_case(filter)
+    }
+
+    fn rebuild_rows(&mut self) {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| String::from("(absent)"));
+        let mut rows = Vec::new();
+        for change in &self.diff.changed {
+            let fields: Vec<&FieldChange> = change.fields.iter().filter(|f| self.matches(f)).collect();
+            if fields.is_empty() {
+                continue;
+            }
+            rows.push(Row {
+                kind: RowKind::Changed,
+                left: format!("~ {}", change.name),
+                right: format!("~ {}", change.name),
+            });
+            for field in fields {
+                let label = field.key.label();
+                rows.push(Row {
+                    kind: RowKind::Field,
+                    left: format!("    {label}: {}", show(&field.old)),
+                    right: format!("    {label}: {}", show(&field.new)),
+                });
+            }
+        }
+        // Whole types have no single field to match, so a filter hides them.
+        if self.filter.is_empty() {
+            for name in &self.diff.added {
+                rows.push(Row {
+                    kind: RowKind::Added,
+                    left: String::new(),
+                    right: format!("+ {name}"),
+                });
+            }
+            for name in &self.diff.removed {
+                rows.push(Row {
+                    kind: RowKind::Removed,
+                    left: format!("- {name}"),
+                    right: String::new(),
+                });
+            }
+        }
+        self.rows = rows;
+        self.state.select((!self.rows.is_empty()).then_some(0));
+    }
+
+    fn scroll(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let new = (current + delta).clamp(0, self.rows.len() as isize - 1);
+        self.state.select(Some(new as usize));
+    }
+
+    /// Markdown report of what's listed (the filter applies).
+    pub fn report(&self) -> String {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| String::from("(absent)"));
+        let mut out = format!("# {} → {}\n", self.left, self.right);
+        if !self.filter.is_empty() {
+            out.push_str(&format!("\nOnly changes to `{}`.\n", self.filter));
+        }
+        let changed: Vec<_> = self
+            .diff
+            .changed
+            .iter()
+            .map(|c| (c, c.fields.iter().filter(|f| self.matches(f)).collect::<Vec<_>>()))
+            .filter(|(_, fields)| !fields.is_empty())
+            .collect();
+        out.push_str(&format!("\n## Changed ({})\n", changed.len()));
+        for (change, fields) in changed {
+            out.push_str(&format!("\n### {}\n\n| field | {} | {} |\n|---|---|---|\n", change.name, self.left, self.right));
+            for field in fields {
+                out.push_str(&format!("| {} | {} | {} |\n", field.key.label(), show(&field.old), show(&field.new)));
+            }
+        }
+        if self.filter.is_empty() {
+            for (title, names) in [
+                (format!("Only in {}", self.right), &self.diff.added),
+                (format!("Only in {}", self.left), &self.diff.removed),
+            ] {
+                out.push_str(&format!("\n## {title} ({})\n\n", names.len()));
+                for name in names {
+                    out.push_str(&format!("- {name}\n"));
+                }
+            }
+        }
+        out
+    }
+
+    fn export(&mut self) {
+        let stem = |label: &str| {
+            label
+                .trim_start_matches("ssh:")
+                .trim_end_matches(".xml")
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+                .collect::<String>()
+        };
+        let path = self
+            .export_dir
+            .join(format!("compare-{}-{}.md", stem(&self.left), stem(&self.right)));
+        self.status = match fs::write(&path, self.report()) {
+            Ok(()) => format!("Report written to {}", path.display()),
+            Err(err) => format!("Export failed: {err}"),
+        };
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, show_help: bool) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(5), Constraint::Length(3)].as_ref())
+            .split(f.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(chunks[0]);
+
+        let style = |kind: RowKind| match kind {
+            RowKind::Changed => Style::default().fg(Color::Yellow),
+            RowKind::Field => Style::default(),
+            RowKind::Added => Style::default().fg(Color::Green),
+            RowKind::Removed => Style::default().fg(Color::Red),
+        };
+        let highlight = Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        let left: Vec<ListItem> = self.rows.iter().map(|r| ListItem::new(r.left.clone()).style(style(r.kind))).collect();
+        let right: Vec<ListItem> = self.rows.iter().map(|r| ListItem::new(r.right.clone()).style(style(r.kind))).collect();
+        let left = List::new(left)
+            .block(Block::default().title(format!("A: {}", self.left)).borders(Borders::ALL))
+            .highlight_style(highlight);
+        let right = List::new(right)
+            .block(Block::default().title(format!("B: {}", self.right)).borders(Borders::ALL))
+            .highlight_style(highlight);
+        // Both columns share one selection so they scroll together.
+        let mut right_state = self.state.clone();
+        f.render_stateful_widget(left, columns[0], &mut self.state);
+        f.render_stateful_widget(right, columns[1], &mut right_state);
+
+        let filter = if self.editing_filter {
+            format!("Field filter: {} (Enter: apply, Esc: clear)", line_edit::view(&self.filter, self.filter_cursor, usize::MAX))
+        } else if self.filter.is_empty() {
+            String::from("f: filter by field | e: export Markdown | Esc/q: back")
+        } else {
+            format!("Field filter: {} (f: change, Esc: clear) | e: export Markdown | q: back", self.filter)
+        };
+        let footer = Paragraph::new(format!("{filter} | {}", self.status))
+            .block(Block::default().borders(Borders::ALL).title("Compare"))
+            .wrap(Wrap { trim: true });
+        f.render_widget(footer, chunks[1]);
+
+        if show_help {
+            let area = utils::centered_rect(60, 50, f.size());
+            let text = "Compare Help\n\n- Up/Down, PgUp/PgDown: scroll\n- f: show only changes to one element (e.g. nominal, or flags @deloot)\n- e: write the listed differences as a Markdown report to the start directory\n- Esc: clear the filter, or go back to the file picker\n- q: back to the file picker\n- ?: toggle this help";
+            let help = Paragraph::new(text)
+                .block(Block::default().title("Help").borders(Borders::ALL))
+                .wrap(Wrap { trim: true });
+            f.render_widget(Clear, area);
+            f.render_widget(help, area);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::SAMPLE;
+
+    fn view() -> CompareView {
+        let (left, _) = parse_types(SAMPLE).unwrap();
+        let mut right = left.clone();
+        right[0].fields[0].value = String::from("10");
+        right.remove(1);
+        let mut pear = right[1].clone();
+        pear.name = String::from("Pear");
+        right.push(pear);
+        CompareView::new(String::from("a.xml"), &left, String::from("b.xml"), &right, PathBuf::new())
+    }
+
+    #[test]
+    fn changed_added_and_removed_types_are_listed_per_side() {
+        let view = view();
+        assert_eq!(view.status, "1 changed, 1 only in b.xml, 1 only in a.xml");
+        let rows: Vec<_> = view.rows.iter().map(|r| (r.left.as_str(), r.right.as_str())).collect();
+        assert_eq!(
+            rows,
+            [
+                ("~ AKM", "~ AKM"),
+                ("    nominal: 8", "    nominal: 10"),
+                ("", "+ Pear"),
+                ("- Apple", ""),
+            ]
+        );
+        let report = view.report();
+        assert!(report.contains("## Changed (1)\n\n### AKM\n\n| field | a.xml | b.xml |\n|---|---|---|\n| nominal | 8 | 10 |\n"), "{report}");
+        assert!(report.contains("## Only in b.xml (1)\n\n- Pear\n"), "{report}");
+        assert!(report.contains("## Only in a.xml (1)\n\n- Apple\n"), "{report}");
+    }
+
+    #[test]
+    fn a_field_filter_keeps_only_that_fields_changes() {
+        let mut view = view();
+        view.filter = String::from("lifetime");
+        view.rebuild_rows();
+        assert!(view.rows.is_empty());
+        view.filter = String::from("NOMINAL");
+        view.rebuild_rows();
+        assert_eq!(view.rows.len(), 2);
+        assert!(!view.report().contains("Pear"));
+
+        let (same, _) = parse_types(SAMPLE).unwrap();
+        let unchanged = CompareView::new(String::from("a.xml"), &same, String::from("b.xml"), &same, PathBuf::new());
+        assert_eq!(unchanged.status, "No differences");
+        assert!(unchanged.rows.is_empty());
+    }
+}
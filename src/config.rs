@@ -0,0 +1,968 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{self, Field, FieldKey, LineEnding, TypeEntry},
+    paths::AppDirs,
+    remote::AuthMethod,
+};
+
+/// Per-mission overrides, looked up next to the opened types file.
+pub const PROJECT_FILE: &str = ".dayzlooteditor.toml";
+
+/// One source of settings as written in a TOML file; `None` means "not set here".
+/// Serializing writes only the keys that are set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_file_warning_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_keep: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readonly: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints_baseline: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classnames: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missions_root: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instant_quit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_hidden: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve_symlinks: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_value_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch_file: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch_remote: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vim_keys: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_missing_flags: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remember_bulk: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_changelog: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_changelog_keep: Option<usize>,
+    /// Named lifetimes in seconds for `:lifetime`, merged per name; 0 drops a preset.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub lifetime_presets: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "SaveLayer::is_empty")]
+    pub save: SaveLayer,
+    #[serde(skip_serializing_if = "ValidationLayer::is_empty")]
+    pub validation: ValidationLayer,
+    #[serde(skip_serializing_if = "SshLayer::is_empty")]
+    pub ssh: SshLayer,
+    #[serde(skip_serializing_if = "NewTypeLayer::is_empty")]
+    pub new_type: NewTypeLayer,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SaveLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_order: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_report: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize_names: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_endings: Option<LineEndings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_backup: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shrink_guard_percent: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empty_guard: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_free_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clean_start: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run_remote: Option<bool>,
+}
+
+impl SaveLayer {
+    fn is_empty(&self) -> bool {
+        self.canonical_order.is_none()
+            && self.change_report.is_none()
+            && self.normalize_names.is_none()
+            && self.line_endings.is_none()
+            && self.require_backup.is_none()
+            && self.shrink_guard_percent.is_none()
+            && self.empty_guard.is_none()
+            && self.min_free_mb.is_none()
+            && self.clean_start.is_none()
+            && self.dry_run_remote.is_none()
+    }
+}
+
+/// `[save] line_endings`: keep what the file uses, or force one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    #[default]
+    Auto,
+    Lf,
+    Crlf,
+}
+
+impl LineEndings {
+    /// The ending a save writes for a file that was read with `detected`.
+    pub fn resolve(self, detected: LineEnding) -> LineEnding {
+        match self {
+            Self::Auto => detected,
+            Self::Lf => LineEnding::Lf,
+            Self::Crlf => LineEnding::Crlf,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ValidationLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_flags: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_cost: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_cost: Option<i64>,
+}
+
+impl ValidationLayer {
+    fn is_empty(&self) -> bool {
+        self.strict.is_none() && self.require_flags.is_none() && self.check_cost.is_none() && self.expected_cost.is_none()
+    }
+}
+
+/// `[ssh]`: the profile the SSH connect form starts from when the `SSH_*` variables
+/// aren't set. Passwords and passphrases are never stored here.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SshLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<PathBuf>,
+    /// `agent`, `key` or `password`; unset, the key when there is one, else the agent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthMethod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readonly: Option<bool>,
+    /// The server's missions directory, which `M` in the picker jumps to while connected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missions_root: Option<PathBuf>,
+}
+
+impl SshLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[new_type]`: the fields of types added with `a`, on top of the built-in skeleton.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NewTypeLayer {
+    /// Built-in fields to leave out, by label (`cost`, `category@name`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omit: Option<Vec<String>>,
+    /// Values keyed by field label (`lifetime`, `flags@deloot`); labels the skeleton
+    /// lacks add the field.
+    #[serde(flatten)]
+    pub values: BTreeMap<String, String>,
+}
+
+impl NewTypeLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The layer that makes `ty`'s fields the skeleton, and how many fields it can't hold
+    /// (second and later `usage`s and the like; labels name the first element only).
+    pub fn from_type(ty: &TypeEntry) -> (Self, usize) {
+        let mut values = BTreeMap::new();
+        let mut skipped = 0;
+        for field in &ty.fields {
+            match &field.key {
+                FieldKey::Element { name, index: 0 } => values.insert(name.clone(), field.value.clone()),
+                FieldKey::Attribute { element, index: 0, attr } => values.insert(format!("{element}@{attr}"), field.value.clone()),
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+        }
+        let omit = model::default_fields()
+            .into_iter()
+            .filter(|builtin| !ty.fields.iter().any(|f| f.key == builtin.key))
+            .map(|f| f.key.label().replace(' ', ""))
+            .collect();
+        (Self { omit: Some(omit), values }, skipped)
+    }
+
+    /// Labels `model::parse_field_label` can't read, for the unknown-keys warning.
+    fn bad_labels(section: &toml::Table) -> Vec<String> {
+        let omitted = section.get("omit").and_then(|v| v.as_array()).into_iter().flatten().filter_map(|v| v.as_str());
+        let labels = section.keys().map(String::as_str).filter(|&k| k != "omit");
+        labels.chain(omitted).filter(|l| model::parse_field_label(l).is_none()).map(|l| format!("new_type.{l}")).collect()
+    }
+}
+
+/// The built-in skeleton without the omitted fields and with the configured values,
+/// fields it lacks added in the vanilla element order.
+fn new_type_skeleton(values: &BTreeMap<String, String>, omit: &[String]) -> Vec<Field> {
+    let omitted: Vec<FieldKey> = omit.iter().filter_map(|label| model::parse_field_label(label)).collect();
+    let mut fields: Vec<Field> = model::default_fields().into_iter().filter(|f| !omitted.contains(&f.key)).collect();
+    for (label, value) in values {
+        let Some(key) = model::parse_field_label(label).filter(|key| !omitted.contains(key)) else {
+            continue;
+        };
+        match fields.iter_mut().find(|f| f.key == key) {
+            Some(field) => field.value = value.clone(),
+            None => {
+                let rank = model::canonical_rank(key.get_element_name());
+                let at = fields
+                    .iter()
+                    .rposition(|f| model::canonical_rank(f.key.get_element_name()) <= rank)
+                    .map_or(0, |i| i + 1);
+                fields.insert(at, Field { key, value: value.clone() });
+            }
+        }
+    }
+    fields
+}
+
+/// The shipped lifetime presets.
+const LIFETIME_PRESETS: [(&str, u64); 5] =
+    [("45min", 2700), ("4h", 14400), ("1day", 86400), ("1week", 604800), ("persistent", 3888000)];
+
+/// The shipped presets with `configured` over them, without those set to 0, shortest first.
+fn lifetime_presets(configured: &BTreeMap<String, u64>) -> Vec<(String, u64)> {
+    let mut presets: BTreeMap<String, u64> = LIFETIME_PRESETS.iter().map(|&(name, seconds)| (name.to_string(), seconds)).collect();
+    presets.extend(configured.iter().map(|(name, &seconds)| (name.clone(), seconds)));
+    let mut presets: Vec<(String, u64)> = presets.into_iter().filter(|&(_, seconds)| seconds > 0).collect();
+    presets.sort_by_key(|&(_, seconds)| seconds);
+    presets
+}
+
+/// `theme`: `default` colours, or `mono` for terminals (and eyes) where colour doesn't work;
+/// mono keeps bold and reverse video.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Default,
+    Mono,
+}
+
+/// Why the environment asks for no colour, if it does: `NO_COLOR` set to anything but the
+/// empty string (no-color.org), `TERM=dumb`, or a terminal reporting fewer than 8 colours.
+pub fn no_colour_reason(no_color: Option<&str>, term: Option<&str>, colours: u16) -> Option<&'static str> {
+    if no_color.is_some_and(|v| !v.is_empty()) {
+        Some("NO_COLOR is set")
+    } else if term == Some("dumb") {
+        Some("TERM=dumb")
+    } else if colours < 8 {
+        Some("the terminal reports fewer than 8 colours")
+    } else {
+        None
+    }
+}
+
+impl Theme {
+    /// `mono` when this process's environment asks for no colour (`no_colour_reason`).
+    /// Used when neither `--no-color` nor a config file picks a theme.
+    pub fn detect() -> Option<Theme> {
+        let no_color = std::env::var("NO_COLOR").ok();
+        let term = std::env::var("TERM").ok();
+        let colours = crossterm::style::available_color_count();
+        no_colour_reason(no_color.as_deref(), term.as_deref(), colours).map(|_| Theme::Mono)
+    }
+}
+
+/// Keys `ConfigLayer` understands; `new_type` takes field labels, checked on their own.
+const KNOWN_KEYS: [&str; 25] = [
+    "large_file_warning_mb",
+    "backup_keep",
+    "readonly",
+    "hints_baseline",
+    "classnames",
+    "default_dir",
+    "missions_root",
+    "instant_quit",
+    "show_hidden",
+    "resolve_symlinks",
+    "max_value_length",
+    "watch_file",
+    "watch_remote",
+    "author",
+    "theme",
+    "vim_keys",
+    "create_missing_flags",
+    "remember_bulk",
+    "inline_changelog",
+    "inline_changelog_keep",
+    "lifetime_presets",
+    "save",
+    "validation",
+    "ssh",
+    "new_type",
+];
+const KNOWN_SAVE_KEYS: [&str; 10] = [
+    "canonical_order",
+    "change_report",
+    "normalize_names",
+    "line_endings",
+    "require_backup",
+    "shrink_guard_percent",
+    "empty_guard",
+    "min_free_mb",
+    "clean_start",
+    "dry_run_remote",
+];
+const KNOWN_VALIDATION_KEYS: [&str; 4] = ["strict", "require_flags", "check_cost", "expected_cost"];
+const KNOWN_SSH_KEYS: [&str; 7] = ["host", "user", "port", "key_path", "auth", "readonly", "missions_root"];
+
+/// Effective settings after merging every layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Files larger than this ask for confirmation before they are read and parsed.
+    pub large_file_warning_mb: u64,
+    /// Backups kept per file on save: `.bak`, `.bak.1`, ...; 0 disables them.
+    pub backup_keep: usize,
+    /// Refuse every edit and save until read-only is switched off in the session.
+    pub readonly: bool,
+    /// types.xml whose value ranges the Tips pane compares against instead of vanilla Chernarus.
+    pub hints_baseline: Option<PathBuf>,
+    /// The server's classnames, one per line: `:fixcase` spells type names as it does, and
+    /// adding or renaming a type completes and checks names against it.
+    pub classnames: Option<PathBuf>,
+    /// Where the file picker starts instead of the directory the editor was launched in.
+    pub default_dir: Option<PathBuf>,
+    /// The local server's missions directory, which `M` in the picker jumps to.
+    pub missions_root: Option<PathBuf>,
+    /// `q` in the editor quits the app at once instead of going back to the file picker.
+    pub instant_quit: bool,
+    /// The file picker lists dot files and directories (toggled with `H`).
+    pub show_hidden: bool,
+    /// A file opened through a symlink in the picker opens under the path the links point
+    /// to; off, under the path as browsed.
+    pub resolve_symlinks: bool,
+    /// Typing or pasting stops at this many characters per value; loaded values aren't cut.
+    pub max_value_length: usize,
+    /// Check the open local file every few seconds and say when it changed on disk.
+    pub watch_file: bool,
+    /// Watch remote files too, through the periodic SFTP stat; off by default.
+    pub watch_remote: bool,
+    /// Set to record who changed each field in a `.notes.toml` next to the file.
+    pub author: Option<String>,
+    pub theme: Theme,
+    /// `h`/`j`/`k`/`l` move like the arrow keys; off, they are plain keys.
+    pub vim_keys: bool,
+    /// Setting a flag on a type without `<flags>` (`:flags`, `:apply`) first gives it the
+    /// default six; off, such types are skipped.
+    pub create_missing_flags: bool,
+    /// Keep the bulk commands' last arguments in the session file, so a bare `:addusage`
+    /// or `:again` picks them up after a restart too.
+    pub remember_bulk: bool,
+    /// Each save that changed something adds a one-line summary as a comment before `</types>`.
+    pub inline_changelog: bool,
+    /// Inline changelog comments kept in the file; older ones are dropped on save.
+    pub inline_changelog_keep: usize,
+    /// `:lifetime` presets and lifetime edit suggestions, shortest first.
+    pub lifetime_presets: Vec<(String, u64)>,
+    /// Write each type's elements in vanilla order (nominal, lifetime, ... usage, value).
+    pub canonical_order: bool,
+    /// Write a Markdown change report next to the file on every save that changed something.
+    pub change_report: bool,
+    /// Read text-form `<category>weapons</category>` (and usage/tag) as `name` attributes,
+    /// so saves write `<category name="weapons"/>`.
+    pub normalize_names: bool,
+    /// Line endings of saved files; `Auto` keeps the ones the file was read with.
+    pub line_endings: LineEndings,
+    /// Abort a save whose `.bak` can't be written instead of saving without one.
+    pub require_backup: bool,
+    /// Ask before a save that leaves the file this many percent smaller than it was; 0 never asks.
+    pub shrink_guard_percent: u8,
+    /// Ask before a save that leaves no types in a file that had some.
+    pub empty_guard: bool,
+    /// Ask before a save when the target's filesystem has less than this many MiB free.
+    pub min_free_mb: u64,
+    /// Saves drop a BOM and whitespace before the XML declaration; off, they are written back.
+    pub clean_start: bool,
+    /// Rehearse every save over SSH as `:dryrun` does and abort it when the rehearsal
+    /// reads back something other than the editor holds.
+    pub dry_run_remote: bool,
+    /// Also report elements the editor doesn't know about.
+    pub strict_validation: bool,
+    /// Report types without `<flags>` and offer to add the defaults before saving.
+    pub require_flags: bool,
+    /// Report types whose cost isn't `expected_cost` and mark them in the Types pane.
+    pub check_cost: bool,
+    /// The cost nearly every type uses; `:cost` sets it.
+    pub expected_cost: i64,
+    /// Fields of types added with `a`.
+    pub new_type: Vec<Field>,
+    /// The `[ssh]` profile, merged per key.
+    pub ssh: SshLayer,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            large_file_warning_mb: 20,
+            backup_keep: 1,
+            readonly: false,
+            hints_baseline: None,
+            classnames: None,
+            default_dir: None,
+            missions_root: None,
+            instant_quit: false,
+            show_hidden: false,
+            resolve_symlinks: true,
+            max_value_length: 1024,
+            watch_file: true,
+            watch_remote: false,
+            author: None,
+            theme: Theme::Default,
+            vim_keys: true,
+            create_missing_flags: true,
+            remember_bulk: false,
+            inline_changelog: false,
+            inline_changelog_keep: 20,
+            lifetime_presets: lifetime_presets(&BTreeMap::new()),
+            canonical_order: false,
+            change_report: false,
+            normalize_names: true,
+            line_endings: LineEndings::Auto,
+            require_backup: false,
+            shrink_guard_percent: 90,
+            empty_guard: true,
+            min_free_mb: 100,
+            clean_start: true,
+            dry_run_remote: false,
+            strict_validation: false,
+            require_flags: true,
+            check_cost: true,
+            expected_cost: 100,
+            new_type: model::default_fields(),
+            ssh: SshLayer::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Merges the layers. Precedence, highest first: command line, project file next to
+    /// the opened file, user config, built-in defaults. `new_type` values merge per key.
+    pub fn resolve(user: &ConfigLayer, project: Option<&ConfigLayer>, cli: &ConfigLayer) -> Self {
+        let mut config = Self::default();
+        let mut new_type_values = BTreeMap::new();
+        let mut presets = BTreeMap::new();
+        let mut omit = Vec::new();
+        for layer in [Some(user), project, Some(cli)].into_iter().flatten() {
+            if let Some(mb) = layer.large_file_warning_mb {
+                config.large_file_warning_mb = mb;
+            }
+            if let Some(keep) = layer.backup_keep {
+                config.backup_keep = keep;
+            }
+            if let Some(readonly) = layer.readonly {
+                config.readonly = readonly;
+            }
+            if let Some(path) = &layer.hints_baseline {
+                config.hints_baseline = Some(path.clone());
+            }
+            if let Some(path) = &layer.classnames {
+                config.classnames = Some(path.clone());
+            }
+            if let Some(path) = &layer.default_dir {
+                config.default_dir = Some(path.clone());
+            }
+            if let Some(path) = &layer.missions_root {
+                config.missions_root = Some(path.clone());
+            }
+            if let Some(instant) = layer.instant_quit {
+                config.instant_quit = instant;
+            }
+            if let Some(show) = layer.show_hidden {
+                config.show_hidden = show;
+            }
+            if let Some(resolve) = layer.resolve_symlinks {
+                config.resolve_symlinks = resolve;
+            }
+            if let Some(max) = layer.max_value_length {
+                config.max_value_length = max;
+            }
+            if let Some(watch) = layer.watch_file {
+                config.watch_file = watch;
+            }
+            if let Some(watch) = layer.watch_remote {
+                config.watch_remote = watch;
+            }
+            if let Some(author) = &layer.author {
+                config.author = Some(author.clone()).filter(|a| !a.trim().is_empty());
+            }
+            if let Some(theme) = layer.theme {
+                config.theme = theme;
+            }
+            if let Some(vim) = layer.vim_keys {
+                config.vim_keys = vim;
+            }
+            if let Some(create) = layer.create_missing_flags {
+                config.create_missing_flags = create;
+            }
+            if let Some(remember) = layer.remember_bulk {
+                config.remember_bulk = remember;
+            }
+            if let Some(inline) = layer.inline_changelog {
+                config.inline_changelog = inline;
+            }
+            if let Some(keep) = layer.inline_changelog_keep {
+                config.inline_changelog_keep = keep;
+            }
+            if let Some(canonical) = layer.save.canonical_order {
+                config.canonical_order = canonical;
+            }
+            if let Some(report) = layer.save.change_report {
+                config.change_report = report;
+            }
+            if let Some(normalize) = layer.save.normalize_names {
+                config.normalize_names = normalize;
+            }
+            if let Some(endings) = layer.save.line_endings {
+                config.line_endings = endings;
+            }
+            if let Some(require) = layer.save.require_backup {
+                config.require_backup = require;
+            }
+            if let Some(percent) = layer.save.shrink_guard_percent {
+                config.shrink_guard_percent = percent.min(100);
+            }
+            if let Some(guard) = layer.save.empty_guard {
+                config.empty_guard = guard;
+            }
+            if let Some(mb) = layer.save.min_free_mb {
+                config.min_free_mb = mb;
+            }
+            if let Some(clean) = layer.save.clean_start {
+                config.clean_start = clean;
+            }
+            if let Some(dry_run) = layer.save.dry_run_remote {
+                config.dry_run_remote = dry_run;
+            }
+            if let Some(strict) = layer.validation.strict {
+                config.strict_validation = strict;
+            }
+            if let Some(require) = layer.validation.require_flags {
+                config.require_flags = require;
+            }
+            if let Some(check) = layer.validation.check_cost {
+                config.check_cost = check;
+            }
+            if let Some(cost) = layer.validation.expected_cost {
+                config.expected_cost = cost;
+            }
+            presets.extend(layer.lifetime_presets.iter().map(|(name, &seconds)| (name.clone(), seconds)));
+            // `flags @deloot` and `flags@deloot` are the same key.
+            new_type_values.extend(layer.new_type.values.iter().map(|(k, v)| (k.replace(' ', ""), v.clone())));
+            if let Some(labels) = &layer.new_type.omit {
+                omit = labels.clone();
+            }
+            let ssh = &layer.ssh;
+            config.ssh.host = ssh.host.clone().or(config.ssh.host.take());
+            config.ssh.user = ssh.user.clone().or(config.ssh.user.take());
+            config.ssh.port = ssh.port.or(config.ssh.port);
+            config.ssh.key_path = ssh.key_path.clone().or(config.ssh.key_path.take());
+            config.ssh.auth = ssh.auth.or(config.ssh.auth);
+            config.ssh.readonly = ssh.readonly.or(config.ssh.readonly);
+            config.ssh.missions_root = ssh.missions_root.clone().or(config.ssh.missions_root.take());
+        }
+        config.new_type = new_type_skeleton(&new_type_values, &omit);
+        config.lifetime_presets = lifetime_presets(&presets);
+        config
+    }
+
+    pub fn large_file_warning_bytes(&self) -> u64 {
+        self.large_file_warning_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+impl ConfigLayer {
+    pub fn user_path() -> Option<PathBuf> {
+        AppDirs::locate().config_file()
+    }
+
+    /// Loads the user config. A missing file yields an empty layer; a broken one yields
+    /// an empty layer plus a message for the status bar.
+    pub fn load_user() -> (Self, Option<String>) {
+        let Some(path) = Self::user_path() else {
+            return (Self::default(), None);
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => match Self::parse(&content) {
+                Ok((layer, unknown)) if unknown.is_empty() => (layer, None),
+                Ok((layer, unknown)) => (
+                    layer,
+                    Some(format!("{}: ignoring unknown keys {}", path.display(), unknown.join(", "))),
+                ),
+                Err(err) => (Self::default(), Some(format!("Ignoring {}: {}", path.display(), err))),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (Self::default(), None),
+            Err(err) => (
+                Self::default(),
+                Some(format!("Could not read {}: {}", path.display(), err)),
+            ),
+        }
+    }
+
+    /// Reads the project config found next to a file, if any. Returns the layer, unless it
+    /// doesn't parse, and a note saying which of the two happened.
+    pub fn load_project(content: Option<&str>) -> (Option<Self>, Option<String>) {
+        match content.map(Self::parse) {
+            Some(Ok((layer, unknown))) if unknown.is_empty() => (Some(layer), Some(String::from("project config loaded"))),
+            Some(Ok((layer, unknown))) => {
+                (Some(layer), Some(format!("project config loaded; unknown keys ignored: {}", unknown.join(", "))))
+            }
+            Some(Err(err)) => (None, Some(format!("{PROJECT_FILE} ignored: {err}"))),
+            None => (None, None),
+        }
+    }
+
+    /// Parses one TOML file, returning the layer and any keys it didn't recognise.
+    pub fn parse(content: &str) -> Result<(Self, Vec<String>), String> {
+        let table: toml::Table = toml::from_str(content).map_err(|e| e.message().to_string())?;
+        let mut unknown = Vec::new();
+        for (key, value) in &table {
+            let nested: Option<&[&str]> = match key.as_str() {
+                "save" => Some(&KNOWN_SAVE_KEYS),
+                "validation" => Some(&KNOWN_VALIDATION_KEYS),
+                "ssh" => Some(&KNOWN_SSH_KEYS),
+                _ => None,
+            };
+            if !KNOWN_KEYS.contains(&key.as_str()) {
+                unknown.push(key.clone());
+            } else if let ("new_type", Some(section)) = (key.as_str(), value.as_table()) {
+                unknown.extend(NewTypeLayer::bad_labels(section));
+            } else if let (Some(known), Some(section)) = (nested, value.as_table()) {
+                unknown.extend(
+                    section
+                        .keys()
+                        .filter(|k| !known.contains(&k.as_str()))
+                        .map(|k| format!("{key}.{k}")),
+                );
+            }
+        }
+        let layer = table.try_into().map_err(|e: toml::de::Error| e.message().to_string())?;
+        Ok((layer, unknown))
+    }
+
+    /// The layer as a config file, keys that aren't set left out.
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Writes the layer to `path`. An existing file is edited in place (see `edit_toml`);
+    /// a new one starts with `CONFIG_HEADER`.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = match fs::read_to_string(path) {
+            Ok(existing) => self.edit_toml(&existing),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => self.to_toml().map(|content| format!("{CONFIG_HEADER}{content}")),
+            Err(err) => return Err(err),
+        };
+        fs::write(path, content.map_err(io::Error::other)?)
+    }
+
+    /// `existing` with the keys whose value differs from this layer's rewritten, added or
+    /// removed; everything else, comments and unknown keys included, stays as it was.
+    pub fn edit_toml(&self, existing: &str) -> Result<String, String> {
+        let mut document: toml_edit::DocumentMut = existing.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+        let as_table = |layer: &Self| layer.to_toml().and_then(|c| toml::from_str::<toml::Table>(&c).map_err(|e| e.to_string()));
+        let before = as_table(&Self::parse(existing)?.0)?;
+        let after = as_table(self)?;
+        edit_table(document.as_table_mut(), &before, &after);
+        Ok(document.to_string())
+    }
+}
+
+/// Brings the keys of `before` (what `table` said) to what `after` says, leaving the keys
+/// neither has alone. A section left empty is removed.
+fn edit_table(table: &mut dyn toml_edit::TableLike, before: &toml::Table, after: &toml::Table) {
+    let empty = toml::Table::new();
+    for (key, old) in before {
+        let new = after.get(key);
+        if new == Some(old) {
+            continue;
+        }
+        match (old, new, table.get_mut(key).and_then(|item| item.as_table_like_mut())) {
+            (toml::Value::Table(old), None | Some(toml::Value::Table(_)), Some(section)) => {
+                let new = match new {
+                    Some(toml::Value::Table(new)) => new,
+                    _ => &empty,
+                };
+                edit_table(section, old, new);
+                if section.is_empty() {
+                    table.remove(key);
+                }
+            }
+            _ => match new {
+                Some(new) => set_item(table, key, new),
+                None => {
+                    table.remove(key);
+                }
+            },
+        }
+    }
+    for (key, new) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+        match (new, table.get_mut(key).and_then(|item| item.as_table_like_mut())) {
+            // A section that so far held only unknown keys.
+            (toml::Value::Table(new), Some(section)) => edit_table(section, &empty, new),
+            _ => set_item(table, key, new),
+        }
+    }
+}
+
+/// Sets `key` to `value`, keeping the comments around the old value.
+fn set_item(table: &mut dyn toml_edit::TableLike, key: &str, value: &toml::Value) {
+    let mut item = toml_item(value);
+    let Some(slot) = table.get_mut(key) else {
+        table.insert(key, item);
+        return;
+    };
+    if let (Some(old), Some(new)) = (slot.as_value(), item.as_value_mut()) {
+        *new.decor_mut() = old.decor().clone();
+    }
+    *slot = item;
+}
+
+/// A value as a `toml_edit` item; tables become sections at the end of the file.
+fn toml_item(value: &toml::Value) -> toml_edit::Item {
+    match value {
+        toml::Value::Table(entries) => {
+            let mut section = toml_edit::Table::new();
+            for (key, value) in entries {
+                section.insert(key, toml_item(value));
+            }
+            toml_edit::Item::Table(section)
+        }
+        value => value.to_string().parse::<toml_edit::Value>().map(toml_edit::Item::Value).unwrap_or_default(),
+    }
+}
+
+/// First lines of a config file the editor writes itself.
+const CONFIG_HEADER: &str = "# dayz-loot-editor settings, written by :setup. The README lists every key.\n";
+
+/// Remembered between runs; kept apart from the hand-written config so saving it
+/// never rewrites the user's file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    /// Widths of the Types, Fields and Tips panes in percent; Tips 0 means collapsed.
+    pub panes: Option<[u16; 3]>,
+    /// The bulk commands' last arguments by command (`addusage` → `Military Ammo*`),
+    /// kept only with `remember_bulk`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub bulk: BTreeMap<String, String>,
+    /// The bulk command `:again` repeats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_bulk: Option<String>,
+}
+
+impl Session {
+    pub fn path() -> Option<PathBuf> {
+        AppDirs::locate().session_file()
+    }
+
+    /// A missing or unreadable session file is the same as a fresh start.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| io::Error::other("no config directory (HOME is unset)"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(content: &str) -> ConfigLayer {
+        ConfigLayer::parse(content).unwrap().0
+    }
+
+    #[test]
+    fn no_color_and_dumb_terminals_ask_for_mono() {
+        assert_eq!(no_colour_reason(Some("1"), Some("xterm-256color"), 256), Some("NO_COLOR is set"));
+        assert_eq!(no_colour_reason(Some(""), Some("xterm"), 8), None, "an empty NO_COLOR doesn't count");
+        assert_eq!(no_colour_reason(None, Some("dumb"), 8), Some("TERM=dumb"));
+        assert!(no_colour_reason(None, Some("vt100"), 2).is_some());
+        assert_eq!(no_colour_reason(None, None, 8), None, "Windows consoles leave TERM unset");
+    }
+
+    #[test]
+    fn empty_layers_give_defaults() {
+        let empty = ConfigLayer::default();
+        assert_eq!(Config::resolve(&empty, None, &empty), Config::default());
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let user = layer("large_file_warning_mb = 5\nbackup_keep = 3\n[save]\ncanonical_order = true\n");
+        let project = layer("backup_keep = 0\n[validation]\nstrict = true\n");
+        let cli = layer("[save]\ncanonical_order = false\n");
+
+        let user_only = Config::resolve(&user, None, &ConfigLayer::default());
+        assert_eq!(user_only.large_file_warning_mb, 5);
+        assert_eq!(user_only.backup_keep, 3);
+        assert!(user_only.canonical_order);
+        assert!(!user_only.strict_validation);
+
+        let merged = Config::resolve(&user, Some(&project), &cli);
+        assert_eq!(merged.large_file_warning_mb, 5, "unset in project keeps the user value");
+        assert_eq!(merged.backup_keep, 0, "project beats user");
+        assert!(merged.strict_validation);
+        assert!(!merged.canonical_order, "command line beats user");
+    }
+
+    #[test]
+    fn cli_readonly_beats_files() {
+        let user = layer("readonly = false\n");
+        let project = layer("readonly = false\n");
+        let cli = crate::cli::Cli::parse([String::from("--readonly")]).unwrap().config_layer();
+        assert!(Config::resolve(&user, Some(&project), &cli).readonly);
+        assert!(!Config::resolve(&user, Some(&project), &ConfigLayer::default()).readonly);
+    }
+
+    fn skeleton_value<'a>(config: &'a Config, label: &str) -> Option<&'a str> {
+        config.new_type.iter().find(|f| f.key.label() == label).map(|f| f.value.as_str())
+    }
+
+    #[test]
+    fn new_type_merges_per_key() {
+        let user = layer("[new_type]\nlifetime = \"3600\"\nnominal = \"5\"\n");
+        let project = layer("[new_type]\nnominal = \"10\"\n");
+        let merged = Config::resolve(&user, Some(&project), &ConfigLayer::default());
+        assert_eq!(skeleton_value(&merged, "lifetime"), Some("3600"));
+        assert_eq!(skeleton_value(&merged, "nominal"), Some("10"));
+    }
+
+    #[test]
+    fn lifetime_presets_merge_per_name_and_sort_by_length() {
+        let user = layer("[lifetime_presets]\n2h = 7200\n45min = 0\n");
+        let project = layer("[lifetime_presets]\n2h = 9000\n");
+        let merged = Config::resolve(&user, Some(&project), &ConfigLayer::default());
+        let names: Vec<&str> = merged.lifetime_presets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["2h", "4h", "1day", "1week", "persistent"]);
+        assert_eq!(merged.lifetime_presets[0], (String::from("2h"), 9000));
+    }
+
+    #[test]
+    fn new_type_skeleton_drops_and_adds_fields_in_element_order() {
+        let (user, unknown) = ConfigLayer::parse(
+            "[new_type]\nomit = [\"cost\", \"category@name\", \"bad label\"]\n\"usage@name\" = \"Military\"\n\"flags @custom\" = \"1\"\n\"a<b\" = \"x\"\n",
+        )
+        .unwrap();
+        assert_eq!(unknown, vec![String::from("new_type.a<b"), String::from("new_type.bad label")]);
+        let config = Config::resolve(&user, None, &ConfigLayer::default());
+        let labels: Vec<String> = config.new_type.iter().map(|f| f.key.label()).collect();
+        assert!(!labels.contains(&String::from("cost")) && !labels.contains(&String::from("category @name")));
+        assert_eq!(labels.last().map(String::as_str), Some("usage @name"));
+        let custom = labels.iter().position(|l| l == "flags @custom").unwrap();
+        assert_eq!(labels[custom - 1], "flags @deloot", "{labels:?}");
+
+        let ty = TypeEntry { name: String::from("Template"), fields: config.new_type.clone() };
+        let (from_type, skipped) = NewTypeLayer::from_type(&ty);
+        assert_eq!(skipped, 0);
+        let saved = ConfigLayer { new_type: from_type, ..ConfigLayer::default() };
+        let (read, unknown) = ConfigLayer::parse(&saved.to_toml().unwrap()).unwrap();
+        assert!(unknown.is_empty(), "{unknown:?}");
+        assert_eq!(Config::resolve(&read, None, &ConfigLayer::default()).new_type, config.new_type);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_not_fatal() {
+        let (parsed, unknown) =
+            ConfigLayer::parse("backup_keep = 2\ncolour = \"red\"\n[save]\nindent = 4\n").unwrap();
+        assert_eq!(parsed.backup_keep, Some(2));
+        assert_eq!(unknown, vec![String::from("colour"), String::from("save.indent")]);
+    }
+
+    #[test]
+    fn written_layers_read_back_unchanged() {
+        let (original, _) = ConfigLayer::parse(
+            "backup_keep = 3\ntheme = \"mono\"\n[save]\nline_endings = \"crlf\"\n[ssh]\nhost = \"example.org\"\nport = 2222\nauth = \"agent\"\n",
+        )
+        .unwrap();
+        assert_eq!(Config::resolve(&original, None, &ConfigLayer::default()).ssh.auth, Some(AuthMethod::Agent));
+        let written = original.to_toml().unwrap();
+        assert!(!written.contains("[validation]"), "empty sections are left out:\n{written}");
+        let (read, unknown) = ConfigLayer::parse(&written).unwrap();
+        assert!(unknown.is_empty(), "{unknown:?}");
+        let empty = ConfigLayer::default();
+        assert_eq!(Config::resolve(&read, None, &empty), Config::resolve(&original, None, &empty));
+        assert_eq!(ConfigLayer::default().to_toml().unwrap(), "");
+    }
+
+    #[test]
+    fn writing_over_a_config_keeps_its_comments_and_unknown_keys() {
+        let existing = "# mine\nbackup_keep = 3 # plenty\ncolour = \"red\"\n\n[ssh]\nhost = \"old.example.org\"\nport = 2222\nnote = \"keep\"\n\n[save]\n# one line each\nchange_report = true\n";
+        let (mut layer, unknown) = ConfigLayer::parse(existing).unwrap();
+        assert_eq!(unknown, ["colour", "ssh.note"]);
+        layer.backup_keep = Some(5);
+        layer.theme = Some(Theme::Mono);
+        layer.ssh.host = Some(String::from("new.example.org"));
+        layer.ssh.port = None;
+        layer.save = SaveLayer::default();
+        layer.lifetime_presets.insert(String::from("long"), 86400);
+
+        let written = layer.edit_toml(existing).unwrap();
+        assert_eq!(
+            written,
+            "# mine\nbackup_keep = 5 # plenty\ncolour = \"red\"\ntheme = \"mono\"\n\n[ssh]\nhost = \"new.example.org\"\nnote = \"keep\"\n\n[lifetime_presets]\nlong = 86400\n"
+        );
+        let (read, _) = ConfigLayer::parse(&written).unwrap();
+        let empty = ConfigLayer::default();
+        assert_eq!(Config::resolve(&read, None, &empty), Config::resolve(&layer, None, &empty));
+        assert_eq!(layer.edit_toml(&written).unwrap(), written, "nothing to change");
+    }
+
+    #[test]
+    fn wrong_types_are_errors() {
+        assert!(ConfigLayer::parse("backup_keep = \"many\"\n").is_err());
+        assert!(ConfigLayer::parse("not toml at all [").is_err());
+    }
+}
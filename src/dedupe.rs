@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::model::{FieldKey, TypeEntry};
+
+/// Elements the Central Economy reads once per type; for repeats only the last one counts.
+pub const SINGLETON_ELEMENTS: [&str; 8] = [
+    "nominal", "lifetime", "restock", "min", "quantmin", "quantmax", "cost", "flags",
+];
+/// Elements that may repeat, but an identical repeat is pointless.
+const LIST_ELEMENTS: [&str; 3] = ["usage", "tag", "value"];
+
+/// Redundant copies of one element inside one type.
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    pub type_idx: usize,
+    pub element: String,
+    /// Element indices that would be removed.
+    pub extra_indices: Vec<usize>,
+    pub description: String,
+}
+
+pub fn find_duplicates(types: &[TypeEntry]) -> Vec<Duplicate> {
+    types
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, ty)| find_in_type(idx, ty))
+        .collect()
+}
+
+/// Everything one serialized element carries: sorted attributes plus optional text.
+#[derive(Default, PartialEq, Eq, Hash)]
+struct ElementContent {
+    attrs: Vec<(String, String)>,
+    text: Option<String>,
+}
+
+/// `(element, index)` groups of one type in order of appearance.
+fn element_groups(ty: &TypeEntry) -> Vec<(String, usize, ElementContent)> {
+    let mut groups: Vec<(String, usize, ElementContent)> = Vec::new();
+    for field in &ty.fields {
+        let (element, index) = field.key.element_key();
+        let pos = match groups.iter().position(|(e, i, _)| e == element && *i == index) {
+            Some(pos) => pos,
+            None => {
+                groups.push((element.to_string(), index, ElementContent::default()));
+                groups.len() - 1
+            }
+        };
+        let content = &mut groups[pos].2;
+        match &field.key {
+            FieldKey::Element { .. } => content.text = Some(field.value.clone()),
+            FieldKey::Attribute { attr, .. } => content.attrs.push((attr.clone(), field.value.clone())),
+        }
+    }
+    for (_, _, content) in &mut groups {
+        content.attrs.sort();
+    }
+    groups
+}
+
+pub fn find_in_type(type_idx: usize, ty: &TypeEntry) -> Vec<Duplicate> {
+    let groups = element_groups(ty);
+    let mut by_element: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (element, index, _) in &groups {
+        by_element.entry(element.as_str()).or_default().push(*index);
+    }
+
+    let mut found = Vec::new();
+    for (element, indices) in &by_element {
+        if indices.len() > 1 && SINGLETON_ELEMENTS.contains(element) {
+            found.push(Duplicate {
+                type_idx,
+                element: element.to_string(),
+                extra_indices: indices[..indices.len() - 1].to_vec(),
+                description: format!("{element} appears {} times; only the last one is used", indices.len()),
+            });
+        }
+    }
+
+    let mut seen: HashMap<(&str, &ElementContent), usize> = HashMap::new();
+    let mut repeats: BTreeMap<(&str, usize), Vec<usize>> = BTreeMap::new();
+    for (element, index, content) in &groups {
+        if !LIST_ELEMENTS.contains(&element.as_str()) {
+            continue;
+        }
+        match seen.get(&(element.as_str(), content)) {
+            Some(&first) => repeats.entry((element.as_str(), first)).or_default().push(*index),
+            None => {
+                seen.insert((element.as_str(), content), *index);
+            }
+        }
+    }
+    for ((element, first), extra_indices) in repeats {
+        let shown = groups
+            .iter()
+            .find(|(e, i, _)| e == element && *i == first)
+            .map(|(_, _, content)| {
+                let mut parts: Vec<String> = content.attrs.iter().map(|(k, v)| format!("{k}=\"{v}\"")).collect();
+                parts.extend(content.text.clone());
+                parts.join(" ")
+            })
+            .unwrap_or_default();
+        found.push(Duplicate {
+            type_idx,
+            element: element.to_string(),
+            description: format!("{element} {shown} is repeated {} more time(s)", extra_indices.len()),
+            extra_indices,
+        });
+    }
+    found
+}
+
+/// Removes the extra elements and renumbers what is left so indices stay contiguous.
+pub fn remove_duplicates(types: &mut [TypeEntry], duplicates: &[Duplicate]) {
+    for dup in duplicates {
+        let Some(ty) = types.get_mut(dup.type_idx) else {
+            continue;
+        };
+        ty.fields.retain(|f| {
+            let (element, index) = f.key.element_key();
+            element != dup.element || !dup.extra_indices.contains(&index)
+        });
+    }
+    let mut touched: Vec<usize> = duplicates.iter().map(|d| d.type_idx).collect();
+    touched.dedup();
+    for idx in touched {
+        if let Some(ty) = types.get_mut(idx) {
+            ty.reindex_elements();
+        }
+    }
+}
+
+/// Attribute fields whose name comes again later on the same element, in field order.
+/// XML allows one of each, so only the last copy can be written.
+pub fn repeated_attributes(ty: &TypeEntry) -> Vec<usize> {
+    (0..ty.fields.len())
+        .filter(|&i| {
+            matches!(ty.fields[i].key, FieldKey::Attribute { .. }) && ty.fields[i + 1..].iter().any(|f| f.key == ty.fields[i].key)
+        })
+        .collect()
+}
+
+/// Drops every repeated attribute but its last copy; returns how many fields went.
+pub fn remove_repeated_attributes(ty: &mut TypeEntry) -> usize {
+    let repeated = repeated_attributes(ty);
+    let mut idx = 0;
+    ty.fields.retain(|_| {
+        idx += 1;
+        !repeated.contains(&(idx - 1))
+    });
+    repeated.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_types;
+
+    fn parse(xml: &str) -> TypeEntry {
+        parse_types(&format!("<types>{xml}</types>")).unwrap().0.remove(0)
+    }
+
+    fn elements(ty: &TypeEntry) -> Vec<String> {
+        ty.fields
+            .iter()
+            .map(|f| {
+                let (element, index) = f.key.element_key();
+                format!("{element}[{index}] {}", f.value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repeated_singletons_keep_their_last_copy() {
+        let mut types = vec![parse(
+            r#"<type name="AKM"><nominal>5</nominal><lifetime>7200</lifetime><nominal>9</nominal><min>2</min></type>"#,
+        )];
+        let found = find_duplicates(&types);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].element.as_str(), found[0].extra_indices.as_slice()), ("nominal", &[0][..]));
+        assert_eq!(found[0].description, "nominal appears 2 times; only the last one is used");
+
+        remove_duplicates(&mut types, &found);
+        assert_eq!(elements(&types[0]), ["lifetime[0] 7200", "nominal[0] 9", "min[0] 2"]);
+        assert!(find_duplicates(&types).is_empty());
+    }
+
+    #[test]
+    fn single_elements_and_distinct_list_entries_are_left_alone() {
+        let ty = parse(
+            r#"<type name="AKM"><nominal>8</nominal><lifetime>7200</lifetime><flags count_in_map="1" deloot="0"/><usage name="Military"/><usage name="Police"/><value name="Tier3"/></type>"#,
+        );
+        assert!(find_in_type(0, &ty).is_empty());
+    }
+
+    #[test]
+    fn identical_list_entries_drop_all_but_the_first() {
+        let mut types = vec![parse(
+            r#"<type name="AKM"><usage name="Military"/><usage name="Police"/><usage name="Military"/><usage name="Military"/></type>"#,
+        )];
+        let found = find_duplicates(&types);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].extra_indices, [2, 3]);
+        assert_eq!(found[0].description, "usage name=\"Military\" is repeated 2 more time(s)");
+
+        remove_duplicates(&mut types, &found);
+        assert_eq!(elements(&types[0]), ["usage[0] Military", "usage[1] Police"]);
+    }
+}
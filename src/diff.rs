@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::model::{FieldKey, TypeEntry};
+
+/// A field whose value differs between two versions of a type; `None` means absent on that side.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct FieldChange {
+    pub key: FieldKey,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct TypeChange {
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Type-level comparison of two files, matched by type name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct TypesDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<TypeChange>,
+}
+
+impl TypesDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Pairs types by name; repeated names are paired by order of appearance.
+pub fn diff_types(old: &[TypeEntry], new: &[TypeEntry]) -> TypesDiff {
+    let mut old_by_name: HashMap<&str, Vec<&TypeEntry>> = HashMap::new();
+    for ty in old {
+        old_by_name.entry(ty.name.as_str()).or_default().push(ty);
+    }
+    for matches in old_by_name.values_mut() {
+        matches.reverse();
+    }
+
+    let mut diff = TypesDiff::default();
+    for ty in new {
+        match old_by_name.get_mut(ty.name.as_str()).and_then(|m| m.pop()) {
+            Some(previous) => {
+                let fields = diff_fields(previous, ty);
+                if !fields.is_empty() {
+                    diff.changed.push(TypeChange {
+                        name: ty.name.clone(),
+                        fields,
+                    });
+                }
+            }
+            None => diff.added.push(ty.name.clone()),
+        }
+    }
+    for ty in old {
+        if let Some(remaining) = old_by_name.get_mut(ty.name.as_str())
+            && remaining.pop().is_some()
+        {
+            diff.removed.push(ty.name.clone());
+        }
+    }
+    diff
+}
+
+/// Field-level differences between two versions of one type, in `new`'s field order.
+pub fn diff_fields(old: &TypeEntry, new: &TypeEntry) -> Vec<FieldChange> {
+    let old_values: HashMap<&FieldKey, &str> = old.fields.iter().map(|f| (&f.key, f.value.as_str())).collect();
+    let new_keys: HashMap<&FieldKey, ()> = new.fields.iter().map(|f| (&f.key, ())).collect();
+
+    let mut changes: Vec<FieldChange> = new
+        .fields
+        .iter()
+        .filter(|f| old_values.get(&f.key) != Some(&f.value.as_str()))
+        .map(|f| FieldChange {
+            key: f.key.clone(),
+            old: old_values.get(&f.key).map(|v| v.to_string()),
+            new: Some(f.value.clone()),
+        })
+        .collect();
+    changes.extend(
+        old.fields
+            .iter()
+            .filter(|f| !new_keys.contains_key(&f.key))
+            .map(|f| FieldChange {
+                key: f.key.clone(),
+                old: Some(f.value.clone()),
+                new: None,
+            }),
+    );
+    changes
+}
@@ -0,0 +1,378 @@
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    config::{self, ConfigLayer},
+    paths::AppDirs,
+    remote::{self, AuthMethod, RemoteConfig, SshBackend},
+    utils,
+};
+
+/// Smaller terminals cut off pane titles and the footer.
+const MIN_COLUMNS: u16 = 80;
+const MIN_ROWS: u16 = 24;
+/// How long `--connect` waits for the TCP connection before giving up on a host.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Level {
+    pub fn tag(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+        }
+    }
+
+    /// `doctor`'s exit status: 0 when everything passed, 1 for warnings, 2 for failures.
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// The outcome of one check, with what to do about it unless it passed.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub level: Level,
+    pub label: String,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(label: &str, detail: impl Into<String>) -> Self {
+        Self { level: Level::Ok, label: label.to_string(), detail: detail.into(), hint: None }
+    }
+
+    fn warn(label: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { level: Level::Warn, label: label.to_string(), detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(label: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { level: Level::Fail, label: label.to_string(), detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    /// `WARN terminal: 70x20`, plus an indented hint line when there is one.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("{:<4} {}: {}", self.level.tag(), self.label, self.detail)];
+        lines.extend(self.hint.iter().map(|hint| format!("     -> {hint}")));
+        lines
+    }
+}
+
+/// Every check in order; `connect` also logs in to the SSH host, which can take seconds.
+pub fn run_all(connect: bool) -> Vec<Check> {
+    let dirs = AppDirs::locate();
+    let mut checks = vec![terminal(), config_file(dirs.config_file().as_deref())];
+    let env_var = |name: &str| env::var(name).ok();
+    checks.extend(ssh_profile(&env_var));
+    if RemoteConfig::from_env().is_some_and(|config| config.auth == AuthMethod::Agent) {
+        checks.push(agent());
+    }
+    if connect {
+        checks.push(match RemoteConfig::from_env() {
+            Some(config) => connectivity(&config, CONNECT_TIMEOUT),
+            None => Check::ok("ssh connect", "skipped, no SSH profile configured"),
+        });
+    }
+    checks.push(match &dirs.config_dir {
+        Some(dir) => write_access("config directory", dir),
+        None => Check::fail("config directory", "unavailable", "set HOME or an absolute XDG_CONFIG_HOME"),
+    });
+    match env::current_dir() {
+        Ok(dir) => checks.push(write_access("backup directory", &dir)),
+        Err(err) => checks.push(Check::fail("backup directory", format!("no working directory: {err}"), "start from a directory that exists")),
+    }
+    checks
+}
+
+/// The most severe level among `checks`.
+pub fn worst(checks: &[Check]) -> Level {
+    checks.iter().map(|c| c.level).max().unwrap_or(Level::Ok)
+}
+
+pub fn terminal() -> Check {
+    const LABEL: &str = "terminal";
+    if !io::stdout().is_terminal() {
+        return Check::warn(LABEL, "output is not a terminal", "run in a terminal for the full-screen editor, or use --plain");
+    }
+    match crossterm::terminal::size() {
+        Ok((cols, rows)) if cols < MIN_COLUMNS || rows < MIN_ROWS => Check::warn(
+            LABEL,
+            format!("{cols}x{rows}"),
+            format!("enlarge the window to at least {MIN_COLUMNS}x{MIN_ROWS}"),
+        ),
+        Ok((cols, rows)) => {
+            let no_color = env::var("NO_COLOR").ok();
+            let term = env::var("TERM").ok();
+            match config::no_colour_reason(no_color.as_deref(), term.as_deref(), crossterm::style::available_color_count()) {
+                Some(reason) => Check::ok(LABEL, format!("{cols}x{rows}, no colours ({reason})")),
+                None => Check::ok(LABEL, format!("{cols}x{rows}")),
+            }
+        }
+        Err(err) => Check::fail(LABEL, format!("size unknown: {err}"), "check TERM, or use --plain"),
+    }
+}
+
+/// Whether the user config reads and parses; a missing file is fine.
+pub fn config_file(path: Option<&Path>) -> Check {
+    const LABEL: &str = "config file";
+    let Some(path) = path else {
+        return Check::warn(LABEL, "no location (HOME is unset)", "set HOME or an absolute XDG_CONFIG_HOME");
+    };
+    match fs::read_to_string(path) {
+        Ok(content) => match ConfigLayer::parse(&content) {
+            Ok((_, unknown)) if unknown.is_empty() => Check::ok(LABEL, path.display().to_string()),
+            Ok((_, unknown)) => Check::warn(
+                LABEL,
+                format!("{}: unknown keys {}", path.display(), unknown.join(", ")),
+                "check the spelling; unknown keys are ignored",
+            ),
+            Err(err) => Check::fail(
+                LABEL,
+                format!("{}: {err}", path.display()),
+                "fix the file; until then every setting is at its default",
+            ),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Check::ok(LABEL, format!("{} (not created, defaults apply)", path.display()))
+        }
+        Err(err) => Check::fail(LABEL, format!("{}: {err}", path.display()), "make the file readable"),
+    }
+}
+
+/// The `SSH_*` variables that make up the one SSH profile, its key file and known_hosts.
+/// `var` looks a variable up, so tests don't have to touch the environment.
+pub fn ssh_profile(var: &dyn Fn(&str) -> Option<String>) -> Vec<Check> {
+    const LABEL: &str = "ssh profile";
+    let (host, user) = (var("SSH_HOST"), var("SSH_USER"));
+    let (host, user) = match (host, user) {
+        (None, None) => return vec![Check::ok(LABEL, "none configured (SSH_HOST/SSH_USER unset)")],
+        (Some(_), None) => {
+            return vec![Check::fail(LABEL, "SSH_HOST is set but SSH_USER is not", "export SSH_USER too")];
+        }
+        (None, Some(_)) => {
+            return vec![Check::fail(LABEL, "SSH_USER is set but SSH_HOST is not", "export SSH_HOST too")];
+        }
+        (Some(host), Some(user)) => (host, user),
+    };
+    let mut checks = Vec::new();
+    match var("SSH_PORT") {
+        Some(port) if port.parse::<u16>().is_err() => checks.push(Check::warn(
+            LABEL,
+            format!("{user}@{host}, SSH_PORT '{port}' is not a port number"),
+            "fix SSH_PORT; port 22 is used instead",
+        )),
+        port => checks.push(Check::ok(LABEL, format!("{user}@{host}:{}", port.as_deref().unwrap_or("22")))),
+    }
+    let key = var("SSH_KEY");
+    let implied = AuthMethod::implied(key.as_deref().map(Path::new), var("SSH_PASSWORD").as_deref());
+    let auth = match var("SSH_AUTH") {
+        Some(value) => AuthMethod::parse(&value).unwrap_or_else(|| {
+            checks.push(Check::warn(
+                "ssh auth",
+                format!("SSH_AUTH '{value}' is not agent, key or password"),
+                format!("fix SSH_AUTH; {} is used instead", implied.label()),
+            ));
+            implied
+        }),
+        None => implied,
+    };
+    match (auth, key) {
+        (AuthMethod::Key, Some(key)) => checks.push(key_file(Path::new(&key))),
+        (AuthMethod::Key, None) => checks.push(Check::fail("ssh key", "SSH_AUTH=key but SSH_KEY is not set", "export SSH_KEY")),
+        (AuthMethod::Password, _) if var("SSH_PASSWORD").is_none() => {
+            checks.push(Check::fail("ssh key", "SSH_AUTH=password but SSH_PASSWORD is not set", "export SSH_PASSWORD"));
+        }
+        (AuthMethod::Password, _) => checks.push(Check::ok("ssh key", "none, using SSH_PASSWORD")),
+        (AuthMethod::Agent, _) => checks.push(Check::ok("ssh key", "none, using the SSH agent")),
+    }
+    checks.push(known_hosts(var("HOME").map(|home| PathBuf::from(home).join(".ssh/known_hosts")), &host));
+    checks
+}
+
+/// The agent must be reachable and hold a key for agent logins to have anything to offer.
+pub fn agent() -> Check {
+    const LABEL: &str = "ssh agent";
+    match remote::agent_identities() {
+        Ok(keys) if keys.is_empty() => Check::warn(LABEL, "reachable, but it holds no keys", "ssh-add your key"),
+        Ok(keys) => Check::ok(LABEL, format!("{}: {}", utils::plural(keys.len(), "key"), keys.join(", "))),
+        Err(err) => Check::fail(LABEL, err.to_string(), "start ssh-agent and export SSH_AUTH_SOCK, or use SSH_KEY"),
+    }
+}
+
+/// The key must be a readable file; OpenSSH also insists nobody else can read it.
+pub fn key_file(path: &Path) -> Check {
+    const LABEL: &str = "ssh key";
+    let meta = match fs::metadata(path) {
+        Ok(meta) if meta.is_file() => meta,
+        Ok(_) => return Check::fail(LABEL, format!("{} is not a file", path.display()), "point SSH_KEY at the private key file"),
+        Err(err) => return Check::fail(LABEL, format!("{}: {err}", path.display()), "point SSH_KEY at the private key file"),
+    };
+    if let Err(err) = fs::File::open(path) {
+        return Check::fail(LABEL, format!("{}: {err}", path.display()), "make the key readable by this user");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Check::warn(
+                LABEL,
+                format!("{} has mode {mode:o}", path.display()),
+                format!("chmod 600 {}", path.display()),
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = meta;
+    Check::ok(LABEL, path.display().to_string())
+}
+
+/// Whether `~/.ssh/known_hosts` lists `host`; hashed entries can't be matched by name.
+pub fn known_hosts(path: Option<PathBuf>, host: &str) -> Check {
+    const LABEL: &str = "known_hosts";
+    let Some(path) = path else {
+        return Check::warn(LABEL, "no location (HOME is unset)", "set HOME");
+    };
+    let hint = format!("connect once with `ssh {host}` to record the host key");
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => return Check::warn(LABEL, format!("{}: {err}", path.display()), hint),
+    };
+    let listed = content.lines().any(|line| {
+        line.split_whitespace().next().is_some_and(|hosts| {
+            hosts.split(',').any(|entry| entry == host || entry.strip_prefix('[').is_some_and(|e| e.starts_with(&format!("{host}]:"))))
+        })
+    });
+    if listed {
+        Check::ok(LABEL, format!("{host} listed"))
+    } else if content.lines().any(|line| line.starts_with("|1|")) {
+        Check::ok(LABEL, format!("{host} not listed by name (file has hashed entries)"))
+    } else {
+        Check::warn(LABEL, format!("{host} not in {}", path.display()), hint)
+    }
+}
+
+/// Reaches the host within `timeout`, then logs in and opens SFTP as the picker would.
+pub fn connectivity(config: &RemoteConfig, timeout: Duration) -> Check {
+    const LABEL: &str = "ssh connect";
+    let target = format!("{}:{}", config.host, config.port);
+    let addrs = match (&*config.host, config.port).to_socket_addrs() {
+        Ok(addrs) => addrs.collect::<Vec<_>>(),
+        Err(err) => return Check::fail(LABEL, format!("{target}: {err}"), "check SSH_HOST"),
+    };
+    if !addrs.iter().any(|addr| TcpStream::connect_timeout(addr, timeout).is_ok()) {
+        return Check::fail(
+            LABEL,
+            format!("{target}: no answer within {} s", timeout.as_secs()),
+            "check SSH_HOST, SSH_PORT and any firewall in between",
+        );
+    }
+    match SshBackend::connect(config) {
+        Ok(_) => Check::ok(LABEL, format!("{target}, logged in as {}", config.username)),
+        Err(err) => Check::fail(LABEL, format!("{target}: {err}"), "check SSH_USER and the key, password or agent"),
+    }
+}
+
+/// Creates and removes a probe file in `dir`; a directory that doesn't exist yet is
+/// judged by the nearest parent it would be created in.
+pub fn write_access(label: &str, dir: &Path) -> Check {
+    let Some(existing) = dir.ancestors().find(|d| d.is_dir()) else {
+        return Check::fail(label, format!("{}: no parent directory exists", dir.display()), "create it");
+    };
+    let probe = existing.join(format!(".dayzlooteditor-doctor-{}", std::process::id()));
+    let detail = if existing == dir {
+        dir.display().to_string()
+    } else {
+        format!("{} (not created yet)", dir.display())
+    };
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::ok(label, detail)
+        }
+        Err(err) => Check::fail(
+            label,
+            format!("{detail}: {err}"),
+            format!("make {} writable by this user", existing.display()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+    }
+
+    #[test]
+    fn worst_level_decides_the_exit_code() {
+        let checks = [Check::ok("a", ""), Check::warn("b", "", ""), Check::ok("c", "")];
+        assert_eq!(worst(&checks), Level::Warn);
+        assert_eq!(worst(&checks).exit_code(), 1);
+        assert_eq!(worst(&[]).exit_code(), 0);
+        assert_eq!(Level::Fail.exit_code(), 2);
+    }
+
+    #[test]
+    fn half_configured_profile_fails() {
+        let checks = ssh_profile(&vars(&[("SSH_HOST", "dayz.example")]));
+        assert_eq!(worst(&checks), Level::Fail);
+        assert!(checks[0].detail.contains("SSH_USER"));
+        assert_eq!(worst(&ssh_profile(&vars(&[]))), Level::Ok);
+    }
+
+    #[test]
+    fn missing_key_file_fails() {
+        let checks = ssh_profile(&vars(&[
+            ("SSH_HOST", "dayz.example"),
+            ("SSH_USER", "admin"),
+            ("SSH_KEY", "/nonexistent/id_ed25519"),
+        ]));
+        let key = checks.iter().find(|c| c.label == "ssh key").unwrap();
+        assert_eq!(key.level, Level::Fail);
+    }
+
+    #[test]
+    fn ssh_auth_overrides_the_method_the_variables_imply() {
+        let profile = [("SSH_HOST", "dayz.example"), ("SSH_USER", "admin"), ("SSH_KEY", "/nonexistent/id_ed25519")];
+        let with = |extra: &[(&str, &str)]| {
+            let all: Vec<(&str, &str)> = profile.iter().chain(extra).copied().collect();
+            ssh_profile(&vars(&all))
+        };
+        let detail = |checks: &[Check], label: &str| checks.iter().find(|c| c.label == label).map(|c| (c.level, c.detail.clone()));
+        assert_eq!(detail(&with(&[("SSH_AUTH", "agent")]), "ssh key"), Some((Level::Ok, String::from("none, using the SSH agent"))));
+        assert_eq!(detail(&with(&[("SSH_AUTH", "Password")]), "ssh key").map(|d| d.0), Some(Level::Fail));
+        let checks = with(&[("SSH_AUTH", "kerberos")]);
+        assert_eq!(detail(&checks, "ssh auth").map(|d| d.0), Some(Level::Warn));
+        assert_eq!(detail(&checks, "ssh key").map(|d| d.0), Some(Level::Fail), "falls back to the key");
+    }
+
+    #[test]
+    fn known_hosts_matches_plain_and_bracketed_entries() {
+        let dir = env::temp_dir().join(format!("dayz-doctor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("known_hosts");
+        fs::write(&path, "other.example ssh-ed25519 AAAA\n[dayz.example]:2222,10.0.0.1 ssh-ed25519 AAAA\n").unwrap();
+        assert_eq!(known_hosts(Some(path.clone()), "dayz.example").level, Level::Ok);
+        assert_eq!(known_hosts(Some(path.clone()), "10.0.0.1").level, Level::Ok);
+        assert_eq!(known_hosts(Some(path.clone()), "missing.example").level, Level::Warn);
+        assert_eq!(write_access("dir", &dir).level, Level::Ok);
+        assert_eq!(write_access("dir", &dir.join("sub/dir")).level, Level::Ok);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
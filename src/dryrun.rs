@@ -0,0 +1,175 @@
+use std::{
+    collections::hash_map::RandomState,
+    fs::{self, File, OpenOptions},
+    hash::BuildHasher,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    diff::{self, TypesDiff},
+    model::{parse_types, TypeEntry},
+    sniff, utils,
+};
+
+/// What reading a rehearsed save back showed.
+#[derive(Debug)]
+pub enum Outcome {
+    Matches,
+    /// From the model to what was read back: `removed` types were lost in writing.
+    Differs(TypesDiff),
+    Unparsable(String),
+}
+
+/// `:dryrun`: the save written to a temporary file and read back, without touching the
+/// file being edited.
+#[derive(Debug)]
+pub struct Rehearsal {
+    pub bytes: u64,
+    pub outcome: Outcome,
+}
+
+/// Writes `xml` to a temporary file in `dir`, reads it back and removes the file again.
+pub fn round_trip(xml: &str, dir: &Path) -> io::Result<String> {
+    let (path, mut file) = create_scratch(dir)?;
+    let result = file.write_all(xml.as_bytes()).and_then(|()| fs::read_to_string(&path));
+    drop(file);
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// A new file in `dir` under a random name. It is created, never opened if something is
+/// already there (a file or a planted link), and on Unix only this user can read it.
+fn create_scratch(dir: &Path) -> io::Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut attempts = 0;
+    loop {
+        let token = RandomState::new().hash_one(process::id());
+        let path = dir.join(format!("dayz-loot-editor-dryrun-{token:016x}.xml"));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempts < 8 => attempts += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Round-trips `xml`, the serialized `types`, through `dir` and compares what parses back,
+/// after `normalize` like any loaded file, with `types`.
+pub fn rehearse(types: &[TypeEntry], xml: &str, dir: &Path, normalize: impl FnOnce(&mut [TypeEntry])) -> io::Result<Rehearsal> {
+    let read_back = round_trip(xml, dir)?;
+    let bytes = read_back.len() as u64;
+    let outcome = match parse_types(&read_back) {
+        Ok((mut reparsed, _)) => {
+            normalize(&mut reparsed);
+            let diff = diff::diff_types(types, &reparsed);
+            if diff.is_empty() { Outcome::Matches } else { Outcome::Differs(diff) }
+        }
+        Err(err) => Outcome::Unparsable(err.to_string()),
+    };
+    Ok(Rehearsal { bytes, outcome })
+}
+
+impl Rehearsal {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Matches)
+    }
+
+    /// One line for the status bar.
+    pub fn summary(&self) -> String {
+        let size = sniff::format_size(self.bytes);
+        match &self.outcome {
+            Outcome::Matches => format!("Dry run: {size} written and read back, matching the editor"),
+            Outcome::Differs(diff) => {
+                let count = diff.added.len() + diff.removed.len() + diff.changed.iter().map(|c| c.fields.len()).sum::<usize>();
+                format!("Dry run: {size} written, but {} when read back (see the list)", utils::plural(count, "difference"))
+            }
+            Outcome::Unparsable(err) => format!("Dry run: {size} written, but it doesn't parse back: {err}"),
+        }
+    }
+
+    /// The differences, one per line, for the overlay.
+    pub fn lines(&self) -> Vec<String> {
+        let diff = match &self.outcome {
+            Outcome::Matches => return vec![String::from("What was written reads back the same as the editor holds it.")],
+            Outcome::Unparsable(err) => return vec![format!("The written file doesn't parse: {err}")],
+            Outcome::Differs(diff) => diff,
+        };
+        let mut lines: Vec<String> = diff.removed.iter().map(|name| format!("- {name} (missing when read back)")).collect();
+        lines.extend(diff.added.iter().map(|name| format!("+ {name} (only in what was read back)")));
+        for change in &diff.changed {
+            for field in &change.fields {
+                let show = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("(none)"));
+                lines.push(format!(
+                    "~ {}: {} {} in the editor, {} read back",
+                    change.name,
+                    field.key.label(),
+                    show(&field.old),
+                    show(&field.new)
+                ));
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::serialize_document,
+        test_harness::{Fixture, SAMPLE},
+    };
+
+    #[test]
+    fn a_faithful_save_matches_and_leaves_no_file_behind() {
+        let fixture = Fixture::new();
+        let (types, _) = parse_types(SAMPLE).unwrap();
+        let xml = serialize_document(&types, false, &[]).unwrap();
+        let rehearsal = rehearse(&types, &xml, &fixture.dir, |_| {}).unwrap();
+        assert!(rehearsal.passed(), "{:?}", rehearsal.lines());
+        assert_eq!(rehearsal.bytes, xml.len() as u64);
+        assert!(rehearsal.summary().contains("matching"));
+        assert_eq!(fs::read_dir(&fixture.dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn lost_types_changed_values_and_broken_output_are_reported() {
+        let fixture = Fixture::new();
+        let (types, _) = parse_types(SAMPLE).unwrap();
+        let (mut written, _) = parse_types(SAMPLE).unwrap();
+        written.retain(|t| t.name != "Apple");
+        written[0].fields[0].value = String::from("08");
+        let xml = serialize_document(&written, false, &[]).unwrap();
+        let rehearsal = rehearse(&types, &xml, &fixture.dir, |_| {}).unwrap();
+        assert!(!rehearsal.passed());
+        assert!(rehearsal.summary().contains("2 differences"), "{}", rehearsal.summary());
+        assert_eq!(rehearsal.lines(), [
+            "- Apple (missing when read back)",
+            "~ AKM: nominal 8 in the editor, 08 read back",
+        ]);
+
+        let rehearsal = rehearse(&types, "<types><type name=\"AKM\">", &fixture.dir, |_| {}).unwrap();
+        assert!(matches!(rehearsal.outcome, Outcome::Unparsable(_)));
+        assert!(rehearsal.summary().contains("doesn't parse back"));
+        assert_eq!(fs::read_dir(&fixture.dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn scratch_files_are_new_private_and_never_reused() {
+        let fixture = Fixture::new();
+        let (first, _) = create_scratch(&fixture.dir).unwrap();
+        let (second, _) = create_scratch(&fixture.dir).unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        assert!(round_trip("<types/>", &fixture.dir.join("missing")).is_err());
+    }
+}
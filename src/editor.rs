@@ -1,45 +1,104 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    env,
     fs,
     io,
-    path::PathBuf,
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        TryLockError,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 use tui::{
-    layout::{Constraint, Direction, Layout},
-    style::{Modifier, Style},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
-};
-use xml::{
-    reader::{EventReader, XmlEvent},
-    writer::EmitterConfig,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState, Wrap},
 };
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
     action::Action,
-    remote::{FileSelection, FileSource},
+    annotations::{self, Annotations},
+    assign::{self, Assignment},
+    backup::{self, BackupFile},
+    classnames::{self, CaseReview, Classnames},
+    balance::{self, CapChange, NominalStats},
+    config::{Config, ConfigLayer, NewTypeLayer, Session, Theme, PROJECT_FILE},
+    dedupe::{self, Duplicate},
+    doctor,
+    bulk::{self, BulkEdit, BulkOp, BulkWritten, DryRun},
+    diff::{self, TypesDiff},
+    dryrun::{self, Rehearsal},
+    field_help::{FieldHelp, HELP_FILE},
+    hints::Baseline,
+    watch::{FileStamp, WATCH_INTERVAL},
+    expr,
+    fuzzy,
+    paths::{self, AppDirs},
+    merge::{self, Conflict, Side},
+    progress::{self, Job},
+    overlay_help::{self, OverlayHelp},
+    model::{self, parse_types, Field, FieldKey, LineEnding, ParseWarning, TypeEntry},
+    remote::{ConnectResult, FileSelection, FileSource, RemoteConfig, SshBackend},
+    report,
+    selection::TypeSelection,
+    sniff,
+    spawnable::{self, Spawnables},
+    status_log::StatusLog,
+    strip::{self, StripPlan, StripTarget},
+    summary::TypeSummary,
+    flags,
+    line_edit,
+    tiers,
+    tutorial::{self, Tutorial},
     utils,
+    validation::{self, Issue, IssueCode},
 };
 
-#[derive(Clone, Debug)]
-enum FieldKey {
-    Element { name: String, index: usize },
-    Attribute { element: String, index: usize, attr: String },
-}
-
-#[derive(Clone, Debug)]
-struct Field {
-    key: FieldKey,
-    value: String,
-}
-
-#[derive(Clone, Debug)]
-struct TypeEntry {
-    name: String,
-    fields: Vec<Field>,
-}
+/// How often the open remote file is stat'ed to detect a dropped session.
+const LIVENESS_INTERVAL: Duration = Duration::from_secs(15);
+/// Oldest undo entries are dropped beyond this many.
+const UNDO_LIMIT: usize = 100;
+/// Oldest message log entries are dropped beyond this many.
+const MESSAGE_LIMIT: usize = 200;
+/// Commands that change many types from their arguments; their last arguments are kept
+/// for a bare command and `:again`.
+const BULK_COMMANDS: [&str; 5] = ["apply", "addusage", "addtag", "setcategory", "stripfield"];
+/// Commands that edit straight away or open a tool that does nothing but edit; refused up
+/// front in read-only mode. Tools that also report are refused at their Enter instead.
+const MUTATING_COMMANDS: [&str; 6] = ["cost", "lifetime", "fixcase", "stripfield", "undo", "redo"];
+/// Columns of the table view; `category` is the `category @name` attribute, the rest are elements.
+const TABLE_COLUMNS: [&str; 7] = ["nominal", "min", "lifetime", "restock", "quantmin", "quantmax", "category"];
+const TABLE_NAME_WIDTH: u16 = 32;
+const TABLE_COLUMN_WIDTH: u16 = 10;
+/// Types, Fields and Tips widths in percent.
+const DEFAULT_PANES: [u16; 3] = [35, 45, 20];
+/// Narrowest each pane may get; Tips can also collapse to 0.
+const MIN_PANES: [u16; 3] = [15, 20, 10];
+const PANE_STEP: u16 = 5;
+/// Values remembered per field label for the quick-pick list.
+const RECENT_LIMIT: usize = 10;
+/// Calculations kept in the `:calc` overlay.
+const CALC_HISTORY: usize = 5;
+/// Fields listed as variables in the calculator; any other numeric element works too.
+const CALC_VARIABLES: [&str; 7] = ["nominal", "min", "lifetime", "restock", "quantmin", "quantmax", "cost"];
+/// Types named in the missing-flags prompt before it says "and N more".
+const MISSING_FLAGS_LISTED: usize = 10;
+/// Oldest jump list entries are dropped beyond this many.
+const JUMP_LIMIT: usize = 100;
+/// Fuzzy suggestions offered when `G` finds no exact name.
+const GOTO_MATCHES: usize = 10;
+/// Columns of the categories pane; the percentage split shares what is left.
+const CATEGORY_PANE_WIDTH: u16 = 24;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum EditorFocus {
+    /// The categories pane toggled with `F`.
+    Categories,
     TypeList,
     FieldList,
     Editing,
@@ -50,71 +109,904 @@ enum EditTarget {
     TypeName,
     FieldName,
     FieldValue,
+    SavePath,
+    /// A `:` command typed into the footer.
+    Command,
+    /// A field value edited from the table view.
+    TableCell,
+    /// `G`: a type name to jump to.
+    Goto,
+    /// `C`: the name for a copy of the highlighted type, added once the name is entered.
+    DuplicateName,
 }
 
-pub struct Editor {
-    path: Option<PathBuf>,
-    source: FileSource,
-    types: Vec<TypeEntry>,
-    selected_type: usize,
-    selected_field: usize,
-    focus: EditorFocus,
-    editing_target: Option<EditTarget>,
-    input_buffer: String,
-    status: String,
+enum Overlay {
+    RemoteLost,
+    /// `:calc`: arithmetic over the current type's values; the expression is in `input_buffer`.
+    Calc { result: Option<String>, error: Option<String> },
+    Statistics,
+    ValidationReport,
+    NominalCap(CapTool),
+    Propagate(PropagateTool),
+    Duplicates(DuplicateReview),
+    Quantity(QuantityTool),
+    Tiers(TierTool),
+    /// `:flags`: the marked types' flags side by side.
+    FlagMatrix(FlagMatrix),
+    /// `:lifetime`: the preset (a row of `lifetime_presets`) to give `targets`, the marked
+    /// types or the highlighted one.
+    LifetimePresets { targets: Vec<usize>, cursor: usize },
+    /// Asks before `:readonly` switches edits back on.
+    ConfirmWritable,
+    /// `q` with unsaved changes: leave anyway, save first, or stay.
+    ConfirmClose,
+    /// `:backups`: the open file's backups, with a diff preview and restore.
+    Backups(BackupBrowser),
+    /// `a` on the Fields pane: an element built up step by step, typed into `input_buffer`.
+    AddElement(PendingAdd),
+    /// The file changed on disk since it was loaded; pick a side for each clash before saving.
+    SaveConflicts(ConflictReview),
+    /// `:apply` dry run (scrolled by the number): what one edit would change in every types
+    /// file under a directory.
+    BulkApply(DryRun, u16),
+    /// `G` without an exact match: the closest type names (indices into `types`) to pick from.
+    GotoPick { query: String, matches: Vec<usize>, cursor: usize },
+    /// `:history`: redo entries, the current state, then undo entries, newest first;
+    /// `cursor` is a row of that list.
+    History { cursor: usize },
+    /// Save found types without `<flags>`: add the defaults to them, save anyway, or stop.
+    MissingFlags(Vec<usize>),
+    /// Save would leave far less than the file had, e.g. after deleting every type.
+    ConfirmShrink(ShrinkWarning),
+    /// Asks before saving onto a filesystem with too little room.
+    ConfirmLowSpace(SpaceWarning),
+    /// `e` on a field of another name than the marked one: swap anyway?
+    ConfirmExchange(ExchangeEnd, ExchangeEnd),
+    /// Deleting a type cfgspawnabletypes.xml names; holds the warning shown.
+    ConfirmDelete(String),
+    /// `R`: reload from disk, save merging with it, or dismiss the changed-on-disk banner.
+    Reload,
+    /// `:addusage`/`:addtag`/`:setcategory` preview (scrolled by the number); Enter applies.
+    Assign(Assignment, u16),
+    /// `:fixcase` preview (scrolled by the number): type names to respell as the classname
+    /// list has them; Enter applies.
+    CaseFixes(CaseReview, u16),
+    /// `:stripfield` preview (scrolled by the number): the types that lose the field; Enter
+    /// removes it from all of them.
+    StripFields(StripPlan, u16),
+    /// Read-only scrollable list, e.g. the message log or parse warnings.
+    Text { title: String, lines: Vec<String>, scroll: u16 },
 }
 
-impl FieldKey {
-    pub fn set_name(&mut self, new_name: String) {
+const CONFIRM_KEYS: &[(&str, &str)] = &[("y / Enter", "go ahead"), ("n / Esc", "cancel")];
+const PREVIEW_KEYS: &[(&str, &str)] = &[("↑/↓", "scroll"), ("Enter", "apply it all as one undo step"), ("Esc", "cancel; nothing changes")];
+
+impl Overlay {
+    /// The overlay's own keys, for the panel F1 shows over it. Every overlay has one, so a
+    /// new one can't go without.
+    fn help(&self) -> OverlayHelp {
         match self {
-            FieldKey::Element { name, .. } => *name = new_name,
-            FieldKey::Attribute { attr, .. } => *attr = new_name,
+            Self::RemoteLost => OverlayHelp::new("Connection lost", &[
+                ("r / Enter", "reconnect with the same settings and retry the save"),
+                ("s", "save a local copy instead"),
+                ("Esc", "cancel; the changes stay in memory"),
+            ]),
+            Self::Calc { .. } => OverlayHelp::new("Calculator", &[
+                ("+ - * / % ( )", "arithmetic; the current type's fields are variables"),
+                ("Enter", "evaluate; on an empty line, the last result goes into the next value you edit"),
+                ("↑", "the last expression again"),
+                ("Esc", "close"),
+            ]),
+            Self::Statistics => OverlayHelp::new("Statistics", &[("Esc / Enter / i", "close")]),
+            Self::ValidationReport => OverlayHelp::new("Validation report", &[
+                ("Enter / n", "jump to the next issue"),
+                ("d", "remove attributes repeated on one element, keeping the last value"),
+                ("Esc / v", "close"),
+            ]),
+            Self::NominalCap(_) => OverlayHelp::new("Nominal cap", &[
+                ("0-9, Backspace", "type the cap"),
+                ("p", "cap at p95 again"),
+                ("Tab", "scale min along or not"),
+                ("q", "scale quantmin/quantmax of stackables too"),
+                ("Enter", "apply the cap to the types in view"),
+                ("Esc", "close"),
+            ]),
+            Self::Propagate(_) => OverlayHelp::new("Copy values", &[
+                ("↑/↓", "move"),
+                ("Space", "tick a field to copy"),
+                ("Enter", "copy the ticked fields to the marked types"),
+                ("Esc", "cancel"),
+            ]),
+            Self::Duplicates(_) => OverlayHelp::new("Duplicate elements", &[
+                ("↑/↓, PgUp/PgDn", "move"),
+                ("Enter", "remove every extra copy in one undo step"),
+                ("Esc", "cancel"),
+            ]),
+            Self::Quantity(_) => OverlayHelp::new("Quantity", &[
+                ("0-9, Backspace", "type a percentage"),
+                ("Tab, ←/→", "switch between quantmin and quantmax"),
+                ("u", "not used (-1/-1) or not"),
+                ("r", "edit the raw field instead"),
+                ("Enter", "apply"),
+                ("Esc", "cancel"),
+            ]),
+            Self::Tiers(_) => OverlayHelp::new("Tiers", &[
+                ("↑/↓", "move"),
+                ("Space", "tick or untick a tier for all the types"),
+                ("Enter", "apply in one undo step"),
+                ("Esc", "cancel"),
+            ]),
+            Self::FlagMatrix(_) => OverlayHelp::new("Flags of the marked types", &[
+                ("←/→", "pick a column"),
+                ("↑/↓, PgUp/PgDn", "scroll the types"),
+                ("1-6", "set that column to what most types have"),
+                ("+ / -", "set the highlighted column to 1 or 0"),
+                ("Esc", "close"),
+            ]),
+            Self::LifetimePresets { .. } => OverlayHelp::new("Lifetime presets", &[
+                ("↑/↓", "pick a preset"),
+                ("Enter", "set it on the marked types, or the highlighted one"),
+                ("Esc", "cancel"),
+            ]),
+            Self::ConfirmWritable => OverlayHelp::new("Leave read-only mode", CONFIRM_KEYS),
+            Self::ConfirmClose => OverlayHelp::new("Unsaved changes", &[
+                ("y / Enter", "close without saving"),
+                ("s", "save, then close"),
+                ("n / Esc", "keep editing"),
+            ]),
+            Self::Backups(_) => OverlayHelp::new("Backups", &[
+                ("↑/↓", "pick a backup"),
+                ("Enter", "preview what restoring it would change"),
+                ("o", "load it into the editor as unsaved changes"),
+                ("w", "write it over the file, backing the file up first"),
+                ("Esc", "close"),
+            ]),
+            Self::AddElement(_) => OverlayHelp::new("Add element", &[
+                ("Enter", "next step; on an empty attribute name, add the element"),
+                ("Esc", "discard it"),
+            ]),
+            Self::SaveConflicts(_) => OverlayHelp::new("Save conflicts", &[
+                ("↑/↓, PgUp/PgDn", "move"),
+                ("←", "keep the value here"),
+                ("→", "take the value on disk"),
+                ("Tab / Space", "switch sides"),
+                ("Enter", "merge and save"),
+                ("Esc", "cancel; nothing is written"),
+            ]),
+            Self::BulkApply(..) => OverlayHelp::new("Dry run", &[
+                ("↑/↓", "scroll"),
+                ("Enter", "write the changed files, each with a backup"),
+                ("Esc", "cancel; nothing is written"),
+            ]),
+            Self::GotoPick { .. } => OverlayHelp::new("Go to", &[("↑/↓", "pick a type"), ("Enter", "go there"), ("Esc", "cancel")]),
+            Self::History { .. } => OverlayHelp::new("History", &[
+                ("↑/↓", "pick a point"),
+                ("Enter", "undo or redo up to it"),
+                ("Esc", "close"),
+            ]),
+            Self::MissingFlags(_) => OverlayHelp::new("Types without flags", &[
+                ("f / Enter", "add the default flags to them and save"),
+                ("s", "save anyway"),
+                ("Esc", "cancel the save"),
+            ]),
+            Self::ConfirmShrink(_) => OverlayHelp::new("Save shrinks the file", CONFIRM_KEYS),
+            Self::ConfirmLowSpace(_) => OverlayHelp::new("Low disk space", CONFIRM_KEYS),
+            Self::ConfirmExchange(..) => OverlayHelp::new("Swap values", CONFIRM_KEYS),
+            Self::ConfirmDelete(_) => OverlayHelp::new("Delete a referenced type", CONFIRM_KEYS),
+            Self::Reload => OverlayHelp::new("Reload", &[
+                ("r / Enter", "reload from disk (u brings your edits back)"),
+                ("s", "save, merging with the file on disk"),
+                ("i", "ignore the change on disk"),
+                ("Esc", "close"),
+            ]),
+            Self::Assign(..) => OverlayHelp::new("Assignment preview", PREVIEW_KEYS),
+            Self::CaseFixes(..) => OverlayHelp::new("Classname spelling", PREVIEW_KEYS),
+            Self::StripFields(..) => OverlayHelp::new("Strip preview", PREVIEW_KEYS),
+            Self::Text { .. } => OverlayHelp::new("List", &[("↑/↓, PgUp/PgDn", "scroll"), ("Esc / Enter", "close")]),
+        }
+    }
+}
+
+/// A save that would shrink the file drastically: sizes in bytes and type counts, as
+/// last loaded or saved and as about to be written.
+struct ShrinkWarning {
+    bytes: (u64, u64),
+    types: (usize, usize),
+}
+
+impl ShrinkWarning {
+    /// `1.2 MB → 96 B (-99%), 2000 → 0 types`.
+    fn describe(&self) -> String {
+        let (before, after) = self.bytes;
+        let percent = 100 - (after.saturating_mul(100) / before.max(1)).min(100);
+        format!(
+            "{} → {} (-{percent}%), {} → {} types",
+            sniff::format_size(before),
+            sniff::format_size(after),
+            self.types.0,
+            self.types.1
+        )
+    }
+}
+
+/// Too little room where the file is saved: bytes free there, the size about to be
+/// written, and the `min_free_mb` threshold in bytes.
+struct SpaceWarning {
+    free: u64,
+    needed: u64,
+    threshold: u64,
+}
+
+impl SpaceWarning {
+    fn describe(&self) -> String {
+        let free = sniff::format_size(self.free);
+        if self.free < self.needed {
+            format!("only {free} free for a {} file", sniff::format_size(self.needed))
+        } else {
+            format!("only {free} free (min_free_mb is {})", self.threshold / MIB)
         }
     }
+}
+
+const MIB: u64 = 1024 * 1024;
+
+/// What opening a file keeps of the previous one. The file's own state (types, undo,
+/// marks, the cursor) always goes; the view is how you look at any file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadMode {
+    /// Filters, the categories pane, the table and the display toggles back to their defaults.
+    Fresh,
+    /// Those kept, and the filters re-applied to the new types; one matching nothing is
+    /// dropped with a note.
+    KeepView,
+}
+
+/// Work running on a worker thread behind a progress bar.
+enum EditorJob {
+    DryRun(Job<Option<DryRun>>),
+    Write(BulkEdit, Job<BulkWritten>),
+    /// A remote file downloading before it opens.
+    Read(FileSelection, LoadMode, Job<io::Result<Sources>>),
+    /// A remote save uploading; the model is settled once it finishes. `Err` holds the
+    /// rehearsal `dry_run_remote` stopped it at.
+    Upload(Box<Upload>, Job<io::Result<Result<Uploaded, Rehearsal>>>),
+    /// `:dryrun` writing the save to a scratch file and reading it back.
+    Rehearse(Job<io::Result<Rehearsal>>),
+    /// `R` on a remote file: the file downloading and parsing before it replaces the model.
+    Reload(Job<io::Result<(String, Vec<TypeEntry>)>>),
+}
 
-    pub fn get_help_text(&self) -> String{
+impl EditorJob {
+    /// Asks the job to stop where it safely can; returns the status to show.
+    fn cancel(&self) -> String {
         match self {
-            FieldKey::Element { name, .. } => {
-                match name.as_str() {
-                    "nominal" => "The nominal (wanted) amount in the server. Same as max is max is not used.".to_string(),
-                    "lifetime" => "The amount of time it takes for the item to despawn when the item is on the ground.\nDoes not come into effect if the item is ruined".to_string(),
-                    "restock" => "How long after one of the same item (despawns or is picked up by the player) is a new one spawned.".to_string(),
-                    "min" => "Minimum quantity of items to spawn this applies to the entire map.".to_string(),
-                    "quantmin" => "Minimum quantity of the item to spawn in a stack. Eg. Ammunition stack quantity.".to_string(),
-                    "quantmax" => "Maximum quantity of the item to spawn in a stack. Eg. Ammunition stack quantity.".to_string(),
-                    "cost" => "Loot spawning prioritizer - no one really knows what this does exactly. :D".to_string(),
-                    "category" | "usage" | "tag" => "The location class of where this item can spawn.".to_string(),
-                    "flags" => "".to_string(),
-                    _ => "Unknown field - open a github issue with the field name.".to_string()
-                }
-            }
-            FieldKey::Attribute { attr, .. } => {
-                match attr.as_str() {
-                    "count_in_cargo" => "Boolean flag. Sets the total amount that can spawn (map wide) in cargo (tents, boxes, vehicles).\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "count_in_hoarder" => "Boolean flag. Sets the total amount that can spawn (map wide) in Zombies.\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "count_in_map" => "Boolean flag. Sets the total amount that can spawn on the map.\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "count_in_player" => "Boolean flag. Sets the total amount that can spawn (map wide) on players.\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "crafted" => "Boolean flag. Sets the total amount based on crafted count (map wide)\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "deloot" => "Boolean flag. Sets the total amount (map wide) from dynamic events. E.g Helicopter crashes etc.\nIf flag is set to 1, item won't spawn if there are already a nominal number of items for this flag.".to_string(),
-                    "name" => "The location class of where this item can spawn.".to_string(),
-                    _ => {
-                        format!("Unknown attribute - open a github issue. {}", attr)
-                    },
+            EditorJob::DryRun(job) => job.cancel(),
+            EditorJob::Write(_, job) => job.cancel(),
+            EditorJob::Read(_, _, job) => {
+                job.cancel();
+                return String::from("Cancelling the download");
+            }
+            EditorJob::Upload(..) => {
+                return String::from("A started save runs to the end; the file is replaced only once it is complete");
+            }
+            EditorJob::Rehearse(job) => {
+                job.cancel();
+                return String::from("Cancelling the dry run");
+            }
+            EditorJob::Reload(job) => {
+                job.cancel();
+                return String::from("Cancelling the reload");
+            }
+        }
+        String::from("Cancelling; files already written keep their .bak")
+    }
+}
+
+/// What opening a file reads: the file itself and the optional files next to it.
+struct Sources {
+    content: String,
+    project_content: Option<String>,
+    /// `help.toml` next to the file.
+    help_content: Option<String>,
+    /// The edit attribution sidecar, `<stem>.notes.toml`.
+    notes_content: Option<String>,
+    limits_content: Option<String>,
+    remote_config: Option<RemoteConfig>,
+}
+
+/// `file` next to the types file at `path`, then one directory up (vanilla keeps types.xml
+/// in `db/` and the other CE files in the mission folder).
+fn beside_or_above(path: &Path, file: &str) -> Vec<PathBuf> {
+    let dir = path.parent();
+    dir.into_iter().chain(dir.and_then(|d| d.parent())).map(|d| d.join(file)).collect()
+}
+
+/// cfgspawnabletypes.xml as last read (`None` when there was none), with the stamps of the
+/// types file and of it at the time.
+struct SpawnableCache {
+    stamps: (Option<FileStamp>, Option<FileStamp>),
+    spawnables: Option<Spawnables>,
+}
+
+impl Sources {
+    /// Reads `selection` and its neighbours; `progress(done, total)` follows the main file
+    /// in bytes and cancels the read by returning `false`.
+    fn read(selection: &FileSelection, progress: &mut dyn FnMut(u64, u64) -> bool) -> io::Result<Self> {
+        let dir = selection.path.parent();
+        let project_path = dir.map(|dir| dir.join(PROJECT_FILE));
+        let help_path = dir.map(|dir| dir.join(HELP_FILE));
+        let notes_path = annotations::notes_path(&selection.path);
+        let limits_paths = beside_or_above(&selection.path, tiers::LIMITS_FILE);
+        Ok(match &selection.source {
+            FileSource::Local => Sources {
+                content: fs::read_to_string(&selection.path)?,
+                project_content: project_path.as_ref().and_then(|p| fs::read_to_string(p).ok()),
+                help_content: help_path.as_ref().and_then(|p| fs::read_to_string(p).ok()),
+                notes_content: fs::read_to_string(&notes_path).ok(),
+                limits_content: limits_paths.iter().find_map(|p| fs::read_to_string(p).ok()),
+                remote_config: None,
+            },
+            FileSource::Remote(client) => {
+                let client = client.lock().map_err(|_| io::Error::other("SSH backend in use"))?;
+                Sources {
+                    content: client.read_file_with_progress(&selection.path, progress)?,
+                    project_content: project_path.as_ref().and_then(|p| client.read_file(p).ok()),
+                    help_content: help_path.as_ref().and_then(|p| client.read_file(p).ok()),
+                    notes_content: client.read_file(&notes_path).ok(),
+                    limits_content: limits_paths.iter().find_map(|p| client.read_file(p).ok()),
+                    remote_config: Some(client.config().clone()),
                 }
             }
+        })
+    }
+}
+
+/// What finishing a remote save needs once the upload is through.
+struct Upload {
+    path: PathBuf,
+    hash: [u8; 32],
+    size: u64,
+    changes: TypesDiff,
+    /// The inline changelog as uploaded, kept once the upload succeeds.
+    changelog: Vec<String>,
+    /// `q` → save from the unsaved-changes prompt: close the file when the save succeeds.
+    close_after: bool,
+}
+
+/// What went wrong alongside a remote save that went through.
+struct Uploaded {
+    /// Why the backup failed, when it did.
+    backup_failure: Option<String>,
+    /// Why the notes sidecar wasn't written, when it wasn't.
+    notes_failure: Option<String>,
+}
+
+/// Side-by-side editor for a type's quantmin/quantmax pair.
+struct QuantityTool {
+    min_field: usize,
+    max_field: usize,
+    min_input: String,
+    max_input: String,
+    unused: bool,
+    /// Which of the two inputs receives typed digits.
+    on_max: bool,
+}
+
+/// Tier checkboxes for one type or the whole multi-selection.
+struct TierTool {
+    targets: Vec<usize>,
+    rows: Vec<TierRow>,
+    cursor: usize,
+}
+
+/// The six flags of every marked type; rows scroll, one column is highlighted for `+`/`-`.
+struct FlagMatrix {
+    targets: Vec<usize>,
+    column: usize,
+    scroll: usize,
+}
+
+/// One end of an `e` value exchange: a field of a type, the name kept to find the type
+/// again if others were added or removed meanwhile.
+#[derive(Clone, Debug, PartialEq)]
+struct ExchangeEnd {
+    type_idx: usize,
+    type_name: String,
+    key: FieldKey,
+}
+
+struct TierRow {
+    name: String,
+    /// How many targets carry the tier when the tool opened.
+    have: usize,
+    /// `Some` once toggled: the state to apply to every target.
+    set: Option<bool>,
+}
+
+impl QuantityTool {
+    fn check(&self) -> Result<(i64, i64), String> {
+        if self.unused {
+            return Ok((-1, -1));
+        }
+        let min = self.min_input.parse().map_err(|_| String::from("quantmin must be a whole number"))?;
+        let max = self.max_input.parse().map_err(|_| String::from("quantmax must be a whole number"))?;
+        validation::check_quantity_pair(min, max).map(|_| (min, max))
+    }
+}
+
+/// Edits that clash with changes made to the file on disk, shown before a merged save.
+struct ConflictReview {
+    /// The file as found on disk when saving, raw and parsed.
+    on_disk: String,
+    theirs: Vec<TypeEntry>,
+    conflicts: Vec<Conflict>,
+    cursor: usize,
+    /// One-line summaries of each side's changes since the file was loaded.
+    their_changes: String,
+    my_changes: String,
+}
+
+/// An element staged by the guided add; nothing touches the model until it is committed.
+struct PendingAdd {
+    targets: Vec<usize>,
+    step: AddStep,
+    name: String,
+    text: Option<String>,
+    attrs: Vec<(String, String)>,
+    /// Why the last Enter was refused, shown under the prompt.
+    error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AddStep {
+    ElementName,
+    Text,
+    AttrName,
+    /// Waiting for the value of the last entry in `attrs`.
+    AttrValue,
+}
+
+impl PendingAdd {
+    /// The staged element as it will be written.
+    fn preview(&self) -> String {
+        if self.name.is_empty() {
+            return String::from("(no element yet)");
+        }
+        let attrs: String = self.attrs.iter().map(|(name, value)| format!(" {name}=\"{value}\"")).collect();
+        match &self.text {
+            Some(text) => format!("<{}{attrs}>{text}</{}>", self.name, self.name),
+            None => format!("<{}{attrs} />", self.name),
+        }
+    }
+
+    /// The fields to insert into a type whose next free index for the element is `index`.
+    fn fields(&self, index: usize) -> Vec<Field> {
+        let mut fields = Vec::new();
+        if self.text.is_some() || self.attrs.is_empty() {
+            fields.push(Field {
+                key: FieldKey::Element {
+                    name: self.name.clone(),
+                    index,
+                },
+                value: self.text.clone().unwrap_or_default(),
+            });
+        }
+        fields.extend(self.attrs.iter().map(|(attr, value)| Field {
+            key: FieldKey::Attribute {
+                element: self.name.clone(),
+                index,
+                attr: attr.clone(),
+            },
+            value: value.clone(),
+        }));
+        fields
+    }
+}
+
+struct BackupBrowser {
+    backups: Vec<BackupFile>,
+    cursor: usize,
+    /// What restoring the highlighted backup would change; empty until previewed.
+    preview: Vec<String>,
+}
+
+/// Redundant elements found by `:dedupe`, shown for review before removal.
+struct DuplicateReview {
+    found: Vec<Duplicate>,
+    cursor: usize,
+}
+
+/// Checklist of the source type's fields to copy onto the multi-selection.
+struct PropagateTool {
+    source: usize,
+    groups: Vec<PropagateGroup>,
+    cursor: usize,
+}
+
+/// All fields of one `(element, index)`, so e.g. the six flags attributes travel together.
+struct PropagateGroup {
+    label: String,
+    fields: Vec<Field>,
+    checked: bool,
+}
+
+/// State of the nominal distribution / capping overlay.
+struct CapTool {
+    scope: Vec<usize>,
+    stats: NominalStats,
+    cap_input: String,
+    scale_min: bool,
+    /// Scale quantmin/quantmax along too, for ammo and other stackables.
+    scale_quantity: bool,
+    /// Set once the cap was applied; the overlay then lists what changed.
+    applied: Option<Vec<CapChange>>,
+}
+
+/// A place in the jump list. The name is what counts; the index only settles which of
+/// several same-named types it was, and where the type stood before others were deleted.
+#[derive(Clone, PartialEq, Eq)]
+struct Jump {
+    type_name: String,
+    type_idx: usize,
+    field: usize,
+}
+
+/// One step of undo or redo history: how to get the model back to before `description`
+/// (on the redo stack, to after it), and where the selection was.
+struct UndoEntry {
+    description: String,
+    change: Change,
+    selected_type: usize,
+    selected_field: usize,
+}
+
+/// What an undo entry restores. `push_undo` runs before an edit, when its outcome isn't
+/// known yet, so the newest undo entry starts as a snapshot; the next entry turns it into
+/// the span of types the edit touched, keeping one full copy of the model in the history.
+enum Change {
+    Snapshot(Vec<TypeEntry>),
+    Splice(Splice),
+}
+
+/// Puts `types` back in place of `len` types at `start`, against the model as it is when
+/// the entry is at the top of its stack.
+struct Splice {
+    start: usize,
+    len: usize,
+    types: Vec<TypeEntry>,
+}
+
+impl Splice {
+    /// The splice that turns `now` into `then`: everything between their common start and end.
+    fn between(now: &[TypeEntry], then: &[TypeEntry]) -> Self {
+        let start = now.iter().zip(then).take_while(|(a, b)| a == b).count();
+        let end = now[start..].iter().rev().zip(then[start..].iter().rev()).take_while(|(a, b)| a == b).count();
+        Self {
+            start,
+            len: now.len() - start - end,
+            types: then[start..then.len() - end].to_vec(),
+        }
+    }
+
+    /// Applies the splice to `types`, returning the one that undoes it.
+    fn apply(self, types: &mut Vec<TypeEntry>) -> Self {
+        let start = self.start.min(types.len());
+        let end = (start + self.len).min(types.len());
+        let len = self.types.len();
+        let replaced = types.splice(start..end, self.types).collect();
+        Self { start, len, types: replaced }
+    }
+}
+
+impl Change {
+    /// Applies the change to `types`, returning the one that takes it back.
+    fn apply(self, types: &mut Vec<TypeEntry>) -> Self {
+        match self {
+            Change::Snapshot(then) => {
+                let back = Splice::between(&then, types);
+                *types = then;
+                Change::Splice(back)
+            }
+            Change::Splice(splice) => Change::Splice(splice.apply(types)),
+        }
+    }
+}
+
+/// The last committed field edit, which `.` applies again to the highlighted type.
+#[derive(Clone, Debug)]
+enum LastEdit {
+    /// Fields set to these values: a typed value, copied values or a quantity pair.
+    /// Fields the type lacks are added.
+    Values(Vec<Field>),
+    /// A value typed as `=expression`, worked out again on the type it's repeated on;
+    /// `field` is added as it was when the type lacks it.
+    Expression { field: Field, expression: String },
+    /// A column set in the `:flags` matrix.
+    Flag { flag: &'static str, on: bool },
+    /// Two fields of one type exchanged with `e`.
+    Swap(FieldKey, FieldKey),
+    /// `:apply`, aimed at the highlighted type instead of its pattern.
+    Bulk(BulkEdit),
+    /// `:addusage`, `:addtag` or `:setcategory`.
+    Assign { element: String, value: String },
+    /// Tiers ticked (`true`) or unticked in the tier checkboxes.
+    Tiers(Vec<(String, bool)>),
+    Cap { cap: i64, scale_min: bool, scale_quantity: bool },
+}
+
+impl LastEdit {
+    /// `lifetime = 14400`, for the status once it's repeated.
+    fn describe(&self) -> String {
+        match self {
+            Self::Values(fields) => fields
+                .iter()
+                .map(|f| format!("{} = {}", f.key.label(), f.value))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Self::Expression { field, expression } => format!("{} = {expression}", field.key.label()),
+            Self::Flag { flag, on } => format!("{flag}={}", u8::from(*on)),
+            Self::Swap(a, b) => format!("{} ⇄ {}", a.label(), b.label()),
+            Self::Bulk(edit) => match &edit.op {
+                BulkOp::Set(value) => format!("{} = {value}", edit.field),
+                BulkOp::Scale(factor) => format!("{} *{factor}", edit.field),
+            },
+            Self::Assign { element, value } => format!("{element} {value}"),
+            Self::Tiers(changes) => changes
+                .iter()
+                .map(|(tier, on)| format!("{}{tier}", if *on { '+' } else { '-' }))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Self::Cap { cap, .. } => format!("nominal cap {cap}"),
+        }
+    }
+}
+
+/// Evaluates `expression` with `fields`' element values as its variables, formatted as a value.
+fn eval_over(fields: &[Field], expression: &str) -> Result<String, String> {
+    let lookup = |name: &str| {
+        fields
+            .iter()
+            .find(|f| matches!(&f.key, FieldKey::Element { name: n, .. } if n == name))
+            .and_then(|f| f.value.trim().parse().ok())
+    };
+    expr::eval(expression, lookup).map(expr::format_number)
+}
+
+/// `dryrun::rehearse` with `normalize_names` applied to what reads back; for worker threads.
+fn rehearse(types: &[TypeEntry], xml: &str, scratch: &Path, normalize: bool) -> io::Result<Rehearsal> {
+    dryrun::rehearse(types, xml, scratch, |types| {
+        if normalize {
+            model::normalize_name_elements(types);
         }
+    })
+}
+
+/// What the Types pane shows after each name, cycled with `V`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum InlineValues {
+    Off,
+    Nominal,
+    NominalMin,
+}
+
+/// Which rows the Fields pane leaves out, cycled with `z`. The selected field is always shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldFilter {
+    All,
+    /// Fields with an empty value, as a skeleton type has many of.
+    Empty,
+    /// Those, and `flags` attributes at 0 where 0 is also the default.
+    EmptyAndDefaultFlags,
+}
+
+/// State of the table view toggled with `T`; `selected_type` stays the selected row.
+struct TableView {
+    column: usize,
+    /// Sorted column and whether it sorts descending.
+    sort: Option<(usize, bool)>,
+    /// First column shown when the terminal is too narrow for all of them.
+    first_column: usize,
+}
+
+/// Restricts which types the Types pane shows; `selected_type` always stays an index into `types`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TypeFilter {
+    ChangedThisSession,
+}
+
+/// The category picked in the categories pane; combines with `type_filter`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum CategoryFilter {
+    Named(String),
+    /// Types without a `category` element (or with an empty name).
+    Uncategorized,
+}
+
+impl CategoryFilter {
+    fn of(ty: &TypeEntry) -> Self {
+        ty.fields
+            .iter()
+            .find(|f| matches!(&f.key, FieldKey::Attribute { element, attr, .. } if element == "category" && attr == "name"))
+            .map(|f| f.value.trim())
+            .filter(|name| !name.is_empty())
+            .map_or(Self::Uncategorized, |name| Self::Named(name.to_string()))
     }
 
-    pub fn get_element_name(&self) -> &str {
+    fn label(&self) -> &str {
         match self {
-            FieldKey::Element { name, .. } => name.as_str(),
-            FieldKey::Attribute { element, .. } => element.as_str(),
+            Self::Named(name) => name,
+            Self::Uncategorized => "<none>",
         }
     }
 }
 
+pub struct Editor {
+    path: Option<PathBuf>,
+    source: FileSource,
+    types: Vec<TypeEntry>,
+    selected_type: usize,
+    selected_field: usize,
+    focus: EditorFocus,
+    editing_target: Option<EditTarget>,
+    input_buffer: String,
+    /// Where typing goes in `input_buffer` (`line_edit`); `None` at the end.
+    input_cursor: Option<usize>,
+    status: String,
+    overlay: Option<Overlay>,
+    /// F1 over an overlay: its keys are shown on top of it until Esc or F1 again.
+    overlay_help: bool,
+    remote_config: Option<RemoteConfig>,
+    remote_alive: bool,
+    last_liveness_check: Instant,
+    /// The remote file's stamp once the check is through; `None` means the session is gone.
+    liveness_rx: Option<Receiver<Option<FileStamp>>>,
+    reconnect_rx: Option<Receiver<ConnectResult>>,
+    /// Edits per type name since the file was loaded; kept across saves.
+    session_changes: HashMap<String, usize>,
+    type_filter: Option<TypeFilter>,
+    show_categories: bool,
+    category_filter: Option<CategoryFilter>,
+    /// Highlighted row of the categories pane; row 0 is "all types".
+    category_cursor: usize,
+    /// Current validation findings, ordered by type then field.
+    issues: Vec<Issue>,
+    undo_stack: VecDeque<UndoEntry>,
+    redo_stack: Vec<UndoEntry>,
+    /// Types marked with Space for multi-type operations.
+    selected_types: TypeSelection,
+    /// The model as last loaded or saved; drives the modified markers.
+    baseline: Vec<TypeEntry>,
+    baseline_index: HashMap<String, usize>,
+    /// Pane to go back to once a `:` command line is closed.
+    command_return_focus: EditorFocus,
+    /// Notable status messages of this session, oldest first.
+    messages: StatusLog,
+    /// Non-fatal oddities found while parsing the current file.
+    parse_warnings: Vec<ParseWarning>,
+    /// Line endings the current file was read with.
+    line_ending: LineEnding,
+    /// Actions captured since `m` started recording.
+    recording: Option<Vec<Action>>,
+    /// The last recorded macro, replayed with `@`.
+    macro_actions: Vec<Action>,
+    /// Digits typed before `@`, as in vim's `5@q`.
+    count_prefix: Option<usize>,
+    /// Set while a macro replays so its edits collapse into one undo step.
+    replaying: bool,
+    /// `Some` while the table view replaces the panes.
+    table: Option<TableView>,
+    user_config: ConfigLayer,
+    cli_config: ConfigLayer,
+    /// Settings for the open file: the layers above merged with its project file, if any.
+    config: Config,
+    /// The project file merged into `config`, for `:about`.
+    project_config_path: Option<PathBuf>,
+    /// Its settings, kept so `:setup` can merge new user settings the same way.
+    project_config: Option<ConfigLayer>,
+    /// Value flags declared in the mission's cfglimitsdefinition.xml, if one was found.
+    limit_tiers: Vec<String>,
+    /// The mission's cfgspawnabletypes.xml, read on first use; see `spawnables()`.
+    spawnables: Option<SpawnableCache>,
+    /// The map the file's mission folder names, for the tier tables in Tips.
+    mission_map: Option<String>,
+    /// Edits and saves are refused; set by `--readonly`, config, a remote profile or `:readonly`.
+    readonly: bool,
+    /// Hash of the file as last loaded or saved; a mismatch at save time means someone else wrote it.
+    disk_hash: Option<[u8; 32]>,
+    /// Its size in bytes then; with the baseline's type count, what the shrink guard compares to.
+    disk_bytes: u64,
+    /// Free space expected after the save in progress, for its status line.
+    free_after_save: Option<u64>,
+    /// Free-space lookup for the low-space check: `FileSource::free_space`, or a fixed figure in tests.
+    free_space: fn(&FileSource, &Path) -> Option<u64>,
+    /// Where dry runs write their temporary file: the system's temp dir, or elsewhere in tests.
+    scratch_dir: PathBuf,
+    /// Inline changelog entries in the file as last loaded or saved, oldest first.
+    changelog: Vec<String>,
+    /// Other comments in the file as last loaded; the next save drops them.
+    other_comments: usize,
+    /// The BOM and whitespace the file started with; written back unless `clean_start`.
+    leading: String,
+    /// Size and mtime of the file when last checked; `None` until the next check.
+    disk_stamp: Option<FileStamp>,
+    last_watch_check: Instant,
+    /// Someone else wrote the file; the header says so until it's reloaded or dismissed.
+    disk_changed: bool,
+    /// Reference value ranges for the Tips pane.
+    hints: Baseline,
+    /// The server's classnames, when configured or loaded with `:classnames`.
+    classnames: Option<Classnames>,
+    /// Field explanations for the Tips pane, built in plus `help.toml` files.
+    help: FieldHelp,
+    /// Who last changed which field, from the notes sidecar.
+    notes: Annotations,
+    /// The sidecar exists or something was recorded, so saves write it.
+    notes_kept: bool,
+    /// The sidecar didn't parse; it's left alone and nothing is recorded.
+    notes_broken: bool,
+    /// Set once `q` may go back to the file picker; read by the main loop.
+    close_requested: bool,
+    /// `:setup` was run; the main loop shows the wizard.
+    setup_requested: bool,
+    /// `:apply` arguments waiting for the directory `?` asked the picker for.
+    apply_awaiting_dir: Option<Vec<String>>,
+    /// Set with `apply_awaiting_dir`; main takes it and opens the picker.
+    directory_requested: bool,
+    /// A remote file that failed to download or was cancelled; main goes back to the picker.
+    load_failure: Option<String>,
+    /// `--goto`/`--field`: the type (and field) the next file to load opens at.
+    start_at: Option<(String, Option<String>)>,
+    inline_values: InlineValues,
+    field_filter: FieldFilter,
+    /// The status set by `fail`, shown in red while it is still the current status.
+    error_status: Option<String>,
+    /// A rejected type name Enter was pressed on once; pressing it again commits it anyway.
+    name_override: Option<String>,
+    /// One entry per type, kept in step with `types` by `revalidate`/`revalidate_type`.
+    summaries: Vec<TypeSummary>,
+    /// Pane widths in percent, adjusted with Ctrl+Left/Right and remembered in the session file.
+    panes: [u16; 3],
+    /// Values committed this session per field label, most recent first.
+    recent_values: HashMap<String, VecDeque<String>>,
+    /// Highlighted quick-pick entry while editing a value; `None` once free text is typed.
+    recent_pick: Option<usize>,
+    /// A long operation running in the background; input waits until it's done or cancelled.
+    job: Option<EditorJob>,
+    /// Places left by goto, issue navigation and filtering, oldest first, for Ctrl+O/Ctrl+I.
+    jumps: Vec<Jump>,
+    /// Position in `jumps` while moving through it; `jumps.len()` when not.
+    jump_pos: usize,
+    /// Recent `:calc` expressions and their results, newest last.
+    calc_history: VecDeque<(String, String)>,
+    /// A calculator result waiting to pre-fill the next value edit.
+    calc_carry: Option<String>,
+    /// Set by every path that commits a field edit; kept across files.
+    last_edit: Option<LastEdit>,
+    /// The bulk commands' last arguments by command (`addusage` → `Military Ammo*`): a
+    /// bare command offers them again, and `:again` repeats `last_bulk` with them.
+    bulk_args: BTreeMap<String, String>,
+    last_bulk: Option<String>,
+    /// The command line a bare bulk command filled in, to tell when it runs unchanged.
+    bulk_prefill: Option<String>,
+    /// Said once the `:apply` dry run that reused earlier arguments finishes.
+    bulk_note: Option<String>,
+    /// The field `e` marked, until `e` on another one swaps their values.
+    exchange: Option<ExchangeEnd>,
+    /// The `:tutorial` panel, until it is closed or another file opens.
+    tutorial: Option<Tutorial>,
+    /// The tutorial's sample is open: it has no path, and saving asks where to write a copy.
+    sample: bool,
+}
+
+
 impl Editor {
-    pub fn new() -> Self {
+    pub fn new(user_config: ConfigLayer, cli_config: ConfigLayer) -> Self {
+        let config = Config::resolve(&user_config, None, &cli_config);
+        let mut session = Session::load();
+        if !config.remember_bulk {
+            session.bulk.clear();
+            session.last_bulk = None;
+        }
         Self {
             path: None,
             source: FileSource::Local,
@@ -124,752 +1016,7548 @@ impl Editor {
             focus: EditorFocus::TypeList,
             editing_target: None,
             input_buffer: String::new(),
+            input_cursor: None,
             status: String::from("Load a file to begin"),
+            overlay: None,
+            overlay_help: false,
+            remote_config: None,
+            remote_alive: true,
+            last_liveness_check: Instant::now(),
+            liveness_rx: None,
+            reconnect_rx: None,
+            session_changes: HashMap::new(),
+            type_filter: None,
+            show_categories: false,
+            category_filter: None,
+            category_cursor: 0,
+            issues: Vec::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            selected_types: TypeSelection::default(),
+            baseline: Vec::new(),
+            baseline_index: HashMap::new(),
+            command_return_focus: EditorFocus::TypeList,
+            messages: StatusLog::new(MESSAGE_LIMIT),
+            parse_warnings: Vec::new(),
+            line_ending: LineEnding::Lf,
+            recording: None,
+            macro_actions: Vec::new(),
+            count_prefix: None,
+            replaying: false,
+            table: None,
+            user_config,
+            cli_config,
+            readonly: config.readonly,
+            project_config_path: None,
+            project_config: None,
+            config,
+            limit_tiers: Vec::new(),
+            spawnables: None,
+            mission_map: None,
+            disk_hash: None,
+            disk_bytes: 0,
+            free_after_save: None,
+            free_space: FileSource::free_space,
+            scratch_dir: env::temp_dir(),
+            changelog: Vec::new(),
+            other_comments: 0,
+            leading: String::new(),
+            disk_stamp: None,
+            last_watch_check: Instant::now(),
+            disk_changed: false,
+            hints: Baseline::vanilla(),
+            classnames: None,
+            help: FieldHelp::embedded(),
+            notes: Annotations::default(),
+            notes_kept: false,
+            notes_broken: false,
+            close_requested: false,
+            setup_requested: false,
+            apply_awaiting_dir: None,
+            directory_requested: false,
+            load_failure: None,
+            start_at: None,
+            inline_values: InlineValues::Off,
+            field_filter: FieldFilter::All,
+            name_override: None,
+            error_status: None,
+            summaries: Vec::new(),
+            recent_values: HashMap::new(),
+            recent_pick: None,
+            job: None,
+            jumps: Vec::new(),
+            jump_pos: 0,
+            calc_history: VecDeque::new(),
+            calc_carry: None,
+            last_edit: None,
+            bulk_args: session.bulk,
+            last_bulk: session.last_bulk,
+            bulk_prefill: None,
+            bulk_note: None,
+            exchange: None,
+            tutorial: None,
+            sample: false,
+            panes: session.panes.filter(|p| valid_panes(*p)).unwrap_or(DEFAULT_PANES),
         }
     }
 
-    pub fn load(&mut self, selection: FileSelection) -> io::Result<()> {
-        let content = match &selection.source {
-            FileSource::Local => fs::read_to_string(&selection.path)?,
-            FileSource::Remote(client) => {
-                let client = client.lock().map_err(|_| io::Error::new(io::ErrorKind::Other, "SSH backend in use"))?;
-                client.read_file(&selection.path)?
-            }
-        };
-        let types = parse_types(&content)
+    pub fn load(&mut self, selection: FileSelection, mode: LoadMode) -> io::Result<()> {
+        let sources = Sources::read(&selection, &mut |_, _| true)?;
+        self.open_sources(selection, sources, None, mode)
+    }
+
+    /// Opens local files at once; remote ones download on a worker thread behind a
+    /// progress bar that Esc cancels. A failed download shows up in `take_load_failure`.
+    pub fn start_load(&mut self, selection: FileSelection, mode: LoadMode) -> io::Result<()> {
+        if matches!(selection.source, FileSource::Local) {
+            return self.load(selection, mode);
+        }
+        let worker_selection = selection.clone();
+        let label = selection.path.display().to_string();
+        let title = format!("Downloading {}", selection.short_label());
+        let job = progress::spawn(title, move |progress| {
+            Sources::read(&worker_selection, &mut |done, total| {
+                progress.step(done as usize, total as usize, label.clone())
+            })
+        });
+        self.job = Some(EditorJob::Read(selection, mode, job.in_bytes()));
+        Ok(())
+    }
+
+    /// Why the last `start_load` download didn't open a file, once.
+    pub fn take_load_failure(&mut self) -> Option<String> {
+        self.load_failure.take()
+    }
+
+    /// `transfer` is the size and duration of the download, for the message log.
+    fn open_sources(
+        &mut self,
+        selection: FileSelection,
+        sources: Sources,
+        transfer: Option<(u64, Duration)>,
+        mode: LoadMode,
+    ) -> io::Result<()> {
+        let Sources { content, project_content, help_content, notes_content, limits_content, remote_config } = sources;
+        // Parsed before anything is replaced, so a file that doesn't parse leaves the open
+        // one, its config and its notes as they were.
+        let (project, project_note) = ConfigLayer::load_project(project_content.as_deref());
+        let config = Config::resolve(&self.user_config, project.as_ref(), &self.cli_config);
+        let started = Instant::now();
+        let (mut types, report) = parse_types(&content)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("XML parse error: {}", e)))?;
+        let normalized = if config.normalize_names { model::normalize_name_elements(&mut types) } else { 0 };
+        let elapsed = started.elapsed();
 
+        self.project_config_path = project.as_ref().and(selection.path.parent().map(|dir| dir.join(PROJECT_FILE)));
+        self.limit_tiers = limits_content.map(|c| tiers::parse_limits(&c)).unwrap_or_default();
+        self.spawnables = None;
+        self.mission_map = tiers::detect_map(&selection.path);
+        self.tutorial = None;
+        self.sample = false;
+        self.config = config;
+        self.project_config = project;
+        let hints_note = self.load_hints();
+        let classnames_note = self.load_classnames();
+        let help_notes = self.load_help(help_content);
+        let notes_note = self.load_notes(notes_content);
+        let profile_readonly = remote_config.as_ref().is_some_and(|c: &RemoteConfig| c.readonly);
+        self.readonly = self.readonly || self.config.readonly || profile_readonly;
+        self.remember_disk(merge::content_hash(&content), content.len() as u64);
         self.path = Some(selection.path);
         self.source = selection.source;
         self.types = types;
+        self.line_ending = report.line_ending;
+        self.changelog = report.changelog;
+        self.other_comments = report.other_comments;
+        self.leading = format!("{}{}", if report.has_bom { "\u{feff}" } else { "" }, report.leading_whitespace);
+        self.remote_config = remote_config;
+        self.reset_file_state();
+        self.reset_baseline();
+        self.revalidate();
+        let view_notes = match mode {
+            LoadMode::Fresh => {
+                self.reset_view();
+                Vec::new()
+            }
+            LoadMode::KeepView => self.reapply_view(),
+        };
+
+        let fields: usize = self.types.iter().map(|t| t.fields.len()).sum();
+        let unnamed = self.types.iter().filter(|t| t.name.trim().is_empty()).count();
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        for ty in &self.types {
+            *names.entry(ty.name.as_str()).or_insert(0) += 1;
+        }
+        let duplicates = names.values().filter(|&&n| n > 1).count();
+        let mut summary = format!(
+            "Loaded {} types, {} fields in {} ms ({}{}, {}, <{}>)",
+            self.types.len(),
+            fields,
+            elapsed.as_millis(),
+            report.encoding,
+            if report.has_bom { " with BOM" } else { "" },
+            report.line_ending.name(),
+            report.root
+        );
+        let saved_ending = self.config.line_endings.resolve(report.line_ending);
+        if saved_ending != report.line_ending {
+            summary.push_str(&format!("; saves convert to {} (line_endings)", saved_ending.name()));
+        } else if report.mixed_line_endings {
+            summary.push_str(&format!("; mixed line endings, saves write {}", saved_ending.name()));
+        }
+        let start = match (report.has_bom, report.leading_whitespace.is_empty()) {
+            (true, true) => Some("a BOM"),
+            (true, false) => Some("a BOM and whitespace"),
+            (false, false) => Some("whitespace"),
+            (false, true) => None,
+        };
+        if let Some(start) = start {
+            let fate = if self.config.clean_start { "saves remove it" } else { "saves keep it" };
+            summary.push_str(&format!("; starts with {start} before the XML, {fate} (clean_start)"));
+        }
+        if unnamed > 0 {
+            summary.push_str(&format!("; {unnamed} without a name"));
+        }
+        if duplicates > 0 {
+            summary.push_str(&format!("; {duplicates} duplicate names"));
+        }
+        let bad_names = self
+            .types
+            .iter()
+            .filter(|t| !t.name.trim().is_empty() && validation::check_type_name(&t.name).is_err())
+            .count();
+        if bad_names > 0 {
+            summary.push_str(&format!("; {bad_names} names DayZ will reject (v)"));
+        }
+        if !report.warnings.is_empty() {
+            summary.push_str(&format!("; {} parse warnings (:warnings)", report.warnings.len()));
+        }
+        if self.other_comments > 0 {
+            summary.push_str(&format!("; {} saves won't keep", utils::plural(self.other_comments, "comment")));
+        }
+        if normalized > 0 {
+            summary.push_str(&format!("; {normalized} text-form category/usage/tag read as name attributes"));
+        }
+        if let Some(note) = project_note {
+            summary.push_str(&format!(" ({note})"));
+        }
+        if let Some(note) = hints_note {
+            summary.push_str(&format!(" ({note})"));
+        }
+        if let Some(note) = classnames_note {
+            summary.push_str(&format!(" ({note})"));
+        }
+        for note in help_notes {
+            summary.push_str(&format!(" ({note})"));
+        }
+        if let Some(note) = notes_note {
+            summary.push_str(&format!(" ({note})"));
+        }
+        for note in view_notes {
+            summary.push_str(&format!("; {note}"));
+        }
+        if self.readonly {
+            summary.push_str(" [read-only]");
+        }
+        if let Some((bytes, elapsed)) = transfer {
+            summary.push_str(&format!("; downloaded {}", progress::transfer_done(bytes, elapsed)));
+        }
+        self.log(format!("{}: {}", self.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(), summary));
+        for warning in &report.warnings {
+            self.messages.detail(format!("  line {}: {}", warning.line, warning.message));
+        }
+        self.parse_warnings = report.warnings;
+        self.status = summary;
+        if let Some((name, field)) = self.start_at.take() {
+            self.go_to_start(&name, field.as_deref());
+        }
+        Ok(())
+    }
+
+    /// Forgets what belonged to the previous file: the cursor, any input or overlay, the
+    /// remote session checks, the session's changes, undo and the marks.
+    fn reset_file_state(&mut self) {
         self.selected_type = 0;
         self.selected_field = 0;
         self.focus = EditorFocus::TypeList;
         self.editing_target = None;
         self.input_buffer.clear();
-        self.status = String::from("Loaded file");
-        Ok(())
+        self.overlay = None;
+        self.remote_alive = true;
+        self.last_liveness_check = Instant::now();
+        self.liveness_rx = None;
+        self.reconnect_rx = None;
+        self.session_changes.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.selected_types.clear();
+        self.exchange = None;
     }
 
-    pub fn is_editing(&self) -> bool {
-        self.focus == EditorFocus::Editing
+    /// The view as a new editor has it. Pane widths stay: they are remembered across runs.
+    fn reset_view(&mut self) {
+        self.type_filter = None;
+        self.show_categories = false;
+        self.category_filter = None;
+        self.category_cursor = 0;
+        self.table = None;
+        self.inline_values = InlineValues::Off;
+        self.field_filter = FieldFilter::All;
     }
 
-    pub fn handle_action(&mut self, action: Action) -> io::Result<()> {
-        match self.focus {
-            EditorFocus::Editing => {
-                match action {
-                    Action::Input(c) => self.input_buffer.push(c),
-                    Action::Backspace => {
-                        self.input_buffer.pop();
-                    }
-                    Action::Activate => {
-                        let continue_editing = self.apply_input();
-                        if !continue_editing {
-                            self.stop_editing();
-                        }
-                    }
-                    Action::Cancel => {
-                        self.input_buffer.clear();
-                        self.stop_editing();
-                        self.status = String::from("Edit cancelled");
-                    }
-                    _ => {}
+    /// Re-applies the filters to freshly loaded types, dropping those that would leave the
+    /// Types pane empty; returns a note for each dropped one.
+    fn reapply_view(&mut self) -> Vec<String> {
+        let mut notes = Vec::new();
+        // Nothing has changed in a file just opened, so this filter would hide every type.
+        if self.type_filter.take() == Some(TypeFilter::ChangedThisSession) {
+            notes.push(String::from("the changed-types filter (f) was cleared, nothing has changed in this file yet"));
+        }
+        if let Some(category) = &self.category_filter
+            && !self.types.iter().any(|ty| CategoryFilter::of(ty) == *category)
+        {
+            notes.push(format!("no type is in {}, showing all categories", category.label()));
+            self.category_filter = None;
+        }
+        let rows = self.category_rows();
+        self.category_cursor = match &self.category_filter {
+            Some(category) => rows.iter().position(|(c, _)| c == category).map_or(0, |i| i + 1),
+            None => 0,
+        };
+        self.clamp_selection_to_view();
+        notes
+    }
+
+    /// `:tutorial`: opens the embedded sample in place of the file and shows the steps.
+    fn start_tutorial(&mut self) {
+        if self.types != self.baseline && !self.sample {
+            self.fail(String::from("Save or undo your changes first; :tutorial replaces the open file"));
+            return;
+        }
+        let selection = FileSelection {
+            path: PathBuf::from("tutorial_types.xml"),
+            source: FileSource::Local,
+        };
+        let sources = Sources {
+            content: tutorial::SAMPLE.to_string(),
+            project_content: None,
+            help_content: None,
+            notes_content: None,
+            limits_content: None,
+            remote_config: None,
+        };
+        if let Err(err) = self.open_sources(selection, sources, None, LoadMode::Fresh) {
+            self.fail(format!("Tutorial sample failed to load: {err}"));
+            return;
+        }
+        self.path = None;
+        self.disk_hash = None;
+        self.disk_bytes = 0;
+        self.sample = true;
+        self.tutorial = Some(Tutorial::new(&self.types));
+        self.status = String::from("Tutorial sample loaded; follow the panel (Esc closes it)");
+    }
+
+    /// Makes the next file that loads open at type `name`, and at `field` if given.
+    pub fn open_at(&mut self, name: String, field: Option<String>) {
+        self.start_at = Some((name, field));
+    }
+
+    /// The exact name, else the first one containing `name` in any case, with a note; a
+    /// type that isn't there leaves the file as it opened, with a warning.
+    fn go_to_start(&mut self, name: &str, field: Option<&str>) {
+        let needle = name.to_lowercase();
+        let exact = self.types.iter().position(|t| t.name == name);
+        let Some(idx) = exact.or_else(|| self.types.iter().position(|t| t.name.to_lowercase().contains(&needle))) else {
+            self.fail(format!("--goto: no type matches '{name}'"));
+            return;
+        };
+        self.select_type(idx);
+        let mut notes = Vec::new();
+        if exact.is_none() {
+            notes.push(format!("no type is named '{name}', opened at '{}', the first containing it", self.types[idx].name));
+        }
+        if let Some(field) = field {
+            let key = model::parse_field_label(field);
+            match self.types[idx].fields.iter().position(|f| Some(&f.key) == key.as_ref() || f.key.get_element_name() == field) {
+                Some(position) => {
+                    self.selected_field = position;
+                    self.focus = EditorFocus::FieldList;
                 }
+                None => notes.push(format!("'{}' has no {field}", self.types[idx].name)),
             }
-            _ => match action {
-                Action::Up => self.move_selection(-1),
-                Action::Down => self.move_selection(1),
-                Action::Left => self.focus = EditorFocus::TypeList,
-                Action::Right => {
-                    if !self.types.is_empty() {
-                        self.focus = EditorFocus::FieldList;
-                    }
-                }
-                Action::PgUp => self.move_selection(-10),
-                Action::PgDown => self.move_selection(10),
-                Action::Activate => {
-                    self.begin_editing();
-                }
-                Action::Add => self.add(),
-                Action::AddAttribute => self.add_attribute(),
-                Action::Copy => self.copy(),
-                Action::Delete => self.delete(),
-                Action::Save => {
-                    self.save()?;
-                }
-                _ => {}
-            },
         }
-        Ok(())
+        if !notes.is_empty() {
+            self.log(format!("--goto: {}", notes.join("; ")));
+        }
     }
 
-    pub fn draw<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, show_help: bool) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints(
-                [
-                    Constraint::Length(3),
-                    Constraint::Min(10),
-                    Constraint::Length(3),
-                ]
-                .as_ref(),
-            )
-            .split(f.size());
+    /// Picks the Tips pane's reference ranges: the configured baseline file, else vanilla.
+    /// Returns a note when the baseline file can't be used.
+    fn load_hints(&mut self) -> Option<String> {
+        self.hints = Baseline::vanilla();
+        let path = self.config.hints_baseline.clone()?;
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| parse_types(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok((types, _)) => {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+                self.hints = Baseline::from_types(&types, name.unwrap_or_else(|| String::from("baseline")));
+                None
+            }
+            Err(err) => Some(format!("hints baseline {} ignored: {err}", path.display())),
+        }
+    }
 
-        let header_text = match &self.path {
-            Some(path) => {
-                let src = match self.source {
-                    FileSource::Local => "local",
-                    FileSource::Remote(_) => "ssh",
-                };
-                format!("Editing: {} ({})", path.display(), src)
+    /// Reads the configured classname list; without one, a list loaded with `:classnames`
+    /// stays. Returns a note when the file can't be read.
+    fn load_classnames(&mut self) -> Option<String> {
+        let path = self.config.classnames.clone()?;
+        match read_classnames(&path) {
+            Ok(list) => {
+                self.classnames = Some(list);
+                None
             }
-            None => String::from("No file loaded"),
+            Err(err) => Some(format!("classnames {} ignored: {err}", path.display())),
+        }
+    }
+
+    /// Lays the user's and the opened file's `help.toml` over the built-in field help;
+    /// returns a note for each one that didn't parse.
+    fn load_help(&mut self, project_content: Option<String>) -> Vec<String> {
+        let (help, notes) = AppDirs::locate().layered_help(project_content.as_deref());
+        self.help = help;
+        notes
+    }
+
+    /// Reads the notes sidecar; returns a note when it didn't parse or edits are recorded.
+    fn load_notes(&mut self, content: Option<String>) -> Option<String> {
+        self.notes_kept = content.is_some();
+        self.notes_broken = false;
+        self.notes = match content.as_deref().map(Annotations::parse) {
+            Some(Ok(notes)) => notes,
+            Some(Err(err)) => {
+                self.notes_broken = true;
+                return Some(format!("notes sidecar ignored and left alone: {err}"));
+            }
+            None => Annotations::default(),
         };
-        let header = Paragraph::new(header_text)
-            .block(Block::default().title("File").borders(Borders::ALL))
-            .wrap(Wrap { trim: true });
-        f.render_widget(header, chunks[0]);
+        self.config.author.as_ref().map(|author| format!("edits recorded as {author}"))
+    }
 
-        let body = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(35),
-                Constraint::Percentage(45),
-                Constraint::Percentage(20)
-            ].as_ref())
-            .split(chunks[1]);
-
-        let type_items: Vec<ListItem> = self
-            .types
-            .iter()
-            .map(|t| ListItem::new(t.name.clone()))
-            .collect();
-        let mut type_state = ListState::default();
-        if !self.types.is_empty() {
-            type_state.select(Some(self.selected_type));
+    /// Records who changed `type_idx`'s fields since the last undo snapshot, when `author`
+    /// is set. Local sidecars are written at once, remote ones with the next save.
+    fn annotate(&mut self, type_idx: usize) {
+        let Some(author) = self.config.author.clone() else {
+            return;
+        };
+        if self.notes_broken {
+            return;
         }
-        let type_list = List::new(type_items)
-            .block(Block::default().title("Types").borders(Borders::ALL))
-            .highlight_symbol("▶ ")
-            .highlight_style(highlight_for(self.focus == EditorFocus::TypeList));
-        f.render_stateful_widget(type_list, body[0], &mut type_state);
+        let (Some(ty), Some(before)) = (self.types.get(type_idx), self.before_last_edit()) else {
+            return;
+        };
+        let old = before
+            .get(type_idx)
+            .filter(|t| t.name == ty.name)
+            .or_else(|| before.iter().find(|t| t.name == ty.name));
+        let Some(old) = old else {
+            return;
+        };
+        let changes = diff::diff_fields(old, ty);
+        if self.notes.record(&ty.name, &changes, &author, utils::now_unix()) == 0 {
+            return;
+        }
+        self.notes_kept = true;
+        if let (FileSource::Local, Some(path)) = (&self.source, &self.path)
+            && let Err(err) = fs::write(annotations::notes_path(path), self.notes.to_toml())
+        {
+            self.fail(format!("Couldn't write the notes sidecar: {err}"));
+        }
+    }
+
+    /// The sidecar as the next save writes it, without notes on deleted types or fields;
+    /// `None` when there's nothing to keep.
+    fn notes_for_save(&mut self) -> Option<String> {
+        if !self.notes_kept || self.notes_broken {
+            return None;
+        }
+        self.notes.prune(&self.types);
+        Some(self.notes.to_toml())
+    }
 
-        let field_items: Vec<ListItem> = self
-            .current_fields()
+    /// `:changedby [author]`: every field the author (default: you) last changed.
+    fn show_changed_by(&mut self, author: Option<&str>) {
+        let Some(author) = author.map(str::to_string).or_else(|| self.config.author.clone()) else {
+            let known = self.notes.authors().join(", ");
+            self.status = if known.is_empty() {
+                String::from("No edits recorded; set author in the config to record them")
+            } else {
+                format!(":changedby <author>; recorded: {known}")
+            };
+            return;
+        };
+        let notes = self.notes.by_author(&author);
+        let mut lines: Vec<String> = notes
             .iter()
-            .map(|field| {
-                let label = format!("{}: {}", field_label(&field.key), field.value);
-                ListItem::new(label)
-            })
+            .map(|n| format!("{} {}: {}", n.type_name, n.field, n.describe()))
             .collect();
-        let mut field_state = ListState::default();
-        if !field_items.is_empty() {
-            field_state.select(Some(self.selected_field));
+        if lines.is_empty() {
+            lines.push(format!("No recorded changes by {author}."));
         }
-        let field_list = List::new(field_items)
-            .block(Block::default().title("Fields").borders(Borders::ALL))
-            .highlight_symbol("▶ ")
-            .highlight_style(highlight_for(
-                self.focus == EditorFocus::FieldList || self.focus == EditorFocus::Editing,
-            ));
-        f.render_stateful_widget(field_list, body[1], &mut field_state);
-
-        let selected_field_string = self.current_field().unwrap().key.get_help_text().to_string();
-        let tips_widget = Paragraph::new(selected_field_string)
-            .block(Block::default().title("Tips").borders(Borders::ALL))
-            .wrap(Wrap { trim: true });
+        self.overlay = Some(Overlay::Text {
+            title: format!("Changed by {author} ({})", notes.len()),
+            lines,
+            scroll: 0,
+        });
+    }
 
-        f.render_widget(tips_widget, body[2]);
+    /// Like `log`, but the status shows in red until the next message replaces it.
+    fn fail(&mut self, message: String) {
+        self.error_status = Some(message.clone());
+        self.messages.push_error(message.clone());
+        self.status = message;
+    }
 
-        let footer_text = if self.focus == EditorFocus::Editing {
-            format!("Help: ? | Quit: q | Status: editing ({})", self.input_buffer)
-        } else {//
-            format!("Help: ? | Quit: q | Status: {}", self.status)
-        };
-        let footer = Paragraph::new(footer_text)
-            .block(Block::default().title("Status").borders(Borders::ALL))
-            .wrap(Wrap { trim: true });
-        f.render_widget(footer, chunks[2]);
+    /// Status for an edit `u` can take back; every undoable edit reports through here so
+    /// the wording stays `<what was done> (u to undo)`.
+    fn undoable(&mut self, message: String) {
+        self.log(format!("{message} (u to undo)"));
+    }
 
-        if show_help {
-            render_help_overlay(f);
+    /// Where an edit landed, for statuses: `'AKM'` for one type, `12 types` for several.
+    fn describe_targets(&self, targets: &[usize]) -> String {
+        match targets {
+            [one] => format!("'{}'", self.types.get(*one).map(|t| t.name.as_str()).unwrap_or_default()),
+            many => utils::plural(many.len(), "type"),
         }
     }
 
-    fn calculate_move_idx(&mut self, selected: usize, new_idx: isize, arr_len: isize) -> isize {
-        if selected == (arr_len - 1) as usize && new_idx >= arr_len {
-            return 0;
-        } else if selected == 0 && new_idx < 0 {
-            return arr_len - 1;
-        } else if new_idx < 0 {
-            return 0;
-        } else if new_idx >= arr_len {
-            return arr_len - 1;
+    /// Sets the status line and keeps the message in the session's message log. A macro
+    /// replay logs one summary at the end instead of every run's messages.
+    fn log(&mut self, message: String) {
+        if !self.replaying {
+            self.messages.push(message.clone());
         }
-        new_idx
+        self.status = message;
     }
 
-    fn move_selection(&mut self, delta: isize) {
-        match self.focus {
-            EditorFocus::TypeList => {
-                if self.types.is_empty() {
-                    return;
-                }
-                let types_len = self.types.len() as isize;
-                let new_idx = self
-                    .calculate_move_idx(self.selected_type, self.selected_type as isize + delta, types_len);
-                self.selected_type = new_idx as usize;
-                self.selected_field = 0;
+    /// Polls background remote work; called once per main loop iteration.
+    pub fn tick(&mut self) -> io::Result<()> {
+        self.poll_job();
+        self.poll_liveness();
+        self.poll_watch();
+        self.poll_reconnect()
+    }
+
+    /// The file on disk is now `hash` (just loaded or written); watching starts over from it.
+    fn remember_disk(&mut self, hash: [u8; 32], bytes: u64) {
+        self.disk_hash = Some(hash);
+        self.disk_bytes = bytes;
+        self.disk_stamp = None;
+        self.disk_changed = false;
+    }
+
+    /// Every `WATCH_INTERVAL`, stats the open local file; a new stamp whose content differs
+    /// from what the editor last read or wrote raises the changed-on-disk banner.
+    fn poll_watch(&mut self) {
+        // A running job may be writing the file itself.
+        if !self.config.watch_file
+            || self.disk_changed
+            || self.job.is_some()
+            || self.last_watch_check.elapsed() < WATCH_INTERVAL
+        {
+            return;
+        }
+        let (FileSource::Local, Some(path)) = (&self.source, &self.path) else {
+            return;
+        };
+        self.last_watch_check = Instant::now();
+        let Ok(stamp) = FileStamp::local(path) else {
+            return;
+        };
+        if self.disk_stamp != Some(stamp) {
+            let changed = fs::read_to_string(path).is_ok_and(|c| self.disk_hash != Some(merge::content_hash(&c)));
+            self.disk_stamp = Some(stamp);
+            if changed {
+                self.note_disk_change();
             }
-            EditorFocus::FieldList => {
-                let fields_len = self.current_fields_len() as isize;
-                if fields_len == 0 {
-                    return;
+        }
+    }
+
+    fn note_disk_change(&mut self) {
+        self.disk_changed = true;
+        let name = self.path.as_ref().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned());
+        self.log(format!("{} changed on disk; R reloads it or dismisses this", name.unwrap_or_default()));
+    }
+
+    fn poll_liveness(&mut self) {
+        if let Some(rx) = &self.liveness_rx {
+            match rx.try_recv() {
+                Ok(stamp) => {
+                    self.liveness_rx = None;
+                    let alive = stamp.is_some();
+                    if let Some(stamp) = stamp
+                        && self.config.watch_remote
+                    {
+                        // Remote content isn't hashed: any new stamp counts as a change.
+                        let seen = self.disk_stamp.replace(stamp);
+                        if seen.is_some_and(|seen| seen != stamp) && !self.disk_changed && self.job.is_none() {
+                            self.note_disk_change();
+                        }
+                    }
+                    if !alive && self.remote_alive {
+                        self.status = String::from("Remote connection lost; save to reconnect or keep a local copy");
+                    }
+                    self.remote_alive = alive;
                 }
-                let new_idx = self
-                    .calculate_move_idx(self.selected_field, self.selected_field as isize + delta, fields_len);
-                self.selected_field = new_idx as usize;
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.liveness_rx = None,
             }
-            EditorFocus::Editing => {}
+            return;
         }
+
+        if self.reconnect_rx.is_some() || self.last_liveness_check.elapsed() < LIVENESS_INTERVAL {
+            return;
+        }
+        let (FileSource::Remote(client), Some(path)) = (&self.source, &self.path) else {
+            return;
+        };
+        self.last_liveness_check = Instant::now();
+        let client = client.clone();
+        let path = path.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let stamp = match client.try_lock() {
+                Ok(client) => client.probe(&path).ok(),
+                // A transfer or listing has the session, which says enough; try next time.
+                Err(TryLockError::WouldBlock) => return,
+                Err(TryLockError::Poisoned(_)) => None,
+            };
+            let _ = tx.send(stamp);
+        });
+        self.liveness_rx = Some(rx);
     }
 
-    fn begin_editing(&mut self) {
-        match self.focus {
-            EditorFocus::TypeList => {
-                if let Some(ty) = self.types.get(self.selected_type) {
-                    self.input_buffer = ty.name.clone();
-                    self.editing_target = Some(EditTarget::TypeName);
-                    self.focus = EditorFocus::Editing;
-                    self.status = String::from("Editing type name");
+    fn poll_reconnect(&mut self) -> io::Result<()> {
+        let Some(rx) = &self.reconnect_rx else {
+            return Ok(());
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return Ok(()),
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("connection worker exited")),
+        };
+        self.reconnect_rx = None;
+        match result {
+            Ok(client) => {
+                self.source = FileSource::Remote(client);
+                self.remote_alive = true;
+                self.last_liveness_check = Instant::now();
+                if let Err(err) = self.save() {
+                    self.fail(format!("Save failed: {err}"));
                 }
             }
-            EditorFocus::FieldList => {
-                if let Some(field) = self.current_field() {
-                    self.input_buffer = field.value.clone();
-                    self.editing_target = Some(EditTarget::FieldValue);
-                    self.focus = EditorFocus::Editing;
-                    self.status = String::from("Editing field value");
-                }
+            Err(err) => {
+                self.status = format!("Reconnect failed: {err}");
+                self.overlay = Some(Overlay::RemoteLost);
             }
-            EditorFocus::Editing => {}
         }
+        Ok(())
     }
 
-    fn stop_editing(&mut self) {
-        self.focus = match self.editing_target {
-            Some(EditTarget::TypeName) => EditorFocus::TypeList,
-            Some(EditTarget::FieldName) => EditorFocus::FieldList,
-            Some(EditTarget::FieldValue) => EditorFocus::FieldList,
-            None => self.focus,
+    fn start_reconnect(&mut self) {
+        let Some(config) = self.remote_config.clone() else {
+            self.status = String::from("No remote connection details to reconnect with");
+            return;
         };
-        self.editing_target = None;
-        self.input_buffer.clear();
+        self.status = format!("Reconnecting to {}@{}:{}...", config.username, config.host, config.port);
+        self.overlay = None;
+        self.reconnect_rx = Some(SshBackend::connect_in_background(config));
     }
 
-    fn apply_input(&mut self) -> bool {
-        let value = self.input_buffer.clone();
-        match self.editing_target {
-            Some(EditTarget::TypeName) => {
-                if let Some(ty) = self.types.get_mut(self.selected_type) {
-                    ty.name = value;
-                    self.status = String::from("Type renamed");
+    fn begin_save_as(&mut self) {
+        let file_name = self
+            .path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(if self.sample { "tutorial_types.xml" } else { "types.xml" }));
+        let local = env::current_dir().map(|d| d.join(&file_name)).unwrap_or(file_name);
+        self.overlay = None;
+        self.input_buffer = local.display().to_string();
+        self.editing_target = Some(EditTarget::SavePath);
+        self.focus = EditorFocus::Editing;
+        self.status = String::from("Enter a local path for the copy");
+    }
+
+    /// Handles a key while an overlay is open; the overlay was taken out of `self.overlay`
+    /// and is put back unless the action closes it.
+    fn handle_overlay_action(&mut self, overlay: Overlay, action: Action) {
+        match overlay {
+            Overlay::RemoteLost => match action {
+                Action::ToggleRemote | Action::Activate => self.start_reconnect(),
+                Action::Save => self.begin_save_as(),
+                Action::Cancel => {
+                    self.status = String::from("Save cancelled; changes are only in memory");
+                }
+                _ => self.overlay = Some(overlay),
+            },
+            Overlay::Statistics => {
+                if !matches!(action, Action::Cancel | Action::Stats | Action::Activate) {
+                    self.overlay = Some(overlay);
                 }
-                false
             }
-            Some(EditTarget::FieldName) => {
-                if let Some(field) = self.current_field_mut() {
-                    field.key.set_name(value);
-                    if let Some(field) = self.current_field() {
-                        self.input_buffer = field.value.clone();
-                        self.editing_target = Some(EditTarget::FieldValue);
-                        self.status = String::from("Field renamed; edit value");
-                        return true;
+            Overlay::ValidationReport => match action {
+                Action::Cancel | Action::Validate => {}
+                Action::Activate | Action::NextIssue => self.jump_to_issue(true),
+                Action::Delete => self.remove_repeated_attributes(),
+                _ => self.overlay = Some(overlay),
+            },
+            Overlay::NominalCap(tool) => self.handle_cap_action(tool, action),
+            Overlay::Propagate(tool) => self.handle_propagate_action(tool, action),
+            Overlay::Duplicates(review) => self.handle_duplicates_action(review, action),
+            Overlay::Quantity(tool) => self.handle_quantity_action(tool, action),
+            Overlay::Tiers(tool) => self.handle_tier_action(tool, action),
+            Overlay::FlagMatrix(matrix) => self.handle_flag_matrix_action(matrix, action),
+            Overlay::Backups(browser) => self.handle_backups_action(browser, action),
+            Overlay::AddElement(pending) => self.handle_add_element_action(pending, action),
+            Overlay::Calc { result, error } => self.handle_calc_action(result, error, action),
+            Overlay::ConfirmClose => match action {
+                Action::Activate | Action::Input('y') => self.close_requested = true,
+                Action::Save => {
+                    if let Err(err) = self.save() {
+                        self.fail(format!("Save failed: {err}"));
+                    }
+                    if let Some(EditorJob::Upload(upload, _)) = &mut self.job {
+                        upload.close_after = true;
+                    } else {
+                        self.close_requested = self.overlay.is_none() && self.types == self.baseline;
                     }
-                    self.status = String::from("Field renamed");
                 }
-                false
+                Action::Cancel | Action::NextIssue => self.status = String::from("Still editing"),
+                _ => self.overlay = Some(overlay),
+            },
+            Overlay::MissingFlags(targets) => {
+                let result = match action {
+                    Action::Activate | Action::Input('f') => {
+                        self.add_default_flags(&targets);
+                        self.save_unchecked()
+                    }
+                    Action::Input('s') => self.save_unchecked(),
+                    Action::Cancel | Action::NextIssue => {
+                        self.status = String::from("Save cancelled");
+                        Ok(())
+                    }
+                    _ => {
+                        self.overlay = Some(Overlay::MissingFlags(targets));
+                        Ok(())
+                    }
+                };
+                if let Err(err) = result {
+                    self.fail(format!("Save failed: {err}"));
+                }
             }
-            Some(EditTarget::FieldValue) => {
-                if let Some(field) = self.current_field_mut() {
-                    field.value = value;
-                    self.status = String::from("Value updated");
+            Overlay::ConfirmShrink(warning) => match action {
+                Action::Activate | Action::Input('y') => {
+                    if let Err(err) = self.save_checking_space() {
+                        self.fail(format!("Save failed: {err}"));
+                    }
+                }
+                Action::Cancel | Action::NextIssue => self.status = String::from("Save cancelled; nothing was written"),
+                _ => self.overlay = Some(Overlay::ConfirmShrink(warning)),
+            },
+            Overlay::ConfirmLowSpace(warning) => match action {
+                Action::Activate | Action::Input('y') => {
+                    if let Err(err) = self.save_checking_flags() {
+                        self.fail(format!("Save failed: {err}"));
+                    }
+                }
+                Action::Cancel | Action::NextIssue => self.status = String::from("Save cancelled; nothing was written"),
+                _ => self.overlay = Some(Overlay::ConfirmLowSpace(warning)),
+            },
+            Overlay::ConfirmDelete(warning) => match action {
+                Action::Activate | Action::Input('y') => self.delete_type(),
+                Action::Cancel | Action::NextIssue => self.status = String::from("Nothing deleted"),
+                _ => self.overlay = Some(Overlay::ConfirmDelete(warning)),
+            },
+            Overlay::Reload => match action {
+                Action::Activate | Action::Input('r') => {
+                    if let Err(err) = self.reload_from_disk() {
+                        self.fail(format!("Reload failed: {err}"));
+                    }
+                }
+                Action::Input('s') if self.types != self.baseline => {
+                    if let Err(err) = self.save() {
+                        self.fail(format!("Save failed: {err}"));
+                    }
+                }
+                Action::Input('i') if self.disk_changed => {
+                    self.disk_changed = false;
+                    self.status = String::from("Ignoring the change on disk; saving merges it with your edits");
+                }
+                Action::Cancel | Action::NextIssue => self.status = String::from("Nothing reloaded"),
+                _ => self.overlay = Some(overlay),
+            },
+            Overlay::ConfirmExchange(from, to) => match action {
+                Action::Activate | Action::Input('y') => self.swap_values(&from, &to),
+                Action::Cancel | Action::NextIssue => self.status = String::from("Exchange cancelled; nothing changed"),
+                _ => self.overlay = Some(Overlay::ConfirmExchange(from, to)),
+            },
+            Overlay::ConfirmWritable => match action {
+                Action::Activate | Action::Input('y') => {
+                    self.readonly = false;
+                    self.log(String::from("Read-only mode off; edits and saves are allowed"));
+                }
+                Action::Cancel | Action::NextIssue => self.status = String::from("Still read-only"),
+                _ => self.overlay = Some(overlay),
+            },
+            Overlay::SaveConflicts(review) => {
+                if let Err(err) = self.handle_conflicts_action(review, action) {
+                    self.fail(format!("Save failed: {err}"));
                 }
-                false
             }
-            None => false,
+            Overlay::BulkApply(run, scroll) => match action {
+                Action::Activate if run.writable() > 0 => self.start_bulk_write(run),
+                Action::Cancel | Action::Activate => self.status = String::from("Nothing written"),
+                Action::Up => self.overlay = Some(Overlay::BulkApply(run, scroll.saturating_sub(1))),
+                Action::Down => {
+                    let scroll = scroll.saturating_add(1).min(run.files.len() as u16);
+                    self.overlay = Some(Overlay::BulkApply(run, scroll));
+                }
+                _ => self.overlay = Some(Overlay::BulkApply(run, scroll)),
+            },
+            Overlay::Assign(assignment, scroll) => match action {
+                Action::Activate => self.apply_assignment(assignment),
+                Action::Cancel => self.status = String::from("Nothing changed"),
+                Action::Up => self.overlay = Some(Overlay::Assign(assignment, scroll.saturating_sub(1))),
+                Action::Down => {
+                    let last = (assignment.changes.len() + assignment.skipped.len() + 4) as u16;
+                    self.overlay = Some(Overlay::Assign(assignment, scroll.saturating_add(1).min(last)));
+                }
+                _ => self.overlay = Some(Overlay::Assign(assignment, scroll)),
+            },
+            Overlay::StripFields(plan, scroll) => match action {
+                Action::Activate => self.apply_strip(plan),
+                Action::Cancel => self.status = String::from("Nothing changed"),
+                Action::Up => self.overlay = Some(Overlay::StripFields(plan, scroll.saturating_sub(1))),
+                Action::Down => {
+                    let last = plan.changes.len() as u16 + 2;
+                    self.overlay = Some(Overlay::StripFields(plan, scroll.saturating_add(1).min(last)));
+                }
+                _ => self.overlay = Some(Overlay::StripFields(plan, scroll)),
+            },
+            Overlay::CaseFixes(review, scroll) => match action {
+                Action::Activate => self.apply_case_fixes(review),
+                Action::Cancel => self.status = String::from("Nothing changed"),
+                Action::Up => self.overlay = Some(Overlay::CaseFixes(review, scroll.saturating_sub(1))),
+                Action::Down => {
+                    let last = review.lines().len() as u16;
+                    self.overlay = Some(Overlay::CaseFixes(review, scroll.saturating_add(1).min(last)));
+                }
+                _ => self.overlay = Some(Overlay::CaseFixes(review, scroll)),
+            },
+            Overlay::GotoPick { query, matches, cursor } => match action {
+                Action::Up => {
+                    let cursor = cursor.saturating_sub(1);
+                    self.overlay = Some(Overlay::GotoPick { query, matches, cursor });
+                }
+                Action::Down => {
+                    let cursor = (cursor + 1).min(matches.len() - 1);
+                    self.overlay = Some(Overlay::GotoPick { query, matches, cursor });
+                }
+                Action::Activate => self.jump_to_type(matches[cursor]),
+                Action::Cancel => self.status = String::from("Go to cancelled"),
+                _ => self.overlay = Some(Overlay::GotoPick { query, matches, cursor }),
+            },
+            Overlay::LifetimePresets { targets, cursor } => match action {
+                Action::Up => self.overlay = Some(Overlay::LifetimePresets { targets, cursor: cursor.saturating_sub(1) }),
+                Action::Down => {
+                    let cursor = (cursor + 1).min(self.config.lifetime_presets.len().saturating_sub(1));
+                    self.overlay = Some(Overlay::LifetimePresets { targets, cursor });
+                }
+                Action::Activate => self.apply_lifetime_preset(&targets, cursor),
+                Action::Cancel => self.status = String::from("Lifetime unchanged"),
+                _ => self.overlay = Some(Overlay::LifetimePresets { targets, cursor }),
+            },
+            Overlay::History { cursor } => match action {
+                Action::Up => self.overlay = Some(Overlay::History { cursor: cursor.saturating_sub(1) }),
+                Action::Down => {
+                    let last = self.undo_stack.len() + self.redo_stack.len();
+                    self.overlay = Some(Overlay::History { cursor: (cursor + 1).min(last) });
+                }
+                Action::Activate => self.travel_history(cursor),
+                Action::Cancel => {}
+                _ => self.overlay = Some(Overlay::History { cursor }),
+            },
+            Overlay::Text { title, lines, scroll } => {
+                let scroll = match action {
+                    Action::Cancel | Action::Activate => return,
+                    Action::Up => scroll.saturating_sub(1),
+                    Action::Down => scroll.saturating_add(1).min(lines.len().saturating_sub(1) as u16),
+                    Action::PgUp => scroll.saturating_sub(10),
+                    Action::PgDown => scroll.saturating_add(10).min(lines.len().saturating_sub(1) as u16),
+                    _ => scroll,
+                };
+                self.overlay = Some(Overlay::Text { title, lines, scroll });
+            }
         }
     }
 
-    fn save(&mut self) -> io::Result<()> {
-        let path = match &self.path {
-            Some(p) => p.clone(),
-            None => {
-                self.status = String::from("No file loaded");
-                return Ok(());
-            }
+    /// Opens the tier checkboxes for the marked types, or the highlighted one when nothing is marked.
+    fn open_tier_tool(&mut self) {
+        let marked = self.marked_types();
+        let targets: Vec<usize> = if marked.is_empty() {
+            vec![self.selected_type]
+        } else {
+            marked.into_iter().collect()
         };
-        let mut backup_path = path.clone();
-        backup_path.add_extension("bak");
+        let targets: Vec<usize> = targets.into_iter().filter(|&i| i < self.types.len()).collect();
+        if targets.is_empty() {
+            return;
+        }
+        let mut names: Vec<String> = tiers::DEFAULT_TIERS.iter().map(|t| t.to_string()).collect();
+        names.extend(self.limit_tiers.iter().cloned());
+        for &i in &targets {
+            names.extend(tiers::tiers_of(&self.types[i], &self.limit_tiers));
+        }
+        names.sort();
+        names.dedup();
+        tiers::sort_tiers(&mut names);
+        let rows = names
+            .into_iter()
+            .map(|name| TierRow {
+                have: targets
+                    .iter()
+                    .filter(|&&i| tiers::tiers_of(&self.types[i], &self.limit_tiers).contains(&name))
+                    .count(),
+                name,
+                set: None,
+            })
+            .collect();
+        self.overlay = Some(Overlay::Tiers(TierTool { targets, rows, cursor: 0 }));
+    }
 
-        match &self.source {
-            FileSource::Local => {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    let _ = fs::write(&backup_path, content);
+    fn handle_tier_action(&mut self, mut tool: TierTool, action: Action) {
+        match action {
+            Action::Cancel => return,
+            Action::Up => tool.cursor = tool.cursor.saturating_sub(1),
+            Action::Down => tool.cursor = (tool.cursor + 1).min(tool.rows.len().saturating_sub(1)),
+            Action::ToggleSelect => {
+                if let Some(row) = tool.rows.get_mut(tool.cursor) {
+                    let all = row.set.unwrap_or(row.have == tool.targets.len());
+                    row.set = Some(!all);
                 }
-                let xml = serialize_types(&self.types)?;
-                fs::write(&path, xml)?;
-                self.status = format!("Saved {}", path.display());
             }
-            FileSource::Remote(client) => {
-                let client = client.lock().map_err(|_| io::Error::new(io::ErrorKind::Other, "SSH backend in use"))?;
-                if let Ok(content) = client.read_file(&path) {
-                    let _ = client.write_file(&backup_path, &content);
+            Action::Activate => {
+                let changes: Vec<(&str, bool)> =
+                    tool.rows.iter().filter_map(|r| r.set.map(|s| (r.name.as_str(), s))).collect();
+                if changes.is_empty() {
+                    self.status = String::from("No tiers changed");
+                    return;
+                }
+                self.push_undo(format!("set tiers on {} types", tool.targets.len()));
+                let mut touched = 0;
+                for &idx in &tool.targets {
+                    let before = self.types[idx].clone();
+                    for &(tier, present) in &changes {
+                        tiers::set_tier(&mut self.types[idx], tier, present);
+                    }
+                    if self.types[idx] != before {
+                        touched += 1;
+                        self.note_change(idx);
+                        self.revalidate_type(idx);
+                    }
                 }
-                let xml = serialize_types(&self.types)?;
-                client.write_file(&path, &xml)?;
-                self.status = format!("Saved remote {}", path.display());
+                self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+                self.last_edit = Some(LastEdit::Tiers(changes.iter().map(|&(t, on)| (t.to_string(), on)).collect()));
+                self.undoable(format!("Updated tiers on {touched} of {}", utils::plural(tool.targets.len(), "type")));
+                return;
             }
+            _ => {}
         }
-        Ok(())
+        self.overlay = Some(Overlay::Tiers(tool));
     }
 
-    fn add(&mut self) {
-        match self.focus {
-            EditorFocus::TypeList => {
-                let new_type = TypeEntry {
-                    name: String::from("new_type"),
-                    fields: default_fields(),
-                };
-                self.types.push(new_type);
-                self.selected_type = self.types.len().saturating_sub(1);
-                self.selected_field = 0;
-                self.focus = EditorFocus::TypeList;
-                self.editing_target = Some(EditTarget::TypeName);
-                self.input_buffer = String::from("new_type");
-                self.status = String::from("Enter a name for the new type");
-            }
-            EditorFocus::FieldList => {
-                if self.types.is_empty() {
-                    return;
-                }
-                let new_field_name = String::from("new_field");
-                let idx = self
-                    .types
-                    .get(self.selected_type)
-                    .map(|t| t.fields.iter().filter(|f| matches!(&f.key, FieldKey::Element { name, .. } if name == &new_field_name)).count())
-                    .unwrap_or(0);
-                let field = Field {
-                    key: FieldKey::Element { name: new_field_name.clone(), index: idx },
-                    value: String::new(),
-                };
-                if let Some(ty) = self.types.get_mut(self.selected_type) {
-                    ty.fields.push(field);
-                    self.selected_field = ty.fields.len().saturating_sub(1);
-                    // First edit the field name, then fall through to value editing when applied.
-                    self.input_buffer = new_field_name;
-                    self.editing_target = Some(EditTarget::FieldName);
-                    self.focus = EditorFocus::Editing;
-                }
-                self.status = String::from("Added new field; enter a name");
-            }
-            EditorFocus::Editing => {}
+    /// `:lifetime`: a menu of the lifetime presets for the marked types, or the highlighted one.
+    fn open_lifetime_presets(&mut self) {
+        if self.config.lifetime_presets.is_empty() {
+            self.status = String::from("No lifetime presets; add some under [lifetime_presets] in the config");
+            return;
         }
+        let marked = self.marked_types();
+        let targets: Vec<usize> = if marked.is_empty() {
+            vec![self.selected_type]
+        } else {
+            marked.into_iter().collect()
+        };
+        let targets: Vec<usize> = targets.into_iter().filter(|&i| i < self.types.len()).collect();
+        if targets.is_empty() {
+            return;
+        }
+        self.overlay = Some(Overlay::LifetimePresets { targets, cursor: 0 });
     }
 
-    fn add_attribute(&mut self) {
-        if self.types.is_empty() {
+    /// Sets preset `row` as the lifetime of every target in one undo step, adding
+    /// `<lifetime>` to types without one.
+    fn apply_lifetime_preset(&mut self, targets: &[usize], row: usize) {
+        let Some((name, seconds)) = self.config.lifetime_presets.get(row).cloned() else {
+            return;
+        };
+        let value = seconds.to_string();
+        let has_it = |ty: &TypeEntry| ty.element_field("lifetime").is_some_and(|f| ty.fields[f].value.trim() == value);
+        let changing: Vec<usize> = targets.iter().copied().filter(|&i| !has_it(&self.types[i])).collect();
+        if changing.is_empty() {
+            self.status = format!("Lifetime is already {value} ({name}) on {}", utils::plural(targets.len(), "type"));
             return;
         }
-        let Some(base_field) = self.current_field() else {
+        self.push_undo(format!("set lifetime to {name} on {}", utils::plural(changing.len(), "type")));
+        let mut added = 0;
+        for &idx in &changing {
+            if self.types[idx].set_element("lifetime", &value) {
+                added += 1;
+            }
+            self.note_change(idx);
+            self.revalidate_type(idx);
+        }
+        let ty = &self.types[changing[0]];
+        self.last_edit = ty.element_field("lifetime").map(|f| LastEdit::Values(vec![ty.fields[f].clone()]));
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        let mut notes = Vec::new();
+        if added > 0 {
+            notes.push(format!("{added} got a new <lifetime>"));
+        }
+        if changing.len() < targets.len() {
+            notes.push(format!("{} already had it", targets.len() - changing.len()));
+        }
+        let notes = if notes.is_empty() { String::new() } else { format!("; {}", notes.join(", ")) };
+        self.undoable(format!("Set lifetime to {value} ({name}) on {}{notes}", utils::plural(changing.len(), "type")));
+    }
+
+    /// `:flags`: compares the marked types' flags in a matrix.
+    fn open_flag_matrix(&mut self) {
+        let marked = self.marked_types();
+        if marked.is_empty() {
+            self.status = String::from("Mark types with Space first; :flags compares their flags");
             return;
-        };
-        let element = base_field.key.get_element_name().to_string();
-        let index = match &base_field.key {
-            FieldKey::Element { index, .. } => *index,
-            FieldKey::Attribute { index, .. } => *index,
-        };
-        let new_attr_name = "new_attr".to_string();
-        let field = Field {
-            key: FieldKey::Attribute {
-                element,
-                index,
-                attr: new_attr_name.clone(),
-            },
-            value: String::new(),
-        };
-        if let Some(ty) = self.types.get_mut(self.selected_type) {
-            ty.fields.push(field);
-            self.selected_field = ty.fields.len().saturating_sub(1);
-            self.input_buffer = new_attr_name;
-            self.editing_target = Some(EditTarget::FieldName);
-            self.focus = EditorFocus::Editing;
-            self.status = String::from("Added new attribute; enter a name");
         }
+        let targets = marked.into_iter().collect();
+        self.overlay = Some(Overlay::FlagMatrix(FlagMatrix { targets, column: 0, scroll: 0 }));
     }
 
-    fn copy(&mut self) {
-        match self.focus {
-            EditorFocus::TypeList => {
-                if let Some(current) = self.types.get(self.selected_type).cloned() {
-                    let mut clone = current.clone();
-                    clone.name = format!("{}_copy", clone.name);
-                    self.types.push(clone);
-                    self.selected_type = self.types.len().saturating_sub(1);
-                    self.selected_field = 0;
-                    self.status = String::from("Type copied");
-                }
+    fn handle_flag_matrix_action(&mut self, mut matrix: FlagMatrix, action: Action) {
+        let last_row = matrix.targets.len().saturating_sub(1);
+        match action {
+            Action::Cancel => return,
+            Action::Left => matrix.column = matrix.column.saturating_sub(1),
+            Action::Right => matrix.column = (matrix.column + 1).min(flags::FLAGS.len() - 1),
+            Action::Up => matrix.scroll = matrix.scroll.saturating_sub(1),
+            Action::Down => matrix.scroll = (matrix.scroll + 1).min(last_row),
+            Action::PgUp => matrix.scroll = matrix.scroll.saturating_sub(10),
+            Action::PgDown => matrix.scroll = (matrix.scroll + 10).min(last_row),
+            Action::Input(c @ '1'..='6') => {
+                matrix.column = c as usize - '1' as usize;
+                self.set_flag_column(&matrix, None);
             }
-            EditorFocus::FieldList => {
-                if let Some(ty) = self.types.get_mut(self.selected_type) {
-                    if let Some(field) = ty.fields.get(self.selected_field).cloned() {
-                        ty.fields.push(field);
-                        self.selected_field = ty.fields.len().saturating_sub(1);
-                        self.status = String::from("Field copied");
-                    }
-                }
+            Action::Input('+') => self.set_flag_column(&matrix, Some(true)),
+            Action::Input('-') => self.set_flag_column(&matrix, Some(false)),
+            _ => {}
+        }
+        self.overlay = Some(Overlay::FlagMatrix(matrix));
+    }
+
+    /// Sets the matrix's column on every row, to `value` or else the value most rows have
+    /// (the default on a tie), in one undo step. Rows without `<flags>` get the defaults
+    /// first, or are skipped with `create_missing_flags` off.
+    fn set_flag_column(&mut self, matrix: &FlagMatrix, value: Option<bool>) {
+        let flag = flags::FLAGS[matrix.column];
+        let on = value.unwrap_or_else(|| {
+            let count = |v: &str| matrix.targets.iter().filter(|&&i| flags::flag_of(&self.types[i], flag) == Some(v)).count();
+            let (ones, zeros) = (count("1"), count("0"));
+            if ones == zeros { flags::default_of(flag) } else { ones > zeros }
+        });
+        let wanted = if on { "1" } else { "0" };
+        let create = self.config.create_missing_flags;
+        let has_flags = |ty: &TypeEntry| ty.fields.iter().any(|f| f.key.get_element_name() == "flags");
+        let skipped = if create { 0 } else { matrix.targets.iter().filter(|&&i| !has_flags(&self.types[i])).count() };
+        let skip_note = match skipped {
+            0 => String::new(),
+            n => format!("; {} without <flags> skipped (create_missing_flags is off)", utils::plural(n, "type")),
+        };
+        let targets: Vec<usize> = matrix
+            .targets
+            .iter()
+            .copied()
+            .filter(|&i| flags::flag_of(&self.types[i], flag) != Some(wanted) && (create || has_flags(&self.types[i])))
+            .collect();
+        if targets.is_empty() {
+            self.status = format!("All {} already have {flag}={wanted}{skip_note}", utils::plural(matrix.targets.len() - skipped, "type"));
+            return;
+        }
+        let described = self.describe_targets(&targets);
+        self.push_undo(format!("set {flag}={wanted} on {described}"));
+        let mut created = 0;
+        for &idx in &targets {
+            if flags::set_flag(&mut self.types[idx], flag, on, create) == flags::FlagSet::Created {
+                created += 1;
             }
-            EditorFocus::Editing => {}
+            self.note_change(idx);
+            self.revalidate_type(idx);
         }
+        self.last_edit = Some(LastEdit::Flag { flag, on });
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        let created_note = match created {
+            0 => String::new(),
+            n => format!(", created flags on {n}"),
+        };
+        self.undoable(format!("Set {flag}={wanted} on {described}{created_note}{skip_note}"));
     }
 
-    fn delete(&mut self) {
-        match self.focus {
-            EditorFocus::TypeList => {
-                if !self.types.is_empty() {
-                    self.types.remove(self.selected_type);
-                    if self.selected_type >= self.types.len() && !self.types.is_empty() {
-                        self.selected_type = self.types.len() - 1;
-                    } else if self.types.is_empty() {
-                        self.selected_type = 0;
-                    }
-                    self.selected_field = 0;
-                    self.status = String::from("Type deleted");
-                }
+    /// Opens the paired editor when the highlighted field is quantmin or quantmax and the type has both.
+    fn open_quantity_tool(&mut self) -> bool {
+        let Some(ty) = self.types.get(self.selected_type) else {
+            return false;
+        };
+        let (on_max, index) = match self.current_field().map(|f| &f.key) {
+            Some(&FieldKey::Element { ref name, index }) if name == "quantmin" => (false, index),
+            Some(&FieldKey::Element { ref name, index }) if name == "quantmax" => (true, index),
+            _ => return false,
+        };
+        // The highlighted field, and its partner at the same position (else the first one).
+        let partner = |name: &str| {
+            let at = |f: &Field| matches!(&f.key, FieldKey::Element { name: n, index: i } if n == name && *i == index);
+            ty.fields.iter().position(at).or_else(|| ty.element_field(name))
+        };
+        let (min_field, max_field) = if on_max {
+            (partner("quantmin"), Some(self.selected_field))
+        } else {
+            (Some(self.selected_field), partner("quantmax"))
+        };
+        let (Some(min_field), Some(max_field)) = (min_field, max_field) else {
+            return false;
+        };
+        let min_input = ty.fields[min_field].value.trim().to_string();
+        let max_input = ty.fields[max_field].value.trim().to_string();
+        let unused = min_input == "-1" && max_input == "-1";
+        self.overlay = Some(Overlay::Quantity(QuantityTool {
+            min_field,
+            max_field,
+            min_input: if unused { String::new() } else { min_input },
+            max_input: if unused { String::new() } else { max_input },
+            unused,
+            on_max,
+        }));
+        true
+    }
+
+    fn handle_quantity_action(&mut self, mut tool: QuantityTool, action: Action) {
+        let input = if tool.on_max { &mut tool.max_input } else { &mut tool.min_input };
+        match action {
+            Action::Cancel => return,
+            Action::Input(c) if c.is_ascii_digit() && !tool.unused => input.push(c),
+            Action::Backspace => {
+                input.pop();
             }
-            EditorFocus::FieldList => {
-                if let Some(ty) = self.types.get_mut(self.selected_type) {
-                    if !ty.fields.is_empty() {
-                        ty.fields.remove(self.selected_field);
-                        if self.selected_field >= ty.fields.len() && !ty.fields.is_empty() {
-                            self.selected_field = ty.fields.len() - 1;
-                        } else if ty.fields.is_empty() {
-                            self.selected_field = 0;
-                        }
-                        self.status = String::from("Field deleted");
+            Action::Tab | Action::BackTab | Action::Left | Action::Right => tool.on_max = !tool.on_max,
+            Action::Undo => tool.unused = !tool.unused,
+            Action::ToggleRemote => {
+                self.selected_field = if tool.on_max { tool.max_field } else { tool.min_field };
+                self.begin_field_value_edit();
+                return;
+            }
+            Action::Activate => match tool.check() {
+                Ok((min, max)) => {
+                    let type_idx = self.selected_type;
+                    let label = if tool.unused { String::from("unused") } else { format!("{min}–{max}%") };
+                    self.push_undo(format!("set quantity to {label}"));
+                    if let Some(ty) = self.types.get_mut(type_idx) {
+                        ty.fields[tool.min_field].value = min.to_string();
+                        ty.fields[tool.max_field].value = max.to_string();
+                        self.last_edit = Some(LastEdit::Values(vec![
+                            ty.fields[tool.min_field].clone(),
+                            ty.fields[tool.max_field].clone(),
+                        ]));
                     }
+                    self.note_change(type_idx);
+                    self.revalidate_type(type_idx);
+                    self.status = format!("Quantity set to {label}");
+                    return;
                 }
-            }
-            EditorFocus::Editing => {}
+                Err(message) => self.status = message,
+            },
+            _ => {}
         }
+        self.overlay = Some(Overlay::Quantity(tool));
     }
 
-    fn current_fields(&self) -> Vec<Field> {
-        self.types
-            .get(self.selected_type)
-            .map(|t| t.fields.clone())
-            .unwrap_or_default()
+    fn begin_command(&mut self) {
+        self.command_return_focus = self.focus;
+        self.input_buffer.clear();
+        self.editing_target = Some(EditTarget::Command);
+        self.focus = EditorFocus::Editing;
     }
 
-    fn current_fields_len(&self) -> usize {
-        self.types.get(self.selected_type).map(|t| t.fields.len()).unwrap_or(0)
+    fn show_about(&mut self) {
+        let dirs = AppDirs::locate();
+        let project = match (&self.project_config_path, &self.source) {
+            (Some(path), FileSource::Remote(_)) => format!("{} (remote)", path.display()),
+            (Some(path), FileSource::Local) => path.display().to_string(),
+            (None, _) => format!("none ({PROJECT_FILE} next to the open file)"),
+        };
+        let backups = match self.config.backup_keep {
+            0 => String::from("disabled (backup_keep = 0)"),
+            keep => format!("next to the open file, keeping {keep} (backup_keep)"),
+        };
+        let mut lines: Vec<String> = paths::version_text().lines().map(String::from).collect();
+        lines.extend([
+            String::new(),
+            format!("Config file:       {}", paths::describe(dirs.config_file().as_deref())),
+            format!("Project config:    {project}"),
+            format!("Session file:      {}", paths::describe(dirs.session_file().as_deref())),
+            format!("Field help:        {}", paths::describe(dirs.help_file().as_deref())),
+            String::from("SSH profiles:      SSH_* environment variables (no profiles file)"),
+            String::from("Log file:          none (:messages shows this session's log)"),
+            format!("Backups:           {backups}"),
+            String::from("Optional features: none in this build"),
+        ]);
+        self.overlay = Some(Overlay::Text {
+            title: String::from("About"),
+            lines,
+            scroll: 0,
+        });
     }
 
-    fn current_field(&self) -> Option<&Field> {
-        self.types
-            .get(self.selected_type)
-            .and_then(|t| t.fields.get(self.selected_field))
+    /// `:doctor`: the `doctor` checks without the SSH login, which would block the UI.
+    fn show_doctor(&mut self) {
+        let checks = doctor::run_all(false);
+        self.overlay = Some(Overlay::Text {
+            title: format!("Doctor: {}", doctor::worst(&checks).tag()),
+            lines: checks.iter().flat_map(doctor::Check::lines).collect(),
+            scroll: 0,
+        });
     }
 
-    fn current_field_mut(&mut self) -> Option<&mut Field> {
-        self.types
-            .get_mut(self.selected_type)
-            .and_then(|t| t.fields.get_mut(self.selected_field))
+    fn begin_goto(&mut self) {
+        self.command_return_focus = self.focus;
+        self.input_buffer.clear();
+        self.editing_target = Some(EditTarget::Goto);
+        self.focus = EditorFocus::Editing;
+    }
+
+    /// Jumps to the type named `query` (any case); otherwise offers the closest names.
+    /// An empty query goes back to where the last jump came from.
+    fn goto_type(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            self.travel_jumps(false);
+            return;
+        }
+        if let Some(idx) = self.types.iter().position(|t| t.name.eq_ignore_ascii_case(query)) {
+            self.jump_to_type(idx);
+            return;
+        }
+        let matches = fuzzy::top_matches(query, self.types.iter().map(|t| t.name.as_str()), GOTO_MATCHES);
+        match matches.as_slice() {
+            [] => self.status = format!("No type matches '{query}'"),
+            [only] => self.jump_to_type(*only),
+            _ => {
+                self.overlay = Some(Overlay::GotoPick {
+                    query: query.to_string(),
+                    matches,
+                    cursor: 0,
+                });
+            }
+        }
     }
-}
 
-fn field_label(key: &FieldKey) -> String {
-    match key {
-        FieldKey::Element { name, .. } => name.clone(),
-        FieldKey::Attribute { element, attr, .. } => format!("{} @{}", element, attr),
+    /// Selects a type by model index, clearing a filter that hides it.
+    fn jump_to_type(&mut self, idx: usize) {
+        if idx != self.selected_type {
+            self.record_jump();
+        }
+        self.selected_type = idx;
+        self.selected_field = 0;
+        let cleared = self.reveal_type(idx);
+        let name = &self.types[idx].name;
+        if !cleared {
+            self.status = format!("{name} (Ctrl+O jumps back)");
+        } else {
+            self.status = format!("{name} (filter cleared to show it; Ctrl+O jumps back)");
+        }
     }
-}
 
-fn highlight_for(active: bool) -> Style {
-    if active {
-        Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
-    } else {
-        Style::default()
+    fn here(&self) -> Option<Jump> {
+        Some(Jump {
+            type_name: self.types.get(self.selected_type)?.name.clone(),
+            type_idx: self.selected_type,
+            field: self.selected_field,
+        })
     }
-}
 
-fn render_help_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
-    let area = utils::centered_rect(70, 70, f.size());
-    let text = "Editor Help\n\nNavigation: Up/Down or j/k or PageUp/PageDown to move, Left/Right to switch pane\nEditing: Enter to edit, Esc to cancel, type to change text, Enter to apply\nActions: a add (type or field), t add field with attribute, c copy, d delete, s save, q quit, ? help";
-    let block = Block::default().title("Help").borders(Borders::ALL);
-    let help = Paragraph::new(text).wrap(Wrap { trim: true }).block(block);
-    f.render_widget(Clear, area);
-    f.render_widget(help, area);
-}
+    /// Remembers the current place before a jump; forward entries are dropped, as in vim.
+    fn record_jump(&mut self) {
+        let Some(here) = self.here() else {
+            return;
+        };
+        self.jumps.truncate(self.jump_pos);
+        if self.jumps.last() != Some(&here) {
+            self.jumps.push(here);
+        }
+        if self.jumps.len() > JUMP_LIMIT {
+            self.jumps.remove(0);
+        }
+        self.jump_pos = self.jumps.len();
+    }
 
+    /// The type a jump points at now, if it still exists.
+    fn resolve_jump(&self, jump: &Jump) -> Option<usize> {
+        match self.types.get(jump.type_idx) {
+            Some(ty) if ty.name == jump.type_name => Some(jump.type_idx),
+            _ => self.types.iter().position(|t| t.name == jump.type_name),
+        }
+    }
 
-fn parse_types(content: &str) -> Result<Vec<TypeEntry>, xml::reader::Error> {
-    let parser = EventReader::new(content.as_bytes());
-    let mut types = Vec::new();
-    let mut current: Option<TypeEntry> = None;
-    let mut element_indices: HashMap<String, usize> = HashMap::new();
-    let mut current_element: Option<(String, usize)> = None;
+    /// Ctrl+O / Ctrl+I: moves through the jump list, skipping places whose type was deleted
+    /// and the place the cursor is already at.
+    fn travel_jumps(&mut self, forward: bool) {
+        let leaving_present = !forward && self.jump_pos >= self.jumps.len();
+        if leaving_present && let Some(here) = self.here() {
+            // So that Ctrl+I can come back here.
+            self.jumps.push(here);
+            self.jump_pos = self.jumps.len() - 1;
+        }
+        let here = self.here();
+        let candidates: Vec<usize> = if forward {
+            (self.jump_pos + 1..self.jumps.len()).collect()
+        } else {
+            (0..self.jump_pos.min(self.jumps.len())).rev().collect()
+        };
+        let mut skipped = 0;
+        for pos in candidates {
+            let jump = self.jumps[pos].clone();
+            let Some(type_idx) = self.resolve_jump(&jump) else {
+                skipped += 1;
+                continue;
+            };
+            if here.as_ref().is_some_and(|h| h.type_name == jump.type_name && h.field == jump.field) {
+                continue;
+            }
+            self.jump_pos = pos;
+            self.selected_type = type_idx;
+            self.selected_field = jump.field.min(self.current_fields_len().saturating_sub(1));
+            self.reveal_type(type_idx);
+            let mut status = format!("{} (jump {}/{})", jump.type_name, pos + 1, self.jumps.len());
+            if skipped > 0 {
+                status.push_str(&format!("; skipped {} to deleted types", utils::plural(skipped, "jump")));
+            }
+            self.status = status;
+            return;
+        }
+        if leaving_present {
+            self.jumps.pop();
+            self.jump_pos = self.jumps.len();
+        }
+        let direction = if forward { "later" } else { "earlier" };
+        self.status = match skipped {
+            0 => format!("No {direction} jump"),
+            n => format!("No {direction} jump; {} to deleted types skipped", utils::plural(n, "jump")),
+        };
+    }
 
-    for event in parser {
-        match event? {
-            XmlEvent::StartElement { name, attributes, .. } => {
-                let el = name.local_name;
-                if el == "type" {
-                    let name_attr = attributes
-                        .iter()
-                        .find(|a| a.name.local_name == "name")
-                        .map(|a| a.value.clone())
-                        .unwrap_or_default();
-                    current = Some(TypeEntry {
-                        name: name_attr,
-                        fields: Vec::new(),
-                    });
-                    element_indices.clear();
-                    current_element = None;
+    fn run_command(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return;
+        };
+        if MUTATING_COMMANDS.contains(&command) && self.refuse_if_readonly() {
+            return;
+        }
+        if BULK_COMMANDS.contains(&command) {
+            let rest = line.trim()[command.len()..].trim();
+            if rest.is_empty()
+                && let Some(args) = self.bulk_args.get(command).cloned()
+            {
+                self.prefill_bulk(command, args);
+                return;
+            }
+            match self.bulk_prefill.take() {
+                Some(filled) if filled == line.trim() => {
+                    self.reusing_bulk(command, format!("Using the last :{command} arguments ({rest})"));
+                }
+                // Edited: new arguments, and "filled in" no longer holds.
+                Some(_) => self.status.clear(),
+                None => {}
+            }
+        }
+        match command {
+            "dedupe" => self.open_duplicate_review(),
+            "refs" => self.show_references(),
+            "cost" => self.normalize_cost(),
+            "tiers" => self.open_tier_tool(),
+            "lifetime" => self.open_lifetime_presets(),
+            "classnames" => self.classnames_command(&words.collect::<Vec<_>>().join(" ")),
+            "fixcase" => self.open_case_review(),
+            "stripfield" => self.open_strip(&words.collect::<Vec<_>>().join(" ")),
+            "flags" => self.open_flag_matrix(),
+            "cap" => match words.next() {
+                None => self.open_cap_tool(false),
+                Some("quant" | "quantity") => self.open_cap_tool(true),
+                Some(other) => self.status = format!("Unknown :cap option '{other}'; :cap quant also scales quantmin/quantmax"),
+            },
+            "stats" => self.overlay = Some(Overlay::Statistics),
+            "validate" => {
+                self.revalidate();
+                self.overlay = Some(Overlay::ValidationReport);
+            }
+            "about" => self.show_about(),
+            "doctor" => self.show_doctor(),
+            "setup" if ConfigLayer::user_path().is_none() => {
+                self.fail(String::from("No config directory (HOME is unset); :setup has nowhere to write"));
+            }
+            "setup" => self.setup_requested = true,
+            "skeleton" => match ConfigLayer::user_path() {
+                Some(path) => self.save_skeleton(&path, words.next() == Some("reset")),
+                None => self.fail(String::from("No config directory (HOME is unset); :skeleton has nowhere to write")),
+            },
+            "changedby" => {
+                let author = words.collect::<Vec<_>>().join(" ");
+                self.show_changed_by(Some(author.as_str()).filter(|a| !a.is_empty()));
+            }
+            "report" => {
+                let changes = diff::diff_types(&self.baseline, &self.types);
+                if changes.is_empty() {
+                    self.status = String::from("No changes since the last load or save");
                 } else {
-                    let idx = *element_indices.entry(el.clone()).or_insert(0);
-                    if let Some(count) = element_indices.get_mut(&el) {
-                        *count += 1;
-                    }
-                    if let Some(ref mut t) = current {
-                        for attr in attributes {
-                            t.fields.push(Field {
-                                key: FieldKey::Attribute {
-                                    element: el.clone(),
-                                    index: idx,
-                                    attr: attr.name.local_name,
-                                },
-                                value: attr.value,
-                            });
-                        }
+                    match self.write_change_report(&changes) {
+                        Ok(path) => self.log(format!("Change report of unsaved changes written to {}", path.display())),
+                        Err(err) => self.fail(format!("Change report failed: {err}")),
                     }
-                    current_element = Some((el, idx));
                 }
             }
-            XmlEvent::Characters(text) => {
-                let trimmed = text.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                if let (Some((el, idx)), Some(ref mut t)) = (current_element.clone(), current.as_mut()) {
-                    t.fields.push(Field {
-                        key: FieldKey::Element { name: el, index: idx },
-                        value: trimmed.to_string(),
-                    });
-                }
+            "calc" => self.open_calculator(&words.collect::<Vec<_>>().join(" ")),
+            "messages" => {
+                self.overlay = Some(Overlay::Text {
+                    title: format!("Messages ({})", self.messages.len()),
+                    lines: self.messages.lines(),
+                    scroll: 0,
+                });
             }
-            XmlEvent::EndElement { name } => {
-                let el = name.local_name;
-                if el == "type" {
-                    if let Some(t) = current.take() {
-                        types.push(t);
-                    }
-                    element_indices.clear();
+            "warnings" => {
+                let lines = if self.parse_warnings.is_empty() {
+                    vec![String::from("No parse warnings for this file.")]
+                } else {
+                    self.parse_warnings
+                        .iter()
+                        .map(|w| format!("line {}: {}", w.line, w.message))
+                        .collect()
+                };
+                self.overlay = Some(Overlay::Text {
+                    title: format!("Parse warnings ({})", self.parse_warnings.len()),
+                    lines,
+                    scroll: 0,
+                });
+            }
+            "backups" | "restore" => self.open_backup_browser(),
+            "inline" => self.cycle_inline_values(),
+            "apply" => self.open_bulk_apply(&words.collect::<Vec<_>>()),
+            "addusage" => self.open_assignment("usage", &words.collect::<Vec<_>>(), false),
+            "addtag" => self.open_assignment("tag", &words.collect::<Vec<_>>(), false),
+            "setcategory" => self.open_assignment("category", &words.collect::<Vec<_>>(), false),
+            "again" => self.repeat_bulk(),
+            "goto" => self.goto_type(&words.collect::<Vec<_>>().join(" ")),
+            "panes" => match words.next() {
+                Some("reset") => self.set_panes(DEFAULT_PANES),
+                Some(other) => self.status = format!("Unknown :panes option '{other}'; try :panes reset"),
+                None => {
+                    let [types, fields, tips] = self.panes;
+                    self.status = format!("Panes {types}/{fields}/{tips}; Ctrl+Left/Right resize, :panes reset");
                 }
-                current_element = None;
+            },
+            "undo" => self.restore_snapshot(false),
+            "redo" => self.restore_snapshot(true),
+            "history" => {
+                self.overlay = Some(Overlay::History {
+                    cursor: self.redo_stack.len(),
+                })
             }
-            _ => {}
+            "tutorial" => self.start_tutorial(),
+            "dryrun" => self.dry_run(),
+            "readonly" if self.readonly => self.overlay = Some(Overlay::ConfirmWritable),
+            "readonly" => {
+                self.readonly = true;
+                self.log(String::from("Read-only mode on; edits and saves are refused"));
+            }
+            other => self.status = format!("Unknown command: {other}"),
         }
     }
 
-    Ok(types)
-}
+    /// The cfgspawnabletypes.xml beside the file or one folder up. It is read on first use
+    /// and read again once its stamp or the types file's has changed.
+    fn spawnables(&mut self) -> Option<&Spawnables> {
+        self.refresh_spawnables();
+        self.spawnables.as_ref()?.spawnables.as_ref()
+    }
 
-fn serialize_types(types: &[TypeEntry]) -> io::Result<String> {
-    let mut buf: Vec<u8> = Vec::new();
-    {
-        let mut writer = EmitterConfig::new()
-            .perform_indent(true)
-            .create_writer(&mut buf);
+    fn refresh_spawnables(&mut self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let found = beside_or_above(&path, spawnable::SPAWNABLE_FILE)
+            .into_iter()
+            .find_map(|p| Some((self.source.stamp(&p).ok()?, p)));
+        let stamps = (self.source.stamp(&path).ok(), found.as_ref().map(|(stamp, _)| *stamp));
+        if self.spawnables.as_ref().is_none_or(|cache| cache.stamps != stamps) {
+            let spawnables = found.and_then(|(_, p)| self.source.read_file(&p).ok()).map(|c| spawnable::parse(&c));
+            self.spawnables = Some(SpawnableCache { stamps, spawnables });
+        }
+    }
 
-        writer
-            .write(xml::writer::XmlEvent::start_element("types"))
-            .map_err(to_io)?;
+    /// `:refs`: what cfgspawnabletypes.xml names that the types file lacks, and the reverse.
+    fn show_references(&mut self) {
+        self.refresh_spawnables();
+        let Some(spawnables) = self.spawnables.as_ref().and_then(|cache| cache.spawnables.as_ref()) else {
+            self.status = format!("No {} next to this file or one folder up", spawnable::SPAWNABLE_FILE);
+            return;
+        };
+        self.overlay = Some(Overlay::Text {
+            title: format!("References between this file and {}", spawnable::SPAWNABLE_FILE),
+            lines: spawnables.dangling_report(&self.types),
+            scroll: 0,
+        });
+    }
 
-        for t in types {
-            let type_element = xml::writer::XmlEvent::start_element("type").attr("name", t.name.as_str());
-            writer.write(type_element).map_err(to_io)?;
+    fn read_source_file(&self, path: &Path) -> io::Result<String> {
+        self.source.read_file(path)
+    }
 
-            let mut order: Vec<(String, usize)> = Vec::new();
-            let mut seen: HashSet<(String, usize)> = HashSet::new();
-            let mut element_map: HashMap<(String, usize), ElementData> = HashMap::new();
+    /// Backups next to the open file; listing errors count as "none".
+    fn list_backups(&self) -> Vec<BackupFile> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        let dir = path.parent().unwrap_or(Path::new("."));
+        backup::backups_of(path, self.source.list_dir(dir).unwrap_or_default())
+    }
 
-            for field in &t.fields {
-                let key = match &field.key {
-                    FieldKey::Element { name, index } => (name.clone(), *index),
-                    FieldKey::Attribute { element, index, .. } => (element.clone(), *index),
-                };
-                if seen.insert(key.clone()) {
-                    order.push(key.clone());
-                }
-                let entry = element_map.entry(key).or_insert_with(ElementData::default);
-                match &field.key {
-                    FieldKey::Element { .. } => entry.text = Some(field.value.clone()),
-                    FieldKey::Attribute { attr, .. } => entry.attrs.push((attr.clone(), field.value.clone())),
-                }
+    /// `:addusage <value> <pattern>` and friends: previews which types would get the value,
+    /// or which of the marked ones for `:again`.
+    fn open_assignment(&mut self, element: &str, args: &[&str], on_marked: bool) {
+        let command = if element == "category" { String::from("setcategory") } else { format!("add{element}") };
+        let [value, pattern] = args else {
+            self.status = format!("Usage: :{command} <{element}> <type pattern>, e.g. :{command} Military Ammo*");
+            return;
+        };
+        let planned = if on_marked {
+            let marked: Vec<usize> = self.marked_types().into_iter().collect();
+            assign::plan_marked(&self.types, element, value, pattern, &marked)
+        } else {
+            assign::plan(&self.types, element, value, pattern)
+        };
+        let assignment = match planned {
+            Ok(assignment) => assignment,
+            Err(err) => {
+                self.fail(format!(":{command}: {err}"));
+                return;
             }
+        };
+        self.remember_bulk(&command, args.join(" "));
+        if assignment.changes.is_empty() {
+            let scope = if on_marked { String::from("marked") } else { format!("matching '{pattern}'") };
+            self.status = match assignment.skipped.len() {
+                0 => format!("No type matches '{pattern}'"),
+                n => format!("All {} {scope} are already {}", utils::plural(n, "type"), assignment.describe()),
+            };
+            return;
+        }
+        self.overlay = Some(Overlay::Assign(assignment, 0));
+    }
 
-            for (element, index) in order {
-                if let Some(data) = element_map.get(&(element.clone(), index)) {
-                    let mut elem = xml::writer::XmlEvent::start_element(element.as_str());
-                    for (k, v) in &data.attrs {
-                        elem = elem.attr(k.as_str(), v.as_str());
-                    }
-                    writer.write(elem).map_err(to_io)?;
-                    if let Some(text) = &data.text {
-                        writer.write(xml::writer::XmlEvent::characters(text)).map_err(to_io)?;
-                    }
-                    writer.write(xml::writer::XmlEvent::end_element()).map_err(to_io)?;
-                }
-            }
+    /// Keeps a bulk command's arguments for a bare `:command` and `:again`; with
+    /// `remember_bulk`, in the session file too.
+    fn remember_bulk(&mut self, command: &str, args: String) {
+        self.bulk_args.insert(command.to_string(), args);
+        self.last_bulk = Some(command.to_string());
+        if !self.config.remember_bulk {
+            return;
+        }
+        let session = Session { bulk: self.bulk_args.clone(), last_bulk: self.last_bulk.clone(), ..Session::load() };
+        if let Err(err) = session.save() {
+            self.messages.push_error(format!("Could not keep the bulk arguments in the session file: {err}"));
+        }
+    }
+
+    /// A bare bulk command: its last arguments go on the command line for Enter, or an
+    /// edit first.
+    fn prefill_bulk(&mut self, command: &str, args: String) {
+        self.begin_command();
+        self.input_buffer = format!("{command} {args}");
+        self.bulk_prefill = Some(self.input_buffer.clone());
+        self.status = format!("Last :{command} filled in; Enter runs it again, or edit it first");
+    }
+
+    /// Says that earlier arguments are in use, now and, for `:apply`, once its dry run is
+    /// done; the preview itself is worked out afresh either way.
+    fn reusing_bulk(&mut self, command: &str, message: String) {
+        self.bulk_note = (command == "apply").then(|| String::from("last :apply arguments"));
+        self.status = message;
+    }
 
-            writer
-                .write(xml::writer::XmlEvent::end_element())
-                .map_err(to_io)?;
+    /// `:again`: the last bulk command with its last arguments, straight to its preview.
+    /// `:addusage` and friends go to the marked types when some are marked, instead of
+    /// the ones their pattern matches.
+    fn repeat_bulk(&mut self) {
+        let last = self.last_bulk.clone().and_then(|command| {
+            let args = self.bulk_args.get(&command)?.clone();
+            Some((command, args))
+        });
+        let Some((command, args)) = last else {
+            self.status = format!("No bulk command to repeat yet ({})", BULK_COMMANDS.map(|c| format!(":{c}")).join(", "));
+            return;
+        };
+        let marked = self.marked_types().len();
+        let element = match command.as_str() {
+            "addusage" => Some("usage"),
+            "addtag" => Some("tag"),
+            "setcategory" => Some("category"),
+            _ => None,
+        };
+        match element {
+            Some(element) if marked > 0 => {
+                self.reusing_bulk(&command, format!("Repeating :{command} {args} on {}", utils::plural(marked, "marked type")));
+                self.open_assignment(element, &args.split_whitespace().collect::<Vec<_>>(), true);
+            }
+            _ => {
+                self.reusing_bulk(&command, format!("Repeating :{command} {args}"));
+                self.run_command(&format!("{command} {args}"));
+            }
         }
+    }
 
-        writer
-            .write(xml::writer::XmlEvent::end_element())
-            .map_err(to_io)?;
+    /// `:classnames <file>` loads a classname list (a local file) for the session; without
+    /// a file, says which one is in use.
+    fn classnames_command(&mut self, path: &str) {
+        if path.is_empty() {
+            self.status = match &self.classnames {
+                Some(list) => format!("{} classnames from {} (:fixcase respells type names)", list.len(), list.source),
+                None => String::from("No classname list; :classnames <file>, one classname per line"),
+            };
+            return;
+        }
+        match read_classnames(Path::new(path)) {
+            Ok(list) => {
+                let message = format!("Loaded {} classnames from {}; :fixcase respells type names", list.len(), list.source);
+                self.classnames = Some(list);
+                self.log(message);
+            }
+            Err(err) => self.fail(format!("Can't read {path}: {err}")),
+        }
     }
 
-    let xml_string = String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    Ok(xml_string)
-}
+    /// `:fixcase`: previews the type names that differ from the classname list only in case.
+    fn open_case_review(&mut self) {
+        let Some(list) = &self.classnames else {
+            self.status = String::from("No classname list; load one with :classnames <file> or set classnames in the config");
+            return;
+        };
+        let review = classnames::review(&self.types, list);
+        if review.fixes.is_empty() && review.clashes.is_empty() && review.unknown.is_empty() {
+            self.status = format!("All {} are spelled as in {}", utils::plural(self.types.len(), "type name"), review.source);
+            return;
+        }
+        self.overlay = Some(Overlay::CaseFixes(review, 0));
+    }
 
-#[derive(Default)]
-struct ElementData {
-    attrs: Vec<(String, String)>,
-    text: Option<String>,
-}
+    /// Renames the reviewed types in one undo step, keeping their marks and change counts.
+    fn apply_case_fixes(&mut self, review: CaseReview) {
+        if review.fixes.is_empty() {
+            self.status = String::from("Nothing to rename");
+            return;
+        }
+        let count = utils::plural(review.fixes.len(), "type");
+        self.push_undo(format!("respell {count} as in {}", review.source));
+        let marked = self.selected_types.indices(&self.types);
+        classnames::apply(&mut self.types, &review.fixes);
+        self.selected_types.clear();
+        for idx in marked {
+            self.selected_types.insert(&self.types, idx);
+        }
+        for fix in &review.fixes {
+            if let Some(edits) = self.session_changes.remove(&fix.from) {
+                self.session_changes.insert(fix.to.clone(), edits);
+            }
+            self.note_change(fix.type_idx);
+        }
+        self.revalidate();
+        let mut message = format!("Respelled {count} as in {}", review.source);
+        if !review.unknown.is_empty() {
+            message.push_str(&format!("; {} not in the list", utils::plural(review.unknown.len(), "name")));
+        }
+        self.undoable(message);
+    }
 
-fn to_io<T>(err: T) -> io::Error
-where
-    T: Into<xml::writer::Error>,
-{
-    let err: xml::writer::Error = err.into();
-    io::Error::new(io::ErrorKind::Other, err)
-}
+    /// `:stripfield <element|element@attr>`: previews removing the field from every type,
+    /// or from the marked ones when some are marked.
+    fn open_strip(&mut self, spec: &str) {
+        let target = match StripTarget::parse(spec) {
+            Ok(target) => target,
+            Err(err) => {
+                self.fail(format!(":stripfield: {err}"));
+                return;
+            }
+        };
+        self.remember_bulk("stripfield", spec.trim().to_string());
+        let marked = self.marked_types();
+        let indices: Vec<usize> = if marked.is_empty() { (0..self.types.len()).collect() } else { marked.into_iter().collect() };
+        let plan = strip::plan(&self.types, &indices, target, indices.len() < self.types.len());
+        if plan.changes.is_empty() {
+            let scope = if plan.marked_only { "marked type" } else { "type" };
+            self.status = format!("No {scope} has {}", plan.target.describe());
+            return;
+        }
+        self.overlay = Some(Overlay::StripFields(plan, 0));
+    }
 
-fn default_fields() -> Vec<Field> {
-    vec![
-        Field {
-            key: FieldKey::Element {
-                name: "nominal".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "lifetime".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "restock".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "min".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "quantmin".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "quantmax".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Element {
-                name: "cost".to_string(),
-                index: 0,
-            },
-            value: String::new(),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "count_in_cargo".to_string(),
-            },
-            value: String::from("0"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "count_in_hoarder".to_string(),
-            },
-            value: String::from("0"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "count_in_map".to_string(),
-            },
-            value: String::from("1"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "count_in_player".to_string(),
-            },
-            value: String::from("0"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "crafted".to_string(),
-            },
-            value: String::from("0"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "flags".to_string(),
-                index: 0,
-                attr: "deloot".to_string(),
+    fn apply_strip(&mut self, plan: StripPlan) {
+        let count = utils::plural(plan.changes.len(), "type");
+        let what = plan.target.describe();
+        self.push_undo(format!("strip {what} from {count}"));
+        let removed = strip::apply(&mut self.types, &plan);
+        for &(idx, _) in &plan.changes {
+            self.note_change(idx);
+        }
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.revalidate();
+        self.undoable(format!("Stripped {what} from {count} ({})", utils::plural(removed, "field")));
+    }
+
+    fn apply_assignment(&mut self, assignment: Assignment) {
+        let count = utils::plural(assignment.changes.len(), "type");
+        let verb = if assignment.replaces() { "set" } else { "add" };
+        self.push_undo(format!("{verb} {} on {count}", assignment.describe()));
+        assign::apply(&mut self.types, &assignment);
+        for change in &assignment.changes {
+            self.note_change(change.type_idx);
+        }
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.revalidate();
+        self.last_edit = Some(LastEdit::Assign {
+            element: assignment.element.clone(),
+            value: assignment.value.clone(),
+        });
+        let mut message = if assignment.replaces() {
+            let replaced = assignment.changes.iter().filter(|c| !c.replaces.is_empty()).count();
+            format!("Set {} on {count} ({replaced} replaced)", assignment.describe())
+        } else {
+            format!("Added {} to {count}", assignment.describe())
+        };
+        if !assignment.skipped.is_empty() {
+            message.push_str(&format!("; {} already had it", assignment.skipped.len()));
+        }
+        self.undoable(message);
+    }
+
+    /// `:apply <pattern> <field> <=value|*factor> [dir]`: dry-runs the edit on every types
+    /// file under `dir` (default: the open file's directory), on the open file's machine.
+    /// A `dir` of `?` is chosen in the file picker first.
+    fn open_bulk_apply(&mut self, args: &[&str]) {
+        let (edit_args, dir) = match args {
+            [edit @ .., dir] if edit.len() == 3 => (edit, Some(PathBuf::from(dir))),
+            _ => (args, None),
+        };
+        let edit = match BulkEdit::parse(edit_args) {
+            Ok(edit) => BulkEdit { create_flags: self.config.create_missing_flags, ..edit },
+            Err(err) => {
+                self.status = format!(":apply: {err}");
+                self.bulk_note = None;
+                return;
+            }
+        };
+        self.remember_bulk("apply", args.join(" "));
+        if dir.as_deref() == Some(Path::new("?")) {
+            self.apply_awaiting_dir = Some(edit_args.iter().map(|arg| arg.to_string()).collect());
+            self.directory_requested = true;
+            return;
+        }
+        let base = self.path.as_deref().and_then(Path::parent).unwrap_or(Path::new(".")).to_path_buf();
+        let root = match dir {
+            Some(dir) if dir.is_relative() => base.join(dir),
+            Some(dir) => dir,
+            None => base,
+        };
+        let source = self.source.clone();
+        let normalize = self.config.normalize_names;
+        let title = format!("Dry run of {}", edit.describe());
+        self.job = Some(EditorJob::DryRun(progress::spawn(title, move |progress| {
+            bulk::dry_run(&source, edit, root, normalize, progress)
+        })));
+    }
+
+    /// Writes every file of the dry run that has changes on a worker thread.
+    fn start_bulk_write(&mut self, run: DryRun) {
+        let source = self.source.clone();
+        let canonical = self.config.canonical_order;
+        let endings = self.config.line_endings;
+        let keep = self.config.backup_keep.max(1);
+        let title = format!("Applying {}", run.edit.describe());
+        self.job = Some(EditorJob::Write(
+            run.edit,
+            progress::spawn(title, move |progress| {
+                bulk::write_all(&source, run.files, canonical, endings, keep, progress)
+            }),
+        ));
+    }
+
+    /// Polls the running job; finished results are taken in here.
+    fn poll_job(&mut self) {
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        match job {
+            EditorJob::DryRun(running) => match running.poll() {
+                None => {}
+                Some(Err(err)) => {
+                    self.job = None;
+                    self.fail(format!("Dry run failed: {err}"));
+                }
+                Some(Ok(None)) => {
+                    self.job = None;
+                    self.status = String::from("Dry run cancelled; nothing written");
+                }
+                Some(Ok(Some(run))) => {
+                    self.job = None;
+                    if run.files.is_empty() {
+                        self.status = format!("No types files under {}", run.root.display());
+                    } else {
+                        self.status = format!("Dry run of {} over {} files", run.edit.describe(), run.files.len());
+                        if let Some(note) = self.bulk_note.take() {
+                            self.status.push_str(&format!(" ({note})"));
+                        }
+                        self.overlay = Some(Overlay::BulkApply(run, 0));
+                    }
+                }
             },
-            value: String::from("0"),
-        },
-        Field {
-            key: FieldKey::Attribute {
-                element: "category".to_string(),
-                index: 0,
-                attr: "name".to_string(),
+            EditorJob::Write(edit, running) => {
+                let result = match running.poll() {
+                    None => return,
+                    Some(result) => result,
+                };
+                let edit = edit.clone();
+                self.job = None;
+                match result {
+                    Ok(written) => self.finish_bulk_write(&edit, written),
+                    Err(err) => self.fail(format!("Apply failed: {err}; see the .bak files of any written file")),
+                }
+            }
+            EditorJob::Read(_, _, running) => {
+                let Some(result) = running.poll() else {
+                    return;
+                };
+                let transfer = (running.done as u64, running.elapsed());
+                let Some(EditorJob::Read(selection, mode, _)) = self.job.take() else {
+                    return;
+                };
+                let label = selection.path.display().to_string();
+                let opened = result.and_then(|read| read).and_then(|sources| {
+                    self.open_sources(selection, sources, Some(transfer), mode)
+                });
+                self.load_failure = match opened {
+                    Ok(()) => None,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                        Some(format!("Download of {label} cancelled"))
+                    }
+                    Err(err) => Some(format!("Failed to open file: {err}")),
+                };
+            }
+            EditorJob::Upload(_, running) => {
+                let Some(result) = running.poll() else {
+                    return;
+                };
+                let elapsed = running.elapsed();
+                let Some(EditorJob::Upload(upload, _)) = self.job.take() else {
+                    return;
+                };
+                self.finish_upload(*upload, result.and_then(|written| written), elapsed);
+            }
+            EditorJob::Rehearse(running) => {
+                let Some(result) = running.poll() else {
+                    return;
+                };
+                let cancelled = running.is_cancelled();
+                self.job = None;
+                match result.and_then(|rehearsal| rehearsal) {
+                    _ if cancelled => self.status = String::from("Dry run cancelled"),
+                    Ok(rehearsal) if rehearsal.passed() => self.log(rehearsal.summary()),
+                    Ok(rehearsal) => self.report_rehearsal(&rehearsal, ""),
+                    Err(err) => self.fail(format!("Dry run failed: {err}")),
+                }
+            }
+            EditorJob::Reload(running) => {
+                let Some(result) = running.poll() else {
+                    return;
+                };
+                self.job = None;
+                match result.and_then(|read| read) {
+                    Ok((content, types)) => self.finish_reload(content, types),
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => self.status = String::from("Reload cancelled"),
+                    Err(err) => self.fail(format!("Reload failed: {err}")),
+                }
+            }
+        }
+    }
+
+    fn finish_bulk_write(&mut self, edit: &BulkEdit, written: BulkWritten) {
+        let summary = format!(
+            "Applied {}: {} files written, {} failed{}",
+            edit.describe(),
+            written.succeeded.len(),
+            written.failed.len(),
+            if written.skipped > 0 { ", rest cancelled" } else { "" }
+        );
+        let mut lines = vec![format!("Succeeded ({}):", written.succeeded.len())];
+        for done in written.succeeded {
+            let mut line = format!("  {}: {} fields changed", done.path.display(), done.changed_fields);
+            if done.created_flags > 0 {
+                line.push_str(&format!(", created flags on {}", utils::plural(done.created_flags, "type")));
+            }
+            if self.path.as_ref() == Some(&done.path) {
+                line.push_str(self.take_bulk_write(edit, done.types, &done.xml));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+        lines.push(format!("Failed ({}):", written.failed.len()));
+        lines.extend(written.failed.iter().map(|(path, err)| format!("  {}: {err}", path.display())));
+        if written.skipped > 0 {
+            lines.push(String::new());
+            lines.push(format!("Cancelled: {} files left untouched", written.skipped));
+        }
+        lines.push(String::new());
+        lines.push(String::from("Each written file's previous version is in its .bak"));
+        self.last_edit = Some(LastEdit::Bulk(edit.clone()));
+        self.log(summary);
+        self.overlay = Some(Overlay::Text {
+            title: format!("Applied {}", edit.describe()),
+            lines,
+            scroll: 0,
+        });
+    }
+
+    /// The open file was rewritten by `:apply`: swap it in as one undoable step unless there
+    /// are unsaved edits, which the next save merges with it instead.
+    fn take_bulk_write(&mut self, edit: &BulkEdit, types: Vec<TypeEntry>, xml: &str) -> &'static str {
+        if self.types != self.baseline {
+            return " (open file has unsaved edits; saving merges them)";
+        }
+        self.push_undo(format!("apply {}", edit.describe()));
+        self.replace_types(types);
+        self.reset_baseline();
+        self.remember_disk(merge::content_hash(xml), xml.len() as u64);
+        " (reloaded in the editor; u undoes)"
+    }
+
+    fn open_backup_browser(&mut self) {
+        let backups = self.list_backups();
+        if backups.is_empty() {
+            self.status = String::from("No backups found");
+            return;
+        }
+        self.overlay = Some(Overlay::Backups(BackupBrowser {
+            backups,
+            cursor: 0,
+            preview: Vec::new(),
+        }));
+    }
+
+    fn handle_backups_action(&mut self, mut browser: BackupBrowser, action: Action) {
+        let last = browser.backups.len().saturating_sub(1);
+        match action {
+            Action::Cancel => return,
+            Action::Up => browser.cursor = browser.cursor.saturating_sub(1),
+            Action::Down => browser.cursor = (browser.cursor + 1).min(last),
+            Action::Activate => match self.preview_backup(&browser.backups[browser.cursor]) {
+                Ok(lines) => browser.preview = lines,
+                Err(err) => self.status = format!("Could not compare with the backup: {err}"),
             },
-            value: String::new(),
-        },
-    ]
+            Action::Input('o') => {
+                if let Err(err) = self.load_backup(&browser.backups[browser.cursor]) {
+                    self.status = format!("Could not load the backup: {err}");
+                }
+                return;
+            }
+            Action::Input('w') => {
+                if let Err(err) = self.restore_backup(&browser.backups[browser.cursor]) {
+                    self.status = format!("Restore failed: {err}");
+                }
+                return;
+            }
+            _ => {}
+        }
+        if matches!(action, Action::Up | Action::Down) {
+            browser.preview.clear();
+        }
+        self.overlay = Some(Overlay::Backups(browser));
+    }
+
+    fn parse_source_file(&self, path: &Path) -> io::Result<(String, Vec<TypeEntry>)> {
+        let content = self.read_source_file(path)?;
+        let (mut types, _) = parse_types(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("XML parse error: {e}")))?;
+        self.normalize(&mut types);
+        Ok((content, types))
+    }
+
+    /// `:dryrun`: writes what a save would to a temporary file, reads it back and lists
+    /// whatever differs from the editor, leaving the real file alone. The round trip runs
+    /// on a worker thread.
+    fn dry_run(&mut self) {
+        let xml = match self.serialize() {
+            Ok(xml) => xml,
+            Err(err) => return self.fail(format!("Dry run failed: {err}")),
+        };
+        let types = self.types.clone();
+        let (scratch, normalize) = (self.scratch_dir.clone(), self.config.normalize_names);
+        let title = format!("Dry run of the save ({})", sniff::format_size(xml.len() as u64));
+        self.job = Some(EditorJob::Rehearse(progress::spawn(title, move |_| rehearse(&types, &xml, &scratch, normalize))));
+    }
+
+    /// A failed rehearsal: the summary in the status and the differences in an overlay.
+    fn report_rehearsal(&mut self, rehearsal: &Rehearsal, prefix: &str) {
+        self.fail(format!("{prefix}{}", rehearsal.summary()));
+        self.overlay = Some(Overlay::Text {
+            title: String::from("Dry run"),
+            lines: rehearsal.lines(),
+            scroll: 0,
+        });
+    }
+
+    /// Applies `normalize_names` to freshly parsed types, so every copy of the file the
+    /// editor compares against has the same shape; returns how many elements changed.
+    fn normalize(&self, types: &mut [TypeEntry]) -> usize {
+        if self.config.normalize_names { model::normalize_name_elements(types) } else { 0 }
+    }
+
+    /// What restoring `backup` would change compared with the file on disk now.
+    fn preview_backup(&self, backup: &BackupFile) -> io::Result<Vec<String>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        let (_, current) = self.parse_source_file(path)?;
+        let (_, restored) = self.parse_source_file(&backup.path)?;
+        let diff = diff::diff_types(&current, &restored);
+        if diff.is_empty() {
+            return Ok(vec![String::from("The backup matches the file on disk.")]);
+        }
+        let mut lines: Vec<String> = diff.added.iter().map(|name| format!("+ {name} (only in the backup)")).collect();
+        lines.extend(diff.removed.iter().map(|name| format!("- {name} (not in the backup)")));
+        for change in &diff.changed {
+            for field in &change.fields {
+                let show = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("(none)"));
+                lines.push(format!(
+                    "~ {}: {} {} → {}",
+                    change.name,
+                    field.key.label(),
+                    show(&field.old),
+                    show(&field.new)
+                ));
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Puts the backup's types in the editor as unsaved changes.
+    fn load_backup(&mut self, backup: &BackupFile) -> io::Result<()> {
+        let (_, types) = self.parse_source_file(&backup.path)?;
+        let name = backup.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        self.push_undo(format!("load backup {name}"));
+        self.replace_types(types);
+        self.log(format!("Loaded {name} into the editor; s saves it, u undoes"));
+        Ok(())
+    }
+
+    /// Writes the backup over the live file, backing the live file up first, and reloads it.
+    fn restore_backup(&mut self, backup: &BackupFile) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let (content, types) = self.parse_source_file(&backup.path)?;
+        let current = self.read_source_file(&path)?;
+        let backup_path = backup::backup_path(&path, 0);
+        backup::write_with_backup(&self.source, &path, &current, &content, self.config.backup_keep.max(1))?;
+        let name = backup.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        self.push_undo(format!("restore {name}"));
+        self.replace_types(types);
+        self.reset_baseline();
+        self.remember_disk(merge::content_hash(&content), content.len() as u64);
+        self.log(format!(
+            "Restored {name} over {}; the replaced version is in {}",
+            path.display(),
+            backup_path.display()
+        ));
+        Ok(())
+    }
+
+    /// `R`: reads the file again as one undoable step; a remote one downloads on a worker
+    /// thread first. The cursor stays on the same type and field by name where they still
+    /// exist.
+    fn reload_from_disk(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let FileSource::Remote(client) = &self.source else {
+            let (content, types) = self.parse_source_file(&path)?;
+            self.finish_reload(content, types);
+            return Ok(());
+        };
+        let client = client.clone();
+        let normalize = self.config.normalize_names;
+        let label = path.display().to_string();
+        let title = format!("Reloading {}", path.file_name().unwrap_or_default().to_string_lossy());
+        let job = progress::spawn(title, move |progress| {
+            let client = client.lock().map_err(|_| io::Error::other("SSH backend in use"))?;
+            let content = client.read_file_with_progress(&path, &mut |done, total| {
+                progress.step(done as usize, total as usize, label.clone())
+            })?;
+            let (mut types, _) = parse_types(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("XML parse error: {e}")))?;
+            if normalize {
+                model::normalize_name_elements(&mut types);
+            }
+            Ok((content, types))
+        });
+        self.job = Some(EditorJob::Reload(job.in_bytes()));
+        Ok(())
+    }
+
+    fn finish_reload(&mut self, content: String, types: Vec<TypeEntry>) {
+        let here = self.here();
+        let field_label = self.current_field().map(|f| f.key.label());
+        let dropped = diff::diff_types(&self.baseline, &self.types);
+        let pulled = diff::diff_types(&self.types, &types);
+        self.push_undo("reload from disk");
+        self.replace_types(types);
+        self.reset_baseline();
+        self.remember_disk(merge::content_hash(&content), content.len() as u64);
+        let view_notes = self.reapply_view();
+        if let Some(type_idx) = here.as_ref().and_then(|h| self.resolve_jump(h)) {
+            self.selected_type = type_idx;
+            let field = self.types[type_idx].fields.iter().position(|f| Some(f.key.label()) == field_label);
+            self.selected_field = field.unwrap_or(0).min(self.current_fields_len().saturating_sub(1));
+            self.reveal_type(type_idx);
+        }
+        let mut message = if pulled.is_empty() {
+            String::from("Reloaded from disk; nothing differed")
+        } else {
+            format!("Reloaded from disk ({})", change_summary(&pulled))
+        };
+        if !dropped.is_empty() {
+            message.push_str(&format!("; unsaved edits dropped ({})", change_summary(&dropped)));
+        }
+        for note in view_notes {
+            message.push_str(&format!("; {note}"));
+        }
+        self.undoable(message);
+    }
+
+    /// Swaps in a whole new model, keeping the cursor and marks in range.
+    fn replace_types(&mut self, types: Vec<TypeEntry>) {
+        self.types = types;
+        self.selected_types.retain_existing(&self.types);
+        let len = self.types.len();
+        self.selected_type = self.selected_type.min(len.saturating_sub(1));
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.clamp_selection_to_view();
+        self.revalidate();
+    }
+
+    fn open_duplicate_review(&mut self) {
+        let found = dedupe::find_duplicates(&self.types);
+        if found.is_empty() {
+            self.status = String::from("No duplicate elements found");
+            return;
+        }
+        self.overlay = Some(Overlay::Duplicates(DuplicateReview { found, cursor: 0 }));
+    }
+
+    fn handle_duplicates_action(&mut self, mut review: DuplicateReview, action: Action) {
+        match action {
+            Action::Cancel => return,
+            Action::Up => review.cursor = review.cursor.saturating_sub(1),
+            Action::Down => review.cursor = (review.cursor + 1).min(review.found.len().saturating_sub(1)),
+            Action::PgUp => review.cursor = review.cursor.saturating_sub(10),
+            Action::PgDown => review.cursor = (review.cursor + 10).min(review.found.len().saturating_sub(1)),
+            Action::Activate => {
+                let removed: usize = review.found.iter().map(|d| d.extra_indices.len()).sum();
+                self.push_undo(format!("remove {removed} duplicate elements"));
+                dedupe::remove_duplicates(&mut self.types, &review.found);
+                let mut touched: Vec<usize> = review.found.iter().map(|d| d.type_idx).collect();
+                touched.dedup();
+                for &idx in &touched {
+                    self.note_change(idx);
+                }
+                self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+                self.revalidate();
+                self.undoable(format!("Removed {removed} duplicate elements from {}", utils::plural(touched.len(), "type")));
+                return;
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::Duplicates(review));
+    }
+
+    fn open_propagate_tool(&mut self) {
+        let targets = self.marked_types().into_iter().filter(|&i| i != self.selected_type).count();
+        if targets == 0 {
+            self.status = String::from("Select target types with Space, then highlight the source type and press P");
+            return;
+        }
+        let Some(source) = self.types.get(self.selected_type) else {
+            return;
+        };
+
+        let mut groups: Vec<PropagateGroup> = Vec::new();
+        let mut group_of: HashMap<(String, usize), usize> = HashMap::new();
+        for field in &source.fields {
+            let (element, index) = field.key.element_key();
+            let slot = *group_of.entry((element.to_string(), index)).or_insert_with(|| {
+                groups.push(PropagateGroup {
+                    label: String::new(),
+                    fields: Vec::new(),
+                    checked: false,
+                });
+                groups.len() - 1
+            });
+            groups[slot].fields.push(field.clone());
+        }
+        for group in &mut groups {
+            group.label = group
+                .fields
+                .iter()
+                .map(|f| match &f.key {
+                    FieldKey::Element { name, .. } => format!("{name} = {}", f.value),
+                    FieldKey::Attribute { element, attr, .. } => format!("{element} @{attr}={}", f.value),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+        }
+        if groups.is_empty() {
+            self.status = String::from("The highlighted type has no fields to copy");
+            return;
+        }
+        self.overlay = Some(Overlay::Propagate(PropagateTool {
+            source: self.selected_type,
+            groups,
+            cursor: 0,
+        }));
+    }
+
+    fn handle_propagate_action(&mut self, mut tool: PropagateTool, action: Action) {
+        match action {
+            Action::Cancel => {
+                self.status = String::from("Copy values cancelled");
+                return;
+            }
+            Action::Up => tool.cursor = tool.cursor.checked_sub(1).unwrap_or(tool.groups.len() - 1),
+            Action::Down => tool.cursor = (tool.cursor + 1) % tool.groups.len(),
+            Action::ToggleSelect => {
+                if let Some(group) = tool.groups.get_mut(tool.cursor) {
+                    group.checked = !group.checked;
+                }
+            }
+            Action::Activate => {
+                let fields: Vec<&Field> = tool
+                    .groups
+                    .iter()
+                    .filter(|g| g.checked)
+                    .flat_map(|g| g.fields.iter())
+                    .collect();
+                if fields.is_empty() {
+                    self.status = String::from("Tick at least one field with Space");
+                    self.overlay = Some(Overlay::Propagate(tool));
+                    return;
+                }
+                let targets: Vec<usize> = self.marked_types().into_iter().filter(|&i| i != tool.source).collect();
+                let source_name = self.types[tool.source].name.clone();
+                self.push_undo(format!("copy values from {source_name} to {} types", targets.len()));
+                for &target in &targets {
+                    let ty = &mut self.types[target];
+                    for field in &fields {
+                        match ty.fields.iter_mut().find(|f| f.key == field.key) {
+                            Some(existing) => existing.value = field.value.clone(),
+                            None => ty.fields.push((*field).clone()),
+                        }
+                    }
+                    self.note_change(target);
+                }
+                self.revalidate();
+                self.last_edit = Some(LastEdit::Values(fields.into_iter().cloned().collect()));
+                let checked = tool.groups.iter().filter(|g| g.checked).count();
+                self.undoable(format!("Copied {checked} fields × {} from {source_name}", utils::plural(targets.len(), "type")));
+                return;
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::Propagate(tool));
+    }
+
+    /// `.`: applies the last committed field edit to the highlighted type as its own undo
+    /// step; a bulk edit applies to this one type only.
+    fn repeat_last_edit(&mut self) {
+        let Some(edit) = self.last_edit.clone() else {
+            self.status = String::from("Nothing to repeat yet; . repeats the last field edit");
+            return;
+        };
+        let type_idx = self.selected_type;
+        let Some(mut ty) = self.types.get(type_idx).cloned() else {
+            return;
+        };
+        match &edit {
+            LastEdit::Values(fields) => {
+                for field in fields {
+                    match ty.fields.iter_mut().find(|f| f.key == field.key) {
+                        Some(existing) => existing.value = field.value.clone(),
+                        None => ty.fields.push(field.clone()),
+                    }
+                }
+            }
+            LastEdit::Expression { field, expression } => {
+                let value = match eval_over(&ty.fields, expression) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        self.fail(format!("Can't repeat {} on {}: {err}", edit.describe(), ty.name));
+                        return;
+                    }
+                };
+                match ty.fields.iter_mut().find(|f| f.key == field.key) {
+                    Some(existing) => existing.value = value,
+                    None => ty.fields.push(Field { value, ..field.clone() }),
+                }
+            }
+            &LastEdit::Flag { flag, on } => {
+                if flags::set_flag(&mut ty, flag, on, self.config.create_missing_flags) == flags::FlagSet::Skipped {
+                    self.status = format!("{} has no <flags> and create_missing_flags is off; nothing to repeat", ty.name);
+                    return;
+                }
+            }
+            LastEdit::Swap(a, b) => {
+                let position = |key: &FieldKey| ty.fields.iter().position(|f| f.key == *key);
+                let (Some(pa), Some(pb)) = (position(a), position(b)) else {
+                    self.status = format!("{} lacks one of {}; nothing to repeat", ty.name, edit.describe());
+                    return;
+                };
+                let value_a = mem::take(&mut ty.fields[pa].value);
+                ty.fields[pa].value = mem::replace(&mut ty.fields[pb].value, value_a);
+            }
+            LastEdit::Bulk(bulk) => bulk.apply_to(&mut ty),
+            LastEdit::Assign { element, value } => {
+                if let Ok(assignment) = assign::plan(std::slice::from_ref(&ty), element, value, "*") {
+                    assign::apply(std::slice::from_mut(&mut ty), &assignment);
+                }
+            }
+            LastEdit::Tiers(changes) => {
+                for (tier, present) in changes {
+                    tiers::set_tier(&mut ty, tier, *present);
+                }
+            }
+            &LastEdit::Cap { cap, scale_min, scale_quantity } => {
+                let one = std::slice::from_mut(&mut ty);
+                let changes = balance::plan_cap(one, &[0], cap, scale_min, scale_quantity);
+                balance::apply_cap(one, &changes);
+            }
+        }
+        let name = ty.name.clone();
+        if ty == self.types[type_idx] {
+            self.status = format!("{name} already has {}; nothing to repeat", edit.describe());
+            return;
+        }
+        self.push_undo(format!("repeat {} on {name}", edit.describe()));
+        self.types[type_idx] = ty;
+        self.note_change(type_idx);
+        self.revalidate_type(type_idx);
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.undoable(format!("{} applied to {name}", edit.describe()));
+    }
+
+    /// `e` on the Fields pane: marks the field, or swaps its value with the marked one's.
+    /// Fields of different names are usually a slip, so those ask first.
+    fn exchange_key(&mut self) {
+        let Some(field) = self.current_field().filter(|_| self.focus == EditorFocus::FieldList) else {
+            self.status = String::from("e exchanges two fields' values; press it on the Fields pane");
+            return;
+        };
+        let here = ExchangeEnd {
+            type_idx: self.selected_type,
+            type_name: self.types[self.selected_type].name.clone(),
+            key: field.key.clone(),
+        };
+        let Some(from) = self.exchange.take() else {
+            self.status = format!("Exchanging {} {}: go to the other field and press e (Esc cancels)", here.type_name, here.key.label());
+            self.exchange = Some(here);
+            return;
+        };
+        let Some(from) = self.find_exchange_end(from) else {
+            self.fail(String::from("The marked field is gone; exchange cancelled"));
+            return;
+        };
+        if from == here {
+            self.status = String::from("That's the marked field; pick another one (Esc cancels)");
+            self.exchange = Some(from);
+        } else if from.key.label() != here.key.label() {
+            self.overlay = Some(Overlay::ConfirmExchange(from, here));
+        } else {
+            self.swap_values(&from, &here);
+        }
+    }
+
+    /// `end` with its type's current position, if the type and field are still there.
+    fn find_exchange_end(&self, mut end: ExchangeEnd) -> Option<ExchangeEnd> {
+        if self.types.get(end.type_idx).is_none_or(|t| t.name != end.type_name) {
+            end.type_idx = self.types.iter().position(|t| t.name == end.type_name)?;
+        }
+        self.types[end.type_idx].fields.iter().any(|f| f.key == end.key).then_some(end)
+    }
+
+    fn swap_values(&mut self, a: &ExchangeEnd, b: &ExchangeEnd) {
+        let position = |editor: &Self, end: &ExchangeEnd| editor.types[end.type_idx].fields.iter().position(|f| f.key == end.key);
+        let (Some(pa), Some(pb)) = (position(self, a), position(self, b)) else {
+            self.fail(String::from("A field of the exchange is gone; nothing changed"));
+            return;
+        };
+        let value_a = self.types[a.type_idx].fields[pa].value.clone();
+        let value_b = self.types[b.type_idx].fields[pb].value.clone();
+        let (label_a, label_b) = (a.key.label(), b.key.label());
+        let message = if label_a == label_b {
+            format!("Swapped {label_a}: {} {value_a} ⇄ {} {value_b}", a.type_name, b.type_name)
+        } else {
+            format!("Swapped {} {label_a} {value_a} ⇄ {} {label_b} {value_b}", a.type_name, b.type_name)
+        };
+        self.push_undo(format!("swap {} {label_a} ⇄ {} {label_b}", a.type_name, b.type_name));
+        self.types[a.type_idx].fields[pa].value = value_b;
+        self.types[b.type_idx].fields[pb].value = value_a;
+        for idx in [a.type_idx, b.type_idx] {
+            self.note_change(idx);
+            self.revalidate_type(idx);
+        }
+        // Across types, `.` sets the value the second end (where e was pressed last) received.
+        self.last_edit = Some(if a.type_idx == b.type_idx {
+            LastEdit::Swap(a.key.clone(), b.key.clone())
+        } else {
+            LastEdit::Values(vec![self.types[b.type_idx].fields[pb].clone()])
+        });
+        self.undoable(message);
+    }
+
+    fn render_confirm_exchange_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, from: &ExchangeEnd, to: &ExchangeEnd) {
+        let area = utils::centered_rect(60, 35, f.size());
+        let value = |end: &ExchangeEnd| {
+            let field = self.types.get(end.type_idx).and_then(|t| t.fields.iter().find(|f| f.key == end.key));
+            field.map(|f| f.value.clone()).unwrap_or_default()
+        };
+        let text = format!(
+            "{} {} = {}\n{} {} = {}\n\nThese fields have different names; swapping their values is usually a slip.\n\ny / Enter: swap anyway\nn / Esc: cancel",
+            from.type_name,
+            from.key.label(),
+            value(from),
+            to.type_name,
+            to.key.label(),
+            value(to)
+        );
+        let prompt = Paragraph::new(text)
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: true })
+            .block(Block::default().title("Exchange values").borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(prompt, area);
+    }
+
+    fn toggle_type_selection(&mut self) {
+        if self.focus != EditorFocus::TypeList || self.types.is_empty() {
+            return;
+        }
+        self.selected_types.toggle(&self.types, self.selected_type);
+        self.status = format!("{} types selected (Esc clears)", self.marked_types().len());
+        self.move_selection(1);
+    }
+
+    /// Positions of the types marked with Space, worked out from the current types.
+    fn marked_types(&self) -> BTreeSet<usize> {
+        self.selected_types.indices(&self.types)
+    }
+
+    fn reset_baseline(&mut self) {
+        self.baseline = self.types.clone();
+        self.baseline_index.clear();
+        for (i, ty) in self.baseline.iter().enumerate() {
+            self.baseline_index.entry(ty.name.clone()).or_insert(i);
+        }
+    }
+
+    fn baseline_for(&self, name: &str) -> Option<&TypeEntry> {
+        self.baseline_index.get(name).and_then(|&i| self.baseline.get(i))
+    }
+
+    /// True when the type differs from its loaded/saved version (or did not exist then).
+    fn is_modified(&self, ty: &TypeEntry) -> bool {
+        self.baseline_for(&ty.name) != Some(ty)
+    }
+
+    fn open_cap_tool(&mut self, scale_quantity: bool) {
+        let scope = self.visible_types();
+        let Some(stats) = balance::nominal_stats(&self.types, &scope) else {
+            self.status = String::from("No numeric nominal values to analyse");
+            return;
+        };
+        self.overlay = Some(Overlay::NominalCap(CapTool {
+            scope,
+            cap_input: stats.p95.to_string(),
+            stats,
+            scale_min: true,
+            scale_quantity,
+            applied: None,
+        }));
+    }
+
+    fn handle_cap_action(&mut self, mut tool: CapTool, action: Action) {
+        if tool.applied.is_some() {
+            if !matches!(action, Action::Cancel | Action::Activate) {
+                self.overlay = Some(Overlay::NominalCap(tool));
+            }
+            return;
+        }
+        match action {
+            Action::Cancel => return,
+            Action::Input(c) if c.is_ascii_digit() => tool.cap_input.push(c),
+            Action::Input('p') => tool.cap_input = tool.stats.p95.to_string(),
+            Action::Backspace => {
+                tool.cap_input.pop();
+            }
+            Action::Tab => tool.scale_min = !tool.scale_min,
+            Action::Input('q') => tool.scale_quantity = !tool.scale_quantity,
+            Action::Activate => {
+                let Ok(cap) = tool.cap_input.parse::<i64>() else {
+                    self.status = String::from("Enter a whole number to cap at");
+                    self.overlay = Some(Overlay::NominalCap(tool));
+                    return;
+                };
+                let changes = balance::plan_cap(&self.types, &tool.scope, cap, tool.scale_min, tool.scale_quantity);
+                if changes.is_empty() {
+                    self.status = format!("No types have a nominal above {cap}");
+                } else {
+                    self.push_undo(format!("cap nominal at {cap}"));
+                    balance::apply_cap(&mut self.types, &changes);
+                    for change in &changes {
+                        self.note_change(change.type_idx);
+                    }
+                    self.revalidate();
+                    self.last_edit = Some(LastEdit::Cap {
+                        cap,
+                        scale_min: tool.scale_min,
+                        scale_quantity: tool.scale_quantity,
+                    });
+                    let mut message = format!("Capped nominal at {cap} on {}", utils::plural(changes.len(), "type"));
+                    if tool.scale_quantity {
+                        let scaled = changes.iter().filter(|c| c.quantity.is_some()).count();
+                        message.push_str(&format!(
+                            "; quantities scaled on {scaled}, {} without stack quantities left alone",
+                            changes.len() - scaled
+                        ));
+                    }
+                    self.undoable(message);
+                    tool.applied = Some(changes);
+                }
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::NominalCap(tool));
+    }
+
+    fn push_undo(&mut self, description: impl Into<String>) {
+        if self.replaying {
+            return;
+        }
+        if let Some(last) = self.undo_stack.back_mut()
+            && let Change::Snapshot(before) = &last.change
+        {
+            last.change = Change::Splice(Splice::between(&self.types, before));
+        }
+        self.undo_stack.push_back(UndoEntry {
+            description: description.into(),
+            change: Change::Snapshot(self.types.clone()),
+            selected_type: self.selected_type,
+            selected_field: self.selected_field,
+        });
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.redo_stack.clear();
+    }
+
+    /// The model as it was before the newest edit, while that edit is the last one made.
+    fn before_last_edit(&self) -> Option<&[TypeEntry]> {
+        match &self.undo_stack.back()?.change {
+            Change::Snapshot(before) => Some(before),
+            Change::Splice(_) => None,
+        }
+    }
+
+    /// Swaps the model with the top of `from`, pushing the current state onto the other stack.
+    fn restore_snapshot(&mut self, redo: bool) {
+        let entry = if redo { self.redo_stack.pop() } else { self.undo_stack.pop_back() };
+        let Some(entry) = entry else {
+            self.status = String::from(if redo { "Nothing to redo" } else { "Nothing to undo" });
+            return;
+        };
+        let description = entry.description.clone();
+        let back = UndoEntry {
+            description: entry.description,
+            change: entry.change.apply(&mut self.types),
+            selected_type: self.selected_type,
+            selected_field: self.selected_field,
+        };
+        if redo {
+            self.undo_stack.push_back(back);
+        } else {
+            self.redo_stack.push(back);
+        }
+        self.selected_type = entry.selected_type.min(self.types.len().saturating_sub(1));
+        self.selected_field = entry.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.clamp_selection_to_view();
+        self.revalidate();
+        self.status = format!("{}: {}", if redo { "Redid" } else { "Undid" }, description);
+    }
+
+    /// Enter in `:history`: undoes or redoes step by step until the state at row `row`,
+    /// as if `u`/`U` were pressed that many times.
+    fn travel_history(&mut self, row: usize) {
+        let now = self.redo_stack.len();
+        let (redo, steps) = if row < now { (true, now - row) } else { (false, row - now) };
+        if steps == 0 {
+            self.status = String::from("Already at this point in the history");
+            return;
+        }
+        let mut last = String::new();
+        for _ in 0..steps {
+            let entry = if redo { self.redo_stack.last() } else { self.undo_stack.back() };
+            last = entry.map(|e| e.description.clone()).unwrap_or_default();
+            self.restore_snapshot(redo);
+        }
+        self.status = if redo {
+            format!("Redid {}, up to: {last}", utils::plural(steps, "step"))
+        } else {
+            format!("Undid {}, back to before: {last}", utils::plural(steps, "step"))
+        };
+    }
+
+    /// `q`: back to the file picker, asking first when there are unsaved changes.
+    fn request_close(&mut self) {
+        self.overlay = None;
+        if self.types != self.baseline {
+            self.overlay = Some(Overlay::ConfirmClose);
+        } else {
+            self.close_requested = true;
+        }
+    }
+
+    /// True once after the user chose to close the file.
+    pub fn take_close_request(&mut self) -> bool {
+        std::mem::take(&mut self.close_requested)
+    }
+
+    /// True once after `:setup`.
+    pub fn take_setup_request(&mut self) -> bool {
+        std::mem::take(&mut self.setup_requested)
+    }
+
+    /// What `choose_directory` should ask for, once after `:apply ... ?`.
+    pub fn take_directory_request(&mut self) -> Option<String> {
+        std::mem::take(&mut self.directory_requested).then(|| String::from("the :apply run"))
+    }
+
+    /// The picker's answer to `take_directory_request`: the waiting `:apply` dry-runs under
+    /// the chosen directory, which has to be on the open file's machine.
+    pub fn directory_chosen(&mut self, selection: Option<FileSelection>) {
+        let Some(mut args) = self.apply_awaiting_dir.take() else {
+            return;
+        };
+        match selection {
+            None => self.status = String::from(":apply cancelled; no directory chosen"),
+            Some(selection) if !selection.source.same_as(&self.source) => {
+                self.fail(format!(":apply: {} isn't on the open file's machine", selection.path.display()));
+            }
+            Some(selection) => {
+                args.push(selection.path.to_string_lossy().into_owned());
+                self.open_bulk_apply(&args.iter().map(String::as_str).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    /// User settings written by the setup wizard; merged with the open file's project
+    /// file like on load. `message` goes to the status line.
+    pub fn set_user_config(&mut self, layer: ConfigLayer, message: Option<String>) {
+        self.user_config = layer;
+        self.config = Config::resolve(&self.user_config, self.project_config.as_ref(), &self.cli_config);
+        if let Some(message) = message {
+            self.log(message);
+        }
+    }
+
+    /// `:skeleton`: writes the highlighted type's fields to `[new_type]` in the user config
+    /// at `path`, so types added with `a` start from them; `reset` goes back to the built-in one.
+    fn save_skeleton(&mut self, path: &Path, reset: bool) {
+        // Start from the file as it is now, so nothing but `[new_type]` changes in it.
+        let mut layer = match fs::read_to_string(path).map(|content| ConfigLayer::parse(&content)) {
+            Ok(Ok((layer, _))) => layer,
+            Ok(Err(err)) => {
+                self.fail(format!("Not writing {}: {err}", path.display()));
+                return;
+            }
+            Err(_) => self.user_config.clone(),
+        };
+        let mut message = if reset {
+            layer.new_type = NewTypeLayer::default();
+            String::from("New types use the built-in fields again")
+        } else {
+            let Some(ty) = self.types.get(self.selected_type) else {
+                self.status = String::from("No type to take the fields from");
+                return;
+            };
+            let (new_type, skipped) = NewTypeLayer::from_type(ty);
+            layer.new_type = new_type;
+            let mut message = format!("New types start with the {} of {}", utils::plural(ty.fields.len() - skipped, "field"), ty.name);
+            if skipped > 0 {
+                message.push_str(&format!(" ({} past the first of their element left out)", utils::plural(skipped, "field")));
+            }
+            message
+        };
+        match layer.write_to(path) {
+            Ok(()) => {
+                message.push_str(&format!("; saved to {}", path.display()));
+                self.set_user_config(layer, Some(message));
+            }
+            Err(err) => self.fail(format!("Could not write {}: {err}", path.display())),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn types(&self) -> &[TypeEntry] {
+        &self.types
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.types != self.baseline
+    }
+
+    /// Whether a background job (`:apply`, a transfer) is still running; `tick` finishes it.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn has_overlay(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Whether save stopped to ask about types without `<flags>` (`f`: add them, `s`: save anyway).
+    pub fn asks_about_missing_flags(&self) -> bool {
+        matches!(self.overlay, Some(Overlay::MissingFlags(_)))
+    }
+
+    /// Whether save stopped because the file would shrink drastically (`y`: save anyway).
+    pub fn asks_about_shrink(&self) -> bool {
+        matches!(self.overlay, Some(Overlay::ConfirmShrink(_)))
+    }
+
+    pub fn asks_about_space(&self) -> bool {
+        matches!(self.overlay, Some(Overlay::ConfirmLowSpace(_)))
+    }
+
+    /// Title and lines of an open read-only list such as `:messages`, for front ends that
+    /// print instead of drawing.
+    pub fn overlay_text(&self) -> Option<(&str, &[String])> {
+        match &self.overlay {
+            Some(Overlay::Text { title, lines, .. }) => Some((title, lines)),
+            _ => None,
+        }
+    }
+
+    /// Highlights a type on the Types pane.
+    pub fn select_type(&mut self, type_idx: usize) {
+        if type_idx < self.types.len() {
+            self.selected_type = type_idx;
+            self.selected_field = 0;
+            self.focus = EditorFocus::TypeList;
+            self.reveal_type(type_idx);
+        }
+    }
+
+    /// Sets one field as if its value had been typed into the Fields pane.
+    pub fn set_value(&mut self, type_idx: usize, field_idx: usize, value: &str) {
+        if self.refuse_if_readonly() {
+            return;
+        }
+        self.select_type(type_idx);
+        self.selected_field = field_idx;
+        self.focus = EditorFocus::FieldList;
+        self.begin_field_value_edit();
+        self.recent_pick = None;
+        self.input_buffer = value.to_string();
+        if !self.apply_input() {
+            self.stop_editing();
+        }
+    }
+
+    pub fn instant_quit(&self) -> bool {
+        self.config.instant_quit
+    }
+
+    /// In read-only mode, says so in the status and returns `true`: the edit is refused.
+    fn refuse_if_readonly(&mut self) -> bool {
+        if self.readonly {
+            self.status = String::from("Read-only mode: edits and saves are disabled (:readonly to allow them)");
+        }
+        self.readonly
+    }
+
+    /// Whether `action` would change the model or write the file in the current context.
+    fn is_mutating(&self, action: &Action) -> bool {
+        match &self.overlay {
+            Some(Overlay::NominalCap(tool)) => tool.applied.is_none() && *action == Action::Activate,
+            Some(Overlay::Propagate(_) | Overlay::Duplicates(_) | Overlay::Tiers(_)) => *action == Action::Activate,
+            Some(Overlay::Quantity(_)) => matches!(action, Action::Activate | Action::ToggleRemote),
+            Some(
+                Overlay::SaveConflicts(_)
+                | Overlay::BulkApply(..)
+                | Overlay::Assign(..)
+                | Overlay::CaseFixes(..)
+                | Overlay::StripFields(..),
+            ) => *action == Action::Activate,
+            Some(Overlay::ConfirmClose) => *action == Action::Save,
+            Some(Overlay::Reload) => *action == Action::Input('s'),
+            Some(Overlay::History { .. }) => *action == Action::Activate,
+            Some(Overlay::ValidationReport) => *action == Action::Delete,
+            Some(Overlay::MissingFlags(_)) => matches!(action, Action::Activate | Action::Input('f' | 's')),
+            Some(Overlay::FlagMatrix(_)) => matches!(action, Action::Input('1'..='6' | '+' | '-')),
+            Some(
+                Overlay::ConfirmShrink(_) | Overlay::ConfirmLowSpace(_) | Overlay::ConfirmExchange(..) | Overlay::ConfirmDelete(_),
+            ) => {
+                matches!(action, Action::Activate | Action::Input('y'))
+            }
+            Some(Overlay::Backups(_)) => matches!(action, Action::Input('o' | 'w')),
+            Some(_) => false,
+            None if matches!(self.focus, EditorFocus::Editing | EditorFocus::Categories) => false,
+            None if *action == Action::Input('e') => self.exchange.is_some(),
+            None => matches!(
+                action,
+                Action::Activate
+                    | Action::Add
+                    | Action::AddAttribute
+                    | Action::Copy
+                    | Action::Duplicate
+                    | Action::Delete
+                    | Action::Save
+                    | Action::PropagateValues
+                    | Action::Undo
+                    | Action::Redo
+                    | Action::ReplayMacro
+                    | Action::RepeatEdit
+            ),
+        }
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.focus == EditorFocus::Editing
+    }
+
+    pub fn handle_action(&mut self, action: Action) -> io::Result<()> {
+        let observed = self.tutorial.is_some().then(|| action.clone());
+        let result = self.dispatch_action(action);
+        if let (Some(action), Some(tutorial)) = (observed, &mut self.tutorial)
+            && tutorial.observe(&action, &self.types, self.selected_type, !self.sample)
+            && tutorial.step() == tutorial::Step::Done
+        {
+            self.log(String::from("Tutorial finished"));
+        }
+        result
+    }
+
+    fn dispatch_action(&mut self, action: Action) -> io::Result<()> {
+        if let Some(job) = &self.job {
+            if action == Action::Cancel {
+                self.status = job.cancel();
+            }
+            return Ok(());
+        }
+        // The keys panel sits over its overlay and takes no part in macros.
+        match (&self.overlay, &action) {
+            (None, _) => self.overlay_help = false,
+            (Some(_), Action::Help | Action::Cancel) if self.overlay_help => {
+                self.overlay_help = false;
+                return Ok(());
+            }
+            (Some(_), _) if self.overlay_help => return Ok(()),
+            (Some(_), Action::Help) => {
+                self.overlay_help = true;
+                return Ok(());
+            }
+            (Some(_), _) => {}
+        }
+        if let Some(recording) = &mut self.recording
+            && !matches!(
+                action,
+                Action::RecordMacro | Action::ReplayMacro | Action::Quit | Action::ResizeLeft | Action::ResizeRight
+            )
+        {
+            recording.push(action.clone());
+        }
+        let count_prefix = self.count_prefix.take();
+        // Every input starts with the cursor at the end, whatever set the buffer.
+        if !self.is_editing() {
+            self.input_cursor = None;
+        }
+        if action == Action::Quit && !self.is_editing() {
+            self.request_close();
+            return Ok(());
+        }
+        if self.is_mutating(&action) && self.refuse_if_readonly() {
+            return Ok(());
+        }
+        if let Some(overlay) = self.overlay.take() {
+            self.handle_overlay_action(overlay, action);
+            return Ok(());
+        }
+        if self.table.is_some() && self.focus != EditorFocus::Editing && self.handle_table_action(&action) {
+            return Ok(());
+        }
+        match self.focus {
+            EditorFocus::Editing => {
+                match action {
+                    Action::Input(c) if c.is_control() => {
+                        self.status = format!("Ignored control character U+{:04X}", c as u32);
+                    }
+                    Action::Left | Action::Right | Action::Home | Action::End => {
+                        self.edit_input(&action);
+                    }
+                    Action::Input(_) | Action::Backspace | Action::Remove | Action::ClearLine | Action::DeleteWord => {
+                        self.name_override = None;
+                        self.recent_pick = None;
+                        self.edit_input(&action);
+                    }
+                    Action::Up | Action::Down => self.move_recent_pick(action == Action::Down),
+                    Action::Activate => {
+                        if let Some((_, value)) = self.recent_pick.and_then(|i| self.value_choices().into_iter().nth(i)) {
+                            self.input_buffer = value;
+                        }
+                        let continue_editing = self.apply_input();
+                        if !continue_editing {
+                            self.stop_editing();
+                        }
+                    }
+                    Action::Cancel => {
+                        let duplicating = self.editing_target == Some(EditTarget::DuplicateName);
+                        self.name_override = None;
+                        self.input_buffer.clear();
+                        self.stop_editing();
+                        self.status = String::from(if duplicating {
+                            "Duplicate discarded; nothing was added"
+                        } else {
+                            "Edit cancelled"
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            _ => match action {
+                Action::Input(c) if c.is_ascii_digit() => {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    self.count_prefix = Some(count_prefix.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                }
+                Action::RecordMacro => self.toggle_recording(),
+                Action::ToggleTable => self.toggle_table(),
+                Action::CycleInlineValues => self.cycle_inline_values(),
+                Action::ResizeLeft => self.resize_panes(false),
+                Action::ResizeRight => self.resize_panes(true),
+                Action::ReplayMacro => {
+                    let count = count_prefix.unwrap_or(1).max(1);
+                    self.replay_macro(count)?;
+                }
+                Action::Up => self.move_selection(-1),
+                Action::Down => self.move_selection(1),
+                Action::Left => {
+                    self.focus = match self.focus {
+                        EditorFocus::TypeList if self.show_categories => EditorFocus::Categories,
+                        EditorFocus::Categories => EditorFocus::Categories,
+                        _ => EditorFocus::TypeList,
+                    };
+                }
+                Action::Right if self.focus == EditorFocus::Categories => self.focus = EditorFocus::TypeList,
+                Action::Right if !self.types.is_empty() => {
+                    self.focus = EditorFocus::FieldList;
+                }
+                Action::Tab | Action::BackTab => self.cycle_focus(action == Action::Tab),
+                Action::ToggleCategories => self.toggle_categories(),
+                Action::PgUp => self.move_selection(-10),
+                Action::PgDown => self.move_selection(10),
+                Action::Activate => {
+                    self.begin_editing();
+                }
+                Action::Add => self.add(),
+                Action::AddAttribute => self.add_attribute(),
+                Action::Copy => self.copy(),
+                Action::Duplicate => self.begin_duplicate(),
+                Action::Reload if self.path.is_some() => self.overlay = Some(Overlay::Reload),
+                Action::Delete => self.delete(),
+                Action::ToggleFilter => self.toggle_changed_filter(),
+                Action::JumpBack => self.travel_jumps(false),
+                Action::JumpForward => self.travel_jumps(true),
+                Action::Stats => self.overlay = Some(Overlay::Statistics),
+                Action::NextIssue => self.jump_to_issue(true),
+                Action::PrevIssue => self.jump_to_issue(false),
+                Action::Validate => {
+                    self.revalidate();
+                    self.overlay = Some(Overlay::ValidationReport);
+                }
+                Action::ToggleSelect => self.toggle_type_selection(),
+                Action::PropagateValues => self.open_propagate_tool(),
+                Action::RepeatEdit => self.repeat_last_edit(),
+                Action::Input('e') => self.exchange_key(),
+                Action::Input('z') => self.cycle_field_filter(),
+                Action::Cancel if self.exchange.is_some() => {
+                    self.exchange = None;
+                    self.status = String::from("Exchange cancelled");
+                }
+                Action::Cancel if !self.marked_types().is_empty() => {
+                    self.selected_types.clear();
+                    self.status = String::from("Selection cleared");
+                }
+                Action::Cancel if self.tutorial.is_some() => {
+                    self.tutorial = None;
+                    self.status = String::from("Tutorial closed (:tutorial starts it again)");
+                }
+                Action::Undo => self.restore_snapshot(false),
+                Action::Redo => self.restore_snapshot(true),
+                Action::NominalTool => self.open_cap_tool(false),
+                Action::Command => self.begin_command(),
+                Action::Input('G') => self.begin_goto(),
+                Action::Save => {
+                    if self.reconnect_rx.is_some() {
+                        self.status = String::from("Still reconnecting; save will retry automatically");
+                    } else if let Err(err) = self.save() {
+                        self.fail(format!("Save failed: {err}"));
+                    }
+                }
+                _ => {}
+            },
+        }
+        // An edit can move the highlighted type out of the chosen category.
+        if self.focus != EditorFocus::Editing {
+            self.clamp_selection_to_view();
+        }
+        Ok(())
+    }
+
+    /// Tab order: categories (when shown), types, fields; wraps around.
+    fn cycle_focus(&mut self, forward: bool) {
+        let mut order = Vec::new();
+        if self.show_categories {
+            order.push(EditorFocus::Categories);
+        }
+        order.push(EditorFocus::TypeList);
+        if !self.types.is_empty() {
+            order.push(EditorFocus::FieldList);
+        }
+        let pos = order.iter().position(|&f| f == self.focus).unwrap_or(0);
+        let next = if forward { pos + 1 } else { pos + order.len() - 1 };
+        self.focus = order[next % order.len()];
+    }
+
+    /// Moves the boundary right of the focused pane by one step. Past its minimum the
+    /// Tips pane collapses and Fields takes its space; moving back brings it out again.
+    fn resize_panes(&mut self, rightwards: bool) {
+        if self.table.is_some() {
+            return;
+        }
+        let left = if matches!(self.focus, EditorFocus::Categories | EditorFocus::TypeList) { 0 } else { 1 };
+        let right = left + 1;
+        let mut panes = self.panes;
+        if rightwards {
+            if panes[right] >= MIN_PANES[right] + PANE_STEP {
+                panes[right] -= PANE_STEP;
+                panes[left] += PANE_STEP;
+            } else if right == 2 && panes[2] > 0 {
+                panes[1] += panes[2];
+                panes[2] = 0;
+            }
+        } else if right == 2 && panes[2] == 0 {
+            if panes[1] >= MIN_PANES[1] + MIN_PANES[2] {
+                panes[1] -= MIN_PANES[2];
+                panes[2] = MIN_PANES[2];
+            }
+        } else if panes[left] >= MIN_PANES[left] + PANE_STEP {
+            panes[left] -= PANE_STEP;
+            panes[right] += PANE_STEP;
+        }
+        if panes == self.panes {
+            self.status = String::from("Pane already at its minimum width");
+            return;
+        }
+        self.set_panes(panes);
+    }
+
+    fn set_panes(&mut self, panes: [u16; 3]) {
+        self.panes = panes;
+        let [types, fields, tips] = panes;
+        let layout = if tips == 0 {
+            format!("Panes {types}/{fields}, Tips hidden")
+        } else {
+            format!("Panes {types}/{fields}/{tips}")
+        };
+        let session = Session {
+            panes: (panes != DEFAULT_PANES).then_some(panes),
+            ..Session::load()
+        };
+        match session.save() {
+            Ok(()) => self.status = format!("{layout} (:panes reset restores the default)"),
+            Err(err) => self.fail(format!("{layout}; could not remember it: {err}")),
+        }
+    }
+
+    fn toggle_table(&mut self) {
+        self.focus = EditorFocus::TypeList;
+        if self.table.take().is_some() {
+            self.clamp_selection_to_view();
+            self.status = String::from("Back to the pane view");
+        } else {
+            self.table = Some(TableView {
+                column: 0,
+                sort: None,
+                first_column: 0,
+            });
+            self.status = String::from("Table view: ←/→ column, Tab sort, Enter edit, T back");
+        }
+    }
+
+    /// Visible types in table order.
+    fn table_rows(&self) -> Vec<usize> {
+        let mut rows = self.visible_types();
+        if let Some((column, descending)) = self.table.as_ref().and_then(|t| t.sort) {
+            rows.sort_by(|&a, &b| {
+                let a = table_cell(&self.types[a], column);
+                let b = table_cell(&self.types[b], column);
+                let ordering = match (a.and_then(|v| v.parse::<f64>().ok()), b.and_then(|v| v.parse::<f64>().ok())) {
+                    (Some(x), Some(y)) => x.total_cmp(&y),
+                    _ => a.cmp(&b),
+                };
+                if descending { ordering.reverse() } else { ordering }
+            });
+        }
+        rows
+    }
+
+    /// Handles navigation, sorting and cell editing in the table view; other actions fall through.
+    fn handle_table_action(&mut self, action: &Action) -> bool {
+        let Some(table) = self.table.as_mut() else {
+            return false;
+        };
+        match action {
+            Action::Left => table.column = table.column.saturating_sub(1),
+            Action::Right => table.column = (table.column + 1).min(TABLE_COLUMNS.len() - 1),
+            Action::Tab => {
+                table.sort = match table.sort {
+                    Some((column, false)) if column == table.column => Some((column, true)),
+                    Some((column, true)) if column == table.column => None,
+                    _ => Some((table.column, false)),
+                };
+            }
+            Action::BackTab => {
+                table.sort = match table.sort {
+                    Some((column, true)) if column == table.column => Some((column, false)),
+                    Some((column, false)) if column == table.column => None,
+                    _ => Some((table.column, true)),
+                };
+            }
+            Action::Up | Action::Down | Action::PgUp | Action::PgDown => {
+                let delta: isize = match action {
+                    Action::Up => -1,
+                    Action::Down => 1,
+                    Action::PgUp => -10,
+                    _ => 10,
+                };
+                let rows = self.table_rows();
+                if let Some(pos) = rows.iter().position(|&i| i == self.selected_type) {
+                    let new_pos = (pos as isize + delta).clamp(0, rows.len() as isize - 1) as usize;
+                    self.selected_type = rows[new_pos];
+                    self.selected_field = 0;
+                } else if let Some(&first) = rows.first() {
+                    self.selected_type = first;
+                }
+            }
+            Action::Activate => self.begin_table_cell_edit(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn begin_table_cell_edit(&mut self) {
+        let Some(column) = self.table.as_ref().map(|t| t.column) else {
+            return;
+        };
+        let Some(ty) = self.types.get(self.selected_type) else {
+            return;
+        };
+        let Some(field_idx) = table_field(ty, column) else {
+            self.status = format!("{} has no {} field; add it in the pane view", ty.name, TABLE_COLUMNS[column]);
+            return;
+        };
+        self.selected_field = field_idx;
+        self.input_buffer = ty.fields[field_idx].value.clone();
+        self.editing_target = Some(EditTarget::TableCell);
+        self.focus = EditorFocus::Editing;
+        self.status = format!("Editing {} of {}", TABLE_COLUMNS[column], ty.name);
+        self.offer_recent();
+        self.use_calc_carry();
+    }
+
+    fn render_table<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, area: Rect) {
+        let rows = self.table_rows();
+        let Some(table) = self.table.as_mut() else {
+            return;
+        };
+        // Scroll horizontally so the selected column is always on screen.
+        let room = area.width.saturating_sub(TABLE_NAME_WIDTH + 4);
+        let fits = ((room / (TABLE_COLUMN_WIDTH + 1)) as usize).max(1);
+        if table.column < table.first_column {
+            table.first_column = table.column;
+        } else if table.column >= table.first_column + fits {
+            table.first_column = table.column + 1 - fits;
+        }
+        let shown: Vec<usize> = (table.first_column..TABLE_COLUMNS.len()).take(fits).collect();
+        let column = table.column;
+        let sort = table.sort;
+
+        let mut header = vec![Cell::from("type")];
+        header.extend(shown.iter().map(|&c| {
+            let arrow = match sort {
+                Some((sorted, false)) if sorted == c => " ▲",
+                Some((sorted, true)) if sorted == c => " ▼",
+                _ => "",
+            };
+            Cell::from(format!("{}{}", TABLE_COLUMNS[c], arrow))
+        }));
+        let body: Vec<Row> = rows
+            .iter()
+            .map(|&i| {
+                let ty = &self.types[i];
+                let name = if self.is_modified(ty) { format!("{} *", ty.name) } else { ty.name.clone() };
+                let mut cells = vec![Cell::from(name)];
+                cells.extend(shown.iter().map(|&c| {
+                    let cell = Cell::from(table_cell(ty, c).unwrap_or("—").to_string());
+                    if i == self.selected_type && c == column {
+                        cell.style(Style::default().add_modifier(Modifier::REVERSED))
+                    } else {
+                        cell
+                    }
+                }));
+                Row::new(cells)
+            })
+            .collect();
+        let mut widths = vec![Constraint::Length(TABLE_NAME_WIDTH)];
+        widths.extend(shown.iter().map(|_| Constraint::Length(TABLE_COLUMN_WIDTH)));
+
+        let more_left = if shown.first().is_some_and(|&c| c > 0) { "◀ " } else { "" };
+        let more_right = if shown.last().is_some_and(|&c| c + 1 < TABLE_COLUMNS.len()) { " ▶" } else { "" };
+        let title = format!("{more_left}Table ({} types; Tab: sort, Enter: edit, T: panes){more_right}", rows.len());
+        let widget = Table::new(body)
+            .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .widths(&widths)
+            .column_spacing(1)
+            .highlight_symbol("▶ ")
+            .highlight_style(Style::default().fg(Color::Yellow));
+        let mut state = TableState::default();
+        state.select(rows.iter().position(|&i| i == self.selected_type));
+        f.render_stateful_widget(widget, area, &mut state);
+    }
+
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(actions) if actions.is_empty() => {
+                self.status = String::from("Macro recording stopped; nothing recorded, previous macro kept");
+            }
+            Some(actions) => {
+                self.status = format!("Recorded macro of {} actions; @ replays it, 5@ five times", actions.len());
+                self.macro_actions = actions;
+            }
+            None => {
+                self.recording = Some(Vec::new());
+                self.status = String::from("Recording macro; m again to stop");
+            }
+        }
+    }
+
+    fn replay_macro(&mut self, count: usize) -> io::Result<()> {
+        if self.recording.is_some() {
+            self.status = String::from("Stop recording (m) before replaying");
+            return Ok(());
+        }
+        if self.macro_actions.is_empty() {
+            self.status = String::from("No macro recorded; press m to start recording");
+            return Ok(());
+        }
+        let actions = self.macro_actions.clone();
+        self.push_undo(format!("replay macro ×{count}"));
+        self.replaying = true;
+        let result = self.run_macro(&actions, count);
+        self.replaying = false;
+        if self.before_last_edit().is_some_and(|before| before == self.types) {
+            self.undo_stack.pop_back();
+        }
+        let (completed, stopped) = result?;
+        match stopped {
+            None => {
+                self.messages.summary(String::from("Replayed macro (u undoes all of it)"), completed);
+                self.status = format!("Replayed macro {completed} time(s) (u undoes all of it)");
+            }
+            Some(reason) => self.log(format!("Macro stopped after {completed} of {count} runs: {reason}")),
+        }
+        Ok(())
+    }
+
+    /// Runs `actions` up to `count` times, moving down one type between runs.
+    /// Returns the number of complete runs and, if it stopped early, why.
+    fn run_macro(&mut self, actions: &[Action], count: usize) -> io::Result<(usize, Option<String>)> {
+        for run in 0..count {
+            if run > 0 {
+                let visible = self.visible_types();
+                let next = visible
+                    .iter()
+                    .position(|&i| i == self.selected_type)
+                    .and_then(|pos| visible.get(pos + 1));
+                match next {
+                    Some(&next) => {
+                        self.selected_type = next;
+                        self.selected_field = 0;
+                    }
+                    None => return Ok((run, Some(String::from("reached the end of the type list")))),
+                }
+            }
+            for (step, action) in actions.iter().enumerate() {
+                if let Some(reason) = self.macro_step_problem(action) {
+                    let name = self.types.get(self.selected_type).map(|t| t.name.as_str()).unwrap_or("?");
+                    return Ok((run, Some(format!("step {} ({action:?}) on {name}: {reason}", step + 1))));
+                }
+                self.handle_action(action.clone())?;
+            }
+        }
+        Ok((count, None))
+    }
+
+    /// Why `action` can't be replayed in the current state, for actions that would otherwise
+    /// wrap around or silently do nothing.
+    fn macro_step_problem(&self, action: &Action) -> Option<&'static str> {
+        if self.overlay.is_some() || self.focus == EditorFocus::Editing {
+            return None;
+        }
+        let (pos, len) = match self.focus {
+            EditorFocus::Categories => (self.category_cursor, self.category_rows().len() + 1),
+            EditorFocus::TypeList => {
+                let visible = self.visible_types();
+                let pos = visible.iter().position(|&i| i == self.selected_type).unwrap_or(0);
+                (pos, visible.len())
+            }
+            _ => (self.selected_field, self.current_fields_len()),
+        };
+        match action {
+            Action::Down | Action::PgDown if pos + 1 >= len => Some("already at the end of the list"),
+            Action::Up | Action::PgUp if pos == 0 => Some("already at the start of the list"),
+            Action::Right if self.types.is_empty() => Some("no types"),
+            Action::Activate | Action::Copy | Action::Delete | Action::AddAttribute
+                if self.focus == EditorFocus::FieldList && self.current_field().is_none() =>
+            {
+                Some("the type has no such field")
+            }
+            Action::Activate | Action::Copy | Action::Duplicate | Action::Delete if self.types.is_empty() => {
+                Some("no types")
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&mut self, f: &mut tui::Frame<B>, show_help: bool) {
+        if let Some(EditorJob::Read(_, _, job)) = &self.job {
+            // Nothing of the new file to show yet; don't leave the last one behind the bar.
+            f.render_widget(Clear, f.size());
+            progress::render(f, job);
+            return;
+        }
+        let marked_line = self.marked_summary();
+        let mut constraints = vec![Constraint::Length(3), Constraint::Min(10)];
+        if marked_line.is_some() {
+            constraints.push(Constraint::Length(1));
+        }
+        constraints.push(Constraint::Length(3));
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(constraints)
+            .split(f.size());
+        let footer_area = chunks[chunks.len() - 1];
+
+        let connection_lost = matches!(self.source, FileSource::Remote(_)) && !self.remote_alive;
+        let header_text = match &self.path {
+            Some(path) => {
+                let src = match self.source {
+                    FileSource::Local => "local",
+                    FileSource::Remote(_) => "ssh",
+                };
+                if connection_lost {
+                    format!(
+                        "Editing: {} ({}) | remote connection lost — changes are only in memory",
+                        path.display(),
+                        src
+                    )
+                } else {
+                    format!("Editing: {} ({})", path.display(), src)
+                }
+            }
+            None if self.sample => String::from("Tutorial sample: not a file on disk; s saves a copy"),
+            None => String::from("No file loaded"),
+        };
+        let header_text = if self.readonly {
+            format!("READ-ONLY | {header_text}")
+        } else {
+            header_text
+        };
+        let header_text = if self.disk_changed {
+            format!("{header_text} | CHANGED ON DISK — R to reload or ignore")
+        } else {
+            header_text
+        };
+        let header_style = if connection_lost {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else if self.readonly || self.disk_changed {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let header = Paragraph::new(header_text)
+            .style(header_style)
+            .block(Block::default().title("File").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(header, chunks[0]);
+
+        if self.table.is_some() {
+            self.render_table(f, chunks[1]);
+        } else {
+            self.render_panes(f, chunks[1]);
+        }
+
+        // The input is cut to what fits on the line around the cursor, so the part being
+        // typed stays visible however long the value gets.
+        if let Some(line) = marked_line {
+            let line = utils::fit_width(&line, chunks[2].width.saturating_sub(1) as usize);
+            f.render_widget(Paragraph::new(format!(" {line}")).style(Style::default().fg(Color::DarkGray)), chunks[2]);
+        }
+        let inner_width = footer_area.width.saturating_sub(2) as usize;
+        let input = |prefix: &str, suffix: &str| {
+            let reserved = prefix.width() + suffix.width();
+            let width = inner_width.saturating_sub(reserved).max(8);
+            format!("{prefix}{}{suffix}", line_edit::view(&self.input_buffer, self.input_cursor, width))
+        };
+        let failed = self.error_status.as_ref() == Some(&self.status);
+        let footer_text = if self.editing_target == Some(EditTarget::SavePath) {
+            input("Save local copy as: ", " | Enter: save, Esc: cancel")
+        } else if self.editing_target == Some(EditTarget::Command) {
+            input(":", "")
+        } else if self.editing_target == Some(EditTarget::DuplicateName) {
+            let source = self.types.get(self.selected_type).map(|t| t.name.as_str()).unwrap_or_default();
+            input(&format!("Duplicate '{source}' as: "), &format!(" | {}", self.status))
+        } else if self.editing_target == Some(EditTarget::Goto) {
+            input("Go to type: ", " | Enter: jump (empty: back to the previous type), Esc: cancel")
+        } else if self.focus == EditorFocus::Editing && self.name_override.is_some() {
+            input("Editing (", &format!(") | {}", self.status))
+        } else if self.focus == EditorFocus::Editing {
+            input("Help: ? | Quit: Ctrl+Q | Status: editing (", ")")
+        } else {
+            let mut text = String::from(if self.config.instant_quit {
+                "Help: ? | Quit: q | "
+            } else {
+                "Help: ? | Close: q | Quit: Ctrl+Q | "
+            });
+            if self.recording.is_some() {
+                text.push_str("recording macro | ");
+            }
+            if let Some(end) = &self.exchange {
+                text.push_str(&format!("exchange: {} {} (e on another field, Esc cancels) | ", end.type_name, end.key.label()));
+            }
+            if let Some(count) = self.count_prefix {
+                text.push_str(&format!("count: {count} | "));
+            }
+            if !self.undo_stack.is_empty() {
+                text.push_str(&format!("undo: {} | ", self.undo_stack.len()));
+            }
+            if !self.issues.is_empty() {
+                text.push_str(&format!("issues: {} | ", self.issues.len()));
+            }
+            let mark = if failed && self.mono() { "! " } else { "" };
+            text.push_str(&format!("Status: {mark}{}", self.status));
+            text
+        };
+        let footer_style = if failed {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let footer = Paragraph::new(footer_text)
+            .style(footer_style)
+            .block(Block::default().title("Status").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+        f.render_widget(footer, footer_area);
+        self.render_recent_values(f, chunks[1]);
+
+        if show_help {
+            render_help_overlay(f);
+        }
+        match &self.job {
+            Some(EditorJob::DryRun(job)) => progress::render(f, job),
+            Some(EditorJob::Write(_, job)) => progress::render(f, job),
+            Some(EditorJob::Upload(_, job)) => progress::render(f, job),
+            Some(EditorJob::Rehearse(job)) => progress::render(f, job),
+            Some(EditorJob::Reload(job)) => progress::render(f, job),
+            Some(EditorJob::Read(..)) | None => {}
+        }
+        match &self.overlay {
+            Some(Overlay::RemoteLost) => render_remote_lost_overlay(f),
+            Some(Overlay::Statistics) => self.render_statistics_overlay(f),
+            Some(Overlay::ValidationReport) => self.render_validation_overlay(f),
+            Some(Overlay::NominalCap(tool)) => self.render_cap_overlay(f, tool),
+            Some(Overlay::Propagate(tool)) => self.render_propagate_overlay(f, tool),
+            Some(Overlay::Duplicates(review)) => self.render_duplicates_overlay(f, review),
+            Some(Overlay::Quantity(tool)) => render_quantity_overlay(f, tool),
+            Some(Overlay::Tiers(tool)) => render_tier_overlay(f, tool),
+            Some(Overlay::FlagMatrix(matrix)) => render_flag_matrix_overlay(f, matrix, &self.types),
+            Some(Overlay::ConfirmWritable) => render_confirm_writable_overlay(f),
+            Some(Overlay::ConfirmClose) => render_confirm_close_overlay(f),
+            Some(Overlay::ConfirmShrink(warning)) => render_confirm_shrink_overlay(f, warning, self.config.backup_keep),
+            Some(Overlay::ConfirmLowSpace(warning)) => render_confirm_low_space_overlay(f, warning),
+            Some(Overlay::ConfirmExchange(from, to)) => self.render_confirm_exchange_overlay(f, from, to),
+            Some(Overlay::ConfirmDelete(warning)) => render_confirm_delete_overlay(f, warning),
+            Some(Overlay::Reload) => {
+                let unsaved = diff::diff_types(&self.baseline, &self.types);
+                render_reload_overlay(f, self.disk_changed, (!unsaved.is_empty()).then(|| change_summary(&unsaved)));
+            }
+            Some(Overlay::Backups(browser)) => render_backups_overlay(f, browser),
+            Some(Overlay::AddElement(pending)) => self.render_add_element_overlay(f, pending),
+            Some(Overlay::Calc { result, error }) => self.render_calc_overlay(f, result.as_deref(), error.as_deref()),
+            Some(Overlay::SaveConflicts(review)) => render_conflicts_overlay(f, review),
+            Some(Overlay::BulkApply(run, scroll)) => {
+                let title = match run.writable() {
+                    0 => String::from("Dry run: no file would change (Esc: close)"),
+                    n => format!("Dry run: Enter writes {n} files with backups, Esc cancels"),
+                };
+                render_text_overlay(f, &title, &run.lines(), *scroll);
+            }
+            Some(Overlay::Assign(assignment, scroll)) => {
+                let title = format!("Enter: change {}", utils::plural(assignment.changes.len(), "type"));
+                render_text_overlay(f, &title, &assignment.lines(&self.types), *scroll);
+            }
+            Some(Overlay::StripFields(plan, scroll)) => {
+                let title = format!("Enter: remove {} from {}", utils::plural(plan.fields(), "field"), utils::plural(plan.changes.len(), "type"));
+                render_text_overlay(f, &title, &plan.lines(&self.types), *scroll);
+            }
+            Some(Overlay::CaseFixes(review, scroll)) => {
+                let title = match review.fixes.len() {
+                    0 => String::from("Nothing to rename (Esc: close)"),
+                    n => format!("Enter: rename {}, Esc: cancel", utils::plural(n, "type")),
+                };
+                render_text_overlay(f, &title, &review.lines(), *scroll);
+            }
+            Some(Overlay::GotoPick { query, matches, cursor }) => {
+                let names: Vec<&str> = matches.iter().map(|&i| self.types[i].name.as_str()).collect();
+                render_goto_overlay(f, query, &names, *cursor);
+            }
+            Some(Overlay::History { cursor }) => self.render_history_overlay(f, *cursor),
+            Some(Overlay::LifetimePresets { targets, cursor }) => {
+                let whom = match targets.as_slice() {
+                    [one] => self.types[*one].name.clone(),
+                    many => utils::plural(many.len(), "marked type"),
+                };
+                render_lifetime_overlay(f, &whom, &self.config.lifetime_presets, *cursor);
+            }
+            Some(Overlay::MissingFlags(targets)) => self.render_missing_flags_overlay(f, targets),
+            Some(Overlay::Text { title, lines, scroll }) => render_text_overlay(f, title, lines, *scroll),
+            None => {}
+        }
+        if self.overlay_help
+            && let Some(overlay) = &self.overlay
+        {
+            overlay_help::render(f, &overlay.help());
+        }
+        if let Some(tutorial) = &self.tutorial {
+            render_tutorial_panel(f, tutorial, chunks[1]);
+        }
+    }
+
+
+    fn render_missing_flags_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, targets: &[usize]) {
+        let area = utils::centered_rect(60, 50, f.size());
+        let mut lines = vec![
+            format!(
+                "{} no <flags> element; the CE will apply its own defaults to them:",
+                match targets.len() {
+                    1 => String::from("1 type has"),
+                    n => format!("{n} types have"),
+                }
+            ),
+            String::new(),
+        ];
+        lines.extend(targets.iter().take(MISSING_FLAGS_LISTED).map(|&i| format!("  {}", self.types[i].name)));
+        if targets.len() > MISSING_FLAGS_LISTED {
+            lines.push(format!("  ... and {} more", targets.len() - MISSING_FLAGS_LISTED));
+        }
+        lines.push(String::new());
+        lines.push(format!("f / Enter: add default flags to all {} and save", utils::plural(targets.len(), "type")));
+        lines.push(String::from("s: save anyway | Esc: cancel"));
+        lines.push(String::from("(require_flags = false under [validation] turns this check off)"));
+        let prompt = Paragraph::new(lines.join("\n"))
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title("Missing flags").borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(prompt, area);
+    }
+
+    /// `:cost`: sets every differing cost among the marked types, or all types, to the
+    /// expected one in one undo step.
+    fn normalize_cost(&mut self) {
+        let expected = self.config.expected_cost;
+        let marked = self.marked_types();
+        let scope: Vec<usize> = if marked.is_empty() {
+            (0..self.types.len()).collect()
+        } else {
+            marked.into_iter().collect()
+        };
+        let targets: Vec<usize> = scope
+            .into_iter()
+            .filter(|&i| validation::unexpected_cost(&self.types[i], expected).is_some())
+            .collect();
+        if targets.is_empty() {
+            self.status = format!("Every cost is already {expected}");
+            return;
+        }
+        self.push_undo(format!("set cost to {expected} on {}", utils::plural(targets.len(), "type")));
+        for &idx in &targets {
+            let ty = &mut self.types[idx];
+            if let Some(field) = ty.element_field("cost") {
+                ty.fields[field].value = expected.to_string();
+            }
+            self.note_change(idx);
+        }
+        self.revalidate();
+        self.undoable(format!("Set cost to {expected} on {}", utils::plural(targets.len(), "type")));
+    }
+
+    /// Gives each target the default `<flags>`, after its numeric elements, in one undo step.
+    fn add_default_flags(&mut self, targets: &[usize]) {
+        self.push_undo(format!("add default flags to {}", utils::plural(targets.len(), "type")));
+        for &idx in targets {
+            let Some(ty) = self.types.get_mut(idx) else {
+                continue;
+            };
+            flags::insert_default_flags(ty);
+            self.note_change(idx);
+        }
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.revalidate();
+        self.undoable(format!("Added default flags to {}", utils::plural(targets.len(), "type")));
+    }
+
+    fn render_history_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, cursor: usize) {
+        let area = utils::centered_rect(60, 60, f.size());
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut items: Vec<ListItem> = self
+            .redo_stack
+            .iter()
+            .map(|entry| ListItem::new(format!("  {} (undone)", entry.description)).style(dim))
+            .collect();
+        items.push(
+            ListItem::new(format!(
+                "● now (undo: {}, redo: {})",
+                self.undo_stack.len(),
+                self.redo_stack.len()
+            ))
+            .style(Style::default().fg(Color::Cyan)),
+        );
+        items.extend(
+            self.undo_stack
+                .iter()
+                .rev()
+                .map(|entry| ListItem::new(format!("  {}", entry.description))),
+        );
+        let mut state = ListState::default();
+        state.select(Some(cursor));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("History, newest first (Enter: go to that point, Esc: close)")
+                    .borders(Borders::ALL),
+            )
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(true));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Quick-pick list of recent values (and lifetime presets), just above the footer's
+    /// input line.
+    fn render_recent_values<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, area: Rect) {
+        let choices = self.value_choices();
+        if choices.is_empty() {
+            return;
+        }
+        let title = match self.current_field() {
+            _ if matches!(self.editing_target, Some(EditTarget::TypeName | EditTarget::DuplicateName)) => {
+                String::from("Classnames (↑/↓, Enter; type for free text)")
+            }
+            Some(field) => {
+                let what = if is_lifetime(&field.key) { "Recent and preset" } else { "Recent" };
+                format!("{what} {} (↑/↓, Enter; type for free text)", field.key.label())
+            }
+            None => return,
+        };
+        let height = (choices.len() as u16 + 2).min(area.height);
+        let width = 50.min(area.width);
+        let rect = Rect::new(area.x, area.y + area.height - height, width, height);
+        let items: Vec<ListItem> = choices.into_iter().map(|(shown, _)| ListItem::new(shown)).collect();
+        let mut state = ListState::default();
+        state.select(self.recent_pick);
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(self.recent_pick.is_some()));
+        f.render_widget(Clear, rect);
+        f.render_stateful_widget(list, rect, &mut state);
+    }
+
+    fn render_panes<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, mut area: Rect) {
+        if self.show_categories {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(CATEGORY_PANE_WIDTH), Constraint::Min(0)])
+                .split(area);
+            self.render_categories(f, columns[0]);
+            area = columns[1];
+        }
+        let constraints: Vec<Constraint> = self
+            .panes
+            .iter()
+            .filter(|&&width| width > 0)
+            .map(|&width| Constraint::Percentage(width))
+            .collect();
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(area);
+
+        let visible = self.visible_types();
+        let marked = self.marked_types();
+        // Borders and the highlight symbol take four columns.
+        let row_width = (body[0].width as usize).saturating_sub(4);
+        let type_items: Vec<ListItem> = visible
+            .iter()
+            .map(|&i| {
+                let t = &self.types[i];
+                let mut spans = Vec::new();
+                if !marked.is_empty() {
+                    let marker = if marked.contains(&i) { "■ " } else { "□ " };
+                    spans.push(Span::raw(marker));
+                }
+                if validation::check_type_name(&t.name).is_ok() {
+                    spans.push(Span::raw(t.name.clone()));
+                } else {
+                    spans.push(Span::styled(format!("{} !", t.name), Style::default().fg(Color::Red)));
+                }
+                if self.config.check_cost && validation::unexpected_cost(t, self.config.expected_cost).is_some() {
+                    spans.push(Span::styled(" ¢", Style::default().fg(Color::DarkGray)));
+                }
+                if self.is_modified(t) {
+                    spans.push(Span::styled(" *", Style::default().fg(Color::Cyan)));
+                }
+                if let Some(count) = self.session_changes.get(&t.name) {
+                    spans.push(Span::styled(format!(" ({count})"), Style::default().fg(Color::Yellow)));
+                }
+                if let Some(values) = self.inline_text(i) {
+                    let used: usize = spans.iter().map(|s| s.content.chars().count()).sum();
+                    let dots = row_width.saturating_sub(used + values.chars().count() + 2);
+                    let leader = if dots > 0 { format!(" {} ", "…".repeat(dots)) } else { String::from(" ") };
+                    spans.push(Span::styled(leader, Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::raw(values));
+                }
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+        let mut type_state = ListState::default();
+        if let Some(pos) = visible.iter().position(|&i| i == self.selected_type) {
+            type_state.select(Some(pos));
+        }
+        let mut types_title = match (self.type_filter, &self.category_filter) {
+            (Some(TypeFilter::ChangedThisSession), None) => format!("Types (changed this session: {})", visible.len()),
+            (Some(TypeFilter::ChangedThisSession), Some(category)) => {
+                format!("Types ({}, changed this session: {})", category.label(), visible.len())
+            }
+            (None, Some(category)) => format!("Types ({}: {})", category.label(), visible.len()),
+            (None, None) => String::from("Types"),
+        };
+        if !marked.is_empty() {
+            types_title.push_str(&format!(" [{} selected]", marked.len()));
+        }
+        let type_list = List::new(type_items)
+            .block(Block::default().title(types_title).borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(self.focus == EditorFocus::TypeList));
+        f.render_stateful_widget(type_list, body[0], &mut type_state);
+
+        let changed_keys: Vec<FieldKey> = match self.types.get(self.selected_type) {
+            Some(ty) => match self.baseline_for(&ty.name) {
+                Some(base) => diff::diff_fields(base, ty).into_iter().map(|c| c.key).collect(),
+                None => ty.fields.iter().map(|f| f.key.clone()).collect(),
+            },
+            None => Vec::new(),
+        };
+        let quantity = self.types.get(self.selected_type).and_then(|ty| {
+            let min = ty.fields.get(ty.element_field("quantmin")?)?.value.trim();
+            let max = ty.fields.get(ty.element_field("quantmax")?)?.value.trim();
+            Some(if min == "-1" && max == "-1" {
+                String::from("unused")
+            } else {
+                format!("{min}–{max}%")
+            })
+        });
+        let fields = self.current_fields();
+        let first_tier = fields
+            .iter()
+            .position(|f| tiers::is_value_name(&f.key) && tiers::is_tier(&f.value, &self.limit_tiers));
+        let tier_summary = self
+            .types
+            .get(self.selected_type)
+            .map(|ty| tiers::summary(&tiers::tiers_of(ty, &self.limit_tiers)))
+            .unwrap_or_default();
+        let field_width = (body[1].width as usize).saturating_sub(4);
+        let shown_fields = self.visible_fields();
+        let mut field_items: Vec<ListItem> = shown_fields
+            .iter()
+            .map(|&i| {
+                let field = &fields[i];
+                let label = match (&field.key, &quantity) {
+                    (FieldKey::Element { name, index: 0 }, Some(quantity)) if name == "quantmin" => {
+                        format!("quantity: {quantity}")
+                    }
+                    _ if Some(i) == first_tier => format!("tiers: {tier_summary}"),
+                    _ => format!("{}: {}", field.key.label(), field.value),
+                };
+                if !changed_keys.contains(&field.key) {
+                    ListItem::new(utils::fit_width(&label, field_width))
+                } else {
+                    ListItem::new(Spans::from(vec![
+                        Span::styled("* ", Style::default().fg(Color::Cyan)),
+                        Span::raw(utils::fit_width(&label, field_width.saturating_sub(2))),
+                    ]))
+                }
+            })
+            .collect();
+        let hidden = fields.len() - shown_fields.len();
+        if hidden > 0 {
+            let what = match self.field_filter {
+                FieldFilter::EmptyAndDefaultFlags => "empty or default-0",
+                _ => "empty",
+            };
+            let plural = if hidden == 1 { "" } else { "s" };
+            let row = format!("({hidden} {what} field{plural} hidden, z shows them)");
+            field_items.push(ListItem::new(Span::styled(utils::fit_width(&row, field_width), Style::default().fg(Color::DarkGray))));
+        }
+        let mut field_state = ListState::default();
+        if let Some(pos) = shown_fields.iter().position(|&i| i == self.selected_field) {
+            field_state.select(Some(pos));
+        }
+        let fields_title = match self.field_filter {
+            FieldFilter::All => "Fields",
+            FieldFilter::Empty => "Fields (hiding empty)",
+            FieldFilter::EmptyAndDefaultFlags => "Fields (hiding empty, default flags)",
+        };
+        let field_list = List::new(field_items)
+            .block(Block::default().title(fields_title).borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(
+                self.focus == EditorFocus::FieldList || self.focus == EditorFocus::Editing,
+            ));
+        f.render_stateful_widget(field_list, body[1], &mut field_state);
+
+        if body.len() < 3 {
+            return;
+        }
+        // A file without types, or a type without fields, has nothing to explain.
+        let mut tips = String::new();
+        if let Some(field) = self.current_field() {
+            tips = self.help.text(&field.key);
+            // Rows are cut to the pane width; the whole value is only readable here.
+            let row = format!("{}: {}", field.key.label(), field.value);
+            if row.chars().count() > field_width.saturating_sub(2) {
+                tips = format!("Full value ({} characters):\n{}\n\n{tips}", field.value.chars().count(), field.value);
+            }
+            if let Some(hint) = self.hints.describe(&field.key, &field.value) {
+                tips.push_str(&format!("\n\n{hint}"));
+            }
+            if tiers::is_value_name(&field.key)
+                && let Some(table) = tiers::describe(&self.help, self.mission_map.as_deref(), &self.limit_tiers, &field.value)
+            {
+                tips.push_str(&format!("\n\n{table}"));
+            }
+            if let Some(summary) = self.types.get(self.selected_type).and_then(balance::spawn_summary) {
+                tips.push_str(&format!("\n\nIn play: {summary}"));
+            }
+            if let Some(note) = self.types.get(self.selected_type).and_then(|ty| self.notes.get(&ty.name, &field.key))
+                && note.new == field.value
+            {
+                tips.push_str(&format!("\n\n{}", note.describe()));
+            }
+        }
+        let tips_widget = Paragraph::new(tips)
+            .block(Block::default().title("Tips").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(tips_widget, body[2]);
+    }
+
+    fn render_categories<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, area: Rect) {
+        let rows = self.category_rows();
+        let chosen = match &self.category_filter {
+            Some(category) => rows.iter().position(|(c, _)| c == category).map_or(0, |i| i + 1),
+            None => 0,
+        };
+        // Borders and the highlight symbol take four columns.
+        let width = (area.width as usize).saturating_sub(4);
+        let all = (String::from("All types"), self.types.len());
+        let items: Vec<ListItem> = std::iter::once(all)
+            .chain(rows.iter().map(|(c, n)| (c.label().to_string(), *n)))
+            .enumerate()
+            .map(|(i, (label, count))| {
+                let count = count.to_string();
+                // Without colour the chosen row needs a mark of its own.
+                let label = if self.mono() && i == chosen { format!("> {label}") } else { label };
+                let label = utils::fit_width(&label, width.saturating_sub(count.len() + 1));
+                let gap = width.saturating_sub(label.width() + count.len()).max(1);
+                let style = if i == chosen { Style::default().fg(Color::Yellow) } else { Style::default() };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(label, style),
+                    Span::raw(" ".repeat(gap)),
+                    Span::styled(count, Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(self.category_cursor.min(rows.len())));
+        let list = List::new(items)
+            .block(Block::default().title("Categories").borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(self.focus == EditorFocus::Categories));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_duplicates_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, review: &DuplicateReview) {
+        let area = utils::centered_rect(70, 70, f.size());
+        let items: Vec<ListItem> = review
+            .found
+            .iter()
+            .map(|d| {
+                let name = self.types.get(d.type_idx).map(|t| t.name.as_str()).unwrap_or("?");
+                ListItem::new(format!("{name}: {}", d.description))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(review.cursor));
+        let title = format!(
+            "{} duplicate elements in {} types (Enter: remove extras, Esc: cancel)",
+            review.found.iter().map(|d| d.extra_indices.len()).sum::<usize>(),
+            review.found.iter().map(|d| d.type_idx).collect::<BTreeSet<_>>().len()
+        );
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(true));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_propagate_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, tool: &PropagateTool) {
+        let area = utils::centered_rect(70, 70, f.size());
+        let targets = self.marked_types().into_iter().filter(|&i| i != tool.source).count();
+        let items: Vec<ListItem> = tool
+            .groups
+            .iter()
+            .map(|g| ListItem::new(format!("[{}] {}", if g.checked { "x" } else { " " }, g.label)))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(tool.cursor));
+        let title = format!(
+            "Copy values from {} to {} selected types (Space: tick, Enter: apply, Esc: cancel)",
+            self.types.get(tool.source).map(|t| t.name.as_str()).unwrap_or("?"),
+            targets
+        );
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_symbol("▶ ")
+            .highlight_style(highlight_for(true));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_cap_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, tool: &CapTool) {
+        let area = utils::centered_rect(70, 80, f.size());
+        let stats = &tool.stats;
+        let mut lines = vec![
+            format!("{} types with a nominal value in view", stats.count),
+            format!(
+                "min {} | p50 {} | p90 {} | p95 {} | p99 {} | max {}",
+                stats.min, stats.p50, stats.p90, stats.p95, stats.p99, stats.max
+            ),
+            String::new(),
+        ];
+        let widest = stats.histogram.iter().map(|b| b.2).max().unwrap_or(1).max(1);
+        for (low, high, count) in &stats.histogram {
+            let bar = "█".repeat((count * 30).div_ceil(widest));
+            let range = if low == high { low.to_string() } else { format!("{low}–{high}") };
+            lines.push(format!("{range:>11} {bar} {count}"));
+        }
+        lines.push(String::new());
+
+        match &tool.applied {
+            Some(changes) => {
+                lines.push(format!("Capped {} types:", changes.len()));
+                lines.extend(changes.iter().map(|change| format!("  {}", change.describe())));
+                lines.push(String::new());
+                lines.push(String::from("Enter/Esc: close | u afterwards to undo"));
+            }
+            None => {
+                let planned = tool
+                    .cap_input
+                    .parse::<i64>()
+                    .map(|cap| balance::plan_cap(&self.types, &tool.scope, cap, tool.scale_min, tool.scale_quantity))
+                    .unwrap_or_default();
+                lines.push(format!("Cap nominal at: {}_  (p: reset to p95 = {})", tool.cap_input, stats.p95));
+                lines.push(format!(
+                    "[{}] scale min proportionally (Tab to toggle)",
+                    if tool.scale_min { "x" } else { " " }
+                ));
+                lines.push(format!(
+                    "[{}] scale quantmin/quantmax of ammo and stackables too (q to toggle)",
+                    if tool.scale_quantity { "x" } else { " " }
+                ));
+                let mut summary = format!("{} types are above the cap", planned.len());
+                if tool.scale_quantity {
+                    let without = planned.iter().filter(|c| c.quantity.is_none()).count();
+                    summary.push_str(&format!("; {without} without stack quantities (-1/-1) keep them"));
+                }
+                lines.push(summary);
+                // As many planned changes as fit above the key hints.
+                let room = (area.height as usize).saturating_sub(lines.len() + 5);
+                for change in planned.iter().take(room) {
+                    lines.push(format!("  {}", change.describe()));
+                }
+                if planned.len() > room {
+                    lines.push(format!("  … and {} more", planned.len() - room));
+                }
+                lines.push(String::new());
+                lines.push(String::from("Enter: apply | Esc: close"));
+            }
+        }
+
+        let block = Block::default().title("Nominal distribution").borders(Borders::ALL);
+        let text = Paragraph::new(lines.join("\n")).wrap(Wrap { trim: false }).block(block);
+        f.render_widget(Clear, area);
+        f.render_widget(text, area);
+    }
+
+    fn render_statistics_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>) {
+        let area = utils::centered_rect(60, 60, f.size());
+        let field_count: usize = self.types.iter().map(|t| t.fields.len()).sum();
+        let total_edits: usize = self.session_changes.values().sum();
+        let mut changed: Vec<(&String, &usize)> = self.session_changes.iter().collect();
+        changed.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        let unsaved = diff::diff_types(&self.baseline, &self.types);
+        let unsaved_line = if unsaved.is_empty() {
+            String::from("Unsaved changes: none")
+        } else {
+            format!(
+                "Unsaved changes: {} added, {} removed, {} modified types",
+                unsaved.added.len(),
+                unsaved.removed.len(),
+                unsaved.changed.len()
+            )
+        };
+        let mut lines = vec![
+            format!("Types: {}", self.types.len()),
+            format!("Fields: {}", field_count),
+            unsaved_line,
+            String::new(),
+            format!("Types changed this session: {}", changed.len()),
+            format!("Edits this session: {}", total_edits),
+        ];
+        if !changed.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Most edited:"));
+            for (name, count) in changed.iter().take(10) {
+                let values = self.types.iter().position(|t| &t.name == *name).and_then(|i| self.summaries.get(i)).map(TypeSummary::values);
+                match values.filter(|v| !v.is_empty()) {
+                    Some(values) => lines.push(format!("  {name}: {count} ({values})")),
+                    None => lines.push(format!("  {name}: {count}")),
+                }
+            }
+        }
+        lines.push(String::new());
+        lines.push(String::from("Esc or i to close"));
+
+        let block = Block::default().title("Statistics").borders(Borders::ALL);
+        let stats = Paragraph::new(lines.join("\n")).wrap(Wrap { trim: false }).block(block);
+        f.render_widget(Clear, area);
+        f.render_widget(stats, area);
+    }
+
+    fn calculate_move_idx(&mut self, selected: usize, new_idx: isize, arr_len: isize) -> isize {
+        if selected == (arr_len - 1) as usize && new_idx >= arr_len {
+            return 0;
+        } else if selected == 0 && new_idx < 0 {
+            return arr_len - 1;
+        } else if new_idx < 0 {
+            return 0;
+        } else if new_idx >= arr_len {
+            return arr_len - 1;
+        }
+        new_idx
+    }
+
+    fn render_validation_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>) {
+        let area = utils::centered_rect(80, 70, f.size());
+        let mut lines: Vec<String> = self.issues.iter().map(|issue| self.describe_issue(issue)).collect();
+        if lines.is_empty() {
+            lines.push(String::from("No issues found."));
+        }
+        lines.push(String::new());
+        if self.issues.iter().any(|i| i.code == IssueCode::DuplicateAttribute) {
+            lines.push(String::from("d: remove repeated attributes, keeping the last value of each"));
+        }
+        lines.push(String::from("Enter/n: jump to next issue | Esc or v: close"));
+        let title = format!("Validation ({} issues)", self.issues.len());
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let report = Paragraph::new(lines.join("\n")).wrap(Wrap { trim: false }).block(block);
+        f.render_widget(Clear, area);
+        f.render_widget(report, area);
+    }
+
+    fn describe_issue(&self, issue: &Issue) -> String {
+        let type_name = self
+            .types
+            .get(issue.type_idx)
+            .map(|t| t.name.as_str())
+            .unwrap_or("?");
+        format!("{}: {}", type_name, issue.message)
+    }
+
+    /// Recomputes everything derived from the model: validation findings and inline summaries.
+    fn validation_rules(&self) -> validation::Rules<'_> {
+        validation::Rules {
+            strict: self.config.strict_validation,
+            help: &self.help,
+            require_flags: self.config.require_flags,
+            expected_cost: self.config.check_cost.then_some(self.config.expected_cost),
+        }
+    }
+
+    fn revalidate(&mut self) {
+        self.issues = validation::validate(&self.types, self.validation_rules());
+        self.summaries = self.types.iter().map(|ty| TypeSummary::of(ty, &self.limit_tiers)).collect();
+    }
+
+    /// Cheaper than `revalidate` when only the fields of one type changed.
+    fn revalidate_type(&mut self, type_idx: usize) {
+        let mut issues = mem::take(&mut self.issues);
+        validation::revalidate_type(&mut issues, &self.types, type_idx, self.validation_rules());
+        self.issues = issues;
+        if let (Some(summary), Some(ty)) = (self.summaries.get_mut(type_idx), self.types.get(type_idx)) {
+            *summary = TypeSummary::of(ty, &self.limit_tiers);
+        }
+    }
+
+    fn cycle_inline_values(&mut self) {
+        let (next, status) = match self.inline_values {
+            InlineValues::Off => (InlineValues::Nominal, "Types show nominal"),
+            InlineValues::Nominal => (InlineValues::NominalMin, "Types show nominal/min"),
+            InlineValues::NominalMin => (InlineValues::Off, "Types show names only"),
+        };
+        self.inline_values = next;
+        self.status = String::from(status);
+    }
+
+    fn cycle_field_filter(&mut self) {
+        let (next, status) = match self.field_filter {
+            FieldFilter::All => (FieldFilter::Empty, "Fields hides empty values"),
+            FieldFilter::Empty => (FieldFilter::EmptyAndDefaultFlags, "Fields hides empty values and flags at their default 0"),
+            FieldFilter::EmptyAndDefaultFlags => (FieldFilter::All, "Fields shows every field"),
+        };
+        self.field_filter = next;
+        self.status = String::from(status);
+    }
+
+    fn field_hidden(&self, field: &Field) -> bool {
+        let default_flag = || match &field.key {
+            FieldKey::Attribute { element, attr, .. } => element == "flags" && field.value.trim() == "0" && !flags::default_of(attr),
+            FieldKey::Element { .. } => false,
+        };
+        match self.field_filter {
+            FieldFilter::All => false,
+            FieldFilter::Empty => field.value.trim().is_empty(),
+            FieldFilter::EmptyAndDefaultFlags => field.value.trim().is_empty() || default_flag(),
+        }
+    }
+
+    /// Indices into the selected type's fields shown in the Fields pane, in display order.
+    /// The selected field is among them even when the filter would hide it, so a field
+    /// being added, edited or jumped to stays in view.
+    fn visible_fields(&self) -> Vec<usize> {
+        let Some(ty) = self.types.get(self.selected_type) else {
+            return Vec::new();
+        };
+        (0..ty.fields.len()).filter(|&i| i == self.selected_field || !self.field_hidden(&ty.fields[i])).collect()
+    }
+
+    /// The highlighted type in one line while types are marked, so its numbers are at hand
+    /// without leaving the Types pane. Read from `summaries`, not the fields.
+    fn marked_summary(&self) -> Option<String> {
+        if self.selected_types.is_empty() || self.focus != EditorFocus::TypeList {
+            return None;
+        }
+        let ty = self.types.get(self.selected_type)?;
+        Some(self.summaries.get(self.selected_type)?.line(&ty.name))
+    }
+
+    /// `8/4` style inline text for a type, `-` for missing elements.
+    fn inline_text(&self, type_idx: usize) -> Option<String> {
+        let summary = self.summaries.get(type_idx)?;
+        let show = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("-"));
+        match self.inline_values {
+            InlineValues::Off => None,
+            InlineValues::Nominal => Some(show(&summary.nominal)),
+            InlineValues::NominalMin => Some(format!("{}/{}", show(&summary.nominal), show(&summary.min))),
+        }
+    }
+
+    /// `d` in the validation report: every attribute repeated on one element goes but its
+    /// last copy, the value a reader would have kept, in one undo step.
+    fn remove_repeated_attributes(&mut self) {
+        self.overlay = Some(Overlay::ValidationReport);
+        let touched: Vec<usize> = (0..self.types.len()).filter(|&i| !dedupe::repeated_attributes(&self.types[i]).is_empty()).collect();
+        if touched.is_empty() {
+            self.status = String::from("No attribute is repeated on its element");
+            return;
+        }
+        self.push_undo("remove repeated attributes");
+        let mut removed = 0;
+        for &idx in &touched {
+            removed += dedupe::remove_repeated_attributes(&mut self.types[idx]);
+            self.note_change(idx);
+        }
+        self.selected_field = self.selected_field.min(self.current_fields_len().saturating_sub(1));
+        self.revalidate();
+        self.undoable(format!(
+            "Removed {} from {}",
+            utils::plural(removed, "repeated attribute"),
+            utils::plural(touched.len(), "type")
+        ));
+    }
+
+    /// Moves the selection to the next (or previous) issue after the cursor, wrapping at the ends.
+    fn jump_to_issue(&mut self, forward: bool) {
+        if self.issues.is_empty() {
+            self.status = String::from("No validation issues");
+            return;
+        }
+        let on_fields = self.focus == EditorFocus::FieldList;
+        let cursor = (self.selected_type, on_fields.then_some(self.selected_field));
+        let position = |i: &Issue| (i.type_idx, i.field_idx);
+        let idx = if forward {
+            self.issues
+                .iter()
+                .position(|i| position(i) > cursor)
+                .unwrap_or(0)
+        } else {
+            self.issues
+                .iter()
+                .rposition(|i| position(i) < cursor)
+                .unwrap_or(self.issues.len() - 1)
+        };
+        let issue = self.issues[idx].clone();
+        self.record_jump();
+        self.selected_type = issue.type_idx;
+        self.reveal_type(issue.type_idx);
+        match issue.field_idx {
+            Some(field_idx) => {
+                self.selected_field = field_idx;
+                self.focus = EditorFocus::FieldList;
+            }
+            None => {
+                self.selected_field = 0;
+                self.focus = EditorFocus::TypeList;
+            }
+        }
+        self.status = format!("Issue {}/{}: {}", idx + 1, self.issues.len(), self.describe_issue(&issue));
+    }
+
+    /// Indices into `types` shown in the Types pane, in display order.
+    fn visible_types(&self) -> Vec<usize> {
+        (0..self.types.len()).filter(|&i| self.passes_filters(i)).collect()
+    }
+
+    /// Whether the type at `type_idx` passes both the `f` filter and the chosen category.
+    fn passes_filters(&self, type_idx: usize) -> bool {
+        let Some(ty) = self.types.get(type_idx) else {
+            return false;
+        };
+        let changed = match self.type_filter {
+            None => true,
+            Some(TypeFilter::ChangedThisSession) => self.session_changes.contains_key(&ty.name),
+        };
+        changed && self.category_filter.as_ref().is_none_or(|category| *category == CategoryFilter::of(ty))
+    }
+
+    /// Clears whichever filters hide the type at `type_idx`; returns whether any was cleared.
+    fn reveal_type(&mut self, type_idx: usize) -> bool {
+        if self.passes_filters(type_idx) {
+            return false;
+        }
+        self.type_filter = None;
+        self.category_filter = None;
+        true
+    }
+
+    /// Rows of the categories pane after "all types": each category name with its type
+    /// count, sorted by name, then `<none>`. The chosen category stays listed at 0.
+    fn category_rows(&self) -> Vec<(CategoryFilter, usize)> {
+        let mut counts: BTreeMap<CategoryFilter, usize> = BTreeMap::new();
+        for ty in &self.types {
+            *counts.entry(CategoryFilter::of(ty)).or_insert(0) += 1;
+        }
+        if let Some(category) = &self.category_filter {
+            counts.entry(category.clone()).or_insert(0);
+        }
+        counts.into_iter().collect()
+    }
+
+    fn toggle_categories(&mut self) {
+        self.show_categories = !self.show_categories;
+        if self.show_categories {
+            self.focus = EditorFocus::Categories;
+            let rows = self.category_rows();
+            self.category_cursor = match &self.category_filter {
+                Some(category) => rows.iter().position(|(c, _)| c == category).map_or(0, |i| i + 1),
+                None => 0,
+            };
+            self.status = String::from("Categories: Enter shows one category, F hides the pane");
+        } else {
+            if self.focus == EditorFocus::Categories {
+                self.focus = EditorFocus::TypeList;
+            }
+            if self.category_filter.take().is_some() {
+                self.status = String::from("Categories hidden; category filter cleared");
+            } else {
+                self.status = String::from("Categories hidden");
+            }
+        }
+    }
+
+    /// Enter on the categories pane: filters the Types pane to the highlighted category.
+    fn choose_category(&mut self) {
+        let rows = self.category_rows();
+        self.record_jump();
+        self.category_filter = self.category_cursor.checked_sub(1).and_then(|i| rows.get(i)).map(|(c, _)| c.clone());
+        self.clamp_selection_to_view();
+        self.status = match &self.category_filter {
+            Some(category) => format!("Showing {} in {}", utils::plural(self.visible_types().len(), "type"), category.label()),
+            None => String::from("Showing all categories"),
+        };
+    }
+
+    /// Keeps `selected_type` on a visible row after the list or the filter changed.
+    fn clamp_selection_to_view(&mut self) {
+        let visible = self.visible_types();
+        if visible.contains(&self.selected_type) {
+            return;
+        }
+        self.selected_type = visible
+            .iter()
+            .copied()
+            .find(|&i| i > self.selected_type)
+            .or_else(|| visible.last().copied())
+            .unwrap_or(0);
+        self.selected_field = 0;
+    }
+
+    fn toggle_changed_filter(&mut self) {
+        if self.type_filter.is_some() {
+            self.type_filter = None;
+            self.status = String::from("Showing all types");
+            return;
+        }
+        if self.session_changes.is_empty() {
+            self.status = String::from("No types changed this session");
+            return;
+        }
+        self.record_jump();
+        self.type_filter = Some(TypeFilter::ChangedThisSession);
+        self.clamp_selection_to_view();
+        self.focus = EditorFocus::TypeList;
+        self.status = format!("Showing {} types changed this session", self.session_changes.len());
+    }
+
+    /// Counts an edit against the type at `type_idx` for the session change markers.
+    fn note_change(&mut self, type_idx: usize) {
+        if let Some(ty) = self.types.get(type_idx) {
+            *self.session_changes.entry(ty.name.clone()).or_insert(0) += 1;
+        }
+        self.annotate(type_idx);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        match self.focus {
+            EditorFocus::Categories => {
+                let rows = self.category_rows().len() as isize + 1;
+                self.category_cursor = self.calculate_move_idx(self.category_cursor, self.category_cursor as isize + delta, rows) as usize;
+            }
+            EditorFocus::TypeList => {
+                let visible = self.visible_types();
+                if visible.is_empty() {
+                    return;
+                }
+                let current = visible.iter().position(|&i| i == self.selected_type).unwrap_or(0);
+                let new_pos = self
+                    .calculate_move_idx(current, current as isize + delta, visible.len() as isize);
+                self.selected_type = visible[new_pos as usize];
+                self.selected_field = 0;
+            }
+            EditorFocus::FieldList => {
+                let visible = self.visible_fields();
+                if visible.is_empty() {
+                    return;
+                }
+                let current = visible.iter().position(|&i| i == self.selected_field).unwrap_or(0);
+                let new_pos = self.calculate_move_idx(current, current as isize + delta, visible.len() as isize);
+                self.selected_field = visible[new_pos as usize];
+            }
+            EditorFocus::Editing => {}
+        }
+    }
+
+    fn begin_editing(&mut self) {
+        match self.focus {
+            EditorFocus::Categories => self.choose_category(),
+            EditorFocus::TypeList => {
+                if let Some(ty) = self.types.get(self.selected_type) {
+                    self.input_buffer = ty.name.clone();
+                    self.editing_target = Some(EditTarget::TypeName);
+                    self.focus = EditorFocus::Editing;
+                    self.status = String::from("Editing type name");
+                }
+            }
+            EditorFocus::FieldList => {
+                let on_tier = self
+                    .current_field()
+                    .is_some_and(|f| tiers::is_value_name(&f.key) && tiers::is_tier(&f.value, &self.limit_tiers));
+                if on_tier {
+                    self.open_tier_tool();
+                } else if !self.open_quantity_tool() {
+                    self.begin_field_value_edit();
+                }
+            }
+            EditorFocus::Editing => {}
+        }
+    }
+
+    fn begin_field_value_edit(&mut self) {
+        if let Some(field) = self.current_field() {
+            self.input_buffer = field.value.clone();
+            self.editing_target = Some(EditTarget::FieldValue);
+            self.focus = EditorFocus::Editing;
+            self.status = String::from("Editing field value");
+            self.offer_recent();
+            self.use_calc_carry();
+        }
+    }
+
+    /// Puts a result taken from the calculator into the edit that just started.
+    fn use_calc_carry(&mut self) {
+        if let Some(value) = self.calc_carry.take() {
+            self.status = format!("{} (calculator result {value} filled in)", self.status);
+            self.input_buffer = value;
+            self.recent_pick = None;
+        }
+    }
+
+    fn open_calculator(&mut self, expression: &str) {
+        self.command_return_focus = self.focus;
+        self.focus = EditorFocus::Editing;
+        self.input_buffer = expression.to_string();
+        let (mut result, mut error) = (None, None);
+        if !expression.trim().is_empty() {
+            self.evaluate_calc(&mut result, &mut error);
+        }
+        self.overlay = Some(Overlay::Calc { result, error });
+    }
+
+    fn evaluate_calc(&mut self, result: &mut Option<String>, error: &mut Option<String>) {
+        let expression = std::mem::take(&mut self.input_buffer).trim().to_string();
+        let fields = self.types.get(self.selected_type).map(|t| t.fields.as_slice()).unwrap_or_default();
+        match eval_over(fields, &expression) {
+            Ok(value) => {
+                *result = Some(value.clone());
+                *error = None;
+                self.calc_history.push_back((expression, value));
+                if self.calc_history.len() > CALC_HISTORY {
+                    self.calc_history.pop_front();
+                }
+            }
+            Err(err) => {
+                *error = Some(err);
+                self.input_buffer = expression;
+            }
+        }
+    }
+
+    fn handle_calc_action(&mut self, mut result: Option<String>, mut error: Option<String>, action: Action) {
+        match action {
+            _ if self.edit_input(&action) => {}
+            // Up recalls the last expression to tweak it.
+            Action::Up => {
+                if let Some((expression, _)) = self.calc_history.back() {
+                    self.input_buffer = expression.clone();
+                    self.input_cursor = None;
+                }
+            }
+            Action::Activate if self.input_buffer.trim().is_empty() => {
+                self.focus = self.command_return_focus;
+                if let Some(value) = result {
+                    self.status = format!("{value} goes into the next value you edit");
+                    self.calc_carry = Some(value);
+                }
+                return;
+            }
+            Action::Activate => self.evaluate_calc(&mut result, &mut error),
+            Action::Cancel => {
+                self.input_buffer.clear();
+                self.focus = self.command_return_focus;
+                return;
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::Calc { result, error });
+    }
+
+    fn render_calc_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, result: Option<&str>, error: Option<&str>) {
+        let area = utils::centered_rect(60, 50, f.size());
+        let mut lines = Vec::new();
+        if let Some(ty) = self.types.get(self.selected_type) {
+            let variables: Vec<String> = CALC_VARIABLES
+                .iter()
+                .filter_map(|name| {
+                    let field = ty.fields.iter().find(|f| matches!(&f.key, FieldKey::Element { name: n, .. } if n == name))?;
+                    Some(format!("{name}={}", field.value))
+                })
+                .collect();
+            lines.push(format!("{}: {}", ty.name, variables.join("  ")));
+            lines.push(String::new());
+        }
+        for (expression, value) in &self.calc_history {
+            lines.push(format!("  {expression} = {value}"));
+        }
+        if !self.calc_history.is_empty() {
+            lines.push(String::new());
+        }
+        let input_width = (area.width as usize).saturating_sub(5);
+        lines.push(format!("> {}", line_edit::view(&self.input_buffer, self.input_cursor, input_width)));
+        match (error, result) {
+            (Some(err), _) => lines.push(format!("Error: {err}")),
+            (None, Some(value)) => lines.push(format!("= {value}   (Enter on an empty line: use it in the next edit)")),
+            (None, None) => lines.push(String::new()),
+        }
+        lines.push(String::new());
+        lines.push(String::from("+ - * / % ( ); Enter: evaluate, Up: last expression, Esc: close"));
+        let paragraph = Paragraph::new(lines.join("\n"))
+            .block(Block::default().title("Calculator").borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    /// What Up/Down offer while a value is edited, as (shown, value): the values committed
+    /// to this field this session, most recent first, then for `lifetime` the presets that
+    /// aren't among them. A type name being typed gets the classnames it starts.
+    fn value_choices(&self) -> Vec<(String, String)> {
+        if matches!(self.editing_target, Some(EditTarget::TypeName | EditTarget::DuplicateName)) {
+            let Some(list) = &self.classnames else {
+                return Vec::new();
+            };
+            return list.completions(&self.input_buffer).into_iter().map(|name| (name.to_string(), name.to_string())).collect();
+        }
+        if !matches!(self.editing_target, Some(EditTarget::FieldValue | EditTarget::TableCell)) {
+            return Vec::new();
+        }
+        let Some(field) = self.current_field() else {
+            return Vec::new();
+        };
+        let mut choices: Vec<(String, String)> =
+            self.recent_values.get(&field.key.label()).into_iter().flatten().map(|v| (v.clone(), v.clone())).collect();
+        if is_lifetime(&field.key) {
+            for (name, seconds) in &self.config.lifetime_presets {
+                let value = seconds.to_string();
+                if !choices.iter().any(|(_, v)| *v == value) {
+                    choices.push((format!("{value} ({name})"), value));
+                }
+            }
+        }
+        choices
+    }
+
+    /// Pre-highlights the most recent value, if there is one; presets wait for Up/Down.
+    fn offer_recent(&mut self) {
+        let recent = self.current_field().and_then(|f| self.recent_values.get(&f.key.label())).is_some_and(|v| !v.is_empty());
+        self.recent_pick = (recent && !self.value_choices().is_empty()).then_some(0);
+    }
+
+    fn move_recent_pick(&mut self, down: bool) {
+        let len = self.value_choices().len();
+        if len == 0 {
+            return;
+        }
+        self.recent_pick = Some(match (self.recent_pick, down) {
+            (None, _) => 0,
+            (Some(i), true) => (i + 1).min(len - 1),
+            (Some(i), false) => i.saturating_sub(1),
+        });
+    }
+
+    fn remember_value(&mut self, label: String, value: &str) {
+        let values = self.recent_values.entry(label).or_default();
+        values.retain(|v| v != value);
+        values.push_front(value.to_string());
+        values.truncate(RECENT_LIMIT);
+    }
+
+    /// Types at the cursor, refusing text past `max_value_length` so a stray paste can't
+    /// produce a value megabytes long.
+    fn push_input(&mut self, c: char) {
+        let max = self.config.max_value_length;
+        if self.input_buffer.chars().count() >= max {
+            self.fail(format!("Input stopped at {max} characters (max_value_length)"));
+            return;
+        }
+        line_edit::edit(&mut self.input_buffer, &mut self.input_cursor, &Action::Input(c));
+    }
+
+    /// Typing and the line-editing keys on `input_buffer`; false for other actions.
+    fn edit_input(&mut self, action: &Action) -> bool {
+        match *action {
+            Action::Input(c) if !c.is_control() => {
+                self.push_input(c);
+                true
+            }
+            _ => line_edit::edit(&mut self.input_buffer, &mut self.input_cursor, action),
+        }
+    }
+
+    fn stop_editing(&mut self) {
+        self.focus = match self.editing_target {
+            Some(EditTarget::TypeName | EditTarget::DuplicateName) => EditorFocus::TypeList,
+            Some(EditTarget::FieldName) => EditorFocus::FieldList,
+            Some(EditTarget::FieldValue) => EditorFocus::FieldList,
+            Some(EditTarget::SavePath) => EditorFocus::TypeList,
+            Some(EditTarget::Command | EditTarget::Goto) => self.command_return_focus,
+            Some(EditTarget::TableCell) => EditorFocus::TypeList,
+            None => self.focus,
+        };
+        self.editing_target = None;
+        self.recent_pick = None;
+        self.input_buffer.clear();
+        self.input_cursor = None;
+    }
+
+    fn apply_input(&mut self) -> bool {
+        let value = self.input_buffer.clone();
+        match self.editing_target {
+            Some(EditTarget::TypeName) => {
+                let problem = validation::check_type_name(&value).err();
+                if let Some(problem) = &problem
+                    && problem.needs_override()
+                    && self.name_override.as_deref() != Some(value.as_str())
+                {
+                    self.status = format!("{}; Enter again to keep it anyway", problem.describe(&value));
+                    self.name_override = Some(value);
+                    return true;
+                }
+                self.name_override = None;
+                let warning = problem.map(|p| p.describe(&value));
+                if let Some(old_name) = self.types.get(self.selected_type).map(|t| t.name.clone())
+                    && old_name != value
+                {
+                    self.push_undo(format!("rename type {old_name} → {value}"));
+                }
+                let marked = self.selected_types.remove(&self.types, self.selected_type);
+                if let Some(ty) = self.types.get_mut(self.selected_type) {
+                    let old_name = std::mem::replace(&mut ty.name, value);
+                    if let Some(count) = self.session_changes.remove(&old_name) {
+                        self.session_changes.insert(ty.name.clone(), count);
+                    }
+                    if marked {
+                        self.selected_types.insert(&self.types, self.selected_type);
+                    }
+                    self.note_change(self.selected_type);
+                    self.revalidate();
+                    let unlisted = self.classnames.as_ref().and_then(|list| list.note(&self.types[self.selected_type].name));
+                    self.status = match (warning, unlisted) {
+                        (Some(warning), _) => format!("Type renamed, but {warning}; DayZ will drop it"),
+                        (None, Some(note)) => format!("Type renamed, but {note}"),
+                        (None, None) => String::from("Type renamed"),
+                    };
+                }
+                false
+            }
+            Some(EditTarget::FieldName) => {
+                let type_idx = self.selected_type;
+                if let Some(clash) = self.attribute_clash(&value) {
+                    self.fail(clash);
+                    return true;
+                }
+                if let Some(field) = self.current_field() {
+                    self.push_undo(format!("rename field {}", field.key.label()));
+                }
+                if let Some(field) = self.current_field_mut() {
+                    field.key.set_name(value);
+                    self.note_change(type_idx);
+                    self.revalidate_type(type_idx);
+                    if let Some(field) = self.current_field() {
+                        self.input_buffer = field.value.clone();
+                        self.editing_target = Some(EditTarget::FieldValue);
+                        self.status = String::from("Field renamed; edit value");
+                        self.offer_recent();
+                        return true;
+                    }
+                    self.status = String::from("Field renamed");
+                }
+                false
+            }
+            Some(EditTarget::FieldValue) | Some(EditTarget::TableCell) => {
+                let type_idx = self.selected_type;
+                let expression = value.strip_prefix('=').map(|e| e.trim().to_string());
+                let value = match &expression {
+                    Some(expression) => {
+                        let fields = self.types.get(type_idx).map(|t| t.fields.as_slice()).unwrap_or_default();
+                        match eval_over(fields, expression) {
+                            Ok(result) => result,
+                            Err(err) => {
+                                self.fail(format!("{err}; fix the expression or Esc to cancel"));
+                                return true;
+                            }
+                        }
+                    }
+                    None => value,
+                };
+                if let Some(field) = self.current_field()
+                    && field.value != value
+                {
+                    let type_name = self.types.get(type_idx).map(|t| t.name.clone()).unwrap_or_default();
+                    self.push_undo(format!("set {} {} → {value} on {type_name}", field.key.label(), field.value));
+                }
+                if let Some(field) = self.current_field_mut() {
+                    let changed = field.value != value;
+                    let label = field.key.label();
+                    field.value = value.clone();
+                    self.last_edit = Some(match expression {
+                        Some(expression) => LastEdit::Expression { field: field.clone(), expression },
+                        None => LastEdit::Values(vec![field.clone()]),
+                    });
+                    if changed {
+                        self.note_change(type_idx);
+                        self.revalidate_type(type_idx);
+                    }
+                    self.remember_value(label, &value);
+                    self.status = String::from("Value updated");
+                }
+                false
+            }
+            Some(EditTarget::SavePath) => {
+                self.save_local_copy(PathBuf::from(value));
+                false
+            }
+            Some(EditTarget::Command) => {
+                self.stop_editing();
+                self.run_command(&value);
+                // A bare bulk command puts its last arguments back on the command line.
+                self.editing_target.is_some()
+            }
+            Some(EditTarget::Goto) => {
+                self.stop_editing();
+                self.goto_type(&value);
+                false
+            }
+            Some(EditTarget::DuplicateName) => self.duplicate_as(value),
+            None => false,
+        }
+    }
+
+    /// Saves, first asking when the file would shrink drastically (`shrink_guard_percent`,
+    /// `empty_guard`), then when its filesystem is nearly full (`min_free_mb`), then about
+    /// types without `<flags>` unless `require_flags` is off.
+    fn save(&mut self) -> io::Result<()> {
+        if self.sample {
+            self.begin_save_as();
+            return Ok(());
+        }
+        if self.path.is_some()
+            && let Some(warning) = self.shrink_warning()
+        {
+            self.status = format!("Saving would shrink the file: {}; y saves anyway", warning.describe());
+            self.overlay = Some(Overlay::ConfirmShrink(warning));
+            return Ok(());
+        }
+        self.save_checking_space()
+    }
+
+    /// Where the free space can be told (statvfs, locally or over SFTP), asks when it is
+    /// below `min_free_mb` or below the size of the file about to be written.
+    fn save_checking_space(&mut self) -> io::Result<()> {
+        self.free_after_save = None;
+        let dir = self.path.as_ref().and_then(|p| p.parent()).filter(|d| !d.as_os_str().is_empty());
+        if let Some(free) = (self.free_space)(&self.source, dir.unwrap_or(Path::new(".")))
+            && let Ok(xml) = self.serialize()
+        {
+            let needed = xml.len() as u64;
+            let threshold = self.config.min_free_mb.saturating_mul(MIB);
+            self.free_after_save = Some(free.saturating_sub(needed.saturating_sub(self.disk_bytes)));
+            if self.path.is_some() && (free < needed || free < threshold) {
+                let warning = SpaceWarning { free, needed, threshold };
+                self.status = format!("Low disk space: {}; y saves anyway", warning.describe());
+                self.overlay = Some(Overlay::ConfirmLowSpace(warning));
+                return Ok(());
+            }
+        }
+        self.save_checking_flags()
+    }
+
+    /// `; dropped 2 comments (...)` after the save that wrote the file without them, once.
+    fn dropped_comments_note(&mut self) -> String {
+        match std::mem::take(&mut self.other_comments) {
+            0 => String::new(),
+            n => format!("; dropped {} (saves keep only inline changelog comments)", utils::plural(n, "comment")),
+        }
+    }
+
+    /// Set when the save would write no types where the file had some, or lose more than
+    /// `shrink_guard_percent` of its bytes.
+    fn shrink_warning(&self) -> Option<ShrinkWarning> {
+        let types = (self.baseline.len(), self.types.len());
+        let emptied = self.config.empty_guard && types.0 > 0 && types.1 == 0;
+        let percent = u64::from(self.config.shrink_guard_percent.min(100));
+        if !emptied && (percent == 0 || self.disk_bytes == 0) {
+            return None;
+        }
+        // An unwritable model fails here too; the save itself reports why.
+        let after = self.serialize().ok()?.len() as u64;
+        let shrunk = percent > 0 && after.saturating_mul(100) < self.disk_bytes.saturating_mul(100 - percent);
+        (emptied || shrunk).then_some(ShrinkWarning { bytes: (self.disk_bytes, after), types })
+    }
+
+    fn save_checking_flags(&mut self) -> io::Result<()> {
+        if self.config.require_flags && self.path.is_some() {
+            let missing = validation::missing_flags(&self.types);
+            if !missing.is_empty() {
+                self.status = format!("{} without <flags>; add the defaults or save anyway", utils::plural(missing.len(), "type"));
+                self.overlay = Some(Overlay::MissingFlags(missing));
+                return Ok(());
+            }
+        }
+        self.save_unchecked()
+    }
+
+    fn save_unchecked(&mut self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(p) => p.clone(),
+            None => {
+                self.status = String::from("No file loaded");
+                return Ok(());
+            }
+        };
+        if let Some(problem) = model::find_unwritable(&self.types) {
+            self.selected_type = problem.type_idx;
+            self.reveal_type(problem.type_idx);
+            self.selected_field = problem.field_idx.unwrap_or(0);
+            self.focus = if problem.field_idx.is_some() { EditorFocus::FieldList } else { EditorFocus::TypeList };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, problem.message));
+        }
+        if matches!(self.source, FileSource::Remote(_)) && !self.remote_alive {
+            self.overlay = Some(Overlay::RemoteLost);
+            self.status = String::from("Remote connection lost; choose how to keep your changes");
+            return Ok(());
+        }
+        let on_disk = match &self.source {
+            FileSource::Local => fs::read_to_string(&path).ok(),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .read_file(&path)
+                .ok(),
+        };
+        if let Some(content) = on_disk.as_ref()
+            && self.disk_hash.is_some_and(|hash| hash != merge::content_hash(content))
+        {
+            self.save_over_changed(content.clone())
+        } else {
+            self.write_file(path, on_disk)
+        }
+    }
+
+    /// Someone else wrote the file since it was loaded: merge their changes in, asking
+    /// about fields both sides changed.
+    fn save_over_changed(&mut self, on_disk: String) -> io::Result<()> {
+        let theirs = match parse_types(&on_disk) {
+            Ok((mut types, report)) => {
+                self.normalize(&mut types);
+                // Their file holds the newest history; this save's entry goes after it.
+                self.changelog = report.changelog;
+                self.other_comments = report.other_comments;
+                types
+            }
+            Err(err) => {
+                self.log(format!("The file changed on disk and no longer parses ({err}); save aborted"));
+                return Ok(());
+            }
+        };
+        let conflicts = merge::conflicts(&self.baseline, &self.types, &theirs);
+        if conflicts.is_empty() {
+            return self.save_merged(on_disk, theirs, &conflicts);
+        }
+        self.status = format!(
+            "The file changed on disk; {} conflicting changes need a side (←/→) before saving",
+            conflicts.len()
+        );
+        self.overlay = Some(Overlay::SaveConflicts(ConflictReview {
+            their_changes: change_summary(&diff::diff_types(&self.baseline, &theirs)),
+            my_changes: change_summary(&diff::diff_types(&self.baseline, &self.types)),
+            on_disk,
+            theirs,
+            conflicts,
+            cursor: 0,
+        }));
+        Ok(())
+    }
+
+    fn save_merged(&mut self, on_disk: String, theirs: Vec<TypeEntry>, conflicts: &[Conflict]) -> io::Result<()> {
+        let merged = merge::merge(&self.baseline, &self.types, &theirs, conflicts);
+        let pulled = diff::diff_types(&self.types, &merged);
+        if !pulled.is_empty() {
+            self.push_undo("merge changes made on disk");
+            self.replace_types(merged);
+        }
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        self.write_file(path, Some(on_disk))?;
+        if !pulled.is_empty() {
+            // Remote saves finish in the background and report their own result.
+            let outcome = if self.job.is_some() { "; uploading" } else { " and saved" };
+            self.log(format!("Merged changes from disk ({}){outcome}", change_summary(&pulled)));
+        }
+        Ok(())
+    }
+
+    fn handle_conflicts_action(&mut self, mut review: ConflictReview, action: Action) -> io::Result<()> {
+        let last = review.conflicts.len().saturating_sub(1);
+        match action {
+            Action::Cancel => {
+                self.status = String::from("Save cancelled; nothing was written");
+                return Ok(());
+            }
+            Action::Up => review.cursor = review.cursor.saturating_sub(1),
+            Action::Down => review.cursor = (review.cursor + 1).min(last),
+            Action::PgUp => review.cursor = review.cursor.saturating_sub(10),
+            Action::PgDown => review.cursor = (review.cursor + 10).min(last),
+            Action::Left => review.conflicts[review.cursor].pick = Side::Mine,
+            Action::Right => review.conflicts[review.cursor].pick = Side::Theirs,
+            Action::Tab | Action::BackTab | Action::ToggleSelect => {
+                let conflict = &mut review.conflicts[review.cursor];
+                conflict.pick = if conflict.pick == Side::Mine { Side::Theirs } else { Side::Mine };
+            }
+            Action::Activate => {
+                return self.save_merged(review.on_disk, review.theirs, &review.conflicts);
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::SaveConflicts(review));
+        Ok(())
+    }
+
+    /// Writes the model to `path`, backing up `on_disk` (the current file content) first.
+    fn write_file(&mut self, path: PathBuf, on_disk: Option<String>) -> io::Result<()> {
+        let backup_path = backup::backup_path(&path, 0);
+        let keep = self.config.backup_keep;
+        let require_backup = self.config.require_backup;
+        let changes = diff::diff_types(&self.baseline, &self.types);
+        let changelog = self.changelog_for_save(&changes);
+        let xml = self.serialize_with(&changelog)?;
+        let notes = self.notes_for_save();
+        let notes_path = annotations::notes_path(&path);
+
+        match &self.source {
+            FileSource::Local => {
+                let mut backup_failure = None;
+                if keep > 0
+                    && let Some(content) = on_disk
+                {
+                    let failure = match backup::rotate(&path, keep, |from, to| fs::rename(from, to)) {
+                        Err(err) => Some(err.to_string()),
+                        Ok(()) => fs::write(&backup_path, content).err().map(|err| backup::describe_failure(&backup_path, &err)),
+                    };
+                    if let Some(failure) = failure {
+                        if require_backup {
+                            self.fail(format!("Save aborted: backup failed: {failure} (require_backup is set)"));
+                            return Ok(());
+                        }
+                        backup_failure = Some(failure);
+                    }
+                }
+                fs::write(&path, &xml)?;
+                self.remember_disk(merge::content_hash(&xml), xml.len() as u64);
+                self.changelog = changelog;
+                self.reset_baseline();
+                let mut note = self.report_after_save(&changes);
+                if let Some(notes) = notes
+                    && let Err(err) = fs::write(&notes_path, notes)
+                {
+                    note.push_str(&format!("; notes sidecar not written: {err}"));
+                }
+                note.push_str(&self.dropped_comments_note());
+                let saved = saved_status(&path.display().to_string(), &changes, self.free_after_save.take());
+                match backup_failure {
+                    Some(failure) => self.fail(format!("{saved}, but backup failed: {failure}{note}")),
+                    None => self.log(format!("{saved}{note}")),
+                }
+            }
+            FileSource::Remote(client) => {
+                let client = client.clone();
+                let rehearse_first = self.config.dry_run_remote.then(|| self.types.clone());
+                let (scratch, normalize) = (self.scratch_dir.clone(), self.config.normalize_names);
+                let upload = Upload {
+                    path: path.clone(),
+                    hash: merge::content_hash(&xml),
+                    size: xml.len() as u64,
+                    changes,
+                    changelog,
+                    close_after: false,
+                };
+                let label = path.display().to_string();
+                let title = format!("Uploading {}", path.file_name().unwrap_or_default().to_string_lossy());
+                let job = progress::spawn(title, move |progress| {
+                    // `dry_run_remote`: the save goes through a local scratch file first.
+                    if let Some(types) = rehearse_first {
+                        let rehearsal = rehearse(&types, &xml, &scratch, normalize)
+                            .map_err(|err| io::Error::new(err.kind(), format!("the dry run failed: {err} (dry_run_remote is set)")))?;
+                        if !rehearsal.passed() {
+                            return Ok(Err(rehearsal));
+                        }
+                    }
+                    let client = client.lock().map_err(|_| io::Error::other("SSH backend in use"))?;
+                    let mut step = |done: u64, total: u64| {
+                        progress.step(done as usize, total as usize, label.clone());
+                    };
+                    // The file on the server becomes the backup by renaming it, so nothing
+                    // is uploaded twice.
+                    let result = if keep > 0 && on_disk.is_some() {
+                        // Backups that couldn't be shifted are left alone rather than
+                        // renaming the file over `.bak`.
+                        match backup::rotate(&path, keep, |from, to| client.replace(from, to)) {
+                            Err(err) if require_backup => Err(io::Error::new(err.kind(), format!("backup failed: {err}"))),
+                            Err(err) => client.write_file_with_progress(&path, &xml, &mut step).map(|()| Some(err.to_string())),
+                            Ok(()) => client
+                                .write_file_with_backup(&path, &xml, &backup_path, require_backup, &mut step)
+                                .map(|failed| failed.map(|err| backup::describe_failure(&backup_path, &err))),
+                        }
+                    } else {
+                        client.write_file_with_progress(&path, &xml, &mut step).map(|()| None)
+                    };
+                    // The sidecar is only bookkeeping; failing to write it doesn't fail the save.
+                    let result = result.map(|backup_failure| {
+                        let notes_failure = notes.and_then(|notes| client.write_file(&notes_path, &notes).err()).map(|e| e.to_string());
+                        Uploaded { backup_failure, notes_failure }
+                    });
+                    // Only offer the reconnect flow when the session itself is gone.
+                    let parent = path.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/"));
+                    result.map(Ok).map_err(|err| match client.check_alive(&parent) {
+                        Ok(()) => err,
+                        Err(_) => io::Error::new(io::ErrorKind::NotConnected, err.to_string()),
+                    })
+                });
+                self.job = Some(EditorJob::Upload(Box::new(upload), job.in_bytes()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Settles a remote save once its upload has finished or failed.
+    fn finish_upload(&mut self, upload: Upload, result: io::Result<Result<Uploaded, Rehearsal>>, elapsed: Duration) {
+        match result {
+            Ok(Err(rehearsal)) => self.report_rehearsal(&rehearsal, "Save aborted (dry_run_remote): "),
+            Ok(Ok(Uploaded { backup_failure, notes_failure })) => {
+                self.remember_disk(upload.hash, upload.size);
+                self.changelog = upload.changelog;
+                self.reset_baseline();
+                let place = format!("remote {}", upload.path.display());
+                let saved = saved_status(&place, &upload.changes, self.free_after_save.take());
+                let mut note = self.report_after_save(&upload.changes);
+                if let Some(err) = notes_failure {
+                    note.push_str(&format!("; notes sidecar not written: {err}"));
+                }
+                note.push_str(&self.dropped_comments_note());
+                let uploaded = progress::transfer_done(upload.size, elapsed);
+                match backup_failure {
+                    Some(failure) => self.fail(format!("{saved}, but backup failed: {failure}; uploaded {uploaded}{note}")),
+                    None => self.log(format!("{saved}; uploaded {uploaded}{note}")),
+                }
+                self.close_requested = upload.close_after;
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotConnected => {
+                self.remote_alive = false;
+                self.overlay = Some(Overlay::RemoteLost);
+                self.status = format!("Remote save failed: {err}");
+            }
+            Err(err) => self.fail(format!("Save failed: {err}")),
+        }
+    }
+
+    /// `; change report: <path>` once the report for a save is written, for the status.
+    fn report_after_save(&self, changes: &TypesDiff) -> String {
+        if !self.config.change_report || changes.is_empty() {
+            return String::new();
+        }
+        match self.write_change_report(changes) {
+            Ok(path) => format!("; change report: {}", path.display()),
+            Err(err) => format!("; change report failed: {err}"),
+        }
+    }
+
+    /// Writes a Markdown report of `changes` next to the file, or to the current
+    /// directory for remote files.
+    fn write_change_report(&self, changes: &TypesDiff) -> io::Result<PathBuf> {
+        let path = self.path.as_ref().ok_or_else(|| io::Error::other("no file loaded"))?;
+        let now = utils::now_unix();
+        let mut target = report::report_path(path, now);
+        if let FileSource::Remote(_) = self.source {
+            target = env::current_dir()?.join(target.file_name().unwrap_or_default());
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        fs::write(&target, report::change_report(&name, changes, &self.types, &self.limit_tiers, now))?;
+        Ok(target)
+    }
+
+    /// The model as saved: in the configured element order, with the file's line endings
+    /// unless `line_endings` forces others.
+    fn serialize(&self) -> io::Result<String> {
+        self.serialize_with(&self.changelog)
+    }
+
+    fn serialize_with(&self, changelog: &[String]) -> io::Result<String> {
+        let xml = model::serialize_document(&self.types, self.config.canonical_order, changelog)?;
+        let xml = self.config.line_endings.resolve(self.line_ending).apply(xml);
+        Ok(if self.config.clean_start { xml } else { format!("{}{xml}", self.leading) })
+    }
+
+    /// The inline changelog a save writes: the file's entries plus one for `changes` when
+    /// `inline_changelog` is on, trimmed to the newest `inline_changelog_keep`. Saves
+    /// without changes write the entries as they are.
+    fn changelog_for_save(&self, changes: &TypesDiff) -> Vec<String> {
+        let mut changelog = self.changelog.clone();
+        if !self.config.inline_changelog || changes.is_empty() {
+            return changelog;
+        }
+        changelog.push(report::changelog_entry(changes, utils::now_unix(), self.config.author.as_deref()));
+        let excess = changelog.len().saturating_sub(self.config.inline_changelog_keep);
+        changelog.drain(..excess);
+        changelog
+    }
+
+    fn save_local_copy(&mut self, path: PathBuf) {
+        let changelog = self.changelog_for_save(&diff::diff_types(&self.baseline, &self.types));
+        let result = self
+            .serialize_with(&changelog)
+            .and_then(|xml| fs::write(&path, &xml).map(|_| (merge::content_hash(&xml), xml.len() as u64)));
+        match result {
+            Ok((hash, bytes)) => {
+                let notes_note = match self.notes_for_save().map(|notes| fs::write(annotations::notes_path(&path), notes)) {
+                    Some(Err(err)) => format!("; notes sidecar not written: {err}"),
+                    _ => String::new(),
+                };
+                self.remember_disk(hash, bytes);
+                self.changelog = changelog;
+                self.reset_baseline();
+                self.log(format!("Saved local copy to {}; now editing the local file{notes_note}", path.display()));
+                self.path = Some(path);
+                self.source = FileSource::Local;
+                self.remote_alive = true;
+                self.liveness_rx = None;
+                self.sample = false;
+            }
+            Err(err) if self.sample => self.fail(format!("Failed to save the sample: {err}")),
+            Err(err) => {
+                self.status = format!("Failed to save local copy: {err}");
+                self.overlay = Some(Overlay::RemoteLost);
+            }
+        }
+    }
+
+    fn add(&mut self) {
+        match self.focus {
+            EditorFocus::TypeList => {
+                let new_type = TypeEntry {
+                    name: String::from("new_type"),
+                    fields: self.config.new_type.clone(),
+                };
+                self.push_undo("add type new_type");
+                self.types.push(new_type);
+                self.selected_type = self.types.len().saturating_sub(1);
+                self.selected_field = 0;
+                self.note_change(self.selected_type);
+                self.reveal_type(self.selected_type);
+                self.revalidate();
+                self.focus = EditorFocus::Editing;
+                self.editing_target = Some(EditTarget::TypeName);
+                self.input_buffer = String::from("new_type");
+                self.undoable(String::from("Added type 'new_type'; enter its name"));
+            }
+            EditorFocus::FieldList => {
+                if self.types.is_empty() {
+                    return;
+                }
+                let marked = self.marked_types();
+                let targets = if marked.is_empty() {
+                    vec![self.selected_type]
+                } else {
+                    marked.into_iter().collect()
+                };
+                self.input_buffer.clear();
+                // Editing focus makes every key arrive as text while the overlay is open.
+                self.focus = EditorFocus::Editing;
+                self.overlay = Some(Overlay::AddElement(PendingAdd {
+                    targets,
+                    step: AddStep::ElementName,
+                    name: String::new(),
+                    text: None,
+                    attrs: Vec::new(),
+                    error: None,
+                }));
+                self.status = String::from("Enter the element name");
+            }
+            EditorFocus::Categories | EditorFocus::Editing => {}
+        }
+    }
+
+    fn handle_add_element_action(&mut self, mut pending: PendingAdd, action: Action) {
+        match action {
+            Action::Cancel => {
+                self.input_buffer.clear();
+                self.focus = EditorFocus::FieldList;
+                self.status = String::from("Add cancelled");
+                return;
+            }
+            _ if self.edit_input(&action) => {}
+            Action::Activate => {
+                let input = std::mem::take(&mut self.input_buffer).trim().to_string();
+                pending.error = None;
+                match pending.step {
+                    AddStep::ElementName if input.is_empty() => {
+                        pending.error = Some(String::from("The element needs a name (Esc cancels)"));
+                    }
+                    AddStep::ElementName | AddStep::AttrName if !input.is_empty() && !model::is_xml_name(&input) => {
+                        pending.error = Some(format!("'{input}' is not a valid XML name"));
+                        self.input_buffer = input;
+                    }
+                    AddStep::ElementName => {
+                        pending.name = input;
+                        pending.step = AddStep::Text;
+                    }
+                    AddStep::Text => {
+                        pending.text = (!input.is_empty()).then_some(input);
+                        pending.step = AddStep::AttrName;
+                    }
+                    AddStep::AttrName if input.is_empty() => {
+                        self.commit_pending_add(pending);
+                        return;
+                    }
+                    AddStep::AttrName if pending.attrs.iter().any(|(attr, _)| *attr == input) => {
+                        pending.error = Some(format!("<{}> already has {input}; each attribute goes on once", pending.name));
+                        self.input_buffer = input;
+                    }
+                    AddStep::AttrName => {
+                        pending.attrs.push((input, String::new()));
+                        pending.step = AddStep::AttrValue;
+                    }
+                    AddStep::AttrValue => {
+                        if let Some(attr) = pending.attrs.last_mut() {
+                            attr.1 = input;
+                        }
+                        pending.step = AddStep::AttrName;
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.overlay = Some(Overlay::AddElement(pending));
+    }
+
+    /// Adds the staged element to every target in one undo step, after any existing
+    /// elements of the same name and with the next free index.
+    fn commit_pending_add(&mut self, pending: PendingAdd) {
+        self.focus = EditorFocus::FieldList;
+        self.push_undo(format!("add {} to {}", pending.preview(), utils::plural(pending.targets.len(), "type")));
+        for &idx in &pending.targets {
+            let Some(ty) = self.types.get_mut(idx) else {
+                continue;
+            };
+            let same = |f: &Field| f.key.get_element_name() == pending.name;
+            let index = ty.fields.iter().filter(|f| same(f)).map(|f| f.key.element_key().1 + 1).max().unwrap_or(0);
+            let position = ty.fields.iter().rposition(same).map(|p| p + 1).unwrap_or(ty.fields.len());
+            let fields = pending.fields(index);
+            if idx == self.selected_type {
+                self.selected_field = position;
+            }
+            ty.fields.splice(position..position, fields);
+            self.note_change(idx);
+            self.revalidate_type(idx);
+        }
+        let targets = self.describe_targets(&pending.targets);
+        self.undoable(format!("Added element {} to {targets}", pending.preview()));
+    }
+
+    fn render_add_element_overlay<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>, pending: &PendingAdd) {
+        let area = utils::centered_rect(60, 40, f.size());
+        let target = match pending.targets.as_slice() {
+            [one] => self.types.get(*one).map(|t| t.name.clone()).unwrap_or_default(),
+            many => format!("{} marked types", many.len()),
+        };
+        let prompt = match pending.step {
+            AddStep::ElementName => String::from("Element name"),
+            AddStep::Text => String::from("Text content (Enter: none)"),
+            AddStep::AttrName => String::from("Attribute name (Enter: finish and add)"),
+            AddStep::AttrValue => format!("Value of {}", pending.attrs.last().map(|a| a.0.as_str()).unwrap_or("")),
+        };
+        let input_width = (area.width as usize).saturating_sub(prompt.width() + 5);
+        let text = format!(
+            "Adding to {target}\n\n{}\n\n{prompt}: {}\n{}\nEsc: discard",
+            pending.preview(),
+            line_edit::view(&self.input_buffer, self.input_cursor, input_width),
+            pending.error.as_deref().unwrap_or("")
+        );
+        let paragraph = Paragraph::new(text)
+            .block(Block::default().title("Add element").borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    /// Why the attribute under the cursor can't be called `name`: another attribute of
+    /// that name on the same element, which XML doesn't allow.
+    fn attribute_clash(&self, name: &str) -> Option<String> {
+        let field = self.current_field()?;
+        let FieldKey::Attribute { element, index, .. } = &field.key else {
+            return None;
+        };
+        let renamed = FieldKey::Attribute { element: element.clone(), index: *index, attr: name.to_string() };
+        let fields = &self.types.get(self.selected_type)?.fields;
+        let taken = fields.iter().enumerate().any(|(i, f)| i != self.selected_field && f.key == renamed);
+        taken.then(|| format!("<{element}> already has {name}; an element can have each attribute once"))
+    }
+
+    fn add_attribute(&mut self) {
+        if self.types.is_empty() {
+            return;
+        }
+        let Some(base_field) = self.current_field() else {
+            return;
+        };
+        let element = base_field.key.get_element_name().to_string();
+        let element_name = element.clone();
+        let index = match &base_field.key {
+            FieldKey::Element { index, .. } => *index,
+            FieldKey::Attribute { index, .. } => *index,
+        };
+        let new_attr_name = "new_attr".to_string();
+        let field = Field {
+            key: FieldKey::Attribute {
+                element,
+                index,
+                attr: new_attr_name.clone(),
+            },
+            value: String::new(),
+        };
+        if self.types[self.selected_type].fields.iter().any(|f| f.key == field.key) {
+            self.fail(format!("<{element_name}> already has a {new_attr_name}; rename that one first"));
+            return;
+        }
+        self.push_undo("add attribute");
+        let message = format!(
+            "Added attribute '{new_attr_name}' to {element_name} in {}; enter its name",
+            self.describe_targets(&[self.selected_type])
+        );
+        if let Some(ty) = self.types.get_mut(self.selected_type) {
+            ty.fields.push(field);
+            self.selected_field = ty.fields.len().saturating_sub(1);
+            self.input_buffer = new_attr_name;
+            self.editing_target = Some(EditTarget::FieldName);
+            self.focus = EditorFocus::Editing;
+            self.undoable(message);
+        }
+    }
+
+    fn copy(&mut self) {
+        match self.focus {
+            EditorFocus::TypeList => {
+                if let Some(current) = self.types.get(self.selected_type).cloned() {
+                    self.push_undo(format!("copy type {}", current.name));
+                    let mut clone = current.clone();
+                    clone.name = format!("{}_copy", clone.name);
+                    let message = format!("Copied type '{}' → '{}'", current.name, clone.name);
+                    self.types.push(clone);
+                    self.selected_type = self.types.len().saturating_sub(1);
+                    self.selected_field = 0;
+                    self.note_change(self.selected_type);
+                    self.revalidate();
+                    self.undoable(message);
+                }
+            }
+            EditorFocus::FieldList => {
+                if let Some(Field { key: FieldKey::Attribute { element, attr, .. }, .. }) = self.current_field() {
+                    let message = format!("<{element}> can have {attr} only once; t adds a different attribute");
+                    self.fail(message);
+                    return;
+                }
+                if let Some(field) = self.current_field() {
+                    self.push_undo(format!("copy field {}", field.key.label()));
+                }
+                let target = self.describe_targets(&[self.selected_type]);
+                if let Some(ty) = self.types.get_mut(self.selected_type)
+                    && let Some(field) = ty.fields.get(self.selected_field).cloned()
+                {
+                    let message = format!("Copied field '{}' in {target}", field.key.label());
+                    ty.fields.push(field);
+                    self.selected_field = ty.fields.len().saturating_sub(1);
+                    self.note_change(self.selected_type);
+                    self.revalidate_type(self.selected_type);
+                    self.undoable(message);
+                }
+            }
+            EditorFocus::Categories | EditorFocus::Editing => {}
+        }
+    }
+
+    /// `C` on the Types pane: asks for the copy's name, starting from the original's.
+    fn begin_duplicate(&mut self) {
+        if self.focus != EditorFocus::TypeList {
+            return;
+        }
+        let Some(source) = self.types.get(self.selected_type) else {
+            return;
+        };
+        self.input_buffer = source.name.clone();
+        self.editing_target = Some(EditTarget::DuplicateName);
+        self.focus = EditorFocus::Editing;
+        self.status = String::from("Enter: add it after the original, Esc: discard");
+    }
+
+    /// Adds the copy right after its original under `name`. An unchanged or taken name is
+    /// refused and the prompt stays open; returns whether it does.
+    fn duplicate_as(&mut self, name: String) -> bool {
+        let Some(source) = self.types.get(self.selected_type).cloned() else {
+            return false;
+        };
+        if name.trim().is_empty() || name == source.name {
+            self.status = String::from("Type a new name for the copy (Esc: discard)");
+            return true;
+        }
+        if self.types.iter().any(|t| t.name == name) {
+            self.status = format!("'{name}' already exists; pick another name (Esc: discard)");
+            return true;
+        }
+        let problem = validation::check_type_name(&name).err();
+        if let Some(problem) = &problem
+            && problem.needs_override()
+            && self.name_override.as_deref() != Some(name.as_str())
+        {
+            self.status = format!("{}; Enter again to keep it anyway", problem.describe(&name));
+            self.name_override = Some(name);
+            return true;
+        }
+        self.name_override = None;
+        self.push_undo(format!("duplicate type {} as {name}", source.name));
+        let idx = self.selected_type + 1;
+        let mut clone = source.clone();
+        clone.name = name;
+        self.types.insert(idx, clone);
+        self.selected_type = idx;
+        self.selected_field = 0;
+        self.note_change(idx);
+        self.revalidate();
+        let mut message = format!("Duplicated type '{}' as '{}'", source.name, self.types[idx].name);
+        if let Some(problem) = problem {
+            message.push_str(&format!(", but {}; DayZ will drop it", problem.describe(&self.types[idx].name)));
+        } else if let Some(note) = self.classnames.as_ref().and_then(|list| list.note(&self.types[idx].name)) {
+            message.push_str(&format!(", but {note}"));
+        }
+        self.undoable(message);
+        false
+    }
+
+    fn delete_type(&mut self) {
+        if let Some(ty) = self.types.get(self.selected_type) {
+            self.push_undo(format!("delete type {}", ty.name));
+            self.selected_types.remove(&self.types, self.selected_type);
+            let removed = self.types.remove(self.selected_type);
+            if !self.types.iter().any(|t| t.name == removed.name) {
+                self.session_changes.remove(&removed.name);
+            }
+            if self.selected_type >= self.types.len() && !self.types.is_empty() {
+                self.selected_type = self.types.len() - 1;
+            } else if self.types.is_empty() {
+                self.selected_type = 0;
+            }
+            self.selected_field = 0;
+            self.clamp_selection_to_view();
+            self.revalidate();
+            self.undoable(format!("Deleted type '{}'", removed.name));
+        }
+    }
+
+    fn delete(&mut self) {
+        match self.focus {
+            EditorFocus::TypeList => {
+                let name = self.types.get(self.selected_type).map(|ty| ty.name.clone());
+                // Another type of the same name keeps the references valid.
+                let warning = name
+                    .filter(|name| self.types.iter().filter(|t| t.name.eq_ignore_ascii_case(name)).count() == 1)
+                    .and_then(|name| self.spawnables()?.deletion_warning(&name));
+                match warning {
+                    Some(warning) => {
+                        self.status = format!("{warning}; delete anyway? (y/n)");
+                        self.overlay = Some(Overlay::ConfirmDelete(warning));
+                    }
+                    None => self.delete_type(),
+                }
+            }
+            EditorFocus::FieldList => {
+                if let Some(field) = self.current_field() {
+                    let type_name = &self.types[self.selected_type].name;
+                    self.push_undo(format!("delete field {} from {type_name}", field.key.label()));
+                }
+                let target = self.describe_targets(&[self.selected_type]);
+                if let Some(key) = self.current_field().map(|f| f.key.clone())
+                    && let Some(ty) = self.types.get_mut(self.selected_type)
+                {
+                    let removed = ty.remove_fields(|k| *k == key);
+                    if self.selected_field >= ty.fields.len() && !ty.fields.is_empty() {
+                        self.selected_field = ty.fields.len() - 1;
+                    } else if ty.fields.is_empty() {
+                        self.selected_field = 0;
+                    }
+                    self.note_change(self.selected_type);
+                    self.revalidate_type(self.selected_type);
+                    let mut message = format!("Deleted field '{}' from {target}", key.label());
+                    if removed > 1 {
+                        message.push_str(&format!(" with the {} on its element", utils::plural(removed - 1, "attribute")));
+                    }
+                    self.undoable(message);
+                }
+            }
+            EditorFocus::Categories | EditorFocus::Editing => {}
+        }
+    }
+
+    /// `theme = "mono"`, `--no-color` or a colourless terminal: cues that are only a colour
+    /// elsewhere get a mark, and the frame is drawn without colours (`utils::Monochrome`).
+    pub fn mono(&self) -> bool {
+        self.config.theme == Theme::Mono
+    }
+
+    fn current_fields(&self) -> Vec<Field> {
+        self.types
+            .get(self.selected_type)
+            .map(|t| t.fields.clone())
+            .unwrap_or_default()
+    }
+
+    fn current_fields_len(&self) -> usize {
+        self.types.get(self.selected_type).map(|t| t.fields.len()).unwrap_or(0)
+    }
+
+    fn current_field(&self) -> Option<&Field> {
+        self.types
+            .get(self.selected_type)
+            .and_then(|t| t.fields.get(self.selected_field))
+    }
+
+    fn current_field_mut(&mut self) -> Option<&mut Field> {
+        self.types
+            .get_mut(self.selected_type)
+            .and_then(|t| t.fields.get_mut(self.selected_field))
+    }
+}
+
+/// Index of the field shown in table column `column`.
+fn table_field(ty: &TypeEntry, column: usize) -> Option<usize> {
+    match TABLE_COLUMNS[column] {
+        "category" => ty.fields.iter().position(|f| {
+            matches!(&f.key, FieldKey::Attribute { element, attr, .. } if element == "category" && attr == "name")
+        }),
+        element => ty.element_field(element),
+    }
+}
+
+fn table_cell(ty: &TypeEntry, column: usize) -> Option<&str> {
+    table_field(ty, column).map(|i| ty.fields[i].value.as_str())
+}
+
+/// Remembered widths are only used if they still add up and respect the minimums.
+fn valid_panes(panes: [u16; 3]) -> bool {
+    panes.iter().sum::<u16>() == 100
+        && panes[0] >= MIN_PANES[0]
+        && panes[1] >= MIN_PANES[1]
+        && (panes[2] == 0 || panes[2] >= MIN_PANES[2])
+}
+
+fn highlight_for(active: bool) -> Style {
+    if active {
+        Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    } else {
+        Style::default()
+    }
+}
+
+/// The `:tutorial` steps in the bottom right corner of `body`, over the panes and any
+/// overlay, so they stay readable while the step's prompts are open.
+fn render_tutorial_panel<B: tui::backend::Backend>(f: &mut tui::Frame<B>, tutorial: &Tutorial, body: Rect) {
+    let width = body.width.min(48);
+    let height = body.height.min(9);
+    let area = Rect::new(body.x + body.width - width, body.y + body.height - height, width, height);
+    let text = format!("{}\n\nEsc closes this panel.", tutorial.instructions());
+    let panel = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title(tutorial.title()).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(panel, area);
+}
+
+fn render_help_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
+    let area = utils::centered_rect(70, 70, f.size());
+    let text = "Editor Help\n\nNew here? :tutorial walks through a sample edit\nNavigation: Up/Down or j/k or PageUp/PageDown to move, Left/Right to switch pane\nEditing: Enter to edit, Esc to cancel, type to change text (Left/Right/Home/End move the cursor, Delete removes the character under it, Ctrl+U clears, Ctrl+W deletes a word), Enter to apply; Up/Down pick a recently used value; . repeats the last field edit on the highlighted type; e on two fields (any types) swaps their values\nActions: a add (type or field), t add field with attribute, c copy, C duplicate type under a new name, d delete, s save, R reload from disk, q close the file (back to the picker), qq or Ctrl+Q quit, ? help (over an overlay, F1 or ? lists its keys)\nView: f show only types changed this session (count shown as (n)), F categories pane with counts (Enter filters the types; Tab: categories, types, fields), i statistics, z hides empty fields (again: flags at their default 0 too; a third time shows all), Ctrl+Left/Right resize panes (:panes reset)\nValidation: v report, n/N jump to next/previous issue, :cost resets unusual costs (marked ¢)\nGo to: G type name (exact, else closest matches; empty: back), or :goto <name>; Ctrl+O/Ctrl+I (or Alt+Left/Alt+Right) back/forward through jumps\nSaving: s save, :dryrun writes to a temporary file and checks it reads back the same\nCalculator: :calc [expression], field names as variables\nHistory: u undo, U redo, :history to browse and jump back, :changedby [author] fields an author last changed (with author set)\nSelection: Space mark/unmark type (while any are marked, a line above the status sums up the highlighted one), Esc clear, P copy chosen values from the highlighted type to the marked ones, :flags compare the marked types' flags (1-6 sets a column to the majority), :lifetime set a lifetime preset\nTools: % nominal distribution and capping (applies to the types in view; q or :cap quant scales quantmin/quantmax too)\nBy name: :addusage/:addtag <value> <pattern>, :setcategory <value> <pattern> (e.g. :addusage Military Ammo*), :stripfield <element|element@attr> removes a field from every (marked) type; bare, each offers its last arguments, and :again repeats the last one (on the marked types when some are marked)\nClassnames: :classnames <file> loads a list of the server's classnames, :fixcase respells type names as it does; type names complete from it";
+    let block = Block::default().title("Help").borders(Borders::ALL);
+    let help = Paragraph::new(text).wrap(Wrap { trim: true }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+fn render_lifetime_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, whom: &str, presets: &[(String, u64)], cursor: usize) {
+    let area = utils::centered_rect(50, 50, f.size());
+    let items: Vec<ListItem> = presets
+        .iter()
+        .map(|(name, seconds)| ListItem::new(format!("{name:<12} {seconds:>9} s")))
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("Lifetime for {whom} (Enter: set, Esc: cancel)"))
+                .borders(Borders::ALL),
+        )
+        .highlight_symbol("▶ ")
+        .highlight_style(highlight_for(true));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_goto_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, query: &str, names: &[&str], cursor: usize) {
+    let area = utils::centered_rect(50, 50, f.size());
+    let items: Vec<ListItem> = names.iter().map(|name| ListItem::new(name.to_string())).collect();
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("No type named '{query}'; closest (Enter: go, Esc: cancel)"))
+                .borders(Borders::ALL),
+        )
+        .highlight_symbol("▶ ")
+        .highlight_style(highlight_for(true));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// The first `<lifetime>`, which the lifetime presets are offered for.
+fn is_lifetime(key: &FieldKey) -> bool {
+    matches!(key, FieldKey::Element { name, index: 0 } if name == "lifetime")
+}
+
+/// A classname list from a local file, named after the file in messages.
+fn read_classnames(path: &Path) -> io::Result<Classnames> {
+    let text = fs::read_to_string(path)?;
+    let source = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    Ok(Classnames::parse(&text, source))
+}
+
+/// `Saved 3 changed types to <place>`, or `Saved <place> (no changes)`, with the free
+/// space left when it is known: `(no changes, 2.0 GB free)`.
+fn saved_status(place: &str, changes: &TypesDiff, free: Option<u64>) -> String {
+    let free = free.map(|free| format!("{} free", sniff::format_size(free)));
+    match (changes.added.len() + changes.removed.len() + changes.changed.len(), free) {
+        (0, Some(free)) => format!("Saved {place} (no changes, {free})"),
+        (0, None) => format!("Saved {place} (no changes)"),
+        (n, Some(free)) => format!("Saved {} to {place} ({free})", utils::plural(n, "changed type")),
+        (n, None) => format!("Saved {} to {place}", utils::plural(n, "changed type")),
+    }
+}
+
+fn render_text_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, title: &str, lines: &[String], scroll: u16) {
+    let area = utils::centered_rect(80, 70, f.size());
+    let block = Block::default()
+        .title(format!("{title} (↑/↓ scroll, Esc: close)"))
+        .borders(Borders::ALL);
+    let text = Paragraph::new(lines.join("\n"))
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+}
+
+fn render_tier_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, tool: &TierTool) {
+    let area = utils::centered_rect(50, 60, f.size());
+    let total = tool.targets.len();
+    let items: Vec<ListItem> = tool
+        .rows
+        .iter()
+        .map(|row| {
+            let mark = match row.set {
+                Some(true) => "x",
+                Some(false) => " ",
+                None if row.have == total => "x",
+                None if row.have == 0 => " ",
+                None => "-",
+            };
+            let count = if total > 1 { format!("  ({}/{total})", row.have) } else { String::new() };
+            ListItem::new(format!("[{mark}] {}{count}", row.name))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(tool.cursor));
+    let title = format!("Tiers for {total} type(s) (Space: toggle, Enter: apply, Esc: cancel)");
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_symbol("▶ ")
+        .highlight_style(highlight_for(true));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_flag_matrix_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, matrix: &FlagMatrix, types: &[TypeEntry]) {
+    const LABELS: [&str; 6] = ["cargo", "hoarder", "map", "player", "crafted", "deloot"];
+    const CELL: usize = 9;
+    let area = utils::centered_rect(80, 80, f.size());
+    let rows: Vec<&TypeEntry> = matrix.targets.iter().map(|&i| &types[i]).collect();
+    let name_width = rows.iter().map(|t| t.name.width()).max().unwrap_or(0).clamp(8, 32);
+    let cell = |ty: &TypeEntry, flag: &str| match flags::flag_of(ty, flag) {
+        Some(v @ ("0" | "1")) => v.to_string(),
+        Some(_) => String::from("?"),
+        None => String::from("·"),
+    };
+    let mixed: Vec<bool> = flags::FLAGS
+        .iter()
+        .map(|flag| rows.windows(2).any(|pair| cell(pair[0], flag) != cell(pair[1], flag)))
+        .collect();
+    let column_style = |col: usize| {
+        let style = if mixed[col] { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
+        if col == matrix.column { style.add_modifier(Modifier::REVERSED) } else { style }
+    };
+
+    let mut header = vec![Span::raw(format!("{:name_width$} ", "Type"))];
+    for (col, label) in LABELS.iter().enumerate() {
+        let mark = if mixed[col] { "≠" } else { " " };
+        header.push(Span::styled(format!("{:^CELL$}", format!("{}{label}{mark}", col + 1)), column_style(col)));
+    }
+    let mut lines = vec![Spans::from(header)];
+    let hint_lines = 4;
+    let visible = usize::from(area.height).saturating_sub(3 + hint_lines + 1);
+    for ty in rows.iter().skip(matrix.scroll).take(visible) {
+        let name = utils::fit_width(&ty.name, name_width);
+        let mut spans = vec![Span::raw(format!("{name}{} ", " ".repeat(name_width.saturating_sub(name.width()))))];
+        for (col, flag) in flags::FLAGS.iter().enumerate() {
+            spans.push(Span::styled(format!("{:^CELL$}", cell(ty, flag)), column_style(col)));
+        }
+        lines.push(Spans::from(spans));
+    }
+
+    let nowhere = rows.iter().filter(|t| flags::counts_nowhere(t)).count();
+    let without = rows.iter().filter(|t| !t.fields.iter().any(|f| f.key.get_element_name() == "flags")).count();
+    let odd = rows.iter().filter(|t| flags::FLAGS.iter().any(|flag| cell(t, flag) == "?")).count();
+    let mut hints = Vec::new();
+    if nowhere > 0 {
+        hints.push(format!("{} count towards no nominal (cargo, hoarder, map and player all 0): nothing caps their spawns", utils::plural(nowhere, "type")));
+    }
+    if without > 0 {
+        hints.push(format!("{} no <flags> (·): the CE applies its own defaults", if without == 1 { String::from("1 type has") } else { format!("{without} types have") }));
+    }
+    if odd > 0 {
+        hints.push(format!("{} flag values other than 0 and 1 (?)", if odd == 1 { String::from("1 type has") } else { format!("{odd} types have") }));
+    }
+    if hints.is_empty() {
+        hints.push(String::from("No flag problems"));
+    }
+    lines.push(Spans::from(""));
+    lines.extend(hints.into_iter().take(hint_lines - 1).map(|hint| Spans::from(Span::styled(hint, Style::default().fg(Color::Yellow)))));
+
+    let title = format!(
+        "Flags of {} ({}-{} shown) | 1-6: set a column to the majority | ←/→ +/-: set to 1/0 | Esc: close",
+        utils::plural(rows.len(), "type"),
+        (matrix.scroll + 1).min(rows.len()),
+        (matrix.scroll + visible).min(rows.len())
+    );
+    let body = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(body, area);
+}
+
+fn render_quantity_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, tool: &QuantityTool) {
+    let area = utils::centered_rect(50, 30, f.size());
+    let input_style = |active: bool| {
+        if tool.unused {
+            Style::default().fg(Color::DarkGray)
+        } else if active {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        }
+    };
+    let check = match tool.check() {
+        Ok(_) => Span::styled("OK", Style::default().fg(Color::Green)),
+        Err(message) => Span::styled(message, Style::default().fg(Color::Red)),
+    };
+    let lines = vec![
+        Spans::from(vec![
+            Span::raw("quantmin: "),
+            Span::styled(format!(" {:>3} ", tool.min_input), input_style(!tool.on_max)),
+            Span::raw("%   quantmax: "),
+            Span::styled(format!(" {:>3} ", tool.max_input), input_style(tool.on_max)),
+            Span::raw("%"),
+        ]),
+        Spans::from(format!("[{}] not used (-1/-1)", if tool.unused { "x" } else { " " })),
+        Spans::from(""),
+        Spans::from(check),
+        Spans::from(""),
+        Spans::from("Tab: switch, u: toggle unused, r: edit raw field, Enter: apply, Esc: cancel"),
+    ];
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Quantity").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// `2 added, 1 removed, 5 changed types` style summary of a diff.
+fn change_summary(diff: &diff::TypesDiff) -> String {
+    if diff.is_empty() {
+        return String::from("no changes");
+    }
+    let mut parts = Vec::new();
+    for (count, what) in [
+        (diff.added.len(), "added"),
+        (diff.removed.len(), "removed"),
+        (diff.changed.len(), "changed"),
+    ] {
+        if count > 0 {
+            parts.push(format!("{count} {what}"));
+        }
+    }
+    format!("{} types", parts.join(", "))
+}
+
+fn render_conflicts_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, review: &ConflictReview) {
+    let area = utils::centered_rect(80, 70, f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(3)].as_ref())
+        .split(area);
+    let show = |value: &Option<String>| value.clone().unwrap_or_else(|| String::from("(deleted)"));
+    let summary = Paragraph::new(format!(
+        "On disk: {}\nHere: {}",
+        review.their_changes, review.my_changes
+    ))
+    .block(Block::default().title("File changed on disk since it was loaded").borders(Borders::ALL));
+    let items: Vec<ListItem> = review
+        .conflicts
+        .iter()
+        .map(|c| {
+            let what = c.key.as_ref().map(|k| k.label()).unwrap_or_else(|| String::from("whole type"));
+            let (mine, theirs) = if c.pick == Side::Mine { ("[x]", "[ ]") } else { ("[ ]", "[x]") };
+            ListItem::new(format!(
+                "{} {}: was {} | {mine} here {} | {theirs} disk {}",
+                c.type_name,
+                what,
+                show(&c.base),
+                show(&c.mine),
+                show(&c.theirs)
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(review.cursor));
+    let title = format!(
+        "{} changed on both sides (←: keep here, →: take disk, Enter: merge and save, Esc: cancel)",
+        review.conflicts.len()
+    );
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_symbol("▶ ")
+        .highlight_style(highlight_for(true));
+    f.render_widget(Clear, area);
+    f.render_widget(summary, chunks[0]);
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+fn render_backups_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, browser: &BackupBrowser) {
+    let area = utils::centered_rect(80, 70, f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(area);
+    let items: Vec<ListItem> = browser
+        .backups
+        .iter()
+        .map(|b| {
+            ListItem::new(format!(
+                "{}  {}  {}",
+                b.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+                b.modified.map(utils::format_age).unwrap_or_else(|| String::from("unknown time")),
+                sniff::format_size(b.size)
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(browser.cursor));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Backups (Enter: preview, o: load into editor, w: restore over the file, Esc: close)")
+                .borders(Borders::ALL),
+        )
+        .highlight_symbol("▶ ")
+        .highlight_style(highlight_for(true));
+    let preview_text = if browser.preview.is_empty() {
+        String::from("Enter shows what restoring the highlighted backup would change.")
+    } else {
+        browser.preview.join("\n")
+    };
+    let preview = Paragraph::new(preview_text)
+        .block(Block::default().title("Backup compared with the file on disk").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, chunks[0], &mut state);
+    f.render_widget(preview, chunks[1]);
+}
+
+fn render_confirm_delete_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, warning: &str) {
+    let area = utils::centered_rect(60, 35, f.size());
+    let text = format!(
+        "{warning}.\n\nThe CE skips entries naming a type types.xml doesn't have; :refs lists them all.\n\ny / Enter: delete anyway\nn / Esc: cancel"
+    );
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Delete a referenced type").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm_close_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
+    let area = utils::centered_rect(50, 25, f.size());
+    let text = "This file has unsaved changes.\n\ny / Enter: close without saving\ns: save, then close\nn / Esc: keep editing";
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Close file").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm_shrink_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, warning: &ShrinkWarning, backups: usize) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let backup = if backups > 0 { "The current file is kept as a .bak." } else { "No backup is kept (backup_keep = 0)." };
+    let text = format!(
+        "Saving would leave far less than the file had:\n\n  {}\n\nDeleting every type or most of the file by accident looks like this; u undoes deletions. {backup}\n\ny / Enter: save anyway\nn / Esc: cancel",
+        warning.describe()
+    );
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Save shrinks the file").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm_low_space_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, warning: &SpaceWarning) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let text = format!(
+        "The disk the file is saved to has {}.\n\nA full disk can make the write fail halfway or leave a truncated file. Free some space first, or save anyway.\n\ny / Enter: save anyway\nn / Esc: cancel",
+        warning.describe()
+    );
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Low disk space").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_reload_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>, changed: bool, unsaved: Option<String>) {
+    let area = utils::centered_rect(60, 35, f.size());
+    let mut text = String::from(if changed {
+        "The file changed on disk since it was loaded or saved.\n\n"
+    } else {
+        "Read the file from disk again.\n\n"
+    });
+    match &unsaved {
+        Some(summary) => {
+            text.push_str(&format!("r / Enter: reload, dropping your unsaved edits ({summary}); u brings them back\n"));
+            text.push_str("s: save instead, merging with the version on disk (clashes are reviewed)\n");
+        }
+        None => text.push_str("r / Enter: reload (u undoes)\n"),
+    }
+    if changed {
+        text.push_str("i: ignore the change and keep editing\n");
+    }
+    text.push_str("Esc: decide later");
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Reload from disk").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm_writable_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
+    let area = utils::centered_rect(50, 25, f.size());
+    let text = "Allow edits and saves to this file again?\n\ny / Enter: leave read-only mode\nn / Esc: stay read-only";
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title("Leave read-only mode").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_remote_lost_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let text = "The SSH session for this file is no longer responding.\nYour changes are only in memory.\n\nr / Enter: reconnect with the same settings and retry the save\ns: save a local copy instead\nEsc: cancel";
+    let block = Block::default().title("Remote connection lost").borders(Borders::ALL);
+    let prompt = Paragraph::new(text)
+        .style(Style::default().fg(Color::Red))
+        .wrap(Wrap { trim: true })
+        .block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{
+        command, drive, editor_for, finish_jobs, load_into, render, render_buffer, render_width, text, Fixture, SAMPLE,
+    };
+
+    fn value(editor: &Editor, type_name: &str, label: &str) -> Option<String> {
+        let ty = editor.types.iter().find(|t| t.name == type_name)?;
+        ty.fields.iter().find(|f| f.key.label() == label).map(|f| f.value.clone())
+    }
+
+    #[test]
+    fn edit_value_and_save_round_trips() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let mut actions = vec![Action::Right, Action::Activate, Action::Backspace];
+        actions.extend(text("25"));
+        actions.extend([Action::Activate, Action::Save]);
+        drive(&mut editor, actions);
+
+        assert_eq!(value(&editor, "AKM", "nominal").as_deref(), Some("25"));
+        assert!(editor.status.starts_with("Saved"), "{}", editor.status);
+        assert_eq!(editor.types, editor.baseline);
+        assert!(fixture.dir.join("types.xml.bak").exists());
+
+        let reloaded = editor_for(&path);
+        assert_eq!(reloaded.types, editor.types);
+        assert!(fixture.read("types.xml.bak").contains("<nominal>8</nominal>"));
+    }
+
+    #[test]
+    fn long_values_are_cut_on_screen_but_saved_whole() {
+        let fixture = Fixture::new();
+        let long = "日本".repeat(1500);
+        let path = fixture.write("types.xml", &SAMPLE.replace("<nominal>8</nominal>", &format!("<nominal>{long}</nominal>")));
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Right]);
+
+        let screen = render(&mut editor);
+        let row = screen.lines().find(|l| l.contains("nominal: 日")).expect(&screen);
+        assert!(row.contains('…'), "{row}");
+        assert_eq!(screen.lines().count(), 30, "nothing wrapped off the screen");
+        assert!(screen.contains("Full value (3000"), "{screen}");
+
+        // Already past the cap: the value can be kept or shortened, not grown.
+        drive(&mut editor, [Action::Activate, Action::Input('x')]);
+        assert!(editor.status.contains("max_value_length"), "{}", editor.status);
+        drive(&mut editor, [Action::Cancel, Action::Down, Action::Activate, Action::Input('0'), Action::Activate, Action::Save]);
+        let saved = fixture.read("types.xml");
+        assert!(saved.contains(&format!("<nominal>{long}</nominal>")));
+        assert!(saved.contains("<lifetime>72000</lifetime>"));
+    }
+
+    #[test]
+    fn typed_input_stops_at_the_configured_length() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        editor.config.max_value_length = 5;
+        drive(&mut editor, [Action::Right, Action::Activate]);
+        drive(&mut editor, text("123456789"));
+
+        assert_eq!(editor.input_buffer, "81234");
+        assert!(editor.status.contains("max_value_length"), "{}", editor.status);
+        let footer = render(&mut editor);
+        assert!(footer.contains("editing (81234▏)"), "{footer}");
+    }
+
+    #[test]
+    fn macro_replays_and_repeated_edits_log_one_entry_each() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::RecordMacro, Action::Right, Action::Activate, Action::ClearLine]);
+        drive(&mut editor, [Action::Input('5'), Action::Activate, Action::Left, Action::RecordMacro, Action::Down]);
+        drive(&mut editor, [Action::Input('2'), Action::ReplayMacro]);
+        assert_eq!(editor.types.iter().map(|t| t.fields[0].value.as_str()).collect::<Vec<_>>(), ["5", "5", "5"]);
+        let lines = editor.messages.lines();
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        assert_eq!(lines[1], "Replayed macro (u undoes all of it) (×2)");
+
+        drive(&mut editor, [Action::Right, Action::Delete, Action::Delete, Action::Delete]);
+        let lines = editor.messages.lines();
+        assert!(lines.last().is_some_and(|l| l.ends_with("(u to undo) (×3)")), "{lines:?}");
+    }
+
+    #[test]
+    fn e_swaps_two_fields_values_across_types_in_one_undo_step() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        let nominals = |editor: &Editor| editor.types.iter().map(|t| t.fields[0].value.clone()).collect::<Vec<_>>();
+        drive(&mut editor, [Action::Right, Action::Input('e')]);
+        assert!(render(&mut editor).contains("exchange: AKM nominal"));
+        drive(&mut editor, [Action::Left, Action::Down, Action::Right, Action::Input('e')]);
+        assert_eq!(nominals(&editor), ["40", "8", "12"]);
+        assert!(editor.status.starts_with("Swapped nominal: AKM 8 ⇄ Apple 40"), "{}", editor.status);
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(nominals(&editor), ["8", "40", "12"], "one undo restores both ends");
+
+        // Another field name asks first; Esc there or while pending changes nothing.
+        drive(&mut editor, [Action::Input('e'), Action::Down, Action::Down, Action::Input('e')]);
+        assert!(matches!(editor.overlay, Some(Overlay::ConfirmExchange(..))));
+        drive(&mut editor, [Action::Cancel]);
+        drive(&mut editor, [Action::Input('e'), Action::Cancel, Action::Up, Action::Input('e')]);
+        assert!(editor.exchange.is_some() && editor.overlay.is_none());
+        drive(&mut editor, [Action::Down, Action::Input('e'), Action::Input('y')]);
+        assert_eq!((editor.types[1].fields[1].value.as_str(), editor.types[1].fields[2].value.as_str()), ("20", "3600"));
+        assert!(editor.status.starts_with("Swapped Apple lifetime 3600 ⇄ Apple min 20"), "{}", editor.status);
+    }
+
+    #[test]
+    fn tips_explain_value_tiers_of_a_detected_mission_map() {
+        let fixture = Fixture::new();
+        let content = SAMPLE.replacen("<category name=\"weapons\" />", "<category name=\"weapons\" />\n    <value name=\"Tier4\" />", 1);
+        let mut editor = editor_for(&fixture.write("dayzOffline.chernarusplus/db/types.xml", &content));
+        assert_eq!(editor.mission_map.as_deref(), Some("chernarus"));
+        let value = editor.types[0].fields.iter().position(|f| tiers::is_value_name(&f.key)).unwrap();
+        drive(&mut editor, std::iter::once(Action::Right).chain(std::iter::repeat_n(Action::Down, value)));
+        assert!(render(&mut editor).contains("Tiers on Chernarus:"));
+
+        let mut plain = editor_for(&fixture.write("loot/types.xml", &content));
+        assert_eq!(plain.mission_map, None);
+        drive(&mut plain, std::iter::once(Action::Right).chain(std::iter::repeat_n(Action::Down, value)));
+        assert!(!render(&mut plain).contains("Tiers on"));
+
+        // Tiers the mission's cfglimitsdefinition.xml declares are listed too, map or not.
+        let limits = "<lists><valueflags><value name=\"Tier4\"/><value name=\"TierPrison\"/></valueflags></lists>";
+        fixture.write("dayzOffline.chernarusplus/cfglimitsdefinition.xml", limits);
+        fixture.write("loot/cfglimitsdefinition.xml", limits);
+        let mut editor = editor_for(&fixture.dir.join("dayzOffline.chernarusplus/db/types.xml"));
+        drive(&mut editor, std::iter::once(Action::Right).chain(std::iter::repeat_n(Action::Down, value)));
+        let screen = render(&mut editor);
+        assert!(screen.contains("Tiers on Chernarus:") && screen.contains("TierPrison (custom)"), "{screen}");
+        let mut plain = editor_for(&fixture.dir.join("loot/types.xml"));
+        drive(&mut plain, std::iter::once(Action::Right).chain(std::iter::repeat_n(Action::Down, value)));
+        let screen = render(&mut plain);
+        assert!(screen.contains("Tiers in") && screen.contains("TierPrison"), "{screen}");
+    }
+
+    #[test]
+    fn lifetime_presets_set_marked_types_in_one_step_and_show_while_editing() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        editor.types[1].fields.retain(|f| f.key.get_element_name() != "lifetime");
+        drive(&mut editor, [Action::ToggleSelect, Action::ToggleSelect]);
+        drive(&mut editor, command("lifetime"));
+        let screen = render(&mut editor);
+        assert!(screen.contains("Lifetime for 2 marked types") && screen.contains("persistent     3888000 s"), "{screen}");
+        drive(&mut editor, [Action::Down, Action::Activate]);
+        assert_eq!(editor.status, "Set lifetime to 14400 (4h) on 2 types; 1 got a new <lifetime> (u to undo)");
+        let lifetime = |editor: &Editor, i: usize| {
+            let ty = &editor.types[i];
+            ty.element_field("lifetime").map(|f| (f, ty.fields[f].value.clone()))
+        };
+        assert_eq!(lifetime(&editor, 0), Some((1, String::from("14400"))));
+        assert_eq!(lifetime(&editor, 1), Some((1, String::from("14400"))), "added after nominal");
+        assert_eq!(lifetime(&editor, 2), Some((1, String::from("7200"))));
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!((lifetime(&editor, 0), lifetime(&editor, 1)), (Some((1, String::from("7200"))), None));
+
+        // Editing a lifetime offers the presets after the values used this session.
+        drive(&mut editor, [Action::Cancel, Action::Up, Action::Up, Action::Right, Action::Down, Action::Activate]);
+        assert!(render(&mut editor).contains("Recent and preset lifetime"));
+        drive(&mut editor, [Action::Down, Action::Down, Action::Activate]);
+        assert_eq!(lifetime(&editor, 0), Some((1, String::from("14400"))));
+    }
+
+    #[test]
+    fn fixcase_respells_type_names_from_the_classname_list_in_one_step() {
+        let fixture = Fixture::new();
+        let list = fixture.write("classnames.txt", "AKM\nApple\nMosin9130\nM4A1\nM4A1_Green\n");
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        editor.types[0].name = String::from("akm");
+        editor.types[2].name = String::from("MOSIN9130");
+        editor.types[1].name = String::from("Aple");
+        drive(&mut editor, command("fixcase"));
+        assert!(editor.status.starts_with("No classname list"));
+        drive(&mut editor, command(&format!("classnames {}", list.display())));
+        assert_eq!(editor.status, "Loaded 5 classnames from classnames.txt; :fixcase respells type names");
+
+        drive(&mut editor, [Action::Down, Action::Down, Action::ToggleSelect]);
+        drive(&mut editor, command("fixcase"));
+        let screen = render(&mut editor);
+        assert!(screen.contains("Enter: rename 2 types") && screen.contains("akm → AKM") && screen.contains("Aple"), "{screen}");
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "Respelled 2 types as in classnames.txt; 1 name not in the list (u to undo)");
+        let names = |editor: &Editor| editor.types.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&editor), ["AKM", "Aple", "Mosin9130"]);
+        assert_eq!(editor.marked_types(), BTreeSet::from([2]), "the mark stays on the respelled type");
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(names(&editor), ["akm", "Aple", "MOSIN9130"]);
+
+        // Renaming completes from the list and says when a name isn't in it.
+        drive(&mut editor, command("goto Aple"));
+        drive(&mut editor, [Action::Activate, Action::ClearLine]);
+        drive(&mut editor, text("m4"));
+        assert!(render(&mut editor).contains("Classnames"));
+        drive(&mut editor, [Action::Down, Action::Down, Action::Activate]);
+        assert_eq!(editor.types[1].name, "M4A1_Green");
+        drive(&mut editor, [Action::Activate, Action::ClearLine]);
+        drive(&mut editor, text("m4a1"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "Type renamed, but classnames.txt spells it 'M4A1' (:fixcase)");
+    }
+
+    #[test]
+    fn stripfield_previews_then_removes_the_field_from_every_type_in_one_step() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, command("stripfield category@name"));
+        assert!(editor.status.contains(":stripfield category"), "{}", editor.status);
+        drive(&mut editor, command("stripfield cost"));
+        assert_eq!(editor.status, "No type has <cost>");
+
+        let has = |editor: &Editor, idx: usize, element: &str| editor.types[idx].fields.iter().any(|f| f.key.get_element_name() == element);
+        drive(&mut editor, command("stripfield category"));
+        let screen = render(&mut editor);
+        assert!(screen.contains("Enter: remove 3 fields from 3 types") && screen.contains("Mosin9130"), "{screen}");
+        drive(&mut editor, [Action::Cancel]);
+        assert!((0..3).all(|idx| has(&editor, idx, "category")));
+
+        // Marked types narrow it.
+        drive(&mut editor, [Action::ToggleSelect]);
+        drive(&mut editor, command("stripfield category"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "Stripped <category> from 1 type (1 field) (u to undo)");
+        assert!(!has(&editor, 0, "category") && has(&editor, 1, "category"));
+        drive(&mut editor, [Action::Undo]);
+        assert!(has(&editor, 0, "category"));
+
+        // The element's attributes go with it.
+        drive(&mut editor, command("stripfield flags"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "Stripped <flags> from 1 type (2 fields) (u to undo)");
+        assert!(!has(&editor, 0, "flags"));
+    }
+
+    #[test]
+    fn an_attribute_can_go_on_an_element_only_once() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Right, Action::Down, Action::Down, Action::Down, Action::Copy]);
+        assert_eq!(editor.status, "<flags> can have count_in_cargo only once; t adds a different attribute");
+        assert_eq!(editor.types[0].fields.len(), 6);
+
+        // Naming a new attribute after one the element has keeps the prompt open.
+        drive(&mut editor, [Action::AddAttribute, Action::ClearLine]);
+        drive(&mut editor, text("count_in_map"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "<flags> already has count_in_map; an element can have each attribute once");
+        assert_eq!(editor.editing_target, Some(EditTarget::FieldName));
+        drive(&mut editor, [Action::ClearLine]);
+        drive(&mut editor, text("deloot"));
+        drive(&mut editor, [Action::Activate, Action::Input('1'), Action::Activate]);
+        assert_eq!(editor.types[0].fields[6].key.label(), "flags @deloot");
+
+        drive(&mut editor, [Action::Add]);
+        drive(&mut editor, text("usage"));
+        drive(&mut editor, [Action::Activate, Action::Activate]);
+        drive(&mut editor, text("name"));
+        drive(&mut editor, [Action::Activate]);
+        drive(&mut editor, text("Town"));
+        drive(&mut editor, [Action::Activate]);
+        drive(&mut editor, text("name"));
+        drive(&mut editor, [Action::Activate]);
+        assert!(render(&mut editor).contains("<usage> already has name"));
+        drive(&mut editor, [Action::Cancel]);
+
+        // One that got in anyway can't be saved; the report removes it, keeping the last.
+        let mut repeat = editor.types[0].fields[4].clone();
+        repeat.value = String::from("0");
+        editor.types[0].fields.push(repeat);
+        editor.revalidate();
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.status.contains("<flags> has count_in_map twice"), "{}", editor.status);
+        drive(&mut editor, [Action::Validate]);
+        assert!(render(&mut editor).contains("d: remove repeated attributes"));
+        drive(&mut editor, [Action::Delete]);
+        assert_eq!(editor.status, "Removed 1 repeated attribute from 1 type (u to undo)");
+        let map: Vec<&str> = editor.types[0].fields.iter().filter(|f| f.key.label() == "flags @count_in_map").map(|f| f.value.as_str()).collect();
+        assert_eq!(map, ["0"]);
+        assert!(!editor.issues.iter().any(|i| i.code == IssueCode::DuplicateAttribute));
+    }
+
+    #[test]
+    fn bulk_arguments_are_offered_again_and_repeated_on_the_marked_types() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, command("again"));
+        assert!(editor.status.starts_with("No bulk command to repeat yet"), "{}", editor.status);
+
+        drive(&mut editor, command("addusage Military A*"));
+        assert!(render(&mut editor).contains("Add usage Military on types matching 'A*':"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "Added usage Military to 2 types (u to undo)");
+
+        // Bare, the command comes back with its arguments; Enter runs them as they were.
+        drive(&mut editor, command("addusage"));
+        assert_eq!((editor.editing_target, editor.input_buffer.as_str()), (Some(EditTarget::Command), "addusage Military A*"));
+        assert_eq!(editor.status, "Last :addusage filled in; Enter runs it again, or edit it first");
+        drive(&mut editor, [Action::ClearLine]);
+        drive(&mut editor, text("addusage Town A*"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.status, "", "an edited line is new arguments");
+        drive(&mut editor, [Action::Cancel, Action::Command]);
+        drive(&mut editor, text("addusage"));
+        drive(&mut editor, [Action::Activate, Action::Activate]);
+        assert_eq!(editor.status, "Using the last :addusage arguments (Town A*)");
+        drive(&mut editor, [Action::Cancel]);
+
+        // :again previews the marked types whatever the pattern said.
+        drive(&mut editor, [Action::Down, Action::Down, Action::ToggleSelect]);
+        drive(&mut editor, command("again"));
+        assert_eq!(editor.status, "Repeating :addusage Town A* on 1 marked type");
+        let screen = render(&mut editor);
+        assert!(screen.contains("Add usage Town on the 1 marked type:") && screen.contains("+ Mosin9130"), "{screen}");
+        drive(&mut editor, [Action::Activate]);
+        assert!(editor.types[2].fields.iter().any(|f| f.key.label() == "usage @name" && f.value == "Town"));
+        assert!(!editor.types[0].fields.iter().any(|f| f.value == "Town"), "the pattern's types were never applied");
+    }
+
+    #[test]
+    fn z_hides_empty_fields_without_moving_edits_to_other_rows() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Add, Action::Activate, Action::Right, Action::Activate, Action::Input('5'), Action::Activate]);
+        drive(&mut editor, [Action::Down, Action::Down, Action::Down, Action::Activate, Action::Input('2'), Action::Activate]);
+        let ty = editor.selected_type;
+        let labels = |editor: &Editor| editor.visible_fields().iter().map(|&i| editor.types[ty].fields[i].key.label()).collect::<Vec<_>>();
+        assert_eq!(labels(&editor).len(), 14);
+        drive(&mut editor, [Action::Up, Action::Up, Action::Up, Action::Input('z')]);
+        assert_eq!(labels(&editor)[..3], ["nominal", "min", "flags @count_in_cargo"]);
+        let screen = render(&mut editor);
+        assert!(screen.contains("Fields (hiding empty)") && screen.contains("(6 empty fields hidden"), "{screen}");
+
+        // Down from nominal lands on min, and the edit goes there.
+        drive(&mut editor, [Action::Down, Action::Activate, Action::ClearLine, Action::Input('3'), Action::Activate]);
+        assert_eq!(editor.types[ty].fields[3].value, "3");
+        assert_eq!(editor.types[ty].fields[1].value, "");
+
+        drive(&mut editor, [Action::Input('z')]);
+        assert_eq!(labels(&editor), ["nominal", "min", "flags @count_in_map"]);
+        assert!(render(&mut editor).contains("(11 empty or default-0 fields hidden"));
+
+        // A field emptied by an edit stays in view while it is selected.
+        drive(&mut editor, [Action::Activate, Action::ClearLine, Action::Activate]);
+        assert_eq!(labels(&editor), ["nominal", "min", "flags @count_in_map"]);
+        drive(&mut editor, [Action::Up]);
+        assert_eq!(labels(&editor), ["nominal", "flags @count_in_map"]);
+        drive(&mut editor, [Action::Input('z')]);
+        assert_eq!(labels(&editor).len(), 14);
+        assert!(!render(&mut editor).contains("fields hidden"));
+    }
+
+    #[test]
+    fn dryrun_reads_the_save_back_without_touching_the_file() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, command("dryrun"));
+        finish_jobs(&mut editor);
+        assert!(editor.status.starts_with("Dry run: ") && editor.status.contains("matching"), "{}", editor.status);
+
+        // Surrounding spaces are trimmed when the file is read again.
+        editor.types[1].fields[0].value = String::from(" 40 ");
+        drive(&mut editor, command("dryrun"));
+        finish_jobs(&mut editor);
+        assert!(editor.status.contains("1 difference when"), "{}", editor.status);
+        let screen = render(&mut editor);
+        assert!(screen.contains("~ Apple: nominal  40  in the editor, 40 read back"), "{screen}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), SAMPLE);
+    }
+
+    #[test]
+    fn apply_with_a_question_mark_asks_the_picker_for_its_directory() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        fixture.write("custom/types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, command("apply AKM nominal =9 ?"));
+        assert_eq!(editor.take_directory_request().as_deref(), Some("the :apply run"));
+        assert_eq!(editor.take_directory_request(), None);
+        assert!(!editor.is_busy());
+        editor.directory_chosen(None);
+        assert_eq!(editor.status, ":apply cancelled; no directory chosen");
+
+        drive(&mut editor, command("apply AKM nominal =9 ?"));
+        editor.directory_chosen(Some(FileSelection { path: fixture.dir.join("custom"), source: FileSource::Local }));
+        finish_jobs(&mut editor);
+        assert_eq!(editor.status, "Dry run of nominal =9 on AKM over 1 files");
+    }
+
+    #[test]
+    fn a_remote_save_whose_dry_run_fails_is_aborted() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let xml = editor.serialize().unwrap();
+        assert!(rehearse(&editor.types, &xml, &fixture.dir, false).unwrap().passed());
+        assert!(rehearse(&editor.types, &xml, &fixture.dir.join("missing"), false).is_err());
+
+        editor.types[1].fields[0].value = String::from(" 40 ");
+        let xml = editor.serialize().unwrap();
+        let rehearsal = rehearse(&editor.types, &xml, &fixture.dir, false).unwrap();
+        assert!(!rehearsal.passed());
+        let upload = Upload {
+            path: path.clone(),
+            hash: merge::content_hash(&xml),
+            size: xml.len() as u64,
+            changes: TypesDiff::default(),
+            changelog: Vec::new(),
+            close_after: true,
+        };
+        editor.finish_upload(upload, Ok(Err(rehearsal)), Duration::ZERO);
+        assert!(editor.status.starts_with("Save aborted (dry_run_remote): Dry run: "), "{}", editor.status);
+        assert!(!editor.close_requested);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), SAMPLE);
+    }
+
+    #[test]
+    fn tutorial_walks_through_an_edit_of_the_sample_and_saves_only_a_copy() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, command("tutorial"));
+        assert_eq!((editor.types.len(), editor.path.as_ref()), (5, None));
+        let screen = render(&mut editor);
+        assert!(screen.contains("Tutorial 1/5") && screen.contains("Tutorial sample: not a file on disk"), "{screen}");
+        let step = |editor: &Editor| editor.tutorial.as_ref().map(Tutorial::step);
+
+        drive(&mut editor, [Action::Down, Action::Down, Action::Down]);
+        assert_eq!(step(&editor), Some(tutorial::Step::EditNominal));
+        drive(&mut editor, [Action::Right, Action::Activate, Action::ClearLine, Action::Input('2'), Action::Input('5'), Action::Activate]);
+        assert_eq!(step(&editor), Some(tutorial::Step::AddUsage));
+        drive(&mut editor, [Action::Add]);
+        for answer in ["usage", "", "name", "Village", ""] {
+            drive(&mut editor, text(answer));
+            drive(&mut editor, [Action::Activate]);
+        }
+        assert_eq!(step(&editor), Some(tutorial::Step::Undo));
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(step(&editor), Some(tutorial::Step::SaveAs));
+
+        let copy = fixture.dir.join("mine.xml");
+        drive(&mut editor, [Action::Save, Action::ClearLine]);
+        drive(&mut editor, text(&copy.display().to_string()));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(step(&editor), Some(tutorial::Step::Done));
+        assert_eq!(editor.path.as_ref(), Some(&copy));
+        assert!(std::fs::read_to_string(&copy).unwrap().contains("<nominal>25</nominal>"));
+        assert_eq!(std::fs::read_to_string(fixture.dir.join("types.xml")).unwrap(), SAMPLE, "the old file is untouched");
+
+        assert!(render(&mut editor).contains("Tutorial: done"));
+        drive(&mut editor, [Action::Cancel]);
+        assert!(editor.tutorial.is_none());
+        assert!(!render(&mut editor).contains("Tutorial: done"));
+    }
+
+    #[test]
+    fn mono_draws_without_colour_and_marks_what_colour_showed() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editors = [editor_for(&path), load_into(Editor::new(ConfigLayer::default(), ConfigLayer::default()), &path)];
+        for editor in &mut editors {
+            drive(editor, [Action::ToggleCategories, Action::Down, Action::Activate]);
+            editor.fail(String::from("Save failed: disk full"));
+        }
+        let [mono, colour] = &mut editors;
+        let screen = render(mono);
+        assert!(screen.contains("Status: ! Save failed: disk full") && screen.contains("> "), "{screen}");
+        let coloured = |buffer: &tui::buffer::Buffer| buffer.content.iter().any(|c| c.fg != Color::Reset || c.bg != Color::Reset);
+        assert!(!coloured(&render_buffer(mono)));
+
+        let screen = render(colour);
+        assert!(screen.contains("Status: Save failed") && !screen.contains("> "), "{screen}");
+        assert!(coloured(&render_buffer(colour)));
+    }
+
+    #[test]
+    fn marks_stay_on_their_types_across_reload_delete_and_duplicate() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Down, Action::ToggleSelect, Action::ToggleSelect]);
+        let marked = |editor: &Editor| editor.marked_types().into_iter().map(|i| editor.types[i].name.clone()).collect::<Vec<_>>();
+        assert_eq!(marked(&editor), ["Apple", "Mosin9130"]);
+
+        // Another tool put a type in front of them and dropped nothing.
+        let pear = SAMPLE.replacen("<type name=\"AKM\">", "<type name=\"Pear\"><nominal>1</nominal></type>\n<type name=\"AKM\">", 1);
+        fixture.write("types.xml", &pear);
+        drive(&mut editor, [Action::Reload, Action::Activate]);
+        assert_eq!(editor.types[0].name, "Pear");
+        assert_eq!(marked(&editor), ["Apple", "Mosin9130"]);
+
+        // Deleting a type before them and duplicating one in between.
+        editor.select_type(0);
+        drive(&mut editor, [Action::Delete, Action::Down, Action::Duplicate]);
+        drive(&mut editor, [Action::ClearLine]);
+        drive(&mut editor, text("Apple2"));
+        drive(&mut editor, [Action::Activate]);
+        assert_eq!(editor.types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["AKM", "Apple", "Apple2", "Mosin9130"]);
+        assert_eq!(marked(&editor), ["Apple", "Mosin9130"]);
+
+        drive(&mut editor, command("flags"));
+        drive(&mut editor, [Action::Right, Action::Right, Action::Input('-')]);
+        let in_map: Vec<Option<&str>> = editor.types.iter().map(|t| flags::flag_of(t, "count_in_map")).collect();
+        assert_eq!(in_map, [Some("1"), Some("0"), None, Some("0")], "only the marked types changed");
+    }
+
+    #[test]
+    fn goto_from_the_command_line_lands_on_the_type_and_field() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let selection = FileSelection { path, source: FileSource::Local };
+
+        editor.open_at(String::from("Apple"), Some(String::from("min")));
+        editor.load(selection.clone(), LoadMode::Fresh).unwrap();
+        assert_eq!((editor.selected_type, editor.focus), (1, EditorFocus::FieldList));
+        assert_eq!(editor.current_field().unwrap().key.label(), "min");
+        assert!(editor.status.starts_with("Loaded 3 types"), "{}", editor.status);
+
+        editor.open_at(String::from("mosin"), Some(String::from("category @name")));
+        editor.load(selection.clone(), LoadMode::Fresh).unwrap();
+        assert_eq!(editor.selected_type, 2);
+        assert_eq!(editor.current_field().unwrap().key.label(), "category @name");
+        assert!(editor.status.contains("opened at 'Mosin9130', the first containing it"), "{}", editor.status);
+
+        editor.open_at(String::from("AKM"), Some(String::from("storage")));
+        editor.load(selection.clone(), LoadMode::Fresh).unwrap();
+        assert_eq!((editor.selected_type, editor.focus), (0, EditorFocus::TypeList));
+        assert_eq!(editor.status, "--goto: 'AKM' has no storage");
+
+        editor.open_at(String::from("Pear"), None);
+        editor.load(selection.clone(), LoadMode::Fresh).unwrap();
+        assert_eq!(editor.status, "--goto: no type matches 'Pear'");
+        editor.load(selection, LoadMode::Fresh).unwrap();
+        assert!(editor.status.starts_with("Loaded"), "only the next load goes to the type");
+    }
+
+    #[test]
+    fn editing_keys_work_at_the_cursor_in_values_and_the_command_line() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Right, Action::Activate, Action::Home, Action::Input('1'), Action::Remove, Action::Input('8'), Action::Left, Action::Left]);
+        assert_eq!(editor.input_buffer, "18");
+        assert!(render(&mut editor).contains("editing (▏18)"));
+        drive(&mut editor, [Action::End, Action::Input('0'), Action::Activate]);
+        assert_eq!(editor.types[0].fields[0].value, "180");
+
+        drive(&mut editor, [Action::Command]);
+        drive(&mut editor, text("goto Mosin"));
+        drive(&mut editor, [Action::DeleteWord, Action::ClearLine]);
+        assert_eq!(editor.input_buffer, "");
+        drive(&mut editor, text("calc 2 + x"));
+        drive(&mut editor, [Action::Home]);
+        assert_eq!(editor.input_cursor, Some(0));
+        drive(&mut editor, [Action::Cancel, Action::Activate]);
+        assert_eq!(editor.input_cursor, None, "a new input starts at the end");
+    }
+
+    #[test]
+    fn typed_markup_is_saved_escaped_and_read_back_as_typed() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let typed = r#"<b> & "c" 'd' 🎁 &amp;"#;
+        drive(&mut editor, [Action::Right, Action::Activate, Action::Backspace]);
+        drive(&mut editor, text(typed));
+        drive(&mut editor, [Action::Activate, Action::Save]);
+        assert_eq!(editor.types[0].fields[0].value, typed);
+
+        let saved = fixture.read("types.xml");
+        assert!(saved.contains("&lt;b&gt; &amp; \"c\" 'd' 🎁 &amp;amp;"), "{saved}");
+        let (reread, _) = parse_types(&saved).unwrap();
+        assert_eq!(reread[0].fields[0].value, typed);
+        assert_eq!(reread, editor.types);
+    }
+
+    #[test]
+    fn failed_backup_is_reported_and_can_abort_the_save() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        // A directory where the backup should go makes writing it fail.
+        fs::create_dir(fixture.dir.join("types.xml.bak")).unwrap();
+        let mut editor = editor_for(&path);
+        editor.set_value(0, 0, "25");
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.status.contains(", but backup failed: "), "{}", editor.status);
+        assert!(editor.status.contains(" on types.xml.bak"), "{}", editor.status);
+        assert!(editor.error_status.is_some());
+        assert!(fixture.read("types.xml").contains("<nominal>25</nominal>"));
+
+        fixture.write(".dayzlooteditor.toml", "[save]\nrequire_backup = true\n");
+        let mut editor = editor_for(&path);
+        editor.set_value(0, 0, "30");
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.status.starts_with("Save aborted: backup failed: "), "{}", editor.status);
+        assert!(fixture.read("types.xml").contains("<nominal>25</nominal>"));
+    }
+
+    #[test]
+    fn backups_that_cant_be_shifted_are_reported_and_kept() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        fixture.write("types.xml.bak", "previous");
+        // A non-empty directory as generation 1 stops `.bak` from moving there.
+        fixture.write("types.xml.bak.1/keep", "");
+        fixture.write(".dayzlooteditor.toml", "backup_keep = 2\n");
+        let mut editor = editor_for(&path);
+        editor.set_value(0, 0, "25");
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.status.contains(", but backup failed: "), "{}", editor.status);
+        assert!(editor.status.contains(" on types.xml.bak"), "{}", editor.status);
+        assert_eq!(fixture.read("types.xml.bak"), "previous");
+        assert!(fixture.read("types.xml").contains("<nominal>25</nominal>"));
+
+        fixture.write(".dayzlooteditor.toml", "backup_keep = 2\n[save]\nrequire_backup = true\n");
+        let mut editor = editor_for(&path);
+        editor.set_value(0, 0, "30");
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.status.starts_with("Save aborted: backup failed: "), "{}", editor.status);
+        assert!(fixture.read("types.xml").contains("<nominal>25</nominal>"));
+    }
+
+    #[test]
+    fn read_only_mode_refuses_editing_commands_and_keys_alike() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        editor.set_value(0, 0, "25");
+        drive(&mut editor, command("readonly"));
+        let types = editor.types.clone();
+        let refused = "Read-only mode: edits and saves are disabled (:readonly to allow them)";
+        for name in MUTATING_COMMANDS {
+            drive(&mut editor, command(name));
+            assert_eq!(editor.status, refused, ":{name}");
+            assert!(!editor.has_overlay(), ":{name}");
+        }
+        editor.set_value(0, 0, "30");
+        assert_eq!(editor.status, refused);
+        drive(&mut editor, [Action::Delete]);
+        assert_eq!(editor.status, refused);
+        assert_eq!(editor.types, types);
+    }
+
+    #[test]
+    fn saves_that_empty_or_gut_the_file_ask_first() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Delete, Action::Delete, Action::Delete, Action::Save]);
+        assert!(editor.asks_about_shrink());
+        assert!(editor.status.contains(", 3 → 0 types"), "{}", editor.status);
+        drive(&mut editor, [Action::Cancel]);
+        assert_eq!(fixture.read("types.xml"), SAMPLE);
+        drive(&mut editor, [Action::Save, Action::Input('y')]);
+        assert!(!fixture.read("types.xml").contains("<type "));
+
+        // Two of three types gone is about 65% of the bytes: fine by default, not at 50.
+        fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Delete, Action::Delete, Action::Save]);
+        assert!(!editor.asks_about_shrink(), "{}", editor.status);
+        fixture.write("types.xml", SAMPLE);
+        fixture.write(".dayzlooteditor.toml", "[save]\nshrink_guard_percent = 50\nempty_guard = false\n");
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Delete, Action::Delete, Action::Save]);
+        assert!(editor.asks_about_shrink(), "{}", editor.status);
+        drive(&mut editor, [Action::Cancel, Action::Delete, Action::Save]);
+        assert!(editor.asks_about_shrink(), "the size still counts with empty_guard off");
+    }
+
+    #[test]
+    fn categories_pane_counts_and_filters_types() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let named = |name: &str| CategoryFilter::Named(name.to_string());
+        drive(&mut editor, [Action::ToggleCategories]);
+        assert_eq!(editor.focus, EditorFocus::Categories);
+        assert_eq!(editor.category_rows(), vec![(named("food"), 1), (named("weapons"), 2)]);
+        let screen = render(&mut editor);
+        assert!(screen.contains("weapons") && screen.contains("All types"), "{screen}");
+
+        drive(&mut editor, [Action::Down, Action::Down, Action::Activate]);
+        assert_eq!(editor.visible_types(), vec![0, 2]);
+        drive(&mut editor, [Action::Tab]);
+        assert_eq!(editor.focus, EditorFocus::TypeList);
+        drive(&mut editor, [Action::Tab]);
+        assert_eq!(editor.focus, EditorFocus::FieldList);
+        drive(&mut editor, [Action::Tab]);
+        assert_eq!(editor.focus, EditorFocus::Categories);
+
+        // Recategorising updates the counts and drops the type from the filtered view.
+        let category = |editor: &Editor, idx: usize| editor.types[idx].fields.iter().position(|f| f.key.label() == "category @name").unwrap();
+        editor.set_value(2, category(&editor, 2), "food");
+        editor.set_value(0, category(&editor, 0), "");
+        assert_eq!(editor.category_rows(), vec![(named("food"), 2), (named("weapons"), 0), (CategoryFilter::Uncategorized, 1)]);
+        assert!(editor.visible_types().is_empty());
+
+        // The `f` filter composes with the category; jumping to a hidden type clears both.
+        drive(&mut editor, [Action::ToggleFilter, Action::Left, Action::Up, Action::Activate]);
+        assert_eq!((editor.type_filter, editor.visible_types()), (Some(TypeFilter::ChangedThisSession), vec![2]));
+        editor.jump_to_type(1);
+        assert_eq!((editor.type_filter, &editor.category_filter), (None, &None));
+        drive(&mut editor, [Action::ToggleCategories]);
+        assert_eq!(editor.focus, EditorFocus::TypeList);
+    }
+
+    #[test]
+    fn saves_onto_a_nearly_full_disk_ask_first() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        editor.free_space = |_, _| Some(2 * 1024 * MIB);
+        drive(&mut editor, [Action::Save]);
+        assert!(!editor.asks_about_space(), "{}", editor.status);
+        assert_eq!(editor.status, format!("Saved {} (no changes, 2.0 GB free)", path.display()));
+
+        fixture.write(".dayzlooteditor.toml", "[save]\nmin_free_mb = 4096\n");
+        fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        editor.free_space = |_, _| Some(2 * 1024 * MIB);
+        drive(&mut editor, [Action::Delete, Action::Save]);
+        assert!(editor.asks_about_space());
+        assert!(editor.status.starts_with("Low disk space: only "), "{}", editor.status);
+        drive(&mut editor, [Action::Cancel]);
+        assert_eq!(fixture.read("types.xml"), SAMPLE);
+        drive(&mut editor, [Action::Save, Action::Input('y')]);
+        assert!(!fixture.read("types.xml").contains("AKM"));
+    }
+
+    #[test]
+    fn inline_changelog_adds_one_comment_per_changing_save() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        fixture.write(".dayzlooteditor.toml", "author = \"Alex\"\ninline_changelog = true\ninline_changelog_keep = 2\n");
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::Delete, Action::Save]);
+        let saved = fixture.read("types.xml");
+        assert_eq!(saved.matches("<!-- DayZLootEditor: ").count(), 1, "{saved}");
+        assert!(saved.contains(" UTC by Alex: removed AKM -->\n</types>"), "{saved}");
+
+        drive(&mut editor, [Action::Save]);
+        assert_eq!(fixture.read("types.xml"), saved, "a save without changes adds no entry");
+
+        let mut editor = editor_for(&path);
+        assert_eq!(editor.changelog.len(), 1);
+        drive(&mut editor, [Action::Copy, Action::Save, Action::Copy, Action::Save]);
+        let saved = fixture.read("types.xml");
+        assert_eq!(saved.matches("<!-- DayZLootEditor: ").count(), 2, "{saved}");
+        assert!(!saved.contains("removed AKM"), "the oldest entry is pruned: {saved}");
+        let (_, report) = parse_types(&saved).unwrap();
+        assert!(report.changelog.iter().all(|entry| entry.contains("by Alex: added ")), "{:?}", report.changelog);
+    }
+
+    #[test]
+    fn saving_types_without_flags_offers_the_defaults_first() {
+        let fixture = Fixture::new();
+        let flagless = SAMPLE.replacen("    <flags count_in_cargo=\"0\" count_in_map=\"1\" />\n", "", 1);
+        let path = fixture.write("types.xml", &flagless);
+        let cli = ConfigLayer { theme: Some(Theme::Mono), ..ConfigLayer::default() };
+        let mut editor = load_into(Editor::new(ConfigLayer::default(), cli), &path);
+        let flags_in_file = |fixture: &Fixture| fixture.read("types.xml").matches("<flags").count();
+
+        drive(&mut editor, [Action::Save]);
+        assert!(editor.asks_about_missing_flags());
+        assert_eq!(editor.status, "3 types without <flags>; add the defaults or save anyway");
+        let screen = render(&mut editor);
+        assert!(screen.contains("3 types have no <flags> element"), "{screen}");
+        assert!(screen.contains("f / Enter: add default flags to all 3 types and save"), "{screen}");
+        drive(&mut editor, [Action::Cancel]);
+        assert_eq!(editor.status, "Save cancelled");
+        assert_eq!(fixture.read("types.xml"), flagless);
+
+        drive(&mut editor, [Action::Save, Action::Input('s')]);
+        assert!(editor.status.starts_with("Saved"), "{}", editor.status);
+        assert_eq!(flags_in_file(&fixture), 0);
+
+        drive(&mut editor, [Action::Save, Action::Input('f')]);
+        assert!(editor.status.starts_with("Saved"), "{}", editor.status);
+        assert_eq!(flags_in_file(&fixture), 3);
+        assert!(validation::missing_flags(&editor.types).is_empty());
+        let added: Vec<&Field> = editor.types[0].fields.iter().filter(|f| f.key.get_element_name() == "flags").collect();
+        assert_eq!(added, model::default_flags().iter().collect::<Vec<_>>());
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(validation::missing_flags(&editor.types), [0, 1, 2], "one undo step takes them all out");
+
+        fixture.write(PROJECT_FILE, "[validation]\nrequire_flags = false\n");
+        let cli = ConfigLayer { theme: Some(Theme::Mono), ..ConfigLayer::default() };
+        let mut editor = load_into(Editor::new(ConfigLayer::default(), cli), &path);
+        drive(&mut editor, [Action::Save]);
+        assert!(!editor.asks_about_missing_flags());
+        assert!(editor.status.starts_with("Saved"), "{}", editor.status);
+    }
+
+    #[test]
+    fn hand_written_comments_are_warned_about_before_a_save_drops_them() {
+        let fixture = Fixture::new();
+        let commented = SAMPLE.replace("<types>", "<types>\n<!-- weapons first -->").replace("</types>", "<!-- keep AKM rare -->\n</types>");
+        let path = fixture.write("types.xml", &commented);
+        fixture.write(".dayzlooteditor.toml", "inline_changelog = true\n");
+        let mut editor = editor_for(&path);
+        assert!(editor.status.contains("; 2 comments saves won't keep"), "{}", editor.status);
+
+        drive(&mut editor, [Action::Delete, Action::Save]);
+        assert!(editor.status.ends_with("; dropped 2 comments (saves keep only inline changelog comments)"), "{}", editor.status);
+        let saved = fixture.read("types.xml");
+        assert!(!saved.contains("AKM rare") && saved.contains("<!-- DayZLootEditor: "), "{saved}");
+
+        drive(&mut editor, [Action::Copy, Action::Save]);
+        assert!(!editor.status.contains("dropped"), "{}", editor.status);
+    }
+
+    #[test]
+    fn bom_and_leading_blank_lines_are_noted_and_dropped_unless_configured() {
+        let fixture = Fixture::new();
+        let content = format!("\u{feff}\n\n{SAMPLE}");
+        let path = fixture.write("types.xml", &content);
+        let mut editor = editor_for(&path);
+        assert!(editor.status.contains("; starts with a BOM and whitespace before the XML, saves remove it"), "{}", editor.status);
+        drive(&mut editor, [Action::Save]);
+        assert!(fixture.read("types.xml").starts_with("<?xml "));
+
+        fixture.write("types.xml", &content);
+        fixture.write(".dayzlooteditor.toml", "[save]\nclean_start = false\n");
+        let mut editor = editor_for(&path);
+        assert!(editor.status.contains("saves keep it (clean_start)"), "{}", editor.status);
+        drive(&mut editor, [Action::Delete, Action::Save]);
+        let saved = fixture.read("types.xml");
+        assert!(saved.starts_with("\u{feff}\n\n<?xml "), "{saved:?}");
+        assert!(!saved.contains("AKM"));
+        let (_, report) = parse_types(&saved).unwrap();
+        assert!(report.has_bom);
+        assert_eq!(report.leading_whitespace, "\n\n");
+    }
+
+    #[test]
+    fn flag_matrix_shows_mismatches_and_sets_columns_in_one_step() {
+        let fixture = Fixture::new();
+        let content = SAMPLE
+            .replacen("<min>20</min>\n", "<min>20</min>\n    <flags count_in_cargo=\"0\" count_in_map=\"1\" />\n", 1)
+            .replacen("<min>6</min>\n", "<min>6</min>\n    <flags count_in_cargo=\"0\" count_in_map=\"0\" />\n", 1);
+        let path = fixture.write("types.xml", &content);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, command("flags"));
+        assert!(editor.overlay.is_none());
+        assert!(editor.status.starts_with("Mark types with Space first"), "{}", editor.status);
+
+        drive(&mut editor, [Action::ToggleSelect, Action::ToggleSelect, Action::ToggleSelect]);
+        drive(&mut editor, command("flags"));
+        let screen = render(&mut editor);
+        assert!(screen.contains("3map≠"), "{screen}");
+        assert!(!screen.contains("1cargo≠"), "{screen}");
+
+        drive(&mut editor, [Action::Input('3')]);
+        assert_eq!(editor.status, "Set count_in_map=1 on 'Mosin9130' (u to undo)");
+        drive(&mut editor, [Action::Input('3')]);
+        assert_eq!(editor.status, "All 3 types already have count_in_map=1");
+        drive(&mut editor, [Action::Left, Action::Left, Action::Input('-'), Action::Right, Action::Right, Action::Input('-')]);
+        assert_eq!(editor.status, "Set count_in_map=0 on 3 types (u to undo)");
+        let screen = render(&mut editor);
+        assert!(screen.contains("3 types count towards no nominal"), "{screen}");
+
+        drive(&mut editor, [Action::Right, Action::Input('+'), Action::Cancel, Action::Undo]);
+        assert!(editor.types.iter().all(|t| flags::flag_of(t, "count_in_player").is_none()));
+        assert_eq!(flags::flag_of(&editor.types[2], "count_in_map"), Some("0"));
+    }
+
+    #[test]
+    fn flag_matrix_creates_missing_flags_unless_switched_off() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::ToggleSelect, Action::ToggleSelect]);
+        drive(&mut editor, command("flags"));
+        drive(&mut editor, std::iter::repeat_n(Action::Right, 5).chain([Action::Input('+')]));
+        assert_eq!(editor.status, "Set deloot=1 on 2 types, created flags on 1 (u to undo)");
+        assert_eq!(flags::flag_of(&editor.types[0], "count_in_map"), Some("1"));
+
+        fixture.write(".dayzlooteditor.toml", "create_missing_flags = false\n");
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::ToggleSelect, Action::ToggleSelect]);
+        drive(&mut editor, command("flags"));
+        drive(&mut editor, std::iter::repeat_n(Action::Right, 5).chain([Action::Input('+')]));
+        assert_eq!(
+            editor.status,
+            "Set deloot=1 on 'AKM'; 1 type without <flags> skipped (create_missing_flags is off) (u to undo)"
+        );
+        assert!(flags::flag_of(&editor.types[1], "deloot").is_none());
+    }
+
+    #[test]
+    fn crlf_files_keep_their_line_endings_on_save() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", &SAMPLE.replace('\n', "\r\n"));
+        let mut editor = editor_for(&path);
+        assert!(editor.status.contains("CRLF"), "{}", editor.status);
+        drive(&mut editor, [Action::Save]);
+
+        let saved = fixture.read("types.xml");
+        assert!(saved.contains("\r\n"));
+        assert_eq!(saved.matches('\n').count(), saved.matches("\r\n").count(), "{saved:?}");
+        let (reread, report) = parse_types(&saved).unwrap();
+        assert_eq!(reread, editor.types);
+        assert_eq!(report.line_ending, LineEnding::Crlf);
+        assert!(!report.mixed_line_endings);
+    }
+
+    #[test]
+    fn line_endings_setting_overrides_the_file() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", &SAMPLE.replace('\n', "\r\n"));
+        let cli = ConfigLayer::parse("[save]\nline_endings = \"lf\"\n[validation]\nrequire_flags = false\n").unwrap().0;
+        let mut editor = Editor::new(ConfigLayer::default(), cli);
+        editor.load(FileSelection { path: path.clone(), source: FileSource::Local }, LoadMode::Fresh).unwrap();
+        assert!(editor.status.contains("saves convert to LF"), "{}", editor.status);
+        drive(&mut editor, [Action::Save]);
+        assert!(!fixture.read("types.xml").contains('\r'));
+    }
+
+    #[test]
+    fn added_type_is_named_from_the_prompt() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Add]);
+        assert!(editor.is_editing());
+
+        let mut actions = vec![Action::Backspace; "new_type".len()];
+        actions.extend(text("Pear"));
+        actions.push(Action::Activate);
+        drive(&mut editor, actions);
+
+        assert_eq!(editor.types.len(), 4);
+        assert_eq!(editor.types[editor.selected_type].name, "Pear");
+        assert_eq!(editor.focus, EditorFocus::TypeList);
+        assert!(!editor.is_editing());
+    }
+
+    #[test]
+    fn deleting_a_type_the_spawnable_types_name_asks_first() {
+        let fixture = Fixture::new();
+        let path = fixture.write("db/types.xml", SAMPLE);
+        let spawnable = r#"<spawnabletypes><type name="AKM"><cargo chance="0.2"><item name="Apple"/><item name="Banana"/></cargo></type></spawnabletypes>"#;
+        fixture.write("cfgspawnabletypes.xml", spawnable);
+        let mut editor = editor_for(&path);
+
+        drive(&mut editor, [Action::Down, Action::Delete]);
+        assert_eq!(editor.status, "Apple is referenced by 1 cargo entry in cfgspawnabletypes.xml; delete anyway? (y/n)");
+        drive(&mut editor, [Action::Cancel]);
+        assert_eq!(editor.types.len(), 3);
+        drive(&mut editor, [Action::Delete, Action::Input('y')]);
+        assert_eq!(editor.status, "Deleted type 'Apple' (u to undo)");
+
+        drive(&mut editor, command("refs"));
+        let Some(Overlay::Text { lines, .. }) = &editor.overlay else {
+            panic!("expected the :refs report");
+        };
+        assert!(lines.contains(&String::from("  Apple (cargo of AKM)")), "{lines:?}");
+        assert!(lines.contains(&String::from("  Banana (cargo of AKM)")), "{lines:?}");
+        drive(&mut editor, [Action::Cancel, Action::Undo]);
+
+        // A changed file is read again on its next use, without a reload.
+        fixture.write("cfgspawnabletypes.xml", "<spawnabletypes />");
+        editor.select_type(1);
+        drive(&mut editor, [Action::Delete]);
+        assert_eq!(editor.status, "Deleted type 'Apple' (u to undo)");
+    }
+
+    #[test]
+    fn skeleton_command_makes_the_highlighted_type_the_template() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        let config_path = fixture.dir.join("config.toml");
+        drive(&mut editor, [Action::Down]);
+        editor.save_skeleton(&config_path, false);
+        assert!(editor.status.starts_with("New types start with the 4 fields of Apple"), "{}", editor.status);
+        drive(&mut editor, [Action::Add, Action::Activate]);
+        let added = &editor.types[3];
+        assert_eq!(added.fields, editor.types[1].fields);
+
+        let (written, unknown) = ConfigLayer::parse(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert!(unknown.is_empty(), "{unknown:?}");
+        assert_eq!(written.new_type.values.get("nominal").map(String::as_str), Some("40"));
+        editor.save_skeleton(&config_path, true);
+        assert_eq!(editor.config.new_type, model::default_fields());
+
+        // Over a hand-written file only `[new_type]` changes.
+        let hand_written = "# mine\nbackup_keep = 3 # plenty\ncolour = \"red\"\n";
+        fixture.write("config.toml", hand_written);
+        editor.save_skeleton(&config_path, false);
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert!(content.starts_with(hand_written) && content.contains("\n[new_type]\n"), "{content}");
+        assert_eq!(editor.config.backup_keep, 3);
+    }
+
+    #[test]
+    fn copy_and_delete_keep_the_selection_in_range() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Down, Action::Down, Action::Copy]);
+        assert_eq!(editor.types[editor.selected_type].name, "Mosin9130_copy");
+
+        drive(&mut editor, [Action::Delete, Action::Delete]);
+        assert_eq!(editor.types.len(), 2);
+        assert_eq!(editor.types[editor.selected_type].name, "Apple");
+
+        // Deleting the last field moves the selection onto the new last one.
+        drive(&mut editor, [Action::Right, Action::PgDown, Action::Delete]);
+        let fields = editor.types[editor.selected_type].fields.len();
+        assert_eq!(fields, 3);
+        assert_eq!(editor.selected_field, fields - 1);
+
+        drive(&mut editor, [Action::Left, Action::Delete, Action::Delete, Action::Delete]);
+        assert!(editor.types.is_empty());
+        assert_eq!(editor.selected_type, 0);
+    }
+
+    #[test]
+    fn unusual_costs_are_reported_marked_and_reset_by_cost() {
+        let fixture = Fixture::new();
+        let content = SAMPLE
+            .replacen("<min>4</min>", "<min>4</min>\n    <cost>90</cost>", 1)
+            .replacen("<min>20</min>", "<min>20</min>\n    <cost>50</cost>", 1)
+            .replacen("<min>6</min>", "<min>6</min>\n    <cost>100</cost>", 1);
+        let path = fixture.write("types.xml", &content);
+        let mut editor = editor_for(&path);
+        let costs = |editor: &Editor| ["AKM", "Apple", "Mosin9130"].map(|name| value(editor, name, "cost").unwrap());
+        let cost_issues = |editor: &Editor| editor.issues.iter().filter(|i| i.code == IssueCode::UnusualCost).map(|i| i.message.clone()).collect::<Vec<_>>();
+        assert_eq!(cost_issues(&editor), ["cost is 90, not the usual 100 (:cost resets it)", "cost is 50, not the usual 100 (:cost resets it)"]);
+        let screen = render(&mut editor);
+        assert!(screen.contains("AKM ¢") && screen.contains("Apple ¢") && !screen.contains("Mosin9130 ¢"), "{screen}");
+
+        drive(&mut editor, command("cost"));
+        assert_eq!(editor.status, "Set cost to 100 on 2 types (u to undo)");
+        assert_eq!(costs(&editor), ["100", "100", "100"]);
+        assert!(cost_issues(&editor).is_empty());
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(costs(&editor), ["90", "50", "100"]);
+
+        // Marked types narrow it.
+        drive(&mut editor, [Action::Down, Action::ToggleSelect]);
+        drive(&mut editor, command("cost"));
+        assert_eq!(editor.status, "Set cost to 100 on 1 type (u to undo)");
+        assert_eq!(costs(&editor), ["90", "100", "100"]);
+        drive(&mut editor, command("cost"));
+        assert_eq!(editor.status, "Every cost is already 100");
+
+        // A project can expect another cost, or not check at all.
+        fixture.write(PROJECT_FILE, "[validation]\nexpected_cost = 50\n");
+        let editor = editor_for(&path);
+        assert_eq!(cost_issues(&editor), ["cost is 90, not the usual 50 (:cost resets it)", "cost is 100, not the usual 50 (:cost resets it)"]);
+        fixture.write(PROJECT_FILE, "[validation]\ncheck_cost = false\n");
+        let mut editor = editor_for(&path);
+        assert!(cost_issues(&editor).is_empty());
+        assert!(!render(&mut editor).contains('¢'));
+    }
+
+    #[test]
+    fn added_element_goes_to_every_marked_type_in_one_undo_step() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        let mut actions = vec![Action::ToggleSelect, Action::Down, Action::ToggleSelect, Action::Right, Action::Add];
+        actions.extend(text("cost"));
+        actions.push(Action::Activate);
+        actions.extend(text("100"));
+        actions.extend([Action::Activate, Action::Activate]);
+        drive(&mut editor, actions);
+
+        assert_eq!(value(&editor, "AKM", "cost").as_deref(), Some("100"));
+        assert_eq!(value(&editor, "Mosin9130", "cost").as_deref(), Some("100"));
+        assert_eq!(value(&editor, "Apple", "cost"), None);
+
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(editor.types, editor.baseline);
+    }
+
+    #[test]
+    fn edit_statuses_name_the_object_count_and_undo() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        editor.free_space = |_, _| Some(2 * 1024 * MIB);
+
+        drive(&mut editor, [Action::Copy]);
+        assert_eq!(editor.status, "Copied type 'AKM' → 'AKM_copy' (u to undo)");
+        drive(&mut editor, [Action::Delete]);
+        assert_eq!(editor.status, "Deleted type 'AKM_copy' (u to undo)");
+
+        drive(&mut editor, [Action::Up, Action::Up, Action::Right, Action::Down, Action::Delete]);
+        assert_eq!(editor.status, "Deleted field 'lifetime' from 'AKM' (u to undo)");
+
+        let mut actions = vec![Action::Left, Action::ToggleSelect, Action::Down, Action::ToggleSelect, Action::Right, Action::Add];
+        actions.extend(text("cost"));
+        actions.extend([Action::Activate, Action::Activate, Action::Activate]);
+        drive(&mut editor, actions);
+        assert_eq!(editor.status, "Added element <cost /> to 2 types (u to undo)");
+
+        drive(&mut editor, [Action::Save]);
+        assert_eq!(editor.status, format!("Saved 2 changed types to {} (2.0 GB free)", path.display()));
+        drive(&mut editor, [Action::Save]);
+        assert_eq!(editor.status, format!("Saved {} (no changes, 2.0 GB free)", path.display()));
+
+        assert!(editor.messages.lines().contains(&String::from("Deleted field 'lifetime' from 'AKM' (u to undo)")));
+    }
+
+    #[test]
+    fn dot_repeats_the_last_field_edit_on_the_highlighted_type() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        drive(&mut editor, [Action::RepeatEdit]);
+        assert!(editor.status.starts_with("Nothing to repeat"), "{}", editor.status);
+
+        editor.set_value(0, 1, "14400");
+        drive(&mut editor, [Action::Left, Action::Down, Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "lifetime").as_deref(), Some("14400"));
+        assert_eq!(editor.status, "lifetime = 14400 applied to Apple (u to undo)");
+        drive(&mut editor, [Action::RepeatEdit]);
+        assert_eq!(editor.status, "Apple already has lifetime = 14400; nothing to repeat");
+
+        // A type without the field gets it.
+        editor.types[2].fields.retain(|f| f.key.label() != "lifetime");
+        drive(&mut editor, [Action::Down, Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Mosin9130", "lifetime").as_deref(), Some("14400"));
+
+        drive(&mut editor, [Action::Undo]);
+        assert_eq!(value(&editor, "Mosin9130", "lifetime"), None);
+        assert_eq!(value(&editor, "Apple", "lifetime").as_deref(), Some("14400"));
+    }
+
+    #[test]
+    fn a_value_starting_with_equals_is_computed() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        editor.set_value(0, 0, "=nominal * 2 + min");
+        assert_eq!(value(&editor, "AKM", "nominal").as_deref(), Some("20"));
+
+        editor.set_value(0, 0, "=cost / 2");
+        assert_eq!(value(&editor, "AKM", "nominal").as_deref(), Some("20"));
+        assert!(editor.status.contains("fix the expression"), "{}", editor.status);
+    }
+
+    #[test]
+    fn dot_repeats_an_expression_presets_flags_and_swaps() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        editor.set_value(0, 0, "=nominal * 2");
+        drive(&mut editor, [Action::Left, Action::Down, Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "nominal").as_deref(), Some("80"));
+        assert_eq!(editor.status, "nominal = nominal * 2 applied to Apple (u to undo)");
+
+        editor.apply_lifetime_preset(&[0], 1);
+        drive(&mut editor, [Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "lifetime").as_deref(), Some("14400"));
+
+        editor.set_flag_column(&FlagMatrix { targets: vec![0], column: 5, scroll: 0 }, Some(true));
+        drive(&mut editor, [Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "flags @deloot").as_deref(), Some("1"));
+
+        let end = |type_idx: usize, type_name: &str, label: &str| {
+            let key = editor.types[type_idx].fields.iter().find(|f| f.key.label() == label).unwrap().key.clone();
+            ExchangeEnd { type_idx, type_name: type_name.to_string(), key }
+        };
+        let (a, b) = (end(0, "AKM", "nominal"), end(0, "AKM", "min"));
+        editor.swap_values(&a, &b);
+        drive(&mut editor, [Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "nominal").as_deref(), Some("20"));
+        assert_eq!(value(&editor, "Apple", "min").as_deref(), Some("80"));
+        assert_eq!(editor.status, "nominal ⇄ min applied to Apple (u to undo)");
+    }
+
+    #[test]
+    fn quantity_tool_edits_the_highlighted_pair() {
+        let fixture = Fixture::new();
+        let quantities = "<quantmin>10</quantmin>\n    <quantmax>20</quantmax>\n    <quantmin>30</quantmin>\n    <quantmax>40</quantmax>\n    <nominal>8</nominal>";
+        let mut editor = editor_for(&fixture.write("types.xml", &SAMPLE.replacen("<nominal>8</nominal>", quantities, 1)));
+        drive(&mut editor, [Action::Right, Action::Down, Action::Down, Action::Activate]);
+        let Some(Overlay::Quantity(tool)) = &editor.overlay else {
+            panic!("no quantity tool");
+        };
+        assert_eq!((tool.min_field, tool.max_field, tool.on_max), (2, 3, false));
+        assert_eq!((tool.min_input.as_str(), tool.max_input.as_str()), ("30", "40"));
+
+        drive(&mut editor, [Action::Cancel, Action::Down, Action::Activate]);
+        let Some(Overlay::Quantity(tool)) = &editor.overlay else {
+            panic!("no quantity tool");
+        };
+        assert_eq!((tool.min_field, tool.max_field, tool.on_max), (2, 3, true));
+    }
+
+    #[test]
+    fn jumps_go_back_and_forward_and_skip_deleted_types() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, command("goto Mosin9130"));
+        drive(&mut editor, command("goto Apple"));
+        let name = |editor: &Editor| editor.types[editor.selected_type].name.clone();
+
+        drive(&mut editor, [Action::JumpBack]);
+        assert_eq!((name(&editor), editor.status.as_str()), (String::from("Mosin9130"), "Mosin9130 (jump 2/3)"));
+        drive(&mut editor, [Action::JumpBack]);
+        assert_eq!(name(&editor), "AKM");
+        drive(&mut editor, [Action::JumpBack]);
+        assert_eq!(editor.status, "No earlier jump");
+        drive(&mut editor, [Action::JumpForward, Action::JumpForward]);
+        assert_eq!((name(&editor), editor.status.as_str()), (String::from("Apple"), "Apple (jump 3/3)"));
+        drive(&mut editor, [Action::JumpForward]);
+        assert_eq!(editor.status, "No later jump");
+
+        editor.types.remove(2);
+        drive(&mut editor, [Action::JumpBack]);
+        assert_eq!(name(&editor), "AKM");
+        assert_eq!(editor.status, "AKM (jump 1/3); skipped 1 jump to deleted types");
+    }
+
+    #[test]
+    fn jump_keys_reach_the_jump_list_whatever_the_terminal_sends() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let key = |code, modifiers| crate::map_key_to_action(KeyEvent::new(code, modifiers), false, false, false);
+        assert_eq!(key(KeyCode::Char('o'), KeyModifiers::CONTROL), Action::JumpBack);
+        // With the kitty keyboard protocol Ctrl+I arrives as itself; elsewhere it is Tab.
+        assert_eq!(key(KeyCode::Char('i'), KeyModifiers::CONTROL), Action::JumpForward);
+        assert_eq!(key(KeyCode::Tab, KeyModifiers::NONE), Action::Tab);
+        assert_eq!(key(KeyCode::Left, KeyModifiers::ALT), Action::JumpBack);
+        assert_eq!(key(KeyCode::Right, KeyModifiers::ALT), Action::JumpForward);
+        let editing = crate::map_key_to_action(KeyEvent::new(KeyCode::Right, KeyModifiers::ALT), true, false, false);
+        assert_ne!(editing, Action::JumpForward);
+    }
+
+    #[test]
+    fn a_file_that_does_not_parse_leaves_the_open_one_and_its_config_alone() {
+        let fixture = Fixture::new();
+        let open = fixture.write("a/types.xml", SAMPLE);
+        fixture.write("a/.dayzlooteditor.toml", "author = \"Alex\"\n");
+        let broken = fixture.write("b/types.xml", "<types><type name=\"Torn\">");
+        fixture.write("b/.dayzlooteditor.toml", "readonly = true\nauthor = \"Sam\"\n");
+        fixture.write("b/cfglimitsdefinition.xml", "<lists><valueflags><value name=\"TierPrison\"/></valueflags></lists>");
+        let mut editor = editor_for(&open);
+
+        let selection = FileSelection { path: broken, source: FileSource::Local };
+        let err = editor.load(selection, LoadMode::Fresh).unwrap_err();
+        assert!(err.to_string().starts_with("XML parse error"), "{err}");
+        assert_eq!(editor.path.as_deref(), Some(open.as_path()));
+        assert_eq!(editor.types.len(), 3);
+        assert_eq!(editor.config.author.as_deref(), Some("Alex"));
+        assert_eq!(editor.project_config_path, Some(fixture.dir.join("a/.dayzlooteditor.toml")));
+        assert!(!editor.readonly && editor.limit_tiers.is_empty());
+    }
+
+    #[test]
+    fn a_change_on_disk_raises_the_banner_and_the_reload_prompt() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        let poll = |editor: &mut Editor| {
+            editor.last_watch_check = Instant::now() - WATCH_INTERVAL;
+            editor.tick().unwrap();
+        };
+        // Written again with the same content: nothing to report.
+        fixture.write("types.xml", SAMPLE);
+        poll(&mut editor);
+        assert!(!editor.disk_changed);
+
+        fixture.write("types.xml", &SAMPLE.replacen("<nominal>8</nominal>", "<nominal>80</nominal>", 1));
+        poll(&mut editor);
+        assert!(editor.disk_changed);
+        assert_eq!(editor.status, "types.xml changed on disk; R reloads it or dismisses this");
+        assert!(render(&mut editor).contains("CHANGED ON DISK — R to reload or ignore"));
+
+        drive(&mut editor, [Action::Reload]);
+        let screen = render(&mut editor);
+        assert!(screen.contains("The file changed on disk since it was loaded or saved."), "{screen}");
+        assert!(screen.contains("i: ignore the change and keep editing"), "{screen}");
+        drive(&mut editor, [Action::Input('r')]);
+        assert_eq!(value(&editor, "AKM", "nominal").as_deref(), Some("80"));
+        assert!(!editor.disk_changed);
+        assert!(!render(&mut editor).contains("CHANGED ON DISK"));
+    }
+
+    #[test]
+    fn dot_repeats_a_bulk_edit_on_one_type() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut editor = editor_for(&path);
+        editor.last_edit = Some(LastEdit::Bulk(BulkEdit::parse(&["*", "nominal", "*2"]).unwrap()));
+        drive(&mut editor, [Action::Down, Action::RepeatEdit]);
+        assert_eq!(value(&editor, "Apple", "nominal").as_deref(), Some("80"));
+        assert_eq!(value(&editor, "AKM", "nominal").as_deref(), Some("8"));
+        assert_eq!(editor.status, "nominal *2 applied to Apple (u to undo)");
+    }
+
+    #[test]
+    fn undo_and_redo_retrace_the_same_states() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        let mut states = vec![editor.types.clone()];
+        let mut rename = vec![Action::Activate];
+        rename.extend(text("_Black"));
+        rename.push(Action::Activate);
+        for step in [rename, vec![Action::Copy], vec![Action::Down, Action::Delete], command("dedupe")] {
+            drive(&mut editor, step);
+            if editor.types != *states.last().unwrap() {
+                states.push(editor.types.clone());
+            }
+        }
+        assert_eq!(states.len(), 4);
+        // Only the newest entry keeps the whole model; older ones the one type they touched.
+        let kept: Vec<_> = editor
+            .undo_stack
+            .iter()
+            .map(|entry| match &entry.change {
+                Change::Snapshot(types) => (true, types.len()),
+                Change::Splice(splice) => (false, splice.types.len()),
+            })
+            .collect();
+        assert_eq!(kept, [(false, 1), (false, 0), (true, 4)]);
+
+        for expected in states.iter().rev().skip(1) {
+            drive(&mut editor, [Action::Undo]);
+            assert_eq!(&editor.types, expected);
+        }
+        for expected in states.iter().skip(1) {
+            drive(&mut editor, [Action::Redo]);
+            assert_eq!(&editor.types, expected);
+        }
+    }
+
+    #[test]
+    fn a_splice_turns_one_model_into_the_other_and_back() {
+        let (types, _) = parse_types(SAMPLE).unwrap();
+        let mut edited = types.clone();
+        edited.remove(1);
+        edited[0].name = String::from("AKM_Black");
+        edited.push(edited[0].clone());
+
+        let splice = Splice::between(&edited, &types);
+        assert_eq!((splice.start, splice.len, splice.types.len()), (0, 3, 3));
+        let mut model = edited.clone();
+        let back = splice.apply(&mut model);
+        assert_eq!(model, types);
+        back.apply(&mut model);
+        assert_eq!(model, edited);
+
+        let same = Splice::between(&types, &types);
+        assert_eq!((same.len, same.types.len()), (0, 0));
+        let mut tail = types.clone();
+        tail.pop();
+        let splice = Splice::between(&tail, &types);
+        assert_eq!((splice.start, splice.len, splice.types.len()), (2, 0, 1));
+    }
+
+    #[test]
+    fn empty_file_survives_every_pane_action() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", "<types></types>"));
+        drive(
+            &mut editor,
+            [
+                Action::Right,
+                Action::Down,
+                Action::Activate,
+                Action::Copy,
+                Action::Delete,
+                Action::AddAttribute,
+                Action::ToggleSelect,
+                Action::ToggleTable,
+                Action::Down,
+                Action::ToggleTable,
+                Action::NextIssue,
+                Action::Stats,
+                Action::Cancel,
+                Action::Input('%'),
+                Action::Undo,
+            ],
+        );
+        assert!(editor.types.is_empty());
+        assert!(render(&mut editor).contains("Types"));
+    }
+
+    #[test]
+    fn f1_shows_the_overlays_keys_over_it_and_esc_goes_back() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        drive(&mut editor, [Action::Validate, Action::Help]);
+        let screen = render(&mut editor);
+        assert!(screen.contains("Keys: Validation report"));
+        assert!(screen.contains("Esc or F1: back"));
+
+        // Keys under the panel don't reach the overlay; Esc only closes the panel.
+        drive(&mut editor, [Action::Delete, Action::Cancel]);
+        assert!(matches!(editor.overlay, Some(Overlay::ValidationReport)));
+        assert!(!render(&mut editor).contains("Keys: Validation report"));
+        drive(&mut editor, [Action::Cancel]);
+        assert!(editor.overlay.is_none());
+
+        // Without an overlay there is nothing to show.
+        drive(&mut editor, [Action::Help]);
+        assert!(!editor.overlay_help);
+    }
+
+    #[test]
+    fn opening_another_file_keeps_the_view_but_not_the_files_state() {
+        let fixture = Fixture::new();
+        let sample = fixture.write("types.xml", SAMPLE);
+        let weapons = fixture.write("weapons.xml", &SAMPLE.replace("\"food\"", "\"weapons\""));
+        let mut editor = editor_for(&sample);
+        drive(&mut editor, [Action::ToggleSelect, Action::Right, Action::Activate, Action::ClearLine]);
+        drive(&mut editor, text("50"));
+        drive(&mut editor, [Action::Activate, Action::Input('z'), Action::CycleInlineValues]);
+        drive(&mut editor, [Action::ToggleCategories, Action::Down, Action::Activate]);
+        assert_eq!(editor.category_filter, Some(CategoryFilter::Named(String::from("food"))));
+        assert_eq!((editor.undo_stack.len(), editor.marked_types().len()), (1, 1));
+
+        // The file's state goes; the view stays, less a category the file doesn't have.
+        let selection = |path: &Path| FileSelection { path: path.to_path_buf(), source: FileSource::Local };
+        editor.load(selection(&weapons), LoadMode::KeepView).unwrap();
+        assert!(editor.undo_stack.is_empty() && editor.marked_types().is_empty());
+        assert!(editor.field_filter == FieldFilter::Empty && editor.inline_values == InlineValues::Nominal);
+        assert!(editor.show_categories);
+        assert_eq!(editor.category_filter, None);
+        assert!(editor.status.contains("; no type is in food, showing all categories"), "{}", editor.status);
+
+        // A category both files have is kept, with the pane's cursor on it.
+        editor.category_filter = Some(CategoryFilter::Named(String::from("weapons")));
+        editor.type_filter = Some(TypeFilter::ChangedThisSession);
+        editor.load(selection(&sample), LoadMode::KeepView).unwrap();
+        assert_eq!(editor.visible_types(), [0, 2]);
+        assert_eq!(editor.category_cursor, 2);
+        assert_eq!(editor.type_filter, None);
+        assert!(editor.status.contains("the changed-types filter (f) was cleared"), "{}", editor.status);
+
+        // R is a load of the same file: the view is checked against what came from disk.
+        fixture.write("types.xml", &SAMPLE.replace("\"weapons\"", "\"tools\""));
+        editor.type_filter = Some(TypeFilter::ChangedThisSession);
+        editor.reload_from_disk().unwrap();
+        assert_eq!((editor.category_filter.as_ref(), editor.type_filter.as_ref()), (None, None));
+        assert!(editor.status.starts_with("Reloaded from disk ("), "{}", editor.status);
+        assert!(
+            editor.status.ends_with("; the changed-types filter (f) was cleared, nothing has changed in this file yet; no type is in weapons, showing all categories (u to undo)"),
+            "{}",
+            editor.status
+        );
+        assert_eq!(editor.visible_types(), [0, 1, 2]);
+
+        editor.load(selection(&weapons), LoadMode::Fresh).unwrap();
+        assert!(!editor.show_categories && editor.category_filter.is_none());
+        assert!(editor.field_filter == FieldFilter::All && editor.inline_values == InlineValues::Off);
+        assert_eq!(editor.panes, DEFAULT_PANES);
+    }
+
+    #[test]
+    fn marked_types_show_the_highlighted_ones_numbers_in_a_line() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        assert!(!render(&mut editor).contains("AKM — "), "only while types are marked");
+        drive(&mut editor, [Action::ToggleSelect, Action::Up]);
+        assert!(render(&mut editor).contains(" AKM — nom 8 min 4 life 2 h cat weapons "));
+
+        // It follows the cursor and edits, and leaves with the focus.
+        drive(&mut editor, [Action::Down, Action::Right, Action::Activate, Action::ClearLine]);
+        drive(&mut editor, text("45"));
+        drive(&mut editor, [Action::Activate]);
+        assert!(!render(&mut editor).contains("Apple — "));
+        drive(&mut editor, [Action::Left]);
+        assert!(render(&mut editor).contains(" Apple — nom 45 min 20 life 1 h cat food "));
+
+        let narrow = render_width(&mut editor, 40);
+        assert!(narrow.contains(" Apple — nom 45 min 20 life 1 h cat f…"), "{narrow}");
+        drive(&mut editor, [Action::Stats]);
+        assert!(render(&mut editor).contains("Apple: 1 (nom 45 min 20 life 1 h cat food)"));
+    }
 }
@@ -0,0 +1,168 @@
+/// Evaluates `+ - * / %`, parentheses, unary minus, decimal numbers and identifiers
+/// resolved through `lookup` (field values like `nominal`).
+pub fn eval(input: &str, lookup: impl Fn(&str) -> Option<f64>) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        lookup: &lookup,
+    };
+    let value = parser.sum()?;
+    parser.skip_spaces();
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{c}' at position {}", parser.pos + 1));
+    }
+    if !value.is_finite() {
+        return Err(String::from("the result is too large"));
+    }
+    Ok(value)
+}
+
+/// Whole numbers without a fraction, others with up to four decimals.
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let text = format!("{value:.4}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    lookup: &'a dyn Fn(&str) -> Option<f64>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// The next operator among `ops`, consumed.
+    fn operator(&mut self, ops: &[char]) -> Option<char> {
+        self.skip_spaces();
+        let c = self.peek().filter(|c| ops.contains(c))?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        while let Some(op) = self.operator(&['+', '-']) {
+            let rhs = self.product()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.operator(&['*', '/', '%']) {
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return Err(String::from("division by zero"));
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.operator(&['-']).is_some() {
+            return Ok(-self.unary()?);
+        }
+        if self.operator(&['+']).is_some() {
+            return self.unary();
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        self.skip_spaces();
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.sum()?;
+                if self.operator(&[')']).is_none() {
+                    return Err(String::from("missing ')'"));
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                text.parse().map_err(|_| format!("'{text}' is not a number"))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                (self.lookup)(&name).ok_or_else(|| format!("unknown name '{name}'"))
+            }
+            Some(c) => Err(format!("unexpected '{c}' at position {}", self.pos + 1)),
+            None => Err(String::from("expression ends too early")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(input: &str) -> Result<f64, String> {
+        eval(input, |name| match name {
+            "nominal" => Some(12.0),
+            "restock" => Some(1800.0),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(calc("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(calc("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(calc("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(calc("2 * -3 + 10 % 4"), Ok(-4.0));
+        assert_eq!(calc("-(2 + 3) * 2"), Ok(-10.0));
+        assert_eq!(calc("1.5 * 4"), Ok(6.0));
+    }
+
+    #[test]
+    fn identifiers_resolve_through_lookup() {
+        assert_eq!(calc("3600 / restock * nominal"), Ok(24.0));
+        assert_eq!(calc("nominal * 0.8"), Ok(12.0 * 0.8));
+        assert_eq!(calc("lifetime / 60"), Err(String::from("unknown name 'lifetime'")));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert_eq!(calc("5 / 0"), Err(String::from("division by zero")));
+        assert_eq!(calc("5 % (2 - 2)"), Err(String::from("division by zero")));
+        assert_eq!(calc("(1 + 2"), Err(String::from("missing ')'")));
+        assert_eq!(calc("1 +"), Err(String::from("expression ends too early")));
+        assert_eq!(calc("2 3"), Err(String::from("unexpected '3' at position 3")));
+        assert_eq!(calc("1..2"), Err(String::from("'1..2' is not a number")));
+        assert_eq!(calc(""), Err(String::from("expression ends too early")));
+    }
+
+    #[test]
+    fn numbers_format_without_noise() {
+        assert_eq!(format_number(24.0), "24");
+        assert_eq!(format_number(-3.0), "-3");
+        assert_eq!(format_number(2.0 / 3.0), "0.6667");
+        assert_eq!(format_number(0.5), "0.5");
+    }
+}
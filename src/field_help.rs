@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::model::FieldKey;
+
+/// Looked up in the config directory and next to the opened file.
+pub const HELP_FILE: &str = "help.toml";
+
+const EMBEDDED: &str = include_str!("help.toml");
+
+/// Tips pane texts: element name → text for element fields, attribute name → text for
+/// attribute fields, and per map (`chernarus`, `livonia`, or a modded map's world name)
+/// tier name → the area it covers.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldHelp {
+    pub elements: BTreeMap<String, String>,
+    pub attributes: BTreeMap<String, String>,
+    pub tiers: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl FieldHelp {
+    /// The built-in texts.
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED).expect("embedded help.toml parses")
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e: toml::de::Error| e.message().to_string())
+    }
+
+    /// The embedded texts with each file's entries laid over them in turn (user, then
+    /// project). A file that doesn't parse is left out and reported.
+    pub fn layered<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>) -> (Self, Vec<String>) {
+        let mut help = Self::embedded();
+        let mut notes = Vec::new();
+        for (place, content) in files {
+            match Self::parse(content) {
+                Ok(layer) => {
+                    help.elements.extend(layer.elements);
+                    help.attributes.extend(layer.attributes);
+                    for (map, tiers) in layer.tiers {
+                        help.tiers.entry(map).or_default().extend(tiers);
+                    }
+                }
+                Err(err) => notes.push(format!("{place} ignored: {err}")),
+            }
+        }
+        (help, notes)
+    }
+
+    pub fn documents_element(&self, name: &str) -> bool {
+        self.elements.contains_key(name)
+    }
+
+    pub fn text(&self, key: &FieldKey) -> String {
+        match key {
+            FieldKey::Element { name, .. } => match self.elements.get(name) {
+                Some(text) => text.clone(),
+                None => String::from("Unknown field - open a github issue with the field name."),
+            },
+            FieldKey::Attribute { attr, .. } => match self.attributes.get(attr) {
+                Some(text) => text.clone(),
+                None => format!("Unknown attribute - open a github issue. {}", attr),
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(name: &str) -> FieldKey {
+        FieldKey::Element { name: name.to_string(), index: 0 }
+    }
+
+    fn attribute(attr: &str) -> FieldKey {
+        FieldKey::Attribute { element: String::from("flags"), index: 0, attr: attr.to_string() }
+    }
+
+    #[test]
+    fn embedded_texts_cover_the_vanilla_fields() {
+        let help = FieldHelp::embedded();
+        assert!(help.text(&element("nominal")).starts_with("The nominal (wanted) amount"));
+        assert!(help.text(&attribute("deloot")).starts_with("Boolean flag."));
+        assert!(help.documents_element("flags") && !help.documents_element("spawnsecondary"));
+        assert_eq!(help.text(&element("spawnsecondary")), "Unknown field - open a github issue with the field name.");
+        assert_eq!(help.text(&attribute("odd")), "Unknown attribute - open a github issue. odd");
+        assert!(help.tiers.contains_key("chernarus") && help.tiers.contains_key("livonia"));
+    }
+
+    #[test]
+    fn layers_add_and_replace_entries_in_order_and_bad_files_are_reported() {
+        let user = "[elements]\nspawnsecondary = \"Spawns a second item.\"\nnominal = \"Wanted amount.\"\n";
+        let project = "[elements]\nnominal = \"How many we want.\"\n[tiers.chernarus]\nTier4 = \"the far north\"\n";
+        let (help, notes) = FieldHelp::layered([("user help.toml", user), ("project help.toml", project), ("broken help.toml", "colour = 1")]);
+        assert_eq!(help.text(&element("nominal")), "How many we want.");
+        assert_eq!(help.text(&element("spawnsecondary")), "Spawns a second item.");
+        assert!(help.documents_element("spawnsecondary"));
+        assert!(help.text(&element("lifetime")).starts_with("The amount of time"), "the rest stays built in");
+        let chernarus = &help.tiers["chernarus"];
+        assert_eq!(chernarus["Tier4"], "the far north");
+        assert_eq!(chernarus["Tier1"], FieldHelp::embedded().tiers["chernarus"]["Tier1"], "tier tables merge per map");
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].starts_with("broken help.toml ignored: "), "{notes:?}");
+    }
+}
@@ -1,16 +1,49 @@
-use std::{fs, io, path::PathBuf, sync::{Arc, Mutex}};
+use std::{
+    collections::VecDeque,
+    env, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::{action::Action, remote::{DirEntry, FileSelection, FileSource, RemoteConfig, SshBackend}, utils};
+use crate::{
+    action::Action,
+    config::Config,
+    line_edit,
+    preview::PreviewState,
+    remote::{self, AuthMethod, ConnectResult, DirEntry, FileSelection, FileSource, RemoteConfig, SshBackend},
+    sniff::{self, FileCheck},
+    utils,
+};
 use tui::{
     layout::{Constraint, Direction, Layout},
     style::{Modifier, Style},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
 
+/// What the picker hands to the main loop.
+pub enum Picked {
+    /// One file for the editor.
+    Open(FileSelection),
+    /// Two files for the compare view, A then B.
+    Compare(FileSelection, FileSelection),
+    /// Several marked files: the first opens now, the rest go back through `queue_files`.
+    Several(Vec<FileSelection>),
+    /// The directory asked for with `choose_directory`; `None` when it was cancelled.
+    Directory(Option<FileSelection>),
+}
+
 #[derive(Debug, Clone)]
 struct Entry {
     name: String,
     is_dir: bool,
+    /// A symlink, listed as what it points to.
+    link: bool,
 }
 
 pub struct FilePicker {
@@ -22,6 +55,84 @@ pub struct FilePicker {
     backend: PickerBackend,
     remote_config: Option<RemoteConfig>,
     mode: PickerMode,
+    /// The connection being made, and the directory to reopen on it (reconnects keep theirs).
+    pending_connect: Option<(RemoteConfig, Receiver<ConnectResult>, Option<PathBuf>)>,
+    /// The `ssh://` file from the command line, opened once `pending_connect` succeeds.
+    open_after_connect: Option<PathBuf>,
+    /// That file on the new connection, until `take_connected_open` hands it to the editor.
+    connected_open: Option<FileSelection>,
+    large_file_bytes: u64,
+    /// Files marked with Space, in marking order, in any directory or backend; the first
+    /// two are A and B for `=`.
+    marks: Vec<FileSelection>,
+    /// Marked files still to be opened, one after another as the editor closes each.
+    open_queue: VecDeque<FileSelection>,
+    /// Last selected entry per (backend, directory), most recent last.
+    cursor_memory: VecDeque<(String, PathBuf, String)>,
+    /// Dot files and directories are listed; `..` always is.
+    show_hidden: bool,
+    /// Files opened through a symlink get the path the links point to.
+    resolve_symlinks: bool,
+    /// Directories entered through a symlink on the way to `cwd`, outermost first; more
+    /// than `MAX_LINK_DEPTH` of them is taken for a loop.
+    links: Vec<PathBuf>,
+    /// The file loaded in the editor; the picker won't delete it.
+    open_file: Option<FileSelection>,
+    /// `Some` while the preview pane (`p`) is shown.
+    preview: Option<PreviewState>,
+    /// `Some` while the current directory is still being read.
+    listing: Option<Listing>,
+    /// What a directory is being chosen for, while `choose_directory` is in effect: `s`
+    /// picks one and Enter still opens directories.
+    directory_purpose: Option<String>,
+    /// Where `M` goes: `missions_root` locally, `[ssh] missions_root` while connected.
+    missions_root: Option<PathBuf>,
+    remote_missions_root: Option<PathBuf>,
+    /// The SSH login directory, once `~` has asked the server for it.
+    remote_home: Option<PathBuf>,
+}
+
+/// Directories `~` and `M` jump to from anywhere in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchor {
+    Home,
+    Missions,
+}
+
+impl Anchor {
+    fn label(self) -> &'static str {
+        match self {
+            Anchor::Home => "home directory",
+            Anchor::Missions => "missions root",
+        }
+    }
+}
+
+fn local_home() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Directories whose cursor position is remembered.
+const CURSOR_MEMORY: usize = 64;
+/// Symlinked directories followed, one inside the other, before the picker stops.
+const MAX_LINK_DEPTH: usize = 8;
+/// Entries the listing worker hands over at a time.
+const LIST_BATCH: usize = 200;
+/// How long opening a directory waits for the whole listing before showing it partly read.
+const LIST_WAIT: Duration = Duration::from_millis(150);
+
+enum ListEvent {
+    Batch(Vec<DirEntry>),
+    Done(io::Result<()>),
+}
+
+/// A directory listing arriving in batches from a worker thread.
+struct Listing {
+    rx: Receiver<ListEvent>,
+    stop: Arc<AtomicBool>,
+    /// Entry to put the cursor on once it arrives.
+    wanted: Option<String>,
+    cursor_moved: bool,
 }
 
 enum PickerBackend {
@@ -32,29 +143,116 @@ enum PickerBackend {
 enum PickerMode {
     Browse,
     RemotePrompt(RemoteForm),
+    /// `r` while connected: disconnect, reconnect, or switch to another server.
+    RemoteMenu { cursor: usize },
+    ConfirmOpen {
+        selection: FileSelection,
+        reasons: Vec<String>,
+    },
+    /// F2/`R`: new name for the highlighted entry in the current directory.
+    Rename { from: String, input: String, cursor: Option<usize> },
+    ConfirmDelete { path: PathBuf, is_dir: bool },
+    /// The editor closed a file while marked files are still queued.
+    OpenNext(FileSelection),
+}
+
+/// Choices of the remote menu, in display order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RemoteChoice {
+    Disconnect,
+    Reconnect,
+    Switch,
 }
 
-struct RemoteForm {
+const REMOTE_CHOICES: [RemoteChoice; 3] = [RemoteChoice::Disconnect, RemoteChoice::Reconnect, RemoteChoice::Switch];
+
+/// The SSH connect form's rows; `Auth` is a choice, the others are typed.
+const FORM_ROWS: usize = 7;
+const AUTH_ROW: usize = 3;
+
+/// The SSH connect form; also the `:setup` wizard's profile step.
+pub struct RemoteForm {
     host: String,
     user: String,
     port: String,
+    auth: AuthMethod,
     password: String,
     key_path: String,
     passphrase: String,
     field_index: usize,
+    /// Cursor in the active field (`line_edit`); back at the end on switching fields.
+    cursor: Option<usize>,
+    /// What the agent said when asked for its keys, once `auth` is the agent.
+    agent: Option<Result<Vec<String>, String>>,
+    /// Carried over from the profile; not editable in the form.
+    readonly: bool,
 }
 
 impl RemoteForm {
-    fn next_field(&mut self) {
-        self.field_index = (self.field_index + 1) % 6;
+    /// Filled in from `defaults`, or empty apart from the login name and port 22.
+    pub fn new(defaults: Option<RemoteConfig>) -> Self {
+        let defaults = defaults.unwrap_or_else(|| RemoteConfig {
+            host: String::new(),
+            port: 22,
+            username: std::env::var("USER").unwrap_or_default(),
+            password: None,
+            key_path: None,
+            passphrase: None,
+            auth: AuthMethod::Agent,
+            readonly: false,
+        });
+        let mut form = Self {
+            host: defaults.host,
+            user: defaults.username,
+            port: defaults.port.to_string(),
+            auth: defaults.auth,
+            password: defaults.password.unwrap_or_default(),
+            key_path: defaults.key_path.unwrap_or_default().to_string_lossy().to_string(),
+            passphrase: defaults.passphrase.unwrap_or_default(),
+            field_index: 0,
+            cursor: None,
+            agent: None,
+            readonly: defaults.readonly,
+        };
+        form.ask_agent();
+        form
     }
 
-    fn prev_field(&mut self) {
-        if self.field_index == 0 {
-            self.field_index = 5;
-        } else {
-            self.field_index -= 1;
+    /// Moves between fields and edits the active one; false for actions it leaves alone.
+    /// PgUp/PgDown go to the first and last field; on Auth, Left/Right or Space pick the
+    /// method.
+    pub fn edit(&mut self, action: &Action) -> bool {
+        match *action {
+            Action::Up | Action::BackTab => self.go_to_field((self.field_index + FORM_ROWS - 1) % FORM_ROWS),
+            Action::Down | Action::Tab => self.go_to_field((self.field_index + 1) % FORM_ROWS),
+            Action::PgUp => self.go_to_field(0),
+            Action::PgDown => self.go_to_field(FORM_ROWS - 1),
+            Action::Left | Action::Right | Action::Input(' ') if self.field_index == AUTH_ROW => {
+                let at = AuthMethod::ALL.iter().position(|&m| m == self.auth).unwrap_or(0);
+                let step = if *action == Action::Left { AuthMethod::ALL.len() - 1 } else { 1 };
+                self.auth = AuthMethod::ALL[(at + step) % AuthMethod::ALL.len()];
+                self.ask_agent();
+            }
+            _ if self.field_index == AUTH_ROW => return false,
+            _ => {
+                let mut cursor = self.cursor;
+                let edited = line_edit::edit(self.active_mut(), &mut cursor, action);
+                self.cursor = cursor;
+                return edited;
+            }
         }
+        true
+    }
+
+    /// Asks the agent for its keys when it is the method, so the form can say what
+    /// connecting will offer before it is tried.
+    fn ask_agent(&mut self) {
+        self.agent = (self.auth == AuthMethod::Agent).then(|| remote::agent_identities().map_err(|e| e.to_string()));
+    }
+
+    fn go_to_field(&mut self, index: usize) {
+        self.field_index = index;
+        self.cursor = None;
     }
 
     fn active_mut(&mut self) -> &mut String {
@@ -62,22 +260,55 @@ impl RemoteForm {
             0 => &mut self.host,
             1 => &mut self.user,
             2 => &mut self.port,
-            3 => &mut self.password,
-            4 => &mut self.key_path,
-            5 => &mut self.passphrase,
+            4 => &mut self.password,
+            5 => &mut self.key_path,
+            6 => &mut self.passphrase,
             _ => &mut self.host,
         }
     }
 
-    fn push_char(&mut self, c: char) {
-        self.active_mut().push(c);
-    }
-
-    fn pop_char(&mut self) {
-        self.active_mut().pop();
+    /// One `> Host: example.org` line per field, the active one marked, with `▏` where
+    /// typing goes once the cursor was moved off the end. Fields the chosen method doesn't
+    /// use say so, and with the agent a line below Auth tells what it holds.
+    pub fn lines(&self) -> Vec<String> {
+        let methods: Vec<String> = AuthMethod::ALL
+            .iter()
+            .map(|&m| if m == self.auth { format!("[{}]", m.label()) } else { m.label().to_string() })
+            .collect();
+        let auth = methods.join(" ");
+        let unused = |method: AuthMethod, label: &'static str| if self.auth == method { label } else { "(not used)" };
+        let fields = [
+            ("Host", self.host.as_str(), ""),
+            ("User", self.user.as_str(), ""),
+            ("Port", self.port.as_str(), ""),
+            ("Auth", auth.as_str(), if self.field_index == AUTH_ROW { "(←/→ to change)" } else { "" }),
+            ("Password", self.password.as_str(), unused(AuthMethod::Password, "")),
+            ("Key Path", self.key_path.as_str(), unused(AuthMethod::Key, "")),
+            ("Passphrase", self.passphrase.as_str(), unused(AuthMethod::Key, "(optional)")),
+        ];
+        let mut lines = Vec::new();
+        for (i, (label, value, note)) in fields.into_iter().enumerate() {
+            let shown = match self.cursor {
+                Some(_) if i == self.field_index && i != AUTH_ROW => line_edit::view(value, self.cursor, usize::MAX),
+                _ => value.to_string(),
+            };
+            let marker = if i == self.field_index { ">" } else { " " };
+            let line = format!("{marker} {label}: {shown}");
+            lines.push(if note.is_empty() { line } else { format!("{line}  {note}") });
+            if i == AUTH_ROW
+                && let Some(agent) = &self.agent
+            {
+                lines.push(match agent {
+                    Ok(keys) if keys.is_empty() => String::from("    The agent holds no keys; ssh-add adds one"),
+                    Ok(keys) => format!("    The agent holds {}: {}", utils::plural(keys.len(), "key"), keys.join(", ")),
+                    Err(err) => format!("    {err}"),
+                });
+            }
+        }
+        lines
     }
 
-    fn to_config(&self) -> RemoteConfig {
+    pub fn to_config(&self) -> RemoteConfig {
         let port: u16 = self.port.parse().unwrap_or(22);
         RemoteConfig {
             host: self.host.clone(),
@@ -86,21 +317,50 @@ impl RemoteForm {
             password: if self.password.is_empty() { None } else { Some(self.password.clone()) },
             key_path: if self.key_path.is_empty() { None } else { Some(PathBuf::from(self.key_path.clone())) },
             passphrase: if self.passphrase.is_empty() { None } else { Some(self.passphrase.clone()) },
+            auth: self.auth,
+            readonly: self.readonly,
         }
     }
 }
 
 impl FilePicker {
-    pub fn new(cwd: PathBuf, remote_config: Option<RemoteConfig>) -> io::Result<Self> {
+    /// Starts in `default_dir` when it is set and a directory, else in `cwd`.
+    pub fn new(cwd: PathBuf, remote_config: Option<RemoteConfig>, config: &Config) -> io::Result<Self> {
+        let mut status = String::from("Press Enter to open, q to quit");
+        let cwd = match &config.default_dir {
+            Some(dir) if dir.is_dir() => dir.clone(),
+            Some(dir) => {
+                status = format!("default_dir {} isn't a directory; starting in {}", dir.display(), cwd.display());
+                cwd
+            }
+            None => cwd,
+        };
         let mut picker = Self {
             local_root: cwd.clone(),
             cwd,
             entries: Vec::new(),
             state: ListState::default(),
-            status: String::from("Press Enter to open, q to quit"),
+            status,
             backend: PickerBackend::Local,
             remote_config,
             mode: PickerMode::Browse,
+            pending_connect: None,
+            open_after_connect: None,
+            connected_open: None,
+            large_file_bytes: config.large_file_warning_bytes(),
+            marks: Vec::new(),
+            open_queue: VecDeque::new(),
+            cursor_memory: VecDeque::new(),
+            show_hidden: config.show_hidden,
+            resolve_symlinks: config.resolve_symlinks,
+            links: Vec::new(),
+            open_file: None,
+            preview: None,
+            listing: None,
+            directory_purpose: None,
+            missions_root: config.missions_root.clone(),
+            remote_missions_root: config.ssh.missions_root.clone(),
+            remote_home: None,
         };
 
         picker.refresh_entries()?;
@@ -111,55 +371,117 @@ impl FilePicker {
         self.status = message.into();
     }
 
-    pub fn handle_action(&mut self, action: Action) -> io::Result<Option<FileSelection>> {
+    /// Closing a file (`None`) offers the next queued one, if any.
+    pub fn set_open_file(&mut self, selection: Option<FileSelection>) {
+        self.open_file = selection;
+        if self.open_file.is_none()
+            && matches!(self.mode, PickerMode::Browse)
+            && let Some(next) = self.open_queue.pop_front()
+        {
+            self.mode = PickerMode::OpenNext(next);
+        }
+    }
+
+    /// Asks for a directory instead of a file, e.g. `choose_directory("the export")`; the
+    /// answer comes back from `handle_action` as `Picked::Directory`.
+    pub fn choose_directory(&mut self, purpose: impl Into<String>) {
+        let purpose = purpose.into();
+        self.status = format!("Choose a directory for {purpose}: s selects, Enter opens, Esc cancels");
+        self.directory_purpose = Some(purpose);
+    }
+
+    /// Whether a `choose_directory` question is open; `q` then cancels it instead of quitting.
+    pub fn is_choosing_directory(&self) -> bool {
+        self.directory_purpose.is_some()
+    }
+
+    /// Files still to open after the one `Picked::Several` put in the editor.
+    pub fn queue_files(&mut self, files: Vec<FileSelection>) {
+        self.open_queue = files.into();
+    }
+
+    pub fn handle_action(&mut self, action: Action) -> io::Result<Option<Picked>> {
         match &mut self.mode {
             PickerMode::Browse => {
                 match action {
-                    Action::Up => self.previous(),
-                    Action::Down => self.next(),
-                    Action::PgUp => self.jump(-5),
-                    Action::PgDown => self.jump(5),
+                    Action::Up => {
+                        self.note_cursor_moved();
+                        self.previous();
+                    }
+                    Action::Down => {
+                        self.note_cursor_moved();
+                        self.next();
+                    }
+                    Action::PgUp => {
+                        self.note_cursor_moved();
+                        self.jump(-5);
+                    }
+                    Action::PgDown => {
+                        self.note_cursor_moved();
+                        self.jump(5);
+                    }
+                    Action::Save if self.directory_purpose.is_some() => {
+                        return Ok(Some(self.select_directory()));
+                    }
+                    Action::Activate if self.directory_purpose.is_some() && !self.highlighted_is_dir() => {
+                        self.status = String::from("That's a file; s selects the directory");
+                    }
+                    Action::Cancel if self.listing.is_none() && self.directory_purpose.is_some() => {
+                        self.directory_purpose = None;
+                        self.status = String::from("No directory chosen");
+                        return Ok(Some(Picked::Directory(None)));
+                    }
+                    Action::Activate if !self.marks.is_empty() && !self.highlighted_is_dir() => {
+                        return Ok(self.open_marked());
+                    }
                     Action::Activate => {
                         if let Some(selection) = self.enter_directory_or_select_file()? {
-                            return Ok(Some(selection));
+                            return Ok(Some(Picked::Open(selection)));
                         }
                     }
-                    Action::ToggleRemote => {
-                        match self.backend {
-                            PickerBackend::Remote(_) => {
-                                self.backend = PickerBackend::Local;
-                                self.cwd = self.local_root.clone();
-                                self.status = "Switched to local".to_string();
-                                self.refresh_entries()?;
-                            }
-                            PickerBackend::Local => self.start_remote_prompt(),
-                        }
+                    Action::ToggleRemote => match self.backend {
+                        PickerBackend::Remote(_) => self.mode = PickerMode::RemoteMenu { cursor: 0 },
+                        PickerBackend::Local => self.start_remote_prompt(),
+                    },
+                    Action::Backspace | Action::Left => self.go_to_parent()?,
+                    Action::ToggleSelect => self.toggle_mark(),
+                    Action::Input('=') => return Ok(self.request_compare()),
+                    Action::Rename | Action::Input('R') => self.start_rename(),
+                    Action::Remove | Action::Input('D') => self.start_delete(),
+                    Action::Input('p') => {
+                        self.preview = match self.preview {
+                            Some(_) => None,
+                            None => Some(PreviewState::Idle(String::new())),
+                        };
+                    }
+                    Action::Input('~') => self.go_to_anchor(Anchor::Home)?,
+                    Action::Input('M') => self.go_to_anchor(Anchor::Missions)?,
+                    Action::Input('H') => {
+                        self.show_hidden = !self.show_hidden;
+                        self.remember_cursor();
+                        self.refresh_entries()?;
+                        self.status = format!("Hidden files {}", if self.show_hidden { "shown" } else { "hidden" });
+                    }
+                    Action::Cancel if self.listing.is_some() => {
+                        self.stop_listing();
+                        self.status = format!("Listing stopped; showing the {} entries read so far", self.listed());
+                    }
+                    Action::Cancel if !self.marks.is_empty() => {
+                        self.marks.clear();
+                        self.status = "Marks cleared".to_string();
                     }
                     _ => {}
                 }
             }
             PickerMode::RemotePrompt(form) => {
+                if form.edit(&action) {
+                    return Ok(None);
+                }
                 match action {
-                    Action::Up => form.prev_field(),
-                    Action::Down => form.next_field(),
-                    Action::Tab => form.next_field(),
-                    Action::PgUp => form.prev_field(),
-                    Action::PgDown => form.next_field(),
-                    Action::Input(c) => form.push_char(c),
-                    Action::Backspace => form.pop_char(),
                     Action::Activate => {
                         let cfg = form.to_config();
-                        match self.try_connect(cfg) {
-                            Ok(true) => {
-                                self.mode = PickerMode::Browse;
-                                self.refresh_entries()?;
-                            }
-                            Ok(false) => {}
-                            Err(err) => {
-                                self.status = format!("SSH connect failed: {err}");
-                                self.mode = PickerMode::Browse;
-                            }
-                        }
+                        self.mode = PickerMode::Browse;
+                        self.start_connect(cfg, None);
                     }
                     Action::Cancel => {
                         self.status = "SSH connect cancelled".to_string();
@@ -168,54 +490,239 @@ impl FilePicker {
                     _ => {}
                 }
             }
+            PickerMode::RemoteMenu { cursor } => {
+                let choice = match action {
+                    Action::Up => {
+                        *cursor = cursor.saturating_sub(1);
+                        None
+                    }
+                    Action::Down => {
+                        *cursor = (*cursor + 1).min(REMOTE_CHOICES.len() - 1);
+                        None
+                    }
+                    Action::Activate => Some(REMOTE_CHOICES[*cursor]),
+                    Action::Input('d') => Some(RemoteChoice::Disconnect),
+                    Action::Input('r') => Some(RemoteChoice::Reconnect),
+                    Action::Input('s') => Some(RemoteChoice::Switch),
+                    Action::Cancel => {
+                        self.mode = PickerMode::Browse;
+                        self.status = "Still connected".to_string();
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(choice) = choice {
+                    self.mode = PickerMode::Browse;
+                    self.choose_remote(choice)?;
+                }
+            }
+            PickerMode::Rename { from, input, cursor } => match action {
+                _ if line_edit::edit(input, cursor, &action) => {}
+                Action::Activate => {
+                    let (from, to) = (from.clone(), input.trim().to_string());
+                    self.mode = PickerMode::Browse;
+                    self.rename_entry(&from, &to)?;
+                }
+                Action::Cancel => {
+                    self.mode = PickerMode::Browse;
+                    self.status = "Rename cancelled".to_string();
+                }
+                _ => {}
+            },
+            PickerMode::ConfirmDelete { path, is_dir } => match action {
+                Action::Activate | Action::Input('y') | Action::Input('Y') => {
+                    let (path, is_dir) = (path.clone(), *is_dir);
+                    self.mode = PickerMode::Browse;
+                    self.delete_entry(&path, is_dir)?;
+                }
+                Action::Cancel | Action::Input('n') | Action::Input('N') => {
+                    self.mode = PickerMode::Browse;
+                    self.status = "Delete cancelled".to_string();
+                }
+                _ => {}
+            },
+            PickerMode::ConfirmOpen { selection, .. } => match action {
+                Action::Activate | Action::Input('y') | Action::Input('Y') => {
+                    let selection = selection.clone();
+                    self.mode = PickerMode::Browse;
+                    self.status = format!("Opening {} anyway", selection.path.display());
+                    return Ok(Some(Picked::Open(selection)));
+                }
+                Action::Cancel | Action::Input('n') | Action::Input('N') => {
+                    self.mode = PickerMode::Browse;
+                    self.status = "Open cancelled".to_string();
+                }
+                _ => {}
+            },
+            PickerMode::OpenNext(selection) => match action {
+                Action::Activate | Action::Input('y') | Action::Input('Y') => {
+                    let selection = selection.clone();
+                    self.mode = PickerMode::Browse;
+                    self.status = format!("Opening {} ({} more queued)", selection.short_label(), self.open_queue.len());
+                    return Ok(Some(Picked::Open(selection)));
+                }
+                Action::Input('n') | Action::Input('N') => {
+                    let skipped = selection.short_label();
+                    self.mode = PickerMode::Browse;
+                    self.status = format!("Skipped {skipped}");
+                    self.set_open_file(None);
+                }
+                Action::Cancel => {
+                    let dropped = self.open_queue.len() + 1;
+                    self.open_queue.clear();
+                    self.mode = PickerMode::Browse;
+                    self.status = format!("Stopped opening marked files; {dropped} not opened");
+                }
+                _ => {}
+            },
         }
         Ok(None)
     }
 
+    /// Lists the current directory again, back where the cursor was last time in it.
     fn refresh_entries(&mut self) -> io::Result<()> {
-        let mut entries = Vec::new();
+        let key = self.backend_key();
+        let remembered = self
+            .cursor_memory
+            .iter()
+            .rev()
+            .find(|(backend, dir, _)| *backend == key && *dir == self.cwd)
+            .map(|(_, _, name)| name.clone());
+        self.refresh_entries_selecting(remembered)
+    }
 
+    /// Starts listing the current directory on a worker thread and waits `LIST_WAIT` for
+    /// it, so small directories show complete at once; the rest arrives through `tick`.
+    /// The cursor goes to `wanted` once it is listed, else to the first entry after `..`,
+    /// until it's moved by hand.
+    fn refresh_entries_selecting(&mut self, wanted: Option<String>) -> io::Result<()> {
+        self.stop_listing();
+        self.entries.clear();
         if self.cwd.parent().is_some() {
-            entries.push(Entry {
+            self.entries.push(Entry {
                 name: "..".to_string(),
                 is_dir: true,
+                link: false,
             });
         }
+        self.state.select(Some(0));
+        if self.preview.is_some() {
+            // The listing may have changed under the previewed file; read it again.
+            self.preview = Some(PreviewState::Idle(String::new()));
+        }
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (source, dir, stopped) = (self.current_source(), self.cwd.clone(), stop.clone());
+        thread::spawn(move || {
+            let result = source.list_dir_batched(&dir, LIST_BATCH, &mut |batch| {
+                !stopped.load(Ordering::Relaxed) && tx.send(ListEvent::Batch(batch)).is_ok()
+            });
+            let _ = tx.send(ListEvent::Done(result));
+        });
+        self.listing = Some(Listing { rx, stop, wanted, cursor_moved: false });
 
-        match &self.backend {
-            PickerBackend::Local => {
-                for entry in fs::read_dir(&self.cwd)? {
-                    let entry = entry?;
-                    let path = entry.path();
-                    let name = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or_default()
-                        .to_string();
-                    let is_dir = path.is_dir();
-                    entries.push(Entry { name, is_dir });
+        let deadline = Instant::now() + LIST_WAIT;
+        while let Some(listing) = &self.listing {
+            let Some(wait) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            match listing.rx.recv_timeout(wait) {
+                Ok(ListEvent::Batch(batch)) => self.add_entries(batch),
+                Ok(ListEvent::Done(result)) => {
+                    self.listing = None;
+                    result?;
                 }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => self.listing = None,
             }
-            PickerBackend::Remote(remote) => {
-                let remote =
-                    remote.lock().map_err(|_| io::Error::new(io::ErrorKind::Other, "SSH backend in use"))?;
-                let remote_entries = remote.list_dir(&self.cwd)?;
-                for DirEntry { name, is_dir } in remote_entries {
-                    entries.push(Entry { name, is_dir });
+        }
+        Ok(())
+    }
+
+    /// Takes in what the listing worker has sent since the last call.
+    fn poll_listing(&mut self) {
+        while let Some(listing) = &self.listing {
+            match listing.rx.try_recv() {
+                Ok(ListEvent::Batch(batch)) => self.add_entries(batch),
+                Ok(ListEvent::Done(result)) => {
+                    self.listing = None;
+                    if let Err(err) = result {
+                        self.status = format!("Listing {} failed: {err}", self.cwd.display());
+                    }
                 }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.listing = None,
             }
         }
+    }
+
+    /// Esc while a directory is still being listed: keeps what has arrived.
+    fn stop_listing(&mut self) {
+        if let Some(listing) = self.listing.take() {
+            listing.stop.store(true, Ordering::Relaxed);
+        }
+    }
 
-        // Keep directories first, then files, both alphabetically.
-        entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    /// Merges a batch into the sorted list, keeping the cursor on the same entry.
+    fn add_entries(&mut self, batch: Vec<DirEntry>) {
+        let highlighted = self.state.selected().and_then(|i| self.entries.get(i)).map(|e| e.name.clone());
+        let show_hidden = self.show_hidden;
+        self.entries.extend(
+            batch
+                .into_iter()
+                .filter(|e| show_hidden || !e.name.starts_with('.'))
+                .map(|DirEntry { name, is_dir, link, .. }| Entry { name, is_dir, link }),
+        );
+        // `..` first, then directories, then files, both alphabetically.
+        self.entries.sort_by(|a, b| {
+            (a.name != "..", !a.is_dir, a.name.to_lowercase()).cmp(&(b.name != "..", !b.is_dir, b.name.to_lowercase()))
         });
+        let position = |name: &str| self.entries.iter().position(|e| e.name == name);
+        let selected = match &self.listing {
+            Some(listing) if !listing.cursor_moved => listing
+                .wanted
+                .as_deref()
+                .and_then(position)
+                .unwrap_or(usize::from(self.entries.len() > 1 && self.entries[0].name == "..")),
+            _ => highlighted.as_deref().and_then(position).unwrap_or(0),
+        };
+        self.state.select(Some(selected));
+    }
 
-        self.entries = entries;
-        self.state.select(Some(0));
-        Ok(())
+    /// Entries shown, not counting `..`.
+    fn listed(&self) -> usize {
+        self.entries.iter().filter(|e| e.name != "..").count()
+    }
+
+    /// The cursor was moved by hand; later batches keep it where it is.
+    fn note_cursor_moved(&mut self) {
+        if let Some(listing) = &mut self.listing {
+            listing.cursor_moved = true;
+        }
+    }
+
+    /// Notes the selected entry of the current directory before leaving it.
+    fn remember_cursor(&mut self) {
+        let Some(name) = self.state.selected().and_then(|i| self.entries.get(i)).map(|e| e.name.clone()) else {
+            return;
+        };
+        let key = self.backend_key();
+        self.cursor_memory.retain(|(backend, dir, _)| !(*backend == key && *dir == self.cwd));
+        if name != ".." {
+            self.cursor_memory.push_back((key, self.cwd.clone(), name));
+            if self.cursor_memory.len() > CURSOR_MEMORY {
+                self.cursor_memory.pop_front();
+            }
+        }
+    }
+
+    /// `local`, or `user@host:port` for the SSH connection being browsed.
+    fn backend_key(&self) -> String {
+        match (&self.backend, &self.remote_config) {
+            (PickerBackend::Remote(_), Some(cfg)) => format!("{}@{}:{}", cfg.username, cfg.host, cfg.port),
+            (PickerBackend::Remote(_), None) => String::from("ssh"),
+            (PickerBackend::Local, _) => String::from("local"),
+        }
     }
 
     pub fn next(&mut self) {
@@ -244,26 +751,351 @@ impl FilePicker {
         let Some(idx) = self.state.selected() else {
             return Ok(None);
         };
-        let entry = &self.entries[idx];
+        let entry = self.entries[idx].clone();
+        let cwd = &self.cwd;
+        self.links.retain(|link| cwd.starts_with(link));
 
         if entry.is_dir {
-            let new_path = if entry.name == ".." {
-                self.cwd.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| self.cwd.clone())
-            } else {
-                self.cwd.join(&entry.name)
-            };
-            self.cwd = new_path;
+            if entry.name == ".." {
+                self.go_to_parent()?;
+                return Ok(None);
+            }
+            let child = self.cwd.join(&entry.name);
+            if entry.link {
+                if self.links.len() >= MAX_LINK_DEPTH {
+                    self.status = format!(
+                        "Not following {}: already {MAX_LINK_DEPTH} symlinks deep, which looks like a loop",
+                        entry.name
+                    );
+                    return Ok(None);
+                }
+                self.links.push(child.clone());
+            }
+            self.remember_cursor();
+            self.cwd = child;
             self.status.clear();
             self.refresh_entries()?;
             Ok(None)
         } else {
-            let selected = self.cwd.join(&entry.name);
-            self.status = format!("Selected file: {} ({})", selected.display(), self.backend_label());
-            Ok(Some(FileSelection {
-                path: selected,
-                source: self.current_source(),
-            }))
+            let browsed = self.cwd.join(&entry.name);
+            let source = self.current_source();
+            let mut via = None;
+            if entry.link || !self.links.is_empty() {
+                match source.resolve(&browsed) {
+                    Ok(real) if self.resolve_symlinks && real != browsed => via = Some(real),
+                    Ok(_) => {}
+                    Err(err) if entry.link => {
+                        self.status = format!("Can't follow the symlink {}: {err}", entry.name);
+                        return Ok(None);
+                    }
+                    // Only the listing went through links; open it as browsed.
+                    Err(_) => {}
+                }
+            }
+            let selection = FileSelection {
+                path: via.clone().unwrap_or_else(|| browsed.clone()),
+                source,
+            };
+            match self.inspect(&selection) {
+                Ok(FileCheck::Ok) => {
+                    self.status = match via {
+                        Some(_) => format!(
+                            "Selected file: {} ({}, through the symlink {})",
+                            selection.path.display(),
+                            self.backend_label(),
+                            browsed.display()
+                        ),
+                        None => format!("Selected file: {} ({})", selection.path.display(), self.backend_label()),
+                    };
+                    Ok(Some(selection))
+                }
+                Ok(FileCheck::Binary) => {
+                    self.status = format!("Refusing to open {}: it contains binary data", selection.path.display());
+                    Ok(None)
+                }
+                Ok(FileCheck::Utf16) => {
+                    self.status = format!("Can't open {}: it is UTF-16 text; re-save it as UTF-8", selection.path.display());
+                    Ok(None)
+                }
+                Ok(FileCheck::Suspicious(reasons)) => {
+                    self.mode = PickerMode::ConfirmOpen { selection, reasons };
+                    Ok(None)
+                }
+                Err(err) => {
+                    self.status = format!("Failed to inspect {}: {}", selection.path.display(), err);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Where `s` would go while choosing a directory: the highlighted one, or the current
+    /// one when `..` or a file is highlighted.
+    fn directory_target(&self) -> PathBuf {
+        match self.highlighted() {
+            Some(entry) if entry.is_dir => self.cwd.join(&entry.name),
+            _ => self.cwd.clone(),
+        }
+    }
+
+    fn select_directory(&mut self) -> Picked {
+        let selection = FileSelection {
+            path: self.directory_target(),
+            source: self.current_source(),
+        };
+        self.directory_purpose = None;
+        self.status = format!("Selected directory: {} ({})", selection.path.display(), self.backend_label());
+        Picked::Directory(Some(selection))
+    }
+
+    /// Backspace/Left (or Enter on `..`): up one directory, with the one just left selected.
+    fn go_to_parent(&mut self) -> io::Result<()> {
+        let Some(parent) = self.cwd.parent().map(|p| p.to_path_buf()) else {
+            self.status = "Already at the top directory".to_string();
+            return Ok(());
+        };
+        let left = self.cwd.file_name().map(|n| n.to_string_lossy().into_owned());
+        self.remember_cursor();
+        self.cwd = parent;
+        self.status.clear();
+        self.refresh_entries_selecting(left)
+    }
+
+    /// Where `anchor` is on the current backend, or why there is nowhere to go.
+    fn anchor_dir(&mut self, anchor: Anchor) -> Result<PathBuf, String> {
+        match (anchor, &self.backend) {
+            (Anchor::Home, PickerBackend::Local) => local_home().ok_or_else(|| String::from("No home directory: HOME is unset")),
+            (Anchor::Home, PickerBackend::Remote(_)) => {
+                if let Some(home) = &self.remote_home {
+                    return Ok(home.clone());
+                }
+                let home = self.current_source().resolve(Path::new(".")).map_err(|e| format!("No home directory: {e}"))?;
+                self.remote_home = Some(home.clone());
+                Ok(home)
+            }
+            (Anchor::Missions, PickerBackend::Local) => {
+                self.missions_root.clone().ok_or_else(|| String::from("No missions root: set missions_root in the config"))
+            }
+            (Anchor::Missions, PickerBackend::Remote(_)) => self
+                .remote_missions_root
+                .clone()
+                .ok_or_else(|| String::from("No missions root on the server: set missions_root under [ssh] in the config")),
+        }
+    }
+
+    /// `~` and `M`: straight to the home directory or the missions root. One that isn't
+    /// there leaves the picker where it is.
+    fn go_to_anchor(&mut self, anchor: Anchor) -> io::Result<()> {
+        let dir = match self.anchor_dir(anchor) {
+            Ok(dir) => dir,
+            Err(err) => {
+                self.status = err;
+                return Ok(());
+            }
+        };
+        let found = match &self.backend {
+            PickerBackend::Local => dir.is_dir(),
+            PickerBackend::Remote(_) => self.current_source().exists(&dir).unwrap_or(false),
+        };
+        if !found {
+            self.status = format!("The {} {} isn't a directory", anchor.label(), dir.display());
+            return Ok(());
+        }
+        self.remember_cursor();
+        self.cwd = dir;
+        self.links.clear();
+        self.status = format!("In the {}", anchor.label());
+        self.refresh_entries()
+    }
+
+    /// The anchor the current directory is inside, the missions root before home.
+    fn anchor_here(&self) -> Option<Anchor> {
+        let (home, missions) = match self.backend {
+            PickerBackend::Local => (local_home(), self.missions_root.as_ref()),
+            PickerBackend::Remote(_) => (self.remote_home.clone(), self.remote_missions_root.as_ref()),
+        };
+        if missions.is_some_and(|dir| self.cwd.starts_with(dir)) {
+            Some(Anchor::Missions)
+        } else if home.is_some_and(|dir| self.cwd.starts_with(dir)) {
+            Some(Anchor::Home)
+        } else {
+            None
+        }
+    }
+
+    fn highlighted(&self) -> Option<&Entry> {
+        self.state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .filter(|e| e.name != "..")
+    }
+
+    fn start_rename(&mut self) {
+        let Some(entry) = self.highlighted() else {
+            return;
+        };
+        let name = entry.name.clone();
+        if self.is_open_in_editor(&self.cwd.join(&name)) {
+            self.status = format!("{name} is open in the editor; close it first");
+            return;
+        }
+        self.mode = PickerMode::Rename {
+            from: name.clone(),
+            input: name,
+            cursor: None,
+        };
+    }
+
+    fn rename_entry(&mut self, from: &str, to: &str) -> io::Result<()> {
+        if to.is_empty() || to == from {
+            self.status = "Rename cancelled".to_string();
+            return Ok(());
+        }
+        if to.contains('/') || to.contains('\\') || to == "." || to == ".." {
+            self.status = format!("'{to}' isn't a plain file name; renaming only works within this directory");
+            return Ok(());
         }
+        let source = self.current_source();
+        let (old, new) = (self.cwd.join(from), self.cwd.join(to));
+        let result = source.exists(&new).and_then(|taken| {
+            if taken {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{to} already exists")));
+            }
+            source.rename(&old, &new)
+        });
+        match result {
+            Ok(()) => {
+                self.forget_marks(&old);
+                self.refresh_entries_selecting(Some(to.to_string()))?;
+                self.status = format!("Renamed {from} to {to}");
+            }
+            Err(err) => self.status = format!("Rename failed: {err}"),
+        }
+        Ok(())
+    }
+
+    fn start_delete(&mut self) {
+        let Some(entry) = self.highlighted() else {
+            return;
+        };
+        let path = self.cwd.join(&entry.name);
+        // A linked directory is unlinked, not emptied.
+        let is_dir = entry.is_dir && !entry.link;
+        if self.is_open_in_editor(&path) {
+            self.status = format!("{} is open in the editor; close it first", entry.name);
+            return;
+        }
+        self.mode = PickerMode::ConfirmDelete { path, is_dir };
+    }
+
+    fn delete_entry(&mut self, path: &Path, is_dir: bool) -> io::Result<()> {
+        // Stay at the same place in the list rather than jumping back to the top.
+        let selected = self.state.selected().unwrap_or(0);
+        let neighbour = self
+            .entries
+            .get(selected + 1)
+            .or_else(|| self.entries.get(selected.wrapping_sub(1)))
+            .map(|e| e.name.clone());
+        match self.current_source().remove(path, is_dir) {
+            Ok(()) => {
+                self.forget_marks(path);
+                self.refresh_entries_selecting(neighbour)?;
+                self.status = format!("Deleted {}", path.display());
+            }
+            Err(err) if is_dir => self.status = format!("Can't delete {} (only empty directories): {err}", path.display()),
+            Err(err) => self.status = format!("Delete failed: {err}"),
+        }
+        Ok(())
+    }
+
+    fn is_open_in_editor(&self, path: &Path) -> bool {
+        let source = self.current_source();
+        self.open_file.as_ref().is_some_and(|f| f.path == path && f.source.same_as(&source))
+    }
+
+    /// Drops marks on a file that was renamed or deleted.
+    fn forget_marks(&mut self, path: &Path) {
+        let source = self.current_source();
+        self.marks.retain(|m| !(m.path == path && m.source.same_as(&source)));
+    }
+
+    fn highlighted_is_dir(&self) -> bool {
+        self.state.selected().and_then(|i| self.entries.get(i)).is_some_and(|e| e.is_dir)
+    }
+
+    /// Space on a file marks it, Space again unmarks it. Marks stay while browsing other
+    /// directories, so files from `db/` and `custom/` can be opened or compared together.
+    fn toggle_mark(&mut self) {
+        let Some(entry) = self.state.selected().and_then(|i| self.entries.get(i)) else {
+            return;
+        };
+        if entry.is_dir {
+            self.status = "Only files can be marked".to_string();
+            return;
+        }
+        let selection = FileSelection {
+            path: self.cwd.join(&entry.name),
+            source: self.current_source(),
+        };
+        if let Some(pos) = self.mark_of(&selection) {
+            self.marks.remove(pos);
+        } else {
+            self.marks.push(selection);
+        }
+        self.status = match self.marks.len() {
+            0 => String::from("No files marked"),
+            2 => String::from("2 files marked; Enter or = compares them (A, then B), Esc clears"),
+            n => format!("{n} marked; Enter on a file opens them one after another, Esc clears"),
+        };
+    }
+
+    fn mark_of(&self, selection: &FileSelection) -> Option<usize> {
+        self.marks
+            .iter()
+            .position(|m| m.path == selection.path && m.source.same_as(&selection.source))
+    }
+
+    fn request_compare(&mut self) -> Option<Picked> {
+        match self.marks.as_slice() {
+            [a, b] => Some(Picked::Compare(a.clone(), b.clone())),
+            _ => {
+                self.status = "Mark exactly two files with Space (A, then B) before pressing =".to_string();
+                None
+            }
+        }
+    }
+
+    /// Enter with files marked: two XML files are compared, one is opened, more are
+    /// opened in marking order as the editor closes each. Other marked files are skipped.
+    fn open_marked(&mut self) -> Option<Picked> {
+        let (xml, other): (Vec<FileSelection>, Vec<FileSelection>) = self.marks.iter().cloned().partition(|m| {
+            m.path.extension().is_some_and(|e| e.eq_ignore_ascii_case("xml"))
+        });
+        let skipped = match other.len() {
+            0 => String::new(),
+            n => format!(" (skipped {n} marked non-XML file{})", if n == 1 { "" } else { "s" }),
+        };
+        let picked = match <[FileSelection; 2]>::try_from(xml) {
+            Ok([a, b]) => Picked::Compare(a, b),
+            Err(xml) if xml.is_empty() => {
+                self.status = format!("None of the marked files is an .xml file{skipped}");
+                return None;
+            }
+            Err(mut xml) if xml.len() == 1 => Picked::Open(xml.remove(0)),
+            Err(xml) => Picked::Several(xml),
+        };
+        self.status = match &picked {
+            Picked::Several(files) => format!("Opening {} marked files one after another{skipped}", files.len()),
+            _ => format!("Opening marked files{skipped}"),
+        };
+        self.marks.clear();
+        Some(picked)
+    }
+
+    /// Looks at the size and first bytes of a file (locally or over SFTP) before committing to a full read.
+    fn inspect(&self, selection: &FileSelection) -> io::Result<FileCheck> {
+        let (size, prefix) = selection.source.read_prefix(&selection.path, sniff::SNIFF_BYTES)?;
+        Ok(sniff::check_file(&selection.path, size, &prefix, self.large_file_bytes))
     }
 
     fn jump(&mut self, delta: isize) {
@@ -308,25 +1140,58 @@ impl FilePicker {
                 if entry.is_dir && entry.name != ".." {
                     label.push('/');
                 }
+                if entry.link {
+                    label.push('@');
+                }
+                let here = FileSelection {
+                    path: self.cwd.join(&entry.name),
+                    source: self.current_source(),
+                };
+                if !self.marks.is_empty() && !entry.is_dir {
+                    let marker = if self.mark_of(&here).is_some() { "■ " } else { "□ " };
+                    label.insert_str(0, marker);
+                }
                 ListItem::new(label)
             })
             .collect();
 
+        let title = match &self.listing {
+            Some(_) => format!("File Picker (loading… {} entries, Esc stops)", self.listed()),
+            None => String::from("File Picker"),
+        };
         let list = List::new(items)
-            .block(Block::default().title("File Picker").borders(Borders::ALL))
+            .block(Block::default().title(title).borders(Borders::ALL))
             .highlight_symbol("▶ ")
             .highlight_style(Style::default().add_modifier(Modifier::BOLD));
-        f.render_stateful_widget(list, chunks[1], &mut self.state);
-
-        let footer_text = format!(
-            "Help: ? | Remote: r | Quit: q | Source: {} | Status: {}",
-            self.backend_label(),
-            if self.status.is_empty() {
-                "No file selected"
-            } else {
-                &self.status
-            }
-        );
+        if let Some(preview) = &self.preview {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(chunks[1]);
+            f.render_stateful_widget(list, columns[0], &mut self.state);
+            let pane = Paragraph::new(preview.text())
+                .block(Block::default().title("Preview (p: hide)").borders(Borders::ALL))
+                .wrap(Wrap { trim: false });
+            f.render_widget(pane, columns[1]);
+        } else {
+            f.render_stateful_widget(list, chunks[1], &mut self.state);
+        }
+
+        let status = if self.status.is_empty() { "No file selected" } else { &self.status };
+        let footer_text = match &self.directory_purpose {
+            Some(purpose) => format!(
+                "Directory for {purpose}: s selects {} | Enter: open directory | Esc: cancel | Source: {} | Status: {status}",
+                self.directory_target().display(),
+                self.backend_label(),
+            ),
+            None => format!(
+                "Help: ? | Remote: r | Mark: Space ({}) | Hidden: {} (H) | Quit: q | Source: {}{} | Status: {status}",
+                self.marks.len(),
+                if self.show_hidden { "on" } else { "off" },
+                self.backend_label(),
+                self.anchor_here().map(|anchor| format!(" | In: {}", anchor.label())).unwrap_or_default(),
+            ),
+        };
         let footer = Paragraph::new(footer_text)
             .block(Block::default().borders(Borders::ALL).title("Status"))
             .wrap(Wrap { trim: true });
@@ -335,29 +1200,59 @@ impl FilePicker {
         if show_help {
             render_help_overlay(f);
         }
-        if let PickerMode::RemotePrompt(form) = &self.mode {
-            render_remote_prompt(f, form);
+        match &self.mode {
+            PickerMode::RemotePrompt(form) => render_remote_prompt(f, form),
+            PickerMode::RemoteMenu { cursor } => {
+                let host = self.remote_config.as_ref().map(|c| format!("{}@{}", c.username, c.host)).unwrap_or_default();
+                render_remote_menu(f, &host, *cursor, self.open_file_note());
+            }
+            PickerMode::ConfirmOpen { selection, reasons } => render_confirm_open(f, selection, reasons),
+            PickerMode::Rename { from, input, cursor } => render_rename_prompt(f, from, input, *cursor),
+            PickerMode::ConfirmDelete { path, is_dir } => render_confirm_delete(f, path, *is_dir),
+            PickerMode::OpenNext(selection) => render_open_next(f, selection, self.open_queue.len()),
+            PickerMode::Browse => {}
         }
     }
 
     fn start_remote_prompt(&mut self) {
-        let defaults = self.remote_config.clone().unwrap_or_else(|| RemoteConfig {
-            host: String::new(),
-            port: 22,
-            username: std::env::var("USER").unwrap_or_default(),
-            password: None,
-            key_path: None,
-            passphrase: None,
-        });
-        self.mode = PickerMode::RemotePrompt(RemoteForm {
-            host: defaults.host,
-            user: defaults.username,
-            port: defaults.port.to_string(),
-            password: defaults.password.unwrap_or_default(),
-            key_path: defaults.key_path.unwrap_or_default().to_string_lossy().to_string(),
-            passphrase: defaults.passphrase.unwrap_or_default(),
-            field_index: 0,
-        });
+        self.mode = PickerMode::RemotePrompt(RemoteForm::new(self.remote_config.clone()));
+    }
+
+    fn choose_remote(&mut self, choice: RemoteChoice) -> io::Result<()> {
+        match choice {
+            RemoteChoice::Disconnect => {
+                self.remember_cursor();
+                self.backend = PickerBackend::Local;
+                self.cwd = self.local_root.clone();
+                self.links.clear();
+                self.status = match self.open_file_note() {
+                    Some(note) => format!("Switched to local; {note}"),
+                    None => "Switched to local".to_string(),
+                };
+                self.refresh_entries()?;
+            }
+            RemoteChoice::Reconnect => match self.remote_config.clone() {
+                Some(cfg) => self.start_connect(cfg, Some(self.cwd.clone())),
+                None => self.status = "No connection details to reconnect with".to_string(),
+            },
+            // The old session stays in use until the new one is up.
+            RemoteChoice::Switch => self.start_remote_prompt(),
+        }
+        Ok(())
+    }
+
+    /// A warning when the file open in the editor came over the current SSH session.
+    fn open_file_note(&self) -> Option<String> {
+        let selection = self.open_file.as_ref().filter(|s| matches!(s.source, FileSource::Remote(_)))?;
+        Some(format!(
+            "{} was opened over the old connection: reload it or save it again once reconnected",
+            selection.short_label()
+        ))
+    }
+
+    /// Profile the SSH form starts from, e.g. after `:setup` wrote a new one.
+    pub fn set_remote_config(&mut self, remote_config: Option<RemoteConfig>) {
+        self.remote_config = remote_config;
     }
 
     fn backend_label(&self) -> &'static str {
@@ -374,60 +1269,544 @@ impl FilePicker {
         }
     }
 
-    fn try_connect(&mut self, cfg: RemoteConfig) -> io::Result<bool> {
-        match SshBackend::connect(&cfg) {
+    fn start_connect(&mut self, cfg: RemoteConfig, resume_dir: Option<PathBuf>) {
+        self.status = format!("Connecting to {}@{}:{}...", cfg.username, cfg.host, cfg.port);
+        let rx = SshBackend::connect_in_background(cfg.clone());
+        self.pending_connect = Some((cfg, rx, resume_dir));
+    }
+
+    /// Connects with `cfg` and, once in, offers `path` through `take_connected_open`;
+    /// the picker shows its directory.
+    pub fn open_remote(&mut self, cfg: RemoteConfig, path: PathBuf) {
+        self.start_connect(cfg, path.parent().map(Path::to_path_buf));
+        self.open_after_connect = Some(path);
+    }
+
+    /// The file `open_remote` asked for, once its connection is made.
+    pub fn take_connected_open(&mut self) -> Option<FileSelection> {
+        self.connected_open.take()
+    }
+
+    /// The highlighted `.xml` file to preview, or what to show instead.
+    fn preview_target(&self) -> Result<PathBuf, String> {
+        let entry = self.highlighted().ok_or_else(String::new)?;
+        if entry.is_dir {
+            return Err(format!("{}/ is a directory", entry.name));
+        }
+        if !Path::new(&entry.name).extension().is_some_and(|e| e.eq_ignore_ascii_case("xml")) {
+            return Err(String::from("Only .xml files are previewed"));
+        }
+        Ok(self.cwd.join(&entry.name))
+    }
+
+    /// Polls background work; called once per main loop iteration.
+    pub fn tick(&mut self) -> io::Result<()> {
+        self.poll_listing();
+        if self.preview.is_some() {
+            let target = self.preview_target();
+            let source = self.current_source();
+            if let Some(preview) = &mut self.preview {
+                preview.follow(target);
+                preview.tick(&source);
+            }
+        }
+        let Some((_, rx, _)) = &self.pending_connect else {
+            return Ok(());
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return Ok(()),
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("connection worker exited")),
+        };
+        let Some((cfg, _, resume_dir)) = self.pending_connect.take() else {
+            return Ok(());
+        };
+        match result {
             Ok(client) => {
+                let note = match self.backend {
+                    PickerBackend::Remote(_) => self.open_file_note().map(|note| format!("; {note}")).unwrap_or_default(),
+                    PickerBackend::Local => String::new(),
+                };
+                self.remember_cursor();
+                // Replacing the backend drops the old session.
                 self.backend = PickerBackend::Remote(client);
-                self.cwd = PathBuf::from("/");
-                self.status = "Connected via SSH".to_string();
+                self.remote_home = None;
+                self.cwd = resume_dir.unwrap_or_else(|| PathBuf::from("/"));
+                self.links.clear();
+                self.status = format!("Connected via SSH to {}{note}", cfg.host);
                 self.remote_config = Some(cfg);
-                Ok(true)
+                if let Some(path) = self.open_after_connect.take() {
+                    self.connected_open = Some(FileSelection { path, source: self.current_source() });
+                }
+                self.refresh_entries()?;
+            }
+            Err(err) if matches!(self.backend, PickerBackend::Remote(_)) => {
+                self.status = format!("SSH connect failed: {err}; the previous connection is still in use");
             }
             Err(err) => {
-                self.status = format!("SSH connect failed: {err}");
-                Ok(false)
+                self.status = match self.open_after_connect.take() {
+                    Some(path) => format!("SSH connect failed: {err}; {} was not opened", path.display()),
+                    None => format!("SSH connect failed: {err}"),
+                };
             }
         }
+        Ok(())
     }
 
     pub fn is_prompt(&self) -> bool {
-        matches!(self.mode, PickerMode::RemotePrompt(_))
+        !matches!(self.mode, PickerMode::Browse)
     }
 }
 
 fn render_help_overlay<B: tui::backend::Backend>(f: &mut tui::Frame<B>) {
     let area = utils::centered_rect(70, 70, f.size());
-    let text = "File Picker Help\n\n- Up/Down or j/k: move\n- PgUp/PgDown: jump lists\n- Enter: open directory/select file\n- r: toggle SSH (enter host/user/port/password/key); r again returns to local\n- q: quit\n- ?: toggle this help";
+    let text = "File Picker Help\n\n- Up/Down or j/k: move\n- PgUp/PgDown: jump lists\n- Enter: open directory/select file\n- s: when asked for a directory, choose the highlighted one (or the current one on `..` or a file); Esc cancels\n- Backspace/Left or h: up one directory\n- Esc while a large directory is still loading: stop and keep the entries read so far\n- H: show/hide dot files and directories\n- ~: home directory (the login directory over SSH); M: missions root (missions_root in the config, under [ssh] for the server)\n- p: show/hide a preview of the highlighted .xml file\n- F2 or R: rename the highlighted entry\n- Delete or D: delete the highlighted file (directories only when empty), after confirming\n- r: connect via SSH (enter host/user/port/password/key); r while connected offers disconnect, reconnect or switching server\n- Space: mark files, in any directory (local or SSH); Enter on a file opens the marked .xml files (two are compared as A and B, more open one after another); = compares two marked files; Esc clears the marks\n- q: quit\n- ?: toggle this help";
     let block = Block::default().title("Help").borders(Borders::ALL);
     let help = Paragraph::new(text).wrap(Wrap { trim: true }).block(block);
     f.render_widget(Clear, area);
     f.render_widget(help, area);
 }
 
+fn render_confirm_open<B: tui::backend::Backend>(f: &mut tui::Frame<B>, selection: &FileSelection, reasons: &[String]) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let text = format!(
+        "{}\n\n{}\n\nOpen anyway? Enter/y to open, Esc/n to cancel",
+        selection.path.display(),
+        reasons.iter().map(|r| format!("- {r}")).collect::<Vec<_>>().join("\n")
+    );
+    let block = Block::default().title("Open file?").borders(Borders::ALL);
+    let prompt = Paragraph::new(text).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_open_next<B: tui::backend::Backend>(f: &mut tui::Frame<B>, selection: &FileSelection, queued: usize) {
+    let area = utils::centered_rect(60, 35, f.size());
+    let text = format!(
+        "Next marked file ({queued} more after it):\n\n{}\n\nEnter/y to open it, n to skip it, Esc to stop opening marked files",
+        selection.path.display()
+    );
+    let block = Block::default().title("Open next?").borders(Borders::ALL);
+    let prompt = Paragraph::new(text).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_rename_prompt<B: tui::backend::Backend>(f: &mut tui::Frame<B>, from: &str, input: &str, cursor: Option<usize>) {
+    let area = utils::centered_rect(60, 35, f.size());
+    let text = format!("{from}\n\nNew name: {}\n\nEnter: rename, Esc: cancel", line_edit::view(input, cursor, usize::MAX));
+    let block = Block::default().title("Rename").borders(Borders::ALL);
+    let prompt = Paragraph::new(text).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_confirm_delete<B: tui::backend::Backend>(f: &mut tui::Frame<B>, path: &Path, is_dir: bool) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let what = if is_dir { "the directory (only if empty)" } else { "the file" };
+    let text = format!(
+        "Delete {what}\n\n{}\n\nThis can't be undone. Enter/y to delete, Esc/n to cancel",
+        path.display()
+    );
+    let block = Block::default().title("Delete?").borders(Borders::ALL);
+    let prompt = Paragraph::new(text).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
+fn render_remote_menu<B: tui::backend::Backend>(f: &mut tui::Frame<B>, host: &str, cursor: usize, note: Option<String>) {
+    let area = utils::centered_rect(60, 40, f.size());
+    let mut lines = vec![format!("Connected to {host}"), String::new()];
+    lines.extend(REMOTE_CHOICES.iter().enumerate().map(|(i, choice)| {
+        let marker = if i == cursor { ">" } else { " " };
+        let label = match choice {
+            RemoteChoice::Disconnect => "d: disconnect, back to local files",
+            RemoteChoice::Reconnect => "r: reconnect to the same host",
+            RemoteChoice::Switch => "s: switch server (connect form)",
+        };
+        format!("{marker} {label}")
+    }));
+    if let Some(note) = note {
+        lines.extend([String::new(), format!("Note: {note}")]);
+    }
+    lines.extend([String::new(), String::from("Up/Down and Enter, or the letter; Esc: stay connected")]);
+    let block = Block::default().title("SSH").borders(Borders::ALL);
+    let prompt = Paragraph::new(lines.join("\n")).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
+}
+
 fn render_remote_prompt<B: tui::backend::Backend>(f: &mut tui::Frame<B>, form: &RemoteForm) {
     let area = utils::centered_rect(70, 70, f.size());
-    let fields = [
-        ("Host", &form.host),
-        ("User", &form.user),
-        ("Port", &form.port),
-        ("Password (optional)", &form.password),
-        ("Key Path (optional)", &form.key_path),
-        ("Passphrase (optional)", &form.passphrase),
-    ];
-    let lines: Vec<String> = fields
-        .iter()
-        .enumerate()
-        .map(|(i, (label, value))| {
-            let marker = if i == form.field_index { ">" } else { " " };
-            format!("{marker} {label}: {value}")
-        })
-        .collect();
     let text = format!(
-        "Connect via SSH\nEnter details (leave password empty if using keys)\n\n{}\n\nEnter to connect, Esc to cancel",
-        lines.join("\n")
+        "Connect via SSH\nEnter details and pick how to log in under Auth\n\n{}\n\nEnter to connect, Esc to cancel\nTab/Up/Down: field, PgUp/PgDown: first/last, ←/→ on Auth: method, Home/End, Ctrl+U: clear, Ctrl+W: delete word",
+        form.lines().join("\n")
     );
     let block = Block::default().title("SSH Connect").borders(Borders::ALL);
     let prompt = Paragraph::new(text).wrap(Wrap { trim: false }).block(block);
     f.render_widget(Clear, area);
     f.render_widget(prompt, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{drive_picker, picker_key, render_picker, Fixture, SAMPLE};
+
+    fn names(picker: &FilePicker) -> Vec<&str> {
+        picker.entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn navigates_into_directories_and_back_up() {
+        let fixture = Fixture::new();
+        fixture.write("mission/db/types.xml", SAMPLE);
+        fixture.write("mission/notes.txt", "not xml");
+        fixture.write("readme.md", "");
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        assert_eq!(names(&picker), ["..", "mission", "readme.md"]);
+
+        let selected_name = |picker: &FilePicker| picker.entries[picker.state.selected().unwrap()].name.clone();
+        // Each directory opens on its first entry after `..`; directories come before files.
+        assert_eq!(selected_name(&picker), "mission");
+        assert!(drive_picker(&mut picker, [Action::Activate]).is_none());
+        assert_eq!(names(&picker), ["..", "db", "notes.txt"]);
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(picker.cwd, fixture.dir.join("mission/db"));
+
+        let Some(Picked::Open(selected)) = drive_picker(&mut picker, [Action::Activate]) else {
+            panic!("types.xml is picked");
+        };
+        assert_eq!(selected.path, fixture.dir.join("mission/db/types.xml"));
+
+        // Going up selects the directory just left.
+        drive_picker(&mut picker, [Action::Up, Action::Activate]);
+        assert_eq!(picker.cwd, fixture.dir.join("mission"));
+        assert_eq!(selected_name(&picker), "db");
+        drive_picker(&mut picker, [Action::Down, Action::Backspace]);
+        assert_eq!(picker.cwd, fixture.dir);
+        assert_eq!(selected_name(&picker), "mission");
+
+        // Coming back finds the cursor where it was left.
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(selected_name(&picker), "notes.txt");
+    }
+
+    #[test]
+    fn a_command_line_ssh_file_that_cannot_connect_is_named_in_the_status() {
+        let fixture = Fixture::new();
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        let uri = crate::remote::SshUri::parse("ssh://dayz@127.0.0.1:1/srv/dayz/db/types.xml").unwrap().unwrap();
+        picker.open_remote(uri.remote_config(None), uri.path);
+        assert_eq!(picker.status, "Connecting to dayz@127.0.0.1:1...");
+
+        let started = std::time::Instant::now();
+        while picker.pending_connect.is_some() && started.elapsed() < std::time::Duration::from_secs(10) {
+            picker.tick().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(picker.status.starts_with("SSH connect failed: "), "{}", picker.status);
+        assert!(picker.status.ends_with("; /srv/dayz/db/types.xml was not opened"), "{}", picker.status);
+        assert!(picker.take_connected_open().is_none());
+        assert_eq!(picker.cwd, fixture.dir, "the picker stays where it was");
+    }
+
+    #[test]
+    fn starts_in_default_dir_and_jumps_to_the_missions_root() {
+        let fixture = Fixture::new();
+        fixture.write("server/mpmissions/dayzOffline.chernarusplus/db/types.xml", SAMPLE);
+        fixture.write("elsewhere/readme.md", "");
+        let missions = fixture.dir.join("server/mpmissions");
+        let config = Config {
+            default_dir: Some(fixture.dir.join("elsewhere")),
+            missions_root: Some(missions.clone()),
+            ..Config::default()
+        };
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &config).unwrap();
+        assert_eq!(picker.cwd, fixture.dir.join("elsewhere"));
+        assert_eq!(picker.anchor_here(), None);
+
+        drive_picker(&mut picker, [Action::Input('M')]);
+        assert_eq!((picker.cwd.as_path(), picker.status.as_str()), (missions.as_path(), "In the missions root"));
+        drive_picker(&mut picker, [Action::Activate, Action::Activate]);
+        assert_eq!(picker.anchor_here(), Some(Anchor::Missions), "anywhere below it counts");
+
+        // A configured path that isn't there leaves the picker where it was.
+        let broken = Config {
+            default_dir: Some(fixture.dir.join("gone")),
+            missions_root: Some(fixture.dir.join("gone")),
+            ..Config::default()
+        };
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &broken).unwrap();
+        assert_eq!(picker.cwd, fixture.dir);
+        assert!(picker.status.starts_with("default_dir ") && picker.status.contains("isn't a directory"), "{}", picker.status);
+        drive_picker(&mut picker, [Action::Input('M')]);
+        assert_eq!(picker.cwd, fixture.dir);
+        assert!(picker.status.starts_with("The missions root "), "{}", picker.status);
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        drive_picker(&mut picker, [Action::Input('M')]);
+        assert_eq!(picker.status, "No missions root: set missions_root in the config");
+    }
+
+    #[test]
+    fn directory_mode_selects_with_s_and_still_navigates_with_enter() {
+        let fixture = Fixture::new();
+        fixture.write("mission/db/types.xml", SAMPLE);
+        fixture.write("types.xml", SAMPLE);
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        let chosen = |picked: Option<Picked>| match picked {
+            Some(Picked::Directory(selection)) => selection.map(|s| s.path),
+            _ => panic!("expected a directory answer"),
+        };
+
+        // `s` arrives as `Save`, the way the main loop maps it.
+        let select = picker_key(&picker, 's');
+        picker.choose_directory("the export");
+        assert_eq!(chosen(drive_picker(&mut picker, [select.clone()])), Some(fixture.dir.join("mission")));
+
+        picker.choose_directory("the export");
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(picker.cwd, fixture.dir.join("mission"));
+        drive_picker(&mut picker, [Action::Up]);
+        assert_eq!(chosen(drive_picker(&mut picker, [select.clone()])), Some(fixture.dir.join("mission")), "`..` means here");
+
+        picker.choose_directory("the export");
+        drive_picker(&mut picker, [Action::Backspace, Action::Down, Action::Activate]);
+        assert_eq!(picker.status, "That's a file; s selects the directory");
+        assert_eq!(chosen(drive_picker(&mut picker, [Action::Cancel])), None);
+        // Back to picking files.
+        assert!(drive_picker(&mut picker, [select]).is_none());
+        assert!(matches!(drive_picker(&mut picker, [Action::Activate]), Some(Picked::Open(_))));
+    }
+
+    #[test]
+    fn remote_menu_switches_through_a_prefilled_form() {
+        let fixture = Fixture::new();
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        picker.remote_config = RemoteConfig::from_profile(&crate::config::SshLayer {
+            host: Some(String::from("old.example.org")),
+            user: Some(String::from("dayz")),
+            ..Default::default()
+        });
+        picker.mode = PickerMode::RemoteMenu { cursor: 0 };
+        drive_picker(&mut picker, [Action::Down, Action::Down, Action::Down, Action::Activate]);
+        let PickerMode::RemotePrompt(form) = &picker.mode else {
+            panic!("switching opens the connect form");
+        };
+        assert!(form.lines().iter().any(|l| l.contains("old.example.org")), "{:?}", form.lines());
+
+        picker.mode = PickerMode::RemoteMenu { cursor: 2 };
+        drive_picker(&mut picker, [Action::Cancel]);
+        assert!(matches!(picker.mode, PickerMode::Browse));
+        drive_picker(&mut picker, [Action::ToggleRemote]);
+        assert!(matches!(picker.mode, PickerMode::RemotePrompt(_)), "r on local files asks for a connection");
+        picker.mode = PickerMode::RemoteMenu { cursor: 0 };
+        drive_picker(&mut picker, [Action::Input('d')]);
+        assert_eq!(picker.status, "Switched to local");
+    }
+
+    #[test]
+    fn connect_form_edits_inside_a_field_and_jumps_to_the_last() {
+        let mut form = RemoteForm::new(Some(RemoteConfig {
+            host: String::from("old.example.org"),
+            port: 22,
+            username: String::from("dayz"),
+            password: None,
+            key_path: Some(PathBuf::from("/home/alex/.ssh/id_rsa")),
+            passphrase: None,
+            auth: AuthMethod::Key,
+            readonly: false,
+        }));
+        for action in [Action::DeleteWord, Action::Input('n'), Action::Input('e'), Action::Input('w'), Action::Home, Action::Input('x')] {
+            assert!(form.edit(&action));
+        }
+        assert_eq!(form.host, "xnew");
+        assert_eq!(form.lines()[0], "> Host: x▏new");
+        form.edit(&Action::PgDown);
+        form.edit(&Action::Up);
+        form.edit(&Action::ClearLine);
+        form.edit(&Action::Input('~'));
+        assert_eq!((form.key_path.as_str(), form.lines()[5].as_str()), ("~", "> Key Path: ~"));
+        form.edit(&Action::PgUp);
+        form.edit(&Action::End);
+        form.edit(&Action::Input('!'));
+        assert_eq!(form.host, "xnew!");
+        assert!(!form.edit(&Action::Activate));
+    }
+
+    #[test]
+    fn connect_form_picks_the_auth_method_and_tells_what_the_agent_holds() {
+        let mut form = RemoteForm::new(None);
+        form.host = String::from("dayz.example.org");
+        for _ in 0..AUTH_ROW {
+            form.edit(&Action::Down);
+        }
+        assert!(form.edit(&Action::Right));
+        assert_eq!((form.auth, form.agent.is_none()), (AuthMethod::Key, true));
+        assert!(!form.edit(&Action::Input('x')), "the method isn't typed");
+        let lines = form.lines();
+        assert_eq!(lines[3], "> Auth: agent [key file] password  (←/→ to change)");
+        assert_eq!(lines[4], "  Password:   (not used)");
+        assert_eq!(lines[6], "  Passphrase:   (optional)");
+        form.edit(&Action::Left);
+        form.edit(&Action::Left);
+        assert_eq!(form.to_config().auth, AuthMethod::Password);
+
+        // With the agent, the line under Auth says what it offers or why it can't be asked.
+        form.auth = AuthMethod::Agent;
+        form.agent = Some(Ok(vec![String::from("alex@laptop"), String::from("deploy")]));
+        assert_eq!(form.lines()[4], "    The agent holds 2 keys: alex@laptop, deploy");
+        form.agent = Some(Err(String::from("SSH agent: SSH_AUTH_SOCK is not set, so there is no agent to ask")));
+        assert_eq!(form.lines()[4], "    SSH agent: SSH_AUTH_SOCK is not set, so there is no agent to ask");
+        assert_eq!(form.lines().len(), FORM_ROWS + 1);
+    }
+
+    #[test]
+    fn suspicious_files_ask_before_opening() {
+        let fixture = Fixture::new();
+        fixture.write("notes.txt", "just some text");
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        assert!(drive_picker(&mut picker, [Action::Activate]).is_none());
+        assert!(matches!(picker.mode, PickerMode::ConfirmOpen { .. }));
+
+        assert!(drive_picker(&mut picker, [Action::Input('n')]).is_none());
+        assert_eq!(picker.status, "Open cancelled");
+        let opened = drive_picker(&mut picker, [Action::Activate, Action::Input('y')]);
+        assert!(matches!(opened, Some(Picked::Open(s)) if s.path == fixture.dir.join("notes.txt")));
+    }
+
+    #[test]
+    fn utf16_files_are_named_instead_of_called_binary() {
+        let fixture = Fixture::new();
+        let utf16: Vec<u8> = format!("\u{feff}{SAMPLE}").encode_utf16().flat_map(u16::to_le_bytes).collect();
+        std::fs::write(fixture.dir.join("types.xml"), utf16).unwrap();
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        assert!(drive_picker(&mut picker, [Action::Activate]).is_none());
+        assert_eq!(
+            picker.status,
+            format!("Can't open {}: it is UTF-16 text; re-save it as UTF-8", fixture.dir.join("types.xml").display())
+        );
+    }
+
+    #[test]
+    fn marks_survive_navigation_and_open_together() {
+        let fixture = Fixture::new();
+        fixture.write("db/types.xml", SAMPLE);
+        fixture.write("custom/extra.xml", SAMPLE);
+        fixture.write("custom/more.xml", SAMPLE);
+        fixture.write("custom/notes.txt", "");
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        assert_eq!(names(&picker), ["..", "custom", "db"]);
+
+        // custom/: mark extra.xml and notes.txt; db/: mark types.xml.
+        drive_picker(&mut picker, [Action::Activate, Action::ToggleSelect, Action::Down, Action::Down, Action::ToggleSelect]);
+        drive_picker(&mut picker, [Action::Backspace, Action::Down, Action::Activate, Action::ToggleSelect]);
+        assert_eq!(picker.marks.len(), 3);
+
+        // Two XML files among the marks: Enter compares them in marking order.
+        let picked = drive_picker(&mut picker, [Action::Activate]);
+        let Some(Picked::Compare(a, b)) = picked else {
+            panic!("two marked XML files are compared");
+        };
+        assert_eq!(a.path, fixture.dir.join("custom/extra.xml"));
+        assert_eq!(b.path, fixture.dir.join("db/types.xml"));
+        assert!(picker.status.contains("skipped 1"), "{}", picker.status);
+        assert!(picker.marks.is_empty());
+
+        // Three: the first opens, the rest are offered one at a time as the editor closes.
+        drive_picker(&mut picker, [Action::ToggleSelect, Action::Backspace, Action::Up, Action::Activate]);
+        // Back in custom/ the cursor is where it was left, on notes.txt.
+        drive_picker(&mut picker, [Action::Up, Action::ToggleSelect, Action::Up, Action::ToggleSelect]);
+        let Some(Picked::Several(mut files)) = drive_picker(&mut picker, [Action::Activate]) else {
+            panic!("three marked files open one after another");
+        };
+        assert_eq!(files.len(), 3);
+        let first = files.remove(0);
+        assert_eq!(first.path, fixture.dir.join("db/types.xml"));
+        picker.queue_files(files);
+        picker.set_open_file(Some(first));
+        picker.set_open_file(None);
+        let Some(Picked::Open(next)) = drive_picker(&mut picker, [Action::Activate]) else {
+            panic!("the next queued file is offered");
+        };
+        assert_eq!(next.path, fixture.dir.join("custom/more.xml"));
+        picker.set_open_file(None);
+        assert!(picker.is_prompt());
+        drive_picker(&mut picker, [Action::Cancel]);
+        assert!(!picker.is_prompt());
+        picker.set_open_file(None);
+        assert!(!picker.is_prompt(), "Esc drops the rest of the queue");
+    }
+
+    #[test]
+    fn esc_clears_marks() {
+        let fixture = Fixture::new();
+        fixture.write("a.xml", SAMPLE);
+        let mut picker = FilePicker::new(fixture.dir.clone(), None, &Config::default()).unwrap();
+        drive_picker(&mut picker, [Action::ToggleSelect]);
+        assert_eq!(picker.marks.len(), 1);
+        drive_picker(&mut picker, [Action::Cancel]);
+        assert!(picker.marks.is_empty());
+        assert_eq!(picker.status, "Marks cleared");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinked_directories_and_opens_files_under_their_target() {
+        use std::os::unix::fs::symlink;
+        let fixture = Fixture::new();
+        fixture.write("data/current/types.xml", SAMPLE);
+        fixture.write("mpmissions/.keep", "");
+        symlink(fixture.dir.join("data/current"), fixture.dir.join("mpmissions/chernarus")).unwrap();
+        symlink(".", fixture.dir.join("data/current/again")).unwrap();
+        symlink("gone.xml", fixture.dir.join("data/current/dangling.xml")).unwrap();
+        let real = std::fs::canonicalize(fixture.dir.join("data/current/types.xml")).unwrap();
+        let select = |picker: &mut FilePicker, name: &str| {
+            let idx = picker.entries.iter().position(|e| e.name == name).unwrap();
+            picker.state.select(Some(idx));
+        };
+
+        let mut picker = FilePicker::new(fixture.dir.join("mpmissions"), None, &Config::default()).unwrap();
+        assert!(render_picker(&mut picker).contains("chernarus/@"));
+        select(&mut picker, "chernarus");
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(picker.cwd, fixture.dir.join("mpmissions/chernarus"));
+        let screen = render_picker(&mut picker);
+        assert!(screen.contains("again/@") && screen.contains("dangling.xml@"), "{screen}");
+
+        select(&mut picker, "dangling.xml");
+        assert!(drive_picker(&mut picker, [Action::Activate]).is_none());
+        assert!(picker.status.starts_with("Can't follow the symlink dangling.xml:"), "{}", picker.status);
+        select(&mut picker, "types.xml");
+        let Some(Picked::Open(opened)) = drive_picker(&mut picker, [Action::Activate]) else {
+            panic!("types.xml opens through the link");
+        };
+        assert_eq!(opened.path, real);
+
+        // `again` points at its own directory: entering it over and over stops at the limit.
+        for _ in 1..MAX_LINK_DEPTH {
+            select(&mut picker, "again");
+            drive_picker(&mut picker, [Action::Activate]);
+        }
+        select(&mut picker, "again");
+        let deepest = picker.cwd.clone();
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(picker.cwd, deepest);
+        assert!(picker.status.contains("looks like a loop"), "{}", picker.status);
+        // Going back up makes room again.
+        drive_picker(&mut picker, [Action::Backspace]);
+        select(&mut picker, "again");
+        drive_picker(&mut picker, [Action::Activate]);
+        assert_eq!(picker.cwd, deepest);
+
+        let config = Config { resolve_symlinks: false, ..Config::default() };
+        let mut picker = FilePicker::new(fixture.dir.join("mpmissions"), None, &config).unwrap();
+        select(&mut picker, "chernarus");
+        drive_picker(&mut picker, [Action::Activate]);
+        select(&mut picker, "types.xml");
+        let Some(Picked::Open(opened)) = drive_picker(&mut picker, [Action::Activate]) else {
+            panic!("types.xml opens under the link");
+        };
+        assert_eq!(opened.path, fixture.dir.join("mpmissions/chernarus/types.xml"));
+    }
+}
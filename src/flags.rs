@@ -0,0 +1,109 @@
+use crate::{
+    model::{self, Field, FieldKey, TypeEntry},
+    validation,
+};
+
+/// The `<flags>` attributes, in the order vanilla files write them.
+pub const FLAGS: [&str; 6] = ["count_in_cargo", "count_in_hoarder", "count_in_map", "count_in_player", "crafted", "deloot"];
+
+/// The flags that make the CE count an item towards its nominal somewhere.
+const COUNTING_FLAGS: [&str; 4] = ["count_in_cargo", "count_in_hoarder", "count_in_map", "count_in_player"];
+
+/// The trimmed value of `flag` on the type's first `<flags>`, if it has one.
+pub fn flag_of<'a>(ty: &'a TypeEntry, flag: &str) -> Option<&'a str> {
+    ty.fields.iter().find_map(|f| match &f.key {
+        FieldKey::Attribute { element, index: 0, attr } if element == "flags" && attr == flag => Some(f.value.trim()),
+        _ => None,
+    })
+}
+
+/// What `default_flags` gives a new type.
+pub fn default_of(flag: &str) -> bool {
+    model::default_flags().iter().any(|f| matches!(&f.key, FieldKey::Attribute { attr, .. } if attr == flag) && f.value == "1")
+}
+
+/// The type has `<flags>` and every counting flag is 0 or left out (0 by default): the
+/// item counts towards no nominal, so the CE never stops spawning it.
+pub fn counts_nowhere(ty: &TypeEntry) -> bool {
+    ty.fields.iter().any(|f| f.key.get_element_name() == "flags")
+        && COUNTING_FLAGS.iter().all(|flag| flag_of(ty, flag).map_or(!default_of(flag), |v| v == "0"))
+}
+
+/// Gives a type without `<flags>` the defaults, after its numeric elements.
+pub fn insert_default_flags(ty: &mut TypeEntry) {
+    let position = ty
+        .fields
+        .iter()
+        .rposition(|f| matches!(&f.key, FieldKey::Element { name, .. } if validation::NUMERIC_ELEMENTS.contains(&name.as_str())))
+        .map(|p| p + 1)
+        .unwrap_or(0);
+    ty.fields.splice(position..position, model::default_flags());
+}
+
+/// What `set_flag` had to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagSet {
+    Set,
+    /// The type had no `<flags>`; it got the defaults first.
+    Created,
+    /// The type has no `<flags>` and `create` was off; it is unchanged.
+    Skipped,
+}
+
+/// Sets `flag` on the first `<flags>` to 1 or 0, adding the attribute as needed. A type
+/// without `<flags>` gets the default ones first when `create` is on.
+pub fn set_flag(ty: &mut TypeEntry, flag: &str, on: bool, create: bool) -> FlagSet {
+    let value = String::from(if on { "1" } else { "0" });
+    let on_flags = |f: &Field| matches!(&f.key, FieldKey::Attribute { element, index: 0, .. } if element == "flags");
+    let mut outcome = FlagSet::Set;
+    if !ty.fields.iter().any(on_flags) {
+        if !create {
+            return FlagSet::Skipped;
+        }
+        insert_default_flags(ty);
+        outcome = FlagSet::Created;
+    }
+    let key = FieldKey::Attribute {
+        element: String::from("flags"),
+        index: 0,
+        attr: flag.to_string(),
+    };
+    match ty.fields.iter_mut().find(|f| f.key == key) {
+        Some(field) => field.value = value,
+        None => {
+            let position = ty.fields.iter().rposition(on_flags).map_or(ty.fields.len(), |p| p + 1);
+            ty.fields.insert(position, Field { key, value });
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    #[test]
+    fn set_flag_edits_adds_and_creates_flags() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        let akm = &mut types[0];
+        assert_eq!(set_flag(akm, "count_in_map", false, true), FlagSet::Set);
+        set_flag(akm, "deloot", true, true);
+        assert_eq!((flag_of(akm, "count_in_map"), flag_of(akm, "deloot")), (Some("0"), Some("1")));
+        assert_eq!(akm.fields.iter().filter(|f| f.key.get_element_name() == "flags").count(), 3);
+        assert!(counts_nowhere(akm), "count_in_hoarder and count_in_player default to 0");
+        set_flag(akm, "count_in_player", true, true);
+        assert!(!counts_nowhere(akm));
+
+        let mut bare = TypeEntry { name: String::from("Bare"), fields: Vec::new() };
+        assert_eq!(set_flag(&mut bare, "crafted", true, false), FlagSet::Skipped);
+        assert!(bare.fields.is_empty());
+        assert_eq!(set_flag(&mut bare, "crafted", true, true), FlagSet::Created);
+        assert_eq!(bare.fields.len(), FLAGS.len());
+        assert_eq!(flag_of(&bare, "crafted"), Some("1"));
+        assert_eq!(flag_of(&bare, "count_in_map"), Some("1"));
+        assert!(!counts_nowhere(&bare));
+        set_flag(&mut bare, "count_in_map", false, true);
+        assert!(counts_nowhere(&bare));
+    }
+}
@@ -0,0 +1,82 @@
+/// Scores `candidate` against `query` as a case-insensitive subsequence; `None` if some
+/// query character is missing. Higher is better: consecutive runs, matches at the start
+/// or after `_`/a lower-to-upper case change, and shorter candidates score more.
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut total = 0;
+    let mut q = 0;
+    let mut previous_match: Option<usize> = None;
+    for (i, &c) in chars.iter().enumerate() {
+        if q == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(query[q])) {
+            continue;
+        }
+        let mut points = 1;
+        if previous_match == Some(i.wrapping_sub(1)) {
+            points += 5;
+        }
+        let boundary = i == 0
+            || chars[i - 1] == '_'
+            || (chars[i - 1].is_lowercase() && c.is_uppercase())
+            || (!chars[i - 1].is_ascii_digit() && c.is_ascii_digit());
+        if boundary {
+            points += 3;
+        }
+        total += points;
+        previous_match = Some(i);
+        q += 1;
+    }
+    (q == query.len()).then(|| total * 10 - chars.len() as i32)
+}
+
+/// Indices of the best `limit` matches, best first; ties keep their original order.
+pub fn top_matches<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>, limit: usize) -> Vec<usize> {
+    let mut scored: Vec<(i32, usize)> = candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, c)| score(query, c).map(|s| (s, i)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().take(limit).map(|(_, i)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsequence_is_required() {
+        assert!(score("akm", "AKM").is_some());
+        assert!(score("akm", "AK_Magazine").is_some());
+        assert!(score("akm", "AK74").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn consecutive_and_boundary_matches_rank_higher() {
+        let exact = score("m4a1", "M4A1").unwrap();
+        let scattered = score("m4a1", "Mag_M4A1_Green").unwrap();
+        let spread = score("m4a1", "Mosin9130_4xA1").unwrap();
+        assert!(exact > scattered, "{exact} vs {scattered}");
+        assert!(scattered > spread, "{scattered} vs {spread}");
+
+        // `pcs` hits the word starts of PlateCarrierSmall, not letters mid-word.
+        assert!(score("pcs", "PlateCarrierSmall").unwrap() > score("pcs", "Pipecutters").unwrap());
+    }
+
+    #[test]
+    fn top_matches_orders_best_first() {
+        let names = ["AKM", "AK101", "Mag_AKM_30Rnd", "AKS74U", "Apple"];
+        let found = top_matches("akm", names, 3);
+        assert_eq!(found[0], 0);
+        assert!(found.contains(&2));
+        assert!(!found.contains(&4));
+        assert!(top_matches("zzz", names, 3).is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    bulk::{self, BulkEdit},
+    cli::Format,
+    config::{Config, ConfigLayer, PROJECT_FILE},
+    diff::{self, TypesDiff},
+    field_help::{FieldHelp, HELP_FILE},
+    model::{self, FieldKey, ParseWarning, TypeEntry},
+    paths::AppDirs,
+    progress::Reporter,
+    remote::FileSource,
+    utils,
+    validation::{self, Issue, Severity},
+};
+
+/// One entry of `check --format json`, which prints an array of them. An issue is the
+/// editor's own `Issue` (`type_idx`, `field_idx`, `code`, `message`, as `validate_types_json`
+/// gives it) with `file`, `type`, `field` and `severity` added; `type` and `field` are null for
+/// problems with the type or the file as a whole. `severity` is `error` or `warning`; `code` is
+/// the check's name (`min-over-nominal`, see `IssueCode`), `parse-warning` for something the
+/// parser had to repair (with the `line` it is on, as the editor's load summary lists them),
+/// or `unreadable` for a file that doesn't read or parse. Neither has `type_idx` or
+/// `field_idx`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CheckRecord<'a> {
+    Issue {
+        file: &'a str,
+        #[serde(rename = "type")]
+        type_name: Option<&'a str>,
+        field: Option<String>,
+        severity: Severity,
+        #[serde(flatten)]
+        issue: &'a Issue,
+    },
+    Parse {
+        file: &'a str,
+        #[serde(rename = "type")]
+        type_name: Option<&'a str>,
+        field: Option<String>,
+        severity: Severity,
+        code: &'static str,
+        line: u64,
+        message: &'a str,
+    },
+    Unreadable {
+        file: &'a str,
+        #[serde(rename = "type")]
+        type_name: Option<&'a str>,
+        field: Option<String>,
+        severity: Severity,
+        code: &'static str,
+        message: &'a str,
+    },
+}
+
+/// `diff --format json`: the editor's `TypesDiff`, `{"added": [names], "removed": [names],
+/// "changed": [{"type", "field", "old", "new"}]}` with types matched by name, one record per
+/// changed field named as `check` names it, and `old` or `new` null where the field is absent
+/// on that side, plus `"unreadable": [{"file", "message"}]` for a file that doesn't read, when
+/// the rest is empty.
+#[derive(Debug, Serialize)]
+struct DiffOutput<'a> {
+    added: &'a [String],
+    removed: &'a [String],
+    changed: Vec<ChangedField<'a>>,
+    unreadable: &'a [Unreadable],
+}
+
+#[derive(Debug, Serialize)]
+struct ChangedField<'a> {
+    #[serde(rename = "type")]
+    type_name: &'a str,
+    field: String,
+    old: Option<&'a str>,
+    new: Option<&'a str>,
+}
+
+impl<'a> DiffOutput<'a> {
+    fn new(changes: &'a TypesDiff, unreadable: &'a [Unreadable]) -> Self {
+        let changed = changes
+            .changed
+            .iter()
+            .flat_map(|change| {
+                change.fields.iter().map(|field| ChangedField {
+                    type_name: &change.name,
+                    field: field_name(&field.key),
+                    old: field.old.as_deref(),
+                    new: field.new.as_deref(),
+                })
+            })
+            .collect();
+        Self { added: &changes.added, removed: &changes.removed, changed, unreadable }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Unreadable {
+    file: String,
+    message: String,
+}
+
+/// A field as the JSON output names it: `nominal`, `flags@deloot`, with `[n]` after the
+/// element for its second and later occurrences (`usage[1]@name`).
+fn field_name(key: &FieldKey) -> String {
+    let (element, index) = key.element_key();
+    let element = if index == 0 { element.to_string() } else { format!("{element}[{index}]") };
+    match key {
+        FieldKey::Element { .. } => element,
+        FieldKey::Attribute { attr, .. } => format!("{element}@{attr}"),
+    }
+}
+
+/// A file read the way the editor opens it: with its project config and field help, and
+/// `normalize_names` applied.
+struct Loaded {
+    types: Vec<TypeEntry>,
+    /// What the parser repaired, as the editor's load summary reports it.
+    warnings: Vec<ParseWarning>,
+    config: Config,
+    help: FieldHelp,
+}
+
+fn load(path: &Path, user: &ConfigLayer) -> Result<Loaded, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let (mut types, report) = model::parse_types(&content).map_err(|e| format!("XML parse error: {e}"))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let (project, note) = ConfigLayer::load_project(fs::read_to_string(dir.join(PROJECT_FILE)).ok().as_deref());
+    if let (None, Some(note)) = (&project, note) {
+        eprintln!("{}: {note}", path.display());
+    }
+    let config = Config::resolve(user, project.as_ref(), &ConfigLayer::default());
+    if config.normalize_names {
+        model::normalize_name_elements(&mut types);
+    }
+    let (help, notes) = AppDirs::locate().layered_help(fs::read_to_string(dir.join(HELP_FILE)).ok().as_deref());
+    for note in notes {
+        eprintln!("{}: {note}", path.display());
+    }
+    Ok(Loaded { types, warnings: report.warnings, config, help })
+}
+
+impl Loaded {
+    /// The editor's validation, with the same `[validation]` settings it would use.
+    fn issues(&self) -> Vec<Issue> {
+        let rules = validation::Rules {
+            strict: self.config.strict_validation,
+            help: &self.help,
+            require_flags: self.config.require_flags,
+            expected_cost: self.config.check_cost.then_some(self.config.expected_cost),
+        };
+        validation::validate(&self.types, rules)
+    }
+}
+
+/// A file and what validating it found, or why it couldn't be read.
+type Checked = Result<(Loaded, Vec<Issue>), String>;
+
+/// `check`: validates each file; returns the exit code, 2 when there are errors (or a file
+/// doesn't read), 1 for warnings only, 0 when every file is clean.
+pub fn check(paths: &[PathBuf], user: &ConfigLayer, format: Format, out: &mut impl Write) -> io::Result<i32> {
+    let mut worst = None;
+    let mut records = Vec::new();
+    let mut lines = Vec::new();
+    let results: Vec<(String, Checked)> = paths
+        .iter()
+        .map(|path| {
+            let loaded = load(path, user).map(|loaded| {
+                let issues = loaded.issues();
+                (loaded, issues)
+            });
+            (path.display().to_string(), loaded)
+        })
+        .collect();
+    for (file, result) in &results {
+        let (loaded, issues) = match result {
+            Ok(found) => found,
+            Err(err) => {
+                worst = Some(Severity::Error);
+                lines.push(format!("{file}: error: {err}"));
+                records.push(CheckRecord::Unreadable {
+                    file,
+                    type_name: None,
+                    field: None,
+                    severity: Severity::Error,
+                    code: "unreadable",
+                    message: err,
+                });
+                continue;
+            }
+        };
+        for warning in &loaded.warnings {
+            worst = worst.max(Some(Severity::Warning));
+            lines.push(format!("{file}: line {}: warning: {}", warning.line, warning.message));
+            records.push(CheckRecord::Parse {
+                file,
+                type_name: None,
+                field: None,
+                severity: Severity::Warning,
+                code: "parse-warning",
+                line: warning.line,
+                message: &warning.message,
+            });
+        }
+        for issue in issues {
+            let severity = issue.code.severity();
+            worst = worst.max(Some(severity));
+            let ty = loaded.types.get(issue.type_idx);
+            let key = ty.zip(issue.field_idx).and_then(|(ty, idx)| ty.fields.get(idx)).map(|f| &f.key);
+            let name = ty.map_or("?", |t| t.name.as_str());
+            let place = key.map(|k| format!(" ({})", k.label())).unwrap_or_default();
+            lines.push(format!("{file}: {name}{place}: {}: {}", severity_word(severity), issue.message));
+            records.push(CheckRecord::Issue {
+                file,
+                type_name: ty.map(|t| t.name.as_str()),
+                field: key.map(field_name),
+                severity,
+                issue,
+            });
+        }
+        let errors = issues.iter().filter(|i| i.code.severity() == Severity::Error).count();
+        let summary = match (errors, issues.len() - errors + loaded.warnings.len()) {
+            (0, 0) => String::from("no issues"),
+            (errors, 0) => utils::plural(errors, "error"),
+            (0, warnings) => utils::plural(warnings, "warning"),
+            (errors, warnings) => format!("{}, {}", utils::plural(errors, "error"), utils::plural(warnings, "warning")),
+        };
+        lines.push(format!("{file}: {} checked, {summary}", utils::plural(loaded.types.len(), "type")));
+    }
+    match format {
+        Format::Text => {
+            for line in &lines {
+                writeln!(out, "{line}")?;
+            }
+        }
+        Format::Json => writeln!(out, "{}", to_json(&records)?)?,
+    }
+    Ok(match worst {
+        None => 0,
+        Some(Severity::Warning) => 1,
+        Some(Severity::Error) => 2,
+    })
+}
+
+fn severity_word(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+/// `diff`: what changed from `old` to `new`; returns the exit code, 0 when they hold the same
+/// types, 1 when they differ and 2 when one doesn't read.
+pub fn diff(old: &Path, new: &Path, user: &ConfigLayer, format: Format, out: &mut impl Write) -> io::Result<i32> {
+    let read = |path: &Path| load(path, user).map_err(|message| Unreadable { file: path.display().to_string(), message });
+    let (changes, unreadable) = match (read(old), read(new)) {
+        (Ok(old), Ok(new)) => (diff::diff_types(&old.types, &new.types), Vec::new()),
+        (old, new) => (TypesDiff::default(), [old.err(), new.err()].into_iter().flatten().collect()),
+    };
+    match format {
+        Format::Text if !unreadable.is_empty() => {
+            for Unreadable { file, message } in &unreadable {
+                eprintln!("{file}: {message}");
+            }
+        }
+        Format::Text => {
+            for line in diff_lines(&changes) {
+                writeln!(out, "{line}")?;
+            }
+        }
+        Format::Json => writeln!(out, "{}", to_json(&DiffOutput::new(&changes, &unreadable))?)?,
+    }
+    Ok(match (unreadable.is_empty(), changes.is_empty()) {
+        (false, _) => 2,
+        (true, true) => 0,
+        (true, false) => 1,
+    })
+}
+
+/// `apply`: works out `edit` on every types file under `dir` as `:apply` does, with the
+/// directory's project config, and prints the dry run; with `write`, then writes the files
+/// that change, each with a backup, and lists which succeeded and failed. Returns 2 when a
+/// file can't be read or written, else 0.
+pub fn apply(dir: &Path, edit: &[String], write: bool, user: &ConfigLayer, out: &mut impl Write) -> io::Result<i32> {
+    let edit = match BulkEdit::parse(&edit.iter().map(String::as_str).collect::<Vec<_>>()) {
+        Ok(edit) => edit,
+        Err(err) => {
+            eprintln!("apply: {err}");
+            return Ok(2);
+        }
+    };
+    let (project, note) = ConfigLayer::load_project(fs::read_to_string(dir.join(PROJECT_FILE)).ok().as_deref());
+    if let (None, Some(note)) = (&project, note) {
+        eprintln!("{}: {note}", dir.display());
+    }
+    let config = Config::resolve(user, project.as_ref(), &ConfigLayer::default());
+    let edit = BulkEdit { create_flags: config.create_missing_flags, ..edit };
+    let progress = Reporter::silent();
+    let Some(planned) = bulk::dry_run(&FileSource::Local, edit, dir.to_path_buf(), config.normalize_names, &progress) else {
+        return Err(io::Error::from(io::ErrorKind::Interrupted));
+    };
+    for line in planned.lines() {
+        writeln!(out, "{line}")?;
+    }
+    let unreadable = planned.files.iter().filter(|f| f.plan.is_err()).count();
+    if !write {
+        writeln!(out)?;
+        writeln!(out, "Dry run: {} would change; add --write to save them", utils::plural(planned.writable(), "file"))?;
+        return Ok(if unreadable > 0 { 2 } else { 0 });
+    }
+    let keep = config.backup_keep.max(1);
+    let written = bulk::write_all(&FileSource::Local, planned.files, config.canonical_order, config.line_endings, keep, &progress);
+    writeln!(out)?;
+    writeln!(out, "Succeeded ({}):", written.succeeded.len())?;
+    for done in &written.succeeded {
+        let created = match done.created_flags {
+            0 => String::new(),
+            n => format!(", created flags on {}", utils::plural(n, "type")),
+        };
+        writeln!(out, "  {}: {} fields changed{created}", done.path.display(), done.changed_fields)?;
+    }
+    writeln!(out, "Failed ({}):", written.failed.len())?;
+    for (path, err) in &written.failed {
+        writeln!(out, "  {}: {err}", path.display())?;
+    }
+    Ok(if written.failed.is_empty() { 0 } else { 2 })
+}
+
+/// `+ Pear`, `- Apple`, `~ AKM: nominal: 8 → 15`, as the change report words them.
+fn diff_lines(changes: &TypesDiff) -> Vec<String> {
+    if changes.is_empty() {
+        return vec![String::from("The files hold the same types.")];
+    }
+    let mut lines: Vec<String> = changes.added.iter().map(|name| format!("+ {name}")).collect();
+    lines.extend(changes.removed.iter().map(|name| format!("- {name}")));
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("(none)"));
+    for change in &changes.changed {
+        for field in &change.fields {
+            lines.push(format!("~ {}: {}: {} → {}", change.name, field.key.label(), show(&field.old), show(&field.new)));
+        }
+    }
+    lines
+}
+
+fn to_json(value: &impl Serialize) -> io::Result<String> {
+    serde_json::to_string_pretty(value).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::ValidationLayer,
+        test_harness::{Fixture, SAMPLE},
+    };
+
+    fn run(output: impl FnOnce(&mut Vec<u8>) -> io::Result<i32>) -> (i32, String) {
+        let mut out = Vec::new();
+        let code = output(&mut out).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn check_reports_each_issue_with_its_code_in_both_formats() {
+        let fixture = Fixture::new();
+        let clean = fixture.write("clean.xml", SAMPLE);
+        let broken = fixture.write("broken.xml", &SAMPLE.replacen("<min>", "<min>x", 1));
+        let missing = fixture.dir.join("missing.xml");
+        // Two of SAMPLE's types have no <flags> on purpose.
+        let user = ConfigLayer {
+            validation: ValidationLayer { require_flags: Some(false), ..ValidationLayer::default() },
+            ..ConfigLayer::default()
+        };
+
+        let (code, text) = run(|out| check(std::slice::from_ref(&clean), &user, Format::Text, out));
+        assert_eq!((code, text.as_str()), (0, format!("{}: 3 types checked, no issues\n", clean.display()).as_str()));
+
+        let (code, json) = run(|out| check(std::slice::from_ref(&broken), &user, Format::Json, out));
+        assert_eq!(code, 2);
+        assert_eq!(json.replace(&broken.display().to_string(), "broken.xml"), r#"[
+  {
+    "file": "broken.xml",
+    "type": "AKM",
+    "field": "min",
+    "severity": "error",
+    "type_idx": 0,
+    "field_idx": 2,
+    "code": "bad-number",
+    "message": "min is not a whole number: 'x4'"
+  }
+]
+"#);
+
+        let paths = [broken.clone(), missing.clone()];
+        let (code, json) = run(|out| check(&paths, &user, Format::Json, out));
+        assert_eq!(code, 2);
+        let records: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let unreadable = &records[1];
+        assert_eq!(unreadable["file"], missing.display().to_string());
+        assert_eq!((unreadable["code"].as_str(), unreadable["type"].is_null()), (Some("unreadable"), true));
+
+        let (code, text) = run(|out| check(&paths, &user, Format::Text, out));
+        assert_eq!(code, 2);
+        assert!(text.starts_with(&format!("{0}: AKM (min): error: min is not a whole number: 'x4'\n{0}: 3 types checked, 1 error\n", broken.display())), "{text}");
+
+        // What the parser had to repair is a warning, as the editor's load summary says.
+        let nested = SAMPLE.replacen("<nominal>8</nominal>", "<nominal>8</nominal>\n        <type name=\"Inner\"><nominal>1</nominal></type>", 1);
+        let nested = fixture.write("nested.xml", &nested);
+        let (code, json) = run(|out| check(std::slice::from_ref(&nested), &user, Format::Json, out));
+        assert_eq!(code, 1);
+        assert_eq!(json.replace(&nested.display().to_string(), "nested.xml"), r#"[
+  {
+    "file": "nested.xml",
+    "type": null,
+    "field": null,
+    "severity": "warning",
+    "code": "parse-warning",
+    "line": 5,
+    "message": "<type name=\"Inner\"> nested inside <type name=\"AKM\"> was read as a separate type"
+  }
+]
+"#);
+        let (code, text) = run(|out| check(std::slice::from_ref(&nested), &user, Format::Text, out));
+        assert_eq!(code, 1);
+        assert_eq!(text, format!(
+            "{0}: line 5: warning: <type name=\"Inner\"> nested inside <type name=\"AKM\"> was read as a separate type\n{0}: 4 types checked, 1 warning\n",
+            nested.display()
+        ));
+    }
+
+    #[test]
+    fn apply_lists_the_dry_run_and_writes_only_when_asked() {
+        let fixture = Fixture::new();
+        fixture.write("db/types.xml", SAMPLE);
+        fixture.write("custom/types.xml", &SAMPLE.replace("AKM", "AKS74U"));
+        let edit = ["AK*", "lifetime", "=900"].map(String::from);
+        let dir = fixture.dir.clone();
+
+        let (code, text) = run(|out| apply(&dir, &edit, false, &ConfigLayer::default(), out));
+        assert_eq!(code, 0);
+        let text = text.replace(&dir.display().to_string(), "DIR");
+        assert_eq!(text, "lifetime =900 on AK* under DIR
+
+  DIR/custom/types.xml: 1 types match, 1 fields change
+  DIR/db/types.xml: 1 types match, 1 fields change
+
+Dry run: 2 files would change; add --write to save them
+");
+        assert_eq!(fixture.read("db/types.xml"), SAMPLE);
+
+        fixture.write("broken.xml", "<types><type name=\"Torn\">");
+        let (code, text) = run(|out| apply(&dir, &edit, true, &ConfigLayer::default(), out));
+        assert_eq!(code, 2);
+        assert!(text.contains("Succeeded (2):\n") && text.contains("Failed (1):\n"), "{text}");
+        assert!(fixture.read("db/types.xml").contains("<lifetime>900</lifetime>"));
+        assert_eq!(fixture.read("db/types.xml.bak"), SAMPLE);
+
+        let bad = ["AK*", "lifetime", "900"].map(String::from);
+        assert_eq!(run(|out| apply(&dir, &bad, true, &ConfigLayer::default(), out)), (2, String::new()));
+    }
+
+    #[test]
+    fn diff_lists_added_removed_and_changed_fields_in_a_stable_shape() {
+        let fixture = Fixture::new();
+        let old = fixture.write("old.xml", SAMPLE);
+        let changed = SAMPLE.replacen("<nominal>", "<nominal>1", 1).replace("count_in_map=\"1\"", "count_in_map=\"0\"");
+        let new = fixture.write("new.xml", &changed.replace("name=\"Apple\"", "name=\"Pear\""));
+        let user = ConfigLayer::default();
+
+        assert_eq!(run(|out| diff(&old, &old, &user, Format::Json, out)), (0, String::from(
+            "{\n  \"added\": [],\n  \"removed\": [],\n  \"changed\": [],\n  \"unreadable\": []\n}\n"
+        )));
+        let (code, json) = run(|out| diff(&old, &new, &user, Format::Json, out));
+        assert_eq!(code, 1);
+        assert_eq!(json, r#"{
+  "added": [
+    "Pear"
+  ],
+  "removed": [
+    "Apple"
+  ],
+  "changed": [
+    {
+      "type": "AKM",
+      "field": "nominal",
+      "old": "8",
+      "new": "18"
+    },
+    {
+      "type": "AKM",
+      "field": "flags@count_in_map",
+      "old": "1",
+      "new": "0"
+    }
+  ],
+  "unreadable": []
+}
+"#);
+
+        let (_, text) = run(|out| diff(&old, &new, &user, Format::Text, out));
+        assert!(text.starts_with("+ Pear\n- Apple\n~ AKM: nominal: 8 → 18\n~ AKM: flags @count_in_map: 1 → 0\n"), "{text}");
+        let gone = fixture.dir.join("gone.xml");
+        assert_eq!(run(|out| diff(&old, &gone, &user, Format::Text, out)), (2, String::new()));
+        let (code, json) = run(|out| diff(&old, &gone, &user, Format::Json, out));
+        assert_eq!(code, 2);
+        let missing = fs::read_to_string(&gone).unwrap_err().to_string();
+        assert_eq!(json.replace(&gone.display().to_string(), "gone.xml").replace(&missing, "MISSING"), r#"{
+  "added": [],
+  "removed": [],
+  "changed": [],
+  "unreadable": [
+    {
+      "file": "gone.xml",
+      "message": "MISSING"
+    }
+  ]
+}
+"#);
+    }
+}
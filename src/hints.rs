@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use crate::model::{FieldKey, TypeEntry};
+
+/// Numeric elements the hints cover.
+pub const HINT_ELEMENTS: [&str; 6] = ["nominal", "min", "lifetime", "restock", "quantmin", "quantmax"];
+
+/// Approximate spread of each element across the vanilla Chernarus types.xml:
+/// (element, lowest, median, highest). Quantities only count types that use them.
+const VANILLA_CHERNARUS: [(&str, i64, i64, i64); 6] = [
+    ("nominal", 0, 8, 200),
+    ("min", 0, 5, 150),
+    ("lifetime", 3, 14400, 3888000),
+    ("restock", 0, 0, 10800),
+    ("quantmin", 0, 30, 90),
+    ("quantmax", 10, 80, 100),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub min: i64,
+    pub median: i64,
+    pub max: i64,
+}
+
+/// Typical values per element from a reference file, shown in the Tips pane.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Baseline {
+    /// Where the figures come from: "vanilla" or the baseline file's name.
+    pub source: String,
+    ranges: BTreeMap<String, Range>,
+}
+
+impl Baseline {
+    /// The compiled-in Chernarus figures.
+    pub fn vanilla() -> Self {
+        Self {
+            source: String::from("vanilla"),
+            ranges: VANILLA_CHERNARUS
+                .iter()
+                .map(|&(name, min, median, max)| (name.to_string(), Range { min, median, max }))
+                .collect(),
+        }
+    }
+
+    /// Figures computed from another types file (Livonia, a modded baseline, ...).
+    /// `-1` quantities mean "not used" and are left out.
+    pub fn from_types(types: &[TypeEntry], source: impl Into<String>) -> Self {
+        let mut values: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+        for ty in types {
+            for field in &ty.fields {
+                let FieldKey::Element { name, index: 0 } = &field.key else {
+                    continue;
+                };
+                let Some(element) = HINT_ELEMENTS.iter().find(|e| *e == name) else {
+                    continue;
+                };
+                if let Ok(value) = field.value.trim().parse::<i64>()
+                    && !(element.starts_with("quant") && value == -1)
+                {
+                    values.entry(element).or_default().push(value);
+                }
+            }
+        }
+        let ranges = values
+            .into_iter()
+            .map(|(element, mut found)| {
+                found.sort_unstable();
+                let range = Range {
+                    min: found[0],
+                    median: found[found.len() / 2],
+                    max: found[found.len() - 1],
+                };
+                (element.to_string(), range)
+            })
+            .collect();
+        Self {
+            source: source.into(),
+            ranges,
+        }
+    }
+
+    pub fn range(&self, element: &str) -> Option<Range> {
+        self.ranges.get(element).copied()
+    }
+
+    /// `vanilla range: 1–320, median 10 (this one: above the median)` for a field, if covered.
+    pub fn describe(&self, key: &FieldKey, value: &str) -> Option<String> {
+        let FieldKey::Element { name, .. } = key else {
+            return None;
+        };
+        let range = self.range(name)?;
+        let mut text = format!(
+            "{} range: {}–{}, median {}",
+            self.source, range.min, range.max, range.median
+        );
+        if let Ok(value) = value.trim().parse::<i64>() {
+            text.push_str(&format!(" (this one: {})", placement(range, value)));
+        }
+        Some(text)
+    }
+}
+
+fn placement(range: Range, value: i64) -> &'static str {
+    if value < range.min {
+        "below the range"
+    } else if value > range.max {
+        "above the range"
+    } else if value == range.median {
+        "at the median"
+    } else if value < range.median {
+        "below the median"
+    } else {
+        "above the median"
+    }
+}
@@ -0,0 +1,11 @@
+//! types.xml handling shared by the terminal editor and other tools: parsing and
+//! serializing, validation, duplicate detection and diffs. Nothing here touches the
+//! terminal or the network, so it also builds for wasm32 with the `wasm` feature.
+
+pub mod dedupe;
+pub mod diff;
+pub mod field_help;
+pub mod model;
+pub mod validation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,156 @@
+//! Typing into a one-line text: the prompts, the connect form and the editor's input all
+//! go through here so the editing keys behave the same everywhere. The cursor counts
+//! grapheme clusters, what shows as one character however many code points make it (an
+//! `é` typed as `e` and a combining accent, a flag, an emoji with a skin tone), and is
+//! `None` at the end, so text replaced wholesale keeps the cursor after it.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{action::Action, utils};
+
+/// Applies an editing key to `text`: typing, Backspace, Delete, Left/Right, Home/End,
+/// Ctrl+U (clear) and Ctrl+W (previous word). False for actions that don't edit text.
+pub fn edit(text: &mut String, cursor: &mut Option<usize>, action: &Action) -> bool {
+    let len = text.graphemes(true).count();
+    let at = cursor.map_or(len, |c| c.min(len));
+    let moved = match *action {
+        Action::Input(c) if !c.is_control() => {
+            // A combining mark joins the cluster before it, so the cursor stays after that
+            // cluster rather than moving on.
+            let index = byte_index(text, at);
+            text.insert(index, c);
+            text[..index + c.len_utf8()].graphemes(true).count()
+        }
+        Action::Backspace => delete_back(text, at, at.saturating_sub(1)),
+        Action::Remove => delete_back(text, (at + 1).min(len), at),
+        Action::DeleteWord => delete_back(text, at, word_start(text, at)),
+        Action::ClearLine => {
+            text.clear();
+            *cursor = None;
+            return true;
+        }
+        Action::Left => at.saturating_sub(1),
+        Action::Right => (at + 1).min(len),
+        Action::Home => 0,
+        Action::End => {
+            *cursor = None;
+            return true;
+        }
+        _ => return false,
+    };
+    let len = text.graphemes(true).count();
+    *cursor = (moved < len).then_some(moved);
+    true
+}
+
+/// Removes the clusters from `from` up to `to` and returns where the cursor ends up.
+fn delete_back(text: &mut String, to: usize, from: usize) -> usize {
+    let range = byte_index(text, from)..byte_index(text, to);
+    text.replace_range(range, "");
+    from
+}
+
+/// Where the word before `at` starts, skipping the whitespace right before the cursor
+/// first, like a shell's Ctrl+W.
+pub fn word_start(text: &str, at: usize) -> usize {
+    let before: Vec<&str> = text.graphemes(true).take(at).collect();
+    let blank = |g: &str| g.chars().all(char::is_whitespace);
+    let mut start = before.len();
+    while start > 0 && blank(before[start - 1]) {
+        start -= 1;
+    }
+    while start > 0 && !blank(before[start - 1]) {
+        start -= 1;
+    }
+    start
+}
+
+/// Where the `clusters`-th grapheme cluster starts, the end of `text` past the last one.
+fn byte_index(text: &str, clusters: usize) -> usize {
+    text.grapheme_indices(true).nth(clusters).map_or(text.len(), |(i, _)| i)
+}
+
+/// `text` in at most `width` columns with `▏` at the cursor, cut so the cursor stays
+/// visible: from the front while it is at the end, around it otherwise.
+pub fn view(text: &str, cursor: Option<usize>, width: usize) -> String {
+    let Some(at) = cursor.filter(|&c| c < text.graphemes(true).count()) else {
+        return format!("{}▏", utils::tail_width(text, width.saturating_sub(1)));
+    };
+    let split = byte_index(text, at);
+    let (before, after) = text.split_at(split);
+    if before.width() + after.width() < width {
+        return format!("{before}▏{after}");
+    }
+    let left = utils::tail_width(before, (width / 2).max(width.saturating_sub(after.width() + 1)));
+    let right = utils::fit_width(after, width.saturating_sub(left.width() + 1));
+    format!("{left}▏{right}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, keys: &[Action]) -> (String, Option<usize>) {
+        let mut text = text.to_string();
+        let mut cursor = None;
+        for key in keys {
+            edit(&mut text, &mut cursor, key);
+        }
+        (text, cursor)
+    }
+
+    #[test]
+    fn home_end_word_and_line_keys_edit_at_the_cursor() {
+        let path = "/home/alex/.ssh/id_ed25519";
+        assert_eq!(apply(path, &[Action::DeleteWord]), (String::new(), None));
+        assert_eq!(apply("ssh -i key", &[Action::DeleteWord, Action::DeleteWord]).0, "ssh ");
+        assert_eq!(apply("one two  ", &[Action::DeleteWord]).0, "one ");
+        assert_eq!(apply(path, &[Action::Home, Action::Input('~'), Action::End, Action::Input('!')]).0, format!("~{path}!"));
+        assert_eq!(apply("abc", &[Action::Left, Action::Backspace]), (String::from("ac"), Some(1)));
+        assert_eq!(apply("abc", &[Action::Home, Action::Backspace, Action::Right, Action::Right, Action::Right]), (String::from("abc"), None));
+        assert_eq!(apply("näh dran", &[Action::Left, Action::Left, Action::DeleteWord]), (String::from("näh an"), Some(4)));
+        assert_eq!(apply("abc", &[Action::Home, Action::ClearLine, Action::Input('x')]), (String::from("x"), None));
+        assert!(!edit(&mut String::new(), &mut None, &Action::Save));
+    }
+
+    #[test]
+    fn accents_emoji_and_wide_characters_edit_as_one_character() {
+        // `e` and a combining acute accent typed one after the other make a single `é`.
+        let cafe = [Action::Input('c'), Action::Input('a'), Action::Input('f'), Action::Input('e'), Action::Input('\u{301}')];
+        assert_eq!(apply("", &cafe), (String::from("cafe\u{301}"), None));
+        assert_eq!(apply("cafe\u{301}", &[Action::Backspace]), (String::from("caf"), None));
+        assert_eq!(apply("cafe\u{301}s", &[Action::Left, Action::Left, Action::Remove]), (String::from("cafs"), Some(3)));
+        // An accent typed in the middle joins the letter before the cursor and leaves the
+        // cursor after it.
+        assert_eq!(apply("ete", &[Action::Left, Action::Left, Action::Input('\u{301}')]), (String::from("e\u{301}te"), Some(1)));
+
+        // A thumbs-up with a skin tone and a flag are two code points each.
+        let thumbs = "ok 👍🏽 🇩🇪";
+        assert_eq!(apply(thumbs, &[Action::Left, Action::Left, Action::Backspace]), (String::from("ok  🇩🇪"), Some(3)));
+        assert_eq!(apply(thumbs, &[Action::Backspace]), (String::from("ok 👍🏽 "), None));
+        assert_eq!(apply(thumbs, &[Action::DeleteWord]), (String::from("ok 👍🏽 "), None));
+        assert_eq!(apply(thumbs, &[Action::Home, Action::Right, Action::Right, Action::Right, Action::Right, Action::Input('!')]).0, "ok 👍🏽! 🇩🇪");
+
+        // Wide CJK characters: one step of the cursor each, two columns on screen.
+        assert_eq!(apply("日本語", &[Action::Left, Action::Input('の'), Action::Right, Action::Backspace]), (String::from("日本の"), None));
+        assert_eq!(view("日本語", Some(1), 10), "日▏本語");
+        assert_eq!(view("日本語テキスト", Some(3), 9), "…語▏テキ…");
+        assert_eq!(view("cafe\u{301}s", Some(3), 10), "caf▏e\u{301}s");
+        for at in 0..7 {
+            assert!(view("日本語テキスト", Some(at), 9).width() <= 9, "{at}");
+        }
+    }
+
+    #[test]
+    fn the_view_keeps_the_cursor_on_screen() {
+        assert_eq!(view("abc", None, 10), "abc▏");
+        assert_eq!(view("abc", Some(1), 10), "a▏bc");
+        assert_eq!(view("abcdefghij", None, 6), "…ghij▏");
+        assert_eq!(view("abcdefghij", Some(0), 6), "▏abcd…");
+        assert_eq!(view("abcdefghij", Some(5), 7), "…de▏fg…");
+        for at in 0..10 {
+            assert!(view("abcdefghij", Some(at), 7).width() <= 7, "{at}");
+        }
+    }
+}
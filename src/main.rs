@@ -1,9 +1,17 @@
-use std::{io, time::Duration};
+use std::{
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    cursor::Show,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
 use tui::{
@@ -17,67 +25,328 @@ mod action;
 mod window_state;
 mod utils;
 mod remote;
+mod config;
+mod sniff;
+mod balance;
+mod report;
+mod backup;
+mod tiers;
+mod flags;
+mod line_edit;
+mod spawnable;
+mod cli;
+mod merge;
+mod hints;
+mod compare;
+mod bulk;
+mod progress;
+mod fuzzy;
+mod expr;
+mod paths;
+mod preview;
+mod assign;
+mod plain;
+mod watch;
+mod annotations;
+mod doctor;
+mod selection;
+mod setup;
+mod status_log;
+mod tutorial;
+mod dryrun;
+mod classnames;
+mod strip;
+mod headless;
+mod overlay_help;
+mod summary;
+#[cfg(test)]
+mod test_harness;
+
+use dayz_loot_editor_rust::{dedupe, diff, field_help, model, validation};
 
-use crate::file_picker::FilePicker;
-use crate::editor::Editor;
+use crate::file_picker::{FilePicker, Picked};
+use crate::editor::{Editor, LoadMode};
 use crate::action::Action;
 use crate::window_state::WindowState;
-use crate::remote::RemoteConfig;
+use crate::remote::{FileSelection, FileSource, RemoteConfig};
+use crate::config::{Config, ConfigLayer, Theme};
+use crate::setup::SetupWizard;
+use crate::cli::Cli;
+use crate::compare::CompareView;
+
+/// A second `q` within this long quits from anywhere.
+const DOUBLE_QUIT_WINDOW: Duration = Duration::from_secs(1);
 
 fn main() -> Result<(), io::Error> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if cli.version {
+        println!("{}", paths::version_text());
+        return Ok(());
+    }
+    if cli.doctor {
+        let checks = doctor::run_all(cli.connect);
+        for check in &checks {
+            for line in check.lines() {
+                println!("{line}");
+            }
+        }
+        std::process::exit(doctor::worst(&checks).exit_code());
+    }
+    if let Some(command) = cli.headless {
+        let (user_config, config_warning) = ConfigLayer::load_user();
+        if let Some(warning) = config_warning {
+            eprintln!("{warning}");
+        }
+        let mut out = io::stdout().lock();
+        let code = match command {
+            cli::Headless::Check => headless::check(&cli.paths, &user_config, cli.format, &mut out)?,
+            cli::Headless::Diff => headless::diff(&cli.paths[0], &cli.paths[1], &user_config, cli.format, &mut out)?,
+            cli::Headless::Apply => headless::apply(&cli.paths[0], &cli.edit, cli.write, &user_config, &mut out)?,
+        };
+        std::process::exit(code);
+    }
+
+    if cli.plain {
+        let (user_config, config_warning) = ConfigLayer::load_user();
+        if let Some(warning) = config_warning {
+            eprintln!("{warning}");
+        }
+        let mut editor = Editor::new(user_config, cli.config_layer());
+        let stdin = io::stdin();
+        let prompt = stdin.is_terminal();
+        return plain::run(&mut editor, stdin.lock(), io::stdout().lock(), prompt);
+    }
+
+    enter_terminal()?;
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = leave_terminal();
+        default_hook(info);
+    }));
 
-    let backend = CrosstermBackend::new(stdout);
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let remote_config = RemoteConfig::from_env();
-    let mut file_picker= FilePicker::new(std::env::current_dir()?, remote_config)?;
-    let mut editor = Editor::new();
+    let (mut user_config, config_warning) = ConfigLayer::load_user();
+    let mut cli_config = cli.config_layer();
+    // NO_COLOR and dumb terminals only pick the theme when nothing else does.
+    if cli_config.theme.is_none() && user_config.theme.is_none() {
+        cli_config.theme = Theme::detect();
+    }
+    let mut config = Config::resolve(&user_config, None, &cli_config);
+    let remote_config = RemoteConfig::from_env().or_else(|| RemoteConfig::from_profile(&config.ssh));
+    let mut file_picker= FilePicker::new(std::env::current_dir()?, remote_config.clone(), &config)?;
+    if let Some(warning) = config_warning {
+        file_picker.set_status(warning);
+    }
+    let mut editor = Editor::new(user_config.clone(), cli_config.clone());
+    let mut compare: Option<CompareView> = None;
 
-    let mut state = WindowState::FilePicker;
+    // First run: no config file yet, so offer the wizard before the picker.
+    let mut setup = ConfigLayer::user_path()
+        .filter(|path| !path.exists())
+        .map(|path| SetupWizard::new(path, user_config.clone(), true));
+    let mut setup_return = WindowState::FilePicker;
+    let mut state = if setup.is_some() { WindowState::Setup } else { WindowState::FilePicker };
+    if let Some(name) = cli.goto.clone() {
+        editor.open_at(name, cli.field.clone());
+    }
+    if let Some(path) = &cli.file {
+        let selection = FileSelection {
+            path: std::env::current_dir()?.join(path),
+            source: FileSource::Local,
+        };
+        let mut opened = WindowState::FilePicker;
+        open_in_editor(&mut editor, &mut file_picker, &mut opened, selection);
+        match state {
+            WindowState::Setup => setup_return = opened,
+            _ => state = opened,
+        }
+    }
+    if let Some(uri) = cli.remote.clone() {
+        file_picker.open_remote(uri.remote_config(remote_config.clone()), uri.path);
+    }
     let mut show_help = false;
+    let mut last_quit: Option<Instant> = None;
 
     let mut running = true;
     while running {
-        match state {
-            WindowState::FilePicker => {
-                let help = show_help;
-                terminal.draw(|f| file_picker.draw(f, help))?;
-            },
-            WindowState::Editor => {
-                let help = show_help;
-                terminal.draw(|f| editor.draw(f, help))?;
+        file_picker.tick()?;
+        if let Some(selection) = file_picker.take_connected_open() {
+            // The ssh:// file from the command line; --goto applies once it has downloaded.
+            match state {
+                WindowState::Setup => {
+                    let mut opened = WindowState::FilePicker;
+                    open_in_editor(&mut editor, &mut file_picker, &mut opened, selection);
+                    setup_return = opened;
+                }
+                _ => open_in_editor(&mut editor, &mut file_picker, &mut state, selection),
             }
         }
+        editor.tick()?;
+        if matches!(state, WindowState::Editor) {
+            if let Some(failure) = editor.take_load_failure() {
+                state = WindowState::FilePicker;
+                file_picker.set_open_file(None);
+                file_picker.set_status(failure);
+            } else if editor.take_close_request() {
+                // A save from the unsaved-changes prompt finished uploading.
+                state = WindowState::FilePicker;
+                file_picker.set_open_file(None);
+                file_picker.set_status(String::from("File closed; Enter to open another, q to quit"));
+            }
+        }
+        let help = show_help;
+        let mono = config.theme == Theme::Mono;
+        terminal.draw(|f| {
+            match state {
+                WindowState::FilePicker => file_picker.draw(f, help),
+                WindowState::Editor => editor.draw(f, help),
+                WindowState::Compare => {
+                    if let Some(view) = compare.as_mut() {
+                        view.draw(f, help);
+                    }
+                }
+                WindowState::Setup => {
+                    match setup_return {
+                        WindowState::Editor => editor.draw(f, false),
+                        _ => file_picker.draw(f, false),
+                    }
+                    if let Some(wizard) = &setup {
+                        wizard.draw(f);
+                    }
+                }
+            }
+            if mono {
+                f.render_widget(utils::Monochrome, f.size());
+            }
+        })?;
 
         if event::poll(Duration::from_millis(200))? {
             match event::read()? {
                 Event::Key(key) => {
-                    let text_editing = matches!(state, WindowState::Editor) && editor.is_editing();
-                    let prompt_mode = matches!(state, WindowState::FilePicker) && file_picker.is_prompt();
-                    let action = map_key_to_action(key, text_editing, prompt_mode);
+                    let text_editing = match state {
+                        WindowState::Editor => editor.is_editing(),
+                        WindowState::Compare => compare.as_ref().is_some_and(|v| v.is_editing()),
+                        WindowState::FilePicker | WindowState::Setup => false,
+                    };
+                    let prompt_mode = match state {
+                        WindowState::FilePicker => file_picker.is_prompt(),
+                        WindowState::Setup => true,
+                        _ => false,
+                    };
+                    let action = map_key_to_action(key, text_editing, prompt_mode, config.vim_keys);
+                    let double_quit = action == Action::Quit
+                        && last_quit.is_some_and(|at| at.elapsed() < DOUBLE_QUIT_WINDOW);
+                    last_quit = (action == Action::Quit).then(Instant::now);
                     match (state, action) {
-                        (_, Action::Quit) => running = false,
+                        (_, Action::ForceQuit) => running = false,
+                        (_, Action::Suspend) => {
+                            suspend()?;
+                            terminal.clear()?;
+                        }
+                        (_, Action::Quit) if double_quit => running = false,
+                        (WindowState::FilePicker, Action::Quit) if file_picker.is_choosing_directory() => {
+                            file_picker.handle_action(Action::Cancel)?;
+                            editor.directory_chosen(None);
+                            state = WindowState::Editor;
+                        }
+                        (WindowState::FilePicker, Action::Quit) => running = false,
+                        (WindowState::Editor, Action::Quit) if editor.instant_quit() => running = false,
+                        // Over an overlay, help is that overlay's keys.
+                        (WindowState::Editor, Action::Help) if editor.has_overlay() && !show_help => {
+                            editor.handle_action(Action::Help)?;
+                        }
                         (_, Action::Help) if !text_editing && !prompt_mode => {
                             show_help = !show_help;
                         }
-                        (WindowState::FilePicker, action) => {
-                            if let Some(selection) = file_picker.handle_action(action)? {
-                                match editor.load(selection) {
-                                    Ok(_) => {
-                                        state = WindowState::Editor;
+                        (WindowState::FilePicker, action) => match file_picker.handle_action(action)? {
+                            Some(Picked::Open(selection)) => {
+                                open_in_editor(&mut editor, &mut file_picker, &mut state, selection);
+                            }
+                            Some(Picked::Several(mut files)) => {
+                                let first = files.remove(0);
+                                file_picker.queue_files(files);
+                                open_in_editor(&mut editor, &mut file_picker, &mut state, first);
+                            }
+                            Some(Picked::Compare(a, b)) => {
+                                match (compare::load_side(&a), compare::load_side(&b)) {
+                                    (Ok(left), Ok(right)) => {
+                                        compare = Some(CompareView::new(
+                                            a.short_label(),
+                                            &left,
+                                            b.short_label(),
+                                            &right,
+                                            std::env::current_dir()?,
+                                        ));
+                                        state = WindowState::Compare;
+                                    }
+                                    (Err(err), _) => {
+                                        file_picker.set_status(format!("Failed to read {}: {}", a.path.display(), err));
                                     }
-                                    Err(err) => {
-                                        file_picker.set_status(format!("Failed to open file: {}", err));
+                                    (_, Err(err)) => {
+                                        file_picker.set_status(format!("Failed to read {}: {}", b.path.display(), err));
                                     }
                                 }
                             }
+                            Some(Picked::Directory(selection)) => {
+                                editor.directory_chosen(selection);
+                                state = WindowState::Editor;
+                            }
+                            None => {}
+                        },
+                        (WindowState::Setup, action) => {
+                            if let Some(done) = setup.as_mut().and_then(|wizard| wizard.handle_action(action)) {
+                                setup = None;
+                                state = setup_return;
+                                user_config = done.layer;
+                                config = Config::resolve(&user_config, None, &cli_config);
+                                file_picker.set_remote_config(
+                                    RemoteConfig::from_env().or_else(|| RemoteConfig::from_profile(&config.ssh)),
+                                );
+                                if matches!(state, WindowState::Editor) {
+                                    editor.set_user_config(user_config.clone(), Some(done.message));
+                                } else {
+                                    editor.set_user_config(user_config.clone(), None);
+                                    file_picker.set_status(done.message);
+                                }
+                            }
+                        }
+                        (WindowState::Compare, action) => {
+                            if compare.as_mut().is_none_or(|view| view.handle_action(action)) {
+                                compare = None;
+                                state = WindowState::FilePicker;
+                            }
                         }
                         (WindowState::Editor, action) => {
                             editor.handle_action(action)?;
+                            if editor.take_setup_request()
+                                && let Some(path) = ConfigLayer::user_path()
+                            {
+                                setup = Some(SetupWizard::new(path, user_config.clone(), false));
+                                setup_return = WindowState::Editor;
+                                state = WindowState::Setup;
+                            }
+                            if let Some(purpose) = editor.take_directory_request() {
+                                file_picker.choose_directory(purpose);
+                                state = WindowState::FilePicker;
+                            }
+                            if editor.take_close_request() {
+                                state = WindowState::FilePicker;
+                                file_picker.set_open_file(None);
+                                file_picker.set_status(String::from("File closed; Enter to open another, q to quit"));
+                            }
                         }
                     }
                 }
@@ -89,24 +358,113 @@ fn main() -> Result<(), io::Error> {
         }
     }
 
+    leave_terminal()
+}
+
+/// Starts loading `selection` in the editor; a failure stays in the picker with the
+/// next queued file, if any, offered instead.
+fn open_in_editor(editor: &mut Editor, file_picker: &mut FilePicker, state: &mut WindowState, selection: FileSelection) {
+    match editor.start_load(selection.clone(), LoadMode::KeepView) {
+        Ok(_) => {
+            file_picker.set_open_file(Some(selection));
+            *state = WindowState::Editor;
+        }
+        Err(err) => {
+            file_picker.set_status(format!("Failed to open file: {}", err));
+            file_picker.set_open_file(None);
+        }
+    }
+}
+
+/// Whether `enter_terminal` asked for the kitty keyboard protocol, so leaving undoes it.
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Raw mode, alternate screen and mouse capture, as the UI needs them. Terminals that speak
+/// the kitty keyboard protocol are asked to tell Ctrl+I from Tab (and Ctrl+M from Enter).
+fn enter_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+        KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Gives the terminal back in the state the shell expects; used on exit, on suspend and
+/// from the panic hook.
+fn leave_terminal() -> io::Result<()> {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show)
+}
 
+/// Raw mode swallows the terminal's own Ctrl+Z, so stop the process ourselves and set
+/// the terminal up again once the shell resumes it; the caller then redraws everything.
+#[cfg(unix)]
+fn suspend() -> io::Result<()> {
+    leave_terminal()?;
+    // SIGTSTP's default action stops the process; raise returns after SIGCONT (`fg`).
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    enter_terminal()
+}
+
+#[cfg(not(unix))]
+fn suspend() -> io::Result<()> {
     Ok(())
 }
 
-fn map_key_to_action(key: KeyEvent, text_editing: bool, prompt_mode: bool) -> Action {
+fn map_key_to_action(key: KeyEvent, text_editing: bool, prompt_mode: bool, vim_keys: bool) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char('q' | 'Q')) {
+        return Action::ForceQuit;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char('z' | 'Z')) {
+        return Action::Suspend;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) && !text_editing && !prompt_mode {
+        match key.code {
+            KeyCode::Left => return Action::ResizeLeft,
+            KeyCode::Right => return Action::ResizeRight,
+            KeyCode::Char('o' | 'O') => return Action::JumpBack,
+            KeyCode::Char('i' | 'I') | KeyCode::Tab => return Action::JumpForward,
+            _ => {}
+        }
+    }
+    // Ctrl+I only reaches us where the terminal tells it from Tab; Alt+Left/Right work everywhere.
+    if key.modifiers.contains(KeyModifiers::ALT) && !text_editing && !prompt_mode {
+        match key.code {
+            KeyCode::Left => return Action::JumpBack,
+            KeyCode::Right => return Action::JumpForward,
+            _ => {}
+        }
+    }
+
+    if (text_editing || prompt_mode) && key.modifiers.contains(KeyModifiers::CONTROL) {
+        match key.code {
+            KeyCode::Char('u' | 'U') => return Action::ClearLine,
+            KeyCode::Char('w' | 'W') => return Action::DeleteWord,
+            _ => {}
+        }
+    }
+
     if text_editing {
         return match key.code {
             KeyCode::Enter => Action::Activate,
             KeyCode::Esc => Action::Cancel,
             KeyCode::Backspace => Action::Backspace,
+            KeyCode::Delete => Action::Remove,
+            KeyCode::Up => Action::Up,
+            KeyCode::Down => Action::Down,
+            KeyCode::Left => Action::Left,
+            KeyCode::Right => Action::Right,
+            KeyCode::Home => Action::Home,
+            KeyCode::End => Action::End,
             KeyCode::Char(c) => Action::Input(c),
+            KeyCode::F(1) => Action::Help,
             _ => Action::None,
         };
     }
@@ -116,11 +474,17 @@ fn map_key_to_action(key: KeyEvent, text_editing: bool, prompt_mode: bool) -> Ac
             KeyCode::Enter => Action::Activate,
             KeyCode::Esc => Action::Cancel,
             KeyCode::Backspace => Action::Backspace,
+            KeyCode::Delete => Action::Remove,
             KeyCode::Tab => Action::Tab,
+            KeyCode::BackTab => Action::BackTab,
             KeyCode::Up => Action::Up,
             KeyCode::Down => Action::Down,
             KeyCode::PageUp => Action::PgUp,
             KeyCode::PageDown => Action::PgDown,
+            KeyCode::Left => Action::Left,
+            KeyCode::Right => Action::Right,
+            KeyCode::Home => Action::Home,
+            KeyCode::End => Action::End,
             KeyCode::Char(c) => Action::Input(c),
             _ => Action::None,
         };
@@ -128,24 +492,51 @@ fn map_key_to_action(key: KeyEvent, text_editing: bool, prompt_mode: bool) -> Ac
 
     match key.code {
         KeyCode::Char('q') => Action::Quit,
-        KeyCode::Up | KeyCode::Char('k') => Action::Up,
-        KeyCode::Down | KeyCode::Char('j') => Action::Down,
+        KeyCode::Up => Action::Up,
+        KeyCode::Down => Action::Down,
         KeyCode::Enter => Action::Activate,
-        KeyCode::Left | KeyCode::Char('h') => Action::Left,
-        KeyCode::Right | KeyCode::Char('l') => Action::Right,
+        KeyCode::Left => Action::Left,
+        KeyCode::Right => Action::Right,
+        KeyCode::Char('k') if vim_keys => Action::Up,
+        KeyCode::Char('j') if vim_keys => Action::Down,
+        KeyCode::Char('h') if vim_keys => Action::Left,
+        KeyCode::Char('l') if vim_keys => Action::Right,
         KeyCode::Char('s') => Action::Save,
         KeyCode::Char('a') => Action::Add,
         KeyCode::Char('c') => Action::Copy,
+        KeyCode::Char('C') => Action::Duplicate,
+        KeyCode::Char('R') => Action::Reload,
         KeyCode::Char('d') => Action::Delete,
         KeyCode::Char('t') => Action::AddAttribute,
         KeyCode::Char('r') => Action::ToggleRemote,
+        KeyCode::Char('f') => Action::ToggleFilter,
+        KeyCode::Char('F') => Action::ToggleCategories,
+        KeyCode::Char('i') => Action::Stats,
+        KeyCode::Char('n') => Action::NextIssue,
+        KeyCode::Char('N') => Action::PrevIssue,
+        KeyCode::Char('v') => Action::Validate,
+        KeyCode::Char('u') => Action::Undo,
+        KeyCode::Char('U') => Action::Redo,
+        KeyCode::Char('%') => Action::NominalTool,
+        KeyCode::Char(' ') => Action::ToggleSelect,
+        KeyCode::Char('P') => Action::PropagateValues,
+        KeyCode::Char(':') => Action::Command,
+        KeyCode::Char('m') => Action::RecordMacro,
+        KeyCode::Char('@') => Action::ReplayMacro,
+        KeyCode::Char('.') => Action::RepeatEdit,
+        KeyCode::Char('T') => Action::ToggleTable,
+        KeyCode::Char('V') => Action::CycleInlineValues,
         KeyCode::Char('?') => Action::Help,
         KeyCode::Tab => Action::Tab,
+        KeyCode::BackTab => Action::BackTab,
         KeyCode::Esc => Action::Cancel,
         KeyCode::Backspace => Action::Backspace,
         KeyCode::Char(c) => Action::Input(c),
         KeyCode::PageUp => Action::PgUp,
         KeyCode::PageDown => Action::PgDown,
+        KeyCode::F(1) => Action::Help,
+        KeyCode::F(2) => Action::Rename,
+        KeyCode::Delete => Action::Remove,
         _ => Action::None,
     }
 }
@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use sha2::{Digest, Sha256};
+
+use crate::model::{Field, FieldKey, TypeEntry};
+
+/// SHA-256 of a file's content, taken on load and compared again before saving.
+pub fn content_hash(content: &str) -> [u8; 32] {
+    Sha256::digest(content.as_bytes()).into()
+}
+
+/// Which version wins a conflict.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Mine,
+    Theirs,
+}
+
+/// Something changed differently in the editor and on disk since the common base.
+/// `key: None` is a whole type that one side deleted and the other changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub type_name: String,
+    pub key: Option<FieldKey>,
+    pub base: Option<String>,
+    pub mine: Option<String>,
+    pub theirs: Option<String>,
+    pub pick: Side,
+}
+
+/// Conflicts of a three-way merge, each defaulting to the editor's version.
+pub fn conflicts(base: &[TypeEntry], mine: &[TypeEntry], theirs: &[TypeEntry]) -> Vec<Conflict> {
+    let mut found = Vec::new();
+    walk(base, mine, theirs, &mut |conflict| {
+        found.push(conflict);
+        Side::Mine
+    });
+    found
+}
+
+/// Merges both sides onto `base`, taking each side's non-conflicting changes and the
+/// picks of `resolved` (as returned by `conflicts`) where both touched the same thing.
+pub fn merge(base: &[TypeEntry], mine: &[TypeEntry], theirs: &[TypeEntry], resolved: &[Conflict]) -> Vec<TypeEntry> {
+    let mut picks = resolved.iter().map(|c| c.pick);
+    walk(base, mine, theirs, &mut |_| picks.next().unwrap_or(Side::Mine))
+}
+
+/// Types keyed by name and occurrence, so repeated names pair up in order as in `diff`.
+fn by_occurrence(types: &[TypeEntry]) -> Vec<((&str, usize), &TypeEntry)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    types
+        .iter()
+        .map(|ty| {
+            let n = seen.entry(ty.name.as_str()).or_insert(0);
+            *n += 1;
+            ((ty.name.as_str(), *n - 1), ty)
+        })
+        .collect()
+}
+
+/// Result order: the editor's types, then types added on disk. `decide` is called for
+/// every conflict in that order.
+fn walk(
+    base: &[TypeEntry],
+    mine: &[TypeEntry],
+    theirs: &[TypeEntry],
+    decide: &mut dyn FnMut(Conflict) -> Side,
+) -> Vec<TypeEntry> {
+    let base_by: HashMap<_, _> = by_occurrence(base).into_iter().collect();
+    let theirs_by: HashMap<_, _> = by_occurrence(theirs).into_iter().collect();
+    let mine_keys: HashSet<_> = by_occurrence(mine).into_iter().map(|(key, _)| key).collect();
+
+    let mut merged = Vec::new();
+    for (key, m) in by_occurrence(mine) {
+        match (base_by.get(&key), theirs_by.get(&key)) {
+            (Some(b), Some(t)) => merged.push(merge_type(b, m, t, decide)),
+            (None, Some(t)) => {
+                let empty = TypeEntry {
+                    name: m.name.clone(),
+                    fields: Vec::new(),
+                };
+                merged.push(merge_type(&empty, m, t, decide));
+            }
+            (Some(b), None) if m == *b => {}
+            (Some(_), None) => {
+                let side = decide(Conflict {
+                    type_name: m.name.clone(),
+                    key: None,
+                    base: Some(String::from("type")),
+                    mine: Some(String::from("changed")),
+                    theirs: None,
+                    pick: Side::Mine,
+                });
+                if side == Side::Mine {
+                    merged.push(m.clone());
+                }
+            }
+            (None, None) => merged.push(m.clone()),
+        }
+    }
+    for (key, t) in by_occurrence(theirs) {
+        if mine_keys.contains(&key) {
+            continue;
+        }
+        match base_by.get(&key) {
+            None => merged.push(t.clone()),
+            Some(b) if t == *b => {}
+            Some(_) => {
+                let side = decide(Conflict {
+                    type_name: t.name.clone(),
+                    key: None,
+                    base: Some(String::from("type")),
+                    mine: None,
+                    theirs: Some(String::from("changed")),
+                    pick: Side::Mine,
+                });
+                if side == Side::Theirs {
+                    merged.push(t.clone());
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// Field-level merge of one type; fields keep the editor's order, disk-only ones follow.
+fn merge_type(
+    base: &TypeEntry,
+    mine: &TypeEntry,
+    theirs: &TypeEntry,
+    decide: &mut dyn FnMut(Conflict) -> Side,
+) -> TypeEntry {
+    let values = |ty: &TypeEntry| -> HashMap<FieldKey, String> {
+        ty.fields.iter().map(|f| (f.key.clone(), f.value.clone())).collect()
+    };
+    let (base_values, mine_values, theirs_values) = (values(base), values(mine), values(theirs));
+
+    let mut keys: Vec<&FieldKey> = mine.fields.iter().map(|f| &f.key).collect();
+    keys.extend(theirs.fields.iter().map(|f| &f.key).filter(|k| !mine_values.contains_key(*k)));
+
+    let mut fields = Vec::new();
+    for key in keys {
+        let (b, m, t) = (base_values.get(key), mine_values.get(key), theirs_values.get(key));
+        let value = if m == t || t == b {
+            m
+        } else if m == b {
+            t
+        } else {
+            let side = decide(Conflict {
+                type_name: mine.name.clone(),
+                key: Some(key.clone()),
+                base: b.cloned(),
+                mine: m.cloned(),
+                theirs: t.cloned(),
+                pick: Side::Mine,
+            });
+            if side == Side::Mine { m } else { t }
+        };
+        if let Some(value) = value {
+            fields.push(Field {
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    TypeEntry {
+        name: mine.name.clone(),
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    fn set(types: &mut [TypeEntry], ty: usize, element: &str, value: &str) {
+        let field = types[ty].element_field(element).unwrap();
+        types[ty].fields[field].value = value.to_string();
+    }
+
+    fn value(types: &[TypeEntry], name: &str, element: &str) -> Option<String> {
+        let ty = types.iter().find(|t| t.name == name)?;
+        Some(ty.fields[ty.element_field(element)?].value.clone())
+    }
+
+    #[test]
+    fn changes_on_one_side_merge_without_asking() {
+        let (base, _) = parse_types(SAMPLE).unwrap();
+        let (mut mine, mut theirs) = (base.clone(), base.clone());
+        set(&mut mine, 0, "nominal", "10");
+        set(&mut theirs, 0, "lifetime", "900");
+        set(&mut theirs, 1, "min", "25");
+        assert!(conflicts(&base, &mine, &theirs).is_empty());
+        let merged = merge(&base, &mine, &theirs, &[]);
+        assert_eq!(value(&merged, "AKM", "nominal").as_deref(), Some("10"));
+        assert_eq!(value(&merged, "AKM", "lifetime").as_deref(), Some("900"));
+        assert_eq!(value(&merged, "Apple", "min").as_deref(), Some("25"));
+    }
+
+    #[test]
+    fn both_sides_changing_a_field_is_a_conflict_settled_by_its_pick() {
+        let (base, _) = parse_types(SAMPLE).unwrap();
+        let (mut mine, mut theirs) = (base.clone(), base.clone());
+        set(&mut mine, 0, "nominal", "10");
+        set(&mut theirs, 0, "nominal", "12");
+        set(&mut mine, 2, "min", "3");
+        set(&mut theirs, 2, "min", "3");
+
+        let mut found = conflicts(&base, &mine, &theirs);
+        assert_eq!(found.len(), 1, "the same change on both sides is no conflict");
+        let conflict = &found[0];
+        assert_eq!((conflict.type_name.as_str(), conflict.pick), ("AKM", Side::Mine));
+        assert_eq!((conflict.base.as_deref(), conflict.mine.as_deref(), conflict.theirs.as_deref()), (Some("8"), Some("10"), Some("12")));
+
+        assert_eq!(value(&merge(&base, &mine, &theirs, &found), "AKM", "nominal").as_deref(), Some("10"));
+        found[0].pick = Side::Theirs;
+        let merged = merge(&base, &mine, &theirs, &found);
+        assert_eq!(value(&merged, "AKM", "nominal").as_deref(), Some("12"));
+        assert_eq!(value(&merged, "Mosin9130", "min").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn types_added_or_removed_on_one_side_survive_the_merge() {
+        let (base, _) = parse_types(SAMPLE).unwrap();
+        let (mut mine, mut theirs) = (base.clone(), base.clone());
+        let mut pear = base[1].clone();
+        pear.name = String::from("Pear");
+        mine.push(pear);
+        let mut plum = base[1].clone();
+        plum.name = String::from("Plum");
+        theirs.push(plum);
+        mine.remove(2);
+        theirs.remove(1);
+
+        assert!(conflicts(&base, &mine, &theirs).is_empty());
+        let names: Vec<String> = merge(&base, &mine, &theirs, &[]).into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["AKM", "Pear", "Plum"]);
+
+        // Removed in the editor but changed on disk asks; the editor's removal is the default.
+        set(&mut theirs, 1, "nominal", "1");
+        let mut found = conflicts(&base, &mine, &theirs);
+        assert_eq!((found.len(), found[0].key.is_none()), (1, true));
+        assert_eq!(value(&merge(&base, &mine, &theirs, &found), "Mosin9130", "nominal"), None);
+        found[0].pick = Side::Theirs;
+        assert_eq!(value(&merge(&base, &mine, &theirs, &found), "Mosin9130", "nominal").as_deref(), Some("1"));
+    }
+}
@@ -0,0 +1,983 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+use xml::{
+    common::Position,
+    reader::{EventReader, ParserConfig, XmlEvent},
+    writer::EmitterConfig,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize)]
+#[cfg_attr(feature = "wasm", derive(serde::Deserialize))]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum FieldKey {
+    Element { name: String, index: usize },
+    Attribute { element: String, index: usize, attr: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    pub key: FieldKey,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeEntry {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+impl TypeEntry {
+    /// Index of the first `<element>` text field with this name.
+    pub fn element_field(&self, element: &str) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|f| matches!(&f.key, FieldKey::Element { name, .. } if name == element))
+    }
+
+    /// Sets the first `<element>` to `value`, adding it where the vanilla element order puts
+    /// it when the type has none; true when it was added.
+    pub fn set_element(&mut self, element: &str, value: &str) -> bool {
+        if let Some(i) = self.element_field(element) {
+            self.fields[i].value = value.to_string();
+            return false;
+        }
+        let rank = canonical_rank(element);
+        let at = self
+            .fields
+            .iter()
+            .rposition(|f| canonical_rank(f.key.get_element_name()) <= rank)
+            .map_or(0, |i| i + 1);
+        let key = FieldKey::Element { name: element.to_string(), index: 0 };
+        self.fields.insert(at, Field { key, value: value.to_string() });
+        true
+    }
+
+    /// Removes the fields whose key `remove` picks. Taking an element's text takes the
+    /// attributes written on that same element with it, which would otherwise be left on
+    /// an element that was meant to go. Returns how many fields went.
+    pub fn remove_fields(&mut self, remove: impl Fn(&FieldKey) -> bool) -> usize {
+        let emptied: HashSet<(String, usize)> = self
+            .fields
+            .iter()
+            .filter(|f| matches!(f.key, FieldKey::Element { .. }) && remove(&f.key))
+            .map(|f| {
+                let (element, index) = f.key.element_key();
+                (element.to_string(), index)
+            })
+            .collect();
+        let before = self.fields.len();
+        self.fields.retain(|f| {
+            let (element, index) = f.key.element_key();
+            !remove(&f.key) && !emptied.iter().any(|(e, i)| e == element && *i == index)
+        });
+        before - self.fields.len()
+    }
+
+    /// Renumbers each element name's indices to 0, 1, 2... in order of appearance.
+    pub fn reindex_elements(&mut self) {
+        let mut mapping: HashMap<(String, usize), usize> = HashMap::new();
+        let mut next: HashMap<String, usize> = HashMap::new();
+        for field in &mut self.fields {
+            let (element, old) = field.key.element_key();
+            let key = (element.to_string(), old);
+            let new = *mapping.entry(key).or_insert_with(|| {
+                let counter = next.entry(element.to_string()).or_insert(0);
+                *counter += 1;
+                *counter - 1
+            });
+            match &mut field.key {
+                FieldKey::Element { index, .. } | FieldKey::Attribute { index, .. } => *index = new,
+            }
+        }
+    }
+}
+
+impl FieldKey {
+    pub fn set_name(&mut self, new_name: String) {
+        match self {
+            FieldKey::Element { name, .. } => *name = new_name,
+            FieldKey::Attribute { attr, .. } => *attr = new_name,
+        }
+    }
+
+    /// Display form: `nominal` for elements, `flags @deloot` for attributes.
+    pub fn label(&self) -> String {
+        match self {
+            FieldKey::Element { name, .. } => name.clone(),
+            FieldKey::Attribute { element, attr, .. } => format!("{} @{}", element, attr),
+        }
+    }
+
+    /// The `(element, index)` pair this field belongs to when serialized.
+    pub fn element_key(&self) -> (&str, usize) {
+        match self {
+            FieldKey::Element { name, index } => (name.as_str(), *index),
+            FieldKey::Attribute { element, index, .. } => (element.as_str(), *index),
+        }
+    }
+
+    pub fn get_element_name(&self) -> &str {
+        match self {
+            FieldKey::Element { name, .. } => name.as_str(),
+            FieldKey::Attribute { element, .. } => element.as_str(),
+        }
+    }
+}
+
+/// A non-fatal oddity noticed while parsing; `line` is 1-based.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "wasm", derive(serde::Serialize))]
+pub struct ParseWarning {
+    pub line: u64,
+    pub message: String,
+}
+
+/// Everything `parse_types` learned about a file besides the types themselves.
+#[derive(Clone, Debug, Default)]
+pub struct ParseReport {
+    pub warnings: Vec<ParseWarning>,
+    /// Encoding named in the XML declaration, `UTF-8` when there is none.
+    pub encoding: String,
+    pub has_bom: bool,
+    /// Whitespace before the XML declaration or root element, after any BOM.
+    pub leading_whitespace: String,
+    pub root: String,
+    /// The line ending most lines use.
+    pub line_ending: LineEnding,
+    /// Both endings occur; saves write `line_ending` throughout.
+    pub mixed_line_endings: bool,
+    /// Inline changelog entries (comments starting with `CHANGELOG_MARKER` directly
+    /// under the root), oldest first, without the marker.
+    pub changelog: Vec<String>,
+    /// Any other comments, anywhere in the document; saves don't write them back.
+    pub other_comments: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// The dominant ending of `content`; ties and files without line breaks count as LF.
+    /// The flag is set when both occur.
+    pub fn detect(content: &str) -> (Self, bool) {
+        let lines = content.matches('\n').count();
+        let crlf = content.matches("\r\n").count();
+        let ending = if crlf > lines - crlf { Self::Crlf } else { Self::Lf };
+        (ending, crlf > 0 && crlf < lines)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    /// Converts the `\n` line breaks `serialize_types` writes.
+    pub fn apply(self, xml: String) -> String {
+        match self {
+            Self::Lf => xml,
+            Self::Crlf => xml.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Elements whose value the game reads from a `name` attribute: `<category name="weapons"/>`.
+/// Some older tools write `<category>weapons</category>` instead.
+pub const NAME_ELEMENTS: [&str; 3] = ["category", "usage", "tag"];
+
+/// Deeper documents are refused rather than read; types.xml needs three levels.
+const MAX_DEPTH: usize = 32;
+
+/// Starts the comments `inline_changelog` writes before `</types>`; other comments are
+/// not kept.
+pub const CHANGELOG_MARKER: &str = "DayZLootEditor:";
+
+/// A `<type>` being read: the entry so far, the depth its element opened at, and how
+/// many of each child element it has had (the next `index`).
+struct OpenType {
+    entry: TypeEntry,
+    depth: usize,
+    element_indices: HashMap<String, usize>,
+}
+
+/// Values come out decoded: `&amp;`, `&lt;` and numeric references like `&#233;` are
+/// the characters they stand for, so the model never holds escaped text.
+pub fn parse_types(content: &str) -> Result<(Vec<TypeEntry>, ParseReport), xml::reader::Error> {
+    let body = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let after_whitespace = body.trim_start();
+    let leading_whitespace = &body[..body.len() - after_whitespace.len()];
+    // xml-rs refuses a declaration that isn't the very first thing; reading the whitespace
+    // just after it instead keeps every later line number as it is in the file.
+    let moved;
+    let readable = match after_whitespace.strip_prefix("<?xml").and_then(|decl| decl.find("?>")) {
+        Some(end) if !leading_whitespace.is_empty() => {
+            let (declaration, rest) = after_whitespace.split_at("<?xml".len() + end + 2);
+            moved = format!("{declaration}{leading_whitespace}{rest}");
+            moved.as_str()
+        }
+        _ => body,
+    };
+    let mut parser = EventReader::new_with_config(readable.as_bytes(), ParserConfig::new().ignore_comments(false));
+    let mut types = Vec::new();
+    let (line_ending, mixed_line_endings) = LineEnding::detect(content);
+    let mut report = ParseReport {
+        encoding: String::from("UTF-8"),
+        has_bom: content.starts_with('\u{feff}'),
+        leading_whitespace: leading_whitespace.to_string(),
+        line_ending,
+        mixed_line_endings,
+        ..ParseReport::default()
+    };
+    let mut current: Option<OpenType> = None;
+    // Types a nested `<type>` interrupted; each resumes once the inner one closes.
+    let mut outer: Vec<OpenType> = Vec::new();
+    let mut current_element: Option<(String, usize)> = None;
+    let mut depth = 0usize;
+    // Start depth of a subtree being skipped.
+    let mut skipping: Option<usize> = None;
+
+    loop {
+        let event = parser.next()?;
+        let line = parser.position().row + 1;
+        let mut warn = |message: String| report.warnings.push(ParseWarning { line, message });
+        match event {
+            XmlEvent::StartDocument { encoding, .. } => report.encoding = encoding,
+            XmlEvent::StartElement { name, attributes, .. } => {
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return Err((&parser, format!("elements nested more than {MAX_DEPTH} levels deep")).into());
+                }
+                if skipping.is_some() {
+                    continue;
+                }
+                let el = name.local_name;
+                if depth == 1 {
+                    if el != "types" {
+                        warn(format!("root element is <{el}>, expected <types>"));
+                    }
+                    report.root = el;
+                } else if el == "type" {
+                    let name_attr = attributes
+                        .iter()
+                        .find(|a| a.name.local_name == "name")
+                        .map(|a| a.value.clone())
+                        .unwrap_or_default();
+                    if name_attr.trim().is_empty() {
+                        warn(String::from("<type> without a name attribute"));
+                    }
+                    if let Some(open) = current.take() {
+                        warn(format!(
+                            "<type name=\"{name_attr}\"> nested inside <type name=\"{}\"> was read as a separate type",
+                            open.entry.name
+                        ));
+                        outer.push(open);
+                    }
+                    current = Some(OpenType {
+                        entry: TypeEntry {
+                            name: name_attr,
+                            fields: Vec::new(),
+                        },
+                        depth,
+                        element_indices: HashMap::new(),
+                    });
+                    current_element = None;
+                } else if let Some(open) = current.as_mut() {
+                    if depth > open.depth + 1 {
+                        let parent = current_element.as_ref().map_or("?", |(name, _)| name.as_str());
+                        warn(format!("<{el}> nested inside <{parent}> in type {} ignored", open.entry.name));
+                        skipping = Some(depth);
+                        continue;
+                    }
+                    let idx = *open.element_indices.entry(el.clone()).or_insert(0);
+                    if let Some(count) = open.element_indices.get_mut(&el) {
+                        *count += 1;
+                    }
+                    for attr in attributes {
+                        let key = FieldKey::Attribute {
+                            element: el.clone(),
+                            index: idx,
+                            attr: attr.name.local_name.clone(),
+                        };
+                        // The reader refuses a repeated name, but `a:deloot` and `deloot`
+                        // share a local name; one element can only be written with one.
+                        if let Some(existing) = open.entry.fields.iter_mut().find(|f| f.key == key) {
+                            warn(format!(
+                                "<{el}> in type {} has {} twice; the last value kept",
+                                open.entry.name, attr.name.local_name
+                            ));
+                            existing.value = attr.value;
+                            continue;
+                        }
+                        open.entry.fields.push(Field { key, value: attr.value });
+                    }
+                    current_element = Some((el, idx));
+                } else {
+                    warn(format!("skipped <{el}> outside of any <type>"));
+                }
+            }
+            XmlEvent::Characters(text) => {
+                let trimmed = text.trim();
+                if trimmed.is_empty() || skipping.is_some() {
+                    continue;
+                }
+                match (current_element.clone(), current.as_mut().map(|open| &mut open.entry)) {
+                    (Some((el, idx)), Some(t)) => {
+                        let key = FieldKey::Element { name: el.clone(), index: idx };
+                        match t.fields.iter_mut().find(|f| f.key == key) {
+                            Some(existing) => {
+                                warn(format!("mixed content in <{el}> collapsed into one value"));
+                                existing.value.push_str(trimmed);
+                            }
+                            None => {
+                                if NAME_ELEMENTS.contains(&el.as_str()) {
+                                    warn(format!("<{el}>{trimmed}</{el}> is in text form; the game expects <{el} name=\"{trimmed}\"/>"));
+                                }
+                                t.fields.push(Field {
+                                    key,
+                                    value: trimmed.to_string(),
+                                })
+                            }
+                        }
+                    }
+                    (None, Some(t)) => warn(format!("text directly inside <type name=\"{}\"> ignored", t.name)),
+                    _ => warn(String::from("text outside of any <type> ignored")),
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                if skipping == Some(depth) {
+                    skipping = None;
+                } else if skipping.is_none() {
+                    if current.as_ref().is_some_and(|open| open.depth == depth) {
+                        types.extend(current.take().map(|open| open.entry));
+                        current = outer.pop();
+                    }
+                    current_element = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            XmlEvent::Comment(text) => match text.trim().strip_prefix(CHANGELOG_MARKER) {
+                Some(entry) if depth == 1 => report.changelog.push(entry.trim().to_string()),
+                _ => report.other_comments += 1,
+            },
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+
+    // Every field and warning comes from markup in the input and values only shrink when
+    // decoded, so what is read can't outgrow the file; the fuzz target leans on this.
+    debug_assert!(types.iter().map(|t| t.fields.len()).sum::<usize>() + report.warnings.len() <= content.len());
+    debug_assert!(types.iter().flat_map(|t| &t.fields).map(|f| f.value.len()).sum::<usize>() <= content.len());
+    Ok((types, report))
+}
+
+/// Turns text-form `<category>weapons</category>` (and `usage`/`tag`) into the
+/// `name` attribute form, so both shapes are the same field for editing, search and
+/// `:apply`, and saves write the canonical form. Elements that already have a `name`
+/// attribute keep their text untouched. Returns how many were converted.
+pub fn normalize_name_elements(types: &mut [TypeEntry]) -> usize {
+    let mut converted = 0;
+    for ty in types {
+        let named: HashSet<(String, usize)> = ty
+            .fields
+            .iter()
+            .filter_map(|f| match &f.key {
+                FieldKey::Attribute { element, index, attr } if attr == "name" => Some((element.clone(), *index)),
+                _ => None,
+            })
+            .collect();
+        for field in &mut ty.fields {
+            if let FieldKey::Element { name, index } = &field.key
+                && NAME_ELEMENTS.contains(&name.as_str())
+                && !named.contains(&(name.clone(), *index))
+            {
+                field.key = FieldKey::Attribute {
+                    element: name.clone(),
+                    index: *index,
+                    attr: String::from("name"),
+                };
+                converted += 1;
+            }
+        }
+    }
+    converted
+}
+
+/// Name of the document's root element, skipping the prolog, comments and doctype;
+/// works on a truncated prefix as long as the root's start tag is in it.
+pub fn root_element(content: &str) -> Option<&str> {
+    let mut rest = content.trim_start_matches('\u{feff}');
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("<?") {
+            rest = &after[after.find("?>")? + 2..];
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = &after[after.find("-->")? + 3..];
+        } else if let Some(after) = rest.strip_prefix("<!") {
+            rest = &after[after.find('>')? + 1..];
+        } else {
+            let after = rest.strip_prefix('<')?;
+            let end = after.find(|c: char| c == '>' || c == '/' || c.is_whitespace())?;
+            return (end > 0).then(|| &after[..end]);
+        }
+    }
+}
+
+/// Characters XML 1.0 allows in text and attribute values.
+pub fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{FFFE}' && c != '\u{FFFF}')
+}
+
+/// Loose check for element and attribute names: non-empty, no whitespace or markup characters.
+pub fn is_xml_name(s: &str) -> bool {
+    !s.is_empty() && !s.chars().any(|c| c.is_whitespace() || "<>&\"'=/".contains(c) || !is_xml_char(c))
+}
+
+/// Reads a field label as config files write it: `lifetime`, `flags@deloot` (or
+/// `flags @deloot`), always the first element of that name. `None` unless both parts
+/// are XML names.
+pub fn parse_field_label(label: &str) -> Option<FieldKey> {
+    match label.split_once('@') {
+        Some((element, attr)) => {
+            let (element, attr) = (element.trim(), attr.trim());
+            (is_xml_name(element) && is_xml_name(attr)).then(|| FieldKey::Attribute {
+                element: element.to_string(),
+                index: 0,
+                attr: attr.to_string(),
+            })
+        }
+        None => is_xml_name(label.trim()).then(|| FieldKey::Element { name: label.trim().to_string(), index: 0 }),
+    }
+}
+
+/// Something `serialize_types` can't write as well-formed XML; `field_idx` is `None` for the type name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unwritable {
+    pub type_idx: usize,
+    pub field_idx: Option<usize>,
+    pub message: String,
+}
+
+/// The first value or name that would make the saved file invalid.
+pub fn find_unwritable(types: &[TypeEntry]) -> Option<Unwritable> {
+    let bad_char = |s: &str| s.chars().find(|&c| !is_xml_char(c));
+    for (type_idx, ty) in types.iter().enumerate() {
+        if let Some(c) = bad_char(&ty.name) {
+            return Some(Unwritable {
+                type_idx,
+                field_idx: None,
+                message: format!("invalid character U+{:04X} in the name of type {}", c as u32, ty.name.escape_debug()),
+            });
+        }
+        for (field_idx, field) in ty.fields.iter().enumerate() {
+            let name = match &field.key {
+                FieldKey::Element { name, .. } => name,
+                FieldKey::Attribute { attr, .. } => attr,
+            };
+            let message = if !is_xml_name(field.key.get_element_name()) || !is_xml_name(name) {
+                format!("'{}' in type {} is not a valid XML name", field.key.label().escape_debug(), ty.name)
+            } else if let Some(c) = bad_char(&field.value) {
+                format!("invalid character U+{:04X} in field {} of type {}", c as u32, field.key.label(), ty.name)
+            } else {
+                continue;
+            };
+            return Some(Unwritable {
+                type_idx,
+                field_idx: Some(field_idx),
+                message,
+            });
+        }
+    }
+    None
+}
+
+/// The vanilla element names, in the order the vanilla files use; `canonical_order` saves
+/// sort by it, unknown elements last.
+pub const VANILLA_ELEMENTS: [&str; 12] = [
+    "nominal", "lifetime", "restock", "min", "quantmin", "quantmax", "cost", "flags", "category", "usage", "tag",
+    "value",
+];
+
+/// Position of `element` in the vanilla element order; unknown elements sort last.
+pub fn canonical_rank(element: &str) -> usize {
+    VANILLA_ELEMENTS.iter().position(|&c| c == element).unwrap_or(VANILLA_ELEMENTS.len())
+}
+
+/// Escapes `&`, `<`, `>` and quotes as needed, so any decoded value reads back unchanged.
+pub fn serialize_types(types: &[TypeEntry], canonical_order: bool) -> io::Result<String> {
+    serialize_document(types, canonical_order, &[])
+}
+
+/// `serialize_types` with inline changelog entries written as marked comments before
+/// `</types>`, so `parse_types` reads them back into `ParseReport::changelog`.
+pub fn serialize_document(types: &[TypeEntry], canonical_order: bool, changelog: &[String]) -> io::Result<String> {
+    let mut buf: Vec<u8> = Vec::new();
+    {
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(&mut buf);
+
+        writer
+            .write(xml::writer::XmlEvent::start_element("types"))
+            .map_err(to_io)?;
+
+        for t in types {
+            let type_element = xml::writer::XmlEvent::start_element("type").attr("name", t.name.as_str());
+            writer.write(type_element).map_err(to_io)?;
+
+            let mut order: Vec<(String, usize)> = Vec::new();
+            let mut seen: HashSet<(String, usize)> = HashSet::new();
+            let mut element_map: HashMap<(String, usize), ElementData> = HashMap::new();
+
+            for field in &t.fields {
+                let key = match &field.key {
+                    FieldKey::Element { name, index } => (name.clone(), *index),
+                    FieldKey::Attribute { element, index, .. } => (element.clone(), *index),
+                };
+                if seen.insert(key.clone()) {
+                    order.push(key.clone());
+                }
+                let entry = element_map.entry(key).or_default();
+                match &field.key {
+                    FieldKey::Element { .. } => entry.text = Some(field.value.clone()),
+                    FieldKey::Attribute { element, attr, .. } => {
+                        // XML allows one of each name; writing both would make a file
+                        // nothing can read, and keeping either would lose a value silently.
+                        if entry.attrs.iter().any(|(k, _)| k == attr) {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("type {}: <{element}> has {attr} twice; remove one before saving", t.name),
+                            ));
+                        }
+                        entry.attrs.push((attr.clone(), field.value.clone()))
+                    }
+                }
+            }
+
+            if canonical_order {
+                order.sort_by_key(|(element, _)| {
+                    canonical_rank(element)
+                });
+            }
+            for (element, index) in order {
+                if let Some(data) = element_map.get(&(element.clone(), index)) {
+                    let mut elem = xml::writer::XmlEvent::start_element(element.as_str());
+                    for (k, v) in &data.attrs {
+                        elem = elem.attr(k.as_str(), v.as_str());
+                    }
+                    writer.write(elem).map_err(to_io)?;
+                    if let Some(text) = &data.text {
+                        writer.write(xml::writer::XmlEvent::characters(text)).map_err(to_io)?;
+                    }
+                    writer.write(xml::writer::XmlEvent::end_element()).map_err(to_io)?;
+                }
+            }
+
+            writer
+                .write(xml::writer::XmlEvent::end_element())
+                .map_err(to_io)?;
+        }
+
+        for entry in changelog {
+            // `--` can't appear inside a comment.
+            let mut entry = entry.clone();
+            while entry.contains("--") {
+                entry = entry.replace("--", "- -");
+            }
+            let text = format!(" {CHANGELOG_MARKER} {entry} ");
+            writer.write(xml::writer::XmlEvent::comment(&text)).map_err(to_io)?;
+        }
+
+        writer
+            .write(xml::writer::XmlEvent::end_element())
+            .map_err(to_io)?;
+    }
+
+    let xml_string = String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(xml_string)
+}
+
+#[derive(Default)]
+struct ElementData {
+    attrs: Vec<(String, String)>,
+    text: Option<String>,
+}
+
+fn to_io<T>(err: T) -> io::Error
+where
+    T: Into<xml::writer::Error>,
+{
+    let err: xml::writer::Error = err.into();
+    io::Error::other(err)
+}
+
+/// The six `<flags>` attributes of `default_fields`, with the same defaults.
+pub fn default_flags() -> Vec<Field> {
+    default_fields().into_iter().filter(|f| f.key.get_element_name() == "flags").collect()
+}
+
+pub fn default_fields() -> Vec<Field> {
+    vec![
+        Field {
+            key: FieldKey::Element {
+                name: "nominal".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "lifetime".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "restock".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "min".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "quantmin".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "quantmax".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Element {
+                name: "cost".to_string(),
+                index: 0,
+            },
+            value: String::new(),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "count_in_cargo".to_string(),
+            },
+            value: String::from("0"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "count_in_hoarder".to_string(),
+            },
+            value: String::from("0"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "count_in_map".to_string(),
+            },
+            value: String::from("1"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "count_in_player".to_string(),
+            },
+            value: String::from("0"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "crafted".to_string(),
+            },
+            value: String::from("0"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "flags".to_string(),
+                index: 0,
+                attr: "deloot".to_string(),
+            },
+            value: String::from("0"),
+        },
+        Field {
+            key: FieldKey::Attribute {
+                element: "category".to_string(),
+                index: 0,
+                attr: "name".to_string(),
+            },
+            value: String::new(),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTE_FORM: &str = r#"<types>
+  <type name="AKM">
+    <nominal>8</nominal>
+    <category name="weapons" />
+    <usage name="Military" />
+    <usage name="Police" />
+    <tag name="shelves" />
+  </type>
+</types>"#;
+
+    const TEXT_FORM: &str = r#"<types>
+  <type name="AKM">
+    <nominal>8</nominal>
+    <category>weapons</category>
+    <usage>Military</usage>
+    <usage name="Police" />
+    <tag>shelves</tag>
+  </type>
+</types>"#;
+
+    fn normalized(content: &str) -> (Vec<TypeEntry>, ParseReport, usize) {
+        let (mut types, report) = parse_types(content).unwrap();
+        let converted = normalize_name_elements(&mut types);
+        (types, report, converted)
+    }
+
+    #[test]
+    fn both_shapes_read_as_the_same_fields() {
+        let (from_attributes, report, converted) = normalized(ATTRIBUTE_FORM);
+        assert_eq!(converted, 0);
+        assert!(report.warnings.is_empty());
+
+        let (from_text, report, converted) = normalized(TEXT_FORM);
+        assert_eq!(converted, 3);
+        assert_eq!(report.warnings.len(), 3);
+        assert!(report.warnings[0].message.contains("<category name=\"weapons\"/>"));
+        assert_eq!(from_text, from_attributes);
+    }
+
+    #[test]
+    fn both_shapes_save_in_attribute_form() {
+        for content in [ATTRIBUTE_FORM, TEXT_FORM] {
+            let (types, _, _) = normalized(content);
+            let saved = serialize_types(&types, false).unwrap();
+            assert!(saved.contains(r#"<category name="weapons" />"#), "{saved}");
+            assert!(saved.contains(r#"<usage name="Military" />"#), "{saved}");
+            assert!(saved.contains(r#"<usage name="Police" />"#), "{saved}");
+            assert!(saved.contains(r#"<tag name="shelves" />"#), "{saved}");
+            assert!(!saved.contains("</category>"), "{saved}");
+
+            let (reread, report, converted) = normalized(&saved);
+            assert_eq!(reread, types);
+            assert!(report.warnings.is_empty());
+            assert_eq!(converted, 0);
+        }
+    }
+
+    #[test]
+    fn text_form_is_kept_without_normalizing() {
+        let (types, _) = parse_types(TEXT_FORM).unwrap();
+        let saved = serialize_types(&types, false).unwrap();
+        assert!(saved.contains("<category>weapons</category>"), "{saved}");
+    }
+
+    /// Values stored decoded, saved escaped: a second save writes the same bytes.
+    fn round_trip(types: &[TypeEntry]) -> String {
+        let saved = serialize_types(types, false).unwrap();
+        let (reread, report) = parse_types(&saved).unwrap_or_else(|e| panic!("{e}\n{saved}"));
+        assert_eq!(reread, types, "{saved}");
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(serialize_types(&reread, false).unwrap(), saved);
+        saved
+    }
+
+    #[test]
+    fn fuzz_corpus_reads_and_saves_stably() {
+        let corpus: [(&str, &[u8]); 6] = [
+            ("vanilla", include_bytes!("../fuzz/corpus/parse_round_trip/vanilla.xml")),
+            ("expansion", include_bytes!("../fuzz/corpus/parse_round_trip/expansion.xml")),
+            ("comments", include_bytes!("../fuzz/corpus/parse_round_trip/comments.xml")),
+            ("crlf", include_bytes!("../fuzz/corpus/parse_round_trip/crlf.xml")),
+            ("latin1", include_bytes!("../fuzz/corpus/parse_round_trip/latin1.xml")),
+            ("truncated", include_bytes!("../fuzz/corpus/parse_round_trip/truncated.xml")),
+        ];
+        for (name, bytes) in corpus {
+            let xml = String::from_utf8_lossy(bytes);
+            let Ok((types, _)) = parse_types(&xml) else {
+                assert_eq!(name, "truncated", "only the truncated file fails to parse");
+                continue;
+            };
+            assert!(!types.is_empty(), "{name}");
+            let first = serialize_types(&types, false).unwrap();
+            let (again, _) = parse_types(&first).unwrap();
+            assert_eq!(again, types, "{name}");
+            assert_eq!(serialize_types(&again, false).unwrap(), first, "{name}");
+        }
+    }
+
+    #[test]
+    fn blank_lines_before_the_declaration_are_read_past() {
+        let content = "\u{feff}\r\n\n<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<types>\n<type><nominal>1</nominal></type>\n</types>\n";
+        let (types, report) = parse_types(content).unwrap();
+        assert_eq!(types.len(), 1);
+        assert!(report.has_bom);
+        assert_eq!(report.leading_whitespace, "\r\n\n");
+        assert_eq!(report.warnings[0].line, 5, "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn changelog_comments_round_trip_and_others_are_dropped() {
+        let content = "<!-- top --><types>\n<!-- hand-written note -->\n<type name=\"A\"><nominal>1<!-- inline --></nominal></type>\n<!-- DayZLootEditor: first -->\n</types>";
+        let (types, report) = parse_types(content).unwrap();
+        assert_eq!(report.changelog, ["first"]);
+        assert_eq!(report.other_comments, 3);
+        let entries = [String::from("first"), String::from("A nominal 1->---2")];
+        let saved = serialize_document(&types, false, &entries).unwrap();
+        assert!(!saved.contains("hand-written"), "{saved}");
+        assert!(saved.ends_with("<!-- DayZLootEditor: A nominal 1->- - -2 -->\n</types>"), "{saved}");
+        let (reread, report) = parse_types(&saved).unwrap();
+        assert_eq!(reread, types);
+        assert_eq!(report.changelog, ["first", "A nominal 1->- - -2"]);
+    }
+
+    #[test]
+    fn markup_characters_round_trip_in_text_and_attributes() {
+        let awkward = ["a & b", "x < y", "y > x", r#"say "hi""#, "it's", "loot 🎁 ✓", "&amp;", "a]]>b", "&#233;"];
+        for value in awkward {
+            let types = vec![TypeEntry {
+                name: format!("T {value}"),
+                fields: vec![
+                    Field { key: FieldKey::Element { name: String::from("nominal"), index: 0 }, value: value.to_string() },
+                    Field {
+                        key: FieldKey::Attribute {
+                            element: String::from("usage"),
+                            index: 0,
+                            attr: String::from("name"),
+                        },
+                        value: value.to_string(),
+                    },
+                ],
+            }];
+            let saved = round_trip(&types);
+            assert!(!saved.contains(" < ") && !saved.contains("a & b"), "{saved}");
+        }
+    }
+
+    #[test]
+    fn dominant_line_ending_is_detected() {
+        assert_eq!(LineEnding::detect("<types/>"), (LineEnding::Lf, false));
+        assert_eq!(LineEnding::detect("a\nb\n"), (LineEnding::Lf, false));
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), (LineEnding::Crlf, false));
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), (LineEnding::Crlf, true));
+        assert_eq!(LineEnding::detect("a\r\nb\n"), (LineEnding::Lf, true));
+    }
+
+    #[test]
+    fn entities_in_the_file_are_read_as_the_characters_they_stand_for() {
+        let content = r#"<types><type name="Caf&#233; &amp; co"><nominal>&amp;amp;</nominal><usage name="&#x1F381;&lt;&gt;&quot;&apos;" /></type></types>"#;
+        let (types, _) = parse_types(content).unwrap();
+        assert_eq!(types[0].name, "Café & co");
+        assert_eq!(types[0].fields[0].value, "&amp;");
+        assert_eq!(types[0].fields[1].value, "🎁<>\"'");
+
+        let saved = round_trip(&types);
+        assert!(saved.contains("Café &amp; co"), "{saved}");
+        assert!(saved.contains("<nominal>&amp;amp;</nominal>"), "{saved}");
+    }
+
+    #[test]
+    fn nested_types_are_read_separately_and_deeper_elements_skipped() {
+        let content = r#"<types>
+  <type name="Outer">
+    <nominal>8</nominal>
+    <type name="Inner"><nominal>3</nominal></type>
+    <min>4</min>
+    <flags count_in_map="1"><flags count_in_map="0"><deep>9</deep></flags></flags>
+  </type>
+</types>"#;
+        let (types, report) = parse_types(content).unwrap();
+        let names: Vec<&str> = types.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Inner", "Outer"]);
+        assert_eq!(types[0].fields.len(), 1);
+        let labels: Vec<String> = types[1].fields.iter().map(|f| f.key.label()).collect();
+        assert_eq!(labels, ["nominal", "min", "flags @count_in_map"]);
+        assert_eq!(types[1].fields[2].value, "1");
+        let messages: Vec<&str> = report.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, [
+            r#"<type name="Inner"> nested inside <type name="Outer"> was read as a separate type"#,
+            "<flags> nested inside <flags> in type Outer ignored",
+        ]);
+    }
+
+    #[test]
+    fn removing_an_element_takes_the_attributes_written_on_it() {
+        let content = r#"<types><type name="A">
+  <usage name="Town">one</usage>
+  <usage name="Village">two</usage>
+  <flags count_in_map="1" deloot="0"/>
+</type></types>"#;
+        let (mut types, _) = parse_types(content).unwrap();
+        let first = FieldKey::Element { name: String::from("usage"), index: 0 };
+        assert_eq!(types[0].remove_fields(|k| *k == first), 2);
+        let labels: Vec<String> = types[0].fields.iter().map(|f| f.key.label()).collect();
+        assert_eq!(labels, ["usage @name", "usage", "flags @count_in_map", "flags @deloot"]);
+        assert_eq!(types[0].remove_fields(|k| k.label() == "flags @deloot"), 1, "an attribute goes alone");
+    }
+
+    #[test]
+    fn an_attribute_repeated_under_a_prefix_keeps_the_last_value_and_is_never_written_twice() {
+        let content = r#"<types xmlns:ce="urn:ce"><type name="A"><flags count_in_map="1" ce:count_in_map="0" deloot="1"/></type></types>"#;
+        let (mut types, report) = parse_types(content).unwrap();
+        let fields: Vec<(String, &str)> = types[0].fields.iter().map(|f| (f.key.label(), f.value.as_str())).collect();
+        assert_eq!(fields, [(String::from("flags @count_in_map"), "0"), (String::from("flags @deloot"), "1")]);
+        let messages: Vec<&str> = report.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, ["<flags> in type A has count_in_map twice; the last value kept"]);
+
+        let copy = types[0].fields[0].clone();
+        types[0].fields.push(copy);
+        let err = serialize_types(&types, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("<flags> has count_in_map twice"), "{err}");
+    }
+
+    #[test]
+    fn pathological_and_truncated_documents_are_errors() {
+        let deep = format!("<types><type name=\"A\">{}{}</type></types>", "<a>".repeat(10_000), "</a>".repeat(10_000));
+        let err = parse_types(&deep).unwrap_err();
+        assert!(err.to_string().contains("nested more than 32 levels"), "{err}");
+
+        let whole = r#"<types><type name="A"><nominal>8</nominal><type name="B"><min>1</min></type></type></types>"#;
+        for cut in 1..whole.len() {
+            assert!(parse_types(&whole[..cut]).is_err(), "{}", &whole[..cut]);
+        }
+    }
+
+    #[test]
+    fn existing_name_attribute_wins_over_text() {
+        let (mut types, _) = parse_types(r#"<types><type name="A"><category name="tools">weapons</category></type></types>"#).unwrap();
+        assert_eq!(normalize_name_elements(&mut types), 0);
+        assert_eq!(types[0].fields.len(), 2);
+    }
+}
@@ -0,0 +1,57 @@
+use tui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+
+use crate::utils;
+
+/// The keys one overlay understands, shown in a panel over it with F1 (or `?` where the
+/// overlay doesn't take typing); Esc goes back to the overlay.
+#[derive(Clone, Copy, Debug)]
+pub struct OverlayHelp {
+    /// What the overlay is, for the panel's title: `Validation report`.
+    pub title: &'static str,
+    /// Each key with what it does there.
+    pub keys: &'static [(&'static str, &'static str)],
+}
+
+impl OverlayHelp {
+    pub const fn new(title: &'static str, keys: &'static [(&'static str, &'static str)]) -> Self {
+        Self { title, keys }
+    }
+
+    /// One row per key, the keys padded to a column.
+    pub fn lines(&self) -> Vec<String> {
+        let width = self.keys.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+        let mut lines: Vec<String> = self
+            .keys
+            .iter()
+            .map(|(key, what)| format!("  {key}{}  {what}", " ".repeat(width - key.chars().count())))
+            .collect();
+        lines.push(String::new());
+        lines.push(String::from("Esc or F1: back"));
+        lines
+    }
+}
+
+/// Draws `help` in a panel over the overlay it belongs to.
+pub fn render<B: tui::backend::Backend>(f: &mut tui::Frame<B>, help: &OverlayHelp) {
+    let area = utils::centered_rect(60, 50, f.size());
+    let block = Block::default().title(format!("Keys: {}", help.title)).borders(Borders::ALL);
+    let text = Paragraph::new(help.lines().join("\n")).wrap(Wrap { trim: false }).block(block);
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_line_up_and_the_way_back_comes_last() {
+        let help = OverlayHelp::new("Validation report", &[("Enter / n", "jump to the next issue"), ("d", "remove repeats")]);
+        assert_eq!(help.lines(), [
+            "  Enter / n  jump to the next issue",
+            "  d          remove repeats",
+            "",
+            "Esc or F1: back",
+        ]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::field_help::{self, FieldHelp};
+
+const APP_DIR: &str = "dayz-loot-editor";
+const CONFIG_FILE: &str = "config.toml";
+/// UI state written by the editor itself, next to the config file.
+const SESSION_FILE: &str = "session.toml";
+
+/// Where the editor keeps its own files. Everything that persists between runs asks
+/// here instead of reading `XDG_CONFIG_HOME`/`HOME` itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppDirs {
+    /// `None` when neither `XDG_CONFIG_HOME` (absolute) nor `HOME` is set.
+    pub config_dir: Option<PathBuf>,
+}
+
+impl AppDirs {
+    /// `$XDG_CONFIG_HOME/dayz-loot-editor`, falling back to `~/.config/dayz-loot-editor`.
+    pub fn locate() -> Self {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+        Self {
+            config_dir: base.map(|base| base.join(APP_DIR)),
+        }
+    }
+
+    pub fn config_file(&self) -> Option<PathBuf> {
+        self.file(CONFIG_FILE)
+    }
+
+    pub fn session_file(&self) -> Option<PathBuf> {
+        self.file(SESSION_FILE)
+    }
+
+    pub fn help_file(&self) -> Option<PathBuf> {
+        self.file(field_help::HELP_FILE)
+    }
+
+    /// The field help a file opens with: the user's help file, then `project_content` (the
+    /// `HELP_FILE` next to it), laid over the embedded texts; plus notes on files left out.
+    pub fn layered_help(&self, project_content: Option<&str>) -> (FieldHelp, Vec<String>) {
+        let user_path = self.help_file();
+        let user_content = user_path.as_ref().and_then(|p| fs::read_to_string(p).ok());
+        let user_place = user_path.map(|p| p.display().to_string()).unwrap_or_default();
+        let project_place = format!("{} next to the file", field_help::HELP_FILE);
+        let files = [(user_place.as_str(), user_content.as_deref()), (project_place.as_str(), project_content)];
+        FieldHelp::layered(files.into_iter().filter_map(|(place, content)| Some((place, content?))))
+    }
+
+    fn file(&self, name: &str) -> Option<PathBuf> {
+        Some(self.config_dir.as_ref()?.join(name))
+    }
+}
+
+/// `path`, or `path (not created)` when nothing is there yet; for the About overlay.
+pub fn describe(path: Option<&Path>) -> String {
+    match path {
+        Some(path) if path.exists() => path.display().to_string(),
+        Some(path) => format!("{} (not created)", path.display()),
+        None => String::from("unavailable (HOME is unset)"),
+    }
+}
+
+/// `--version` output: version, then how this binary was built.
+pub fn version_text() -> String {
+    let profile = if cfg!(debug_assertions) { "debug" } else { "release" };
+    format!(
+        "{} {}\nbuilt for {}-{} ({profile})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH
+    )
+}
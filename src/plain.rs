@@ -0,0 +1,358 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    action::Action,
+    bulk::name_matches,
+    editor::{Editor, LoadMode},
+    remote::{FileSelection, FileSource},
+};
+
+pub const HELP: &str = "Commands:
+  files               .xml files in the current directory, numbered
+  open N|PATH         open file N of `files`, or a path
+  list [PATTERN]      the types, numbered; PATTERN has * and ? wildcards, else it's a substring
+  filter [PATTERN]    keep `list` to matching types; no pattern shows all again
+  show [N|NAME]       a type's fields, numbered; it becomes the current type
+  set FIELD VALUE     on the current type; FIELD is a number from `show` or a label
+                      such as nominal or flags@deloot
+  undo, redo
+  issues              validation issues
+  save
+  :COMMAND            an editor command that works without the full screen, e.g. :cost
+  help
+  quit                (quit! leaves unsaved changes behind)";
+
+/// Line-oriented front end over the same editor (`--plain`): one command per line, results as
+/// plain text, for screen readers, serial consoles and scripts. `prompt` prints `> `
+/// before each line, for interactive use.
+pub fn run(editor: &mut Editor, input: impl BufRead, mut out: impl Write, prompt: bool) -> io::Result<()> {
+    let mut session = Plain {
+        files: Vec::new(),
+        filter: None,
+        current: None,
+    };
+    writeln!(out, "dayz-loot-editor plain mode; `help` lists the commands")?;
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut flow = session.run(editor, line, &mut out)?;
+        // A save can ask several times: about shrinking the file, about low disk space,
+        // then about missing flags.
+        while matches!(flow, Flow::AskShrink | Flow::AskLowSpace | Flow::AskMissingFlags) {
+            let choices = match flow {
+                Flow::AskShrink | Flow::AskLowSpace => "y: save anyway | anything else cancels",
+                _ => "f: add the default flags and save | s: save anyway | anything else cancels",
+            };
+            writeln!(out, "{choices}")?;
+            if prompt {
+                write!(out, "> ")?;
+                out.flush()?;
+            }
+            let answer = lines.next().transpose()?.unwrap_or_default();
+            let action = match (&flow, answer.trim()) {
+                (Flow::AskShrink | Flow::AskLowSpace, "y") => Action::Input('y'),
+                (Flow::AskMissingFlags, "f") => Action::Input('f'),
+                (Flow::AskMissingFlags, "s") => Action::Input('s'),
+                _ => Action::Cancel,
+            };
+            editor.handle_action(action)?;
+            flow = save_question(editor, &mut out)?;
+        }
+        if matches!(flow, Flow::Quit) {
+            break;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+enum Flow {
+    Continue,
+    Quit,
+    /// Save stopped at types without `<flags>`; the next line answers.
+    AskMissingFlags,
+    /// Save would shrink the file drastically; the next line answers.
+    AskShrink,
+    /// The disk the file is saved to is nearly full; the next line answers.
+    AskLowSpace,
+}
+
+struct Plain {
+    /// The last `files` listing, for `open N`.
+    files: Vec<PathBuf>,
+    filter: Option<String>,
+    current: Option<usize>,
+}
+
+impl Plain {
+    fn run(&mut self, editor: &mut Editor, line: &str, out: &mut impl Write) -> io::Result<Flow> {
+        if let Some(command) = line.strip_prefix(':') {
+            let actions = std::iter::once(Action::Command)
+                .chain(command.chars().map(Action::Input))
+                .chain([Action::Activate]);
+            for action in actions {
+                editor.handle_action(action)?;
+            }
+            settle(editor, out)?;
+            return Ok(Flow::Continue);
+        }
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "help" | "?" => writeln!(out, "{HELP}")?,
+            "quit" | "q" if editor.has_unsaved_changes() => {
+                writeln!(out, "There are unsaved changes: `save` first, or `quit!` to leave without them")?;
+            }
+            "quit" | "q" | "quit!" | "q!" => return Ok(Flow::Quit),
+            "files" => self.list_files(out)?,
+            "open" => self.open(editor, rest, out)?,
+            _ if !editor.is_open() => writeln!(out, "No file open; `files` lists them, `open N|PATH` opens one")?,
+            "list" | "ls" => self.list_types(editor, (!rest.is_empty()).then_some(rest), out)?,
+            "filter" => {
+                self.filter = (!rest.is_empty()).then(|| rest.to_string());
+                self.list_types(editor, None, out)?;
+            }
+            "show" => self.show(editor, rest, out)?,
+            "set" => self.set(editor, rest, out)?,
+            "undo" | "redo" => {
+                editor.handle_action(if command == "undo" { Action::Undo } else { Action::Redo })?;
+                settle(editor, out)?;
+            }
+            "issues" => {
+                let issues = editor.issues();
+                if issues.is_empty() {
+                    writeln!(out, "No validation issues")?;
+                }
+                for issue in issues {
+                    let name = editor.types().get(issue.type_idx).map(|t| t.name.as_str()).unwrap_or("?");
+                    writeln!(out, "{:>5}  {name}: {}", issue.type_idx + 1, issue.message)?;
+                }
+            }
+            "save" => {
+                editor.handle_action(Action::Save)?;
+                return save_question(editor, out);
+            }
+            other => writeln!(out, "Unknown command '{other}'; `help` lists the commands")?,
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn list_files(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut files: Vec<PathBuf> = fs::read_dir(".")?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|e| e.eq_ignore_ascii_case("xml")))
+            .collect();
+        files.sort();
+        if files.is_empty() {
+            writeln!(out, "No .xml files here; `open PATH` opens one elsewhere")?;
+        }
+        for (i, path) in files.iter().enumerate() {
+            writeln!(out, "{:>5}  {}", i + 1, path.display())?;
+        }
+        self.files = files;
+        Ok(())
+    }
+
+    fn open(&mut self, editor: &mut Editor, target: &str, out: &mut impl Write) -> io::Result<()> {
+        if editor.has_unsaved_changes() {
+            writeln!(out, "There are unsaved changes: `save` them first")?;
+            return Ok(());
+        }
+        let path = match target.parse::<usize>() {
+            Ok(n) if (1..=self.files.len()).contains(&n) => self.files[n - 1].clone(),
+            Ok(_) if !Path::new(target).exists() => {
+                writeln!(out, "No file {target}; `files` lists {}", self.files.len())?;
+                return Ok(());
+            }
+            _ if target.is_empty() => {
+                writeln!(out, "Usage: open N|PATH")?;
+                return Ok(());
+            }
+            _ => PathBuf::from(target),
+        };
+        match editor.load(FileSelection { path, source: FileSource::Local }, LoadMode::Fresh) {
+            Ok(()) => {
+                self.current = None;
+                writeln!(out, "{}", editor.status())
+            }
+            Err(err) => writeln!(out, "Failed to open file: {err}"),
+        }
+    }
+
+    fn list_types(&self, editor: &Editor, pattern: Option<&str>, out: &mut impl Write) -> io::Result<()> {
+        let patterns: Vec<String> = self.filter.as_deref().into_iter().chain(pattern).map(glob).collect();
+        let mut shown = 0;
+        for (i, ty) in editor.types().iter().enumerate() {
+            if patterns.iter().all(|p| name_matches(p, &ty.name)) {
+                let mark = if self.current == Some(i) { '*' } else { ' ' };
+                writeln!(out, "{:>5}{mark} {}", i + 1, ty.name)?;
+                shown += 1;
+            }
+        }
+        writeln!(out, "{shown} of {} types", editor.types().len())
+    }
+
+    fn show(&mut self, editor: &mut Editor, target: &str, out: &mut impl Write) -> io::Result<()> {
+        let found = if target.is_empty() {
+            self.current
+        } else {
+            match target.parse::<usize>() {
+                Ok(n) => n.checked_sub(1).filter(|&i| i < editor.types().len()),
+                Err(_) => editor.types().iter().position(|t| t.name.eq_ignore_ascii_case(target)),
+            }
+        };
+        let Some(idx) = found else {
+            writeln!(out, "No such type; `list` shows their numbers")?;
+            return Ok(());
+        };
+        self.current = Some(idx);
+        editor.select_type(idx);
+        let ty = &editor.types()[idx];
+        writeln!(out, "{} ({} of {})", ty.name, idx + 1, editor.types().len())?;
+        for (i, field) in ty.fields.iter().enumerate() {
+            writeln!(out, "{:>5}  {}: {}", i + 1, field.key.label(), field.value)?;
+        }
+        for issue in editor.issues().iter().filter(|issue| issue.type_idx == idx) {
+            writeln!(out, "  issue: {}", issue.message)?;
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, editor: &mut Editor, args: &str, out: &mut impl Write) -> io::Result<()> {
+        let Some(type_idx) = self.current else {
+            writeln!(out, "No current type; `show N|NAME` picks one")?;
+            return Ok(());
+        };
+        let Some((field, value)) = args.split_once(char::is_whitespace) else {
+            writeln!(out, "Usage: set FIELD VALUE, e.g. set nominal 20")?;
+            return Ok(());
+        };
+        // An undo or a `:` command may have taken the type away since `show`.
+        let Some(ty) = editor.types().get(type_idx) else {
+            self.current = None;
+            writeln!(out, "The current type is gone; `show N|NAME` picks one")?;
+            return Ok(());
+        };
+        let wanted = field.replace(' ', "");
+        let field_idx = match field.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).filter(|&i| i < ty.fields.len()),
+            Err(_) => ty.fields.iter().position(|f| f.key.label().replace(' ', "").eq_ignore_ascii_case(&wanted)),
+        };
+        let Some(field_idx) = field_idx else {
+            writeln!(out, "No field '{field}' on {}; `show` lists them", ty.name)?;
+            return Ok(());
+        };
+        editor.set_value(type_idx, field_idx, value.trim());
+        settle(editor, out)
+    }
+}
+
+/// `AK` → `*AK*`: a pattern without wildcards matches as a substring.
+fn glob(pattern: &str) -> String {
+    if pattern.contains(['*', '?']) {
+        pattern.to_string()
+    } else {
+        format!("*{pattern}*")
+    }
+}
+
+/// After a save step: the question the save stopped at, with its status, or the settled
+/// result.
+fn save_question(editor: &mut Editor, out: &mut impl Write) -> io::Result<Flow> {
+    let flow = if editor.asks_about_shrink() {
+        Flow::AskShrink
+    } else if editor.asks_about_space() {
+        Flow::AskLowSpace
+    } else if editor.asks_about_missing_flags() {
+        Flow::AskMissingFlags
+    } else {
+        settle(editor, out)?;
+        return Ok(Flow::Continue);
+    };
+    writeln!(out, "{}", editor.status())?;
+    Ok(flow)
+}
+
+/// Waits out background work, prints read-only lists, closes anything else that needs the
+/// full screen, then prints the status.
+fn settle(editor: &mut Editor, out: &mut impl Write) -> io::Result<()> {
+    while editor.is_busy() {
+        editor.tick()?;
+        thread::sleep(Duration::from_millis(50));
+    }
+    if let Some((title, lines)) = editor.overlay_text() {
+        writeln!(out, "{title}")?;
+        for line in lines {
+            writeln!(out, "  {line}")?;
+        }
+        editor.handle_action(Action::Cancel)?;
+        return Ok(());
+    }
+    // Overlays, and prompts a command leaves open (a bare `:addusage` offering its last
+    // arguments), are both for the screen.
+    if editor.has_overlay() || editor.is_editing() {
+        editor.handle_action(Action::Cancel)?;
+        writeln!(out, "That needs the full-screen editor (run without --plain)")?;
+        return Ok(());
+    }
+    writeln!(out, "{}", editor.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{editor_for, Fixture, SAMPLE};
+
+    /// `input` run through plain mode, and what it printed.
+    fn session(editor: &mut Editor, input: &str) -> String {
+        let mut out = Vec::new();
+        run(editor, input.as_bytes(), &mut out, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn open_show_set_and_save() {
+        let fixture = Fixture::new();
+        let first = fixture.write("first.xml", SAMPLE);
+        let second = fixture.write("second.xml", &SAMPLE.replace("AKM", "SKS"));
+        let mut editor = editor_for(&first);
+        let out = session(&mut editor, &format!("open {}\nshow SKS\nset nominal 20\nset bogus 1\nsave\nquit\n", second.display()));
+        assert!(out.contains("SKS (1 of 3)\n    1  nominal: 8\n"), "{out}");
+        assert!(out.contains("Value updated"), "{out}");
+        assert!(out.contains("No field 'bogus' on SKS; `show` lists them"), "{out}");
+        assert!(fixture.read("second.xml").contains("<nominal>20</nominal>"));
+        assert!(fixture.read("first.xml").contains("<nominal>8</nominal>"));
+
+        let out = session(&mut editor, "open 7\nfrobnicate\n");
+        assert!(out.contains("No file 7; `files` lists 0"), "{out}");
+        assert!(out.contains("Unknown command 'frobnicate'; `help` lists the commands"), "{out}");
+    }
+
+    #[test]
+    fn set_on_a_type_that_is_gone_asks_for_another() {
+        let fixture = Fixture::new();
+        let mut editor = editor_for(&fixture.write("types.xml", SAMPLE));
+        let mut plain = Plain { files: Vec::new(), filter: None, current: Some(3) };
+        let mut out = Vec::new();
+        plain.run(&mut editor, "set nominal 20", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "The current type is gone; `show N|NAME` picks one\n");
+        assert_eq!(plain.current, None);
+    }
+}
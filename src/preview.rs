@@ -0,0 +1,195 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{model, remote::FileSource, sniff};
+
+/// At most this much of a file is read for its preview.
+const PREVIEW_BYTES: usize = 16 * 1024;
+/// Raw lines shown under the summary.
+const PREVIEW_LINES: usize = 30;
+/// The cursor has to rest this long before a file is read, so scrolling over SSH stays quick.
+const PREVIEW_DELAY: Duration = Duration::from_millis(300);
+
+/// What the preview pane shows for one file.
+pub struct Preview {
+    pub summary: Vec<String>,
+    pub lines: Vec<String>,
+}
+
+/// Summarises the first bytes of a file of `size` bytes without parsing it.
+pub fn summarize(size: u64, prefix: &[u8]) -> Preview {
+    let text = String::from_utf8_lossy(prefix);
+    let truncated = (prefix.len() as u64) < size;
+    let mut summary = vec![format!("size: {}", sniff::format_size(size))];
+    match model::root_element(&text) {
+        Some(root) => summary.push(format!("root: {root}")),
+        None => summary.push(String::from("root: not found in the first bytes")),
+    }
+    let count = text
+        .match_indices("<type")
+        .filter(|(at, _)| text[at + 5..].starts_with(char::is_whitespace))
+        .count();
+    if count > 0 {
+        summary.push(if truncated {
+            // Entries are about the same size, so scale the count to the whole file.
+            let estimate = (count as f64 * size as f64 / prefix.len() as f64).round();
+            format!("~{estimate} <type> entries (estimated)")
+        } else {
+            format!("{count} <type> entries")
+        });
+    }
+    if let Some(first) = first_type_name(&text) {
+        summary.push(format!("first: {first}"));
+    }
+    let mut lines: Vec<String> = text.lines().take(PREVIEW_LINES).map(|l| l.replace('\t', "  ")).collect();
+    // The last line of a truncated read is usually cut off mid-way.
+    if truncated && text.lines().count() <= PREVIEW_LINES {
+        lines.pop();
+    }
+    Preview { summary, lines }
+}
+
+fn first_type_name(text: &str) -> Option<&str> {
+    let start = text.find("<type name=\"")? + "<type name=\"".len();
+    let len = text[start..].find('"')?;
+    Some(&text[start..start + len])
+}
+
+/// Debounced, background preview of the highlighted file.
+pub enum PreviewState {
+    /// Nothing highlighted that can be previewed (a directory, a non-.xml file).
+    Idle(String),
+    /// Highlighted since `since`; read once the cursor has rested long enough.
+    Waiting { path: PathBuf, since: Instant },
+    Loading { path: PathBuf, rx: Receiver<Result<Preview, String>> },
+    Ready { path: PathBuf, result: Result<Preview, String> },
+}
+
+impl PreviewState {
+    fn path(&self) -> Option<&PathBuf> {
+        match self {
+            PreviewState::Idle(_) => None,
+            PreviewState::Waiting { path, .. } | PreviewState::Loading { path, .. } | PreviewState::Ready { path, .. } => {
+                Some(path)
+            }
+        }
+    }
+
+    /// Follows the cursor: `target` is the highlighted .xml file, or why there's none.
+    pub fn follow(&mut self, target: Result<PathBuf, String>) {
+        match target {
+            Ok(path) if self.path() == Some(&path) => {}
+            Ok(path) => {
+                *self = PreviewState::Waiting {
+                    path,
+                    since: Instant::now(),
+                }
+            }
+            Err(reason) => *self = PreviewState::Idle(reason),
+        }
+    }
+
+    /// Starts the read once the delay is over and picks up a finished one.
+    pub fn tick(&mut self, source: &FileSource) {
+        match self {
+            PreviewState::Waiting { path, since } if since.elapsed() >= PREVIEW_DELAY => {
+                let (tx, rx) = mpsc::channel();
+                let (source, file) = (source.clone(), path.clone());
+                thread::spawn(move || {
+                    let result = source
+                        .read_prefix(&file, PREVIEW_BYTES)
+                        .map(|(size, prefix)| summarize(size, &prefix))
+                        .map_err(|e| e.to_string());
+                    let _ = tx.send(result);
+                });
+                *self = PreviewState::Loading { path: path.clone(), rx };
+            }
+            PreviewState::Loading { path, rx } => {
+                let result = match rx.try_recv() {
+                    Ok(result) => result,
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => Err(String::from("preview worker exited")),
+                };
+                *self = PreviewState::Ready {
+                    path: path.clone(),
+                    result,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Text for the pane.
+    pub fn text(&self) -> String {
+        match self {
+            PreviewState::Idle(reason) => reason.clone(),
+            PreviewState::Waiting { .. } | PreviewState::Loading { .. } => String::from("Reading..."),
+            PreviewState::Ready { result: Ok(preview), .. } => {
+                format!("{}\n\n{}", preview.summary.join("\n"), preview.lines.join("\n"))
+            }
+            PreviewState::Ready { result: Err(err), .. } => format!("Can't preview: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{Fixture, SAMPLE};
+
+    #[test]
+    fn summaries_count_types_and_estimate_from_a_truncated_read() {
+        let whole = summarize(SAMPLE.len() as u64, SAMPLE.as_bytes());
+        assert_eq!(whole.summary, ["size: 516 B", "root: types", "3 <type> entries", "first: AKM"]);
+        assert_eq!(whole.lines.len(), SAMPLE.lines().count());
+
+        // The first type of a file three times as long, cut off in the middle of a line.
+        let end = SAMPLE.find("</type>").unwrap() + "</type>\n  <ty".len();
+        let part = summarize(3 * end as u64, &SAMPLE.as_bytes()[..end]);
+        assert_eq!(part.summary[1..], ["root: types", "~3 <type> entries (estimated)", "first: AKM"]);
+        assert_eq!(part.lines.last().map(String::as_str), Some("  </type>"), "the cut-off line is dropped");
+
+        let other = summarize(7, b"garbage");
+        assert_eq!(other.summary, ["size: 7 B", "root: not found in the first bytes"]);
+    }
+
+    #[test]
+    fn files_are_read_once_the_cursor_rests_and_failures_are_shown() {
+        let fixture = Fixture::new();
+        let path = fixture.write("types.xml", SAMPLE);
+        let mut state = PreviewState::Idle(String::new());
+        state.follow(Ok(path.clone()));
+        state.tick(&FileSource::Local);
+        assert!(matches!(state, PreviewState::Waiting { .. }), "not read before the delay");
+        assert_eq!(state.text(), "Reading...");
+
+        if let PreviewState::Waiting { since, .. } = &mut state {
+            *since -= PREVIEW_DELAY;
+        }
+        // Highlighting the same file again doesn't restart the wait.
+        state.follow(Ok(path.clone()));
+        while !matches!(state, PreviewState::Ready { .. }) {
+            state.tick(&FileSource::Local);
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(state.text().starts_with("size: "), "{}", state.text());
+        assert!(state.text().contains("first: AKM\n\n<?xml"), "{}", state.text());
+
+        state.follow(Err(String::from("A directory")));
+        assert_eq!(state.text(), "A directory");
+
+        state.follow(Ok(fixture.dir.join("missing.xml")));
+        if let PreviewState::Waiting { since, .. } = &mut state {
+            *since -= PREVIEW_DELAY;
+        }
+        while !matches!(state, PreviewState::Ready { .. }) {
+            state.tick(&FileSource::Local);
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(state.text().starts_with("Can't preview: "), "{}", state.text());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Gauge, Paragraph},
+};
+
+use crate::{sniff, utils};
+
+/// Handed to the worker: reports progress and tells it when to stop.
+pub struct Reporter {
+    tx: Sender<(usize, usize, String)>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Reporter {
+    /// A reporter nobody listens to and nothing cancels, for running a job's work in place.
+    pub fn silent() -> Self {
+        let (tx, _) = mpsc::channel();
+        Self {
+            tx,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Reports `done` of `total` items; returns `false` once the job was cancelled.
+    pub fn step(&self, done: usize, total: usize, label: impl Into<String>) -> bool {
+        let _ = self.tx.send((done, total, label.into()));
+        !self.cancelled()
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Work running on a worker thread, polled once per main loop iteration.
+pub struct Job<T> {
+    pub title: String,
+    pub done: usize,
+    pub total: usize,
+    /// What the worker is on right now, e.g. a file name.
+    pub label: String,
+    /// `done` and `total` count bytes: shown as sizes with a transfer rate.
+    pub bytes: bool,
+    started: Instant,
+    steps: Receiver<(usize, usize, String)>,
+    result: Receiver<T>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Starts `work` on its own thread. It should call `Reporter::step` per item and return
+/// early once that says the job was cancelled.
+pub fn spawn<T: Send + 'static>(title: impl Into<String>, work: impl FnOnce(&Reporter) -> T + Send + 'static) -> Job<T> {
+    let (step_tx, steps) = mpsc::channel();
+    let (result_tx, result) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let reporter = Reporter {
+        tx: step_tx,
+        cancel: cancel.clone(),
+    };
+    thread::spawn(move || {
+        let _ = result_tx.send(work(&reporter));
+    });
+    Job {
+        title: title.into(),
+        done: 0,
+        total: 0,
+        label: String::new(),
+        bytes: false,
+        started: Instant::now(),
+        steps,
+        result,
+        cancel,
+    }
+}
+
+impl<T> Job<T> {
+    /// Marks the job as a transfer whose steps are byte counts.
+    pub fn in_bytes(mut self) -> Self {
+        self.bytes = true;
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Takes in the latest progress; `Some` once the worker has finished.
+    pub fn poll(&mut self) -> Option<io::Result<T>> {
+        while let Ok((done, total, label)) = self.steps.try_recv() {
+            self.done = done;
+            self.total = total;
+            self.label = label;
+        }
+        match self.result.try_recv() {
+            Ok(result) => Some(Ok(result)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(io::Error::other("worker thread exited"))),
+        }
+    }
+
+    /// Asks the worker to stop at its next step.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress bar overlay for a running job.
+pub fn render<B: tui::backend::Backend, T>(f: &mut tui::Frame<B>, job: &Job<T>) {
+    let area = utils::centered_rect(60, 20, f.size());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+        .split(area);
+    let ratio = if job.total == 0 { 0.0 } else { (job.done as f64 / job.total as f64).min(1.0) };
+    let hint = if job.is_cancelled() { "cancelling..." } else { "Esc: cancel" };
+    let gauge = Gauge::default()
+        .block(Block::default().title(format!("{} ({hint})", job.title)).borders(Borders::ALL))
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio)
+        .label(if job.bytes {
+            transfer_summary(job.done as u64, job.total as u64, job.elapsed())
+        } else {
+            format!("{} / {}", job.done, job.total)
+        });
+    let label = Paragraph::new(job.label.clone()).block(Block::default().borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(gauge, chunks[0]);
+    f.render_widget(label, chunks[1]);
+}
+
+/// `1.2 MB / 4.2 MB, 512.0 KB/s` for a transfer in progress.
+pub fn transfer_summary(done: u64, total: u64, elapsed: Duration) -> String {
+    let rate = (done as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+    format!("{} / {}, {}/s", sniff::format_size(done), sniff::format_size(total), sniff::format_size(rate))
+}
+
+/// `4.2 MB in 7.8 s`, for the message log once a transfer is done.
+pub fn transfer_done(bytes: u64, elapsed: Duration) -> String {
+    format!("{} in {:.1} s", sniff::format_size(bytes), elapsed.as_secs_f64())
+}
@@ -3,10 +3,18 @@ use std::{
     io::{self, Read, Write},
     net::TcpStream,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::UNIX_EPOCH,
 };
 
-use ssh2::{Session, Sftp};
+use serde::{Deserialize, Serialize};
+use ssh2::{Agent, Session, Sftp};
+
+use crate::{config::SshLayer, utils, watch::FileStamp};
 
 #[derive(Clone)]
 pub enum FileSource {
@@ -20,6 +28,294 @@ pub struct FileSelection {
     pub source: FileSource,
 }
 
+impl FileSource {
+    pub fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self {
+            FileSource::Local => std::fs::read_to_string(path),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .read_file(path),
+        }
+    }
+
+    /// File size and at most `limit` leading bytes, without reading the rest.
+    pub fn read_prefix(&self, path: &Path, limit: usize) -> io::Result<(u64, Vec<u8>)> {
+        match self {
+            FileSource::Local => {
+                let file = std::fs::File::open(path)?;
+                let size = file.metadata()?.len();
+                let mut prefix = Vec::with_capacity(limit);
+                file.take(limit as u64).read_to_end(&mut prefix)?;
+                Ok((size, prefix))
+            }
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .read_prefix(path, limit),
+        }
+    }
+
+    /// Directory listing, locally or over SFTP.
+    pub fn list_dir(&self, dir: &Path) -> io::Result<Vec<DirEntry>> {
+        match self {
+            FileSource::Local => Ok(std::fs::read_dir(dir)?.flatten().filter_map(|entry| local_entry(&entry)).collect()),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .list_dir(dir),
+        }
+    }
+
+    /// Directory listing handed over `batch` entries at a time, for folders too big to wait
+    /// for; `each` returns `false` to stop early. Over SFTP the backend is only locked while
+    /// a batch is read, so other work gets a turn in between.
+    pub fn list_dir_batched(
+        &self,
+        dir: &Path,
+        batch: usize,
+        each: &mut dyn FnMut(Vec<DirEntry>) -> bool,
+    ) -> io::Result<()> {
+        match self {
+            FileSource::Local => {
+                let mut entries = std::fs::read_dir(dir)?.flatten().peekable();
+                while entries.peek().is_some() {
+                    let chunk = entries.by_ref().take(batch).filter_map(|entry| local_entry(&entry));
+                    if !each(chunk.collect()) {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            FileSource::Remote(client) => {
+                let lock = || client.lock().map_err(|_| io::Error::other("SSH backend in use"));
+                let mut handle = lock()?.open_dir(dir)?;
+                loop {
+                    let (chunk, done) = {
+                        let backend = lock()?;
+                        read_dir_batch(&backend.sftp, dir, &mut handle, batch)?
+                    };
+                    if (!chunk.is_empty() && !each(chunk)) || done {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// `path` with every symlink in it followed: what a save through it should write.
+    pub fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        match self {
+            FileSource::Local => std::fs::canonicalize(path),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .realpath(path),
+        }
+    }
+
+    pub fn exists(&self, path: &Path) -> io::Result<bool> {
+        match self {
+            FileSource::Local => path.try_exists(),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .exists(path),
+        }
+    }
+
+    /// Size and mtime, for telling whether the file changed since it was last read.
+    pub fn stamp(&self, path: &Path) -> io::Result<FileStamp> {
+        match self {
+            FileSource::Local => FileStamp::local(path),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .stamp(path),
+        }
+    }
+
+    /// Renames within one machine; callers check that `to` is free first.
+    pub fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        match self {
+            FileSource::Local => std::fs::rename(from, to),
+            FileSource::Remote(client) => client
+                .lock()
+                .map_err(|_| io::Error::other("SSH backend in use"))?
+                .rename(from, to),
+        }
+    }
+
+    /// Deletes a file, or a directory only if it is empty.
+    pub fn remove(&self, path: &Path, is_dir: bool) -> io::Result<()> {
+        match self {
+            FileSource::Local if is_dir => std::fs::remove_dir(path),
+            FileSource::Local => std::fs::remove_file(path),
+            FileSource::Remote(client) => {
+                let client = client.lock().map_err(|_| io::Error::other("SSH backend in use"))?;
+                if is_dir { client.remove_dir(path) } else { client.remove_file(path) }
+            }
+        }
+    }
+
+    /// Bytes available to this user on the filesystem holding `dir`; `None` where that
+    /// can't be told (SFTP servers without the statvfs extension, non-Unix systems).
+    pub fn free_space(&self, dir: &Path) -> Option<u64> {
+        match self {
+            FileSource::Local => local_free_space(dir),
+            FileSource::Remote(client) => client.lock().ok()?.free_space(dir),
+        }
+    }
+
+    /// Whether both point at the same machine (local, or the same SSH session).
+    pub fn same_as(&self, other: &FileSource) -> bool {
+        match (self, other) {
+            (FileSource::Local, FileSource::Local) => true,
+            (FileSource::Remote(a), FileSource::Remote(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A local listing entry; a symlink is described by its target, or by the link itself
+/// when the target is missing.
+fn local_entry(entry: &std::fs::DirEntry) -> Option<DirEntry> {
+    let link = entry.file_type().is_ok_and(|t| t.is_symlink());
+    let meta = std::fs::metadata(entry.path()).or_else(|_| entry.metadata()).ok()?;
+    Some(DirEntry {
+        name: entry.file_name().to_string_lossy().into_owned(),
+        is_dir: meta.is_dir(),
+        link,
+        size: meta.len(),
+        modified: meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+    })
+}
+
+// The statvfs field widths differ between platforms; they're u64 on Linux.
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)]
+fn local_free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stats` is a writable statvfs.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some(u64::from(stats.f_bavail).saturating_mul(u64::from(stats.f_frsize)))
+}
+
+#[cfg(not(unix))]
+fn local_free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+impl FileSelection {
+    /// `types.xml` or `ssh:types.xml`, for titles and reports.
+    pub fn short_label(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string());
+        match self.source {
+            FileSource::Local => name,
+            FileSource::Remote(_) => format!("ssh:{name}"),
+        }
+    }
+}
+
+/// How `SshBackend::connect` logs in (`SSH_AUTH`, `[ssh] auth`, the form's Auth field).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMethod {
+    Agent,
+    Key,
+    Password,
+}
+
+impl AuthMethod {
+    pub const ALL: [Self; 3] = [Self::Agent, Self::Key, Self::Password];
+
+    /// `agent`, `key` or `password`, in any case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "agent" => Some(Self::Agent),
+            "key" => Some(Self::Key),
+            "password" => Some(Self::Password),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Agent => "agent",
+            Self::Key => "key file",
+            Self::Password => "password",
+        }
+    }
+
+    /// The method when none is named: the key when there is one, else the password, else
+    /// the agent, as connecting always picked.
+    pub fn implied(key_path: Option<&Path>, password: Option<&str>) -> Self {
+        match (key_path, password) {
+            (Some(_), _) => Self::Key,
+            (None, Some(_)) => Self::Password,
+            (None, None) => Self::Agent,
+        }
+    }
+}
+
+/// The keys the SSH agent holds, by comment (usually `user@host` or the key's file name).
+/// The error says why the agent couldn't be asked, e.g. that `SSH_AUTH_SOCK` is unset.
+pub fn agent_identities() -> io::Result<Vec<String>> {
+    let session = Session::new().map_err(|e| io::Error::other(format!("Failed to create SSH session: {e}")))?;
+    let agent = connect_agent(&session)?;
+    Ok(agent.identities().map_err(agent_error)?.iter().map(|key| key.comment().to_string()).collect())
+}
+
+fn agent_error(err: ssh2::Error) -> io::Error {
+    io::Error::other(format!("SSH agent: {err}"))
+}
+
+/// The agent, connected and with its identities listed.
+fn connect_agent(session: &Session) -> io::Result<Agent> {
+    if env::var_os("SSH_AUTH_SOCK").is_none_or(|sock| sock.is_empty()) {
+        return Err(io::Error::other("SSH agent: SSH_AUTH_SOCK is not set, so there is no agent to ask"));
+    }
+    let mut agent = session.agent().map_err(agent_error)?;
+    agent.connect().map_err(|e| io::Error::other(format!("SSH agent unreachable: {e}")))?;
+    agent.list_identities().map_err(agent_error)?;
+    Ok(agent)
+}
+
+/// Offers the agent's keys one by one; the error names those the server turned down.
+fn userauth_agent(session: &Session, username: &str) -> io::Result<()> {
+    let mut agent = connect_agent(session)?;
+    let identities = agent.identities().map_err(agent_error)?;
+    if identities.is_empty() {
+        return Err(io::Error::other("SSH agent auth: the agent holds no keys (ssh-add adds one)"));
+    }
+    let mut tried = Vec::new();
+    for key in &identities {
+        if agent.userauth(username, key).is_ok() {
+            let _ = agent.disconnect();
+            return Ok(());
+        }
+        tried.push(key.comment().to_string());
+    }
+    let _ = agent.disconnect();
+    Err(io::Error::other(format!(
+        "SSH agent auth: {username} was refused with all {} ({})",
+        utils::plural(tried.len(), "agent key"),
+        tried.join(", ")
+    )))
+}
+
 #[derive(Clone, Debug)]
 pub struct RemoteConfig {
     pub host: String,
@@ -28,9 +324,14 @@ pub struct RemoteConfig {
     pub password: Option<String>,
     pub key_path: Option<PathBuf>,
     pub passphrase: Option<String>,
+    pub auth: AuthMethod,
+    /// Files opened through this connection start read-only (`SSH_READONLY=1`).
+    pub readonly: bool,
 }
 
 impl RemoteConfig {
+    /// The `SSH_*` variables; `SSH_AUTH` picks the method, which otherwise follows from
+    /// whether `SSH_KEY` or `SSH_PASSWORD` is set.
     pub fn from_env() -> Option<Self> {
         let host = env::var("SSH_HOST").ok()?;
         let username = env::var("SSH_USER").ok()?;
@@ -41,6 +342,11 @@ impl RemoteConfig {
         let password = env::var("SSH_PASSWORD").ok();
         let key_path = env::var("SSH_KEY").ok().map(PathBuf::from);
         let passphrase = env::var("SSH_PASSPHRASE").ok();
+        let readonly = env::var("SSH_READONLY").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "yes"));
+        let auth = env::var("SSH_AUTH")
+            .ok()
+            .and_then(|v| AuthMethod::parse(&v))
+            .unwrap_or_else(|| AuthMethod::implied(key_path.as_deref(), password.as_deref()));
 
         Some(Self {
             host,
@@ -49,54 +355,206 @@ impl RemoteConfig {
             password,
             key_path,
             passphrase,
+            auth,
+            readonly,
         })
     }
+
+    /// The `[ssh]` config profile, when it names at least a host and a user.
+    pub fn from_profile(profile: &SshLayer) -> Option<Self> {
+        Some(Self {
+            host: profile.host.clone()?,
+            port: profile.port.unwrap_or(22),
+            username: profile.user.clone()?,
+            password: None,
+            key_path: profile.key_path.clone(),
+            passphrase: None,
+            auth: profile.auth.unwrap_or_else(|| AuthMethod::implied(profile.key_path.as_deref(), None)),
+            readonly: profile.readonly.unwrap_or(false),
+        })
+    }
+}
+
+/// A file given on the command line as `ssh://user@host[:port]/path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SshUri {
+    pub username: String,
+    pub host: String,
+    pub port: u16,
+    pub path: PathBuf,
+}
+
+impl SshUri {
+    /// `None` when `arg` isn't an `ssh://` URI at all, else the URI or what's wrong with it.
+    pub fn parse(arg: &str) -> Option<Result<Self, String>> {
+        let rest = arg.strip_prefix("ssh://")?;
+        let parsed = (|| {
+            let (authority, path) = rest.split_at(rest.find('/').ok_or("it names no file after the host")?);
+            let (username, address) = authority.split_once('@').ok_or("it needs a user: ssh://user@host/path")?;
+            let (host, port) = match address.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse().map_err(|_| format!("'{port}' is not a port"))?),
+                None => (address, 22),
+            };
+            if username.is_empty() || host.is_empty() {
+                return Err(String::from("it needs a user and a host: ssh://user@host/path"));
+            }
+            if path.len() < 2 || path.ends_with('/') {
+                return Err(String::from("it names no file after the host"));
+            }
+            Ok(Self {
+                username: username.to_string(),
+                host: host.to_string(),
+                port,
+                path: PathBuf::from(path),
+            })
+        })();
+        Some(parsed.map_err(|err: String| format!("{arg}: {err}")))
+    }
+
+    /// The connection for this URI. The key, password and login method come from `base`
+    /// (the `SSH_*` variables or `[ssh]` profile) when it is for the same host, else the
+    /// SSH agent is asked.
+    pub fn remote_config(&self, base: Option<RemoteConfig>) -> RemoteConfig {
+        let same_host = base.filter(|base| base.host == self.host);
+        RemoteConfig {
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            password: same_host.as_ref().and_then(|b| b.password.clone()),
+            key_path: same_host.as_ref().and_then(|b| b.key_path.clone()),
+            passphrase: same_host.as_ref().and_then(|b| b.passphrase.clone()),
+            auth: same_host.as_ref().map_or(AuthMethod::Agent, |b| b.auth),
+            readonly: same_host.is_some_and(|b| b.readonly),
+        }
+    }
 }
 
+/// Upper bound for a single blocking SSH call, so a dropped link surfaces as an error instead of a hang.
+const SESSION_TIMEOUT_MS: u32 = 10_000;
+/// The same for the liveness probe, which holds the session while it waits.
+const PROBE_TIMEOUT_MS: u32 = 2_000;
+/// Bytes moved between progress reports during a download or upload.
+const TRANSFER_CHUNK: usize = 64 * 1024;
+/// Uploads go to `<file>.part` and are renamed over the file once complete.
+const PARTIAL_SUFFIX: &str = ".part";
+
+pub type ConnectResult = io::Result<Arc<Mutex<SshBackend>>>;
+
 pub struct SshBackend {
-    _session: Session,
+    session: Session,
     sftp: Sftp,
+    config: RemoteConfig,
 }
 
 impl SshBackend {
     pub fn connect(config: &RemoteConfig) -> io::Result<Arc<Mutex<Self>>> {
         let tcp = TcpStream::connect((&*config.host, config.port))
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH connect: {e}")))?;
+            .map_err(|e| io::Error::other(format!("SSH connect: {e}")))?;
         let mut session = Session::new()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to create SSH session: {e}")))?;
+            .map_err(|e| io::Error::other(format!("Failed to create SSH session: {e}")))?;
         session.set_tcp_stream(tcp);
+        session.set_timeout(SESSION_TIMEOUT_MS);
         session
             .handshake()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH handshake: {e}")))?;
-
-        if let Some(ref key) = config.key_path {
-            session
-                .userauth_pubkey_file(
-                    &config.username,
-                    None,
-                    key,
-                    config.passphrase.as_deref(),
-                )
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH key auth: {e}")))?;
-        } else if let Some(ref pwd) = config.password {
-            session
-                .userauth_password(&config.username, pwd)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH password auth: {e}")))?;
-        } else {
-            session
-                .userauth_agent(&config.username)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH agent auth: {e}")))?;
+            .map_err(|e| io::Error::other(format!("SSH handshake: {e}")))?;
+
+        match config.auth {
+            AuthMethod::Key => {
+                let key = config
+                    .key_path
+                    .as_deref()
+                    .ok_or_else(|| io::Error::other("SSH key auth: no key file given"))?;
+                session
+                    .userauth_pubkey_file(&config.username, None, key, config.passphrase.as_deref())
+                    .map_err(|e| io::Error::other(format!("SSH key auth: {e}")))?;
+            }
+            AuthMethod::Password => {
+                session
+                    .userauth_password(&config.username, config.password.as_deref().unwrap_or_default())
+                    .map_err(|e| io::Error::other(format!("SSH password auth: {e}")))?;
+            }
+            AuthMethod::Agent => userauth_agent(&session, &config.username)?,
         }
 
         if !session.authenticated() {
-            return Err(io::Error::new(io::ErrorKind::Other, "SSH authentication failed"));
+            return Err(io::Error::other("SSH authentication failed"));
         }
 
         let sftp = session
             .sftp()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SSH SFTP init: {e}")))?;
+            .map_err(|e| io::Error::other(format!("SSH SFTP init: {e}")))?;
+
+        Ok(Arc::new(Mutex::new(Self {
+            session,
+            sftp,
+            config: config.clone(),
+        })))
+    }
+
+    /// Runs `connect` on a worker thread; the result arrives on the returned channel.
+    pub fn connect_in_background(config: RemoteConfig) -> Receiver<ConnectResult> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(Self::connect(&config));
+        });
+        rx
+    }
+
+    pub fn config(&self) -> &RemoteConfig {
+        &self.config
+    }
+
+    /// The file's size and mtime; doubles as the liveness round trip when watching.
+    pub fn stamp(&self, path: &Path) -> io::Result<FileStamp> {
+        let stat = self
+            .sftp
+            .stat(path)
+            .map_err(|e| io::Error::other(format!("SFTP stat: {e}")))?;
+        Ok(FileStamp {
+            size: stat.size.unwrap_or(0),
+            modified: stat.mtime.map(|secs| u128::from(secs) * 1_000_000_000),
+        })
+    }
+
+    /// Whether `path` is there; only the server's "no such file" counts as no, any other
+    /// failure is passed on.
+    pub fn exists(&self, path: &Path) -> io::Result<bool> {
+        match self.sftp.stat(path) {
+            Ok(_) => Ok(true),
+            Err(e) if e.code() == ssh2::ErrorCode::SFTP(LIBSSH2_FX_NO_SUCH_FILE) => Ok(false),
+            Err(e) => Err(io::Error::other(format!("SFTP stat: {e}"))),
+        }
+    }
+
+    /// `stamp` under a short timeout, for the background liveness check: a dead link then
+    /// keeps the session from other work for seconds, not `SESSION_TIMEOUT_MS`.
+    pub fn probe(&self, path: &Path) -> io::Result<FileStamp> {
+        self.session.set_timeout(PROBE_TIMEOUT_MS);
+        let stamp = self.stamp(path);
+        self.session.set_timeout(SESSION_TIMEOUT_MS);
+        stamp
+    }
 
-        Ok(Arc::new(Mutex::new(Self { _session: session, sftp })))
+    /// Cheap round trip used to tell whether the session is still usable.
+    pub fn check_alive(&self, path: &Path) -> io::Result<()> {
+        self.sftp
+            .stat(path)
+            .map(|_| ())
+            .map_err(|e| io::Error::other(format!("SFTP stat: {e}")))
+    }
+
+    /// `statvfs@openssh.com` on `dir`; `None` when the server doesn't offer it.
+    pub fn free_space(&self, dir: &Path) -> Option<u64> {
+        let stats = self.sftp.opendir(dir).ok()?.statvfs().ok()?;
+        let block = if stats.f_frsize > 0 { stats.f_frsize } else { stats.f_bsize };
+        Some(stats.f_bavail.saturating_mul(block))
+    }
+
+    /// An open directory handle for `read_dir_batch`.
+    pub fn open_dir(&self, path: &Path) -> io::Result<ssh2::File> {
+        self.sftp
+            .opendir(path)
+            .map_err(|e| io::Error::other(format!("SFTP opendir: {e}")))
     }
 
     pub fn list_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
@@ -104,44 +562,314 @@ impl SshBackend {
         for (p, stat) in self
             .sftp
             .readdir(path)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SFTP readdir: {e}")))? {
+            .map_err(|e| io::Error::other(format!("SFTP readdir: {e}")))? {
             let Some(name) = p.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()) else {
                 continue;
             };
-            let is_dir = is_dir(&stat);
-            entries.push(DirEntry { name, is_dir });
+            entries.push(remote_entry(&self.sftp, path, name, stat));
         }
         Ok(entries)
     }
 
+    /// The server's `realpath` of `path`, symlinks followed.
+    pub fn realpath(&self, path: &Path) -> io::Result<PathBuf> {
+        self.sftp
+            .realpath(path)
+            .map_err(|e| io::Error::other(format!("SFTP realpath: {e}")))
+    }
+
     pub fn read_file(&self, path: &Path) -> io::Result<String> {
+        self.read_file_with_progress(path, &mut |_, _| true)
+    }
+
+    /// Downloads in chunks, calling `progress(done, total)` in bytes after each; when it
+    /// returns `false` the download stops with `ErrorKind::Interrupted`.
+    pub fn read_file_with_progress(
+        &self,
+        path: &Path,
+        progress: &mut dyn FnMut(u64, u64) -> bool,
+    ) -> io::Result<String> {
         let mut file = self
             .sftp
             .open(path)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SFTP open: {e}")))?;
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)?;
-        Ok(buf)
+            .map_err(|e| io::Error::other(format!("SFTP open: {e}")))?;
+        let total = file.stat().ok().and_then(|stat| stat.size).unwrap_or(0);
+        let mut buf = Vec::with_capacity(total as usize);
+        let mut chunk = vec![0; TRANSFER_CHUNK];
+        loop {
+            let n = file.read(&mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let done = buf.len() as u64;
+            if !progress(done, total.max(done)) {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "download cancelled"));
+            }
+        }
+        String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns the file size and at most `limit` leading bytes without downloading the rest.
+    pub fn read_prefix(&self, path: &Path, limit: usize) -> io::Result<(u64, Vec<u8>)> {
+        let size = self
+            .sftp
+            .stat(path)
+            .map_err(|e| io::Error::other(format!("SFTP stat: {e}")))?
+            .size
+            .unwrap_or(0);
+        let file = self
+            .sftp
+            .open(path)
+            .map_err(|e| io::Error::other(format!("SFTP open: {e}")))?;
+        let mut buf = Vec::with_capacity(limit);
+        file.take(limit as u64).read_to_end(&mut buf)?;
+        Ok((size, buf))
     }
 
     pub fn write_file(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.write_file_with_progress(path, contents, &mut |_, _| {})
+    }
+
+    /// Uploads to `<path>.part` in chunks, calling `progress(done, total)` after each, then
+    /// moves it over `path`; a dropped connection never leaves `path` half-written, and the
+    /// partial file is removed when the upload fails.
+    pub fn write_file_with_progress(
+        &self,
+        path: &Path,
+        contents: &str,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> io::Result<()> {
+        self.write_via_partial(path, contents, None, progress).map(|_| ())
+    }
+
+    /// `write_file_with_progress`, keeping the current file as `backup`: once the upload is
+    /// complete it is renamed there before the new one takes its place, so the backup costs
+    /// no transfer. A failed rename comes back as `Ok(Some(error))` with the new file saved
+    /// anyway, or, with `require`, fails the save before `path` is touched.
+    pub fn write_file_with_backup(
+        &self,
+        path: &Path,
+        contents: &str,
+        backup: &Path,
+        require: bool,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> io::Result<Option<io::Error>> {
+        self.write_via_partial(path, contents, Some((backup, require)), progress)
+    }
+
+    fn write_via_partial(
+        &self,
+        path: &Path,
+        contents: &str,
+        backup: Option<(&Path, bool)>,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> io::Result<Option<io::Error>> {
+        let partial = partial_path(path);
+        if let Err(err) = self.upload(&partial, contents.as_bytes(), progress) {
+            let _ = self.sftp.unlink(&partial);
+            return Err(err);
+        }
+        // Keep the original's permissions on the file that replaces it.
+        if let Ok(stat) = self.sftp.stat(path) {
+            let perm = ssh2::FileStat { size: None, uid: None, gid: None, perm: stat.perm, atime: None, mtime: None };
+            let _ = self.sftp.setstat(&partial, perm);
+        }
+        move_into_place(self, &partial, path, backup)
+    }
+
+    fn upload(&self, path: &Path, bytes: &[u8], progress: &mut dyn FnMut(u64, u64)) -> io::Result<()> {
         let mut file = self
             .sftp
             .create(path)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("SFTP create: {e}")))?;
-        file.write_all(contents.as_bytes())?;
+            .map_err(|e| io::Error::other(format!("SFTP create: {e}")))?;
+        let mut done = 0;
+        for chunk in bytes.chunks(TRANSFER_CHUNK) {
+            file.write_all(chunk)?;
+            done += chunk.len() as u64;
+            progress(done, bytes.len() as u64);
+        }
         Ok(())
     }
+
+    /// Renames `from` over `to`, which may exist; see `replace`.
+    pub fn replace(&self, from: &Path, to: &Path) -> io::Result<()> {
+        replace(self, from, to)
+    }
+
+    /// A plain rename, which fails when `to` exists; `replace` is the way over a file.
+    pub fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.sftp
+            .rename(from, to, Some(ssh2::RenameFlags::ATOMIC | ssh2::RenameFlags::NATIVE))
+            .map_err(|e| {
+                // Keep `NotFound`: backup rotation skips generations that don't exist.
+                let message = format!("SFTP rename: {e}");
+                io::Error::new(io::Error::from(e).kind(), message)
+            })
+    }
+
+    pub fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.sftp
+            .unlink(path)
+            .map_err(|e| io::Error::other(format!("SFTP unlink: {e}")))
+    }
+
+    /// Fails unless the directory is empty.
+    pub fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.sftp
+            .rmdir(path)
+            .map_err(|e| io::Error::other(format!("SFTP rmdir: {e}")))
+    }
+}
+
+/// The renames and removals around an upload, apart from the transfer itself, so the order
+/// they happen in can be tested without a server.
+pub trait RemoteFiles {
+    /// Fails when `to` exists, as SFTP v3 renames (OpenSSH's among them) do.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> io::Result<bool>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+}
+
+impl RemoteFiles for SshBackend {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        SshBackend::rename(self, from, to)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        SshBackend::exists(self, path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        SshBackend::remove_file(self, path)
+    }
+}
+
+/// `<path>.part`, where uploads go before they replace `path`.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    PathBuf::from(partial)
+}
+
+/// `<path>.replaced`, where `replace` keeps the file it is replacing until the rename is done.
+fn aside_path(path: &Path) -> PathBuf {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(".replaced");
+    PathBuf::from(aside)
+}
+
+/// Renames `from` over `to`. Where `to` exists and the server won't rename over it, `to` is
+/// moved aside first, moved back if `from` then can't take its place, and only removed
+/// once it has: at no point is neither file there.
+pub fn replace(files: &impl RemoteFiles, from: &Path, to: &Path) -> io::Result<()> {
+    let err = match files.rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+    if !files.exists(to)? {
+        return Err(err);
+    }
+    let aside = aside_path(to);
+    // Left over from an interrupted replace whose rename went through; `to` is newer.
+    if files.exists(&aside)? {
+        files.remove_file(&aside)?;
+    }
+    files.rename(to, &aside)?;
+    if let Err(err) = files.rename(from, to) {
+        let _ = files.rename(&aside, to);
+        return Err(err);
+    }
+    let _ = files.remove_file(&aside);
+    Ok(())
+}
+
+/// Puts a complete upload at `partial` in place of `path`, first renaming the current file
+/// to `backup` if one is given; see `SshBackend::write_file_with_backup` for what `require`
+/// does. When the upload can't take its place the backup is moved back and `partial` is
+/// kept, so neither the old contents nor the new are lost.
+pub fn move_into_place(
+    files: &impl RemoteFiles,
+    partial: &Path,
+    path: &Path,
+    backup: Option<(&Path, bool)>,
+) -> io::Result<Option<io::Error>> {
+    let mut backup_error = None;
+    let mut backed_up = None;
+    if let Some((backup, require)) = backup {
+        match replace(files, path, backup) {
+            Ok(()) => backed_up = Some(backup),
+            Err(err) if require => {
+                let _ = files.remove_file(partial);
+                return Err(io::Error::new(err.kind(), format!("backup failed: {err}")));
+            }
+            Err(err) => backup_error = Some(err),
+        }
+    }
+    if let Err(err) = replace(files, partial, path) {
+        if let Some(backup) = backed_up {
+            let _ = files.rename(backup, path);
+        }
+        return Err(io::Error::new(err.kind(), format!("{err}; the upload is kept as {}", partial.display())));
+    }
+    Ok(backup_error)
 }
 
 pub struct DirEntry {
     pub name: String,
     pub is_dir: bool,
+    /// A symbolic link; `is_dir`, `size` and `modified` describe what it points to.
+    pub link: bool,
+    pub size: u64,
+    /// Seconds since the Unix epoch, when the server reports it.
+    pub modified: Option<u64>,
 }
 
 const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
 const S_IFMT: u32 = 0o170000;
 
+/// libssh2's "no more entries" from `readdir`.
+const LIBSSH2_ERROR_FILE: i32 = -16;
+/// SFTP status `SSH_FX_NO_SUCH_FILE`.
+const LIBSSH2_FX_NO_SUCH_FILE: i32 = 2;
+
+/// Up to `batch` entries of an open directory, without `.` and `..`; `true` once the end
+/// is reached.
+fn read_dir_batch(sftp: &Sftp, dir: &Path, handle: &mut ssh2::File, batch: usize) -> io::Result<(Vec<DirEntry>, bool)> {
+    let mut entries = Vec::new();
+    while entries.len() < batch {
+        match handle.readdir() {
+            Ok((name, stat)) => {
+                let Some(name) = name.to_str().map(String::from) else {
+                    continue;
+                };
+                if name == "." || name == ".." {
+                    continue;
+                }
+                entries.push(remote_entry(sftp, dir, name, stat));
+            }
+            Err(e) if e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_FILE) => return Ok((entries, true)),
+            Err(e) => return Err(io::Error::other(format!("SFTP readdir: {e}"))),
+        }
+    }
+    Ok((entries, false))
+}
+
+/// A remote listing entry. `readdir` reports a symlink itself, so links get one more
+/// `stat` for their target; a dangling link stays described by the link.
+fn remote_entry(sftp: &Sftp, dir: &Path, name: String, stat: ssh2::FileStat) -> DirEntry {
+    let link = stat.perm.is_some_and(|p| p & S_IFMT == S_IFLNK);
+    let stat = if link { sftp.stat(&dir.join(&name)).unwrap_or(stat) } else { stat };
+    DirEntry {
+        name,
+        is_dir: is_dir(&stat),
+        link,
+        size: stat.size.unwrap_or(0),
+        modified: stat.mtime,
+    }
+}
+
 fn is_dir(stat: &ssh2::FileStat) -> bool {
     stat.perm
         .map(|p| p & S_IFMT == S_IFDIR)
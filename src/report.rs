@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use crate::{diff::TypesDiff, model::TypeEntry, summary::TypeSummary, utils};
+
+/// Markdown summary of a diff for people who review loot changes outside the editor.
+/// `types` are the saved ones: added types are listed with their key numbers.
+pub fn change_report(file: &str, diff: &TypesDiff, types: &[TypeEntry], custom_tiers: &[String], unix_secs: u64) -> String {
+    let (y, mo, d, h, mi, _) = utils::utc_datetime(unix_secs);
+    let mut out = format!("# Loot changes: {file}\n\n{y:04}-{mo:02}-{d:02} {h:02}:{mi:02} UTC\n\n");
+    out.push_str(&format!(
+        "{} changed, {} added, {} removed\n",
+        utils::plural(diff.changed.len(), "type"),
+        diff.added.len(),
+        diff.removed.len()
+    ));
+    if !diff.changed.is_empty() {
+        out.push_str("\n## Changed\n");
+        for change in &diff.changed {
+            out.push_str(&format!("\n### {}\n\n", change.name));
+            for field in &change.fields {
+                let label = field.key.label();
+                out.push_str(&match (&field.old, &field.new) {
+                    (Some(old), Some(new)) => format!("- {label}: {old} → {new}\n"),
+                    (None, Some(new)) => format!("- {label}: added ({new})\n"),
+                    (Some(old), None) => format!("- {label}: removed (was {old})\n"),
+                    (None, None) => continue,
+                });
+            }
+        }
+    }
+    for (title, names) in [("Added", &diff.added), ("Removed", &diff.removed)] {
+        if !names.is_empty() {
+            out.push_str(&format!("\n## {title}\n\n"));
+            for name in names {
+                // Removed types aren't among the saved ones, so only added ones get numbers.
+                let saved = types.iter().find(|t| &t.name == name);
+                let line = saved.map_or_else(|| name.clone(), |ty| TypeSummary::of(ty, custom_tiers).line(name));
+                out.push_str(&format!("- {line}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// Types whose field changes an inline changelog entry spells out; the rest are counted.
+const CHANGELOG_DETAIL: usize = 3;
+
+/// One line for the `inline_changelog` comment:
+/// `2026-10-16 14:03 UTC by Alex: AKM nominal 8->15, cost +100; added Pear; removed 2 types`.
+pub fn changelog_entry(diff: &TypesDiff, unix_secs: u64, author: Option<&str>) -> String {
+    let (y, mo, d, h, mi, _) = utils::utc_datetime(unix_secs);
+    let by = author.map(|a| format!(" by {a}")).unwrap_or_default();
+    let mut parts: Vec<String> = diff
+        .changed
+        .iter()
+        .take(CHANGELOG_DETAIL)
+        .map(|change| {
+            let fields: Vec<String> = change
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    let label = field.key.label();
+                    Some(match (&field.old, &field.new) {
+                        (Some(old), Some(new)) => format!("{label} {old}->{new}"),
+                        (None, Some(new)) => format!("{label} +{new}"),
+                        (Some(old), None) => format!("{label} -{old}"),
+                        (None, None) => return None,
+                    })
+                })
+                .collect();
+            format!("{} {}", change.name, fields.join(", "))
+        })
+        .collect();
+    if diff.changed.len() > CHANGELOG_DETAIL {
+        parts.push(format!("{} more changed", utils::plural(diff.changed.len() - CHANGELOG_DETAIL, "type")));
+    }
+    for (verb, names) in [("added", &diff.added), ("removed", &diff.removed)] {
+        match names.as_slice() {
+            [] => {}
+            [name] => parts.push(format!("{verb} {name}")),
+            _ => parts.push(format!("{verb} {}", utils::plural(names.len(), "type"))),
+        }
+    }
+    format!("{y:04}-{mo:02}-{d:02} {h:02}:{mi:02} UTC{by}: {}", parts.join("; "))
+}
+
+/// `types.xml.changes-20261016-140305.md` next to `path`.
+pub fn report_path(path: &Path, unix_secs: u64) -> PathBuf {
+    let (y, mo, d, h, mi, s) = utils::utc_datetime(unix_secs);
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{name}.changes-{y:04}{mo:02}{d:02}-{h:02}{mi:02}{s:02}.md"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diff::diff_types, model::parse_types};
+
+    const BEFORE: &str = r#"<types>
+  <type name="AKM"><nominal>8</nominal><min>4</min><flags deloot="0" /><usage name="Military" /></type>
+  <type name="Apple"><nominal>40</nominal></type>
+  <type name="Mosin9130"><nominal>12</nominal></type>
+</types>"#;
+
+    const AFTER: &str = r#"<types>
+  <type name="AKM"><nominal>15</nominal><min>4</min><flags deloot="1" /><cost>100</cost></type>
+  <type name="Mosin9130"><nominal>12</nominal></type>
+  <type name="Pear"><nominal>20</nominal></type>
+</types>"#;
+
+    #[test]
+    fn report_matches_golden_output() {
+        let (before, _) = parse_types(BEFORE).unwrap();
+        let (after, _) = parse_types(AFTER).unwrap();
+        // 2026-10-16 14:03:05 UTC
+        let report = change_report("types.xml", &diff_types(&before, &after), &after, &[], 1_792_159_385);
+        let expected = "# Loot changes: types.xml
+
+2026-10-16 14:03 UTC
+
+1 type changed, 1 added, 1 removed
+
+## Changed
+
+### AKM
+
+- nominal: 8 → 15
+- flags @deloot: 0 → 1
+- cost: added (100)
+- usage @name: removed (was Military)
+
+## Added
+
+- Pear — nom 20
+
+## Removed
+
+- Apple
+";
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn changelog_entry_fits_on_one_line() {
+        let (before, _) = parse_types(BEFORE).unwrap();
+        let (after, _) = parse_types(AFTER).unwrap();
+        let entry = changelog_entry(&diff_types(&before, &after), 1_792_159_385, Some("Alex"));
+        assert_eq!(
+            entry,
+            "2026-10-16 14:03 UTC by Alex: AKM nominal 8->15, flags @deloot 0->1, cost +100, usage @name -Military; added Pear; removed Apple"
+        );
+        let entry = changelog_entry(&diff_types(&after, &[]), 1_792_159_385, None);
+        assert_eq!(entry, "2026-10-16 14:03 UTC: removed 3 types");
+    }
+
+    #[test]
+    fn report_path_sits_next_to_the_file() {
+        assert_eq!(
+            report_path(Path::new("/srv/mission/db/types.xml"), 1_792_159_385),
+            PathBuf::from("/srv/mission/db/types.xml.changes-20261016-140305.md")
+        );
+        assert_eq!(utils::utc_datetime(951_782_400), (2000, 2, 29, 0, 0, 0));
+    }
+}
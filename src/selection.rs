@@ -0,0 +1,111 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::model::TypeEntry;
+
+/// The types marked with Space, kept by name and occurrence (the second `AKM` in a file
+/// with a duplicate is `("AKM", 1)`) rather than by position, so inserting, removing or
+/// reordering other types, undo and reloading from disk leave the marks on the same types.
+#[derive(Clone, Debug, Default)]
+pub struct TypeSelection {
+    keys: BTreeSet<(String, usize)>,
+}
+
+/// Name and occurrence of `types[idx]`.
+fn key_of(types: &[TypeEntry], idx: usize) -> Option<(String, usize)> {
+    let name = &types.get(idx)?.name;
+    Some((name.clone(), types[..idx].iter().filter(|t| &t.name == name).count()))
+}
+
+impl TypeSelection {
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Marks `types[idx]` or unmarks it if it was marked; true when it is marked now.
+    pub fn toggle(&mut self, types: &[TypeEntry], idx: usize) -> bool {
+        let Some(key) = key_of(types, idx) else {
+            return false;
+        };
+        if self.keys.remove(&key) {
+            return false;
+        }
+        self.keys.insert(key)
+    }
+
+    /// Unmarks `types[idx]`; true when it was marked.
+    pub fn remove(&mut self, types: &[TypeEntry], idx: usize) -> bool {
+        key_of(types, idx).is_some_and(|key| self.keys.remove(&key))
+    }
+
+    pub fn insert(&mut self, types: &[TypeEntry], idx: usize) {
+        if let Some(key) = key_of(types, idx) {
+            self.keys.insert(key);
+        }
+    }
+
+    /// Positions of the marked types that are in `types`, in file order.
+    pub fn indices(&self, types: &[TypeEntry]) -> BTreeSet<usize> {
+        if self.keys.is_empty() {
+            return BTreeSet::new();
+        }
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let mut out = BTreeSet::new();
+        for (i, ty) in types.iter().enumerate() {
+            let nth = seen.entry(ty.name.as_str()).or_insert(0);
+            if self.keys.contains(&(ty.name.clone(), *nth)) {
+                out.insert(i);
+            }
+            *nth += 1;
+        }
+        out
+    }
+
+    /// Forgets marks on types that are no longer there.
+    pub fn retain_existing(&mut self, types: &[TypeEntry]) {
+        let present: BTreeSet<(String, usize)> = self.indices(types).into_iter().filter_map(|i| key_of(types, i)).collect();
+        self.keys = present;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    fn named(name: &str) -> TypeEntry {
+        TypeEntry { name: name.to_string(), fields: Vec::new() }
+    }
+
+    #[test]
+    fn marks_follow_the_types_through_inserts_removals_and_sorting() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        let mut selection = TypeSelection::default();
+        assert!(selection.toggle(&types, 1));
+        assert!(selection.toggle(&types, 2));
+        types.insert(0, named("Pear"));
+        types.insert(2, named("Plum"));
+        let marked = |types: &[TypeEntry], selection: &TypeSelection| {
+            selection.indices(types).into_iter().map(|i| types[i].name.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(marked(&types, &selection), ["Apple", "Mosin9130"]);
+        types.remove(1);
+        types.sort_by(|a, b| b.name.cmp(&a.name));
+        assert_eq!(marked(&types, &selection), ["Mosin9130", "Apple"]);
+        assert!(!selection.toggle(&types, 2), "toggling a marked type unmarks it");
+        assert_eq!(marked(&types, &selection), ["Apple"]);
+
+        // A second type of the same name isn't marked along, and marks on types that are
+        // gone can be dropped.
+        types.push(named("Apple"));
+        assert_eq!(selection.indices(&types).len(), 1);
+        types.retain(|t| t.name != "Apple");
+        selection.retain_existing(&types);
+        assert!(selection.indices(&types).is_empty());
+        types.push(named("Apple"));
+        assert!(selection.indices(&types).is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! The first-run setup wizard (also `:setup`): theme, navigation keys, an optional SSH
+//! profile and backup retention, written to the user config file.
+
+use std::path::PathBuf;
+
+use tui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+
+use crate::{
+    action::Action,
+    config::{ConfigLayer, SshLayer, Theme},
+    file_picker::RemoteForm,
+    remote::{AuthMethod, RemoteConfig},
+    utils,
+};
+
+/// Backup counts offered, with `backup_keep`'s default among them.
+const BACKUP_CHOICES: [usize; 4] = [0, 1, 3, 5];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Theme,
+    Navigation,
+    Ssh,
+    SshForm,
+    Backups,
+    Confirm,
+}
+
+/// What the wizard left behind: the user layer now in effect and a line for the status bar.
+pub struct SetupDone {
+    pub layer: ConfigLayer,
+    pub message: String,
+}
+
+pub struct SetupWizard {
+    path: PathBuf,
+    /// The config file as it was; the answers are laid over it.
+    base: ConfigLayer,
+    /// Skipping writes `base` anyway, so the wizard doesn't come back on the next start.
+    first_run: bool,
+    step: Step,
+    /// Highlighted option on the current choice step.
+    cursor: usize,
+    theme: Theme,
+    vim_keys: bool,
+    ssh: Option<RemoteForm>,
+    backup_keep: usize,
+}
+
+impl SetupWizard {
+    pub fn new(path: PathBuf, base: ConfigLayer, first_run: bool) -> Self {
+        let mut wizard = Self {
+            theme: base.theme.unwrap_or_default(),
+            vim_keys: base.vim_keys.unwrap_or(true),
+            backup_keep: base.backup_keep.unwrap_or(1),
+            ssh: RemoteConfig::from_profile(&base.ssh).map(|profile| RemoteForm::new(Some(profile))),
+            path,
+            base,
+            first_run,
+            step: Step::Theme,
+            cursor: 0,
+        };
+        wizard.cursor = wizard.current_choice();
+        wizard
+    }
+
+    pub fn handle_action(&mut self, action: Action) -> Option<SetupDone> {
+        if action == Action::Cancel {
+            return Some(self.skip());
+        }
+        if self.step == Step::SshForm {
+            let form = self.ssh.as_mut()?;
+            if !form.edit(&action) && action == Action::Activate {
+                self.go_to(Step::Backups);
+            }
+            return None;
+        }
+        match action {
+            Action::Up | Action::BackTab => self.cursor = self.cursor.saturating_sub(1),
+            Action::Down | Action::Tab => self.cursor = (self.cursor + 1).min(self.options().len().saturating_sub(1)),
+            Action::Backspace => self.back(),
+            Action::Activate => return self.choose(),
+            _ => {}
+        }
+        None
+    }
+
+    fn options(&self) -> Vec<String> {
+        match self.step {
+            Step::Theme => vec![
+                String::from("default: coloured panes, markers and messages"),
+                String::from("mono: no colours; highlights use reverse video"),
+            ],
+            Step::Navigation => vec![
+                String::from("arrows and h/j/k/l (vim style)"),
+                String::from("arrows only; h/j/k/l are ordinary keys"),
+            ],
+            Step::Ssh => vec![
+                String::from("no profile; r in the file picker asks each time"),
+                String::from("set up a profile"),
+            ],
+            Step::Backups => BACKUP_CHOICES
+                .iter()
+                .map(|&n| match n {
+                    0 => String::from("0: no backups"),
+                    1 => String::from("1: types.xml.bak"),
+                    n => format!("{n}: types.xml.bak, .bak.1 ... .bak.{}", n - 1),
+                })
+                .collect(),
+            Step::SshForm | Step::Confirm => Vec::new(),
+        }
+    }
+
+    /// The option matching the answer so far, so going back shows it highlighted.
+    fn current_choice(&self) -> usize {
+        match self.step {
+            Step::Theme => usize::from(self.theme == Theme::Mono),
+            Step::Navigation => usize::from(!self.vim_keys),
+            Step::Ssh => usize::from(self.ssh.is_some()),
+            Step::Backups => BACKUP_CHOICES.iter().position(|&n| n == self.backup_keep).unwrap_or(1),
+            Step::SshForm | Step::Confirm => 0,
+        }
+    }
+
+    fn go_to(&mut self, step: Step) {
+        self.step = step;
+        self.cursor = self.current_choice();
+    }
+
+    fn back(&mut self) {
+        let previous = match self.step {
+            Step::Theme => Step::Theme,
+            Step::Navigation => Step::Theme,
+            Step::Ssh => Step::Navigation,
+            Step::SshForm => Step::Ssh,
+            Step::Backups if self.ssh.is_some() => Step::SshForm,
+            Step::Backups => Step::Ssh,
+            Step::Confirm => Step::Backups,
+        };
+        self.go_to(previous);
+    }
+
+    fn choose(&mut self) -> Option<SetupDone> {
+        match self.step {
+            Step::Theme => {
+                self.theme = if self.cursor == 1 { Theme::Mono } else { Theme::Default };
+                self.go_to(Step::Navigation);
+            }
+            Step::Navigation => {
+                self.vim_keys = self.cursor == 0;
+                self.go_to(Step::Ssh);
+            }
+            Step::Ssh if self.cursor == 1 => {
+                self.ssh.get_or_insert_with(|| RemoteForm::new(None));
+                self.go_to(Step::SshForm);
+            }
+            Step::Ssh => {
+                self.ssh = None;
+                self.go_to(Step::Backups);
+            }
+            Step::Backups => {
+                self.backup_keep = BACKUP_CHOICES[self.cursor.min(BACKUP_CHOICES.len() - 1)];
+                self.go_to(Step::Confirm);
+            }
+            Step::SshForm => {}
+            Step::Confirm => return Some(self.finish()),
+        }
+        None
+    }
+
+    /// The base layer with the answers applied.
+    fn answers(&self) -> ConfigLayer {
+        let mut layer = self.base.clone();
+        layer.theme = Some(self.theme);
+        layer.vim_keys = Some(self.vim_keys);
+        layer.backup_keep = Some(self.backup_keep);
+        layer.ssh = match self.ssh.as_ref().map(RemoteForm::to_config) {
+            Some(remote) if !remote.host.trim().is_empty() => SshLayer {
+                host: Some(remote.host.trim().to_string()),
+                user: Some(remote.username.trim().to_string()).filter(|u| !u.is_empty()),
+                port: Some(remote.port).filter(|&p| p != 22),
+                auth: Some(remote.auth).filter(|&auth| auth != AuthMethod::implied(remote.key_path.as_deref(), None)),
+                key_path: remote.key_path,
+                readonly: self.base.ssh.readonly,
+                missions_root: self.base.ssh.missions_root.clone(),
+            },
+            _ => SshLayer::default(),
+        };
+        layer
+    }
+
+    fn finish(&self) -> SetupDone {
+        let layer = self.answers();
+        let message = match layer.write_to(&self.path) {
+            Ok(()) => format!("Setup saved to {}", self.path.display()),
+            Err(err) => format!("Could not write {}: {err}; the settings apply to this session only", self.path.display()),
+        };
+        SetupDone { layer, message }
+    }
+
+    fn skip(&self) -> SetupDone {
+        let layer = self.base.clone();
+        if !self.first_run {
+            return SetupDone { layer, message: String::from("Setup cancelled; config unchanged") };
+        }
+        let message = match layer.write_to(&self.path) {
+            Ok(()) => format!("Setup skipped; wrote {} (:setup in the editor runs it again)", self.path.display()),
+            Err(err) => format!("Setup skipped; could not write {}: {err}", self.path.display()),
+        };
+        SetupDone { layer, message }
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>) {
+        let area = utils::centered_rect(70, 70, f.size());
+        let (number, title, question) = match self.step {
+            Step::Theme => (1, "Theme", "How should the editor look?"),
+            Step::Navigation => (2, "Navigation", "Which keys move around?"),
+            Step::Ssh => (3, "SSH profile", "Do you edit files on a server over SSH?"),
+            Step::SshForm => (3, "SSH profile", "Profile for r in the file picker. Passwords and passphrases aren't saved; use a key or SSH_PASSWORD."),
+            Step::Backups => (4, "Backups", "How many backups should a save keep?"),
+            Step::Confirm => (5, "Save", "This will be written to"),
+        };
+        let mut lines = vec![question.to_string(), String::new()];
+        match self.step {
+            Step::SshForm => {
+                lines.extend(self.ssh.iter().flat_map(RemoteForm::lines));
+                lines.extend([String::new(), String::from("Up/Down: field, Enter: next, Backspace: erase, Ctrl+U: clear, Esc: skip setup")]);
+            }
+            Step::Confirm => {
+                lines.push(self.path.display().to_string());
+                lines.push(String::new());
+                let toml = self.answers().to_toml().unwrap_or_else(|err| format!("({err})"));
+                lines.extend(toml.lines().map(|line| format!("  {line}")));
+                lines.extend([
+                    String::new(),
+                    String::from("An existing file keeps its comments and other keys; only changed settings are rewritten."),
+                    String::from("Enter: save, Backspace: back, Esc: skip setup"),
+                ]);
+            }
+            _ => {
+                lines.extend(self.options().iter().enumerate().map(|(i, option)| {
+                    let marker = if i == self.cursor { ">" } else { " " };
+                    format!("{marker} {option}")
+                }));
+                lines.extend([String::new(), String::from("Up/Down: choose, Enter: next, Backspace: back, Esc: skip setup")]);
+            }
+        }
+        let block = Block::default().title(format!("Setup {number}/5: {title}")).borders(Borders::ALL);
+        let body = Paragraph::new(lines.join("\n")).wrap(Wrap { trim: false }).block(block);
+        f.render_widget(Clear, area);
+        f.render_widget(body, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::Fixture;
+
+    fn drive(wizard: &mut SetupWizard, actions: &[Action]) -> Option<SetupDone> {
+        actions.iter().find_map(|action| wizard.handle_action(action.clone()))
+    }
+
+    fn typed(text: &str) -> Vec<Action> {
+        text.chars().map(Action::Input).collect()
+    }
+
+    #[test]
+    fn answers_are_written_and_read_back() {
+        let fixture = Fixture::new();
+        let path = fixture.dir.join("config/config.toml");
+        let mut wizard = SetupWizard::new(path.clone(), ConfigLayer::default(), true);
+        let mut actions = vec![Action::Down, Action::Activate, Action::Down, Action::Activate, Action::Down, Action::Activate];
+        actions.extend(std::iter::repeat_n(Action::Backspace, 40));
+        actions.extend(typed("server.example.org"));
+        actions.push(Action::Down);
+        actions.extend(std::iter::repeat_n(Action::Backspace, 40));
+        actions.extend(typed("dayz"));
+        actions.extend([Action::Activate, Action::Down, Action::Activate, Action::Activate]);
+        let done = drive(&mut wizard, &actions).expect("the wizard finished");
+        assert!(done.message.starts_with("Setup saved to"), "{}", done.message);
+
+        let (read, unknown) = ConfigLayer::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(unknown.is_empty(), "{unknown:?}");
+        assert_eq!(read.theme, Some(Theme::Mono));
+        assert_eq!(read.vim_keys, Some(false));
+        assert_eq!(read.backup_keep, Some(3));
+        assert_eq!(read.ssh.host.as_deref(), Some("server.example.org"));
+        assert_eq!(read.ssh.user.as_deref(), Some("dayz"));
+        assert_eq!(read.ssh.port, None, "port 22 is the default and left out");
+    }
+
+    #[test]
+    fn esc_on_first_run_writes_a_minimal_config_and_keeps_existing_ones() {
+        let fixture = Fixture::new();
+        let path = fixture.dir.join("config.toml");
+        let done = drive(&mut SetupWizard::new(path.clone(), ConfigLayer::default(), true), &[Action::Down, Action::Cancel]).unwrap();
+        assert!(done.message.starts_with("Setup skipped"), "{}", done.message);
+        assert!(path.exists(), "the wizard must not come back on the next start");
+
+        std::fs::write(&path, "backup_keep = 4 # mine\n").unwrap();
+        let (base, _) = ConfigLayer::parse("backup_keep = 4\n").unwrap();
+        let done = drive(&mut SetupWizard::new(path.clone(), base, false), &[Action::Activate, Action::Cancel]).unwrap();
+        assert_eq!(done.message, "Setup cancelled; config unchanged");
+        assert_eq!(done.layer.backup_keep, Some(4));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "backup_keep = 4 # mine\n");
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+
+/// How many leading bytes are read to classify a file before opening it.
+pub const SNIFF_BYTES: usize = 512;
+
+pub enum FileCheck {
+    Ok,
+    /// Openable, but the user should confirm first; each entry is one reason.
+    Suspicious(Vec<String>),
+    /// Not something the editor can make sense of.
+    Binary,
+    /// Text with a UTF-16 byte order mark; the editor reads UTF-8 only.
+    Utf16,
+}
+
+/// Classifies a file from its name, total size and first few hundred bytes.
+pub fn check_file(path: &Path, size: u64, prefix: &[u8], large_file_bytes: u64) -> FileCheck {
+    // UTF-16 puts a zero byte next to every ASCII character, so it would read as binary.
+    if prefix.starts_with(b"\xFF\xFE") || prefix.starts_with(b"\xFE\xFF") {
+        return FileCheck::Utf16;
+    }
+    if looks_binary(prefix) {
+        return FileCheck::Binary;
+    }
+
+    let mut reasons = Vec::new();
+    let is_xml_name = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("xml"))
+        .unwrap_or(false);
+    if !is_xml_name || !looks_like_xml(prefix) {
+        reasons.push(String::from("This doesn't look like an XML file"));
+    }
+    if size > large_file_bytes {
+        reasons.push(format!(
+            "The file is {}, loading it may take a while and use a lot of memory",
+            format_size(size)
+        ));
+    }
+
+    if reasons.is_empty() {
+        FileCheck::Ok
+    } else {
+        FileCheck::Suspicious(reasons)
+    }
+}
+
+fn looks_binary(prefix: &[u8]) -> bool {
+    if prefix.contains(&0) {
+        return true;
+    }
+    let control = prefix
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t'))
+        .count();
+    control * 10 > prefix.len()
+}
+
+fn looks_like_xml(prefix: &[u8]) -> bool {
+    let text = prefix.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(prefix);
+    text.iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .map(|&b| b == b'<')
+        .unwrap_or(false)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    let b = bytes as f64;
+    if b >= GB {
+        format!("{:.1} GB", b / GB)
+    } else if b >= MB {
+        format!("{:.1} MB", b / MB)
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LARGE: u64 = 20 * 1024 * 1024;
+
+    fn reasons(check: FileCheck) -> Vec<String> {
+        match check {
+            FileCheck::Ok => Vec::new(),
+            FileCheck::Suspicious(reasons) => reasons,
+            FileCheck::Binary => vec![String::from("binary")],
+            FileCheck::Utf16 => vec![String::from("utf-16")],
+        }
+    }
+
+    #[test]
+    fn xml_opens_and_other_text_or_large_files_ask_first() {
+        let xml = b"\xEF\xBB\xBF\n  <?xml version=\"1.0\"?><types>";
+        assert!(reasons(check_file(Path::new("types.XML"), 100, xml, LARGE)).is_empty());
+        assert_eq!(
+            reasons(check_file(Path::new("types.txt"), 100, xml, LARGE)),
+            ["This doesn't look like an XML file"]
+        );
+        assert_eq!(
+            reasons(check_file(Path::new("serverDZ.xml"), 100, b"hostname = \"My server\";\n", LARGE)),
+            ["This doesn't look like an XML file"]
+        );
+        assert_eq!(
+            reasons(check_file(Path::new("types.xml"), 30 * 1024 * 1024, xml, LARGE)),
+            ["The file is 30.0 MB, loading it may take a while and use a lot of memory"]
+        );
+    }
+
+    #[test]
+    fn binary_data_is_refused_and_utf16_named() {
+        assert_eq!(reasons(check_file(Path::new("mod.pbo"), 100, b"\0sreV\0\0\0", LARGE)), ["binary"]);
+        assert_eq!(reasons(check_file(Path::new("a.xml"), 100, &[1, 2, 3, 4, b'<'], LARGE)), ["binary"]);
+        let utf16: Vec<u8> = "\u{feff}<types>".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(reasons(check_file(Path::new("types.xml"), 100, &utf16, LARGE)), ["utf-16"]);
+        let big_endian: Vec<u8> = "\u{feff}<types>".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(reasons(check_file(Path::new("types.xml"), 100, &big_endian, LARGE)), ["utf-16"]);
+    }
+
+    #[test]
+    fn sizes_read_in_the_nearest_unit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
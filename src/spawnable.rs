@@ -0,0 +1,210 @@
+use std::{
+    cell::OnceCell,
+    collections::{HashMap, HashSet},
+};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{balance, model::TypeEntry};
+
+/// Looked up next to the types file and one directory up, like `tiers::LIMITS_FILE`.
+pub const SPAWNABLE_FILE: &str = "cfgspawnabletypes.xml";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Attachment,
+    Cargo,
+}
+
+impl Slot {
+    fn name(self) -> &'static str {
+        match self {
+            Slot::Attachment => "attachment",
+            Slot::Cargo => "cargo",
+        }
+    }
+}
+
+/// One `<item name>` in a `<type>`'s `<attachments>` or `<cargo>` block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemRef {
+    pub owner: String,
+    pub slot: Slot,
+    pub item: String,
+}
+
+/// What cfgspawnabletypes.xml says about which types spawn which items. The CE matches
+/// names in any case, so lookups do too.
+#[derive(Debug, Default)]
+pub struct Spawnables {
+    /// Names of its `<type>` entries, in file order.
+    pub owners: Vec<String>,
+    pub items: Vec<ItemRef>,
+    /// Positions in `items` by lower-cased item name, built on the first lookup. The editor
+    /// reads a new `Spawnables` when either file changes, which drops it.
+    index: OnceCell<HashMap<String, Vec<usize>>>,
+}
+
+/// Reads what it can; a malformed file yields the entries before the error.
+pub fn parse(content: &str) -> Spawnables {
+    let mut spawnables = Spawnables::default();
+    let mut owner: Option<String> = None;
+    let mut slot: Option<Slot> = None;
+    for event in EventReader::new(content.as_bytes()) {
+        match event {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                let name_attr = || attributes.iter().find(|a| a.name.local_name == "name").map(|a| a.value.clone());
+                match name.local_name.as_str() {
+                    "type" => {
+                        owner = name_attr();
+                        spawnables.owners.extend(owner.clone());
+                    }
+                    "attachments" => slot = Some(Slot::Attachment),
+                    "cargo" => slot = Some(Slot::Cargo),
+                    "item" => {
+                        if let (Some(owner), Some(slot), Some(item)) = (&owner, slot, name_attr()) {
+                            spawnables.items.push(ItemRef { owner: owner.clone(), slot, item });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) => match name.local_name.as_str() {
+                "type" => owner = None,
+                "attachments" | "cargo" => slot = None,
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    spawnables
+}
+
+impl Spawnables {
+    /// Items that spawn `name` as an attachment or cargo.
+    pub fn references_to(&self, name: &str) -> Vec<&ItemRef> {
+        let index = self.index.get_or_init(|| {
+            let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+            for (i, item) in self.items.iter().enumerate() {
+                index.entry(item.item.to_lowercase()).or_default().push(i);
+            }
+            index
+        });
+        index
+            .get(&name.to_lowercase())
+            .map(|found| found.iter().map(|&i| &self.items[i]).collect())
+            .unwrap_or_default()
+    }
+
+    fn has_owner(&self, name: &str) -> bool {
+        self.owners.iter().any(|owner| owner.eq_ignore_ascii_case(name))
+    }
+
+    /// `AKM is referenced by 3 cargo entries in cfgspawnabletypes.xml`, or `None` when
+    /// deleting `name` leaves nothing there pointing at it.
+    pub fn deletion_warning(&self, name: &str) -> Option<String> {
+        let references = self.references_to(name);
+        let mut parts = Vec::new();
+        for slot in [Slot::Attachment, Slot::Cargo] {
+            match references.iter().filter(|r| r.slot == slot).count() {
+                0 => {}
+                1 => parts.push(format!("1 {} entry", slot.name())),
+                n => parts.push(format!("{n} {} entries", slot.name())),
+            }
+        }
+        if self.has_owner(name) {
+            parts.push(String::from("its own <type> entry"));
+        }
+        (!parts.is_empty()).then(|| format!("{name} is referenced by {} in {SPAWNABLE_FILE}", parts.join(" and ")))
+    }
+
+    /// The report `:refs` shows: items and `<type>` entries naming a type the types file
+    /// doesn't have, and types that never spawn (nominal 0 and not an item anywhere).
+    pub fn dangling_report(&self, types: &[TypeEntry]) -> Vec<String> {
+        let known: HashSet<String> = types.iter().map(|t| t.name.to_lowercase()).collect();
+        let missing = |name: &str| !known.contains(&name.to_lowercase());
+        let unknown_items: Vec<String> = self
+            .items
+            .iter()
+            .filter(|r| missing(&r.item))
+            .map(|r| format!("  {} ({} of {})", r.item, r.slot.name(), r.owner))
+            .collect();
+        let unknown_owners: Vec<String> = self.owners.iter().filter(|o| missing(o)).map(|o| format!("  {o}")).collect();
+        let never_spawn: Vec<String> = types
+            .iter()
+            .filter(|t| balance::element_value(t, "nominal").unwrap_or(0) == 0 && self.references_to(&t.name).is_empty())
+            .map(|t| format!("  {}", t.name))
+            .collect();
+
+        let mut lines = Vec::new();
+        for (title, names) in [
+            (format!("Items with no type ({})", unknown_items.len()), unknown_items),
+            (format!("<type> entries with no type ({})", unknown_owners.len()), unknown_owners),
+            (format!("Types that never spawn: nominal 0 and no item entry ({})", never_spawn.len()), never_spawn),
+        ] {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(title);
+            if names.is_empty() {
+                lines.push(String::from("  none"));
+            }
+            lines.extend(names);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    const SPAWNABLE: &str = r#"<spawnabletypes>
+    <type name="AKM">
+        <attachments chance="0.5"><item name="AK_WoodBttstck" chance="1"/></attachments>
+        <cargo chance="0.2"><item name="apple" chance="1"/></cargo>
+        <cargo chance="0.1"><item name="Apple" chance="1"/></cargo>
+    </type>
+    <type name="Mosin9130"><hoarder/></type>
+    <type name="Pear"><cargo><item name="Mosin9130"/></cargo></type>
+</spawnabletypes>"#;
+
+    #[test]
+    fn items_are_found_by_name_in_any_case() {
+        let spawnables = parse(SPAWNABLE);
+        assert_eq!(spawnables.owners, ["AKM", "Mosin9130", "Pear"]);
+        let apples = spawnables.references_to("APPLE");
+        assert_eq!(apples.len(), 2);
+        assert!(apples.iter().all(|r| r.owner == "AKM" && r.slot == Slot::Cargo));
+        assert!(spawnables.references_to("AKM").is_empty());
+
+        assert_eq!(spawnables.deletion_warning("Apple").as_deref(), Some("Apple is referenced by 2 cargo entries in cfgspawnabletypes.xml"));
+        assert_eq!(
+            spawnables.deletion_warning("Mosin9130").as_deref(),
+            Some("Mosin9130 is referenced by 1 cargo entry and its own <type> entry in cfgspawnabletypes.xml")
+        );
+        assert_eq!(spawnables.deletion_warning("Banana"), None);
+    }
+
+    #[test]
+    fn the_report_lists_dangling_references_both_ways() {
+        let unspawned = SAMPLE.replace("<nominal>8</nominal>", "<nominal>0</nominal>").replace("<nominal>12</nominal>", "<nominal>0</nominal>");
+        let (types, _) = parse_types(&unspawned).unwrap();
+        let lines = parse(SPAWNABLE).dangling_report(&types);
+        assert_eq!(
+            lines,
+            [
+                "Items with no type (1)",
+                "  AK_WoodBttstck (attachment of AKM)",
+                "",
+                "<type> entries with no type (1)",
+                "  Pear",
+                "",
+                "Types that never spawn: nominal 0 and no item entry (1)",
+                "  AKM",
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Messages this close after the previous one with the same template share its entry.
+pub const COALESCE_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntryKind {
+    Message,
+    /// Only an identical error joins an error's entry.
+    Error,
+    /// Load warnings and bulk summaries: never joined.
+    Fixed,
+}
+
+#[derive(Debug)]
+struct Entry {
+    text: String,
+    template: String,
+    kind: EntryKind,
+    count: usize,
+    /// Later messages differed from `text` in their numbers or quoted names.
+    varied: bool,
+    at: Instant,
+}
+
+/// The session's message log (`:messages`). Consecutive messages that differ only in
+/// numbers and quoted names collapse into one entry with a count, so an edit repeated by
+/// a macro shows once as `Set nominal to … on '…' (u to undo) (×134)`.
+#[derive(Debug)]
+pub struct StatusLog {
+    entries: VecDeque<Entry>,
+    limit: usize,
+}
+
+impl StatusLog {
+    /// Keeps the last `limit` entries.
+    pub fn new(limit: usize) -> Self {
+        Self { entries: VecDeque::new(), limit }
+    }
+
+    pub fn push(&mut self, message: String) {
+        self.record(message, EntryKind::Message, 1, Instant::now());
+    }
+
+    pub fn push_error(&mut self, message: String) {
+        self.record(message, EntryKind::Error, 1, Instant::now());
+    }
+
+    /// A line that stands on its own, like a load warning; never collapsed.
+    pub fn detail(&mut self, line: String) {
+        self.record(line, EntryKind::Fixed, 1, Instant::now());
+    }
+
+    /// One entry for `count` items of a bulk operation, instead of a message per item.
+    pub fn summary(&mut self, message: String, count: usize) {
+        self.record(message, EntryKind::Fixed, count, Instant::now());
+    }
+
+    fn record(&mut self, text: String, kind: EntryKind, count: usize, at: Instant) {
+        let template = template_of(&text);
+        if let Some(last) = self.entries.back_mut()
+            && kind != EntryKind::Fixed
+            && last.kind == kind
+            && at.saturating_duration_since(last.at) <= COALESCE_WINDOW
+            && if kind == EntryKind::Error { last.text == text } else { last.template == template }
+        {
+            last.count += count;
+            last.varied |= last.text != text;
+            last.at = at;
+            return;
+        }
+        self.entries.push_back(Entry { text, template, kind, count, varied: false, at });
+        while self.entries.len() > self.limit {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The entries oldest first, `(×n)` after those standing for several messages.
+    pub fn lines(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let text = if entry.varied { &entry.template } else { &entry.text };
+                match entry.count {
+                    1 => text.clone(),
+                    n => format!("{text} (×{n})"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// `message` with runs of digits and `'quoted'` names replaced by `…`: what stays the
+/// same when one edit is repeated over many types.
+fn template_of(message: &str) -> String {
+    let chars: Vec<char> = message.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            out.push('…');
+            continue;
+        }
+        // A quote after a letter is an apostrophe (`doesn't`), not the start of a name.
+        let opens = c == '\'' && (i == 0 || !chars[i - 1].is_alphanumeric());
+        if opens && let Some(len) = chars[i + 1..].iter().position(|&q| q == '\'') {
+            out.push_str("'…'");
+            i += len + 2;
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_at(log: &mut StatusLog, message: &str, kind: EntryKind, at: Instant) {
+        log.record(message.to_string(), kind, 1, at);
+    }
+
+    #[test]
+    fn repeated_messages_collapse_within_the_window() {
+        let start = Instant::now();
+        let mut log = StatusLog::new(10);
+        for (i, name) in ["AKM", "Apple", "Mosin9130"].iter().enumerate() {
+            let at = start + Duration::from_millis(500 * i as u64);
+            log_at(&mut log, &format!("Set nominal to {} on '{name}' (u to undo)", 10 + i), EntryKind::Message, at);
+        }
+        log_at(&mut log, "Saved 3 changed types to types.xml", EntryKind::Message, start + Duration::from_secs(2));
+        log_at(&mut log, "Saved 3 changed types to types.xml", EntryKind::Message, start + Duration::from_secs(3));
+        assert_eq!(log.lines(), [
+            "Set nominal to … on '…' (u to undo) (×3)",
+            "Saved 3 changed types to types.xml (×2)",
+        ]);
+
+        // Too far apart, or something else in between, and they stay separate.
+        log_at(&mut log, "Saved 3 changed types to types.xml", EntryKind::Message, start + Duration::from_secs(9));
+        log_at(&mut log, "Deleted 'AKM' (u to undo)", EntryKind::Message, start + Duration::from_secs(9));
+        log_at(&mut log, "Saved 3 changed types to types.xml", EntryKind::Message, start + Duration::from_secs(9));
+        assert_eq!(log.len(), 5);
+    }
+
+    #[test]
+    fn distinct_errors_details_and_summaries_stay_apart() {
+        let at = Instant::now();
+        let mut log = StatusLog::new(10);
+        log_at(&mut log, "Line 3: 'x' is not a number", EntryKind::Error, at);
+        log_at(&mut log, "Line 4: 'y' is not a number", EntryKind::Error, at);
+        log_at(&mut log, "Line 4: 'y' is not a number", EntryKind::Error, at);
+        log_at(&mut log, "Line 4: 'y' is not a number", EntryKind::Message, at);
+        log_at(&mut log, "  line 7: unknown element", EntryKind::Fixed, at);
+        log_at(&mut log, "  line 8: unknown element", EntryKind::Fixed, at);
+        log.record(String::from("Replayed macro"), EntryKind::Fixed, 134, at);
+        assert_eq!(log.lines(), [
+            "Line 3: 'x' is not a number",
+            "Line 4: 'y' is not a number (×2)",
+            "Line 4: 'y' is not a number",
+            "  line 7: unknown element",
+            "  line 8: unknown element",
+            "Replayed macro (×134)",
+        ]);
+    }
+
+    #[test]
+    fn templates_keep_apostrophes_and_the_oldest_entries_go_first() {
+        assert_eq!(template_of("The file doesn't parse at line 12"), "The file doesn't parse at line …");
+        assert_eq!(template_of("Renamed 'AKM' to 'AKM_Black'"), "Renamed '…' to '…'");
+        assert_eq!(template_of("it's 'open"), "it's 'open");
+
+        let mut log = StatusLog::new(2);
+        for message in ["a", "b", "c"] {
+            log.detail(message.to_string());
+        }
+        assert_eq!(log.lines(), ["b", "c"]);
+    }
+}
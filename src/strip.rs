@@ -0,0 +1,152 @@
+use crate::{
+    model::{FieldKey, TypeEntry},
+    utils,
+};
+
+/// What `:stripfield` removes: an element with everything on it (`cost`, `tag`), or one
+/// attribute wherever that element has it (`flags@deloot`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StripTarget {
+    Element(String),
+    Attribute { element: String, attr: String },
+}
+
+impl StripTarget {
+    /// `element` or `element@attr`, as `:apply` names fields. `name` attributes are
+    /// refused: they are what a category, usage or tag is, and stripping them would
+    /// leave empty elements behind.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let target = match spec.split_once('@') {
+            Some((element, attr)) => Self::Attribute { element: element.trim().to_string(), attr: attr.trim().to_string() },
+            None => Self::Element(spec.trim().to_string()),
+        };
+        match &target {
+            Self::Element(name) if name.is_empty() => Err(String::from("name an element (cost) or element@attribute (flags@deloot)")),
+            Self::Element(name) if name == "name" || name == "type" => {
+                Err(format!("'{name}' is the type itself; delete types with d instead"))
+            }
+            Self::Attribute { element, attr } if element.is_empty() || attr.is_empty() => {
+                Err(format!("'{spec}' should be element@attribute, e.g. flags@deloot"))
+            }
+            Self::Attribute { element, attr } if attr == "name" => Err(format!(
+                "'{element}@name' is what each {element} is; :stripfield {element} removes the elements whole"
+            )),
+            _ => Ok(target),
+        }
+    }
+
+    pub fn matches(&self, key: &FieldKey) -> bool {
+        match (self, key) {
+            (Self::Element(name), key) => key.get_element_name() == name,
+            (Self::Attribute { element, attr }, FieldKey::Attribute { element: e, attr: a, .. }) => e == element && a == attr,
+            (Self::Attribute { .. }, FieldKey::Element { .. }) => false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Element(name) => format!("<{name}>"),
+            Self::Attribute { element, attr } => format!("{attr} on <{element}>"),
+        }
+    }
+}
+
+/// A strip worked out before anything changes, so the preview shows exactly what Enter
+/// will remove.
+#[derive(Clone, Debug)]
+pub struct StripPlan {
+    pub target: StripTarget,
+    /// Types that lose fields, with how many each.
+    pub changes: Vec<(usize, usize)>,
+    /// How many types were looked at: all of them, or the marked ones.
+    pub scope: usize,
+    pub marked_only: bool,
+}
+
+/// Counts what stripping `target` would take from each of `indices`.
+pub fn plan(types: &[TypeEntry], indices: &[usize], target: StripTarget, marked_only: bool) -> StripPlan {
+    let changes = indices
+        .iter()
+        .filter_map(|&idx| {
+            let count = types.get(idx)?.fields.iter().filter(|f| target.matches(&f.key)).count();
+            (count > 0).then_some((idx, count))
+        })
+        .collect();
+    StripPlan { target, changes, scope: indices.len(), marked_only }
+}
+
+impl StripPlan {
+    pub fn fields(&self) -> usize {
+        self.changes.iter().map(|&(_, count)| count).sum()
+    }
+
+    /// The preview: the totals, then every type that changes.
+    pub fn lines(&self, types: &[TypeEntry]) -> Vec<String> {
+        let scope = if self.marked_only { "marked types" } else { "types" };
+        let mut lines = vec![
+            format!(
+                "Remove {} from {} of {} {scope} ({}):",
+                self.target.describe(),
+                self.changes.len(),
+                self.scope,
+                utils::plural(self.fields(), "field")
+            ),
+            String::new(),
+        ];
+        for &(idx, count) in &self.changes {
+            let name = types.get(idx).map_or("?", |t| t.name.as_str());
+            lines.push(if count == 1 { format!("  - {name}") } else { format!("  - {name} ({count} fields)") });
+        }
+        lines
+    }
+}
+
+/// Removes the planned fields; an element's attributes go with it (`remove_fields`).
+pub fn apply(types: &mut [TypeEntry], plan: &StripPlan) -> usize {
+    let mut removed = 0;
+    for &(idx, _) in &plan.changes {
+        if let Some(ty) = types.get_mut(idx) {
+            removed += ty.remove_fields(|key| plan.target.matches(key));
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    #[test]
+    fn targets_parse_and_name_attributes_are_refused() {
+        assert_eq!(StripTarget::parse("cost"), Ok(StripTarget::Element(String::from("cost"))));
+        assert_eq!(
+            StripTarget::parse("flags@deloot"),
+            Ok(StripTarget::Attribute { element: String::from("flags"), attr: String::from("deloot") })
+        );
+        assert!(StripTarget::parse("usage@name").unwrap_err().contains(":stripfield usage"));
+        assert!(StripTarget::parse("name").is_err());
+        assert!(StripTarget::parse("flags@").is_err());
+    }
+
+    #[test]
+    fn stripping_counts_per_type_and_takes_whole_elements() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        types[1].fields.retain(|f| f.key.get_element_name() != "category");
+        let all: Vec<usize> = (0..types.len()).collect();
+        let plan = plan(&types, &all, StripTarget::parse("category").unwrap(), false);
+        assert_eq!((plan.changes.len(), plan.fields()), (2, 2));
+        assert_eq!(plan.lines(&types), ["Remove <category> from 2 of 3 types (2 fields):", "", "  - AKM", "  - Mosin9130"]);
+        assert_eq!(apply(&mut types, &plan), 2);
+        assert!(types.iter().all(|t| t.fields.iter().all(|f| f.key.get_element_name() != "category")));
+
+        types[1].fields.extend(crate::model::default_flags());
+        let cargo = StripTarget::parse("flags@count_in_cargo").unwrap();
+        let first = super::plan(&types, &[0], cargo, true);
+        assert_eq!((first.changes.len(), first.scope), (1, 1));
+        apply(&mut types, &first);
+        assert!(types[0].fields.iter().any(|f| f.key.get_element_name() == "flags"), "other flags stay");
+        assert!(!types[0].fields.iter().any(|f| first.target.matches(&f.key)));
+        assert!(types[1].fields.iter().any(|f| first.target.matches(&f.key)), "unmarked types keep theirs");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    balance,
+    model::{FieldKey, TypeEntry},
+    tiers,
+};
+
+/// A type's key numbers, read again whenever the type changes: the Types pane's inline
+/// values, the line shown while types are marked, the statistics and the change report
+/// all come from here.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TypeSummary {
+    pub nominal: Option<String>,
+    pub min: Option<String>,
+    pub lifetime: Option<String>,
+    pub category: Option<String>,
+    /// Tier names in display order, custom ones from the limits file included.
+    pub tiers: Vec<String>,
+}
+
+impl TypeSummary {
+    pub fn of(ty: &TypeEntry, custom_tiers: &[String]) -> Self {
+        let value = |name| {
+            let value = ty.fields.get(ty.element_field(name)?)?.value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        let category = ty
+            .fields
+            .iter()
+            .find(|f| matches!(&f.key, FieldKey::Attribute { element, attr, .. } if element == "category" && attr == "name"))
+            .map(|f| f.value.trim().to_string())
+            .filter(|name| !name.is_empty());
+        Self {
+            nominal: value("nominal"),
+            min: value("min"),
+            lifetime: value("lifetime"),
+            category,
+            tiers: tiers::tiers_of(ty, custom_tiers),
+        }
+    }
+
+    /// `nom 8 min 4 life 4 h cat weapons tiers 3,4`, leaving out what the type lacks; a
+    /// lifetime of whole minutes, hours or days reads as such.
+    pub fn values(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(nominal) = &self.nominal {
+            parts.push(format!("nom {nominal}"));
+        }
+        if let Some(min) = &self.min {
+            parts.push(format!("min {min}"));
+        }
+        if let Some(lifetime) = &self.lifetime {
+            let shown = lifetime.parse().map_or_else(|_| lifetime.clone(), balance::duration);
+            parts.push(format!("life {shown}"));
+        }
+        if let Some(category) = &self.category {
+            parts.push(format!("cat {category}"));
+        }
+        if !self.tiers.is_empty() {
+            let tiers: Vec<&str> = self.tiers.iter().map(|t| tiers::short_name(t)).collect();
+            parts.push(format!("tiers {}", tiers.join(",")));
+        }
+        parts.join(" ")
+    }
+
+    /// `AKM — nom 8 min 4 life 4 h cat weapons tiers 3,4`; just the name when none of it is set.
+    pub fn line(&self, name: &str) -> String {
+        match self.values() {
+            values if values.is_empty() => name.to_string(),
+            values => format!("{name} — {values}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::parse_types, test_harness::SAMPLE};
+
+    #[test]
+    fn the_line_has_what_the_type_sets() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        tiers::set_tier(&mut types[0], "Tier4", true);
+        tiers::set_tier(&mut types[0], "Tier3", true);
+        tiers::set_tier(&mut types[0], "Coast", true);
+        let custom = [String::from("Coast")];
+        assert_eq!(TypeSummary::of(&types[0], &custom).line("AKM"), "AKM — nom 8 min 4 life 2 h cat weapons tiers 3,4,Coast");
+
+        types[1].fields.retain(|f| !matches!(f.key.get_element_name(), "min" | "category"));
+        types[1].fields[1].value = String::from("3601");
+        assert_eq!(TypeSummary::of(&types[1], &[]).values(), "nom 40 life 3601 s");
+        types[1].fields.clear();
+        assert_eq!(TypeSummary::of(&types[1], &[]).line("Apple"), "Apple");
+    }
+}
@@ -0,0 +1,211 @@
+//! Drives the editor and file picker through `Action`s against fixture files, the way
+//! the main loop does, so flows can be tested without a terminal.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+use crate::{
+    action::Action,
+    config::{ConfigLayer, Theme, ValidationLayer},
+    editor::{Editor, LoadMode},
+    file_picker::{FilePicker, Picked},
+    remote::{FileSelection, FileSource},
+    utils,
+};
+
+/// Three small types: enough to move around, mark several and delete at either end.
+pub const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<types>
+  <type name="AKM">
+    <nominal>8</nominal>
+    <lifetime>7200</lifetime>
+    <min>4</min>
+    <flags count_in_cargo="0" count_in_map="1" />
+    <category name="weapons" />
+  </type>
+  <type name="Apple">
+    <nominal>40</nominal>
+    <lifetime>3600</lifetime>
+    <min>20</min>
+    <category name="food" />
+  </type>
+  <type name="Mosin9130">
+    <nominal>12</nominal>
+    <lifetime>7200</lifetime>
+    <min>6</min>
+    <category name="weapons" />
+  </type>
+</types>
+"#;
+
+/// A temporary directory removed again when dropped.
+pub struct Fixture {
+    pub dir: PathBuf,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "dayz-loot-editor-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).expect("create fixture directory");
+        Self { dir }
+    }
+
+    pub fn write(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("create fixture subdirectory");
+        }
+        fs::write(&path, content).expect("write fixture file");
+        path
+    }
+
+    pub fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.dir.join(name)).expect("read fixture file")
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// An editor with default settings and `path` loaded. It draws in the monochrome theme,
+/// as `--no-color` does, so screens hold every cue as text, and leaves `require_flags`
+/// off so saving `SAMPLE`, two of whose types have no `<flags>`, doesn't stop to ask.
+pub fn editor_for(path: &Path) -> Editor {
+    let cli = ConfigLayer {
+        theme: Some(Theme::Mono),
+        validation: ValidationLayer { require_flags: Some(false), ..ValidationLayer::default() },
+        ..ConfigLayer::default()
+    };
+    load_into(Editor::new(ConfigLayer::default(), cli), path)
+}
+
+/// `editor` with `path` loaded.
+pub fn load_into(mut editor: Editor, path: &Path) -> Editor {
+    editor
+        .load(
+            FileSelection {
+                path: path.to_path_buf(),
+                source: FileSource::Local,
+            },
+            LoadMode::Fresh,
+        )
+        .expect("load fixture");
+    editor
+}
+
+/// Each character as typed text.
+pub fn text(s: &str) -> Vec<Action> {
+    s.chars().map(Action::Input).collect()
+}
+
+/// `:line` followed by Enter.
+pub fn command(line: &str) -> Vec<Action> {
+    let mut actions = vec![Action::Command];
+    actions.extend(text(line));
+    actions.push(Action::Activate);
+    actions
+}
+
+/// Feeds `actions` in order, rendering after each one like the main loop would, so a
+/// panic in either handling or drawing fails the test at the step that caused it.
+pub fn drive(editor: &mut Editor, actions: impl IntoIterator<Item = Action>) {
+    let mut terminal = terminal();
+    for action in actions {
+        editor.handle_action(action).expect("handle action");
+        draw(&mut terminal, editor);
+    }
+}
+
+/// Ticks the editor until its worker-thread job (a dry run, an apply) has finished.
+pub fn finish_jobs(editor: &mut Editor) {
+    while editor.is_busy() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        editor.tick().expect("tick");
+    }
+}
+
+/// Like `drive`, for the picker; returns the last thing it handed to the main loop.
+pub fn drive_picker(picker: &mut FilePicker, actions: impl IntoIterator<Item = Action>) -> Option<Picked> {
+    let mut terminal = terminal();
+    let mut picked = None;
+    for action in actions {
+        if let Some(outcome) = picker.handle_action(action).expect("handle action") {
+            picked = Some(outcome);
+        }
+        terminal.draw(|f| picker.draw(f, false)).expect("draw picker");
+    }
+    picked
+}
+
+/// The action pressing `key` gives in the picker, through the main loop's key mapping.
+pub fn picker_key(picker: &FilePicker, key: char) -> Action {
+    crate::map_key_to_action(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE), false, picker.is_prompt(), false)
+}
+
+/// The editor's screen as text, one line per row.
+pub fn render(editor: &mut Editor) -> String {
+    render_width(editor, 120)
+}
+
+/// `render` on a terminal `width` columns wide, for narrow layouts.
+pub fn render_width(editor: &mut Editor, width: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, 30)).expect("test terminal");
+    draw(&mut terminal, editor);
+    screen(&terminal)
+}
+
+/// The picker's screen as text, one line per row.
+pub fn render_picker(picker: &mut FilePicker) -> String {
+    let mut terminal = terminal();
+    terminal.draw(|f| picker.draw(f, false)).expect("draw picker");
+    screen(&terminal)
+}
+
+/// The editor's screen with its styles.
+pub fn render_buffer(editor: &mut Editor) -> Buffer {
+    let mut terminal = terminal();
+    draw(&mut terminal, editor);
+    terminal.backend().buffer().clone()
+}
+
+/// One frame, as the main loop draws it.
+fn draw(terminal: &mut Terminal<TestBackend>, editor: &mut Editor) {
+    terminal
+        .draw(|f| {
+            editor.draw(f, false);
+            if editor.mono() {
+                f.render_widget(utils::Monochrome, f.size());
+            }
+        })
+        .expect("draw editor");
+}
+
+fn terminal() -> Terminal<TestBackend> {
+    Terminal::new(TestBackend::new(120, 30)).expect("test terminal")
+}
+
+fn screen(terminal: &Terminal<TestBackend>) -> String {
+    let buffer = terminal.backend().buffer();
+    let mut out = String::new();
+    for y in 0..buffer.area.height {
+        for x in 0..buffer.area.width {
+            out.push_str(&buffer.get(x, y).symbol);
+        }
+        out.push('\n');
+    }
+    out
+}
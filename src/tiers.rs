@@ -0,0 +1,230 @@
+use std::{collections::BTreeSet, path::Path};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    field_help::FieldHelp,
+    model::{Field, FieldKey, TypeEntry},
+};
+
+/// Tiers offered even when no cfglimitsdefinition.xml is around.
+pub const DEFAULT_TIERS: [&str; 4] = ["Tier1", "Tier2", "Tier3", "Tier4"];
+/// Looked up next to the types file and one directory up (vanilla keeps types.xml in `db/`).
+pub const LIMITS_FILE: &str = "cfglimitsdefinition.xml";
+
+/// `TierN`, the naming vanilla uses for map tiers.
+pub fn is_numbered_tier(name: &str) -> bool {
+    name.strip_prefix("Tier")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether a `value @name` is a tier: numbered, or declared in the mission's limits file.
+pub fn is_tier(name: &str, custom: &[String]) -> bool {
+    is_numbered_tier(name) || custom.iter().any(|c| c == name)
+}
+
+/// Numbered tiers by number, then custom ones alphabetically.
+pub fn sort_tiers(tiers: &mut [String]) {
+    tiers.sort_by_key(|t| match t.strip_prefix("Tier").and_then(|n| n.parse::<u32>().ok()) {
+        Some(n) => (0, n, String::new()),
+        None => (1, 0, t.clone()),
+    });
+}
+
+/// Tier names present on a type, in display order.
+pub fn tiers_of(ty: &TypeEntry, custom: &[String]) -> Vec<String> {
+    let mut tiers: Vec<String> = ty
+        .fields
+        .iter()
+        .filter(|f| is_value_name(&f.key) && is_tier(&f.value, custom))
+        .map(|f| f.value.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    sort_tiers(&mut tiers);
+    tiers
+}
+
+/// Compact form for the Fields pane: `1 · 3 · 4`, custom tiers by name.
+pub fn summary(tiers: &[String]) -> String {
+    if tiers.is_empty() {
+        return String::from("none");
+    }
+    tiers.iter().map(|t| short_name(t)).collect::<Vec<_>>().join(" · ")
+}
+
+/// `3` for `Tier3`; custom tiers keep their name.
+pub fn short_name(tier: &str) -> &str {
+    if is_numbered_tier(tier) { tier.trim_start_matches("Tier") } else { tier }
+}
+
+pub fn is_value_name(key: &FieldKey) -> bool {
+    matches!(key, FieldKey::Attribute { element, attr, .. } if element == "value" && attr == "name")
+}
+
+/// Adds or removes one tier on a type. New entries get the next free `value` index and go
+/// after the existing `value` fields; removal drops every field of the matching elements.
+pub fn set_tier(ty: &mut TypeEntry, tier: &str, present: bool) {
+    let matching: Vec<usize> = ty
+        .fields
+        .iter()
+        .filter(|f| is_value_name(&f.key) && f.value == tier)
+        .map(|f| f.key.element_key().1)
+        .collect();
+    if present {
+        if !matching.is_empty() {
+            return;
+        }
+        let values = ty.fields.iter().filter(|f| f.key.get_element_name() == "value");
+        let index = values.clone().map(|f| f.key.element_key().1 + 1).max().unwrap_or(0);
+        let position = ty
+            .fields
+            .iter()
+            .rposition(|f| f.key.get_element_name() == "value")
+            .map(|p| p + 1)
+            .unwrap_or(ty.fields.len());
+        ty.fields.insert(
+            position,
+            Field {
+                key: FieldKey::Attribute {
+                    element: String::from("value"),
+                    index,
+                    attr: String::from("name"),
+                },
+                value: tier.to_string(),
+            },
+        );
+    } else {
+        ty.fields.retain(|f| {
+            let (element, index) = f.key.element_key();
+            element != "value" || !matching.contains(&index)
+        });
+    }
+}
+
+/// The map a types file is for, from the mission folder it sits in (or one up, as with
+/// `db/types.xml`). Mission folders are named `<mission>.<world>`: vanilla's
+/// `dayzOffline.chernarusplus` is `chernarus` and `dayzOffline.enoch` is `livonia`; other
+/// worlds keep their name, lowercased. A folder without a dot counts only when it is one of
+/// those map names itself.
+pub fn detect_map(types_path: &Path) -> Option<String> {
+    let dir = types_path.parent()?;
+    [Some(dir), dir.parent()].into_iter().flatten().find_map(|d| {
+        let name = d.file_name()?.to_str()?.to_lowercase();
+        let (world, dotted) = match name.rsplit_once('.') {
+            Some((mission, world)) if !mission.is_empty() && !world.is_empty() => (world.to_string(), true),
+            _ => (name, false),
+        };
+        match world.as_str() {
+            "chernarusplus" | "chernarus" => Some(String::from("chernarus")),
+            "enoch" | "livonia" => Some(String::from("livonia")),
+            _ if dotted => Some(world),
+            _ => None,
+        }
+    })
+}
+
+/// The Tips pane's tier table: the help data's rows for `map` plus the tiers the mission's
+/// cfglimitsdefinition.xml declares (`custom`), the row for `tier` marked; declared tiers
+/// the help data doesn't describe are bare names. `None` when neither knows the tier.
+pub fn describe(help: &FieldHelp, map: Option<&str>, custom: &[String], tier: &str) -> Option<String> {
+    let table = map.and_then(|map| help.tiers.get(map));
+    let mut names: Vec<String> = table.map(|t| t.keys().cloned().collect()).unwrap_or_default();
+    names.extend(custom.iter().filter(|c| !names.contains(c)).cloned().collect::<Vec<_>>());
+    if !names.iter().any(|name| name == tier) {
+        return None;
+    }
+    sort_tiers(&mut names);
+    let mut out = match map {
+        Some(map) => {
+            let mut title: Vec<char> = map.chars().collect();
+            if let Some(first) = title.first_mut() {
+                *first = first.to_ascii_uppercase();
+            }
+            format!("Tiers on {}:", title.into_iter().collect::<String>())
+        }
+        None => format!("Tiers in {LIMITS_FILE}:"),
+    };
+    for name in names {
+        let marker = if name == tier { "▶" } else { " " };
+        match table.and_then(|t| t.get(&name)) {
+            Some(area) => out.push_str(&format!("\n{marker} {name} = {area}")),
+            None if table.is_some() => out.push_str(&format!("\n{marker} {name} (custom)")),
+            None => out.push_str(&format!("\n{marker} {name}")),
+        }
+    }
+    Some(out)
+}
+
+/// Names declared under `<valueflags>` in a cfglimitsdefinition.xml.
+pub fn parse_limits(content: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut in_valueflags = false;
+    for event in EventReader::new(content.as_bytes()) {
+        match event {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => match name.local_name.as_str() {
+                "valueflags" => in_valueflags = true,
+                "value" if in_valueflags => {
+                    names.extend(attributes.into_iter().find(|a| a.name.local_name == "name").map(|a| a.value));
+                }
+                _ => {}
+            },
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "valueflags" => in_valueflags = false,
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_map_comes_from_the_mission_folder_name() {
+        let map = |path: &str| detect_map(Path::new(path));
+        assert_eq!(map("/srv/mpmissions/dayzOffline.chernarusplus/db/types.xml").as_deref(), Some("chernarus"));
+        assert_eq!(map("/srv/mpmissions/dayzOffline.enoch/types.xml").as_deref(), Some("livonia"));
+        assert_eq!(map("C:/DayZServer/mpmissions/DayZOffline.ChernarusPlus/db/types.xml").as_deref(), Some("chernarus"));
+        assert_eq!(map("/srv/mpmissions/empty.deerisle/db/types.xml").as_deref(), Some("deerisle"));
+        assert_eq!(map("/home/me/livonia/types.xml").as_deref(), Some("livonia"));
+        // Plain folders, hidden ones and a mission two levels up say nothing.
+        assert_eq!(map("/home/me/loot/db/types.xml"), None);
+        assert_eq!(map("/home/me/.config/types.xml"), None);
+        assert_eq!(map("/srv/dayzOffline.chernarusplus/db/mods/types.xml"), None);
+        assert_eq!(map("types.xml"), None);
+    }
+
+    #[test]
+    fn tier_tables_mark_the_tier_and_follow_help_overrides() {
+        let text = describe(&FieldHelp::embedded(), Some("chernarus"), &[], "Tier4").unwrap();
+        assert!(text.starts_with("Tiers on Chernarus:\n  Tier1 = coast"), "{text}");
+        assert!(text.contains("\n▶ Tier4 = far north-west military"), "{text}");
+        assert_eq!(describe(&FieldHelp::embedded(), Some("chernarus"), &[], "Tier9"), None);
+        assert_eq!(describe(&FieldHelp::embedded(), Some("deerisle"), &[], "Tier1"), None);
+
+        let layer = "[tiers.chernarus]\nTier4 = \"NWAF\"\n[tiers.deerisle]\nTier1 = \"the island's coast\"";
+        let (help, notes) = FieldHelp::layered([("help.toml", layer)]);
+        assert!(notes.is_empty(), "{notes:?}");
+        assert!(describe(&help, Some("chernarus"), &[], "Tier4").unwrap().contains("▶ Tier4 = NWAF"));
+        assert!(describe(&help, Some("chernarus"), &[], "Tier1").unwrap().contains("Tier1 = coast"), "built-in rows stay");
+        assert_eq!(describe(&help, Some("deerisle"), &[], "Tier1").as_deref(), Some("Tiers on Deerisle:\n▶ Tier1 = the island's coast"));
+    }
+
+    #[test]
+    fn tier_tables_list_the_tiers_cfglimitsdefinition_declares() {
+        let limits = "<lists><valueflags><value name=\"Tier1\"/><value name=\"Tier2\"/><value name=\"TierPrison\"/></valueflags></lists>";
+        let custom = parse_limits(limits);
+        let text = describe(&FieldHelp::embedded(), Some("chernarus"), &custom, "TierPrison").unwrap();
+        assert!(text.contains("\n  Tier1 = coast"), "{text}");
+        assert!(text.ends_with("\n▶ TierPrison (custom)"), "{text}");
+        assert!(describe(&FieldHelp::embedded(), Some("chernarus"), &custom, "Tier4").unwrap().contains("\n  TierPrison (custom)"));
+
+        // A map without help rows, or no map at all, still gets the declared tiers.
+        let text = describe(&FieldHelp::embedded(), None, &custom, "Tier2").unwrap();
+        assert_eq!(text, "Tiers in cfglimitsdefinition.xml:\n  Tier1\n▶ Tier2\n  TierPrison");
+        assert!(describe(&FieldHelp::embedded(), Some("deerisle"), &custom, "TierPrison").unwrap().starts_with("Tiers on Deerisle:\n  Tier1\n"));
+        assert_eq!(describe(&FieldHelp::embedded(), None, &custom, "Tier9"), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    action::Action,
+    model::{FieldKey, TypeEntry},
+};
+
+/// The types file `:tutorial` opens. It stays in memory; saving asks where to write a copy.
+pub const SAMPLE: &str = include_str!("tutorial.xml");
+
+/// The sample type the steps edit.
+pub const TARGET: &str = "Canteen";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    SelectType,
+    EditNominal,
+    AddUsage,
+    Undo,
+    SaveAs,
+    Done,
+}
+
+/// Progress through `:tutorial`. After every action the editor handles it looks at the
+/// result and moves on once the current step shows in it, however the user got there.
+#[derive(Clone, Debug)]
+pub struct Tutorial {
+    step: Step,
+    /// `TARGET`'s nominal and number of usages as the sample has them.
+    nominal: String,
+    usages: usize,
+}
+
+fn target(types: &[TypeEntry]) -> Option<&TypeEntry> {
+    types.iter().find(|t| t.name == TARGET)
+}
+
+fn nominal_of(ty: &TypeEntry) -> Option<&str> {
+    ty.fields.iter().find(|f| matches!(&f.key, FieldKey::Element { name, index: 0 } if name == "nominal")).map(|f| f.value.trim())
+}
+
+fn usages_of(ty: &TypeEntry) -> usize {
+    ty.fields.iter().filter(|f| f.key.get_element_name() == "usage").count()
+}
+
+impl Tutorial {
+    /// Starts at the first step; `types` is the sample as loaded.
+    pub fn new(types: &[TypeEntry]) -> Self {
+        let ty = target(types);
+        Self {
+            step: Step::SelectType,
+            nominal: ty.and_then(nominal_of).unwrap_or_default().to_string(),
+            usages: ty.map_or(0, usages_of),
+        }
+    }
+
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    /// Moves past the current step when `types`, the highlighted type and whether the
+    /// sample has been saved as a file show it done after `action`; true when it did.
+    pub fn observe(&mut self, action: &Action, types: &[TypeEntry], selected: usize, saved: bool) -> bool {
+        let ty = target(types);
+        let done = match self.step {
+            Step::SelectType => types.get(selected).is_some_and(|t| t.name == TARGET),
+            Step::EditNominal => ty.and_then(nominal_of).is_some_and(|n| n != self.nominal),
+            Step::AddUsage => ty.is_some_and(|t| usages_of(t) > self.usages),
+            Step::Undo => *action == Action::Undo && ty.is_some_and(|t| usages_of(t) == self.usages),
+            Step::SaveAs => saved,
+            Step::Done => false,
+        };
+        if done {
+            self.step = match self.step {
+                Step::SelectType => Step::EditNominal,
+                Step::EditNominal => Step::AddUsage,
+                Step::AddUsage => Step::Undo,
+                Step::Undo => Step::SaveAs,
+                Step::SaveAs | Step::Done => Step::Done,
+            };
+        }
+        done
+    }
+
+    /// `Tutorial 2/5: edit a value`.
+    pub fn title(&self) -> String {
+        let (n, what) = match self.step {
+            Step::SelectType => (1, "pick a type"),
+            Step::EditNominal => (2, "edit a value"),
+            Step::AddUsage => (3, "add a usage"),
+            Step::Undo => (4, "undo"),
+            Step::SaveAs => (5, "save a copy"),
+            Step::Done => return String::from("Tutorial: done"),
+        };
+        format!("Tutorial {n}/5: {what}")
+    }
+
+    pub fn instructions(&self) -> &'static str {
+        match self.step {
+            Step::SelectType => {
+                "This is a sample types.xml, not a file on disk. Types are listed on the left: move to Canteen with j/k or ↓/↑ (or press G and type its name)."
+            }
+            Step::EditNominal => {
+                "→ moves to the Fields pane. On nominal press Enter, change the number (Ctrl+U clears it) and press Enter again. Changed fields get a *."
+            }
+            Step::AddUsage => {
+                "On the Fields pane, a adds an element, asking one thing at a time: type usage and Enter, Enter again for no text, then name and Enter for its attribute, Village and Enter for the value, and Enter once more to add it."
+            }
+            Step::Undo => "u takes back the last step: press it and the usage you added goes away again (U would redo it).",
+            Step::SaveAs => {
+                "The sample has nowhere to be saved, so s asks for a path: press s, check the path and press Enter. From then on you edit that file."
+            }
+            Step::Done => "That's the basic loop. ? lists every key and :tutorial runs this again.",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field_help::FieldHelp, model::parse_types, validation};
+
+    #[test]
+    fn the_sample_is_a_clean_vanilla_file() {
+        let (types, report) = parse_types(SAMPLE).unwrap();
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert!(target(&types).is_some());
+        let help = FieldHelp::embedded();
+        let rules = validation::Rules { strict: true, help: &help, require_flags: true, expected_cost: Some(100) };
+        let issues = validation::validate(&types, rules);
+        assert!(issues.is_empty(), "{:?}", issues.iter().map(|i| &i.message).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn steps_advance_on_what_the_editor_shows() {
+        let (mut types, _) = parse_types(SAMPLE).unwrap();
+        let canteen = types.iter().position(|t| t.name == TARGET).unwrap();
+        let mut tutorial = Tutorial::new(&types);
+        assert!(!tutorial.observe(&Action::Down, &types, 0, false));
+        assert!(tutorial.observe(&Action::Down, &types, canteen, false));
+        assert_eq!(tutorial.title(), "Tutorial 2/5: edit a value");
+
+        // Editing another type's nominal doesn't count.
+        types[0].fields[0].value = String::from("9");
+        assert!(!tutorial.observe(&Action::Activate, &types, 0, false));
+        let sample = types.clone();
+        types[canteen].fields[0].value = String::from("25");
+        assert!(tutorial.observe(&Action::Activate, &types, canteen, false));
+
+        let mut with_usage = types.clone();
+        let usage = with_usage[canteen].fields.iter().position(|f| f.key.get_element_name() == "usage").unwrap();
+        let mut added = with_usage[canteen].fields[usage].clone();
+        added.key = FieldKey::Attribute { element: String::from("usage"), index: 1, attr: String::from("name") };
+        added.value = String::from("Village");
+        with_usage[canteen].fields.insert(usage + 1, added);
+        assert!(tutorial.observe(&Action::Activate, &with_usage, canteen, false));
+        assert_eq!(tutorial.step(), Step::Undo);
+
+        // Deleting the usage instead of undoing isn't the step.
+        assert!(!tutorial.observe(&Action::Delete, &types, canteen, false));
+        assert!(tutorial.observe(&Action::Undo, &types, canteen, false));
+        assert!(!tutorial.observe(&Action::Save, &sample, canteen, false));
+        assert!(tutorial.observe(&Action::Activate, &sample, canteen, true));
+        assert_eq!((tutorial.step(), tutorial.title()), (Step::Done, String::from("Tutorial: done")));
+    }
+}
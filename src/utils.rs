@@ -1,4 +1,93 @@
-use tui::layout::{Constraint, Direction, Layout, Rect};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier},
+    widgets::Widget,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// `5 min ago` style age of a Unix timestamp.
+pub fn format_age(unix_secs: u64) -> String {
+    let secs = now_unix().saturating_sub(unix_secs);
+    match secs {
+        0..60 => String::from("just now"),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+/// Calendar date and time (UTC) of a Unix timestamp: `(year, month, day, hour, minute, second)`.
+pub fn utc_datetime(unix_secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (unix_secs / 86400) as i64;
+    let secs = unix_secs % 86400;
+    // Days to civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, (secs / 3600) as u32, (secs % 3600 / 60) as u32, (secs % 60) as u32)
+}
+
+pub fn now_unix() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// `1 type`, `12 types`.
+pub fn plural(n: usize, word: &str) -> String {
+    if n == 1 { format!("1 {word}") } else { format!("{n} {word}s") }
+}
+
+/// `text` cut to `width` terminal columns, ending in `…` when something was cut. Cuts fall
+/// between grapheme clusters, so an accent or a skin tone stays with its letter or emoji.
+pub fn fit_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for g in text.graphemes(true) {
+        let w = g.width();
+        if used + w + 1 > width {
+            break;
+        }
+        used += w;
+        out.push_str(g);
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// The end of `text` that fits in `width` columns, starting with `…` when the front was
+/// cut; for input lines, where the cursor is at the end.
+pub fn tail_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for g in text.graphemes(true).rev() {
+        let w = g.width();
+        if used + w + 1 > width {
+            break;
+        }
+        used += w;
+        kept.push(g);
+    }
+    let mut out = String::from(if width > 0 { "…" } else { "" });
+    out.extend(kept.into_iter().rev());
+    out
+}
 
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -27,3 +116,60 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
     horizontal[1]
 }
+
+/// Drawn over a finished frame for `theme = "mono"`: drops every colour, turning coloured
+/// backgrounds (selections, highlights) into reverse video so they stay visible.
+pub struct Monochrome;
+
+impl Widget for Monochrome {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_left_alone() {
+        assert_eq!(fit_width("nominal: 8", 20), "nominal: 8");
+        assert_eq!(tail_width("nominal: 8", 10), "nominal: 8");
+        assert_eq!(fit_width("", 0), "");
+    }
+
+    #[test]
+    fn long_text_ends_in_an_ellipsis() {
+        assert_eq!(fit_width("abcdefghij", 5), "abcd…");
+        assert_eq!(tail_width("abcdefghij", 5), "…ghij");
+        assert_eq!(fit_width("abcdefghij", 1), "…");
+        assert_eq!(fit_width("abcdefghij", 0), "");
+    }
+
+    #[test]
+    fn widths_count_columns_not_bytes() {
+        // Two bytes each, one column each.
+        assert_eq!(fit_width("ÄÖÜäöü", 6), "ÄÖÜäöü");
+        assert_eq!(fit_width("ÄÖÜäöüß", 6), "ÄÖÜäö…");
+        // Two columns each: a wide character that would straddle the edge is dropped.
+        assert_eq!(fit_width("日本語テキスト", 6), "日本…");
+        assert_eq!(tail_width("日本語テキスト", 6), "…スト");
+        // A combining accent is cut together with its letter, never on its own.
+        assert_eq!(fit_width("cafe\u{301}s", 4), "caf…");
+        assert_eq!(tail_width("ae\u{301}", 1), "…");
+        assert_eq!(tail_width("xe\u{301}e\u{301}", 2), "…e\u{301}");
+        for width in 0..16 {
+            assert!(fit_width("日本語テキストabc", width).width() <= width);
+            assert!(tail_width("日本語テキストabc", width).width() <= width);
+        }
+    }
+}
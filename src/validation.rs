@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use crate::{
+    dedupe,
+    field_help::{FieldHelp, HELP_FILE},
+    model::{FieldKey, TypeEntry, VANILLA_ELEMENTS},
+};
+
+/// Elements whose text must be a whole number.
+pub const NUMERIC_ELEMENTS: [&str; 7] = ["nominal", "lifetime", "restock", "min", "quantmin", "quantmax", "cost"];
+
+/// Characters that would break the XML attribute the name is written into.
+const XML_UNSAFE: [char; 5] = ['<', '>', '&', '"', '\''];
+
+/// Why the game would drop a type: the first offending character of its name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameProblem {
+    /// Character (not byte) position in the name.
+    pub position: usize,
+    pub ch: char,
+    pub reason: &'static str,
+}
+
+impl NameProblem {
+    /// Whitespace and XML-unsafe characters need an explicit override to be committed.
+    pub fn needs_override(&self) -> bool {
+        self.ch.is_whitespace() || XML_UNSAFE.contains(&self.ch)
+    }
+
+    /// The name with the offending character bracketed: `AKM[ ]Blue`.
+    pub fn highlight(&self, name: &str) -> String {
+        name.chars()
+            .enumerate()
+            .map(|(i, c)| if i == self.position { format!("[{c}]") } else { c.to_string() })
+            .collect()
+    }
+
+    pub fn describe(&self, name: &str) -> String {
+        format!("type name {} {}", self.highlight(name), self.reason)
+    }
+}
+
+/// Type names are classnames: ASCII letters, digits and `_`, not starting with a digit.
+/// Empty names are reported separately.
+pub fn check_type_name(name: &str) -> Result<(), NameProblem> {
+    for (position, ch) in name.chars().enumerate() {
+        let reason = if ch.is_whitespace() {
+            "contains whitespace"
+        } else if XML_UNSAFE.contains(&ch) {
+            "contains a character that breaks the XML"
+        } else if !ch.is_ascii() {
+            "contains a non-ASCII character"
+        } else if position == 0 && ch.is_ascii_digit() {
+            "starts with a digit"
+        } else if !(ch.is_ascii_alphanumeric() || ch == '_') {
+            "contains a character not allowed in classnames"
+        } else {
+            continue;
+        };
+        return Err(NameProblem { position, ch, reason });
+    }
+    Ok(())
+}
+
+/// Which optional checks run, from the `[validation]` config section.
+#[derive(Clone, Copy, Debug)]
+pub struct Rules<'a> {
+    /// Also report element names outside the vanilla set, e.g. typos in mod files.
+    pub strict: bool,
+    /// Elements with help text count as known to `strict`, so mod elements can be declared.
+    pub help: &'a FieldHelp,
+    /// Report types without a `<flags>` element.
+    pub require_flags: bool,
+    /// Report costs other than this one.
+    pub expected_cost: Option<i64>,
+}
+
+/// How bad an issue is: errors are values the CE misreads or rejects, warnings are legal but
+/// likely unintended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Which check found an issue. Serialized in kebab-case (`min-over-nominal`); scripts match
+/// on these, so existing codes keep their names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueCode {
+    NoName,
+    DuplicateType,
+    BadName,
+    MissingFlags,
+    BadNumber,
+    BadFlag,
+    EmptyName,
+    UnknownElement,
+    UnusualCost,
+    MinOverNominal,
+    QuantityPair,
+    DuplicateField,
+    DuplicateAttribute,
+}
+
+impl IssueCode {
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::MissingFlags | Self::UnknownElement | Self::UnusualCost | Self::DuplicateField => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A problem found in the loaded types; `field_idx` is `None` for type-level problems.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Issue {
+    pub type_idx: usize,
+    pub field_idx: Option<usize>,
+    pub code: IssueCode,
+    pub message: String,
+}
+
+pub fn validate(types: &[TypeEntry], rules: Rules) -> Vec<Issue> {
+    let counts = name_counts(types);
+    types
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, ty)| validate_type(idx, ty, &counts, rules))
+        .collect()
+}
+
+/// Re-checks a single type, replacing its entries in `issues` and keeping the list ordered.
+pub fn revalidate_type(issues: &mut Vec<Issue>, types: &[TypeEntry], type_idx: usize, rules: Rules) {
+    issues.retain(|i| i.type_idx != type_idx);
+    if let Some(ty) = types.get(type_idx) {
+        let counts = name_counts(types);
+        issues.extend(validate_type(type_idx, ty, &counts, rules));
+    }
+    issues.sort_by_key(|i| (i.type_idx, i.field_idx));
+}
+
+fn name_counts(types: &[TypeEntry]) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for ty in types {
+        *counts.entry(ty.name.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Types with no `<flags>` element at all; the CE then applies its own defaults.
+pub fn missing_flags(types: &[TypeEntry]) -> Vec<usize> {
+    (0..types.len()).filter(|&i| !has_flags(&types[i])).collect()
+}
+
+/// The type's cost when it's a number other than `expected`; a missing or broken cost
+/// isn't this check's business.
+pub fn unexpected_cost(ty: &TypeEntry, expected: i64) -> Option<i64> {
+    let cost = ty.fields.get(ty.element_field("cost")?)?.value.trim().parse::<i64>().ok()?;
+    (cost != expected).then_some(cost)
+}
+
+fn has_flags(ty: &TypeEntry) -> bool {
+    ty.fields.iter().any(|f| f.key.get_element_name() == "flags")
+}
+
+fn validate_type(type_idx: usize, ty: &TypeEntry, name_counts: &HashMap<&str, usize>, rules: Rules) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let type_issue = |code: IssueCode, message: String| Issue {
+        type_idx,
+        field_idx: None,
+        code,
+        message,
+    };
+
+    if ty.name.trim().is_empty() {
+        issues.push(type_issue(IssueCode::NoName, String::from("type has no name")));
+    } else if name_counts.get(ty.name.as_str()).copied().unwrap_or(0) > 1 {
+        issues.push(type_issue(IssueCode::DuplicateType, format!("duplicate type name '{}'", ty.name)));
+    }
+    if let Err(problem) = check_type_name(&ty.name) {
+        issues.push(type_issue(IssueCode::BadName, problem.describe(&ty.name)));
+    }
+    if rules.require_flags && !has_flags(ty) {
+        issues.push(type_issue(
+            IssueCode::MissingFlags,
+            String::from("no <flags> element; the CE falls back to its defaults"),
+        ));
+    }
+
+    let mut numbers: HashMap<&str, (usize, i64)> = HashMap::new();
+    for (field_idx, field) in ty.fields.iter().enumerate() {
+        let problem = match &field.key {
+            FieldKey::Element { name, .. } if NUMERIC_ELEMENTS.contains(&name.as_str()) => {
+                match check_number(name, &field.value) {
+                    Ok(n) => {
+                        numbers.insert(name.as_str(), (field_idx, n));
+                        None
+                    }
+                    Err(message) => Some((IssueCode::BadNumber, message)),
+                }
+            }
+            FieldKey::Attribute { element, attr, .. } if element == "flags" => {
+                (field.value != "0" && field.value != "1")
+                    .then(|| (IssueCode::BadFlag, format!("flag {attr} must be 0 or 1, found '{}'", field.value)))
+            }
+            FieldKey::Attribute { element, attr, .. } if attr == "name" && field.value.trim().is_empty() => {
+                Some((IssueCode::EmptyName, format!("{element} has an empty name")))
+            }
+            FieldKey::Element { name, .. }
+                if rules.strict && !VANILLA_ELEMENTS.contains(&name.as_str()) && !rules.help.documents_element(name) =>
+            {
+                Some((
+                    IssueCode::UnknownElement,
+                    format!("unknown element <{name}> (describe it in {HELP_FILE} if it's meant to be there)"),
+                ))
+            }
+            _ => None,
+        };
+        let problem = problem.or_else(|| match &field.key {
+            FieldKey::Element { name, .. } if name == "cost" => {
+                let expected = rules.expected_cost?;
+                let cost = unexpected_cost(ty, expected)?;
+                Some((IssueCode::UnusualCost, format!("cost is {cost}, not the usual {expected} (:cost resets it)")))
+            }
+            _ => None,
+        });
+        if let Some((code, message)) = problem {
+            issues.push(Issue {
+                type_idx,
+                field_idx: Some(field_idx),
+                code,
+                message,
+            });
+        }
+    }
+
+    if let (Some(&(min_idx, min)), Some(&(_, nominal))) = (numbers.get("min"), numbers.get("nominal"))
+        && min > nominal
+    {
+        issues.push(Issue {
+            type_idx,
+            field_idx: Some(min_idx),
+            code: IssueCode::MinOverNominal,
+            message: format!("min ({min}) is greater than nominal ({nominal})"),
+        });
+    }
+    if let (Some(&(qmin_idx, qmin)), Some(&(_, qmax))) = (numbers.get("quantmin"), numbers.get("quantmax")) {
+        let message = if (qmin == -1) != (qmax == -1) {
+            Some(String::from("quantmin and quantmax must both be -1 or both be set"))
+        } else if qmin != -1 && qmin > qmax {
+            Some(format!("quantmin ({qmin}) is greater than quantmax ({qmax})"))
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            issues.push(Issue {
+                type_idx,
+                field_idx: Some(qmin_idx),
+                code: IssueCode::QuantityPair,
+                message,
+            });
+        }
+    }
+
+    for dup in dedupe::find_in_type(type_idx, ty) {
+        let field_idx = ty.fields.iter().position(|f| {
+            let (element, index) = f.key.element_key();
+            element == dup.element && dup.extra_indices.contains(&index)
+        });
+        issues.push(Issue {
+            type_idx,
+            field_idx,
+            code: IssueCode::DuplicateField,
+            message: format!("{} (:dedupe removes the extras)", dup.description),
+        });
+    }
+
+    for field_idx in dedupe::repeated_attributes(ty) {
+        if let FieldKey::Attribute { element, attr, .. } = &ty.fields[field_idx].key {
+            issues.push(Issue {
+                type_idx,
+                field_idx: Some(field_idx),
+                code: IssueCode::DuplicateAttribute,
+                message: format!("{element} has {attr} more than once and can't be saved (d in this list keeps the last)"),
+            });
+        }
+    }
+
+    issues.sort_by_key(|i| i.field_idx);
+    issues
+}
+
+/// The rule for a quantmin/quantmax pair as a whole: both -1, or both 0..=100 with min ≤ max.
+pub fn check_quantity_pair(min: i64, max: i64) -> Result<(), String> {
+    match (min, max) {
+        (-1, -1) => Ok(()),
+        (-1, _) | (_, -1) => Err(String::from("quantmin and quantmax must both be -1 or both be set")),
+        _ if !(0..=100).contains(&min) || !(0..=100).contains(&max) => {
+            Err(String::from("quantity must be between 0 and 100%"))
+        }
+        _ if min > max => Err(format!("quantmin ({min}) is greater than quantmax ({max})")),
+        _ => Ok(()),
+    }
+}
+
+fn check_number(name: &str, value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("{name} is empty"));
+    }
+    let n: i64 = value
+        .parse()
+        .map_err(|_| format!("{name} is not a whole number: '{value}'"))?;
+    match name {
+        // The same range `check_quantity_pair` holds the pair to.
+        "quantmin" | "quantmax" if n != -1 && !(0..=100).contains(&n) => {
+            Err(format!("{name} must be -1 or between 0 and 100, found {n}"))
+        }
+        "quantmin" | "quantmax" => Ok(n),
+        _ if n < 0 => Err(format!("{name} must not be negative, found {n}")),
+        _ => Ok(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classnames_pass() {
+        assert_eq!(check_type_name("MyMod_AKM_Blue"), Ok(()));
+        assert_eq!(check_type_name("AKM"), Ok(()));
+        assert_eq!(check_type_name("_Base2"), Ok(()));
+    }
+
+    #[test]
+    fn whitespace_is_rejected_and_needs_override() {
+        let problem = check_type_name("AKM Blue").unwrap_err();
+        assert_eq!((problem.position, problem.ch), (3, ' '));
+        assert!(problem.needs_override());
+        assert_eq!(problem.highlight("AKM Blue"), "AKM[ ]Blue");
+    }
+
+    #[test]
+    fn leading_digit_is_rejected() {
+        let problem = check_type_name("1Fast").unwrap_err();
+        assert_eq!((problem.position, problem.ch), (0, '1'));
+        assert!(!problem.needs_override());
+    }
+
+    #[test]
+    fn first_offending_character_is_reported() {
+        let problem = check_type_name("Gewehr-Süd").unwrap_err();
+        assert_eq!((problem.position, problem.ch), (6, '-'));
+        assert_eq!(check_type_name("GewehrSüd").unwrap_err().ch, 'ü');
+        assert!(check_type_name("Box<1>").unwrap_err().needs_override());
+    }
+
+    #[test]
+    fn single_quantities_follow_the_pair_rule() {
+        for value in ["-1", "0", "100"] {
+            assert!(check_number("quantmin", value).is_ok(), "{value}");
+            assert!(check_quantity_pair(value.parse().unwrap(), value.parse().unwrap()).is_ok(), "{value}");
+        }
+        for value in ["101", "-2"] {
+            assert!(check_number("quantmin", value).is_err(), "{value}");
+            assert!(check_quantity_pair(value.parse().unwrap(), 100).is_err(), "{value}");
+        }
+        assert_eq!(check_number("quantmax", "101").unwrap_err(), "quantmax must be -1 or between 0 and 100, found 101");
+    }
+}
@@ -0,0 +1,124 @@
+//! String-in, string-out wrappers for JavaScript callers. Types travel as JSON arrays of
+//! `{"name", "fields": [{"key": {"kind": "element", "name", "index"}, "value"}]}`,
+//! the same shape `parse_types_json` produces, so its output can be edited and handed
+//! back to `serialize_types_json`. Errors come back as the message string.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    diff::diff_types,
+    field_help::FieldHelp,
+    model::{self, ParseWarning, TypeEntry},
+    validation::{self, Issue, Rules},
+};
+
+#[derive(Serialize)]
+struct Parsed {
+    types: Vec<TypeEntry>,
+    warnings: Vec<ParseWarning>,
+}
+
+/// The `[validation]` settings a caller can pass to `validate_types_json`; all optional.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ValidationOptions {
+    strict: bool,
+    require_flags: bool,
+    expected_cost: Option<i64>,
+}
+
+fn from_json<'a, T: Deserialize<'a>>(what: &str, json: &'a str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("{what}: {e}"))
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+/// Parses a types.xml document into `{"types": [...], "warnings": [{"line", "message"}]}`.
+#[wasm_bindgen]
+pub fn parse_types_json(xml: &str) -> Result<String, String> {
+    let (types, report) = model::parse_types(xml).map_err(|e| e.to_string())?;
+    to_json(&Parsed { types, warnings: report.warnings })
+}
+
+/// Writes a JSON types array back out as types.xml, optionally in the vanilla element order.
+#[wasm_bindgen]
+pub fn serialize_types_json(types_json: &str, canonical_order: bool) -> Result<String, String> {
+    let types: Vec<TypeEntry> = from_json("types", types_json)?;
+    model::serialize_types(&types, canonical_order).map_err(|e| e.to_string())
+}
+
+/// Checks a JSON types array; `options_json` may be empty or e.g. `{"strict": true}`.
+/// Returns `[{"type_idx", "field_idx", "code", "message"}]`.
+#[wasm_bindgen]
+pub fn validate_types_json(types_json: &str, options_json: &str) -> Result<String, String> {
+    let types: Vec<TypeEntry> = from_json("types", types_json)?;
+    let options: ValidationOptions = if options_json.trim().is_empty() {
+        ValidationOptions::default()
+    } else {
+        from_json("options", options_json)?
+    };
+    let help = FieldHelp::embedded();
+    let rules = Rules {
+        strict: options.strict,
+        help: &help,
+        require_flags: options.require_flags,
+        expected_cost: options.expected_cost,
+    };
+    let issues: Vec<Issue> = validation::validate(&types, rules);
+    to_json(&issues)
+}
+
+/// Compares two JSON types arrays by type name: `{"added", "removed", "changed"}`.
+#[wasm_bindgen]
+pub fn diff_types_json(old_json: &str, new_json: &str) -> Result<String, String> {
+    let old: Vec<TypeEntry> = from_json("old types", old_json)?;
+    let new: Vec<TypeEntry> = from_json("new types", new_json)?;
+    to_json(&diff_types(&old, &new))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<types>
+    <type name="Apple">
+        <nominal>40</nominal>
+        <lifetime>3600</lifetime>
+        <category name="food"/>
+    </type>
+</types>
+"#;
+
+    fn types_of(parsed: &str) -> serde_json::Value {
+        let value: serde_json::Value = serde_json::from_str(parsed).unwrap();
+        value["types"].clone()
+    }
+
+    #[test]
+    fn json_round_trip_keeps_the_types() {
+        let types = types_of(&parse_types_json(XML).unwrap());
+        let key = &types[0]["fields"][2]["key"];
+        assert_eq!(key["kind"], "attribute");
+        assert_eq!((key["element"].as_str(), key["attr"].as_str()), (Some("category"), Some("name")));
+        let xml = serialize_types_json(&types.to_string(), false).unwrap();
+        assert_eq!(types_of(&parse_types_json(&xml).unwrap()), types);
+    }
+
+    #[test]
+    fn validate_and_diff_answer_in_json() {
+        let old = types_of(&parse_types_json(XML).unwrap()).to_string();
+        let new = old.replace(r#""value":"3600""#, r#""value":"-5""#);
+        let issues = validate_types_json(&new, "").unwrap();
+        assert!(issues.contains(r#""type_idx":0,"field_idx":1"#), "{issues}");
+        let diff = diff_types_json(&old, &new).unwrap();
+        assert!(diff.contains(r#""old":"3600","new":"-5""#), "{diff}");
+        assert!(validate_types_json(&new, r#"{"strictt": true}"#).unwrap_err().starts_with("options:"));
+    }
+}
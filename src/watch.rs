@@ -0,0 +1,31 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// How often the open local file is checked for changes made outside the editor.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Size and modification time of a file: cheap to fetch, and different whenever the
+/// file has been rewritten. Equal stamps don't prove equal content; the hash decides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the epoch locally, whole seconds over SFTP.
+    pub modified: Option<u128>,
+}
+
+impl FileStamp {
+    pub fn local(path: &Path) -> io::Result<Self> {
+        let meta = fs::metadata(path)?;
+        Ok(Self {
+            size: meta.len(),
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos()),
+        })
+    }
+}
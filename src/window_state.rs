@@ -2,5 +2,8 @@
 #[derive(Clone, Copy)]
 pub enum WindowState {
     FilePicker, 
-    Editor
+    Editor,
+    Compare,
+    /// The setup wizard, over the view it was started from.
+    Setup,
 }